@@ -0,0 +1,98 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use std::hint::black_box;
+use hypeerlog::{AHashBuilder, Hypeerlog, WyHashBuilder, Xxh3BuildHasher};
+use rand::prelude::*;
+
+
+// `n` random printable keys of `len` bytes, standing in for URLs or serialized documents
+fn random_keys(n: usize, len: usize) -> Vec<String> {
+    let mut rng = rand::rng();
+    (0..n)
+        .map(|_| (0..len).map(|_| rng.random_range(b'!'..=b'~') as char).collect())
+        .collect()
+}
+
+
+
+fn bench_long_keys(c: &mut Criterion) {
+    let mut group = c.benchmark_group("HLL_hashers_100_byte_keys");
+
+    group.sample_size(20);
+    group.warm_up_time(std::time::Duration::from_secs(1));
+
+    let keys = random_keys(100_000, 100);
+    group.throughput(criterion::Throughput::Elements(keys.len() as u64));
+
+    let mut murmur = Hypeerlog::with_precision(14);
+    group.bench_function("murmur3_insert", |b| {
+        b.iter(|| keys.iter().for_each(|key| {
+            murmur.insert(black_box(key.as_str()));
+        }));
+    });
+    group.bench_function("murmur3_insert_str", |b| {
+        b.iter(|| keys.iter().for_each(|key| {
+            murmur.insert_str(black_box(key));
+        }));
+    });
+
+    let mut xxh3 = Hypeerlog::with_hasher_precision(14, Xxh3BuildHasher::new(0));
+    group.bench_function("xxh3_insert", |b| {
+        b.iter(|| keys.iter().for_each(|key| {
+            xxh3.insert(black_box(key.as_str()));
+        }));
+    });
+    group.bench_function("xxh3_insert_str", |b| {
+        b.iter(|| keys.iter().for_each(|key| {
+            xxh3.insert_str(black_box(key));
+        }));
+    });
+    group.finish();
+}
+
+
+
+fn bench_u64_keys(c: &mut Criterion) {
+    let mut group = c.benchmark_group("HLL_hashers_u64_keys");
+
+    group.sample_size(20);
+    group.warm_up_time(std::time::Duration::from_secs(1));
+
+    let mut rng = rand::rng();
+    let keys: Vec<u64> = (0..1_000_000).map(|_| rng.random()).collect();
+    group.throughput(criterion::Throughput::Elements(keys.len() as u64));
+
+    let mut murmur = Hypeerlog::with_precision(14);
+    group.bench_function("murmur3", |b| {
+        b.iter(|| keys.iter().for_each(|&key| {
+            murmur.insert(black_box(key));
+        }));
+    });
+    let mut xxh3 = Hypeerlog::with_hasher_precision(14, Xxh3BuildHasher::new(0));
+    group.bench_function("xxh3", |b| {
+        b.iter(|| keys.iter().for_each(|&key| {
+            xxh3.insert(black_box(key));
+        }));
+    });
+    let mut ahash = Hypeerlog::with_hasher_precision(14, AHashBuilder::new(0));
+    group.bench_function("ahash", |b| {
+        b.iter(|| keys.iter().for_each(|&key| {
+            ahash.insert(black_box(key));
+        }));
+    });
+    let mut wyhash = Hypeerlog::with_hasher_precision(14, WyHashBuilder::new(0));
+    group.bench_function("wyhash", |b| {
+        b.iter(|| keys.iter().for_each(|&key| {
+            wyhash.insert(black_box(key));
+        }));
+    });
+    group.finish();
+}
+
+
+//--------------
+// Running the benchmarks
+//--------------
+
+
+criterion_group!(benches, bench_long_keys, bench_u64_keys);
+criterion_main!(benches);
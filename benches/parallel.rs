@@ -0,0 +1,116 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use std::hint::black_box;
+use hypeerlog::Hypeerlog;
+
+
+// Builds `n` sketches at precision 14, each holding a distinct slice of 1,000 elements
+fn build_sketches(n: u64) -> Vec<Hypeerlog> {
+    (0..n).map(|i| {
+        let mut hll = Hypeerlog::with_precision(14);
+        for x in i * 1000..(i + 1) * 1000 {
+            hll.insert(x);
+        }
+        hll
+    }).collect()
+}
+
+
+
+fn bench_par_merge(c: &mut Criterion) {
+    let mut group = c.benchmark_group("HLL_parallel_merging");
+
+    group.sample_size(10);
+    group.warm_up_time(std::time::Duration::from_secs(1));
+
+    let sketches = build_sketches(1000);
+
+    group.bench_function("sequential_1000_p14", |b| {
+        b.iter(|| {
+            let mut acc = Hypeerlog::with_precision(14);
+            acc.merge_all(&sketches).unwrap();
+            black_box(acc)
+        });
+    });
+
+    // Run the parallel reduction at increasing thread counts to show the scaling
+    let max_threads = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+    let mut threads = 1;
+    while threads <= max_threads {
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+        group.bench_function(format!("par_merge_all_1000_p14_threads={}", threads), |b| {
+            b.iter(|| pool.install(|| black_box(Hypeerlog::par_merge_all(&sketches).unwrap())));
+        });
+        threads *= 2;
+    }
+    group.finish();
+}
+
+fn bench_par_insert(c: &mut Criterion) {
+    let mut group = c.benchmark_group("HLL_parallel_inserts");
+
+    group.sample_size(10);
+    group.warm_up_time(std::time::Duration::from_secs(1));
+
+    let elems: Vec<u64> = (0..10_000_000u64).map(|x| x.wrapping_mul(0x9e37_79b9_7f4a_7c15)).collect();
+
+    group.bench_function("insert_many_10M_p14", |b| {
+        b.iter(|| {
+            let mut hll = Hypeerlog::with_precision(14);
+            hll.insert_many(&elems);
+            black_box(hll)
+        });
+    });
+
+    let max_threads = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+    let mut threads = 1;
+    while threads <= max_threads {
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+        group.bench_function(format!("par_insert_many_10M_p14_threads={}", threads), |b| {
+            b.iter(|| {
+                let mut hll = Hypeerlog::with_precision(14);
+                pool.install(|| hll.par_insert_many(&elems));
+                black_box(hll)
+            });
+        });
+        threads *= 2;
+    }
+    group.finish();
+}
+
+fn bench_count_distinct_par(c: &mut Criterion) {
+    use hypeerlog::{count_distinct, count_distinct_par};
+    use rayon::prelude::*;
+
+    let mut group = c.benchmark_group("HLL_parallel_count_distinct");
+
+    group.sample_size(10);
+    group.warm_up_time(std::time::Duration::from_secs(1));
+
+    let elems: Vec<u64> = (0..50_000_000u64).map(|x| x.wrapping_mul(0x9e37_79b9_7f4a_7c15)).collect();
+
+    group.bench_function("count_distinct_50M_p14", |b| {
+        b.iter(|| black_box(count_distinct(&elems)));
+    });
+
+    let max_threads = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+    let mut threads = 1;
+    while threads <= max_threads {
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+        group.bench_function(format!("count_distinct_par_50M_p14_threads={}", threads), |b| {
+            b.iter(|| pool.install(|| black_box(count_distinct_par(elems.par_iter()))));
+        });
+        threads *= 2;
+    }
+    group.finish();
+}
+
+
+
+
+//--------------
+// Running the benchmarks
+//--------------
+
+
+criterion_group!(benches, bench_par_merge, bench_par_insert, bench_count_distinct_par);
+criterion_main!(benches);
@@ -4,7 +4,32 @@ use std::hint::black_box;
 use rand::prelude::*;
 use std::collections::HashSet;
 use std::hash::Hash;
-use hypeerlog::Hypeerlog;
+use hypeerlog::{cardinality_of_dump, Hypeerlog, PackedHypeerlog};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+
+// Counts heap allocations so the dumping benchmark can report how many each strategy performs
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+// SAFETY: every call is forwarded unchanged to the system allocator
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        // SAFETY: the caller upholds the `GlobalAlloc::alloc` contract
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        // SAFETY: `ptr` was returned by `alloc` with the same layout
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
 
 
 fn generate_random_list_with_cardinality(length: usize, cardinality: usize) -> Result<Vec<u64>, String> {
@@ -44,7 +69,7 @@ fn run_trial<H: Hash>(p: u8, card: usize, elems: &[H]) -> (f64, f64) {
     let mut hll = Hypeerlog::with_precision(p);
     hll.insert_many(elems);
     let estimated_cardinality = hll.cardinality();
-    let relative_error = (estimated_cardinality as f64 - card as f64).abs() / card as f64;
+    let relative_error = (estimated_cardinality - card as f64).abs() / card as f64;
     (estimated_cardinality, relative_error)
 }
 
@@ -125,10 +150,281 @@ fn bench_merging(c: &mut Criterion) {
 
 
 
+fn bench_loading(c: &mut Criterion) {
+    let mut group = c.benchmark_group("HLL_loading");
+
+    group.sample_size(30);
+    group.warm_up_time(std::time::Duration::from_secs(1));
+
+    for p in [14, 16] {
+        let list = generate_random_list_with_cardinality(10_000, 5_000)
+            .expect("Failed to generate list for benchmark");
+        let mut hll = Hypeerlog::with_precision(p);
+        hll.insert_many(&list);
+        let dump = hll.dump();
+
+        // Compare checksum verification against the unchecked path to show its cost
+        group.bench_function(format!("load_p={}", p), |b| {
+            b.iter_batched(|| dump.clone(), |bytes| black_box(Hypeerlog::load(bytes)), criterion::BatchSize::SmallInput);
+        });
+        group.bench_function(format!("load_unchecked_p={}", p), |b| {
+            b.iter_batched(|| dump.clone(), |bytes| black_box(Hypeerlog::load_unchecked(bytes)), criterion::BatchSize::SmallInput);
+        });
+
+        // Estimating straight from the bytes skips the allocation and copy of the load path
+        group.bench_function(format!("load_cardinality_p={}", p), |b| {
+            b.iter(|| black_box(Hypeerlog::from_bytes(&dump).unwrap().cardinality()));
+        });
+        group.bench_function(format!("cardinality_of_dump_p={}", p), |b| {
+            b.iter(|| black_box(cardinality_of_dump(&dump).unwrap()));
+        });
+    }
+    group.finish();
+}
+
+
+
+
+
+
+
+fn bench_pre_hashed(c: &mut Criterion) {
+    let mut group = c.benchmark_group("HLL_pre_hashed");
+
+    group.sample_size(10);
+    group.warm_up_time(std::time::Duration::from_secs(1));
+
+    let mut rng = rand::rng();
+    let hashes: Vec<u64> = (0..10_000_000).map(|_| rng.random()).collect();
+    group.throughput(criterion::Throughput::Elements(hashes.len() as u64));
+
+    // Towards p=24 the registers outgrow the caches, which is where prefetching pays off
+    for p in [12, 16, 20, 24] {
+        let mut hll = Hypeerlog::with_precision(p);
+        group.bench_function(format!("insert_hashed_loop_p={}", p), |b| {
+            b.iter(|| {
+                for &hash in &hashes {
+                    hll.insert_hashed(black_box(hash));
+                }
+            });
+        });
+        group.bench_function(format!("insert_hashes_p={}", p), |b| {
+            b.iter(|| hll.insert_hashes(black_box(&hashes)));
+        });
+    }
+    group.finish();
+}
+
+
+
+// The 10k-element lists of `bench_hll_combinations`, inserted one by one and in hashed batches; the gap
+// between `insert_many` and `insert_many_u64` widens with the `simd` feature on CPUs with AVX2
+fn bench_u64_batches(c: &mut Criterion) {
+    let mut group = c.benchmark_group("HLL_u64_batches");
+
+    group.sample_size(30);
+    group.warm_up_time(std::time::Duration::from_secs(1));
+
+    for p in P_VALUES {
+        let list = generate_random_list_with_cardinality(10_000, 5_000)
+            .expect("Failed to generate list for benchmark");
+        group.throughput(criterion::Throughput::Elements(list.len() as u64));
+
+        let mut hll = Hypeerlog::with_precision(p);
+        group.bench_function(format!("insert_loop_p={}", p), |b| {
+            b.iter(|| {
+                for elem in black_box(&list) {
+                    hll.insert(elem);
+                }
+            });
+        });
+        group.bench_function(format!("insert_many_p={}", p), |b| {
+            b.iter(|| hll.insert_many(black_box(&list)));
+        });
+        group.bench_function(format!("insert_many_u64_p={}", p), |b| {
+            b.iter(|| hll.insert_many_u64(black_box(&list)));
+        });
+    }
+    group.finish();
+}
+
+// Elements inserted one by one and with `insert_many`, which only batches their hashes once the registers
+// outgrow the caches
+fn bench_large_insert_many(c: &mut Criterion) {
+    let mut group = c.benchmark_group("HLL_large_insert_many");
+
+    group.sample_size(10);
+    group.warm_up_time(std::time::Duration::from_secs(1));
+
+    let mut rng = rand::rng();
+    let elems: Vec<u64> = (0..1_000_000).map(|_| rng.random()).collect();
+    group.throughput(criterion::Throughput::Elements(elems.len() as u64));
+
+    for p in [16, 20, 24] {
+        let mut hll = Hypeerlog::with_precision(p);
+        group.bench_function(format!("insert_loop_p={}", p), |b| {
+            b.iter(|| {
+                for elem in black_box(&elems) {
+                    hll.insert(elem);
+                }
+            });
+        });
+        group.bench_function(format!("insert_many_p={}", p), |b| {
+            b.iter(|| hll.insert_many(black_box(&elems)));
+        });
+    }
+    group.finish();
+}
+
+fn bench_short_strings(c: &mut Criterion) {
+    let mut group = c.benchmark_group("HLL_short_strings");
+
+    group.sample_size(20);
+    group.warm_up_time(std::time::Duration::from_secs(1));
+
+    let mut rng = rand::rng();
+    for len in [4, 8, 16, 32] {
+        let strings: Vec<String> = (0..100_000)
+            .map(|_| (0..len).map(|_| rng.random_range(b'a'..=b'z') as char).collect())
+            .collect();
+        group.throughput(criterion::Throughput::Elements(strings.len() as u64));
+
+        let mut hll = Hypeerlog::with_precision(14);
+        // `insert` goes through the streaming hasher, `insert_str` through the one-shot hash of the bytes
+        group.bench_function(format!("insert_len={}", len), |b| {
+            b.iter(|| {
+                for s in &strings {
+                    hll.insert(black_box(s.as_str()));
+                }
+            });
+        });
+        group.bench_function(format!("insert_str_len={}", len), |b| {
+            b.iter(|| {
+                for s in &strings {
+                    hll.insert_str(black_box(s));
+                }
+            });
+        });
+    }
+    group.finish();
+}
+
+
+
 //--------------
 // Running the benchmarks
 //--------------
 
 
-criterion_group!(benches, bench_hll_combinations, bench_merging);
+fn bench_dumping(c: &mut Criterion) {
+    let mut group = c.benchmark_group("HLL_dumping");
+
+    group.sample_size(30);
+    group.warm_up_time(std::time::Duration::from_secs(1));
+
+    for p in [14, 16] {
+        let list = generate_random_list_with_cardinality(10_000, 5_000)
+            .expect("Failed to generate list for benchmark");
+        let mut hll = Hypeerlog::with_precision(p);
+        hll.insert_many(&list);
+
+        let mut out = Vec::new();
+        hll.dump_into(&mut out);
+        let before = ALLOCATIONS.load(Ordering::Relaxed);
+        for _ in 0..1000 {
+            hll.dump_into(black_box(&mut out));
+        }
+        let steady_state = ALLOCATIONS.load(Ordering::Relaxed) - before;
+        assert_eq!(steady_state, 0);
+
+        group.bench_function(format!("dump_p={}", p), |b| {
+            b.iter(|| black_box(hll.dump()));
+        });
+        group.bench_function(format!("dump_into_p={}", p), |b| {
+            b.iter(|| hll.dump_into(black_box(&mut out)));
+        });
+    }
+    group.finish();
+}
+
+
+// The estimate is kept up to date by the inserts, so its latency should not grow with the precision, unlike
+// estimating from a dump or a borrowed view, which scan the registers
+fn bench_cardinality(c: &mut Criterion) {
+    let mut group = c.benchmark_group("HLL_cardinality");
+
+    group.sample_size(30);
+    group.warm_up_time(std::time::Duration::from_secs(1));
+
+    for p in [12, 14, 16, 18, 22] {
+        let list = generate_random_list_with_cardinality(100_000, 50_000)
+            .expect("Failed to generate list for benchmark");
+        let mut hll = Hypeerlog::with_precision(p);
+        hll.insert_many(&list);
+        let dump = hll.dump();
+
+        group.bench_function(format!("cardinality_p={}", p), |b| {
+            b.iter(|| black_box(black_box(&hll).cardinality()));
+        });
+        group.bench_function(format!("cardinality_of_dump_p={}", p), |b| {
+            b.iter(|| black_box(cardinality_of_dump(&dump).unwrap()));
+        });
+        // A single pass over the registers, without the checksum of the dump
+        let view = Hypeerlog::borrow_dump(&dump).unwrap();
+        group.bench_function(format!("view_cardinality_p={}", p), |b| {
+            b.iter(|| black_box(black_box(&view).cardinality()));
+        });
+        let mut other = Hypeerlog::with_precision(p);
+        other.insert_many(&generate_random_list_with_cardinality(100_000, 50_000).expect("Failed to generate list for benchmark"));
+        let other_dump = other.dump();
+        let other_view = Hypeerlog::borrow_dump(&other_dump).unwrap();
+        group.bench_function(format!("view_union_cardinality_p={}", p), |b| {
+            b.iter(|| black_box(black_box(&view).union_cardinality(&other_view).unwrap()));
+        });
+    }
+    group.finish();
+}
+
+
+// The cost of the 6-bit packed registers: the same hashes and elements inserted into unpacked and packed sketches
+fn bench_packed(c: &mut Criterion) {
+    let mut group = c.benchmark_group("HLL_packed");
+
+    group.sample_size(10);
+    group.warm_up_time(std::time::Duration::from_secs(1));
+
+    let mut rng = rand::rng();
+    let hashes: Vec<u64> = (0..1_000_000).map(|_| rng.random()).collect();
+    group.throughput(criterion::Throughput::Elements(hashes.len() as u64));
+
+    for p in [12, 14, 16, 20] {
+        let mut hll = Hypeerlog::with_precision(p);
+        let mut packed = PackedHypeerlog::with_precision(p);
+        group.bench_function(format!("insert_hashed_p={}", p), |b| {
+            b.iter(|| {
+                for &hash in &hashes {
+                    hll.insert_hashed(black_box(hash));
+                }
+            });
+        });
+        group.bench_function(format!("packed_insert_hashed_p={}", p), |b| {
+            b.iter(|| {
+                for &hash in &hashes {
+                    packed.insert_hashed(black_box(hash));
+                }
+            });
+        });
+        group.bench_function(format!("insert_p={}", p), |b| {
+            b.iter(|| hll.insert_many(black_box(&hashes)));
+        });
+        group.bench_function(format!("packed_insert_p={}", p), |b| {
+            b.iter(|| packed.insert_many(black_box(&hashes)));
+        });
+    }
+    group.finish();
+}
+
+
+
+criterion_group!(benches, bench_hll_combinations, bench_merging, bench_loading, bench_dumping, bench_pre_hashed, bench_short_strings, bench_u64_batches, bench_large_insert_many, bench_cardinality, bench_packed);
 criterion_main!(benches);
@@ -0,0 +1,20 @@
+#![no_main]
+
+//! Throws arbitrary bytes at every dump loader: none of them may panic, and whatever loads must
+//! round-trip through a fresh dump. Run with `cargo fuzz run load` from the crate root.
+
+use hypeerlog::Hypeerlog;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let loaded = Hypeerlog::load(data.to_vec());
+    let _ = Hypeerlog::load_unchecked(data.to_vec());
+    let _ = Hypeerlog::read_from(&mut &data[..]);
+    let _ = Hypeerlog::borrow_dump(data).map(|view| view.cardinality());
+    let _ = hypeerlog::cardinality_of_dump(data);
+
+    if let Ok(hll) = loaded {
+        assert!(hll.cardinality().is_finite());
+        assert_eq!(Hypeerlog::load(hll.dump()).unwrap(), hll);
+    }
+});
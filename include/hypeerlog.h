@@ -0,0 +1,106 @@
+#ifndef HYPEERLOG_H
+#define HYPEERLOG_H
+
+/* Generated by cbindgen from src/ffi.rs, do not edit. */
+
+#include <stddef.h>
+#include <stdint.h>
+
+// The call succeeded.
+#define HLL_OK 0
+
+// A pointer argument was null.
+#define HLL_ERR_NULL -1
+
+// The sketches have different precisions.
+#define HLL_ERR_PRECISION_MISMATCH -2
+
+// The sketches were built with different hasher seeds.
+#define HLL_ERR_SEED_MISMATCH -3
+
+// The sketches were built with different hash algorithms.
+#define HLL_ERR_HASHER_MISMATCH -4
+
+// The call panicked, which is a bug of this crate.
+#define HLL_ERR_PANIC -5
+
+// An opaque sketch, created by [`hll_new`] or [`hll_load`] and freed by [`hll_free`].
+typedef struct Hll Hll;
+
+#ifdef __cplusplus
+extern "C" {
+#endif  // __cplusplus
+
+// Creates an empty sketch with a precision and a seed for the default Murmur3 hasher.
+//
+// The precision value is silently clamped to the valid range of `4..=25`. Returns null if the call panicked.
+Hll *hll_new(uint8_t precision, uint32_t seed);
+
+// Inserts the `len` bytes at `data`, hashed like [`Hypeerlog::insert_bytes`] does.
+//
+// Returns 1 if the register of the item was raised, 0 if it was not, or a negative `HLL_ERR_*` code. `data` may
+// be null when `len` is 0.
+//
+// # Safety
+//
+// `hll` must be null or a sketch of this API that is not used by another thread during the call, and `data`
+// must point to `len` readable bytes unless `len` is 0.
+int hll_insert_bytes(Hll *hll, const uint8_t *data, size_t len);
+
+// Returns the estimated distinct element count of a sketch, or NaN if `hll` is null or the call panicked.
+//
+// # Safety
+//
+// `hll` must be null or a sketch of this API that is not modified by another thread during the call.
+double hll_cardinality(const Hll *hll);
+
+// Merges the sketch `src` into `dst`, like [`Hypeerlog::merge_with`].
+//
+// Returns [`HLL_OK`], or a negative `HLL_ERR_*` code, in which case `dst` is not modified. `dst` and `src` may
+// be the same sketch.
+//
+// # Safety
+//
+// `dst` and `src` must be null or sketches of this API that are not used by another thread during the call.
+int hll_merge(Hll *dst, const Hll *src);
+
+// Serializes a sketch like [`Hypeerlog::dump`], and writes the length of the dump to `out_len`.
+//
+// The dump must be freed by [`hll_dump_free`] with that length. Returns null if an argument is null or the call
+// panicked.
+//
+// # Safety
+//
+// `hll` must be null or a sketch of this API that is not modified by another thread during the call, and
+// `out_len` must be null or point to a writable `size_t`.
+uint8_t *hll_dump(const Hll *hll, size_t *out_len);
+
+// Frees a dump returned by [`hll_dump`], given its length. Does nothing if `dump` is null.
+//
+// # Safety
+//
+// `dump` must be null or a dump returned by [`hll_dump`] with the length `len`, and not freed before.
+void hll_dump_free(uint8_t *dump, size_t len);
+
+// Reads a sketch from the `len` bytes at `bytes` like [`Hypeerlog::from_bytes`], with the hasher seed of the dump.
+//
+// Returns null if `bytes` is null, if the bytes are not a valid dump of a sketch with the default hasher, or if
+// the call panicked.
+//
+// # Safety
+//
+// `bytes` must be null or point to `len` readable bytes.
+Hll *hll_load(const uint8_t *bytes, size_t len);
+
+// Frees a sketch created by [`hll_new`] or [`hll_load`]. Does nothing if `hll` is null.
+//
+// # Safety
+//
+// `hll` must be null or a sketch of this API that is not freed before and not used after the call.
+void hll_free(Hll *hll);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* HYPEERLOG_H */
@@ -0,0 +1,130 @@
+//! Python bindings of `hypeerlog`, built with `maturin develop` or `maturin build` in this directory.
+//!
+//! The `hypeerlog` module exposes a `Hypeerlog` class over sketches with the default Murmur3 hasher, whose dumps
+//! are the ones of `Hypeerlog::dump` in Rust. Strings are inserted as their UTF-8 bytes and bytes as they are,
+//! both through `Hypeerlog::insert_bytes`, and integers as the little-endian bytes of a `u64` or an `i64`, which
+//! is how `Hypeerlog::insert` hashes them; sketches built from the same items in Python and Rust have the same
+//! registers and merge with each other.
+//!
+//! ```python
+//! from hypeerlog import Hypeerlog
+//!
+//! with open("users-2024-01-01.hll", "rb") as f:
+//!     users = Hypeerlog.loads(f.read())
+//! users.update(["user-1", "user-2", 42])
+//! print(users.cardinality())
+//! ```
+//!
+//! Errors of the crate are raised as `hypeerlog.HypeerlogError`, a subclass of `ValueError`.
+
+use pyo3::create_exception;
+use pyo3::exceptions::{PyOverflowError, PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyByteArray, PyBytes, PyInt, PyString};
+
+use hypeerlog::Hypeerlog;
+
+
+
+create_exception!(hypeerlog, HypeerlogError, PyValueError, "An error of the hypeerlog crate, such as a merge of sketches of different precisions or an invalid dump.");
+
+
+/// A HyperLogLog sketch, mergeable with the sketches of the hypeerlog Rust crate.
+#[pyclass(name = "Hypeerlog", module = "hypeerlog")]
+struct PyHypeerlog(Hypeerlog);
+
+
+#[pymethods]
+impl PyHypeerlog {
+    /// Creates an empty sketch with a precision, clamped to 4..=25, and a seed for the Murmur3 hasher.
+    #[new]
+    #[pyo3(signature = (precision = 14, seed = 0))]
+    fn new(precision: u8, seed: u32) -> Self {
+        PyHypeerlog(Hypeerlog::with_precision_seed(precision, seed))
+    }
+
+    /// Inserts a `str`, `bytes`, `bytearray` or `int` item, and returns whether its register was raised.
+    fn insert(&mut self, item: &Bound<'_, PyAny>) -> PyResult<bool> {
+        if let Ok(s) = item.downcast::<PyString>() {
+            Ok(self.0.insert_bytes(s.to_cow()?.as_bytes()))
+        } else if let Ok(bytes) = item.downcast::<PyBytes>() {
+            Ok(self.0.insert_bytes(bytes.as_bytes()))
+        } else if let Ok(bytes) = item.downcast::<PyByteArray>() {
+            Ok(self.0.insert_bytes(&bytes.to_vec()))
+        } else if let Ok(int) = item.downcast::<PyInt>() {
+            let bytes = match int.extract::<u64>() {
+                Ok(int) => int.to_le_bytes(),
+                Err(_) => int.extract::<i64>().map_err(|_| PyOverflowError::new_err("integers must fit in a u64 or an i64"))?.to_le_bytes(),
+            };
+            Ok(self.0.insert_bytes(&bytes))
+        } else {
+            Err(PyTypeError::new_err(format!("cannot insert an item of type {}", item.get_type().name()?)))
+        }
+    }
+
+    /// Inserts exactly the given bytes, and returns whether their register was raised.
+    fn insert_bytes(&mut self, data: &[u8]) -> bool {
+        self.0.insert_bytes(data)
+    }
+
+    /// Inserts every item of an iterable, like `insert` does.
+    fn update(&mut self, items: &Bound<'_, PyAny>) -> PyResult<()> {
+        for item in items.try_iter()? {
+            self.insert(&item?)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the estimated number of distinct items.
+    fn cardinality(&self) -> f64 {
+        self.0.cardinality()
+    }
+
+    /// Merges another sketch into this one, so that it estimates the union of both.
+    ///
+    /// Raises `HypeerlogError` if the sketches have different precisions or seeds.
+    fn merge(&mut self, other: &Self) -> PyResult<()> {
+        self.0.merge_with(&other.0).map_err(to_py_err)
+    }
+
+    /// Serializes the sketch to the dump format of the Rust crate.
+    fn dumps<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, &self.0.dump())
+    }
+
+    /// Reads a sketch from a dump of the Rust crate, with the seed recorded in the dump.
+    ///
+    /// Raises `HypeerlogError` if the data is not a valid dump.
+    #[staticmethod]
+    fn loads(data: &[u8]) -> PyResult<Self> {
+        Hypeerlog::from_bytes(data).map(PyHypeerlog).map_err(to_py_err)
+    }
+
+    /// The precision of the sketch.
+    #[getter]
+    fn precision(&self) -> u8 {
+        self.0.precision()
+    }
+
+    fn __eq__(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Hypeerlog(precision={}, cardinality={:.1})", self.0.precision(), self.0.cardinality())
+    }
+}
+
+
+fn to_py_err(err: hypeerlog::HypeerlogError) -> PyErr {
+    HypeerlogError::new_err(err.to_string())
+}
+
+
+#[pymodule]
+#[pyo3(name = "hypeerlog")]
+fn hypeerlog_python(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyHypeerlog>()?;
+    m.add("HypeerlogError", m.py().get_type::<HypeerlogError>())?;
+    Ok(())
+}
@@ -0,0 +1,66 @@
+from pathlib import Path
+
+import pytest
+
+from hypeerlog import Hypeerlog, HypeerlogError
+
+
+# Written by `test_python_fixture` in the Rust tests, from the same items as `fixture_items`
+FIXTURE = Path(__file__).resolve().parents[2] / "tests" / "fixtures" / "python_items_p12_seed7.bin"
+
+
+def fixture_items():
+    return [f"user-{i}" for i in range(1000)] + list(range(500))
+
+
+def test_sketches_match_the_rust_fixture():
+    rust = Hypeerlog.loads(FIXTURE.read_bytes())
+    assert rust.precision == 12
+
+    python = Hypeerlog(12, seed=7)
+    python.update(fixture_items())
+    assert python.dumps() == FIXTURE.read_bytes()
+    assert python == rust
+    assert python.cardinality() == rust.cardinality()
+
+    # Strings are their UTF-8 bytes, and integers the little-endian bytes of a u64
+    by_bytes = Hypeerlog(12, seed=7)
+    for i in range(1000):
+        by_bytes.insert_bytes(f"user-{i}".encode())
+    for i in range(500):
+        by_bytes.insert(bytearray(i.to_bytes(8, "little")))
+    assert by_bytes == rust
+
+
+def test_merges_with_rust_sketches():
+    rust = Hypeerlog.loads(FIXTURE.read_bytes())
+    later = Hypeerlog(12, seed=7)
+    later.update(f"user-{i}" for i in range(800, 3000))
+    later.merge(rust)
+
+    union = Hypeerlog(12, seed=7)
+    union.update(fixture_items())
+    union.update(f"user-{i}" for i in range(800, 3000))
+    assert later == union
+    assert Hypeerlog.loads(later.dumps()) == union
+    assert abs(later.cardinality() - 3500) < 3500 * 4 * 1.04 / 64
+
+
+def test_errors_are_raised_as_exceptions():
+    sketch = Hypeerlog(12, seed=7)
+    with pytest.raises(HypeerlogError, match="precision"):
+        sketch.merge(Hypeerlog(13, seed=7))
+    with pytest.raises(HypeerlogError, match="seed"):
+        sketch.merge(Hypeerlog(12))
+    with pytest.raises(HypeerlogError):
+        Hypeerlog.loads(FIXTURE.read_bytes()[:-1])
+    with pytest.raises(HypeerlogError):
+        Hypeerlog.loads(b"not a dump")
+    assert issubclass(HypeerlogError, ValueError)
+
+    with pytest.raises(TypeError):
+        sketch.insert(1.5)
+    with pytest.raises(OverflowError):
+        sketch.insert(2**64)
+    assert sketch.cardinality() == 0.0
+    assert Hypeerlog(40).precision == 25
@@ -0,0 +1,105 @@
+use core::hash::BuildHasher;
+
+use ahash::{AHasher, RandomState};
+
+use crate::HasherId;
+
+
+
+/// The [`HasherId::hasher_tag`] of [`AHashBuilder`] when aHash uses its AES-NI implementation.
+pub const AHASH_AES_TAG: u32 = u32::from_le_bytes(*b"AHSA");
+
+/// The [`HasherId::hasher_tag`] of [`AHashBuilder`] when aHash uses its portable fallback implementation.
+pub const AHASH_FALLBACK_TAG: u32 = u32::from_le_bytes(*b"AHSF");
+
+// aHash picks its implementation at compile time with the same condition (with its `nightly-arm-aes` feature
+// off), and the two produce different hashes
+#[cfg(all(any(target_arch = "x86", target_arch = "x86_64"), target_feature = "aes", not(miri)))]
+const AHASH_TAG: u32 = AHASH_AES_TAG;
+#[cfg(not(all(any(target_arch = "x86", target_arch = "x86_64"), target_feature = "aes", not(miri))))]
+const AHASH_TAG: u32 = AHASH_FALLBACK_TAG;
+
+
+/// A `BuildHasher` for aHash with fixed keys derived from a 64-bit seed.
+///
+/// aHash is the fastest of the optional hashers on integer elements, especially where AES-NI is enabled
+/// (`-C target-feature=+aes` or `-C target-cpu=native`). Unlike `ahash::RandomState::new()`, the keys only
+/// depend on the seed, so the same seed gives the same sketch on every run, and the seed is recorded in dumps.
+///
+/// aHash does not promise the same hashes across its releases, nor between its AES-NI and fallback
+/// implementations. The [`HasherId::hasher_tag`] tells the two implementations apart, so a dump built by one
+/// refuses to load or merge with the other, but dumps meant to outlive an upgrade of the `ahash` dependency
+/// should use a hasher with a fixed specification instead.
+///
+/// ```
+/// use hypeerlog::{AHashBuilder, Hypeerlog};
+///
+/// let mut hll = Hypeerlog::with_hasher(AHashBuilder::new(42));
+/// for user_id in 0..1000u64 {
+///     hll.insert(user_id);
+/// }
+/// assert!((hll.cardinality() - 1000.0).abs() < 50.0);
+/// ```
+#[derive(Debug, Clone)]
+pub struct AHashBuilder {
+    seed: u64,
+    state: RandomState,
+}
+
+impl AHashBuilder {
+    /// Creates a builder of hashers whose keys are derived from `seed`.
+    pub fn new(seed: u64) -> Self {
+        // SplitMix64 spreads the seed over the four 64-bit keys of aHash
+        let mut x = seed;
+        let mut next = || {
+            x = x.wrapping_add(0x9e3779b97f4a7c15);
+            let mut z = x;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+            z ^ (z >> 31)
+        };
+        let state = RandomState::with_seeds(next(), next(), next(), next());
+        AHashBuilder { seed, state }
+    }
+
+    /// Returns the seed the keys were derived from.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+}
+
+impl Default for AHashBuilder {
+    fn default() -> Self {
+        AHashBuilder::new(0)
+    }
+}
+
+impl PartialEq for AHashBuilder {
+    fn eq(&self, other: &Self) -> bool {
+        self.seed == other.seed
+    }
+}
+
+impl Eq for AHashBuilder {}
+
+impl BuildHasher for AHashBuilder {
+    type Hasher = AHasher;
+
+    fn build_hasher(&self) -> Self::Hasher {
+        self.state.build_hasher()
+    }
+}
+
+impl HasherId for AHashBuilder {
+    fn hasher_seed(&self) -> Option<u64> {
+        Some(self.seed)
+    }
+
+    fn reseeded(&self, seed: u64) -> Option<Self> {
+        Some(AHashBuilder::new(seed))
+    }
+
+    fn hasher_tag(&self) -> Option<u32> {
+        Some(AHASH_TAG)
+    }
+}
@@ -0,0 +1,154 @@
+use core::hash::BuildHasher;
+
+use crate::{dump, murmur_from_stored, Allocator, HasherId, Hypeerlog, HypeerlogError, Murmur3BuildHasher, Vec};
+use crate::registers::RegisterArray;
+use crate::utils::pow_two;
+
+
+
+impl<S, A> Hypeerlog<S, A>
+where
+    S: BuildHasher + HasherId,
+    A: Allocator,
+{
+    /// Creates a new instance using a custom hasher builder with a default precision of 14, whose registers are
+    /// allocated by `alloc`.
+    pub fn with_hasher_in(hasher_builder: S, alloc: A) -> Self {
+        Self::with_hasher_precision_in(14, hasher_builder, alloc)
+    }
+
+    /// Creates a new instance with a custom hasher builder and a specific precision, whose registers are
+    /// allocated by `alloc`.
+    ///
+    /// The precision value is silently clamped to the valid range of `4..=25`. Like those of
+    /// [`Hypeerlog::with_hasher_precision`], the registers are only allocated by the first insert or merge that
+    /// raises one of them.
+    pub fn with_hasher_precision_in(precision: u8, hasher_builder: S, alloc: A) -> Self {
+        let p = precision.clamp(4, 25);
+        Hypeerlog {
+            hasher: hasher_builder,
+            precision: p,
+            registers: RegisterArray::zeroed_in(pow_two(p) as usize, alloc),
+            insertions: 0,
+        }
+    }
+
+    /// Deserializes a dump from a borrowed slice using a custom hasher, copying the registers into `alloc`.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`Hypeerlog::load_with_hasher`].
+    pub fn from_bytes_with_hasher_in(bytes: &[u8], hasher_builder: S, alloc: A) -> Result<Self, HypeerlogError> {
+        let parsed = dump::parse_dump(bytes, dump::Checksum::Verify)?;
+        parsed.hasher.check(dump::StoredHasher::of(&hasher_builder))?;
+        Ok(Hypeerlog {
+            hasher: hasher_builder,
+            precision: parsed.precision,
+            registers: parsed.register_array_in(bytes, alloc),
+            insertions: parsed.insertions,
+        })
+    }
+
+    /// Returns the allocator of the registers.
+    pub fn allocator(&self) -> &A {
+        self.registers.allocator()
+    }
+
+    /// Copies the sketch into a new one whose registers are allocated by `alloc`.
+    ///
+    /// The registers of a sketch that never saw an element stay unallocated in the copy.
+    pub fn clone_in<B: Allocator>(&self, alloc: B) -> Hypeerlog<S, B>
+    where
+        S: Clone,
+    {
+        Hypeerlog {
+            hasher: self.hasher.clone(),
+            precision: self.precision,
+            registers: self.registers.clone_in(alloc),
+            insertions: self.insertions,
+        }
+    }
+
+    /// Serializes the state of the sketch like [`Hypeerlog::dump`], into a vector allocated by `alloc`.
+    pub fn dump_in<B: Allocator>(&self, alloc: B) -> Vec<u8, B> {
+        let prefix = self.dump_prefix();
+        let mut bytes = Vec::with_capacity_in(prefix.as_bytes().len() + self.dump_payload().len(), alloc);
+        bytes.extend_from_slice(prefix.as_bytes());
+        bytes.extend_from_slice(self.dump_payload());
+        bytes
+    }
+}
+
+
+impl<A: Allocator> Hypeerlog<Murmur3BuildHasher, A> {
+    /// Creates a new instance with a precision of 14 using the default `Murmur3BuildHasher`, whose registers are
+    /// allocated by `alloc`.
+    ///
+    /// ```
+    /// #![feature(allocator_api)]
+    /// use std::alloc::Global;
+    /// use hypeerlog::Hypeerlog;
+    ///
+    /// let mut hll = Hypeerlog::new_in(Global);
+    /// hll.insert_many(&[1, 2, 3, 2, 1]);
+    /// assert_eq!(hll.cardinality().round(), 3.0);
+    /// assert_eq!(Hypeerlog::load(hll.dump_in(Global)).unwrap(), hll);
+    /// ```
+    pub fn new_in(alloc: A) -> Self {
+        Self::with_precision_in(14, alloc)
+    }
+
+    /// Constructs a new instance with a specific precision using the default `Murmur3BuildHasher`, whose registers
+    /// are allocated by `alloc`.
+    ///
+    /// The precision value is silently clamped to `4..=25`.
+    pub fn with_precision_in(precision: u8, alloc: A) -> Self {
+        Self::with_hasher_precision_in(precision, Murmur3BuildHasher::new(0), alloc)
+    }
+
+    /// Constructs a new instance with a custom precision and seed for the default hasher, whose registers are
+    /// allocated by `alloc`.
+    ///
+    /// Precision is clamped to `4..=25`.
+    pub fn with_precision_seed_in(precision: u8, seed: u32, alloc: A) -> Self {
+        Self::with_hasher_precision_in(precision, Murmur3BuildHasher::new(seed), alloc)
+    }
+
+    /// Deserializes a dump from a borrowed slice using the default `Murmur3BuildHasher`, copying the registers
+    /// into `alloc`.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`Hypeerlog::load`].
+    pub fn from_bytes_in(bytes: &[u8], alloc: A) -> Result<Self, HypeerlogError> {
+        let parsed = dump::parse_dump(bytes, dump::Checksum::Verify)?;
+        let (hasher, _) = murmur_from_stored(parsed.hasher)?;
+        Ok(Hypeerlog {
+            hasher,
+            precision: parsed.precision,
+            registers: parsed.register_array_in(bytes, alloc),
+            insertions: parsed.insertions,
+        })
+    }
+}
+
+
+// Sketches compare their precision and registers only, like those of the global allocator, whatever allocated
+// the registers of either side
+impl<S, A, B> PartialEq<Hypeerlog<S, B>> for Hypeerlog<S, A>
+where
+    S: BuildHasher + HasherId,
+    A: Allocator,
+    B: Allocator,
+{
+    fn eq(&self, other: &Hypeerlog<S, B>) -> bool {
+        self.precision == other.precision && self.registers.eq_in(&other.registers)
+    }
+}
+
+impl<S, A> Eq for Hypeerlog<S, A>
+where
+    S: BuildHasher + HasherId,
+    A: Allocator,
+{
+}
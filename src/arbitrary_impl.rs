@@ -0,0 +1,41 @@
+use core::hash::BuildHasher;
+
+use arbitrary::{Arbitrary, Unstructured};
+
+use crate::{HasherId, Hypeerlog, Vec};
+use crate::registers::RegisterArray;
+use crate::utils::{max_rank, pow_two};
+
+
+
+// Larger sketches are valid too, but would make every fuzz input allocate megabytes of registers
+const MAX_PRECISION: u8 = 16;
+
+
+/// Generates sketches that uphold the same invariants as loaded ones, so that they can be merged,
+/// dumped and loaded back without tripping validation.
+///
+/// The precision is drawn from `4..=16` and each register from `0..=max_rank(p)`, until the input
+/// runs out; the remaining registers are left empty. The hasher is `S::default()`, so that two
+/// generated sketches of the same precision can always be merged.
+impl<'a, S> Arbitrary<'a> for Hypeerlog<S>
+where
+    S: BuildHasher + HasherId + Default,
+{
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        let precision = u.int_in_range(4..=MAX_PRECISION)?;
+        let max = max_rank(precision);
+
+        let mut registers: Vec<u8> = vec![0; pow_two(precision) as usize];
+        for register in registers.iter_mut() {
+            if u.is_empty() { break; }
+            *register = u.int_in_range(0..=max)?;
+        }
+        Ok(Hypeerlog {
+            hasher: S::default(),
+            precision,
+            registers: RegisterArray::new(registers),
+            insertions: 0,
+        })
+    }
+}
@@ -0,0 +1,110 @@
+use core::hash::BuildHasher;
+
+use rkyv::bytecheck::Verify;
+use rkyv::rancor::{Fallible, Source};
+use rkyv::ser::{Allocator, Writer};
+use rkyv::vec::{ArchivedVec, VecResolver};
+use rkyv::{Archive, Deserialize, Place, Serialize};
+
+use crate::{ArchivedHypeerlog, HasherId, HypeerlogError, Murmur3BuildHasher, Vec};
+use crate::dump;
+use crate::registers::RegisterArray;
+use crate::utils::{estimate_cardinality, estimate_from_registers, fixed_to_f64, pow_two, union_aggregates};
+
+
+
+// The registers are archived as the plain vector of their values, and their aggregates are recomputed
+// when deserializing, so archives keep the layout of releases that stored only the values
+impl Archive for RegisterArray {
+    type Archived = ArchivedVec<u8>;
+    type Resolver = VecResolver;
+
+    fn resolve(&self, resolver: Self::Resolver, out: Place<Self::Archived>) {
+        ArchivedVec::resolve_from_len(self.len(), resolver, out);
+    }
+}
+
+impl<S: Fallible + Allocator + Writer + ?Sized> Serialize<S> for RegisterArray {
+    fn serialize(&self, serializer: &mut S) -> Result<Self::Resolver, S::Error> {
+        match self.values() {
+            Some(values) => ArchivedVec::serialize_from_slice(values, serializer),
+            None => ArchivedVec::serialize_from_iter::<u8, _, _>(self.iter(), serializer),
+        }
+    }
+}
+
+impl<D: Fallible + ?Sized> Deserialize<RegisterArray, D> for ArchivedVec<u8>
+where
+    ArchivedVec<u8>: Deserialize<Vec<u8>, D>,
+{
+    fn deserialize(&self, deserializer: &mut D) -> Result<RegisterArray, D::Error> {
+        Deserialize::<Vec<u8>, D>::deserialize(self, deserializer).map(RegisterArray::new)
+    }
+}
+
+
+// Checked when an archive is accessed with validation, so that the estimators below can rely on
+// the same invariants as a loaded sketch without re-checking them
+// SAFETY: `verify` only reads fields that bytecheck has already validated
+unsafe impl<S, C> Verify<C> for ArchivedHypeerlog<S>
+where
+    S: BuildHasher + HasherId + rkyv::Archive,
+    C: Fallible + ?Sized,
+    C::Error: Source,
+{
+    fn verify(&self, _context: &mut C) -> Result<(), C::Error> {
+        let valid = || {
+            if !(4..=25).contains(&self.precision) { return Err(HypeerlogError::InvalidPrecision); }
+            if self.registers.len() != pow_two(self.precision) as usize { return Err(HypeerlogError::InvalidLength); }
+            dump::check_ranks(self.precision, &self.registers)
+        };
+        valid().map_err(C::Error::new)
+    }
+}
+
+
+impl<S> ArchivedHypeerlog<S>
+where
+    S: BuildHasher + HasherId + rkyv::Archive,
+{
+    /// Returns the total number of register buckets of the archived sketch.
+    pub fn len(&self) -> usize {
+        self.registers.len()
+    }
+
+    /// Returns `true` if no element was ever inserted into the archived sketch.
+    pub fn is_empty(&self) -> bool {
+        self.registers.iter().all(|&val| val == 0)
+    }
+
+    /// Returns the precision of the archived sketch.
+    pub fn precision(&self) -> u8 {
+        self.precision
+    }
+
+    /// Estimates the number of unique elements directly on the archived registers, exactly like
+    /// [`Hypeerlog::cardinality`](crate::Hypeerlog::cardinality) on the live sketch.
+    pub fn cardinality(&self) -> f64 {
+        estimate_from_registers(self.precision, &self.registers)
+    }
+}
+
+impl ArchivedHypeerlog<Murmur3BuildHasher> {
+    /// Estimates the cardinality of the union of two archived sketches, without materializing the merged registers.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`HypeerlogError::PrecisionMismatch`] if the precisions differ, or [`HypeerlogError::SeedMismatch`]
+    /// if the sketches were built with different seeds.
+    pub fn union_cardinality(&self, other: &Self) -> Result<f64, HypeerlogError> {
+        if self.precision != other.precision {
+            return Err(HypeerlogError::PrecisionMismatch);
+        }
+        if self.hasher != other.hasher {
+            return Err(HypeerlogError::SeedMismatch);
+        }
+
+        let (zeros, sum) = union_aggregates(&self.registers, &other.registers);
+        Ok(estimate_cardinality(self.precision, zeros, fixed_to_f64(sum)))
+    }
+}
@@ -0,0 +1,65 @@
+use core::future::poll_fn;
+use core::hash::{BuildHasher, Hash};
+use core::pin::pin;
+use core::task::Poll;
+
+use futures_core::Stream;
+
+use crate::{HasherId, Hypeerlog, Murmur3BuildHasher};
+
+
+// The items inserted in one poll before yielding back to the executor, when the stream has more ready
+const STREAM_CHUNK: usize = 1024;
+
+
+
+impl<S> Hypeerlog<S>
+where
+    S: BuildHasher + HasherId,
+{
+    /// Inserts every item of an asynchronous stream, completing when the stream ends.
+    ///
+    /// The items are inserted as the stream yields them, like [`Hypeerlog::insert`]. When the stream has many
+    /// items ready, the future inserts at most 1024 of them per poll, then wakes itself and returns `Pending`,
+    /// so that other tasks of the executor run in between. It only needs a `futures_core::Stream`, and runs
+    /// on any executor.
+    ///
+    /// ```
+    /// use futures::executor::block_on;
+    /// use futures::stream;
+    /// use hypeerlog::Hypeerlog;
+    ///
+    /// let mut hll = Hypeerlog::new();
+    /// block_on(hll.insert_stream(stream::iter([1, 2, 3, 2, 1])));
+    /// assert_eq!(hll.cardinality().round(), 3.0);
+    /// ```
+    pub async fn insert_stream<T: Hash, St: Stream<Item = T>>(&mut self, stream: St) {
+        let mut stream = pin!(stream);
+        poll_fn(|cx| {
+            for _ in 0..STREAM_CHUNK {
+                match stream.as_mut().poll_next(cx) {
+                    Poll::Ready(Some(item)) => {
+                        self.insert(item);
+                    }
+                    Poll::Ready(None) => return Poll::Ready(()),
+                    Poll::Pending => return Poll::Pending,
+                }
+            }
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }).await
+    }
+}
+
+
+impl Hypeerlog<Murmur3BuildHasher> {
+    /// Creates a sketch of the given precision, with the default `Murmur3BuildHasher`, from every item of an
+    /// asynchronous stream, see [`Hypeerlog::insert_stream`].
+    ///
+    /// The precision value is silently clamped to the valid range of `4..=25`.
+    pub async fn from_stream_with_precision<T: Hash, St: Stream<Item = T>>(precision: u8, stream: St) -> Self {
+        let mut hll = Self::with_precision(precision);
+        hll.insert_stream(stream).await;
+        hll
+    }
+}
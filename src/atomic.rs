@@ -0,0 +1,254 @@
+use core::fmt;
+use core::hash::{BuildHasher, Hash};
+use core::sync::atomic::{AtomicU8, Ordering};
+
+use crate::{check_hashers, dump, HasherId, Hypeerlog, HypeerlogError, Murmur3BuildHasher, Vec};
+use crate::registers::RegisterArray;
+use crate::utils::{crc32, estimate_from_registers, get_bucket, longest_run, pow_two, rel_error_from_p};
+
+
+
+/// A HyperLogLog sketch whose registers are atomics, so that it is inserted into through a shared reference.
+///
+/// It can sit in an `Arc` shared by any number of threads or async tasks, without a lock: an
+/// insert raises its register with an atomic maximum, and only writes when the register grows. The registers
+/// are at most raised, never lowered, whatever the order of the inserts, so none is lost and the sketch ends up
+/// with the same registers as a [`Hypeerlog`] of the same elements.
+///
+/// The reads, [`AtomicHypeerlog::cardinality`], [`AtomicHypeerlog::snapshot`] and [`AtomicHypeerlog::dump`], use
+/// relaxed loads. While other threads insert, they may miss some of the latest inserts, so the estimate can be
+/// slightly stale, but every register they read is one the sketch really had, so it is never invalid. Unlike
+/// [`Hypeerlog::cardinality`], [`AtomicHypeerlog::cardinality`] scans the registers, as the aggregates cannot
+/// be maintained without a lock.
+///
+/// When all the writers run on threads of their own, `ShardedHypeerlog` avoids them writing to the same cache
+/// lines.
+///
+/// ```
+/// use std::sync::Arc;
+/// use hypeerlog::AtomicHypeerlog;
+///
+/// let hll = Arc::new(AtomicHypeerlog::new());
+/// let handles: Vec<_> = (0..4u64).map(|t| {
+///     let hll = Arc::clone(&hll);
+///     std::thread::spawn(move || (t * 250..(t + 1) * 250).for_each(|x| hll.insert(x)))
+/// }).collect();
+/// handles.into_iter().for_each(|h| h.join().unwrap());
+///
+/// assert!((hll.cardinality() - 1000.0).abs() < 20.0);
+/// ```
+pub struct AtomicHypeerlog<S = Murmur3BuildHasher>
+where
+    S: BuildHasher + HasherId,
+{
+    hasher: S,
+    precision: u8,
+    registers: Vec<AtomicU8>,
+}
+
+
+impl<S> AtomicHypeerlog<S>
+where
+    S: BuildHasher + HasherId,
+{
+    /// Creates a new atomic instance using a custom hasher builder with a default precision of 14.
+    pub fn with_hasher(hasher_builder: S) -> Self {
+        Self::with_hasher_precision(14, hasher_builder)
+    }
+
+    /// Creates a new atomic instance with a custom hasher builder and a specific precision.
+    ///
+    /// The precision value is silently clamped to the valid range of `4..=25`.
+    pub fn with_hasher_precision(precision: u8, hasher_builder: S) -> Self {
+        let p = precision.clamp(4, 25);
+        let mut registers = Vec::with_capacity(pow_two(p) as usize);
+        registers.resize_with(pow_two(p) as usize, || AtomicU8::new(0));
+        AtomicHypeerlog {
+            hasher: hasher_builder,
+            precision: p,
+            registers,
+        }
+    }
+
+    /// Returns the number of registers of the sketch, $2^p$.
+    pub fn len(&self) -> usize {
+        self.registers.len()
+    }
+
+    /// Returns the precision of the sketch.
+    pub fn precision(&self) -> u8 {
+        self.precision
+    }
+
+    /// Returns the expected standard relative error of the precision.
+    pub fn relative_error(&self) -> f64 {
+        rel_error_from_p(self.precision as u32)
+    }
+
+    /// Inserts a single hashable item into the sketch, see [`Hypeerlog::insert`].
+    pub fn insert<H: Hash>(&self, data: H) {
+        self.insert_hashed(self.hasher.hash_one(&data));
+    }
+
+    /// Inserts an item by a hash computed outside of the sketch, see [`Hypeerlog::insert_hashed`].
+    pub fn insert_hashed(&self, hash: u64) {
+        raise(&self.registers[get_bucket(self.precision, hash)], longest_run(self.precision, hash));
+    }
+
+    /// Inserts exactly the given bytes, fed to the hasher as they are, see [`Hypeerlog::insert_bytes`].
+    pub fn insert_bytes(&self, bytes: &[u8]) {
+        self.insert_hashed(self.hasher.hash_bytes(bytes));
+    }
+
+    /// Inserts the UTF-8 bytes of a string as they are, see [`Hypeerlog::insert_str`].
+    pub fn insert_str(&self, s: &str) {
+        self.insert_bytes(s.as_bytes());
+    }
+
+    /// Inserts a slice of items into the sketch.
+    pub fn insert_many<H: Hash>(&self, data: &[H]) {
+        for elem in data {
+            self.insert(elem);
+        }
+    }
+
+    /// Returns `true` if no elements have been observed by this sketch yet.
+    pub fn is_empty(&self) -> bool {
+        self.registers.iter().all(|reg| reg.load(Ordering::Relaxed) == 0)
+    }
+
+    /// Resets all registers back to zero.
+    ///
+    /// This takes `&mut self` because inserts running at the same time would leave an arbitrary part of them
+    /// in the sketch.
+    pub fn clear(&mut self) {
+        self.registers.iter_mut().for_each(|reg| *reg.get_mut() = 0);
+    }
+
+    /// Returns the estimated distinct element count (cardinality) observed by this sketch.
+    ///
+    /// See the type documentation for what it observes of concurrent inserts.
+    pub fn cardinality(&self) -> f64 {
+        estimate_from_registers(self.precision, &self.load_registers())
+    }
+
+    /// Merges a plain sketch of the same precision and hasher into this one in place, through a shared reference.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`HypeerlogError::PrecisionMismatch`] if the precisions differ, and
+    /// [`HypeerlogError::SeedMismatch`] or [`HypeerlogError::HasherMismatch`] if the two sketches hash
+    /// differently. `self` is not modified in that case.
+    pub fn merge_from(&self, other: &Hypeerlog<S>) -> Result<(), HypeerlogError> {
+        if self.precision != other.precision {
+            return Err(HypeerlogError::PrecisionMismatch);
+        }
+        check_hashers(&self.hasher, &other.hasher)?;
+        self.registers.iter()
+            .zip(other.registers.values().unwrap_or_default())
+            .for_each(|(reg, &val)| raise(reg, val));
+        Ok(())
+    }
+
+    /// Copies the registers into a plain [`Hypeerlog`] with the precision and hasher of this one.
+    ///
+    /// See the type documentation for what it observes of concurrent inserts.
+    pub fn snapshot(&self) -> Hypeerlog<S>
+    where
+        S: Clone,
+    {
+        Hypeerlog {
+            hasher: self.hasher.clone(),
+            precision: self.precision,
+            registers: RegisterArray::new(self.load_registers()),
+            insertions: 0,
+        }
+    }
+
+    /// Serializes a snapshot of the registers like [`Hypeerlog::dump`].
+    ///
+    /// The registers are loaded once, so the checksum always matches the dumped registers.
+    pub fn dump(&self) -> Vec<u8> {
+        let registers = self.load_registers();
+        let prefix = dump::DumpPrefix::new(self.precision, dump::StoredHasher::of(&self.hasher), 0, crc32(&registers));
+        dump::assemble(&prefix, &registers)
+    }
+
+    fn load_registers(&self) -> Vec<u8> {
+        self.registers.iter().map(|reg| reg.load(Ordering::Relaxed)).collect()
+    }
+}
+
+
+// Summarized like the `Debug` of `Hypeerlog`, without reading the registers other threads may be writing
+impl<S> fmt::Debug for AtomicHypeerlog<S>
+where
+    S: BuildHasher + HasherId,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AtomicHypeerlog")
+            .field("precision", &self.precision)
+            .field("hasher_seed", &self.hasher.hasher_seed())
+            .field("hasher_tag", &self.hasher.hasher_tag())
+            .field("len", &self.registers.len())
+            .finish()
+    }
+}
+
+
+impl AtomicHypeerlog<Murmur3BuildHasher> {
+    /// Creates a new atomic instance using the default `Murmur3BuildHasher` with a default precision of 14.
+    pub fn new() -> Self {
+        Self::with_precision(14)
+    }
+
+    /// Creates a new atomic instance with a specific precision.
+    ///
+    /// The precision value is silently clamped to the valid range of `4..=25`.
+    pub fn with_precision(precision: u8) -> Self {
+        Self::with_hasher_precision(precision, Murmur3BuildHasher::new(0))
+    }
+
+    /// Creates a new atomic instance with a specific precision and a custom seed for the default
+    /// `Murmur3BuildHasher`.
+    pub fn with_precision_seed(precision: u8, seed: u32) -> Self {
+        Self::with_hasher_precision(precision, Murmur3BuildHasher::new(seed))
+    }
+}
+
+impl Default for AtomicHypeerlog<Murmur3BuildHasher> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+
+impl<S: BuildHasher + HasherId> From<Hypeerlog<S>> for AtomicHypeerlog<S> {
+    fn from(hll: Hypeerlog<S>) -> Self {
+        AtomicHypeerlog {
+            registers: hll.registers.iter().map(AtomicU8::new).collect(),
+            hasher: hll.hasher,
+            precision: hll.precision,
+        }
+    }
+}
+
+impl<S: BuildHasher + HasherId> From<AtomicHypeerlog<S>> for Hypeerlog<S> {
+    fn from(hll: AtomicHypeerlog<S>) -> Self {
+        Hypeerlog {
+            registers: RegisterArray::new(hll.registers.into_iter().map(AtomicU8::into_inner).collect()),
+            insertions: 0,
+            hasher: hll.hasher,
+            precision: hll.precision,
+        }
+    }
+}
+
+
+// Raises an atomic register to `rank` if it is lower. Most inserts do not raise their register, and checking it
+// first with a load keeps its cache line shared between the threads reading it
+pub(crate) fn raise(register: &AtomicU8, rank: u8) {
+    if register.load(Ordering::Relaxed) < rank {
+        register.fetch_max(rank, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,109 @@
+use core::hash::BuildHasher;
+use core::str::FromStr;
+
+use crate::{HasherId, Hypeerlog, HypeerlogError, String, Vec};
+
+
+
+// Standard alphabet (RFC 4648, section 4), always padded with '='
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+const PAD: u8 = b'=';
+
+// Reverse lookup of the alphabet, with 0xff for characters outside of it
+const DECODE: [u8; 256] = {
+    let mut table = [0xff; 256];
+    let mut i = 0;
+    while i < 64 {
+        table[ALPHABET[i] as usize] = i as u8;
+        i += 1;
+    }
+    table
+};
+
+
+impl<S> Hypeerlog<S>
+where
+    S: BuildHasher + HasherId,
+{
+    /// Serializes the sketch like [`Hypeerlog::dump`], encoded as standard, padded base64 text.
+    ///
+    /// Useful for storing sketches in JSON documents, configuration files or text columns. The result
+    /// can be read back with [`Hypeerlog::load_base64`].
+    pub fn dump_base64(&self) -> String {
+        encode(&self.dump())
+    }
+
+    /// Deserializes a sketch from the base64 text produced by [`Hypeerlog::dump_base64`], using a custom hasher.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`HypeerlogError::Encoding`] if `s` is not standard, padded base64, and any error of
+    /// [`Hypeerlog::load_with_hasher`] if the decoded bytes are not a valid dump.
+    pub fn load_base64_with_hasher(s: &str, hasher_builder: S) -> Result<Self, HypeerlogError> {
+        Self::load_with_hasher(decode(s)?, hasher_builder)
+    }
+}
+
+impl Hypeerlog {
+    /// Deserializes a sketch from the base64 text produced by [`Hypeerlog::dump_base64`].
+    ///
+    /// The input must be standard (not URL-safe) base64 with padding. Whitespace, including line breaks
+    /// inserted by tools that wrap their output, is rejected rather than skipped, so strip it beforehand.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`HypeerlogError::Encoding`] if `s` is not standard, padded base64, and any error of
+    /// [`Hypeerlog::load`] if the decoded bytes are not a valid dump.
+    pub fn load_base64(s: &str) -> Result<Self, HypeerlogError> {
+        Self::load(decode(s)?)
+    }
+}
+
+/// Parses the base64 text of a dump with [`Hypeerlog::load_base64`], running all of its validation.
+impl FromStr for Hypeerlog {
+    type Err = HypeerlogError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::load_base64(s)
+    }
+}
+
+
+pub(crate) fn encode(bytes: &[u8]) -> String {
+    let mut out = Vec::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        out.push(ALPHABET[(n >> 18) as usize & 63]);
+        out.push(ALPHABET[(n >> 12) as usize & 63]);
+        out.push(if chunk.len() > 1 { ALPHABET[(n >> 6) as usize & 63] } else { PAD });
+        out.push(if chunk.len() > 2 { ALPHABET[n as usize & 63] } else { PAD });
+    }
+    // Every byte pushed comes from the ASCII alphabet
+    String::from_utf8(out).expect("base64 output is ASCII")
+}
+
+pub(crate) fn decode(s: &str) -> Result<Vec<u8>, HypeerlogError> {
+    let s = s.as_bytes();
+    if !s.len().is_multiple_of(4) { return Err(HypeerlogError::Encoding); }
+
+    let mut out = Vec::with_capacity(s.len() / 4 * 3);
+    let last = s.len() / 4;
+    for (i, quad) in s.chunks(4).enumerate() {
+        // Padding may only end the final quad, and never take up more than its last two characters
+        let padding = quad.iter().rev().take_while(|&&c| c == PAD).count();
+        if padding > 2 || (padding > 0 && i + 1 != last) { return Err(HypeerlogError::Encoding); }
+
+        let mut n = 0u32;
+        for &c in &quad[..4 - padding] {
+            let value = DECODE[c as usize];
+            if value == 0xff { return Err(HypeerlogError::Encoding); }
+            n = n << 6 | value as u32;
+        }
+        n <<= 6 * padding as u32;
+
+        let decoded = [(n >> 16) as u8, (n >> 8) as u8, n as u8];
+        out.extend_from_slice(&decoded[..3 - padding]);
+    }
+    Ok(out)
+}
@@ -0,0 +1,370 @@
+use core::fmt;
+use core::hash::{BuildHasher, Hash};
+
+use crate::{HasherId, Hypeerlog, HypeerlogError, Murmur3BuildHasher, Vec};
+
+
+
+// Ring dump layout, all integers little-endian:
+//
+// | Offset | Size | Field                                                                     |
+// |--------|------|---------------------------------------------------------------------------|
+// | 0      | 4    | magic `HLLR`                                                              |
+// | 4      | 1    | version (1)                                                               |
+// | 5      | 8    | bucket duration `d`                                                       |
+// | 13     | 4    | number of buckets `n`                                                     |
+// | 17     | 1    | 1 if the ring has seen a timestamp, else 0                                |
+// | 18     | 8    | number `k` of the newest bucket, which covers `k * d .. (k + 1) * d`      |
+// | 26     | ...  | `n` buckets from the oldest to the newest: dump length (u32), dump        |
+//
+// Bucket `i` of the dump covers `(k - n + 1 + i) * d .. (k - n + 2 + i) * d`, so the boundaries of every bucket
+// follow from the duration and the newest bucket.
+const MAGIC: [u8; 4] = *b"HLLR";
+const VERSION: u8 = 1;
+const HEADER_LEN: usize = 26;
+
+
+/// A ring of sketches of consecutive, fixed-duration time buckets, e.g. the last 24 hours as hourly sketches,
+/// whose most recent buckets are unioned on demand.
+///
+/// An insert at a timestamp, in any unit, goes to the bucket of `timestamp / bucket_duration`. A timestamp past
+/// the newest bucket advances the ring, which clears the buckets that fall out of it, and so does
+/// [`BucketedHypeerlog::advance`]; timestamps need not be increasing, and inserts older than the ring are
+/// ignored. [`BucketedHypeerlog::cardinality_last`] estimates the union of the newest buckets without changing
+/// them, so the answer is the items of whole buckets: this is coarser than [`crate::SlidingHypeerlog`], but
+/// takes $2^p$ bytes per bucket, whatever the stream, and each bucket is a plain [`Hypeerlog`].
+///
+/// All buckets share the precision and hasher of the ring, so that they can be unioned.
+///
+/// ```
+/// use hypeerlog::BucketedHypeerlog;
+///
+/// // 24 hourly buckets, with timestamps in seconds
+/// let mut ring = BucketedHypeerlog::with_buckets(3600, 24);
+/// ring.insert("ada", 0);
+/// ring.insert("grace", 10 * 3600);
+/// ring.insert("ada", 24 * 3600 + 5);
+///
+/// // The first hour expired with the last insert
+/// assert_eq!(ring.cardinality_last(24).round(), 2.0);
+/// assert_eq!(ring.cardinality_last(1).round(), 1.0);
+/// ```
+#[derive(Clone)]
+pub struct BucketedHypeerlog<S = Murmur3BuildHasher>
+where
+    S: BuildHasher + HasherId,
+{
+    bucket_duration: u64,
+    // Bucket number `k` is `buckets[k % len]`
+    buckets: Vec<Hypeerlog<S>>,
+    newest: Option<u64>,
+}
+
+
+impl<S> BucketedHypeerlog<S>
+where
+    S: BuildHasher + HasherId + Clone,
+{
+    /// Creates an empty ring of `bucket_count` buckets of `bucket_duration` each, with a custom hasher builder
+    /// and a specific precision shared by all of them.
+    ///
+    /// The precision value is silently clamped to the valid range of `4..=25`, and the duration and the number
+    /// of buckets are at least 1.
+    pub fn with_hasher_precision_buckets(precision: u8, bucket_duration: u64, bucket_count: usize, hasher_builder: S) -> Self {
+        let empty = Hypeerlog::with_hasher_precision(precision, hasher_builder);
+        BucketedHypeerlog {
+            bucket_duration: bucket_duration.max(1),
+            buckets: (0..bucket_count.max(1)).map(|_| empty.clone()).collect(),
+            newest: None,
+        }
+    }
+
+    /// Returns the estimated distinct element count of the items of the newest `n` buckets, see
+    /// [`BucketedHypeerlog::union_last`].
+    pub fn cardinality_last(&self, n: usize) -> f64 {
+        self.union_last(n).cardinality()
+    }
+
+    /// Unions the newest `n` buckets into a new [`Hypeerlog`], leaving them unchanged.
+    ///
+    /// The newest bucket is the one of the latest timestamp inserted or advanced to, and counts even if it is not
+    /// over yet. `n` is at most the number of buckets; 0 gives an empty sketch.
+    pub fn union_last(&self, n: usize) -> Hypeerlog<S> {
+        let mut union = self.empty_bucket();
+        let Some(newest) = self.newest else {
+            return union;
+        };
+        let n = n.min(self.buckets.len()) as u64;
+        let buckets = (0..n).filter_map(|age| newest.checked_sub(age)).map(|number| &self.buckets[self.index(number)]);
+        union.merge_all(buckets).expect("the buckets share the precision and hasher of the ring");
+        union
+    }
+
+    /// Reads a ring written by [`BucketedHypeerlog::dump`], with a custom hasher for its buckets.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`HypeerlogError::InvalidMagic`] if the data is not a ring dump, [`HypeerlogError::UnsupportedVersion`]
+    /// if it was written by a newer release, [`HypeerlogError::InvalidLength`] if it is truncated or its header is
+    /// inconsistent, [`HypeerlogError::PrecisionMismatch`] if its buckets differ in precision, or any error of
+    /// [`Hypeerlog::from_bytes_with_hasher`] for the dump of a bucket.
+    pub fn from_bytes_with_hasher(bytes: &[u8], hasher_builder: S) -> Result<Self, HypeerlogError> {
+        let ParsedRing { bucket_duration, newest, dumps } = parse_ring(bytes)?;
+        let mut buckets: Vec<Hypeerlog<S>> = Vec::with_capacity(dumps.len());
+        for dump in dumps {
+            let bucket = Hypeerlog::from_bytes_with_hasher(dump, hasher_builder.clone())?;
+            if buckets.first().is_some_and(|first| first.precision() != bucket.precision()) {
+                return Err(HypeerlogError::PrecisionMismatch);
+            }
+            buckets.push(bucket);
+        }
+        let mut ring = BucketedHypeerlog { bucket_duration, buckets, newest };
+        // The dump lists the buckets from the oldest
+        let oldest = ring.oldest_index();
+        ring.buckets.rotate_right(oldest);
+        Ok(ring)
+    }
+
+    // Merges the buckets of a ring of the same duration and length, after advancing to its newest bucket; the
+    // buckets of `other` that are older than this ring are dropped
+    pub(crate) fn merge_ring(&mut self, other: &Self) -> Result<(), HypeerlogError> {
+        debug_assert_eq!((self.bucket_duration, self.buckets.len()), (other.bucket_duration, other.buckets.len()));
+        self.buckets[0].check_compatible(&other.buckets[0])?;
+        if let Some(newest) = other.newest_bucket_start() {
+            self.advance(newest);
+        }
+        for (start, bucket) in other.buckets() {
+            if let Some(mine) = self.bucket_at(start) {
+                mine.merge_registers(bucket);
+            }
+        }
+        Ok(())
+    }
+
+    fn empty_bucket(&self) -> Hypeerlog<S> {
+        Hypeerlog::with_hasher_precision(self.precision(), self.buckets[0].hasher.clone())
+    }
+}
+
+impl<S> BucketedHypeerlog<S>
+where
+    S: BuildHasher + HasherId,
+{
+    /// Returns the precision of the buckets.
+    pub fn precision(&self) -> u8 {
+        self.buckets[0].precision()
+    }
+
+    /// Returns the duration of a bucket, in the unit of the timestamps.
+    pub fn bucket_duration(&self) -> u64 {
+        self.bucket_duration
+    }
+
+    /// Returns the number of buckets of the ring.
+    pub fn bucket_count(&self) -> usize {
+        self.buckets.len()
+    }
+
+    /// Returns the expected standard relative error of the precision.
+    pub fn relative_error(&self) -> f64 {
+        self.buckets[0].relative_error()
+    }
+
+    /// Returns the timestamp at which the newest bucket starts, or `None` before the first insert or advance.
+    pub fn newest_bucket_start(&self) -> Option<u64> {
+        self.newest.and_then(|number| number.checked_mul(self.bucket_duration))
+    }
+
+    /// Inserts a single hashable item seen at `timestamp` into its bucket, advancing the ring to it first if it
+    /// is past the newest bucket.
+    ///
+    /// Returns whether the register of the item was raised in its bucket, see [`Hypeerlog::insert`], and `false`
+    /// if `timestamp` is older than the oldest bucket of the ring.
+    pub fn insert<H: Hash>(&mut self, data: H, timestamp: u64) -> bool {
+        self.bucket_at(timestamp).is_some_and(|bucket| bucket.insert(data))
+    }
+
+    /// Inserts an item by a hash computed outside of the sketch, see [`Hypeerlog::insert_hashed`].
+    ///
+    /// Returns whether the register was raised, like [`BucketedHypeerlog::insert`].
+    pub fn insert_hashed(&mut self, hash: u64, timestamp: u64) -> bool {
+        self.bucket_at(timestamp).is_some_and(|bucket| bucket.insert_hashed(hash))
+    }
+
+    /// Advances the ring to the bucket of `now`, clearing the buckets that fall out of it, and returns how many
+    /// buckets it cleared.
+    ///
+    /// Advancing to a time within the newest bucket or before it changes nothing.
+    pub fn advance(&mut self, now: u64) -> usize {
+        let number = now / self.bucket_duration;
+        let Some(newest) = self.newest else {
+            self.newest = Some(number);
+            return 0;
+        };
+        if number <= newest {
+            return 0;
+        }
+        let expired = (number - newest).min(self.buckets.len() as u64);
+        for age in 0..expired {
+            let index = self.index(number - age);
+            self.buckets[index].clear();
+        }
+        self.newest = Some(number);
+        expired as usize
+    }
+
+    /// Returns the buckets from the oldest to the newest, each with the timestamp at which it starts.
+    ///
+    /// Before the first insert or advance, the ring has no bucket to return.
+    pub fn buckets(&self) -> impl Iterator<Item = (u64, &Hypeerlog<S>)> {
+        let n = self.buckets.len() as u64;
+        // Bucket numbers before 0 have never been written to
+        let numbers = self.newest.into_iter().flat_map(move |newest| newest.saturating_sub(n - 1)..=newest);
+        numbers.filter_map(move |number| Some((number.checked_mul(self.bucket_duration)?, &self.buckets[self.index(number)])))
+    }
+
+    /// Resets every bucket, and the ring to one that has seen no timestamp.
+    pub fn clear(&mut self) {
+        self.buckets.iter_mut().for_each(Hypeerlog::clear);
+        self.newest = None;
+    }
+
+    /// Serializes the whole ring: its configuration, the position of its newest bucket, and the dump of every
+    /// bucket, see [`Hypeerlog::dump`].
+    pub fn dump(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_LEN + self.buckets.iter().map(|bucket| 4 + bucket.dump_len()).sum::<usize>());
+        bytes.extend_from_slice(&MAGIC);
+        bytes.push(VERSION);
+        bytes.extend_from_slice(&self.bucket_duration.to_le_bytes());
+        bytes.extend_from_slice(&(self.buckets.len() as u32).to_le_bytes());
+        bytes.push(self.newest.is_some() as u8);
+        bytes.extend_from_slice(&self.newest.unwrap_or(0).to_le_bytes());
+        let (newer, older) = self.buckets.split_at(self.oldest_index());
+        for bucket in older.iter().chain(newer) {
+            let dump = bucket.dump();
+            bytes.extend_from_slice(&(dump.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(&dump);
+        }
+        bytes
+    }
+
+    // The position in `buckets` of the oldest bucket, which follows the newest one in the ring
+    fn oldest_index(&self) -> usize {
+        self.newest.map_or(0, |newest| (self.index(newest) + 1) % self.buckets.len())
+    }
+
+    // The bucket of `timestamp`, after advancing to it, or `None` if it is older than the ring
+    fn bucket_at(&mut self, timestamp: u64) -> Option<&mut Hypeerlog<S>> {
+        self.advance(timestamp);
+        let number = timestamp / self.bucket_duration;
+        let newest = self.newest.expect("advancing sets the newest bucket");
+        if newest - number >= self.buckets.len() as u64 {
+            return None;
+        }
+        let index = self.index(number);
+        Some(&mut self.buckets[index])
+    }
+
+    fn index(&self, number: u64) -> usize {
+        (number % self.buckets.len() as u64) as usize
+    }
+}
+
+
+// Summarized like the `Debug` of `Hypeerlog`, by the shape of the ring instead of the registers of its buckets
+impl<S> fmt::Debug for BucketedHypeerlog<S>
+where
+    S: BuildHasher + HasherId,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BucketedHypeerlog")
+            .field("precision", &self.buckets[0].precision)
+            .field("hasher_seed", &self.buckets[0].hasher.hasher_seed())
+            .field("hasher_tag", &self.buckets[0].hasher.hasher_tag())
+            .field("bucket_duration", &self.bucket_duration)
+            .field("bucket_count", &self.buckets.len())
+            .field("newest", &self.newest)
+            .finish()
+    }
+}
+
+
+impl BucketedHypeerlog<Murmur3BuildHasher> {
+    /// Creates an empty ring of `bucket_count` buckets of `bucket_duration` each, using the default
+    /// `Murmur3BuildHasher` with a default precision of 14.
+    pub fn with_buckets(bucket_duration: u64, bucket_count: usize) -> Self {
+        Self::with_precision_buckets(14, bucket_duration, bucket_count)
+    }
+
+    /// Creates an empty ring of buckets with a specific precision.
+    ///
+    /// The precision value is silently clamped to the valid range of `4..=25`.
+    pub fn with_precision_buckets(precision: u8, bucket_duration: u64, bucket_count: usize) -> Self {
+        Self::with_precision_seed_buckets(precision, 0, bucket_duration, bucket_count)
+    }
+
+    /// Creates an empty ring of buckets with a specific precision and a custom seed for the default
+    /// `Murmur3BuildHasher`, shared by all the buckets.
+    pub fn with_precision_seed_buckets(precision: u8, seed: u32, bucket_duration: u64, bucket_count: usize) -> Self {
+        Self::with_hasher_precision_buckets(precision, bucket_duration, bucket_count, Murmur3BuildHasher::new(seed))
+    }
+
+    /// Reads a ring written by [`BucketedHypeerlog::dump`], using the default `Murmur3BuildHasher` restored from
+    /// the seed recorded in the dumps of its buckets.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of [`BucketedHypeerlog::from_bytes_with_hasher`], and [`HypeerlogError::SeedMismatch`]
+    /// if its buckets were built with different seeds.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, HypeerlogError> {
+        let first = Hypeerlog::from_bytes(parse_ring(bytes)?.dumps[0])?;
+        Self::from_bytes_with_hasher(bytes, first.hasher)
+    }
+}
+
+
+// The fields of a ring dump, with the bucket dumps from the oldest
+struct ParsedRing<'a> {
+    bucket_duration: u64,
+    newest: Option<u64>,
+    dumps: Vec<&'a [u8]>,
+}
+
+fn parse_ring(bytes: &[u8]) -> Result<ParsedRing<'_>, HypeerlogError> {
+    if bytes.get(..4).is_none_or(|magic| magic != MAGIC) {
+        return Err(HypeerlogError::InvalidMagic);
+    }
+    let header = bytes.get(..HEADER_LEN).ok_or(HypeerlogError::InvalidLength)?;
+    if header[4] != VERSION {
+        return Err(HypeerlogError::UnsupportedVersion);
+    }
+    let bucket_duration = u64::from_le_bytes(header[5..13].try_into().expect("the duration is 8 bytes"));
+    let count = u32::from_le_bytes(header[13..17].try_into().expect("the count is 4 bytes")) as usize;
+    let newest = match header[17] {
+        0 => None,
+        1 => Some(u64::from_le_bytes(header[18..].try_into().expect("the bucket number is 8 bytes"))),
+        _ => return Err(HypeerlogError::InvalidLength),
+    };
+    // Every bucket takes at least its length, which bounds the count before anything is allocated for it
+    let mut rest = &bytes[HEADER_LEN..];
+    if bucket_duration == 0 || count == 0 || count > rest.len() / 4 {
+        return Err(HypeerlogError::InvalidLength);
+    }
+    // The newest bucket starts at a timestamp, like every bucket a ring advances to
+    if newest.is_some_and(|newest| newest.checked_mul(bucket_duration).is_none()) {
+        return Err(HypeerlogError::InvalidLength);
+    }
+
+    let mut dumps = Vec::with_capacity(count);
+    for _ in 0..count {
+        let len = rest.get(..4).ok_or(HypeerlogError::InvalidLength)?;
+        let len = u32::from_le_bytes(len.try_into().expect("took 4 bytes")) as usize;
+        let dump = rest.get(4..4 + len).ok_or(HypeerlogError::InvalidLength)?;
+        dumps.push(dump);
+        rest = &rest[4 + len..];
+    }
+    if !rest.is_empty() {
+        return Err(HypeerlogError::InvalidLength);
+    }
+    Ok(ParsedRing { bucket_duration, newest, dumps })
+}
@@ -0,0 +1,95 @@
+use crate::HypeerlogError;
+
+
+
+// Fixed-width fields of the binary formats. Every multi-byte integer is written and read with an
+// explicit little-endian conversion, so the bytes are the same on every platform, and lengths are
+// never derived from `usize` casts of field values.
+
+
+// Appends fields to a buffer of at most `N` bytes, for headers whose maximal size is known
+pub(crate) struct Writer<const N: usize> {
+    bytes: [u8; N],
+    len: usize,
+}
+
+impl<const N: usize> Writer<N> {
+    pub(crate) fn new() -> Self {
+        Writer { bytes: [0; N], len: 0 }
+    }
+
+    pub(crate) fn put_bytes(&mut self, field: &[u8]) {
+        self.bytes[self.len..self.len + field.len()].copy_from_slice(field);
+        self.len += field.len();
+    }
+
+    pub(crate) fn put_u8(&mut self, value: u8) {
+        self.put_bytes(&[value]);
+    }
+
+    pub(crate) fn put_u32(&mut self, value: u32) {
+        self.put_bytes(&value.to_le_bytes());
+    }
+
+    pub(crate) fn put_u64(&mut self, value: u64) {
+        self.put_bytes(&value.to_le_bytes());
+    }
+
+    // Drops the fields written after the first `len` bytes
+    pub(crate) fn truncate(&mut self, len: usize) {
+        debug_assert!(len <= self.len);
+        self.len = len;
+    }
+
+    // Sets bits of a byte written earlier
+    pub(crate) fn or_byte(&mut self, offset: usize, bits: u8) {
+        debug_assert!(offset < self.len);
+        self.bytes[offset] |= bits;
+    }
+
+    pub(crate) fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+}
+
+
+// Reads fields from the front of a buffer, failing with `InvalidLength` when it runs out
+pub(crate) struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub(crate) fn new(bytes: &'a [u8]) -> Self {
+        Reader { bytes, position: 0 }
+    }
+
+    // The number of bytes read so far
+    pub(crate) fn position(&self) -> usize {
+        self.position
+    }
+
+    pub(crate) fn get_bytes(&mut self, len: usize) -> Result<&'a [u8], HypeerlogError> {
+        let end = self.position.checked_add(len).ok_or(HypeerlogError::InvalidLength)?;
+        let field = self.bytes.get(self.position..end).ok_or(HypeerlogError::InvalidLength)?;
+        self.position = end;
+        Ok(field)
+    }
+
+    fn get_array<const L: usize>(&mut self) -> Result<[u8; L], HypeerlogError> {
+        let field = self.get_bytes(L)?;
+        Ok(field.try_into().expect("the field has the requested length"))
+    }
+
+    pub(crate) fn get_u8(&mut self) -> Result<u8, HypeerlogError> {
+        self.get_array::<1>().map(|[value]| value)
+    }
+
+    pub(crate) fn get_u32(&mut self) -> Result<u32, HypeerlogError> {
+        self.get_array().map(u32::from_le_bytes)
+    }
+
+    pub(crate) fn get_u64(&mut self) -> Result<u64, HypeerlogError> {
+        self.get_array().map(u64::from_le_bytes)
+    }
+}
@@ -0,0 +1,99 @@
+use core::hash::BuildHasher;
+
+use crate::{vec, HasherId, Hypeerlog, HypeerlogError, Vec};
+use crate::dump::{self, FLAG_COMPACT};
+
+
+
+// Compact register encoding:
+//
+// A sequence of `(zero run, value)` pairs, one per nonzero register in ascending index order.
+// The zero run is the number of zero registers since the previous nonzero one (or since the
+// start), as an unsigned LEB128 varint, and the value is the register byte itself. Zero
+// registers after the last pair are implied by the precision.
+
+// A run is at most 2^25 registers, which fits in 4 varint bytes; a 5th is accepted for any u32
+const MAX_VARINT_LEN: usize = 5;
+
+
+impl<S> Hypeerlog<S>
+where
+    S: BuildHasher + HasherId,
+{
+    /// Serializes the state of the sketch like [`Hypeerlog::dump`], run-length encoding the zero registers.
+    ///
+    /// Each nonzero register is stored as a varint-coded count of the zero registers preceding it plus its
+    /// value, which makes dumps of sparsely populated sketches a fraction of the dense size. The compact
+    /// encoding is only used when it is smaller than the dense one, in which case a flag is set in the
+    /// header; otherwise the result is identical to [`Hypeerlog::dump`]. Either way, [`Hypeerlog::load`]
+    /// reads it back transparently.
+    pub fn dump_compact(&self) -> Vec<u8> {
+        // All-zero registers are dumped compact already, without a payload
+        let (false, Some(registers)) = (self.is_empty(), self.registers.values()) else {
+            return self.dump();
+        };
+        let mut prefix = self.dump_prefix();
+        match encode(registers) {
+            Some(payload) => {
+                prefix.set_flag(FLAG_COMPACT);
+                dump::assemble(&prefix, &payload)
+            }
+            None => self.dump(),
+        }
+    }
+}
+
+
+// Encodes the registers, or returns `None` as soon as the encoding is no smaller than the dense array
+fn encode(registers: &[u8]) -> Option<Vec<u8>> {
+    let mut out = Vec::new();
+    let mut run = 0u32;
+    for &value in registers {
+        if value == 0 {
+            run += 1;
+            continue;
+        }
+        write_varint(&mut out, run);
+        out.push(value);
+        run = 0;
+        if out.len() >= registers.len() {
+            return None;
+        }
+    }
+    Some(out)
+}
+
+// Decodes a compact payload into `len` dense registers
+pub(crate) fn decode(payload: &[u8], len: usize) -> Result<Vec<u8>, HypeerlogError> {
+    let mut registers = vec![0; len];
+    let mut index = 0usize;
+    let mut rest = payload;
+    while !rest.is_empty() {
+        let (run, tail) = read_varint(rest)?;
+        let (&value, tail) = tail.split_first().ok_or(HypeerlogError::InvalidLength)?;
+        index += run as usize;
+        *registers.get_mut(index).ok_or(HypeerlogError::InvalidRegisterIndex)? = value;
+        index += 1;
+        rest = tail;
+    }
+    Ok(registers)
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u32) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn read_varint(bytes: &[u8]) -> Result<(u32, &[u8]), HypeerlogError> {
+    let mut value = 0u32;
+    for (i, &byte) in bytes.iter().take(MAX_VARINT_LEN).enumerate() {
+        value |= ((byte & 0x7f) as u32) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Ok((value, &bytes[i + 1..]));
+        }
+    }
+    Err(HypeerlogError::InvalidLength)
+}
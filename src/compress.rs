@@ -0,0 +1,85 @@
+use core::hash::BuildHasher;
+
+use crate::{HasherId, Hypeerlog, HypeerlogError, Vec};
+use crate::dump;
+
+
+
+// Codec identifiers stored in the `FLAG_COMPRESSED` field of a dump
+pub(crate) const CODEC_ZSTD: u8 = 1;
+pub(crate) const CODEC_GZIP: u8 = 2;
+
+
+// Decompresses a payload, refusing to produce more than `limit` bytes so that a small malicious
+// dump cannot claim a small precision and then expand into an arbitrarily large buffer
+pub(crate) fn decompress(codec: u8, payload: &[u8], limit: usize) -> Result<Vec<u8>, HypeerlogError> {
+    match codec {
+        #[cfg(feature = "zstd")]
+        CODEC_ZSTD => {
+            let decoder = zstd::stream::read::Decoder::new(payload).map_err(|_| HypeerlogError::DecompressionFailed)?;
+            read_bounded(decoder, limit)
+        }
+        #[cfg(feature = "flate2")]
+        CODEC_GZIP => read_bounded(flate2::read::GzDecoder::new(payload), limit),
+        _ => Err(HypeerlogError::UnsupportedCompression),
+    }
+}
+
+// Reads at most `limit` bytes, failing if the stream holds more
+#[cfg(any(feature = "zstd", feature = "flate2"))]
+fn read_bounded(reader: impl std::io::Read, limit: usize) -> Result<Vec<u8>, HypeerlogError> {
+    use std::io::Read;
+
+    let mut out = Vec::with_capacity(limit);
+    reader.take(limit as u64 + 1)
+        .read_to_end(&mut out)
+        .map_err(|_| HypeerlogError::DecompressionFailed)?;
+    if out.len() > limit {
+        return Err(HypeerlogError::InvalidLength);
+    }
+    Ok(out)
+}
+
+
+impl<S> Hypeerlog<S>
+where
+    S: BuildHasher + HasherId,
+{
+    /// Serializes the state of the sketch like [`Hypeerlog::dump`], compressing the registers with zstd.
+    ///
+    /// `level` is the zstd compression level (`1..=22`, or `0` for the library default). Register values
+    /// are heavily skewed towards a few small ranks, so the dense array compresses well even for large
+    /// sketches. [`Hypeerlog::load`] recognizes the compression flag in the header and decompresses transparently.
+    ///
+    /// An empty sketch is dumped like [`Hypeerlog::dump`], whose compact payload is already empty.
+    #[cfg(feature = "zstd")]
+    pub fn dump_zstd(&self, level: i32) -> Vec<u8> {
+        let (false, Some(registers)) = (self.is_empty(), self.registers.values()) else {
+            return self.dump();
+        };
+        // Compressing an in-memory buffer can only fail on allocation failure
+        let payload = zstd::bulk::compress(registers, level).expect("zstd compression failed");
+        dump::assemble(&self.dump_prefix().with_codec(CODEC_ZSTD), &payload)
+    }
+
+    /// Serializes the state of the sketch like [`Hypeerlog::dump`], compressing the registers with gzip.
+    ///
+    /// Useful when the consumers of the dumps only speak gzip; otherwise `Hypeerlog::dump_zstd` (behind the
+    /// `zstd` feature) usually compresses better and faster. [`Hypeerlog::load`] decompresses transparently.
+    ///
+    /// An empty sketch is dumped like [`Hypeerlog::dump`], as with `Hypeerlog::dump_zstd`.
+    #[cfg(feature = "flate2")]
+    pub fn dump_gzip(&self) -> Vec<u8> {
+        use std::io::Write;
+
+        let (false, Some(registers)) = (self.is_empty(), self.registers.values()) else {
+            return self.dump();
+        };
+
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        // Writing to a vector cannot fail
+        encoder.write_all(registers).expect("gzip compression failed");
+        let payload = encoder.finish().expect("gzip compression failed");
+        dump::assemble(&self.dump_prefix().with_codec(CODEC_GZIP), &payload)
+    }
+}
@@ -0,0 +1,172 @@
+use core::hash::BuildHasher;
+
+use crate::{HasherId, Hypeerlog, HypeerlogError, Vec};
+use crate::dump;
+use crate::murmur::{murmur3_x64_128, Murmur3x64Hasher};
+use crate::registers::RegisterArray;
+use crate::utils::{max_rank, pow_two, two_pow_neg};
+
+
+
+// Apache DataSketches HLL sketch image in HLL mode (see `HllPreambleUtil` in the Java library).
+// All multi-byte integers and floats are little-endian:
+//
+// | offset | size | field                                                      |
+// |--------|------|------------------------------------------------------------|
+// | 0      | 1    | preamble size in 4-byte ints, `10` in HLL mode             |
+// | 1      | 1    | serialization version, `1`                                |
+// | 2      | 1    | family id, `7` for HLL                                     |
+// | 3      | 1    | lgK, the log2 of the number of slots                       |
+// | 4      | 1    | lgArr, unused in HLL mode                                  |
+// | 5      | 1    | flags                                                      |
+// | 6      | 1    | curMin, always `0` for HLL_8                               |
+// | 7      | 1    | current mode in bits 0-1, target HLL type in bits 2-3      |
+// | 8      | 8    | HIP estimator accumulator (f64)                            |
+// | 16     | 8    | kxq0: sum of 2^-v over the slots with v < 32 (f64)         |
+// | 24     | 8    | kxq1: sum of 2^-v over the slots with v >= 32 (f64)        |
+// | 32     | 4    | number of slots at curMin (i32)                            |
+// | 36     | 4    | number of exceptions, HLL_4 only (i32)                     |
+// | 40     | 2^lgK| slots, one byte each for HLL_8                             |
+//
+// DataSketches hashes with MurmurHash3_x64_128 and seed 9001, takes the slot from the low lgK
+// bits of the first half of the hash and the value from the leading zeros of the second half.
+
+const PREAMBLE_INTS: u8 = 10;
+const SER_VER: u8 = 1;
+const FAMILY_HLL: u8 = 7;
+const HEADER_LEN: usize = PREAMBLE_INTS as usize * 4;
+
+const MODE_HLL: u8 = 2;
+const TYPE_HLL_8: u8 = 2;
+// The HIP accumulator is not maintained here, so exported sketches tell DataSketches to ignore it
+const FLAG_OUT_OF_ORDER: u8 = 0b1_0000;
+
+const MIN_LG_K: u8 = 4;
+const MAX_LG_K: u8 = 21;
+
+const MURMUR3_SEED: u64 = 9001;
+
+
+/// The [`HasherId::hasher_tag`] of [`DataSketchesHasher`].
+pub const DATASKETCHES_TAG: u32 = u32::from_le_bytes(*b"DSKH");
+
+
+/// The hasher of sketches imported from Apache DataSketches with [`Hypeerlog::from_datasketches`].
+///
+/// It hashes with MurmurHash3_x64_128 and the default DataSketches seed, and carries its own
+/// [`HasherId::hasher_tag`], so a dump of an imported sketch refuses to load or merge into a sketch built
+/// with another hasher.
+///
+/// DataSketches derives the slot and the value of an element from two separate 64-bit halves of its hash,
+/// which [`Hypeerlog::insert`] cannot reproduce. Use [`Hypeerlog::insert_datasketches`] to add elements
+/// exactly like `HllSketch.update(byte[])`.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DataSketchesHasher;
+
+impl BuildHasher for DataSketchesHasher {
+    type Hasher = Murmur3x64Hasher;
+
+    fn build_hasher(&self) -> Self::Hasher {
+        Murmur3x64Hasher::new(MURMUR3_SEED)
+    }
+}
+
+impl HasherId for DataSketchesHasher {
+    fn hasher_tag(&self) -> Option<u32> {
+        Some(DATASKETCHES_TAG)
+    }
+}
+
+
+impl Hypeerlog<DataSketchesHasher> {
+    /// Imports an Apache DataSketches HLL sketch from its binary image (`HllSketch.toCompactByteArray()` or
+    /// `toUpdatableByteArray()` in Java, `serialize_compact()` or `serialize_updatable()` in C++).
+    ///
+    /// Only sketches in HLL mode with the `HLL_8` target type are supported; their lgK becomes the precision.
+    /// DataSketches hashes with a different function than the default `Murmur3BuildHasher`, so the imported
+    /// sketch can only be merged with other DataSketches-hashed data: other imports, or sketches filled with
+    /// [`Hypeerlog::insert_datasketches`]. Its [`DataSketchesHasher`] makes dumps of it fail to load or merge
+    /// with [`HypeerlogError::HasherMismatch`] anywhere else.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The bytes are not an HLL sketch image of serialization version 1 ([`HypeerlogError::InvalidMagic`]).
+    /// - The sketch is in list or set mode (small sketches), or its target type is `HLL_4` or `HLL_6`
+    ///   ([`HypeerlogError::UnsupportedEncoding`]).
+    /// - lgK is outside `4..=21` ([`HypeerlogError::InvalidPrecision`]).
+    /// - The image is not exactly as long as its preamble and $2^{lgK}$ slots ([`HypeerlogError::InvalidLength`]).
+    /// - A slot is larger than any rank possible at that precision ([`HypeerlogError::InvalidRegisterValue`]).
+    pub fn from_datasketches(bytes: &[u8]) -> Result<Self, HypeerlogError> {
+        if bytes.len() < 8 || bytes[1] != SER_VER || bytes[2] != FAMILY_HLL {
+            return Err(HypeerlogError::InvalidMagic);
+        }
+        let (mode, hll_type) = (bytes[7] & 0b11, bytes[7] >> 2 & 0b11);
+        if mode != MODE_HLL || hll_type != TYPE_HLL_8 {
+            return Err(HypeerlogError::UnsupportedEncoding);
+        }
+        let precision = bytes[3];
+        if !(MIN_LG_K..=MAX_LG_K).contains(&precision) {
+            return Err(HypeerlogError::InvalidPrecision);
+        }
+        if bytes[0] != PREAMBLE_INTS || bytes.len() != HEADER_LEN + pow_two(precision) as usize {
+            return Err(HypeerlogError::InvalidLength);
+        }
+
+        let registers = bytes[HEADER_LEN..].to_vec();
+        dump::check_ranks(precision, &registers)?;
+        Ok(Hypeerlog {
+            hasher: DataSketchesHasher,
+            precision,
+            registers: RegisterArray::new(registers),
+            insertions: 0,
+        })
+    }
+
+    /// Exports the sketch as a DataSketches `HLL_8` sketch image in HLL mode, which the Java and C++ libraries
+    /// can `heapify` or `deserialize` and union with their own sketches.
+    ///
+    /// The image is flagged as out of order, so DataSketches estimates from the slots rather than from its HIP
+    /// accumulator, which this crate does not track.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`HypeerlogError::InvalidPrecision`] if the precision is above 21, the largest lgK DataSketches supports.
+    pub fn to_datasketches(&self) -> Result<Vec<u8>, HypeerlogError> {
+        if self.precision > MAX_LG_K {
+            return Err(HypeerlogError::InvalidPrecision);
+        }
+
+        let (mut kxq0, mut kxq1) = (0.0, 0.0);
+        for val in self.registers.iter() {
+            if val < 32 { kxq0 += two_pow_neg(val) } else { kxq1 += two_pow_neg(val) }
+        }
+        let at_cur_min = self.registers.iter().filter(|&val| val == 0).count() as i32;
+
+        let mut bytes = Vec::with_capacity(HEADER_LEN + self.registers.len());
+        bytes.extend_from_slice(&[PREAMBLE_INTS, SER_VER, FAMILY_HLL, self.precision, 0, FLAG_OUT_OF_ORDER, 0, MODE_HLL | TYPE_HLL_8 << 2]);
+        bytes.extend_from_slice(&self.cardinality().to_le_bytes());
+        bytes.extend_from_slice(&kxq0.to_le_bytes());
+        bytes.extend_from_slice(&kxq1.to_le_bytes());
+        bytes.extend_from_slice(&at_cur_min.to_le_bytes());
+        bytes.extend_from_slice(&0i32.to_le_bytes());
+        self.registers.chunks().for_each(|chunk| bytes.extend_from_slice(chunk));
+        Ok(bytes)
+    }
+
+    /// Adds a raw element exactly like `HllSketch.update(byte[])` does in DataSketches, so the slots stay
+    /// identical to a DataSketches sketch of the same lgK fed the same elements. As there, empty elements are ignored.
+    ///
+    /// Strings are added by DataSketches as their UTF-8 bytes, so `insert_datasketches(s.as_bytes())` matches `update(s)`.
+    pub fn insert_datasketches(&mut self, element: &[u8]) {
+        if element.is_empty() {
+            return;
+        }
+        let (h0, h1) = murmur3_x64_128(element, MURMUR3_SEED);
+        let slot = h0 as usize & (self.registers.len() - 1);
+        self.insertions = self.insertions.saturating_add(1);
+        // DataSketches caps the value at 63; it never gets near the rank bound of a valid sketch in practice
+        let val = (h1.leading_zeros().min(62) as u8 + 1).min(max_rank(self.precision));
+        self.registers.raise(slot, val);
+    }
+}
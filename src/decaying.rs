@@ -0,0 +1,273 @@
+use core::fmt;
+use core::hash::{BuildHasher, Hash};
+
+use crate::{BucketedHypeerlog, HasherId, Hypeerlog, HypeerlogError, Murmur3BuildHasher, Vec};
+
+
+
+// Decaying dump layout, all integers little-endian:
+//
+// | Offset | Size | Field                                                            |
+// |--------|------|------------------------------------------------------------------|
+// | 0      | 4    | magic `HLLD`                                                     |
+// | 4      | 1    | version (1)                                                      |
+// | 5      | 8    | half-life                                                        |
+// | 13     | ...  | dump of the ring of buckets, see `BucketedHypeerlog::dump`       |
+const MAGIC: [u8; 4] = *b"HLLD";
+const VERSION: u8 = 1;
+const HEADER_LEN: usize = 13;
+
+// Buckets per half-life, and half-lives kept by the ring
+const BUCKETS_PER_HALF_LIFE: u64 = 4;
+const HALF_LIVES: u64 = 10;
+
+
+/// A HyperLogLog sketch whose items fade with their age, for distinct counts in which recent activity dominates,
+/// e.g. to detect trends or anomalies.
+///
+/// [`DecayingHypeerlog::decayed_cardinality`] estimates a decayed weight rather than a distinct count: the sum,
+/// over the distinct items, of $2^{-age / half\_life}$, where the age of an item is the time from its latest
+/// insert to `now`. An item inserted just now weighs 1, one last seen a half-life ago weighs 0.5, and an item seen
+/// again is as recent as its latest insert; a steady stream of `r` new items per unit of time settles at a weight
+/// of $r \cdot half\_life / \ln 2$.
+///
+/// The items are kept in a [`BucketedHypeerlog`] of buckets of a quarter of the half-life each (at least 1), over
+/// ten half-lives: the age of an item is taken from the middle of its bucket, which moves its weight by at most
+/// 9% either way for half-lives of at least 4, and the items older than the ring weigh less than 0.1% and are
+/// dropped as it advances. The weight is computed from the estimates of the unions of the newest buckets, so its
+/// error is about the [`DecayingHypeerlog::relative_error`] of the sketches, relative to the items of the ring. An
+/// estimate unions all the buckets, which costs about 40 merges of $2^p$ registers.
+///
+/// ```
+/// use hypeerlog::DecayingHypeerlog;
+///
+/// // A half-life of an hour, with timestamps in seconds
+/// let mut users = DecayingHypeerlog::with_precision_half_life(12, 3600);
+/// for user in 0..1000 {
+///     users.insert(user, 450);
+/// }
+///
+/// let fresh = users.decayed_cardinality(450);
+/// let one_hour = users.decayed_cardinality(450 + 3600);
+/// assert!((fresh - 1000.0).abs() < 50.0);
+/// assert!((one_hour - 500.0).abs() < 25.0);
+/// ```
+#[derive(Clone)]
+pub struct DecayingHypeerlog<S = Murmur3BuildHasher>
+where
+    S: BuildHasher + HasherId,
+{
+    half_life: u64,
+    ring: BucketedHypeerlog<S>,
+}
+
+
+impl<S> DecayingHypeerlog<S>
+where
+    S: BuildHasher + HasherId + Clone,
+{
+    /// Creates an empty sketch with a custom hasher builder, a specific precision and a half-life, in the unit of
+    /// the timestamps.
+    ///
+    /// The precision value is silently clamped to the valid range of `4..=25`, and the half-life is at least 1.
+    pub fn with_hasher_precision_half_life(precision: u8, half_life: u64, hasher_builder: S) -> Self {
+        let half_life = half_life.max(1);
+        let (bucket_duration, bucket_count) = ring_shape(half_life);
+        DecayingHypeerlog {
+            half_life,
+            ring: BucketedHypeerlog::with_hasher_precision_buckets(precision, bucket_duration, bucket_count, hasher_builder),
+        }
+    }
+
+    /// Returns the decayed weight of the distinct items at `now`, see [`DecayingHypeerlog`].
+    ///
+    /// The items inserted after `now` weigh 1, like the ones inserted at `now`.
+    pub fn decayed_cardinality(&self, now: u64) -> f64 {
+        let duration = self.ring.bucket_duration();
+        let mut union: Option<Hypeerlog<S>> = None;
+        let mut previous = 0.0;
+        let mut weight = 0.0;
+        // The items first seen in a bucket, going back in time, weigh as much as its middle
+        for (start, bucket) in self.ring.buckets().collect::<Vec<_>>().into_iter().rev() {
+            let union = match &mut union {
+                Some(union) => {
+                    union.merge_registers(bucket);
+                    union
+                }
+                None => union.insert(bucket.clone()),
+            };
+            let cardinality = union.cardinality();
+            let age = (now as f64 - (start as f64 + duration as f64 / 2.0)) / self.half_life as f64;
+            weight += decay(age) * (cardinality - previous).max(0.0);
+            previous = previous.max(cardinality);
+        }
+        weight
+    }
+
+    /// Merges another sketch into this one, bucket by bucket, after advancing to its newest bucket.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`HypeerlogError::HalfLifeMismatch`] if the sketches have different half-lives, or the errors of
+    /// [`Hypeerlog::merge_with`] if they have different precisions or hashers. `self` is not modified in these
+    /// cases.
+    pub fn merge_with(&mut self, other: &Self) -> Result<(), HypeerlogError> {
+        if self.half_life != other.half_life {
+            return Err(HypeerlogError::HalfLifeMismatch);
+        }
+        self.ring.merge_ring(&other.ring)
+    }
+
+    /// Reads a sketch written by [`DecayingHypeerlog::dump`], with a custom hasher.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`HypeerlogError::InvalidMagic`] if the data is not a decaying dump,
+    /// [`HypeerlogError::UnsupportedVersion`] if it was written by a newer release, [`HypeerlogError::InvalidLength`]
+    /// if it is truncated or its ring does not match its half-life, or any error of
+    /// [`BucketedHypeerlog::from_bytes_with_hasher`] for the ring.
+    pub fn from_bytes_with_hasher(bytes: &[u8], hasher_builder: S) -> Result<Self, HypeerlogError> {
+        let (half_life, ring) = split_dump(bytes)?;
+        Self::from_parts(half_life, BucketedHypeerlog::from_bytes_with_hasher(ring, hasher_builder)?)
+    }
+
+    fn from_parts(half_life: u64, ring: BucketedHypeerlog<S>) -> Result<Self, HypeerlogError> {
+        if (ring.bucket_duration(), ring.bucket_count()) != ring_shape(half_life) {
+            return Err(HypeerlogError::InvalidLength);
+        }
+        Ok(DecayingHypeerlog { half_life, ring })
+    }
+}
+
+impl<S> DecayingHypeerlog<S>
+where
+    S: BuildHasher + HasherId,
+{
+    /// Returns the precision of the sketch.
+    pub fn precision(&self) -> u8 {
+        self.ring.precision()
+    }
+
+    /// Returns the half-life of the items, in the unit of the timestamps.
+    pub fn half_life(&self) -> u64 {
+        self.half_life
+    }
+
+    /// Returns the expected standard relative error of the precision.
+    pub fn relative_error(&self) -> f64 {
+        self.ring.relative_error()
+    }
+
+    /// Inserts a single hashable item seen at `timestamp`.
+    ///
+    /// Returns whether the register of the item was raised in its bucket, and `false` if `timestamp` is more than
+    /// about ten half-lives older than the latest one, see [`BucketedHypeerlog::insert`].
+    pub fn insert<H: Hash>(&mut self, data: H, timestamp: u64) -> bool {
+        self.ring.insert(data, timestamp)
+    }
+
+    /// Inserts an item by a hash computed outside of the sketch, see [`Hypeerlog::insert_hashed`].
+    ///
+    /// Returns whether the register was raised, like [`DecayingHypeerlog::insert`].
+    pub fn insert_hashed(&mut self, hash: u64, timestamp: u64) -> bool {
+        self.ring.insert_hashed(hash, timestamp)
+    }
+
+    /// Resets the sketch to an empty one that has seen no timestamp.
+    pub fn clear(&mut self) {
+        self.ring.clear();
+    }
+
+    /// Serializes the half-life and the ring of buckets, with the timestamp of the newest one.
+    pub fn dump(&self) -> Vec<u8> {
+        let ring = self.ring.dump();
+        let mut bytes = Vec::with_capacity(HEADER_LEN + ring.len());
+        bytes.extend_from_slice(&MAGIC);
+        bytes.push(VERSION);
+        bytes.extend_from_slice(&self.half_life.to_le_bytes());
+        bytes.extend_from_slice(&ring);
+        bytes
+    }
+}
+
+
+// Summarized like the `Debug` of `Hypeerlog`, by its half-life and the summary of its ring
+impl<S> fmt::Debug for DecayingHypeerlog<S>
+where
+    S: BuildHasher + HasherId,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DecayingHypeerlog")
+            .field("half_life", &self.half_life)
+            .field("ring", &self.ring)
+            .finish()
+    }
+}
+
+
+impl DecayingHypeerlog<Murmur3BuildHasher> {
+    /// Creates an empty sketch using the default `Murmur3BuildHasher` with a default precision of 14, and a
+    /// half-life.
+    pub fn with_half_life(half_life: u64) -> Self {
+        Self::with_precision_half_life(14, half_life)
+    }
+
+    /// Creates an empty sketch with a specific precision and a half-life.
+    ///
+    /// The precision value is silently clamped to the valid range of `4..=25`, and the half-life is at least 1.
+    pub fn with_precision_half_life(precision: u8, half_life: u64) -> Self {
+        Self::with_precision_seed_half_life(precision, 0, half_life)
+    }
+
+    /// Creates an empty sketch with a specific precision, a custom seed for the default `Murmur3BuildHasher` and a
+    /// half-life.
+    pub fn with_precision_seed_half_life(precision: u8, seed: u32, half_life: u64) -> Self {
+        Self::with_hasher_precision_half_life(precision, half_life, Murmur3BuildHasher::new(seed))
+    }
+
+    /// Reads a sketch written by [`DecayingHypeerlog::dump`], using the default `Murmur3BuildHasher` restored from
+    /// the seed recorded in the dumps of its buckets.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`DecayingHypeerlog::from_bytes_with_hasher`], with the checks of [`BucketedHypeerlog::from_bytes`].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, HypeerlogError> {
+        let (half_life, ring) = split_dump(bytes)?;
+        Self::from_parts(half_life, BucketedHypeerlog::from_bytes(ring)?)
+    }
+}
+
+
+// The duration and number of buckets of the ring of a half-life
+fn ring_shape(half_life: u64) -> (u64, usize) {
+    let bucket_duration = (half_life / BUCKETS_PER_HALF_LIFE).max(1);
+    (bucket_duration, half_life.saturating_mul(HALF_LIVES).div_ceil(bucket_duration) as usize)
+}
+
+// The weight of an item `age` half-lives old, 1 for the items of the future
+fn decay(age: f64) -> f64 {
+    #[cfg(not(feature = "no_std"))]
+    {
+        (-age.max(0.0)).exp2()
+    }
+    #[cfg(feature = "no_std")]
+    {
+        libm::exp2(-age.max(0.0))
+    }
+}
+
+// The half-life and the ring dump of a decaying dump
+fn split_dump(bytes: &[u8]) -> Result<(u64, &[u8]), HypeerlogError> {
+    if bytes.get(..4).is_none_or(|magic| magic != MAGIC) {
+        return Err(HypeerlogError::InvalidMagic);
+    }
+    let header = bytes.get(..HEADER_LEN).ok_or(HypeerlogError::InvalidLength)?;
+    if header[4] != VERSION {
+        return Err(HypeerlogError::UnsupportedVersion);
+    }
+    let half_life = u64::from_le_bytes(header[5..].try_into().expect("the half-life is 8 bytes"));
+    if half_life == 0 {
+        return Err(HypeerlogError::InvalidLength);
+    }
+    Ok((half_life, &bytes[HEADER_LEN..]))
+}
@@ -0,0 +1,126 @@
+use core::hash::BuildHasher;
+
+use crate::{HasherId, Hypeerlog, HypeerlogError, Vec};
+use crate::utils::{max_rank, pow_two};
+
+
+
+// Arbitrary fixed value hashed to fingerprint the hasher configuration of a sketch
+const FINGERPRINT_PROBE: u64 = 0x9E37_79B9_7F4A_7C15;
+
+// Precision byte + little-endian u32 fingerprint
+pub(crate) const DELTA_HEADER_LEN: usize = 5;
+// Little-endian u32 register index + register value
+pub(crate) const DELTA_ENTRY_LEN: usize = 5;
+
+
+/// A point-in-time copy of the registers of a [`Hypeerlog`], used as the base for [`Hypeerlog::delta_since`].
+///
+/// Taking a snapshot copies the register array once ($2^p$ bytes); it does not borrow the sketch, which
+/// keeps accepting inserts in the meantime.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegisterSnapshot {
+    precision: u8,
+    registers: Vec<u8>,
+}
+
+impl RegisterSnapshot {
+    /// Returns the precision of the sketch this snapshot was taken from.
+    pub fn precision(&self) -> u8 {
+        self.precision
+    }
+}
+
+
+impl<S> Hypeerlog<S>
+where
+    S: BuildHasher + HasherId,
+{
+    /// Captures the current register state so that later changes can be extracted with [`Hypeerlog::delta_since`].
+    pub fn snapshot(&self) -> RegisterSnapshot {
+        RegisterSnapshot {
+            precision: self.precision,
+            registers: self.registers.to_vec(),
+        }
+    }
+
+    /// Serializes only the registers that changed since `snap` was taken.
+    ///
+    /// The delta starts with a 5-byte header (the precision, followed by a little-endian `u32` fingerprint
+    /// of the hasher configuration), followed by one 5-byte entry per changed register: its index as a
+    /// little-endian `u32` and its new value. A sketch that did not change since the snapshot produces
+    /// a header-only delta. Deltas are replayed with [`Hypeerlog::apply_delta`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`HypeerlogError::PrecisionMismatch`] if `snap` was taken from a sketch with a different precision.
+    pub fn delta_since(&self, snap: &RegisterSnapshot) -> Result<Vec<u8>, HypeerlogError> {
+        if self.precision != snap.precision {
+            return Err(HypeerlogError::PrecisionMismatch);
+        }
+
+        let mut delta = Vec::with_capacity(DELTA_HEADER_LEN);
+        delta.push(self.precision);
+        delta.extend_from_slice(&self.fingerprint().to_le_bytes());
+
+        for (idx, (new, &old)) in self.registers.iter().zip(&snap.registers).enumerate() {
+            if new != old {
+                delta.extend_from_slice(&(idx as u32).to_le_bytes());
+                delta.push(new);
+            }
+        }
+        Ok(delta)
+    }
+
+    /// Applies a delta produced by [`Hypeerlog::delta_since`] on a sketch with the same configuration.
+    ///
+    /// Every register in the delta is merged with max semantics, so applying the same delta twice, or
+    /// applying several deltas in any order, converges to the same state. The delta is fully validated
+    /// before any register is touched.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error, leaving `self` unmodified, if:
+    /// - The delta is truncated or has a partial entry ([`HypeerlogError::InvalidLength`]).
+    /// - The delta was produced at a different precision ([`HypeerlogError::PrecisionMismatch`]).
+    /// - The delta was produced with a different hasher configuration ([`HypeerlogError::FingerprintMismatch`]).
+    /// - An entry references a register outside `0..2^p` ([`HypeerlogError::InvalidRegisterIndex`]).
+    /// - An entry holds a value above the maximum rank for the precision ([`HypeerlogError::InvalidRegisterValue`]).
+    pub fn apply_delta(&mut self, delta: &[u8]) -> Result<(), HypeerlogError> {
+        if delta.len() < DELTA_HEADER_LEN || !(delta.len() - DELTA_HEADER_LEN).is_multiple_of(DELTA_ENTRY_LEN) {
+            return Err(HypeerlogError::InvalidLength);
+        }
+        if delta[0] != self.precision {
+            return Err(HypeerlogError::PrecisionMismatch);
+        }
+        if delta[1..DELTA_HEADER_LEN] != self.fingerprint().to_le_bytes() {
+            return Err(HypeerlogError::FingerprintMismatch);
+        }
+
+        let entries = delta[DELTA_HEADER_LEN..].chunks_exact(DELTA_ENTRY_LEN)
+            .map(|e| (u32::from_le_bytes([e[0], e[1], e[2], e[3]]) as usize, e[4]));
+
+        let num_registers = pow_two(self.precision) as usize;
+        let max_value = max_rank(self.precision);
+        for (idx, value) in entries.clone() {
+            if idx >= num_registers { return Err(HypeerlogError::InvalidRegisterIndex); }
+            if value > max_value { return Err(HypeerlogError::InvalidRegisterValue); }
+        }
+
+        for (idx, value) in entries {
+            self.registers.raise(idx, value);
+        }
+        Ok(())
+    }
+
+    // Identifies the hasher configuration: sketches whose hashers map the probe to the same value
+    // are assumed to share an algorithm and seed
+    pub(crate) fn fingerprint(&self) -> u32 {
+        fingerprint_of(&self.hasher)
+    }
+}
+
+pub(crate) fn fingerprint_of<S: BuildHasher>(hasher: &S) -> u32 {
+    let hash = hasher.hash_one(FINGERPRINT_PROBE);
+    (hash ^ (hash >> 32)) as u32
+}
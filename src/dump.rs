@@ -0,0 +1,473 @@
+use core::ops::Range;
+
+use crate::{vec, HasherId, HasherStatus, HypeerlogError, Vec};
+use crate::registers::RegisterArray;
+#[cfg(feature = "allocator-api")]
+use crate::Allocator;
+use crate::utils::{crc32, crc32_zeros, estimate_from_registers, max_rank, pow_two};
+use crate::{compact, compress, packed};
+use crate::merge::incompatible_at;
+use crate::codec::{Reader, Writer};
+
+
+
+// Versioned dump layout:
+//
+// | offset | size  | field                |
+// |--------|-------|----------------------|
+// | 0      | 3     | magic `b"HLL"`       |
+// | 3      | 1     | format version       |
+// | 4      | 1     | precision            |
+// | 5      | 1     | flags                |
+// | 6      | ...   | optional fields      |
+// | ...    | ...   | registers            |
+//
+// Each optional field is present only when its flag bit is set, and they are laid out in
+// ascending flag bit order. All multi-byte integers are little-endian, and are written and read
+// through `codec.rs` so that the layout does not depend on the platform:
+//
+// | flag             | size | field                              |
+// |------------------|------|------------------------------------|
+// | `FLAG_CHECKSUM`  | 4    | CRC32 (IEEE) of the register bytes |
+// | `FLAG_SEED`      | 8    | seed of the hasher                 |
+// | `FLAG_TAG`       | 4    | tag of the hash algorithm          |
+// | `FLAG_COMPRESSED`| 1    | codec of the compressed payload    |
+// | `FLAG_INSERTIONS`| 8    | number of insertions               |
+//
+// The registers are stored as 2^p raw bytes, unless `FLAG_COMPACT` is set, in which case they
+// use the run-length encoding described in `compact.rs`, or `FLAG_PACKED`, in which case they are
+// packed to 6 bits each as described in `packed.rs`; the two encodings exclude each other. With
+// `FLAG_COMPRESSED`, that payload is additionally compressed with the codec from `compress.rs`.
+// The checksum always covers the raw register bytes. The insertion count is only written when it is not 0, so
+// sketches that never saw an insert dump the same bytes as before it was recorded. It is the sum of the counts
+// of the merged sketches, so merging a dump again changes this field even when the registers stay the same.
+//
+// Legacy (v1) dumps are the raw registers followed by a single precision byte.
+//
+// The exact bytes of a dump are pinned by `tests/fixtures/dump_p4_seed42.bin`: a change to them must
+// update that fixture and bump `VERSION`, unless it only adds an optional field under a new flag bit, which
+// older releases reject as `UnsupportedFlags` instead of misreading it.
+
+pub(crate) const MAGIC: [u8; 3] = *b"HLL";
+pub(crate) const VERSION: u8 = 2;
+pub(crate) const HEADER_LEN: usize = 6;
+
+pub(crate) const FLAG_CHECKSUM: u8 = 0b0000_0001;
+pub(crate) const FLAG_SEED: u8 = 0b0000_0010;
+pub(crate) const FLAG_TAG: u8 = 0b0000_0100;
+pub(crate) const FLAG_COMPACT: u8 = 0b0000_1000;
+pub(crate) const FLAG_COMPRESSED: u8 = 0b0001_0000;
+pub(crate) const FLAG_PACKED: u8 = 0b0010_0000;
+pub(crate) const FLAG_INSERTIONS: u8 = 0b0100_0000;
+
+// Flag bits understood by this version of the format
+const KNOWN_FLAGS: u8 = FLAG_CHECKSUM | FLAG_SEED | FLAG_TAG | FLAG_COMPACT | FLAG_COMPRESSED | FLAG_PACKED | FLAG_INSERTIONS;
+
+// The longest header this release writes: every optional field present
+const MAX_PREFIX_LEN: usize = HEADER_LEN + 4 + 8 + 4 + 1 + 8;
+
+// The longest input that can be a valid dump: a header and the registers of the largest sketch,
+// compressed with a codec's worst-case expansion
+pub(crate) const MAX_DUMP_LEN: usize = MAX_PREFIX_LEN + max_payload_len(25);
+
+
+// A validated dump: its precision, its registers and the number of insertions it records (0 if none)
+pub(crate) struct ParsedDump {
+    pub(crate) precision: u8,
+    pub(crate) hasher: StoredHasher,
+    pub(crate) insertions: u64,
+    pub(crate) registers: Registers,
+}
+
+// Dense registers are left in place in the buffer, encoded ones are decoded into their own vector, and the
+// empty compact payload of a sketch that never saw an element stands for zero registers that are not decoded
+pub(crate) enum Registers {
+    Dense(Range<usize>),
+    Decoded(Vec<u8>),
+    Zeros,
+}
+
+// What a dump records about the hasher that produced it
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct StoredHasher {
+    pub(crate) seed: Option<u64>,
+    pub(crate) tag: Option<u32>,
+}
+
+// The hasher fields of the headers of map and array dumps: flags, then the seed and the tag, 0 without one
+pub(crate) const HASHER_HEADER_LEN: usize = 13;
+const HEADER_HAS_SEED: u8 = 1;
+const HEADER_HAS_TAG: u8 = 2;
+
+impl StoredHasher {
+    pub(crate) fn of<S: HasherId>(hasher: &S) -> Self {
+        StoredHasher {
+            seed: hasher.hasher_seed(),
+            tag: hasher.hasher_tag(),
+        }
+    }
+
+    // Appends the `HASHER_HEADER_LEN` bytes of the hasher fields of a map or array dump
+    pub(crate) fn write_header(&self, out: &mut Vec<u8>) {
+        let flags = if self.seed.is_some() { HEADER_HAS_SEED } else { 0 } | if self.tag.is_some() { HEADER_HAS_TAG } else { 0 };
+        out.push(flags);
+        out.extend_from_slice(&self.seed.unwrap_or(0).to_le_bytes());
+        out.extend_from_slice(&self.tag.unwrap_or(0).to_le_bytes());
+    }
+
+    // Reads the hasher fields written by `write_header`
+    pub(crate) fn read_header(fields: &[u8; HASHER_HEADER_LEN]) -> Result<Self, HypeerlogError> {
+        let flags = fields[0];
+        if flags & !(HEADER_HAS_SEED | HEADER_HAS_TAG) != 0 {
+            return Err(HypeerlogError::UnsupportedFlags);
+        }
+        let seed = u64::from_le_bytes(fields[1..9].try_into().expect("the seed is 8 bytes"));
+        let tag = u32::from_le_bytes(fields[9..].try_into().expect("the tag is 4 bytes"));
+        Ok(StoredHasher {
+            seed: (flags & HEADER_HAS_SEED != 0).then_some(seed),
+            tag: (flags & HEADER_HAS_TAG != 0).then_some(tag),
+        })
+    }
+
+    // Checks that the recorded seed and tag agree with the hasher the dump is combined with
+    pub(crate) fn check(&self, expected: StoredHasher) -> Result<HasherStatus, HypeerlogError> {
+        let status = match (self.tag, expected.tag) {
+            (Some(stored), Some(expected)) if stored != expected => return Err(HypeerlogError::HasherMismatch),
+            (Some(_), Some(_)) => HasherStatus::Verified,
+            _ => HasherStatus::Unverified,
+        };
+        match (self.seed, expected.seed) {
+            (Some(stored), Some(expected)) if stored != expected => Err(HypeerlogError::SeedMismatch),
+            _ => Ok(status),
+        }
+    }
+
+    // Reseeds a default-configured hasher with the recorded seed, then checks it like `check`
+    pub(crate) fn restore<S: HasherId>(&self, hasher: S) -> Result<(S, HasherStatus), HypeerlogError> {
+        // A dump of another algorithm is a hasher mismatch, even when its seed does not fit `S`
+        if let (Some(stored), Some(expected)) = (self.tag, hasher.hasher_tag()) && stored != expected {
+            return Err(HypeerlogError::HasherMismatch);
+        }
+        let hasher = match (self.seed, hasher.hasher_seed()) {
+            (Some(stored), Some(current)) if stored != current => {
+                hasher.reseeded(stored).ok_or(HypeerlogError::SeedMismatch)?
+            }
+            _ => hasher,
+        };
+        self.check(StoredHasher::of(&hasher)).map(|status| (hasher, status))
+    }
+
+    // Combines two compatible records, keeping whatever either of them knows
+    fn or(self, other: StoredHasher) -> StoredHasher {
+        StoredHasher {
+            seed: self.seed.or(other.seed),
+            tag: self.tag.or(other.tag),
+        }
+    }
+}
+
+impl ParsedDump {
+    // The registers, or `None` if they are all zero and were not decoded
+    pub(crate) fn registers<'a>(&'a self, bytes: &'a [u8]) -> Option<&'a [u8]> {
+        match &self.registers {
+            Registers::Dense(range) => Some(&bytes[range.clone()]),
+            Registers::Decoded(registers) => Some(registers),
+            Registers::Zeros => None,
+        }
+    }
+
+    // Takes the registers out of the dump, copying them only if they still live in the input, and leaving them
+    // unallocated if they are all zero and were not decoded
+    pub(crate) fn into_register_array(self, bytes: &[u8]) -> RegisterArray {
+        match self.registers {
+            Registers::Dense(range) => RegisterArray::new(bytes[range].to_vec()),
+            Registers::Decoded(registers) => RegisterArray::new(registers),
+            Registers::Zeros => RegisterArray::zeroed(pow_two(self.precision) as usize),
+        }
+    }
+
+    // Copies the registers of the dump into `alloc`, leaving them unallocated if they are all zero and were not
+    // decoded
+    #[cfg(feature = "allocator-api")]
+    pub(crate) fn register_array_in<A: Allocator>(&self, bytes: &[u8], alloc: A) -> RegisterArray<A> {
+        match self.registers(bytes) {
+            Some(values) => RegisterArray::from_slice_in(values, alloc),
+            None => RegisterArray::zeroed_in(pow_two(self.precision) as usize, alloc),
+        }
+    }
+}
+
+
+// Whether the embedded checksum (if any) should be verified while parsing
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Checksum {
+    Verify,
+    Skip,
+}
+
+// Parses either a versioned dump or a legacy tail-byte dump
+pub(crate) fn parse_dump(bytes: &[u8], checksum: Checksum) -> Result<ParsedDump, HypeerlogError> {
+    if bytes.starts_with(&MAGIC) {
+        parse_versioned(bytes, checksum)
+    } else {
+        parse_legacy(bytes)
+    }
+}
+
+// Validates the fixed-size header of a versioned dump, returning its precision and flags
+pub(crate) fn parse_header(header: &[u8]) -> Result<(u8, u8), HypeerlogError> {
+    let mut reader = Reader::new(header);
+    parse_header_fields(&mut reader)
+}
+
+fn parse_header_fields(reader: &mut Reader) -> Result<(u8, u8), HypeerlogError> {
+    if reader.get_bytes(MAGIC.len())? != MAGIC { return Err(HypeerlogError::InvalidMagic); }
+
+    let (version, p, flags) = (reader.get_u8()?, reader.get_u8()?, reader.get_u8()?);
+    if version != VERSION { return Err(HypeerlogError::UnsupportedVersion); }
+    if !(4..=25).contains(&p) { return Err(HypeerlogError::InvalidPrecision); }
+    if flags & !KNOWN_FLAGS != 0 { return Err(HypeerlogError::UnsupportedFlags); }
+    if flags & FLAG_COMPACT != 0 && flags & FLAG_PACKED != 0 { return Err(HypeerlogError::UnsupportedFlags); }
+    Ok((p, flags))
+}
+
+// The total size of the optional fields announced by the flags
+pub(crate) fn fields_len(flags: u8) -> usize {
+    [(FLAG_CHECKSUM, 4), (FLAG_SEED, 8), (FLAG_TAG, 4), (FLAG_COMPRESSED, 1), (FLAG_INSERTIONS, 8)].iter()
+        .filter(|(flag, _)| flags & flag != 0)
+        .map(|(_, len)| len)
+        .sum()
+}
+
+// An upper bound on the payload following the header of a valid dump at precision `p`. Compact and packed
+// payloads are never larger than the dense registers, while the codecs can expand incompressible input slightly,
+// by less than this margin
+pub(crate) const fn max_payload_len(p: u8) -> usize {
+    let len = 1usize << p;
+    len + len / 64 + 256
+}
+
+// The exact length of the registers following the optional fields, for the payloads that have one
+pub(crate) fn registers_len(p: u8, flags: u8) -> Option<usize> {
+    let len = pow_two(p) as usize;
+    match flags & (FLAG_COMPACT | FLAG_COMPRESSED | FLAG_PACKED) {
+        0 => Some(len),
+        FLAG_PACKED => Some(packed::packed_len(len)),
+        _ => None,
+    }
+}
+
+fn parse_versioned(bytes: &[u8], checksum: Checksum) -> Result<ParsedDump, HypeerlogError> {
+    let mut reader = Reader::new(bytes);
+    let (p, flags) = parse_header_fields(&mut reader)?;
+
+    let expected_checksum = if flags & FLAG_CHECKSUM != 0 { Some(reader.get_u32()?) } else { None };
+    let seed = if flags & FLAG_SEED != 0 { Some(reader.get_u64()?) } else { None };
+    let tag = if flags & FLAG_TAG != 0 { Some(reader.get_u32()?) } else { None };
+    let codec = if flags & FLAG_COMPRESSED != 0 { Some(reader.get_u8()?) } else { None };
+    let insertions = if flags & FLAG_INSERTIONS != 0 { reader.get_u64()? } else { 0 };
+    let offset = reader.position();
+
+    // Neither encoding is ever larger than the dense registers, which bounds decompression
+    let len = pow_two(p) as usize;
+    let registers = match codec {
+        Some(codec) => {
+            let payload = compress::decompress(codec, &bytes[offset..], len)?;
+            if flags & FLAG_COMPACT != 0 {
+                Registers::Decoded(compact::decode(&payload, len)?)
+            } else if flags & FLAG_PACKED != 0 {
+                Registers::Decoded(packed::decode(&payload, len)?)
+            } else {
+                if payload.len() != len { return Err(HypeerlogError::InvalidLength); }
+                Registers::Decoded(payload)
+            }
+        }
+        None if flags & FLAG_COMPACT != 0 && offset == bytes.len() => Registers::Zeros,
+        None if flags & FLAG_COMPACT != 0 => Registers::Decoded(compact::decode(&bytes[offset..], len)?),
+        None if flags & FLAG_PACKED != 0 => Registers::Decoded(packed::decode(&bytes[offset..], len)?),
+        None => {
+            if bytes.len() - offset != len { return Err(HypeerlogError::InvalidLength); }
+            Registers::Dense(offset..bytes.len())
+        }
+    };
+
+    let parsed = ParsedDump {
+        precision: p,
+        hasher: StoredHasher { seed, tag },
+        insertions,
+        registers,
+    };
+    if let (Some(expected), Checksum::Verify) = (expected_checksum, checksum) {
+        let got = parsed.registers(bytes).map_or_else(|| crc32_zeros(len), crc32);
+        if got != expected { return Err(HypeerlogError::ChecksumMismatch { expected, got }); }
+    }
+    if let Some(registers) = parsed.registers(bytes) {
+        check_ranks(p, registers)?;
+    }
+    Ok(parsed)
+}
+
+fn parse_legacy(bytes: &[u8]) -> Result<ParsedDump, HypeerlogError> {
+    // Anything without the shape of a legacy dump, 2^p registers followed by `p`, is not a dump at all
+    let legacy_shape = |&(&p, registers): &(&u8, &[u8])| (4..=25).contains(&p) && registers.len() == pow_two(p) as usize;
+    let (&p, registers) = bytes.split_last().filter(legacy_shape).ok_or(HypeerlogError::InvalidMagic)?;
+    check_ranks(p, registers)?;
+    Ok(ParsedDump {
+        precision: p,
+        hasher: StoredHasher::default(),
+        insertions: 0,
+        registers: Registers::Dense(0..registers.len()),
+    })
+}
+
+
+// Rejects register values no hash can produce at this precision, which would also be out of
+// range for the estimator's lookup table
+pub(crate) fn check_ranks(precision: u8, registers: &[u8]) -> Result<(), HypeerlogError> {
+    let max = max_rank(precision);
+    if registers.iter().any(|&val| val > max) {
+        return Err(HypeerlogError::InvalidRegisterValue);
+    }
+    Ok(())
+}
+
+
+// Everything in front of the registers in a dump of this release: the header and its optional fields
+pub(crate) struct DumpPrefix {
+    writer: Writer<MAX_PREFIX_LEN>,
+    insertions: u64,
+}
+
+impl DumpPrefix {
+    // The prefix of a dump of registers whose CRC32 is `checksum`
+    pub(crate) fn new(precision: u8, hasher: StoredHasher, insertions: u64, checksum: u32) -> Self {
+        let mut writer = Writer::new();
+
+        let mut flags = FLAG_CHECKSUM;
+        if hasher.seed.is_some() { flags |= FLAG_SEED; }
+        if hasher.tag.is_some() { flags |= FLAG_TAG; }
+        if insertions != 0 { flags |= FLAG_INSERTIONS; }
+
+        writer.put_bytes(&MAGIC);
+        writer.put_u8(VERSION);
+        writer.put_u8(precision);
+        writer.put_u8(flags);
+        writer.put_u32(checksum);
+        if let Some(seed) = hasher.seed {
+            writer.put_u64(seed);
+        }
+        if let Some(tag) = hasher.tag {
+            writer.put_u32(tag);
+        }
+        if insertions != 0 {
+            writer.put_u64(insertions);
+        }
+        DumpPrefix { writer, insertions }
+    }
+
+    // Marks the registers following the prefix as encoded
+    pub(crate) fn set_flag(&mut self, flag: u8) {
+        self.writer.or_byte(HEADER_LEN - 1, flag);
+    }
+
+    // Marks the payload as compressed; the codec goes in front of the insertion count, the only optional field
+    // with a higher flag bit
+    pub(crate) fn with_codec(mut self, codec: u8) -> Self {
+        self.set_flag(FLAG_COMPRESSED);
+        if self.insertions != 0 {
+            self.writer.truncate(self.writer.as_bytes().len() - 8);
+        }
+        self.writer.put_u8(codec);
+        if self.insertions != 0 {
+            self.writer.put_u64(self.insertions);
+        }
+        self
+    }
+
+    pub(crate) fn as_bytes(&self) -> &[u8] {
+        self.writer.as_bytes()
+    }
+}
+
+// The length of the prefix `DumpPrefix::new` builds for a hasher and an insertion count, without computing the checksum
+pub(crate) fn prefix_len(hasher: StoredHasher, insertions: u64) -> usize {
+    HEADER_LEN + 4
+        + if hasher.seed.is_some() { 8 } else { 0 }
+        + if hasher.tag.is_some() { 4 } else { 0 }
+        + if insertions != 0 { 8 } else { 0 }
+}
+
+// Concatenates a prefix and the payload that follows it
+pub(crate) fn assemble(prefix: &DumpPrefix, payload: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(prefix.as_bytes().len() + payload.len());
+    bytes.extend_from_slice(prefix.as_bytes());
+    bytes.extend_from_slice(payload);
+    bytes
+}
+
+
+/// Merges a sequence of dumps (as produced by [`Hypeerlog::dump`](crate::Hypeerlog::dump)) into a single dump
+/// without constructing any intermediate sketch.
+///
+/// The registers are max-merged into one reusable buffer while streaming through the inputs, and the
+/// result uses the current dump format, so it can be stored or passed to [`Hypeerlog::load`](crate::Hypeerlog::load).
+/// Legacy, versioned, compact and packed dumps can be mixed in the input. The insertion counts they record are
+/// summed, saturating at `u64::MAX`, like [`Hypeerlog::merge_with`](crate::Hypeerlog::merge_with) sums them.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - No dumps are provided ([`HypeerlogError::EmptyInput`]).
+/// - A dump cannot be merged, which is reported as [`HypeerlogError::Incompatible`] with its index and the reason:
+///   - it is malformed or corrupted (any of the errors returned by [`Hypeerlog::load`](crate::Hypeerlog::load));
+///   - it has a different precision than the first one ([`HypeerlogError::PrecisionMismatch`]);
+///   - it records a different hasher seed ([`HypeerlogError::SeedMismatch`]) or algorithm
+///     ([`HypeerlogError::HasherMismatch`]) than the ones before it.
+pub fn merge_dumps(dumps: impl IntoIterator<Item = impl AsRef<[u8]>>) -> Result<Vec<u8>, HypeerlogError> {
+    let mut dumps = dumps.into_iter();
+
+    let first = dumps.next().ok_or(HypeerlogError::EmptyInput)?;
+    let first = first.as_ref();
+    let parsed = parse_dump(first, Checksum::Verify).map_err(|err| incompatible_at(0, err))?;
+    let mut hasher = parsed.hasher;
+    let mut insertions = parsed.insertions;
+    let len = pow_two(parsed.precision) as usize;
+    let mut merged = parsed.registers(first).map_or_else(|| vec![0; len], <[u8]>::to_vec);
+
+    // The first dump was consumed before enumerating
+    for (index, dump) in (1..).zip(dumps) {
+        let dump = dump.as_ref();
+        let other = parse_dump(dump, Checksum::Verify).map_err(|err| incompatible_at(index, err))?;
+        if other.precision != parsed.precision {
+            return Err(incompatible_at(index, HypeerlogError::PrecisionMismatch));
+        }
+        other.hasher.check(hasher).map_err(|err| incompatible_at(index, err))?;
+        hasher = hasher.or(other.hasher);
+        insertions = insertions.saturating_add(other.insertions);
+
+        if let Some(registers) = other.registers(dump) {
+            merged.iter_mut()
+                .zip(registers)
+                .for_each(|(a, b)| *a = (*a).max(*b));
+        }
+    }
+
+    Ok(assemble(&DumpPrefix::new(parsed.precision, hasher, insertions, crc32(&merged)), &merged))
+}
+
+
+/// Estimates the cardinality of a dump (as produced by [`Hypeerlog::dump`](crate::Hypeerlog::dump)) straight from its bytes.
+///
+/// The header is validated and the estimate is computed over the serialized registers in place, so no sketch
+/// is constructed. The result is bit-identical to loading the dump and calling
+/// [`Hypeerlog::cardinality`](crate::Hypeerlog::cardinality). Compact, packed and compressed dumps are accepted too,
+/// but have to be decoded into a temporary buffer first.
+///
+/// # Errors
+///
+/// Returns any of the errors of [`Hypeerlog::load`](crate::Hypeerlog::load) for malformed or corrupted dumps.
+/// The hasher recorded in the dump is not checked, as no hashing is involved.
+pub fn cardinality_of_dump(bytes: &[u8]) -> Result<f64, HypeerlogError> {
+    let parsed = parse_dump(bytes, Checksum::Verify)?;
+    Ok(parsed.registers(bytes).map_or(0.0, |registers| estimate_from_registers(parsed.precision, registers)))
+}
@@ -0,0 +1,211 @@
+//! A C API for sketches mergeable with the Rust ones, enabled by the `ffi` feature.
+//!
+//! The functions take and return an opaque [`Hll`], a sketch with the default Murmur3 hasher, and their
+//! declarations ship with the crate in `include/hypeerlog.h`, generated by cbindgen from this module with
+//! `cbindgen --config cbindgen.toml --output include/hypeerlog.h`. Build the shared library with
+//! `cargo rustc --lib --release --features ffi --crate-type cdylib`, or the static one with `--crate-type
+//! staticlib`.
+//!
+//! Items inserted with [`hll_insert_bytes`] are hashed like [`Hypeerlog::insert_bytes`] does, and the dumps of
+//! [`hll_dump`] are the ones of [`Hypeerlog::dump`], so a C service and a Rust aggregator can exchange sketches
+//! in both directions.
+//!
+//! No error unwinds across the boundary: null pointers, incompatible sketches, invalid dumps and panics are
+//! reported by a negative `HLL_ERR_*` code, or by a null pointer for the functions that return one.
+//!
+//! ```c
+//! Hll *hll = hll_new(14, 0);
+//! hll_insert_bytes(hll, (const uint8_t *)"user-1", 6);
+//!
+//! size_t len;
+//! uint8_t *dump = hll_dump(hll, &len);
+//! send(dump, len);
+//! hll_dump_free(dump, len);
+//! hll_free(hll);
+//! ```
+
+use core::ffi::c_int;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use crate::{Hypeerlog, HypeerlogError};
+
+
+
+/// The call succeeded.
+pub const HLL_OK: c_int = 0;
+/// A pointer argument was null.
+pub const HLL_ERR_NULL: c_int = -1;
+/// The sketches have different precisions.
+pub const HLL_ERR_PRECISION_MISMATCH: c_int = -2;
+/// The sketches were built with different hasher seeds.
+pub const HLL_ERR_SEED_MISMATCH: c_int = -3;
+/// The sketches were built with different hash algorithms.
+pub const HLL_ERR_HASHER_MISMATCH: c_int = -4;
+/// The call panicked, which is a bug of this crate.
+pub const HLL_ERR_PANIC: c_int = -5;
+
+
+/// An opaque sketch, created by [`hll_new`] or [`hll_load`] and freed by [`hll_free`].
+#[derive(Debug)]
+pub struct Hll(Hypeerlog);
+
+
+/// Creates an empty sketch with a precision and a seed for the default Murmur3 hasher.
+///
+/// The precision value is silently clamped to the valid range of `4..=25`. Returns null if the call panicked.
+#[unsafe(no_mangle)]
+pub extern "C" fn hll_new(precision: u8, seed: u32) -> *mut Hll {
+    catch_unwind(|| Box::into_raw(Box::new(Hll(Hypeerlog::with_precision_seed(precision, seed))))).unwrap_or(core::ptr::null_mut())
+}
+
+/// Inserts the `len` bytes at `data`, hashed like [`Hypeerlog::insert_bytes`] does.
+///
+/// Returns 1 if the register of the item was raised, 0 if it was not, or a negative `HLL_ERR_*` code. `data` may
+/// be null when `len` is 0.
+///
+/// # Safety
+///
+/// `hll` must be null or a sketch of this API that is not used by another thread during the call, and `data`
+/// must point to `len` readable bytes unless `len` is 0.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn hll_insert_bytes(hll: *mut Hll, data: *const u8, len: usize) -> c_int {
+    // SAFETY: the caller passes a valid sketch or null
+    let Some(hll) = (unsafe { hll.as_mut() }) else {
+        return HLL_ERR_NULL;
+    };
+    let bytes: &[u8] = match (data.is_null(), len) {
+        (_, 0) => &[],
+        (true, _) => return HLL_ERR_NULL,
+        // SAFETY: the caller guarantees that `data` points to `len` readable bytes
+        (false, _) => unsafe { core::slice::from_raw_parts(data, len) },
+    };
+    guard(AssertUnwindSafe(|| hll.0.insert_bytes(bytes) as c_int))
+}
+
+/// Returns the estimated distinct element count of a sketch, or NaN if `hll` is null or the call panicked.
+///
+/// # Safety
+///
+/// `hll` must be null or a sketch of this API that is not modified by another thread during the call.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn hll_cardinality(hll: *const Hll) -> f64 {
+    // SAFETY: the caller passes a valid sketch or null
+    let Some(hll) = (unsafe { hll.as_ref() }) else {
+        return f64::NAN;
+    };
+    catch_unwind(AssertUnwindSafe(|| hll.0.cardinality())).unwrap_or(f64::NAN)
+}
+
+/// Merges the sketch `src` into `dst`, like [`Hypeerlog::merge_with`].
+///
+/// Returns [`HLL_OK`], or a negative `HLL_ERR_*` code, in which case `dst` is not modified. `dst` and `src` may
+/// be the same sketch, which changes nothing; merging a copy of `dst` leaves its registers unchanged too, but adds
+/// its insertion count, recorded by [`hll_dump`], like [`Hypeerlog::merge_with`] does.
+///
+/// # Safety
+///
+/// `dst` and `src` must be null or sketches of this API that are not used by another thread during the call.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn hll_merge(dst: *mut Hll, src: *const Hll) -> c_int {
+    if dst.is_null() || src.is_null() {
+        return HLL_ERR_NULL;
+    }
+    if core::ptr::eq(dst, src) {
+        return HLL_OK;
+    }
+    // SAFETY: the caller passes valid sketches, which are distinct
+    let (dst, src) = unsafe { (&mut *dst, &*src) };
+    guard(AssertUnwindSafe(|| match dst.0.merge_with(&src.0) {
+        Ok(()) => HLL_OK,
+        Err(err) => error_code(err),
+    }))
+}
+
+/// Serializes a sketch like [`Hypeerlog::dump`], and writes the length of the dump to `out_len`.
+///
+/// The dump must be freed by [`hll_dump_free`] with that length. Returns null if an argument is null or the call
+/// panicked.
+///
+/// # Safety
+///
+/// `hll` must be null or a sketch of this API that is not modified by another thread during the call, and
+/// `out_len` must be null or point to a writable `size_t`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn hll_dump(hll: *const Hll, out_len: *mut usize) -> *mut u8 {
+    // SAFETY: the caller passes a valid sketch or null
+    let Some(hll) = (unsafe { hll.as_ref() }) else {
+        return core::ptr::null_mut();
+    };
+    if out_len.is_null() {
+        return core::ptr::null_mut();
+    }
+    let Ok(dump) = catch_unwind(AssertUnwindSafe(|| hll.0.dump().into_boxed_slice())) else {
+        return core::ptr::null_mut();
+    };
+    // SAFETY: the caller passes a writable length
+    unsafe { out_len.write(dump.len()) };
+    Box::into_raw(dump).cast()
+}
+
+/// Frees a dump returned by [`hll_dump`], given its length. Does nothing if `dump` is null.
+///
+/// # Safety
+///
+/// `dump` must be null or a dump returned by [`hll_dump`] with the length `len`, and not freed before.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn hll_dump_free(dump: *mut u8, len: usize) {
+    if !dump.is_null() {
+        // SAFETY: the caller passes a boxed slice of `len` bytes leaked by `hll_dump`
+        drop(unsafe { Box::from_raw(core::ptr::slice_from_raw_parts_mut(dump, len)) });
+    }
+}
+
+/// Reads a sketch from the `len` bytes at `bytes` like [`Hypeerlog::from_bytes`], with the hasher seed of the dump.
+///
+/// Returns null if `bytes` is null, if the bytes are not a valid dump of a sketch with the default hasher, or if
+/// the call panicked.
+///
+/// # Safety
+///
+/// `bytes` must be null or point to `len` readable bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn hll_load(bytes: *const u8, len: usize) -> *mut Hll {
+    if bytes.is_null() {
+        return core::ptr::null_mut();
+    }
+    // SAFETY: the caller guarantees that `bytes` points to `len` readable bytes
+    let bytes = unsafe { core::slice::from_raw_parts(bytes, len) };
+    match catch_unwind(|| Hypeerlog::from_bytes(bytes)) {
+        Ok(Ok(hll)) => Box::into_raw(Box::new(Hll(hll))),
+        _ => core::ptr::null_mut(),
+    }
+}
+
+/// Frees a sketch created by [`hll_new`] or [`hll_load`]. Does nothing if `hll` is null.
+///
+/// # Safety
+///
+/// `hll` must be null or a sketch of this API that is not freed before and not used after the call.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn hll_free(hll: *mut Hll) {
+    if !hll.is_null() {
+        // SAFETY: the caller passes a sketch boxed by `hll_new` or `hll_load`
+        drop(unsafe { Box::from_raw(hll) });
+    }
+}
+
+
+// Runs `f`, reporting a panic by its code
+fn guard(f: impl FnOnce() -> c_int + core::panic::UnwindSafe) -> c_int {
+    catch_unwind(f).unwrap_or(HLL_ERR_PANIC)
+}
+
+// The code of a merge error, always called under `guard`
+fn error_code(err: HypeerlogError) -> c_int {
+    match err {
+        HypeerlogError::PrecisionMismatch => HLL_ERR_PRECISION_MISMATCH,
+        HypeerlogError::SeedMismatch => HLL_ERR_SEED_MISMATCH,
+        HypeerlogError::HasherMismatch => HLL_ERR_HASHER_MISMATCH,
+        err => unreachable!("merges only fail on incompatible sketches, not with {:?}", err),
+    }
+}
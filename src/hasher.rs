@@ -0,0 +1,122 @@
+use core::hash::{BuildHasher, BuildHasherDefault, Hasher};
+
+use crate::murmur::{murmur3_32, murmur3_u64s, Murmur3BuildHasher};
+
+
+
+/// Identifies how a [`BuildHasher`] is configured, so that sketches built with it can be persisted
+/// and checked for compatibility.
+///
+/// Every hasher used with [`Hypeerlog`](crate::Hypeerlog) implements this trait. All methods have
+/// defaults, so `impl HasherId for MyBuildHasher {}` is enough for hashers without a persistable seed.
+pub trait HasherId: BuildHasher {
+    /// Returns the seed of this hasher, if it has one that fully determines its output.
+    ///
+    /// The seed is recorded in dumps so the same hasher can be restored on load, and a dump whose
+    /// stored seed differs from the hasher it is loaded with is rejected.
+    fn hasher_seed(&self) -> Option<u64> {
+        None
+    }
+
+    /// Returns a hasher of the same kind configured with `seed`, or `None` if this hasher has no seed
+    /// or `seed` is out of its range.
+    ///
+    /// Used to restore hashers that are created with a default configuration, such as in deserialization.
+    fn reseeded(&self, seed: u64) -> Option<Self>
+    where
+        Self: Sized,
+    {
+        None
+    }
+
+    /// Returns a stable identifier of the hash algorithm, or `None` if it is unknown.
+    ///
+    /// The tag is recorded in dumps, and loading or merging a dump whose tag differs from the tag of
+    /// the hasher it is combined with fails with [`HypeerlogError::HasherMismatch`](crate::HypeerlogError::HasherMismatch).
+    /// Tags are conventionally four ASCII characters packed in little-endian order, e.g.
+    /// `u32::from_le_bytes(*b"MUR3")` for the built-in Murmur3 hasher.
+    fn hasher_tag(&self) -> Option<u32> {
+        None
+    }
+
+    /// Returns the width of the hashes in bits, 64 unless the hasher returns narrower hashes zero-extended to a
+    /// `u64`.
+    ///
+    /// Used by [`Hypeerlog::is_reliable`](crate::Hypeerlog::is_reliable), as from about $2^{bits} / 30$ distinct
+    /// elements the hashes collide too often for the estimates to be trusted.
+    fn hash_bits(&self) -> u32 {
+        64
+    }
+
+    /// Hashes exactly `bytes`, with the same result as writing them to a fresh hasher in one
+    /// [`Hasher::write`] call and calling [`Hasher::finish`].
+    ///
+    /// Used by [`Hypeerlog::insert_bytes`](crate::Hypeerlog::insert_bytes). Hashers can override it with a one-shot
+    /// implementation that skips the incremental state, as long as the result stays the same.
+    fn hash_bytes(&self, bytes: &[u8]) -> u64 {
+        let mut hasher = self.build_hasher();
+        hasher.write(bytes);
+        hasher.finish()
+    }
+
+    /// Hashes each of `keys` into the same position of `hashes`, which has the same length, with the same
+    /// results as [`BuildHasher::hash_one`] of each key.
+    ///
+    /// Used by [`Hypeerlog::insert_many_u64`](crate::Hypeerlog::insert_many_u64). Hashers can override it to hash
+    /// several keys at once, as long as the results stay the same.
+    fn hash_u64s(&self, keys: &[u64], hashes: &mut [u64]) {
+        for (hash, &key) in hashes.iter_mut().zip(keys) {
+            let mut hasher = self.build_hasher();
+            hasher.write_u64(key);
+            *hash = hasher.finish();
+        }
+    }
+}
+
+
+/// The [`HasherId::hasher_tag`] of the built-in Murmur3 hasher.
+pub const MURMUR3_TAG: u32 = u32::from_le_bytes(*b"MUR3");
+
+
+/// Whether the hasher a dump was loaded with could be confirmed to be the one that produced it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HasherStatus {
+    /// The dump records a hasher tag, and it matches the tag of the supplied hasher.
+    Verified,
+    /// The dump records no hasher tag (e.g. a legacy dump), or the supplied hasher does not declare one,
+    /// so compatibility could not be checked.
+    Unverified,
+}
+
+
+impl HasherId for Murmur3BuildHasher {
+    fn hasher_seed(&self) -> Option<u64> {
+        Some(self.seed() as u64)
+    }
+
+    fn reseeded(&self, seed: u64) -> Option<Self> {
+        u32::try_from(seed).ok().map(Murmur3BuildHasher::new)
+    }
+
+    fn hasher_tag(&self) -> Option<u32> {
+        Some(MURMUR3_TAG)
+    }
+
+    fn hash_bits(&self) -> u32 {
+        32
+    }
+
+    fn hash_bytes(&self, bytes: &[u8]) -> u64 {
+        murmur3_32(bytes, self.seed()) as u64
+    }
+
+    fn hash_u64s(&self, keys: &[u64], hashes: &mut [u64]) {
+        murmur3_u64s(keys, self.seed(), hashes);
+    }
+}
+
+impl<H: Hasher + Default> HasherId for BuildHasherDefault<H> {}
+
+// Randomly keyed per process, so there is no seed that could be persisted
+#[cfg(not(feature = "no_std"))]
+impl HasherId for std::collections::hash_map::RandomState {}
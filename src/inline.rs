@@ -0,0 +1,276 @@
+use core::fmt::{self, Debug};
+use core::hash::{BuildHasher, Hash};
+
+use crate::{check_hashers, HasherId, Hypeerlog, HypeerlogError, Murmur3BuildHasher};
+use crate::registers::RegisterArray;
+use crate::utils::{estimate_from_registers, get_bucket, longest_run, rel_error_from_p};
+
+
+
+/// The precision `P` of a [`ConstHypeerlog`], as a type.
+///
+/// Stable Rust cannot size an array by an expression of a const parameter (`[u8; 1 << P]`), so the
+/// [`ConstPrecision`] implementations of this type give the register array of each supported precision instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Precision<const P: u8>;
+
+/// The precisions a [`ConstHypeerlog`] can have, `4..=16`, and the inline array of `2^P` registers of each.
+///
+/// Above 16 the registers (64 KiB) are too large to be moved around on the stack comfortably, so those
+/// precisions are only available with the heap-allocated [`Hypeerlog`].
+pub trait ConstPrecision {
+    /// The `[u8; 1 << P]` array of registers.
+    type Registers: AsRef<[u8]> + AsMut<[u8]> + Clone + Debug + PartialEq + Eq;
+
+    /// The registers of an empty sketch.
+    const ZEROED: Self::Registers;
+}
+
+macro_rules! const_precisions {
+    ($($p:literal),*) => {
+        $(
+            impl ConstPrecision for Precision<$p> {
+                type Registers = [u8; 1 << $p];
+
+                const ZEROED: Self::Registers = [0; 1 << $p];
+            }
+        )*
+    };
+}
+
+const_precisions!(4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16);
+
+
+/// A HyperLogLog sketch with a compile-time precision `P`, storing its $2^P$ registers inline.
+///
+/// It never allocates, and takes exactly the size of its registers and its hasher, so it can be embedded in
+/// other structs (e.g. one per connection) or used in `static`s, since [`ConstHypeerlog::new`] is a `const fn`.
+/// It inserts, merges and estimates exactly like a [`Hypeerlog`] of precision `P`: the same elements give the
+/// same registers and bit-identical estimates. Unlike [`Hypeerlog::cardinality`], [`ConstHypeerlog::cardinality`]
+/// scans the registers, as maintaining their aggregates would take more space.
+///
+/// Sketches of different precisions are different types, so merging them does not compile. Conversions to and
+/// from [`Hypeerlog`] copy the registers, and the ones from [`Hypeerlog`] check its precision.
+///
+/// ```
+/// use hypeerlog::{ConstHypeerlog, Hypeerlog};
+///
+/// static EMPTY: ConstHypeerlog<12> = ConstHypeerlog::new();
+///
+/// let mut hll = ConstHypeerlog::<12>::new();
+/// hll.insert_many(&[1, 2, 3, 2, 1]);
+/// assert_eq!(hll.cardinality().round(), 3.0);
+/// assert_eq!(core::mem::size_of_val(&hll), 4096 + 4);
+///
+/// let dynamic = Hypeerlog::from(hll);
+/// assert_eq!(dynamic.precision(), 12);
+/// assert_eq!(ConstHypeerlog::<12>::try_from(dynamic).unwrap().cardinality().round(), 3.0);
+/// ```
+#[derive(Clone)]
+pub struct ConstHypeerlog<const P: u8, S = Murmur3BuildHasher>
+where
+    Precision<P>: ConstPrecision,
+    S: BuildHasher + HasherId,
+{
+    registers: <Precision<P> as ConstPrecision>::Registers,
+    hasher: S,
+}
+
+
+impl<const P: u8, S> ConstHypeerlog<P, S>
+where
+    Precision<P>: ConstPrecision,
+    S: BuildHasher + HasherId,
+{
+    /// Creates an empty sketch using a custom hasher builder.
+    pub const fn with_hasher(hasher_builder: S) -> Self {
+        ConstHypeerlog {
+            registers: <Precision<P> as ConstPrecision>::ZEROED,
+            hasher: hasher_builder,
+        }
+    }
+
+    /// Returns the number of registers of the sketch, $2^P$.
+    pub const fn len(&self) -> usize {
+        1 << P
+    }
+
+    /// Returns the precision `P` of the sketch.
+    pub const fn precision(&self) -> u8 {
+        P
+    }
+
+    /// Returns the expected standard relative error of the precision.
+    pub fn relative_error(&self) -> f64 {
+        rel_error_from_p(P as u32)
+    }
+
+    /// Inserts a single hashable item into the sketch, see [`Hypeerlog::insert`].
+    pub fn insert<H: Hash>(&mut self, data: H) {
+        self.insert_hashed(self.hasher.hash_one(&data));
+    }
+
+    /// Inserts an item by a hash computed outside of the sketch, see [`Hypeerlog::insert_hashed`].
+    pub fn insert_hashed(&mut self, hash: u64) {
+        let register = &mut self.registers.as_mut()[get_bucket(P, hash)];
+        *register = (*register).max(longest_run(P, hash));
+    }
+
+    /// Inserts exactly the given bytes, fed to the hasher as they are, see [`Hypeerlog::insert_bytes`].
+    pub fn insert_bytes(&mut self, bytes: &[u8]) {
+        self.insert_hashed(self.hasher.hash_bytes(bytes));
+    }
+
+    /// Inserts the UTF-8 bytes of a string as they are, see [`Hypeerlog::insert_str`].
+    pub fn insert_str(&mut self, s: &str) {
+        self.insert_bytes(s.as_bytes());
+    }
+
+    /// Inserts a slice of items into the sketch.
+    pub fn insert_many<H: Hash>(&mut self, data: &[H]) {
+        for elem in data {
+            self.insert(elem);
+        }
+    }
+
+    /// Returns `true` if no elements have been observed by this sketch yet.
+    pub fn is_empty(&self) -> bool {
+        self.registers.as_ref().iter().all(|&val| val == 0)
+    }
+
+    /// Resets all registers back to zero.
+    pub fn clear(&mut self) {
+        self.registers.as_mut().fill(0);
+    }
+
+    /// Returns the estimated distinct element count (cardinality) observed by this sketch, bit-identical to
+    /// [`Hypeerlog::cardinality`] of the same registers.
+    pub fn cardinality(&self) -> f64 {
+        estimate_from_registers(P, self.registers.as_ref())
+    }
+
+    /// Merges another sketch of the same precision into this one, consuming both and returning the union.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`ConstHypeerlog::merge_with`].
+    pub fn merge(mut self, other: Self) -> Result<Self, HypeerlogError> {
+        self.merge_with(&other)?;
+        Ok(self)
+    }
+
+    /// Merges a borrowed sketch of the same precision into this one in place.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`HypeerlogError::SeedMismatch`] or [`HypeerlogError::HasherMismatch`] if the two sketches hash
+    /// differently. `self` is not modified in that case.
+    pub fn merge_with(&mut self, other: &Self) -> Result<(), HypeerlogError> {
+        check_hashers(&self.hasher, &other.hasher)?;
+        self.registers.as_mut().iter_mut()
+            .zip(other.registers.as_ref())
+            .for_each(|(a, &b)| *a = (*a).max(b));
+        Ok(())
+    }
+}
+
+
+impl<const P: u8> ConstHypeerlog<P>
+where
+    Precision<P>: ConstPrecision,
+{
+    /// Creates an empty sketch using the default `Murmur3BuildHasher`.
+    pub const fn new() -> Self {
+        Self::with_seed(0)
+    }
+
+    /// Creates an empty sketch with a custom seed for the default `Murmur3BuildHasher`.
+    pub const fn with_seed(seed: u32) -> Self {
+        Self::with_hasher(Murmur3BuildHasher::new(seed))
+    }
+}
+
+impl<const P: u8> Default for ConstHypeerlog<P>
+where
+    Precision<P>: ConstPrecision,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Two sketches are equal when they have the same registers, whatever their hasher builders, like [`Hypeerlog`]s;
+/// their precision is part of their type. The hasher does not need to be `PartialEq`.
+impl<const P: u8, S> PartialEq for ConstHypeerlog<P, S>
+where
+    Precision<P>: ConstPrecision,
+    S: BuildHasher + HasherId,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.registers == other.registers
+    }
+}
+
+impl<const P: u8, S> Eq for ConstHypeerlog<P, S>
+where
+    Precision<P>: ConstPrecision,
+    S: BuildHasher + HasherId,
+{
+}
+
+// Summarized like the `Debug` of `Hypeerlog`: only the alternate form lists the registers, and the hasher is
+// identified by its seed and tag
+impl<const P: u8, S> Debug for ConstHypeerlog<P, S>
+where
+    Precision<P>: ConstPrecision,
+    S: BuildHasher + HasherId,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let alternate = f.alternate();
+        let mut debug = f.debug_struct("ConstHypeerlog");
+        debug.field("precision", &P)
+            .field("hasher_seed", &self.hasher.hasher_seed())
+            .field("hasher_tag", &self.hasher.hasher_tag())
+            .field("estimate", &self.cardinality());
+        if alternate {
+            debug.field("registers", &self.registers);
+        }
+        debug.finish()
+    }
+}
+
+
+impl<const P: u8, S> From<ConstHypeerlog<P, S>> for Hypeerlog<S>
+where
+    Precision<P>: ConstPrecision,
+    S: BuildHasher + HasherId,
+{
+    fn from(hll: ConstHypeerlog<P, S>) -> Self {
+        Hypeerlog {
+            registers: RegisterArray::new(hll.registers.as_ref().to_vec()),
+            insertions: 0,
+            hasher: hll.hasher,
+            precision: P,
+        }
+    }
+}
+
+// Fails with `HypeerlogError::PrecisionMismatch` if the sketch does not have precision `P`
+impl<const P: u8, S> TryFrom<Hypeerlog<S>> for ConstHypeerlog<P, S>
+where
+    Precision<P>: ConstPrecision,
+    S: BuildHasher + HasherId,
+{
+    type Error = HypeerlogError;
+
+    fn try_from(hll: Hypeerlog<S>) -> Result<Self, Self::Error> {
+        if hll.precision != P {
+            return Err(HypeerlogError::PrecisionMismatch);
+        }
+        let mut registers = <Precision<P> as ConstPrecision>::ZEROED;
+        if let Some(values) = hll.registers.values() {
+            registers.as_mut().copy_from_slice(values);
+        }
+        Ok(ConstHypeerlog { registers, hasher: hll.hasher })
+    }
+}
@@ -1,15 +1,6 @@
 #![cfg_attr(feature = "no_std", no_std)]
-
-
-#![allow(unused)]
-#![deny(
-    missing_docs,
-    clippy::missing_safety_doc,
-    clippy::undocumented_unsafe_blocks
-)]
-
-
-#![cfg_attr(feature = "no_std", no_std)]
+#![cfg_attr(feature = "nightly", feature(hasher_prefixfree_extras))]
+#![cfg_attr(feature = "allocator-api", feature(allocator_api))]
 
 #![allow(unused)]
 #![deny(
@@ -54,6 +45,8 @@
 //! assert_eq!(hll.cardinality().floor(), 7.0); 
 //! ```
 //!
+//! For a one-off estimate, [`count_distinct`] builds the sketch, inserts the items and estimates them in one call.
+//!
 //! ## Distributed Workloads & Merging
 //!
 //! HyperLogLog sketches are perfectly additive. You can distribute massive datasets across multiple independent 
@@ -75,6 +68,62 @@
 //! assert_eq!(merged.cardinality().floor(), 7.0);
 //! ```
 //!
+//! Within one process, `ShardedHypeerlog` lets many threads insert into one logical sketch through a shared
+//! reference: each thread mostly writes to its own shard of registers, without locking, and the shards are merged
+//! when the sketch is estimated. [`AtomicHypeerlog`] is a single sketch with atomic registers, for sharing in an
+//! `Arc` across tasks that do not keep to one thread. `SharedHypeerlog` puts a sketch behind a read-write lock, for
+//! estimates read much more often than the sketch is written.
+//!
+//! Across a topology of many shards, e.g. partitions of hosts of regions, `MergeTree` keeps the sketches at paths
+//! and caches the union of every inner node, so that replacing a few leaves only recomputes the nodes above them.
+//!
+//! [`SlidingHypeerlog`] answers questions over a sliding time window, like the distinct users of the last 24
+//! hours: each insert carries a timestamp, and the sketch estimates the items inserted since any cutoff.
+//! [`BucketedHypeerlog`] answers them in whole buckets, e.g. hours, from a ring of plain sketches.
+//! [`TimestampedHypeerlog`] records when each register was last updated and expires the registers untouched
+//! since a cutoff, at 8 more bytes per register and with an estimate biased high once the window outgrows the
+//! registers. [`DecayingHypeerlog`] weighs the distinct items by their age instead, halving the weight of an item
+//! with each half-life since it was last seen.
+//!
+//! ## Keeping Many Sketches in Memory
+//!
+//! Every register fits in 6 bits, so [`PackedHypeerlog`] stores four of them in three bytes: a p=14 sketch takes
+//! 12 KiB instead of 16 KiB. It gives the same estimates as [`Hypeerlog`] at a somewhat higher insert cost, merges
+//! with it in either direction, and its dumps load as either type.
+//!
+//! ```rust
+//! use hypeerlog::{Hypeerlog, PackedHypeerlog};
+//!
+//! let mut packed = PackedHypeerlog::new();
+//! packed.insert_many(&[1, 2, 3, 2, 1]);
+//!
+//! let mut hll = Hypeerlog::load(packed.dump()).unwrap();
+//! hll.merge_with_packed(&packed).unwrap();
+//! assert_eq!(hll.cardinality(), packed.cardinality());
+//! ```
+//!
+//! `HllMap` keeps a sketch per key, e.g. per country, all with the precision and hasher of the map, so that the
+//! sketches of two maps merge key by key, and dumps the whole map at once.
+//! [`MultiHll`] is a fixed array of sketches addressed by index, e.g. one per hour of the day, in a single
+//! allocation, and estimates the union of any range of them.
+//!
+//! To embed sketches in other structs without a heap allocation each, [`ConstHypeerlog<P>`](ConstHypeerlog) fixes
+//! the precision at compile time (up to 16) and stores its registers inline; it can also be built in a `static`.
+//!
+//! ## Choosing a Hasher
+//!
+//! The built-in hashers record a tag and their seed in dumps, so sketches built with different hashers or seeds refuse to
+//! load or merge into each other. The default Murmur3 is a good fit everywhere; the optional ones are faster on
+//! particular workloads and platforms:
+//!
+//! | Hasher | Feature | Recommended for |
+//! |--------|---------|-----------------|
+//! | `Murmur3BuildHasher` | default | Any platform and any element; pinned to the reference MurmurHash3 test vectors |
+//! | `Xxh3BuildHasher` | `xxhash` | Long elements (URLs, documents) on x86_64 and 64-bit ARM, with `insert_str`/`insert_bytes` |
+//! | `AHashBuilder` | `ahash` | Integer elements on x86_64 with AES-NI enabled, for sketches that do not outlive the build |
+//! | `WyHashBuilder` | `wyhash` | Embedded and 32-bit cores without AES or SIMD (e.g. Cortex-A7) |
+//! | `SipBuildHasher` | `siphash` | Untrusted input: a secret key keeps attackers from steering elements into chosen registers |
+//!
 //! ## `no_std` Support
 //! 
 //! This crate features a highly constrained, lightweight memory profile, making it a perfect fit for resource-constrained or bare-metal environments. 
@@ -88,21 +137,200 @@
 //! 
 //! All core estimation and merging features remain fully available in `no_std` mode via safe heap allocations handled contextually by the `alloc` crate.
 //!
+//! ## Python Bindings
+//!
+//! The `python/` directory of the repository holds a PyO3 extension module, built with maturin, that reads and
+//! writes the dumps of this crate, so sketches built by a Rust pipeline can be opened, merged and estimated in
+//! notebooks. Strings and bytes are inserted through [`Hypeerlog::insert_bytes`], and integers as the
+//! little-endian bytes of a `u64` or `i64` like [`Hypeerlog::insert`] does, so sketches of the same items built
+//! in Python and Rust are identical.
+//!
+//! ```python
+//! from hypeerlog import Hypeerlog
+//!
+//! users = Hypeerlog.loads(open("users-2024-01-01.hll", "rb").read())
+//! users.update(["user-1", "user-2", 42])
+//! print(users.cardinality())
+//! ```
+//!
+//! ## Optional Features
+//!
+//! - **`rayon`**: Parallel tree-reduction merging of large batches of sketches (`Hypeerlog::par_merge_many`, `Hypeerlog::par_merge_all`), parallel insertion (`Hypeerlog::par_insert_many`), collecting parallel iterators into sketches (`FromParallelIterator`, `ParallelExtend`), and one-shot estimates of parallel iterators (`count_distinct_par`, `EstimateDistinct`).
+//! - **`async`**: Inserting the items of a `futures_core::Stream` without blocking the executor (`Hypeerlog::insert_stream`, `Hypeerlog::from_stream_with_precision`). Works with any executor and with `no_std`.
+//! - **`zstd`**: zstd-compressed dumps (`Hypeerlog::dump_zstd`), decompressed transparently by `Hypeerlog::load`. Requires `std`.
+//! - **`flate2`**: gzip-compressed dumps (`Hypeerlog::dump_gzip`), decompressed transparently by `Hypeerlog::load`. Requires `std`.
+//! - **`serde`**: `Serialize`/`Deserialize` for `Hypeerlog`, validated like `Hypeerlog::load`, plus `WithHasher` to deserialize with a given hasher, and `Serialize` for `SketchStats`.
+//! - **`rkyv`**: zero-copy archiving of `Hypeerlog`, with cardinality estimates computed directly on `ArchivedHypeerlog`.
+//! - **`arbitrary`**: `arbitrary::Arbitrary` for `Hypeerlog`, generating sketches that always pass validation. Requires `std`.
+//! - **`proptest`**: the `hypeerlog::proptest::sketch` strategy, for property tests over valid sketches. Requires `std`.
+//! - **`redis-compat`**: import of the dense and sparse Redis HyperLogLog encodings and export to the dense one (`Hypeerlog::from_redis`, `Hypeerlog::to_redis`). Imported sketches use Redis's hash function and only merge with Redis-hashed data.
+//! - **`datasketches-compat`**: import and export of Apache DataSketches `HLL_8` sketch images (`Hypeerlog::from_datasketches`, `Hypeerlog::to_datasketches`). Imported sketches use the DataSketches hash function and only merge with DataSketches-hashed data.
+//! - **`zetasketch-compat`**: import and export of the dense ZetaSketch HLL++ protos used by BigQuery's `HLL_COUNT` functions (`Hypeerlog::from_zetasketch`, `Hypeerlog::to_zetasketch`). The Fingerprint2011 hash is not implemented, so imported sketches only merge with each other.
+//! - **`pg-hll-compat`**: import of every postgresql-hll (citusdata) storage representation and export to the FULL one (`Hypeerlog::from_pg_hll`, `Hypeerlog::to_pg_hll`), for the default register width of 5. Imported sketches use the extension's hash function and only merge with data hashed the same way.
+//! - **`prost`**: the canonical `hypeerlog.v1.Sketch` protobuf message (`proto/hypeerlog.proto`) as `hypeerlog::proto::Sketch`, with `Hypeerlog::to_proto` and `Hypeerlog::from_proto`.
+//! - **`mmap`**: `Hypeerlog::open_mmap`, which memory-maps a dump file read-only as a `MappedHypeerlog` and estimates from it without copying the registers. Requires `std`.
+//! - **`ffi`**: a C API over sketches with the default hasher (`hll_new`, `hll_insert_bytes`, `hll_merge`, `hll_dump`, `hll_load`, ...) in `hypeerlog::ffi`, declared by the cbindgen-generated `include/hypeerlog.h`. Errors and panics are returned as codes and never unwind into C. Build the library with `cargo rustc --lib --release --features ffi --crate-type cdylib`. Requires `std`.
+//! - **`wasm`**: `JsHll`, wasm-bindgen bindings for JavaScript on `wasm32-unknown-unknown` (browsers, Cloudflare Workers), with `insertStr`/`insertBytes` on the canonical byte-hashing path, `cardinality`, `merge`, and dumps to and from `Uint8Array` that load and merge natively. The crate has no other dependency on that target. Requires `std`.
+//! - **`xxhash`**: `Xxh3BuildHasher`, an XXH3 hasher for `Hypeerlog::with_hasher` that is much faster than the default Murmur3 on long elements. Its sketches have their own hasher tag and do not merge with Murmur3 sketches.
+//! - **`ahash`**: `AHashBuilder`, an aHash hasher with keys derived from a reproducible seed, the fastest option for integer elements on CPUs with AES-NI. Its hashes can change between `ahash` releases, so it suits sketches that do not outlive the build.
+//! - **`wyhash`**: `WyHashBuilder`, a wyhash hasher for cores without AES or wide SIMD instructions, such as 32-bit ARM. Its sketches have their own hasher tag.
+//! - **`siphash`**: `SipBuildHasher`, SipHash-1-3 with a secret 128-bit key, for sketches fed by untrusted input. Dumps record a fingerprint of the key, never the key itself.
+//! - **`simd`**: AVX2 hashing of eight keys at a time in `Hypeerlog::insert_many_u64` with the default hasher, and AVX2 scans of 32 registers at a time when estimating from dumps, views and archives or loading them, on `x86_64`, detected at runtime. The registers and estimates are the same as without it.
+//! - **`nightly`**: requires a nightly toolchain; the default hasher then implements the unstable `Hasher::write_str` and `Hasher::write_length_prefix` with the same frozen encoding that the stable defaults produce, so string hashing no longer depends on `core`.
+//! - **`allocator-api`**: requires a nightly toolchain; sketches whose registers are allocated by a given `core::alloc::Allocator`, e.g. a per-request arena (`Hypeerlog::new_in`, `Hypeerlog::with_hasher_precision_in`, `Hypeerlog::from_bytes_in`), with `Hypeerlog::clone_in` and `Hypeerlog::dump_in` to copy and serialize into an allocator. Merges allocate from the allocator of the target sketch. Cannot be combined with `rkyv`.
+//!
 
 
 
 
+#[cfg(all(feature = "no_std", feature = "zstd"))]
+compile_error!("the `zstd` feature requires std and cannot be combined with `no_std`");
+#[cfg(all(feature = "no_std", feature = "flate2"))]
+compile_error!("the `flate2` feature requires std and cannot be combined with `no_std`");
+#[cfg(all(feature = "no_std", feature = "arbitrary"))]
+compile_error!("the `arbitrary` feature requires std and cannot be combined with `no_std`");
+#[cfg(all(feature = "no_std", feature = "proptest"))]
+compile_error!("the `proptest` feature requires std and cannot be combined with `no_std`");
+#[cfg(all(feature = "no_std", feature = "mmap"))]
+compile_error!("the `mmap` feature requires std and cannot be combined with `no_std`");
+#[cfg(all(feature = "no_std", feature = "ffi"))]
+compile_error!("the `ffi` feature requires std and cannot be combined with `no_std`");
+#[cfg(all(feature = "no_std", feature = "wasm"))]
+compile_error!("the `wasm` feature requires std and cannot be combined with `no_std`");
+#[cfg(all(feature = "allocator-api", feature = "rkyv"))]
+compile_error!("the `rkyv` feature only archives sketches of the global allocator and cannot be combined with `allocator-api`");
+
 use core::hash::Hash;
 use core::hash::{BuildHasher, Hasher};
 use core::fmt::Debug;
 
 mod murmur;
 mod utils;
+mod registers;
+mod dump;
+mod codec;
+mod merge;
+mod delta;
+mod stats;
+mod hasher;
+mod compact;
+mod packed;
+mod inline;
+mod atomic;
+mod sliding;
+mod bucketed;
+mod timestamped;
+mod multi;
+mod decaying;
+mod compress;
+mod view;
+mod base64;
+#[cfg(feature = "serde")]
+mod serde_impl;
+#[cfg(feature = "rkyv")]
+mod archive;
+#[cfg(feature = "arbitrary")]
+mod arbitrary_impl;
+#[cfg(feature = "redis-compat")]
+mod redis;
+#[cfg(feature = "datasketches-compat")]
+mod datasketches;
+#[cfg(feature = "zetasketch-compat")]
+mod zetasketch;
+#[cfg(feature = "pg-hll-compat")]
+mod pg_hll;
+#[cfg(feature = "proptest")]
+pub mod proptest;
+#[cfg(feature = "prost")]
+pub mod proto;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "wasm")]
+mod wasm;
+#[cfg(feature = "mmap")]
+mod mmap;
+#[cfg(feature = "xxhash")]
+mod xxhash;
+#[cfg(feature = "ahash")]
+mod ahash_impl;
+#[cfg(feature = "wyhash")]
+mod wyhash_impl;
+#[cfg(feature = "siphash")]
+mod siphash;
+#[cfg(all(feature = "simd", target_arch = "x86_64"))]
+mod simd;
+#[cfg(not(feature = "no_std"))]
+mod stream;
+#[cfg(not(feature = "no_std"))]
+mod sketch_archive;
+#[cfg(not(feature = "no_std"))]
+mod sharded;
+#[cfg(not(feature = "no_std"))]
+mod shared;
+#[cfg(not(feature = "no_std"))]
+mod map;
+#[cfg(not(feature = "no_std"))]
+mod merge_tree;
+#[cfg(feature = "rayon")]
+mod parallel;
+#[cfg(feature = "async")]
+mod async_stream;
+#[cfg(feature = "allocator-api")]
+mod allocator;
 use utils::*;
-use murmur::{Murmur3BuildHasher};
+use registers::RegisterArray;
 
 
+pub use murmur::{Murmur3BuildHasher, Murmur3Hasher};
 pub use utils::{rel_error_from_p, p_from_rel_error};
+pub use dump::{cardinality_of_dump, merge_dumps};
+pub use merge::{Mergeable, MergeReport};
+pub use stats::SketchStats;
+pub use delta::RegisterSnapshot;
+pub use view::HypeerlogRef;
+pub use packed::PackedHypeerlog;
+pub use inline::{ConstHypeerlog, ConstPrecision, Precision};
+pub use atomic::AtomicHypeerlog;
+pub use sliding::SlidingHypeerlog;
+pub use bucketed::BucketedHypeerlog;
+pub use timestamped::TimestampedHypeerlog;
+pub use multi::MultiHll;
+pub use decaying::DecayingHypeerlog;
+#[cfg(feature = "serde")]
+pub use serde_impl::WithHasher;
+pub use hasher::{HasherId, HasherStatus, MURMUR3_TAG};
+#[cfg(not(feature = "no_std"))]
+pub use sketch_archive::SketchArchive;
+#[cfg(not(feature = "no_std"))]
+pub use sharded::ShardedHypeerlog;
+#[cfg(not(feature = "no_std"))]
+pub use shared::SharedHypeerlog;
+#[cfg(not(feature = "no_std"))]
+pub use map::{HllMap, HllMapKey};
+#[cfg(not(feature = "no_std"))]
+pub use merge_tree::MergeTree;
+#[cfg(feature = "rayon")]
+pub use parallel::{count_distinct_par, EstimateDistinct};
+#[cfg(feature = "redis-compat")]
+pub use redis::{RedisHasher, REDIS_TAG};
+#[cfg(feature = "datasketches-compat")]
+pub use datasketches::{DataSketchesHasher, DATASKETCHES_TAG};
+#[cfg(feature = "zetasketch-compat")]
+pub use zetasketch::{ZetaSketchHasher, ZETASKETCH_TAG};
+#[cfg(feature = "pg-hll-compat")]
+pub use pg_hll::{PgHllHasher, PG_HLL_TAG};
+#[cfg(feature = "mmap")]
+pub use mmap::MappedHypeerlog;
+#[cfg(feature = "wasm")]
+pub use wasm::JsHll;
+#[cfg(feature = "xxhash")]
+pub use xxhash::{Xxh3BuildHasher, Xxh3Hasher, XXH3_TAG};
+#[cfg(feature = "ahash")]
+pub use ahash_impl::{AHashBuilder, AHASH_AES_TAG, AHASH_FALLBACK_TAG};
+#[cfg(feature = "wyhash")]
+pub use wyhash_impl::{WyHashBuilder, WyHasher, WYHASH_TAG};
+#[cfg(feature = "siphash")]
+pub use siphash::{Sip13Hasher, SipBuildHasher, SIPHASH13_TAG};
 
 
 // Handle vector allocation contextually
@@ -113,17 +341,34 @@ extern crate alloc;
 use alloc::vec::Vec;
 #[cfg(feature = "no_std")]
 use alloc::vec;
+#[cfg(feature = "no_std")]
+use alloc::string::String;
+#[cfg(feature = "no_std")]
+use alloc::boxed::Box;
+#[cfg(feature = "no_std")]
+use alloc::borrow::Cow;
 
 #[cfg(not(feature = "no_std"))]
 use std::vec::Vec;
 #[cfg(not(feature = "no_std"))]
 use std::vec;
+#[cfg(not(feature = "no_std"))]
+use std::string::String;
+#[cfg(not(feature = "no_std"))]
+use std::boxed::Box;
+#[cfg(not(feature = "no_std"))]
+use std::borrow::Cow;
+
+#[cfg(all(feature = "allocator-api", feature = "no_std"))]
+use alloc::alloc::{Allocator, Global};
+#[cfg(all(feature = "allocator-api", not(feature = "no_std")))]
+use std::alloc::{Allocator, Global};
 
 
 
 
 /// All errors that can be returned. This happens when merging or loading
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum HypeerlogError {
     /// The input byte array length does not match the embedded precision.
     InvalidLength,
@@ -131,6 +376,66 @@ pub enum HypeerlogError {
     InvalidPrecision,
     /// Merging failed because the two instances have different precisions.
     PrecisionMismatch,
+    /// Merging a sequence failed because the input at `index` could not be merged with the ones before it.
+    Incompatible {
+        /// Position of the offending input in the sequence.
+        index: usize,
+        /// Why the input could not be merged, e.g. [`HypeerlogError::PrecisionMismatch`] or an error of a dump.
+        source: Box<HypeerlogError>,
+    },
+    /// An operation that needs at least one input was given none.
+    EmptyInput,
+    /// The data was produced by a sketch with a different hasher configuration.
+    FingerprintMismatch,
+    /// A register index in the data is outside the `0..2^p` range.
+    InvalidRegisterIndex,
+    /// A register value in the data is larger than the maximum rank possible at its precision.
+    InvalidRegisterValue,
+    /// The data is neither a versioned dump (missing its magic prefix) nor a legacy dump.
+    InvalidMagic,
+    /// The dump was written with a format version this release cannot read.
+    UnsupportedVersion,
+    /// The dump header sets flags this release does not understand.
+    UnsupportedFlags,
+    /// The two sketches (or a sketch and a dump) hash with different seeds or hashers, so their registers cannot be combined.
+    SeedMismatch,
+    /// The two sketches (or a sketch and a dump) were built with different hash algorithms, as identified by [`HasherId::hasher_tag`].
+    HasherMismatch,
+    /// The dump is compressed with a codec this build does not support (e.g. its cargo feature is disabled).
+    UnsupportedCompression,
+    /// The compressed payload of the dump is malformed.
+    DecompressionFailed,
+    /// Reading the data from a stream failed; carries the kind of the underlying I/O error.
+    #[cfg(not(feature = "no_std"))]
+    Io(std::io::ErrorKind),
+    /// The text is not valid standard, padded base64.
+    Encoding,
+    /// The dump stores its registers compacted, packed or compressed, so they cannot be borrowed in place.
+    EncodedRegisters,
+    /// The checksum stored in the dump does not match its register payload, meaning the data was corrupted.
+    ChecksumMismatch {
+        /// The checksum stored in the dump.
+        expected: u32,
+        /// The checksum computed over the payload.
+        got: u32,
+    },
+    /// The data is in a foreign sketch format, but uses an encoding or mode of it that is not supported.
+    UnsupportedEncoding,
+    /// The archive holds no sketch with the requested name.
+    UnknownName,
+    /// The input is larger than any valid dump, so it was rejected before being read in full.
+    TooLarge,
+    /// The index is outside the sketches of a [`MultiHll`], or a range of them ends past them or before it starts.
+    IndexOutOfBounds {
+        /// The requested index, or the start or end of the requested range.
+        index: usize,
+        /// The number of sketches.
+        len: usize,
+    },
+    /// Merging failed because the two arrays of sketches have different lengths.
+    LengthMismatch,
+    /// Merging failed because the two decaying sketches have different half-lives.
+    HalfLifeMismatch,
 }
 
 impl core::fmt::Display for HypeerlogError {
@@ -139,12 +444,48 @@ impl core::fmt::Display for HypeerlogError {
             Self::InvalidLength => write!(f, "Invalid buffer length for the given precision"),
             Self::InvalidPrecision => write!(f, "Precision must be between 4 and 25"),
             Self::PrecisionMismatch => write!(f, "Cannot merge instances with different precisions"),
+            Self::Incompatible { index, source } => write!(f, "Input at index {} cannot be merged with the previous inputs: {}", index, source),
+            Self::EmptyInput => write!(f, "At least one input is required"),
+            Self::FingerprintMismatch => write!(f, "The data was produced with a different hasher configuration"),
+            Self::InvalidRegisterIndex => write!(f, "Register index is out of range for the given precision"),
+            Self::InvalidRegisterValue => write!(f, "Register value exceeds the maximum rank for the given precision"),
+            Self::InvalidMagic => write!(f, "Data is not a Hypeerlog dump"),
+            Self::UnsupportedVersion => write!(f, "Unsupported dump format version"),
+            Self::UnsupportedFlags => write!(f, "Dump header contains unsupported flags"),
+            Self::SeedMismatch => write!(f, "Cannot combine sketches built with different hasher seeds"),
+            Self::HasherMismatch => write!(f, "Cannot combine sketches built with different hash algorithms"),
+            Self::UnsupportedCompression => write!(f, "Dump is compressed with an unsupported codec"),
+            Self::DecompressionFailed => write!(f, "Failed to decompress the dump payload"),
+            #[cfg(not(feature = "no_std"))]
+            Self::Io(kind) => write!(f, "I/O error while reading the dump: {}", kind),
+            Self::Encoding => write!(f, "Input is not valid padded base64"),
+            Self::EncodedRegisters => write!(f, "Dump registers are encoded and cannot be borrowed"),
+            Self::ChecksumMismatch { expected, got } => write!(f, "Dump checksum mismatch (expected {:#010x}, got {:#010x})", expected, got),
+            Self::UnsupportedEncoding => write!(f, "Unsupported encoding of a foreign sketch format"),
+            Self::UnknownName => write!(f, "The archive has no sketch with the given name"),
+            Self::TooLarge => write!(f, "Input is larger than any valid dump"),
+            Self::IndexOutOfBounds { index, len } => write!(f, "Index {} is out of bounds for {} sketches", index, len),
+            Self::LengthMismatch => write!(f, "Cannot merge arrays of different numbers of sketches"),
+            Self::HalfLifeMismatch => write!(f, "Cannot merge decaying sketches with different half-lives"),
+        }
+    }
+}
+
+impl core::error::Error for HypeerlogError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            Self::Incompatible { source, .. } => Some(&**source),
+            _ => None,
         }
     }
 }
 
 #[cfg(not(feature = "no_std"))]
-impl std::error::Error for HypeerlogError {}
+impl From<std::io::Error> for HypeerlogError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err.kind())
+    }
+}
 
 
 
@@ -152,21 +493,87 @@ impl std::error::Error for HypeerlogError {}
 ///
 /// `Hypeerlog` is generic over its internal [`BuildHasher`]. By default, it employs a highly
 /// optimized `Murmur3BuildHasher` which is ideal for uniform bit distribution, but can be
-/// swapped out for cryptographic hashers if hash DoS protection is required.
-#[derive(Debug, PartialEq, Eq)]
-pub struct Hypeerlog<S = Murmur3BuildHasher> 
+/// swapped out for a keyed hasher such as SipHash (`SipBuildHasher` with the `siphash` feature,
+/// or `std`'s `RandomState`) if the elements come from untrusted users. Custom hashers
+/// also implement [`HasherId`], which lets dumps record their seed.
+///
+/// The default hasher feeds integers to Murmur3 as their little-endian bytes (`usize` and `isize`
+/// widened to 64 bits), so sketches of the same integers have the same registers on every platform,
+/// and releases of this crate keep hashing them the same way.
+///
+/// The $2^p$ registers of a new sketch are only allocated by the first insert or merge that raises one of them, so
+/// sketches that never see an element cost no more than the struct itself. Until then, estimating,
+/// comparing and merging them does not allocate.
+///
+/// A sketch is `Clone` when its hasher is: the clone has its own copy of the registers, so inserting into
+/// either one leaves the other unchanged (a snapshot for reporting, or a copy to merge without consuming).
+///
+/// With the `allocator-api` feature, the registers of a sketch built by `Hypeerlog::new_in` or another `_in`
+/// constructor are allocated by the given `Allocator`, and the sketch type has a second parameter, `A`, which
+/// defaults to the global allocator.
+#[derive(Clone)]
+#[cfg_attr(feature = "rkyv", derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize), rkyv(bytecheck(verify)))]
+pub struct Hypeerlog<S = Murmur3BuildHasher, #[cfg(feature = "allocator-api")] A: Allocator = Global>
 where
-    S: BuildHasher + Debug,
+    S: BuildHasher + HasherId,
 {
     hasher: S,
     precision: u8,
-    registers: Vec<u8>,
+    #[cfg(not(feature = "allocator-api"))]
+    registers: RegisterArray,
+    #[cfg(feature = "allocator-api")]
+    registers: RegisterArray<A>,
+    // Every insert counts, duplicates included; archives keep the layout they had before it was recorded
+    #[cfg_attr(feature = "rkyv", rkyv(with = rkyv::with::Skip))]
+    insertions: u64,
+}
+
+
+// Implements methods for the sketches of any allocator with the `allocator-api` feature, so that the operations
+// that do not create a sketch work the same whatever allocated its registers
+macro_rules! impl_sketch {
+    (impl $trait:path { $($body:tt)* }) => {
+        #[cfg(not(feature = "allocator-api"))]
+        impl<S> $trait for Hypeerlog<S>
+        where
+            S: BuildHasher + HasherId,
+        {
+            $($body)*
+        }
+
+        #[cfg(feature = "allocator-api")]
+        impl<S, A> $trait for Hypeerlog<S, A>
+        where
+            S: BuildHasher + HasherId,
+            A: Allocator,
+        {
+            $($body)*
+        }
+    };
+    ($($body:tt)*) => {
+        #[cfg(not(feature = "allocator-api"))]
+        impl<S> Hypeerlog<S>
+        where
+            S: BuildHasher + HasherId,
+        {
+            $($body)*
+        }
+
+        #[cfg(feature = "allocator-api")]
+        impl<S, A> Hypeerlog<S, A>
+        where
+            S: BuildHasher + HasherId,
+            A: Allocator,
+        {
+            $($body)*
+        }
+    };
 }
 
 
 impl<S> Hypeerlog<S>
 where
-    S: BuildHasher + Debug,
+    S: BuildHasher + HasherId,
 {
     /// Creates a new instance using a custom hasher builder with a default precision of 14.
     ///
@@ -176,7 +583,8 @@ where
         Hypeerlog {
             hasher: hasher_builder,
             precision: 14,
-            registers: vec![0; pow_two(14) as usize],
+            registers: RegisterArray::zeroed(pow_two(14) as usize),
+            insertions: 0,
         }
     }
 
@@ -190,10 +598,31 @@ where
         Hypeerlog {
             hasher: hasher_builder,
             precision: p,
-            registers: vec![0; pow_two(p) as usize],
+            registers: RegisterArray::zeroed(pow_two(p) as usize),
+            insertions: 0,
         }
     }
 
+    /// Builds a sketch of the given precision, with a custom hasher builder, from the items of an iterator, see
+    /// [`Hypeerlog::from_iter_with_precision`].
+    ///
+    /// The precision value is silently clamped to the valid range of `4..=25`.
+    pub fn from_iter_with_hasher_precision<H: Hash>(precision: u8, hasher_builder: S, iter: impl IntoIterator<Item = H>) -> Self {
+        let mut hll = Self::with_hasher_precision(precision, hasher_builder);
+        hll.insert_iter(iter);
+        hll
+    }
+
+    /// Builds a sketch of the given precision, with a custom hasher builder, from a slice of items, see
+    /// [`Hypeerlog::from_slice_with_precision`].
+    ///
+    /// The precision value is silently clamped to the valid range of `4..=25`.
+    pub fn from_slice_with_hasher_precision<H: Hash>(precision: u8, hasher_builder: S, data: &[H]) -> Self {
+        let mut hll = Self::with_hasher_precision(precision, hasher_builder);
+        hll.insert_many(data);
+        hll
+    }
+
     /// Creates a new instance with a custom hasher builder targeting a specific relative error.
     ///
     /// # Panics
@@ -204,46 +633,112 @@ where
         Hypeerlog {
             hasher: hasher_builder,
             precision: p,
-            registers: vec![0; pow_two(p) as usize],
+            registers: RegisterArray::zeroed(pow_two(p) as usize),
+            insertions: 0,
         }
     }
 
     /// Deserializes a dumped `Hypeerlog` state vector using a custom hasher.
     ///
+    /// The versioned format written by [`Hypeerlog::dump`], [`Hypeerlog::dump_compact`] and the compressed
+    /// variants (see the optional features), and the legacy format of previous releases (raw registers
+    /// followed by a precision byte) are all accepted. To parse from a borrowed slice, use [`Hypeerlog::from_bytes_with_hasher`].
+    ///
     /// # Errors
     ///
     /// Returns an error if:
-    /// - The byte buffer is empty or missing its trailing precision metadata byte ([`HypeerlogError::InvalidLength`]).
-    /// - The extracted precision byte is outside `4..=25` ([`HypeerlogError::InvalidPrecision`]).
-    /// - The byte buffer length does not exactly match the expected register count ($2^p$) for that precision ([`HypeerlogError::InvalidLength`]).
-    pub fn load_with_hasher(mut bytes: Vec<u8>, hasher_builder: S) -> Result<Self, HypeerlogError> {
-        let p = bytes.pop().ok_or(HypeerlogError::InvalidLength)?;
-        if p < 4 || p > 25 { return Err(HypeerlogError::InvalidPrecision); }
-        if bytes.len() != (pow_two(p) as usize) {return Err(HypeerlogError::InvalidPrecision);}
-        Ok(Hypeerlog {
-            hasher: hasher_builder,
-            precision: p,
-            registers: bytes,
+    /// - The bytes are neither a versioned dump nor shaped like a legacy one, $2^p$ registers followed by a
+    ///   precision `p` in `4..=25` ([`HypeerlogError::InvalidMagic`]).
+    /// - The dump was written with a newer format version ([`HypeerlogError::UnsupportedVersion`]) or sets unknown flags ([`HypeerlogError::UnsupportedFlags`]).
+    /// - The embedded precision is outside `4..=25` ([`HypeerlogError::InvalidPrecision`]).
+    /// - The payload length does not exactly match the expected register count ($2^p$) for that precision ([`HypeerlogError::InvalidLength`]).
+    /// - A register holds a value larger than any rank possible at that precision ([`HypeerlogError::InvalidRegisterValue`]).
+    /// - The payload does not match the embedded checksum ([`HypeerlogError::ChecksumMismatch`]).
+    /// - The payload is compressed with a codec whose feature is disabled ([`HypeerlogError::UnsupportedCompression`]),
+    ///   is not a valid compressed stream ([`HypeerlogError::DecompressionFailed`]), or decompresses to more than the
+    ///   $2^p$ bytes its precision allows ([`HypeerlogError::InvalidLength`]).
+    /// - The dump records a hasher seed that differs from the seed of `hasher_builder` ([`HypeerlogError::SeedMismatch`]).
+    /// - The dump records a hasher tag that differs from the tag of `hasher_builder` ([`HypeerlogError::HasherMismatch`]).
+    pub fn load_with_hasher(bytes: Vec<u8>, hasher_builder: S) -> Result<Self, HypeerlogError> {
+        Self::from_bytes_with_hasher(&bytes, hasher_builder)
+    }
+
+    /// Deserializes a dump from a borrowed slice using a custom hasher, copying only the registers.
+    ///
+    /// This accepts exactly the same inputs as [`Hypeerlog::load_with_hasher`], without requiring an owned
+    /// vector, which makes it suitable for memory-mapped files and network buffers.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`Hypeerlog::load_with_hasher`].
+    pub fn from_bytes_with_hasher(bytes: &[u8], hasher_builder: S) -> Result<Self, HypeerlogError> {
+        Self::load_checked(bytes, dump::Checksum::Verify, |stored| {
+            stored.check(dump::StoredHasher::of(&hasher_builder)).map(|status| (hasher_builder, status))
+        })
+        .map(|(hll, _)| hll)
+    }
+
+    /// Same as [`Hypeerlog::load_with_hasher`], but also reports whether the hasher could be verified.
+    ///
+    /// Dumps without a hasher tag (such as legacy dumps), or hashers that do not declare one, still load,
+    /// but yield [`HasherStatus::Unverified`]: the caller is responsible for supplying the right hasher.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`Hypeerlog::load_with_hasher`].
+    pub fn load_with_hasher_status(bytes: Vec<u8>, hasher_builder: S) -> Result<(Self, HasherStatus), HypeerlogError> {
+        Self::load_checked(&bytes, dump::Checksum::Verify, |stored| {
+            stored.check(dump::StoredHasher::of(&hasher_builder)).map(|status| (hasher_builder, status))
         })
     }
 
+    /// Deserializes a dumped `Hypeerlog` state vector using a custom hasher, without verifying its checksum.
+    ///
+    /// Only use this when the integrity of the bytes is already guaranteed by the transport or storage layer.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`Hypeerlog::load_with_hasher`], except that [`HypeerlogError::ChecksumMismatch`] is never returned.
+    pub fn load_unchecked_with_hasher(bytes: Vec<u8>, hasher_builder: S) -> Result<Self, HypeerlogError> {
+        Self::load_checked(&bytes, dump::Checksum::Skip, |stored| {
+            stored.check(dump::StoredHasher::of(&hasher_builder)).map(|status| (hasher_builder, status))
+        })
+        .map(|(hll, _)| hll)
+    }
+
+    // Parses a dump and builds the hasher from the seed and tag it records (if any)
+    fn load_checked(
+        bytes: &[u8],
+        checksum: dump::Checksum,
+        make_hasher: impl FnOnce(dump::StoredHasher) -> Result<(S, HasherStatus), HypeerlogError>,
+    ) -> Result<(Self, HasherStatus), HypeerlogError> {
+        let parsed = dump::parse_dump(bytes, checksum)?;
+        let (hasher, status) = make_hasher(parsed.hasher)?;
+        let hll = Hypeerlog {
+            hasher,
+            precision: parsed.precision,
+            insertions: parsed.insertions,
+            registers: parsed.into_register_array(bytes),
+        };
+        Ok((hll, status))
+    }
+
     /// Deserializes a dumped `Hypeerlog` state directly from a streaming reader.
     ///
+    /// At most the size of the largest valid dump is read, so a reader that never ends cannot exhaust memory.
+    ///
     /// # Errors
     ///
-    /// Returns a [`HypeerlogError`] if the underlying reader fails or if the stream represents
-    /// an invalid or corrupted sketch configuration.
+    /// Returns [`HypeerlogError::Io`] if the underlying reader fails, [`HypeerlogError::TooLarge`] if it holds more
+    /// bytes than any valid dump, and any error of [`Hypeerlog::load_with_hasher`] if the dump itself is invalid.
     #[cfg(not(feature = "no_std"))]
-    pub fn load_from_with_hasher<R: std::io::Read>(mut reader: R, hasher_builder: S) -> Result<Self, HypeerlogError> {
-        // We don't know the precision yet, but we can read the exact structure if we 
-        // read the stream. Since the precision byte is at the end, a simple approach for
-        // streaming readers is to read all bytes into a temporary vector.
-        let mut bytes = std::vec::Vec::new();
-        reader.read_to_end(&mut bytes).map_err(|_| HypeerlogError::InvalidLength)?;
-        Self::load_with_hasher(bytes, hasher_builder)
+    pub fn load_from_with_hasher<R: std::io::Read>(reader: R, hasher_builder: S) -> Result<Self, HypeerlogError> {
+        Self::load_with_hasher(read_whole_dump(reader)?, hasher_builder)
     }
+}
 
 
+impl_sketch! {
     /// Returns the total number of underlying register buckets used by the sketch.
     ///
     /// This value equals $2^{\text{precision}}$.
@@ -251,6 +746,63 @@ where
         self.registers.len()
     }
 
+    /// Returns the values of the registers, in bucket order, e.g. to export them to another HyperLogLog
+    /// implementation or to a monitoring system.
+    ///
+    /// The slice has [`Hypeerlog::len`] values, $2^p$, and each is the rank of the longest run the bucket has
+    /// seen: `0` for a bucket that saw no element, and at most $64 - p + 1$. The slice is read-only, so that the
+    /// registers only change through inserts, merges and validated loads. It is borrowed from the registers, except
+    /// for a sketch whose registers were never allocated, e.g. one that never saw an element, for which it is a
+    /// copy of its zero registers; [`Hypeerlog::iter`] reads either without allocating.
+    pub fn register_values(&self) -> Cow<'_, [u8]> {
+        match self.registers.values() {
+            Some(values) => Cow::Borrowed(values),
+            None => Cow::Owned(self.registers.to_vec()),
+        }
+    }
+
+    /// Returns an iterator over the rank of every register, in ascending order of bucket index, so that the
+    /// n-th rank is the one of bucket n.
+    ///
+    /// It yields [`Hypeerlog::len`] ranks, the values of [`Hypeerlog::register_values`], without copying them.
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = u8> + '_ {
+        self.registers.iter()
+    }
+
+    /// Returns an iterator over the `(bucket, rank)` pairs of the registers that saw at least one element, in
+    /// ascending order of bucket index.
+    ///
+    /// The ranks it yields are never 0. It yields nothing for an empty sketch, without scanning its registers.
+    ///
+    /// ```
+    /// use hypeerlog::Hypeerlog;
+    ///
+    /// let mut hll = Hypeerlog::with_precision(4);
+    /// // Bucket 3, with 2 zero bits above the 4 bits of the bucket
+    /// hll.insert_hashed(0b1_00_0011);
+    /// assert_eq!(hll.iter_nonzero().collect::<Vec<_>>(), [(3, 3)]);
+    /// ```
+    pub fn iter_nonzero(&self) -> impl Iterator<Item = (usize, u8)> + '_ {
+        let registers = if self.is_empty() { &[] } else { self.registers.values().unwrap_or_default() };
+        registers.iter().enumerate().filter(|&(_, &rank)| rank != 0).map(|(bucket, &rank)| (bucket, rank))
+    }
+
+    /// Returns the number of registers holding each value: index `k` counts the registers of rank `k`.
+    ///
+    /// Index 0 counts the registers that saw no element, the count LinearCounting estimates small cardinalities
+    /// from. The counts sum to [`Hypeerlog::len`], and every index above $64 - p + 1$ is 0. The histogram is the
+    /// input of estimators that work from the multiplicity of each register value, such as the maximum
+    /// likelihood estimator, and is computed in a single pass over the registers, or none for an empty sketch.
+    pub fn register_histogram(&self) -> [u32; 65] {
+        let mut histogram = [0u32; 65];
+        if self.is_empty() {
+            histogram[0] = self.registers.len() as u32;
+        } else {
+            self.registers.iter().for_each(|rank| histogram[rank as usize] += 1);
+        }
+        histogram
+    }
+
     /// Returns the precision ($p$) configuration of this HyperLogLog.
     pub fn precision(&self) -> u8 {
         self.precision
@@ -265,69 +817,332 @@ where
     ///
     /// This will hash the item and update the appropriate internal register bucket if the item's
     /// hash contains a longer run of leading zeros than previously observed.
-    pub fn insert<H: Hash>(&mut self, data: H) {
-        let mut hasher = self.hasher.build_hasher();
-        data.hash(&mut hasher);
-        let hash = hasher.finish();
-        let register_idx = get_bucket(self.precision, hash);
-        self.registers[register_idx] = longest_run(self.precision, hash).max(self.registers[register_idx]);
+    ///
+    /// With the default hasher, `usize` and `isize` values (and the lengths of slices and collections)
+    /// are hashed as 64-bit integers, so items inserted on 32-bit and 64-bit targets land in the same
+    /// registers and their sketches can be merged.
+    ///
+    /// Returns `true` if the register of the item was raised, and `false` if it already held an equal or higher
+    /// rank, as it does for duplicates and for most items once the sketch has seen many. A `false` insert left
+    /// the registers unchanged: the estimate and the cached estimate are the same as before. Every insert counts
+    /// towards [`Hypeerlog::total_insertions`], whether it raised a register or not.
+    ///
+    /// A reference hashes like the value it points to, so `hll.insert(&item)` inserts the same as `hll.insert(item)`
+    /// without moving it; see [`Hypeerlog::insert_ref`] for unsized items.
+    pub fn insert<H: Hash>(&mut self, data: H) -> bool {
+        self.insert_ref(&data)
     }
 
-    /// Inserts a slice of items into the Hyperloglog.
+    /// Inserts a borrowed item, with the same result as [`Hypeerlog::insert`] on the item itself.
     ///
-    /// Perfect for high-throughput batch updates.
-    pub fn insert_many<H: Hash>(&mut self, data: &[H]) {
-        for elem in data {
-            self.insert(elem);
+    /// The item may be unsized, like `str`, `[u8]` or a custom type that ends in one, so generic code that only has
+    /// a `&T` inserts it without the `T: Sized` bound or a clone. Owned and borrowed forms that hash alike, like
+    /// `String` and `str` or `Vec<u8>` and `[u8]`, land in the same register.
+    ///
+    /// ```
+    /// use hypeerlog::Hypeerlog;
+    ///
+    /// let names = vec![String::from("ada"), String::from("grace")];
+    /// let mut hll = Hypeerlog::new();
+    /// for name in &names {
+    ///     hll.insert_ref(name.as_str());
+    /// }
+    /// assert!(!hll.insert_ref(&names[0]));
+    /// assert_eq!(names.len(), 2);
+    /// ```
+    pub fn insert_ref<H: Hash + ?Sized>(&mut self, data: &H) -> bool {
+        self.insert_hashed(self.hasher.hash_one(data))
+    }
+
+    /// Inserts an item by a hash computed outside of the sketch, skipping the hasher entirely.
+    ///
+    /// The lowest $p$ bits of `hash` select the register, and the rest of it gives the rank, exactly as for the
+    /// hashes of the sketch's own hasher. Use it when records already carry a good 64-bit hash, e.g. one used
+    /// for partitioning. All sketches that are merged together must then be fed the same upstream hash of the
+    /// same items: the seed and tag checks of merges and dumps only describe the sketch's own hasher, so they
+    /// cannot detect sketches built from different hash functions.
+    ///
+    /// Returns whether the register was raised, like [`Hypeerlog::insert`].
+    pub fn insert_hashed(&mut self, hash: u64) -> bool {
+        self.insertions = self.insertions.saturating_add(1);
+        self.registers.raise(get_bucket(self.precision, hash), longest_run(self.precision, hash))
+    }
+
+    /// Inserts a batch of hashes computed outside of the sketch, with the same result as calling
+    /// [`Hypeerlog::insert_hashed`] on each of them in order.
+    ///
+    /// At high precisions the registers no longer fit in the CPU caches, and the random register accesses
+    /// dominate the cost of inserting. This loop requests the registers of upcoming hashes ahead of time
+    /// (on `x86_64`), so that those memory accesses overlap instead of being waited for one by one.
+    ///
+    /// Returns the number of hashes that raised their register, which counts a register once per hash that
+    /// raised it: it is 0 exactly when the batch left the registers unchanged.
+    pub fn insert_hashes(&mut self, hashes: &[u64]) -> usize {
+        // Far enough ahead to hide a main memory access, near enough for the line to still be cached
+        const AHEAD: usize = 16;
+
+        self.insertions = self.insertions.saturating_add(hashes.len() as u64);
+        let precision = self.precision;
+        let registers = &mut self.registers;
+        // The prefetches point into the registers, which must be allocated
+        if !hashes.is_empty() {
+            registers.allocate();
+        }
+        let mut changed = 0;
+        let split = hashes.len().saturating_sub(AHEAD);
+        for (&hash, &upcoming) in hashes[..split].iter().zip(&hashes[AHEAD.min(hashes.len())..]) {
+            prefetch(registers.values().unwrap_or_default(), get_bucket(precision, upcoming));
+            changed += registers.raise(get_bucket(precision, hash), longest_run(precision, hash)) as usize;
+        }
+        for &hash in &hashes[split..] {
+            changed += registers.raise(get_bucket(precision, hash), longest_run(precision, hash)) as usize;
+        }
+        changed
+    }
+
+    /// Inserts exactly the given bytes, fed to the hasher as they are.
+    ///
+    /// Unlike [`Hypeerlog::insert`], which goes through [`Hash`] and may add length prefixes or delimiters,
+    /// no byte is added or removed, so this is the insertion path to use when sketches built by other
+    /// implementations must be merged with this one. With the default hasher, the hash is the 32-bit
+    /// MurmurHash3 (x86_32 variant) of the bytes with the sketch's seed; its lowest $p$ bits select the
+    /// register, and the register is raised to one plus the number of trailing zero bits of the rest
+    /// of the hash, capped at the largest rank of the precision.
+    ///
+    /// Returns whether the register was raised, like [`Hypeerlog::insert`].
+    pub fn insert_bytes(&mut self, bytes: &[u8]) -> bool {
+        self.insert_hashed(self.hasher.hash_bytes(bytes))
+    }
+
+    /// Inserts the UTF-8 bytes of a string as they are, see [`Hypeerlog::insert_bytes`].
+    ///
+    /// `hll.insert(s)` hashes the string with a trailing `0xff` delimiter (see [`Murmur3Hasher`] for the frozen
+    /// encoding of the default hasher), which other implementations do not add; use this method for strings that
+    /// other implementations insert too.
+    pub fn insert_str(&mut self, s: &str) -> bool {
+        self.insert_bytes(s.as_bytes())
+    }
+
+    /// Inserts a slice of items into the Hyperloglog, with the same result as [`Hypeerlog::insert`] on each of them.
+    ///
+    /// Each item is hashed once. From precision 20, where the registers no longer fit in the CPU caches, the items
+    /// are hashed in batches before the registers are updated with [`Hypeerlog::insert_hashes`], which fetches the
+    /// registers of upcoming hashes ahead of time, so that the random register accesses overlap. For `u64` items,
+    /// [`Hypeerlog::insert_many_u64`] gives the same registers faster.
+    ///
+    /// The slice is only borrowed: `hll.insert_many(&names)` inserts a `Vec<String>` that is still needed afterwards,
+    /// and inserts the same as a `&[&str]` of the same strings.
+    ///
+    /// Returns the number of items that raised their register, see [`Hypeerlog::insert_hashes`].
+    pub fn insert_many<H: Hash>(&mut self, data: &[H]) -> usize {
+        self.insert_iter(data)
+    }
+
+    /// Inserts every item of an iterator, with the same result as [`Hypeerlog::insert_many`] on a slice of them.
+    ///
+    /// The iterator is consumed as the items are inserted, so streaming data does not need to be collected first,
+    /// and batches of at most 256 hashes are kept at a time. References hash like the values they point to, so
+    /// `hll.insert_iter(map.keys())` inserts the same as inserting the keys themselves, without cloning them.
+    ///
+    /// ```
+    /// use hypeerlog::Hypeerlog;
+    ///
+    /// let mut hll = Hypeerlog::new();
+    /// hll.insert_iter((0..1000u64).filter(|x| x % 2 == 0).map(|x| x * 3));
+    /// assert!((hll.cardinality() - 500.0).abs() < 10.0);
+    /// ```
+    ///
+    /// Returns the number of items that raised their register, see [`Hypeerlog::insert_hashes`].
+    pub fn insert_iter<I>(&mut self, iter: I) -> usize
+    where
+        I: IntoIterator,
+        I::Item: Hash,
+    {
+        let mut items = iter.into_iter();
+        if self.precision < MIN_BATCHED_PRECISION {
+            return items.map(|elem| self.insert(elem) as usize).sum();
+        }
+        let mut hashes = [0u64; INSERT_BATCH];
+        let mut changed = 0;
+        loop {
+            let mut len = 0;
+            for (hash, elem) in hashes.iter_mut().zip(items.by_ref()) {
+                *hash = self.hasher.hash_one(elem);
+                len += 1;
+            }
+            changed += self.insert_hashes(&hashes[..len]);
+            if len < INSERT_BATCH {
+                return changed;
+            }
+        }
+    }
+
+    /// Inserts a slice of `u64` items, with the same result as [`Hypeerlog::insert_many`] on them.
+    ///
+    /// The keys are hashed in batches ([`HasherId::hash_u64s`]) before the registers are updated. With the
+    /// default hasher and the `simd` feature, eight keys are hashed per iteration with AVX2 on `x86_64` CPUs
+    /// that support it, which is detected at runtime (at compile time with `no_std`).
+    ///
+    /// Returns the number of keys that raised their register, see [`Hypeerlog::insert_hashes`].
+    pub fn insert_many_u64(&mut self, keys: &[u64]) -> usize {
+        let mut hashes = [0u64; INSERT_BATCH];
+        let mut changed = 0;
+        for chunk in keys.chunks(INSERT_BATCH) {
+            let hashes = &mut hashes[..chunk.len()];
+            self.hasher.hash_u64s(chunk, hashes);
+            changed += self.insert_hashes(hashes);
         }
+        changed
     }
 
 
-   /// Returns `true` if no elements have been observed by this Hyperloglog yet.
+    /// Returns `true` if no elements have been observed by this Hyperloglog yet, or since the last
+    /// [`Hypeerlog::clear`].
     pub fn is_empty(&self) -> bool {
-        self.registers.iter().all(|&val| val == 0)
+        self.registers.zero_registers() == self.registers.len()
+    }
+
+    /// Returns the fraction of the registers that saw at least one element, from 0.0 for an empty sketch to 1.0.
+    ///
+    /// It reads the zero count the estimator maintains, so it takes constant time. The estimate comes from
+    /// LinearCounting, which relies on the zero registers, while the raw HyperLogLog estimate is below $2.5m$
+    /// for $m$ registers, which is up to a saturation of about $1 - e^{-2.5} \approx 0.92$, reached around
+    /// $2.5m$ distinct elements. Above it, the harmonic mean of the registers takes over, and the saturation
+    /// soon reaches 1.0. A saturation that stays far below 1.0 suggests that a lower precision would do, while
+    /// one that reaches 1.0 early leaves the estimates to the HyperLogLog estimator alone.
+    pub fn saturation(&self) -> f64 {
+        1.0 - self.registers.zero_registers() as f64 / self.registers.len() as f64
+    }
+
+    /// Returns the largest register value, 0 for an empty sketch.
+    ///
+    /// A register is at most $64 - p + 1$, the rank of a hash whose $64 - p$ bits above the bucket index are all
+    /// zero, so the sketch cannot tell apart the runs that reach that cap. It scans the registers, except for an
+    /// empty sketch.
+    pub fn max_rank(&self) -> u8 {
+        if self.is_empty() { 0 } else { self.registers.iter().max().unwrap_or(0) }
+    }
+
+    /// Returns `false` if the sketch was pushed beyond what its hashes can distinguish, so that its estimate
+    /// should not be trusted.
+    ///
+    /// That is the case when the estimate exceeds $2^{bits} / 30$ for hashes of [`HasherId::hash_bits`] bits,
+    /// the large-range threshold of the original paper, from which hash collisions bias the estimates: about
+    /// 143 million for the 32-bit hashes of the default `Murmur3BuildHasher`, and $6 \cdot 10^{17}$ for 64-bit
+    /// ones. With 64-bit hashes it is also the case when a register is at the cap of [`Hypeerlog::max_rank`] or
+    /// one below it, which uniform hashes essentially never reach, so that it usually means that the hashes are
+    /// not uniform, e.g. ones given to [`Hypeerlog::insert_hashed`] that are not really hashes. The ranks of
+    /// narrower hashes reach the cap of their width after only about $2^{bits - p}$ elements, long before the
+    /// estimate degrades, so only the estimate is checked for them.
+    ///
+    /// It scans the registers for 64-bit hashes, like [`Hypeerlog::max_rank`], and takes constant time otherwise.
+    pub fn is_reliable(&self) -> bool {
+        let bits = self.hasher.hash_bits();
+        if self.cardinality() > large_range_threshold(bits) {
+            return false;
+        }
+        bits < 64 || self.max_rank() < max_rank(self.precision) - 1
     }
 
     /// Resets all internal register buckets back to zero, effectively wiping the history of the sketch
     /// without re-allocating memory.
+    ///
+    /// This is the one reset of the sketch: the maintained zero count and harmonic sum are reset with the
+    /// registers, and the estimate cached by [`Hypeerlog::cardinality_cached`] is discarded, so a cleared
+    /// sketch compares equal to, estimates and dumps exactly like a new one of the same precision and hasher.
     pub fn clear(&mut self) {
-        self.registers.fill(0);
+        self.registers.clear();
+        self.insertions = 0;
+    }
+
+    /// Returns the number of elements inserted into the sketch, duplicates included, since it was created or last
+    /// [`Hypeerlog::clear`]ed.
+    ///
+    /// Every insert counts one, whether it raised a register or not, and the batched inserts count each of their
+    /// items. Merges add the count of the other sketch, and [`Hypeerlog::dump`] records it (when it is not 0), so
+    /// it survives a round-trip through [`Hypeerlog::load`]. Unlike the registers, the count is not idempotent
+    /// under merges: merging the same sketch or dump twice, e.g. a replica received again, adds its count twice,
+    /// and the count over-counts the insertions the merged data saw. The other formats, and the conversions from
+    /// [`AtomicHypeerlog`], `ShardedHypeerlog` and [`PackedHypeerlog`], do not carry it, so their sketches start
+    /// at 0. The count saturates at `u64::MAX`, and is not compared by `==`.
+    pub fn total_insertions(&self) -> u64 {
+        self.insertions
+    }
+
+    /// Returns how many times each distinct element was inserted on average: [`Hypeerlog::total_insertions`]
+    /// divided by the [`Hypeerlog::cardinality`] estimate, or 1.0 for an empty sketch.
+    ///
+    /// A stream without duplicates gives about 1.0, within the error of the estimate, and one that repeats every
+    /// element ten times about 10.0. Sketches loaded from formats that do not carry the insertion count, or merged
+    /// with one, count fewer insertions than they saw, and can give ratios below 1.0; sketches that merged the same
+    /// data more than once count its insertions again, and give ratios above the real one.
+    ///
+    /// ```
+    /// use hypeerlog::Hypeerlog;
+    ///
+    /// let mut hll = Hypeerlog::new();
+    /// for _ in 0..10 {
+    ///     hll.insert_many(&[1, 2, 3]);
+    /// }
+    /// assert_eq!(hll.total_insertions(), 30);
+    /// assert_eq!(hll.duplication_ratio().round(), 10.0);
+    /// ```
+    pub fn duplication_ratio(&self) -> f64 {
+        if self.is_empty() {
+            return 1.0;
+        }
+        self.insertions as f64 / self.cardinality()
     }
 
     /// Returns the estimated distinct element count (cardinality) observed by this sketch.
     ///
     /// This applies bias correction algorithms and transitions dynamically to linear counting
     /// for low-range estimates to keep estimation error within bounds.
+    ///
+    /// The number of zero registers and the harmonic sum of the registers are kept up to date by every
+    /// insert, merge and delta, so this takes constant time at any precision, and can be called after
+    /// every batch of inserts.
     pub fn cardinality(&self) -> f64 {
-        let m = pow_two(self.precision) as f64;
-        let alpha_m = get_alpha_m_bias(m);
-
-        let num_zero_registers = self.registers.iter().filter(|&&val| val == 0).count();
+        debug_assert!(self.registers.aggregates_match(), "register aggregates out of sync");
+        estimate_cardinality(self.precision, self.registers.zero_registers(), self.registers.harmonic_sum())
+    }
 
-        if num_zero_registers == m as usize {
-            return 0.0;
+    /// Returns the estimated distinct element count as an integer: [`Hypeerlog::cardinality`] rounded half-up.
+    ///
+    /// The estimate is clamped to `0..=u64::MAX`, so the estimates above `u64::MAX` that sketches of the lowest
+    /// precisions can reach with saturated registers give `u64::MAX`, and an empty sketch counts exactly 0.
+    /// Like the estimates they are rounded from, the count of a merged sketch estimates the distinct elements of
+    /// the union, so it is usually less than the sum of the counts of the merged sketches.
+    ///
+    /// ```
+    /// use hypeerlog::Hypeerlog;
+    ///
+    /// let mut hll = Hypeerlog::new();
+    /// assert_eq!(hll.count(), 0);
+    /// hll.insert_many(&[1, 2, 3]);
+    /// assert_eq!(hll.count(), 3);
+    /// ```
+    pub fn count(&self) -> u64 {
+        if self.is_empty() {
+            return 0;
         }
+        round_count(self.cardinality())
+    }
 
-        let harmonic_mean = harmonic_mean(&self.registers);
-        let mut estimate = alpha_m * m * m * harmonic_mean;
-
-        // Use LinearCounting if there are still empty buckets AND the raw HLL estimate is low
-        if num_zero_registers > 0 && estimate < (2.5 * m) { 
-            // Linear Counting formula: m * ln(m / V)
-            // V is the number of zero registers.
-            // estimate = m * (m / num_zero_registers as f64).ln();
-
-            let ratio = m / num_zero_registers as f64;
-            
-            #[cfg(not(feature = "no_std"))]
-            {
-                estimate = m * ratio.ln();
-            }
-            #[cfg(feature = "no_std")]
-            {
-                estimate = m * libm::log(ratio); // libm::log is the natural log (ln)
-            }
+    /// Returns the same estimate as [`Hypeerlog::cardinality`], reusing the last one computed by this method
+    /// as long as no register changed since.
+    ///
+    /// Inserting an element whose register already holds an equal or higher rank, which is the case of most
+    /// duplicates, keeps the cached estimate; any insert, merge or delta that raises a register, and
+    /// [`Hypeerlog::clear`], discard it. The cache is not part of the sketch: it is not compared by `==`, nor
+    /// written to dumps, so loaded sketches start without one.
+    pub fn cardinality_cached(&mut self) -> f64 {
+        if let Some(estimate) = self.registers.cached_estimate() {
+            debug_assert_eq!(estimate.to_bits(), self.cardinality().to_bits(), "stale cached estimate");
+            return estimate;
         }
+        let estimate = self.cardinality();
+        self.registers.cache_estimate(estimate);
         estimate
     }
 
@@ -340,27 +1155,98 @@ where
     /// Returns [`HypeerlogError::PrecisionMismatch`] if the two sketches were initialized with
     /// different precision thresholds.
     pub fn merge(mut self, other: Self) -> Result<Self, HypeerlogError> {
-        if self.precision != other.precision {
+        self.merge_with(&other)?;
+        Ok(self)
+    }
+
+    /// Merges a borrowed `Hypeerlog` sketch into this one in place.
+    ///
+    /// Each register is set to the maximum of both sketches, so this sketch ends up observing the union
+    /// of both element sets while `other` is left untouched. The insertion counts of both sketches are summed,
+    /// see [`Hypeerlog::total_insertions`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`HypeerlogError::PrecisionMismatch`] if the two sketches were initialized with
+    /// different precision thresholds. `self` is not modified in that case.
+    pub fn merge_with(&mut self, other: &Self) -> Result<(), HypeerlogError> {
+        self.check_compatible(other)?;
+        self.merge_registers(other);
+        Ok(())
+    }
+
+    /// Merges a dump (as produced by [`Hypeerlog::dump`]) into this sketch without loading it first.
+    ///
+    /// # Errors
+    ///
+    /// Returns any of the errors of [`Hypeerlog::load_with_hasher`] if the dump is malformed, corrupted or
+    /// was built with another seed or hasher, or [`HypeerlogError::PrecisionMismatch`] if it has a different precision.
+    /// `self` is not modified in any of these cases.
+    pub fn merge_from_dump(&mut self, bytes: &[u8]) -> Result<(), HypeerlogError> {
+        let parsed = dump::parse_dump(bytes, dump::Checksum::Verify)?;
+        if parsed.precision != self.precision {
             return Err(HypeerlogError::PrecisionMismatch);
         }
+        parsed.hasher.check(dump::StoredHasher::of(&self.hasher))?;
 
-        self.registers.iter_mut()
-            .zip(other.registers.iter())
-            .for_each(|(a, b)| *a = a.clone().max(b.clone()));
+        if let Some(registers) = parsed.registers(bytes) {
+            self.registers.raise_all(registers);
+        }
+        self.insertions = self.insertions.saturating_add(parsed.insertions);
+        Ok(())
+    }
 
-        Ok(self)
+    // Checks that `other` can be merged into this sketch: same precision, and hashers that map the
+    // same input to the same hash (which also covers differently seeded instances of one hasher)
+    fn check_compatible(&self, other: &Self) -> Result<(), HypeerlogError> {
+        if self.precision != other.precision {
+            return Err(HypeerlogError::PrecisionMismatch);
+        }
+        check_hashers(&self.hasher, &other.hasher)
+    }
+
+    // Max-merges the registers of `other`, which must have passed `check_compatible`, and adds its insertions.
+    // Returns the number of registers it raised
+    fn merge_registers(&mut self, other: &Self) -> usize {
+        self.insertions = self.insertions.saturating_add(other.insertions);
+        self.registers.union(&other.registers)
     }
 
     /// Serializes the state of the sketch into a heap-allocated `Vec<u8>`.
     ///
-    /// The resulting vector contains the raw values of all register bytes, appended with a single
-    /// final byte denoting the precision configuration. This array can be stored or transmitted and
-    /// reloaded later via [`Hypeerlog::load`].
+    /// The resulting vector uses the versioned dump format: a 6-byte header made of the magic bytes
+    /// `b"HLL"`, the format version (`2`), the precision and a flags byte, then a little-endian CRC32
+    /// of the registers, then, when the hasher declares them, its little-endian `u64` seed ([`HasherId::hasher_seed`])
+    /// and `u32` tag ([`HasherId::hasher_tag`]), then, when it is not 0, the little-endian `u64`
+    /// [`Hypeerlog::total_insertions`], followed by the raw values of all register bytes. This array can be stored or transmitted
+    /// and reloaded later via [`Hypeerlog::load`].
+    ///
+    /// An empty sketch, e.g. a new or cleared one, is dumped with the compact encoding of
+    /// [`Hypeerlog::dump_compact`] instead, which takes no byte for all-zero registers and reads no register of a
+    /// sketch that never allocated them.
     pub fn dump(&self) -> Vec<u8> {
-        let mut clone = self.registers.clone();
-        clone.push(self.precision);
-        clone
-    } 
+        let mut bytes = Vec::new();
+        self.dump_into(&mut bytes);
+        bytes
+    }
+
+    /// Serializes the state of the sketch like [`Hypeerlog::dump`], into a caller-provided buffer.
+    ///
+    /// The buffer is cleared first, then grown to exactly [`Hypeerlog::dump_len`] bytes if its capacity is
+    /// too small, so reusing one buffer across calls (e.g. in a checkpoint loop) does not allocate after the first one.
+    pub fn dump_into(&self, out: &mut Vec<u8>) {
+        let prefix = self.dump_prefix();
+        out.clear();
+        out.reserve_exact(prefix.as_bytes().len() + self.dump_payload().len());
+        out.extend_from_slice(prefix.as_bytes());
+        out.extend_from_slice(self.dump_payload());
+    }
+
+    /// Returns the length in bytes of [`Hypeerlog::dump`], to pre-size buffers for [`Hypeerlog::dump_into`]
+    /// or [`Hypeerlog::dump_to_slice`].
+    pub fn dump_len(&self) -> usize {
+        dump::prefix_len(dump::StoredHasher::of(&self.hasher), self.insertions) + self.dump_payload().len()
+    }
 
     /// Writes the exact binary state of the sketch straight to a generic writer.
     ///
@@ -371,8 +1257,8 @@ where
     /// Returns an [`std::io::Error`] if writing to the underlying stream fails.
     #[cfg(not(feature = "no_std"))]
     pub fn dump_to<W: std::io::Write>(&self, mut writer: W) -> std::io::Result<()> {
-        writer.write_all(&self.registers)?;
-        writer.write_all(&[self.precision])?;
+        writer.write_all(self.dump_prefix().as_bytes())?;
+        writer.write_all(self.dump_payload())?;
         Ok(())
     }
 
@@ -383,18 +1269,39 @@ where
     /// # Errors
     ///
     /// Returns [`HypeerlogError::InvalidLength`] if the provided target buffer slice is smaller
-    /// than the required space ($2^{\text{precision}} + 1$ bytes).
+    /// than the required space ([`Hypeerlog::dump_len`]: $2^{\text{precision}}$ plus at most 30 header bytes).
     pub fn dump_to_slice(&self, buf: &mut [u8]) -> Result<usize, HypeerlogError> {
-        let expected_len = self.registers.len() + 1;
+        let prefix = self.dump_prefix();
+        let prefix_len = prefix.as_bytes().len();
+        let expected_len = prefix_len + self.dump_payload().len();
         if buf.len() < expected_len {
             return Err(HypeerlogError::InvalidLength);
         }
 
-        buf[..self.registers.len()].copy_from_slice(&self.registers);
-        buf[self.registers.len()] = self.precision;
+        buf[..prefix_len].copy_from_slice(prefix.as_bytes());
+        buf[prefix_len..expected_len].copy_from_slice(self.dump_payload());
         
         Ok(expected_len)
     }
+
+    // The prefix of the dumps of the sketch, which announces the empty compact payload of all-zero registers
+    fn dump_prefix(&self) -> dump::DumpPrefix {
+        let mut prefix = self.dense_dump_prefix();
+        if self.is_empty() {
+            prefix.set_flag(dump::FLAG_COMPACT);
+        }
+        prefix
+    }
+
+    // The prefix of a dump followed by all the dense registers, allocated or not
+    pub(crate) fn dense_dump_prefix(&self) -> dump::DumpPrefix {
+        dump::DumpPrefix::new(self.precision, dump::StoredHasher::of(&self.hasher), self.insertions, self.registers.checksum())
+    }
+
+    // The registers following `dump_prefix`: the dense ones, or none for all-zero ones
+    fn dump_payload(&self) -> &[u8] {
+        if self.is_empty() { &[] } else { self.registers.values().unwrap_or_default() }
+    }
 }
 
 
@@ -414,10 +1321,61 @@ impl Hypeerlog {
         Hypeerlog {
             hasher: Murmur3BuildHasher::new(0),
             precision: p,
-            registers: vec![0; pow_two(p) as usize],
+            registers: RegisterArray::zeroed(pow_two(p) as usize),
+            insertions: 0,
         }
     }
 
+    /// Builds a sketch of the given precision from the items of an iterator, like collecting them builds one of
+    /// precision 14.
+    ///
+    /// The precision value is silently clamped to `4..=25`, like [`Hypeerlog::with_precision`]. The items are
+    /// inserted with [`Hypeerlog::insert_iter`], and the size of the sketch does not depend on how many there are. For a custom
+    /// hasher, see [`Hypeerlog::from_iter_with_hasher_precision`].
+    ///
+    /// ```
+    /// use hypeerlog::Hypeerlog;
+    ///
+    /// let hll = Hypeerlog::from_iter_with_precision(10, ["a", "b", "a"]);
+    /// assert_eq!((hll.precision(), hll.cardinality().round()), (10, 2.0));
+    /// ```
+    pub fn from_iter_with_precision<H: Hash>(precision: u8, iter: impl IntoIterator<Item = H>) -> Hypeerlog<Murmur3BuildHasher> {
+        let mut hll = Self::with_precision(precision);
+        hll.insert_iter(iter);
+        hll
+    }
+
+    /// Builds a sketch of the given precision from a slice of items, with the same registers as
+    /// [`Hypeerlog::from_iter_with_precision`] on them.
+    ///
+    /// The precision value is silently clamped to `4..=25`. The items are inserted with [`Hypeerlog::insert_many`],
+    /// and the slice is only borrowed.
+    ///
+    /// ```
+    /// use hypeerlog::Hypeerlog;
+    ///
+    /// let names = vec![String::from("ada"), String::from("grace"), String::from("ada")];
+    /// let hll = Hypeerlog::from_slice_with_precision(12, &names);
+    /// assert_eq!((hll.precision(), hll.cardinality().round()), (12, 2.0));
+    /// ```
+    pub fn from_slice_with_precision<H: Hash>(precision: u8, data: &[H]) -> Hypeerlog<Murmur3BuildHasher> {
+        let mut hll = Self::with_precision(precision);
+        hll.insert_many(data);
+        hll
+    }
+
+    /// Builds a sketch of the given precision from hashes computed outside of it, see [`Hypeerlog::insert_hashed`].
+    ///
+    /// The precision value is silently clamped to `4..=25`. The sketch has the default `Murmur3BuildHasher`,
+    /// which is only used if items are later inserted with [`Hypeerlog::insert`].
+    pub fn from_hashes(precision: u8, hashes: impl IntoIterator<Item = u64>) -> Hypeerlog<Murmur3BuildHasher> {
+        let mut hll = Self::with_precision(precision);
+        for hash in hashes {
+            hll.insert_hashed(hash);
+        }
+        hll
+    }
+
     /// Creates a new instance with a given relative error bound using the default `Murmur3BuildHasher`.
     ///
     /// # Panics
@@ -428,19 +1386,22 @@ impl Hypeerlog {
         Hypeerlog {
             hasher: Murmur3BuildHasher::new(0),
             precision: p,
-            registers: vec![0; pow_two(p) as usize],
+            registers: RegisterArray::zeroed(pow_two(p) as usize),
+            insertions: 0,
         }
     }
 
     /// Constructs a new instance with a custom seed for the internal `Murmur3BuildHasher`.
     ///
-    /// Providing a randomized or unexpected seed can protect the structure against Hash DoS attacks
-    /// when managing inputs from untrusted external users.
+    /// Different seeds give independent sketches of the same elements. A secret seed is not a defense against
+    /// crafted inputs from untrusted users, as a 32-bit Murmur3 seed can be recovered from observed estimates;
+    /// use a keyed hasher such as `SipBuildHasher` (with the `siphash` feature) for those.
     pub fn with_seed(seed: u32) -> Hypeerlog<Murmur3BuildHasher> {
         Hypeerlog {
             hasher: Murmur3BuildHasher::new(seed),
             precision: 14,
-            registers: vec![0; pow_two(14) as usize],
+            registers: RegisterArray::zeroed(pow_two(14) as usize),
+            insertions: 0,
         }
     }
 
@@ -452,7 +1413,8 @@ impl Hypeerlog {
         Hypeerlog {
             hasher: Murmur3BuildHasher::new(seed),
             precision: p,
-            registers: vec![0; pow_two(p) as usize],
+            registers: RegisterArray::zeroed(pow_two(p) as usize),
+            insertions: 0,
         }
     }
 
@@ -466,61 +1428,277 @@ impl Hypeerlog {
         Hypeerlog {
             hasher: Murmur3BuildHasher::new(seed),
             precision: p,
-            registers: vec![0; pow_two(p) as usize],
+            registers: RegisterArray::zeroed(pow_two(p) as usize),
+            insertions: 0,
         }
     }
 
     /// Deserializes a dumped `Hypeerlog` state vector using the default `Murmur3BuildHasher`.
     ///
+    /// Both the versioned format written by [`Hypeerlog::dump`] and the legacy format of previous
+    /// releases are accepted. The hasher is restored with the seed recorded in the dump, so a sketch
+    /// built with [`Hypeerlog::with_seed`] keeps hashing the same way after a round-trip. Legacy dumps
+    /// carry no seed and are loaded with seed 0.
+    ///
     /// # Errors
     ///
-    /// Returns a [`HypeerlogError`] if the data payload contains mismatched lengths or invalid precision settings.
-    pub fn load(mut bytes: Vec<u8>) -> Result<Self, HypeerlogError> {
-        let p = bytes.pop().ok_or(HypeerlogError::InvalidLength)?;
-        if p < 4 || p > 25 { return Err(HypeerlogError::InvalidPrecision); }
-        if bytes.len() != (pow_two(p) as usize) {return Err(HypeerlogError::InvalidPrecision);}
-        Ok(Hypeerlog {
-            hasher: Murmur3BuildHasher::new(0),
-            precision: p,
-            registers: bytes,
-        })
+    /// Returns a [`HypeerlogError`] if the data payload is not a dump, uses an unsupported format version,
+    /// or contains mismatched lengths or invalid precision settings (see [`Hypeerlog::load_with_hasher`]).
+    /// A recorded seed that does not fit the 32-bit Murmur3 seed yields [`HypeerlogError::SeedMismatch`], and
+    /// a recorded hasher tag other than [`MURMUR3_TAG`] yields [`HypeerlogError::HasherMismatch`].
+    pub fn load(bytes: Vec<u8>) -> Result<Self, HypeerlogError> {
+        Self::from_bytes(&bytes)
+    }
+
+    /// Deserializes a dump from a borrowed slice using the default `Murmur3BuildHasher`, copying only the registers.
+    ///
+    /// This accepts exactly the same inputs as [`Hypeerlog::load`], without requiring an owned vector.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`Hypeerlog::load`].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, HypeerlogError> {
+        Self::load_checked(bytes, dump::Checksum::Verify, murmur_from_stored).map(|(hll, _)| hll)
+    }
+
+    /// Deserializes a dumped `Hypeerlog` state vector using the default `Murmur3BuildHasher`, without verifying its checksum.
+    ///
+    /// Only use this when the integrity of the bytes is already guaranteed by the transport or storage layer.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`Hypeerlog::load`], except that [`HypeerlogError::ChecksumMismatch`] is never returned.
+    pub fn load_unchecked(bytes: Vec<u8>) -> Result<Self, HypeerlogError> {
+        Self::load_checked(&bytes, dump::Checksum::Skip, murmur_from_stored).map(|(hll, _)| hll)
     }
 
     /// Deserializes a dumped `Hypeerlog` state directly from a streaming reader using the default hasher.
     ///
     /// # Errors
     ///
-    /// Returns a [`HypeerlogError`] if reading fails or if the deserialized sketch properties are corrupt.
+    /// Same as [`Hypeerlog::load_from_with_hasher`], with the hasher checks of [`Hypeerlog::load`].
     #[cfg(not(feature = "no_std"))]
-    pub fn load_from<R: std::io::Read>(mut reader: R) -> Result<Self, HypeerlogError> {
-        let mut bytes = std::vec::Vec::new();
-        reader.read_to_end(&mut bytes).map_err(|_| HypeerlogError::InvalidLength)?;
-        Self::load(bytes)
+    pub fn load_from<R: std::io::Read>(reader: R) -> Result<Self, HypeerlogError> {
+        Self::load(read_whole_dump(reader)?)
+    }
+}
+
+
+/// Estimates the number of distinct items of an iterator, with a sketch of the default precision of 14.
+///
+/// This is [`Hypeerlog::cardinality`] of [`Hypeerlog::from_iter_with_precision`]: the iterator is consumed as its
+/// items are inserted with [`Hypeerlog::insert_iter`], which never collects them, and the sketch is dropped once
+/// estimated. See [`count_distinct_with_precision`] for another precision.
+///
+/// ```
+/// let visitors = ["alice", "bob", "alice", "carol", "bob"];
+/// assert_eq!(hypeerlog::count_distinct(visitors).round(), 3.0);
+/// assert_eq!(hypeerlog::count_distinct((0..1000u64).map(|x| x % 10)).round(), 10.0);
+/// ```
+pub fn count_distinct<I>(iter: I) -> f64
+where
+    I: IntoIterator,
+    I::Item: Hash,
+{
+    Hypeerlog::from_iter_with_precision(14, iter).cardinality()
+}
+
+/// Estimates the number of distinct items of an iterator like [`count_distinct`], with a sketch of the given
+/// precision.
+///
+/// # Errors
+///
+/// Returns [`HypeerlogError::InvalidPrecision`] if `precision` is outside `4..=25`, before reading any
+/// item. Unlike the constructors, which clamp it, a one-shot count has no sketch whose precision could be
+/// checked afterwards.
+pub fn count_distinct_with_precision<I>(precision: u8, iter: I) -> Result<f64, HypeerlogError>
+where
+    I: IntoIterator,
+    I::Item: Hash,
+{
+    if !(4..=25).contains(&precision) {
+        return Err(HypeerlogError::InvalidPrecision);
+    }
+    Ok(Hypeerlog::from_iter_with_precision(precision, iter).cardinality())
+}
+
+
+// Legacy dumps keep their precision byte at the end, so the only way to accept both
+// formats from a streaming reader is to read all bytes into a temporary vector.
+#[cfg(not(feature = "no_std"))]
+fn read_whole_dump<R: std::io::Read>(reader: R) -> Result<Vec<u8>, HypeerlogError> {
+    use std::io::Read;
+
+    let mut bytes = std::vec::Vec::new();
+    reader.take(dump::MAX_DUMP_LEN as u64 + 1).read_to_end(&mut bytes)?;
+    if bytes.len() > dump::MAX_DUMP_LEN {
+        return Err(HypeerlogError::TooLarge);
+    }
+    Ok(bytes)
+}
+
+
+// The number of elements the batched inserts hash before updating their registers
+const INSERT_BATCH: usize = 256;
+
+// The precision from which the inserts of many hashable items batch their hashes. Below it, hashing dominates
+// and a batch only adds a pass over the hashes
+const MIN_BATCHED_PRECISION: u8 = 20;
+
+// Checks that two hashers map the same input to the same hash, comparing their tags first for a more precise error
+fn check_hashers<S: BuildHasher + HasherId>(a: &S, b: &S) -> Result<(), HypeerlogError> {
+    if let (Some(a), Some(b)) = (a.hasher_tag(), b.hasher_tag()) && a != b {
+        return Err(HypeerlogError::HasherMismatch);
+    }
+    if delta::fingerprint_of(a) != delta::fingerprint_of(b) {
+        return Err(HypeerlogError::SeedMismatch);
     }
+    Ok(())
+}
+
+// Rebuilds the default hasher from the seed and tag recorded in a dump
+fn murmur_from_stored(stored: dump::StoredHasher) -> Result<(Murmur3BuildHasher, HasherStatus), HypeerlogError> {
+    stored.restore(Murmur3BuildHasher::default())
 }
 
 
 // Some convinient trait implementations
 
+/// Creates a sketch like [`Hypeerlog::new`]: precision 14, and the default hasher with seed 0.
 impl Default for Hypeerlog<Murmur3BuildHasher> {
     fn default() -> Self {
         Self::new()
     }
 }
 
-impl<H: Hash, S: BuildHasher + Debug> Extend<H> for Hypeerlog<S> {
+/// Inserts every item of the iterator with [`Hypeerlog::insert_iter`].
+///
+/// References are hashable like the values they point to, so `hll.extend(ids.iter())` and `hll.extend(&ids)`
+/// insert the same as `hll.extend(ids)`.
+impl<H: Hash, S: BuildHasher + HasherId> Extend<H> for Hypeerlog<S> {
     fn extend<T: IntoIterator<Item = H>>(&mut self, iter: T) {
-        for item in iter {
-            self.insert(item);
-        }
+        self.insert_iter(iter);
     }
 }
 
+/// Builds a sketch of the default precision of 14 with the default hasher, see
+/// [`Hypeerlog::from_iter_with_precision`] for another precision.
+///
+/// ```
+/// use hypeerlog::Hypeerlog;
+///
+/// let user_ids = [17u64, 42, 17, 99];
+/// let hll: Hypeerlog = user_ids.iter().collect();
+/// assert_eq!(hll.cardinality().round(), 3.0);
+/// ```
 impl<H: Hash> FromIterator<H> for Hypeerlog<Murmur3BuildHasher> {
     fn from_iter<T: IntoIterator<Item = H>>(iter: T) -> Self {
-        let mut hll = Self::new();
-        hll.extend(iter);
-        hll
+        Self::from_iter_with_precision(14, iter)
+    }
+}
+
+/// Two sketches are equal when they have the same precision and the same registers, whatever their hasher
+/// builders and their [`Hypeerlog::total_insertions`].
+///
+/// Equality compares the observed state only: a sketch equals its dump loaded with
+/// [`Hypeerlog::load_with_hasher`] and a new instance of its builder, and the hasher does not need to be
+/// `PartialEq`. Whether two sketches hash alike is checked where it matters, by the merges and loads, which fail
+/// with [`HypeerlogError::SeedMismatch`] or [`HypeerlogError::HasherMismatch`].
+#[cfg(not(feature = "allocator-api"))]
+impl<S: BuildHasher + HasherId> PartialEq for Hypeerlog<S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.precision == other.precision && self.registers == other.registers
+    }
+}
+
+#[cfg(not(feature = "allocator-api"))]
+impl<S: BuildHasher + HasherId> Eq for Hypeerlog<S> {}
+
+// A sketch has thousands of registers, so only the alternate form lists them. The hasher is not required to be
+// `Debug`, and keyed ones should not print their keys, so it is identified by the seed and tag its dumps record
+impl_sketch! {
+    impl Debug {
+        fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+            let alternate = f.alternate();
+            let max_register = self.max_rank();
+            let mut debug = f.debug_struct("Hypeerlog");
+            debug.field("precision", &self.precision)
+                .field("hasher_seed", &self.hasher.hasher_seed())
+                .field("hasher_tag", &self.hasher.hasher_tag())
+                .field("len", &self.registers.len())
+                .field("nonzero_registers", &(self.registers.len() - self.registers.zero_registers()))
+                .field("max_register", &max_register)
+                .field("insertions", &self.insertions)
+                .field("estimate", &self.cardinality());
+            if alternate {
+                debug.field("registers", &self.registers);
+            }
+            debug.finish()
+        }
+    }
+}
+
+/// Summarizes the sketch on one line, e.g. `Hypeerlog(p=14, ~12534 distinct, ±0.8%)`: its precision, its
+/// [`Hypeerlog::count`] and its standard relative error.
+///
+/// The alternate form (`{:#}`) shortens counts from 1000 to one decimal and an SI suffix, e.g. `~1.2M distinct`.
+impl<S: BuildHasher + HasherId> core::fmt::Display for Hypeerlog<S> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "Hypeerlog(p={}, ~", self.precision)?;
+        if f.alternate() {
+            write_compact_count(f, self.count())?;
+        } else {
+            write!(f, "{}", self.count())?;
+        }
+        // Two decimals keep the errors of the precisions from 21 up, all below 0.1%, from rounding to 0.0%
+        let error = self.relative_error() * 100.0;
+        let decimals = if error < 0.1 { 2 } else { 1 };
+        write!(f, " distinct, ±{:.*}%)", decimals, error)
+    }
+}
+
+// Writes a count with at most four digits: counts from 1000 are divided by the largest power of 1000 that keeps
+// at least one digit before the decimal point, rounded half-up to one decimal, and suffixed by its SI prefix
+fn write_compact_count(f: &mut core::fmt::Formatter<'_>, count: u64) -> core::fmt::Result {
+    const PREFIXES: [&str; 6] = ["k", "M", "G", "T", "P", "E"];
+    if count < 1000 {
+        return write!(f, "{}", count);
+    }
+    // The count in tenths of the unit of `PREFIXES[unit]`, which may round up to the next unit
+    let tenths = |unit: u32| {
+        let divisor = 100 * 1000u64.pow(unit);
+        count / divisor + (count % divisor >= divisor / 2) as u64
+    };
+    let mut unit = 0;
+    while unit + 1 < PREFIXES.len() as u32 && tenths(unit) >= 10_000 {
+        unit += 1;
+    }
+    let tenths = tenths(unit);
+    write!(f, "{}.{}{}", tenths / 10, tenths % 10, PREFIXES[unit as usize])
+}
+
+/// Parses a dump with [`Hypeerlog::from_bytes`], running all of its validation.
+impl TryFrom<&[u8]> for Hypeerlog<Murmur3BuildHasher> {
+    type Error = HypeerlogError;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        Self::from_bytes(bytes)
+    }
+}
+
+/// Parses a dump with [`Hypeerlog::load`], running all of its validation.
+impl TryFrom<Vec<u8>> for Hypeerlog<Murmur3BuildHasher> {
+    type Error = HypeerlogError;
+
+    fn try_from(bytes: Vec<u8>) -> Result<Self, Self::Error> {
+        Self::load(bytes)
+    }
+}
+
+/// Serializes the sketch with [`Hypeerlog::dump`].
+impl<S: BuildHasher + HasherId> From<&Hypeerlog<S>> for Vec<u8> {
+    fn from(hll: &Hypeerlog<S>) -> Self {
+        hll.dump()
     }
 }
 
@@ -560,3 +1738,53 @@ macro_rules! hll {
 
 
 
+
+/// Asserts that the estimate of a sketch is within a number of standard errors of the true cardinality, for
+/// accuracy tests that neither flake nor miss regressions.
+///
+/// `assert_cardinality_within!(sketch, true_card, sigmas)` passes when
+/// $|estimate - truth| \le sigmas \cdot \sigma \cdot truth$, where $\sigma = 1.04 / \sqrt{2^p}$ is the standard
+/// relative error of the precision of the sketch ([`Hypeerlog::relative_error`]). An estimate falls outside 3
+/// sigmas about once in 370 trials and outside 4 about once in 16,000, so tests that check many estimates should
+/// allow 4 or more. On failure, it panics with the precision, the estimate, the truth, the relative error and
+/// the allowed bound. It works with any sketch that has `cardinality`, `precision` and `relative_error` methods,
+/// such as [`Hypeerlog`], [`PackedHypeerlog`] and [`AtomicHypeerlog`], and only an estimate of exactly 0 is
+/// within the bound of a true cardinality of 0.
+///
+/// ```
+/// use hypeerlog::{assert_cardinality_within, Hypeerlog};
+///
+/// let hll = Hypeerlog::from_iter_with_precision(12, 0..10_000u64);
+/// assert_cardinality_within!(hll, 10_000, 4);
+/// ```
+///
+/// ```should_panic
+/// use hypeerlog::{assert_cardinality_within, Hypeerlog};
+///
+/// let hll = Hypeerlog::from_iter_with_precision(12, 0..10_000u64);
+/// // Off by 20%, more than 4 sigmas of 1.6% at p=12
+/// assert_cardinality_within!(hll, 12_000, 4);
+/// ```
+#[macro_export]
+macro_rules! assert_cardinality_within {
+    ($sketch:expr, $true_card:expr, $sigmas:expr $(,)?) => {{
+        let sketch = &$sketch;
+        let truth = $true_card as f64;
+        let sigmas = $sigmas as f64;
+        let estimate = sketch.cardinality();
+        let allowed = sigmas * sketch.relative_error();
+        let error = if truth == 0.0 && estimate == 0.0 { 0.0 } else { (estimate - truth).abs() / truth };
+        // Written so that a NaN estimate fails too
+        if !(error <= allowed) {
+            panic!(
+                "cardinality estimate out of bounds: p={} estimate={:.1} truth={} relative error={:.3}% allowed={:.3}% ({} sigmas)",
+                sketch.precision(),
+                estimate,
+                truth,
+                error * 100.0,
+                allowed * 100.0,
+                sigmas,
+            );
+        }
+    }};
+}
@@ -0,0 +1,483 @@
+use core::borrow::Borrow;
+use core::fmt;
+use core::hash::{BuildHasher, Hash};
+use std::collections::HashMap;
+
+use crate::dump::StoredHasher;
+use crate::{check_hashers, HasherId, Hypeerlog, HypeerlogError, Murmur3BuildHasher, String, Vec};
+
+
+
+// Map dump layout, all integers little-endian:
+//
+// | Offset | Size | Field                                                                      |
+// |--------|------|----------------------------------------------------------------------------|
+// | 0      | 4    | magic `HLLM`                                                               |
+// | 4      | 1    | version (1)                                                                |
+// | 5      | 1    | precision                                                                  |
+// | 6      | 1    | flags: bit 0 if the hasher has a seed, bit 1 if it has a tag               |
+// | 7      | 8    | seed of the hasher, 0 without one, see `HasherId::hasher_seed`             |
+// | 15     | 4    | tag of the hasher, 0 without one, see `HasherId::hasher_tag`               |
+// | 19     | 4    | number of entries `n`                                                      |
+// | 23     | ...  | `n` entries: key length (u32), key, see `HllMapKey`, dump length (u32),    |
+// |        |      | dump, see `Hypeerlog::dump`                                                |
+//
+// The header records the configuration of the map, so that an empty map loads with it too.
+const MAGIC: [u8; 4] = *b"HLLM";
+const VERSION: u8 = 1;
+const HEADER_LEN: usize = 23;
+
+
+/// A key of an [`HllMap`] that can be written to its dump, see [`HllMap::dump`].
+///
+/// Strings are written as UTF-8, byte vectors as they are, and integers as their little-endian bytes. Implement it
+/// for other keys to dump the maps that use them.
+pub trait HllMapKey: Hash + Eq + Sized {
+    /// Appends the bytes of the key to `out`.
+    fn write_key(&self, out: &mut Vec<u8>);
+
+    /// Reads a key written by [`HllMapKey::write_key`], or returns `None` if `bytes` are not one.
+    fn read_key(bytes: &[u8]) -> Option<Self>;
+}
+
+impl HllMapKey for String {
+    fn write_key(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(self.as_bytes());
+    }
+
+    fn read_key(bytes: &[u8]) -> Option<Self> {
+        core::str::from_utf8(bytes).ok().map(String::from)
+    }
+}
+
+impl HllMapKey for Vec<u8> {
+    fn write_key(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(self);
+    }
+
+    fn read_key(bytes: &[u8]) -> Option<Self> {
+        Some(bytes.to_vec())
+    }
+}
+
+macro_rules! impl_int_key {
+    ($($int:ty),*) => {$(
+        impl HllMapKey for $int {
+            fn write_key(&self, out: &mut Vec<u8>) {
+                out.extend_from_slice(&self.to_le_bytes());
+            }
+
+            fn read_key(bytes: &[u8]) -> Option<Self> {
+                bytes.try_into().ok().map(<$int>::from_le_bytes)
+            }
+        }
+    )*};
+}
+
+impl_int_key!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
+
+
+/// A collection of sketches by key, e.g. the distinct users per country, which all share one precision and hasher.
+///
+/// The configuration is fixed when the map is created, and every sketch of the map is created with it on the
+/// first insert of its key, so the sketches of a map can always be merged with each other, and with the ones of
+/// another map of the same configuration, see [`HllMap::merge_with`]. [`HllMap::dump`] writes the whole map with its
+/// configuration.
+///
+/// ```
+/// use hypeerlog::HllMap;
+///
+/// let mut monday = HllMap::with_precision(12);
+/// monday.insert(String::from("DE"), "ada");
+/// monday.insert(String::from("DE"), "grace");
+/// monday.insert(String::from("FR"), "ada");
+///
+/// let mut tuesday = HllMap::with_precision(12);
+/// tuesday.insert(String::from("DE"), "ada");
+/// tuesday.insert(String::from("US"), "linus");
+///
+/// monday.merge_with(&tuesday).unwrap();
+/// assert_eq!(monday.cardinality("DE").map(f64::round), Some(2.0));
+/// assert_eq!(monday.cardinality("US").map(f64::round), Some(1.0));
+/// assert_eq!(monday.cardinality("IT"), None);
+///
+/// let loaded = HllMap::<String>::from_bytes(&monday.dump()).unwrap();
+/// assert_eq!(loaded, monday);
+/// ```
+#[derive(Clone)]
+pub struct HllMap<K, S = Murmur3BuildHasher>
+where
+    K: Hash + Eq,
+    S: BuildHasher + HasherId,
+{
+    precision: u8,
+    hasher: S,
+    sketches: HashMap<K, Hypeerlog<S>>,
+}
+
+
+impl<K, S> HllMap<K, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher + HasherId + Clone,
+{
+    /// Creates an empty map whose sketches use a custom hasher builder and a specific precision.
+    ///
+    /// The precision value is silently clamped to the valid range of `4..=25`.
+    pub fn with_hasher_precision(precision: u8, hasher_builder: S) -> Self {
+        HllMap { precision: precision.clamp(4, 25), hasher: hasher_builder, sketches: HashMap::new() }
+    }
+
+    /// Inserts a hashable item into the sketch of `key`, creating that sketch if the key is new.
+    ///
+    /// Returns whether the item raised a register, see [`Hypeerlog::insert`].
+    pub fn insert<H: Hash>(&mut self, key: K, data: H) -> bool {
+        self.sketch_of(key).insert(data)
+    }
+
+    /// Inserts a slice of items into the sketch of `key`, creating that sketch if the key is new, and returns the
+    /// number of items that raised a register, see [`Hypeerlog::insert_many`].
+    pub fn insert_many<H: Hash>(&mut self, key: K, data: &[H]) -> usize {
+        self.sketch_of(key).insert_many(data)
+    }
+
+    /// Merges a sketch into the sketch of `key`, or stores a copy of it if the key is new.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`HypeerlogError::PrecisionMismatch`] if the sketch has another precision than the map, or the errors
+    /// of [`Hypeerlog::merge_with`] if it was built with another seed or hasher. The map is not modified in these
+    /// cases.
+    pub fn merge_sketch(&mut self, key: K, sketch: &Hypeerlog<S>) -> Result<(), HypeerlogError> {
+        self.check_compatible(sketch.precision, &sketch.hasher)?;
+        self.sketch_of(key).merge_registers(sketch);
+        Ok(())
+    }
+
+    /// Merges another map into this one: the sketches of the keys of both maps are merged, and the ones of the keys
+    /// only in `other` are copied.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`HypeerlogError::PrecisionMismatch`] if the maps have different precisions, or
+    /// [`HypeerlogError::HasherMismatch`] or [`HypeerlogError::SeedMismatch`] if they use different hashers. The map
+    /// is not modified in these cases.
+    pub fn merge_with(&mut self, other: &HllMap<K, S>) -> Result<(), HypeerlogError>
+    where
+        K: Clone,
+    {
+        self.check_compatible(other.precision, &other.hasher)?;
+        for (key, sketch) in &other.sketches {
+            match self.sketches.get_mut(key) {
+                Some(mine) => {
+                    mine.merge_registers(sketch);
+                }
+                None => {
+                    self.sketches.insert(key.clone(), sketch.clone());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns a sketch of the union of every key, e.g. the distinct users over all the countries.
+    pub fn union(&self) -> Hypeerlog<S> {
+        let mut union = Hypeerlog::with_hasher_precision(self.precision, self.hasher.clone());
+        self.sketches.values().for_each(|sketch| { union.merge_registers(sketch); });
+        union
+    }
+
+    /// Reads a map written by [`HllMap::dump`], with a custom hasher.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`HypeerlogError::InvalidMagic`] if the data is not a map dump, [`HypeerlogError::UnsupportedVersion`]
+    /// if it was written by a newer release, [`HypeerlogError::InvalidLength`] if it is truncated,
+    /// [`HypeerlogError::InvalidPrecision`] if its precision is out of range, [`HypeerlogError::Encoding`] if a key
+    /// cannot be read by [`HllMapKey::read_key`], [`HypeerlogError::HasherMismatch`] or
+    /// [`HypeerlogError::SeedMismatch`] if the map was written with another hasher, or any error of
+    /// [`Hypeerlog::from_bytes_with_hasher`] for the dumps of its sketches, [`HypeerlogError::PrecisionMismatch`]
+    /// if one of them has another precision than the map.
+    pub fn from_bytes_with_hasher(bytes: &[u8], hasher_builder: S) -> Result<Self, HypeerlogError>
+    where
+        K: HllMapKey,
+    {
+        let parsed = parse_map(bytes)?;
+        parsed.stored.check(StoredHasher::of(&hasher_builder))?;
+        Self::from_parsed(parsed, hasher_builder)
+    }
+
+    fn from_parsed(parsed: ParsedMap<'_>, hasher: S) -> Result<Self, HypeerlogError>
+    where
+        K: HllMapKey,
+    {
+        let mut map = HllMap::with_hasher_precision(parsed.precision, hasher);
+        // Duplicate keys cannot be written, but the last one wins if they are present
+        for (key, dump) in parsed.entries {
+            let sketch = Hypeerlog::from_bytes_with_hasher(dump, map.hasher.clone())?;
+            if sketch.precision != map.precision {
+                return Err(HypeerlogError::PrecisionMismatch);
+            }
+            map.sketches.insert(K::read_key(key).ok_or(HypeerlogError::Encoding)?, sketch);
+        }
+        Ok(map)
+    }
+
+    fn sketch_of(&mut self, key: K) -> &mut Hypeerlog<S> {
+        let (precision, hasher) = (self.precision, &self.hasher);
+        self.sketches.entry(key).or_insert_with(|| Hypeerlog::with_hasher_precision(precision, hasher.clone()))
+    }
+}
+
+impl<K, S> HllMap<K, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher + HasherId,
+{
+    /// Returns the precision of the sketches.
+    pub fn precision(&self) -> u8 {
+        self.precision
+    }
+
+    /// Returns the expected standard relative error of the precision.
+    pub fn relative_error(&self) -> f64 {
+        crate::rel_error_from_p(self.precision as u32)
+    }
+
+    /// Returns the number of keys in the map.
+    pub fn len(&self) -> usize {
+        self.sketches.len()
+    }
+
+    /// Returns `true` if the map holds no key.
+    pub fn is_empty(&self) -> bool {
+        self.sketches.is_empty()
+    }
+
+    /// Returns `true` if the map holds a sketch for `key`.
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.sketches.contains_key(key)
+    }
+
+    /// Returns the sketch of `key`, if the map holds one.
+    pub fn get<Q>(&self, key: &Q) -> Option<&Hypeerlog<S>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.sketches.get(key)
+    }
+
+    /// Returns the estimated distinct element count of `key`, or `None` if the map holds no sketch for it, see
+    /// [`Hypeerlog::cardinality`].
+    pub fn cardinality<Q>(&self, key: &Q) -> Option<f64>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.sketches.get(key).map(Hypeerlog::cardinality)
+    }
+
+    /// Removes the sketch of `key` from the map and returns it, if the map held one.
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<Hypeerlog<S>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.sketches.remove(key)
+    }
+
+    /// Returns the keys with their estimated distinct element count, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, f64)> {
+        self.sketches.iter().map(|(key, sketch)| (key, sketch.cardinality()))
+    }
+
+    /// Returns the keys with their sketch, in no particular order.
+    pub fn sketches(&self) -> impl Iterator<Item = (&K, &Hypeerlog<S>)> {
+        self.sketches.iter()
+    }
+
+    /// Removes every key, keeping the configuration of the map.
+    pub fn clear(&mut self) {
+        self.sketches.clear();
+    }
+
+    /// Serializes the map: its configuration, then each key with the dump of its sketch, see [`Hypeerlog::dump`].
+    ///
+    /// The entries are written in the order of the bytes of their keys, so equal maps have equal dumps.
+    pub fn dump(&self) -> Vec<u8>
+    where
+        K: HllMapKey,
+    {
+        let mut bytes = Vec::with_capacity(HEADER_LEN);
+        bytes.extend_from_slice(&MAGIC);
+        bytes.push(VERSION);
+        bytes.push(self.precision);
+        StoredHasher::of(&self.hasher).write_header(&mut bytes);
+        bytes.extend_from_slice(&u32::try_from(self.len()).expect("a map holds fewer than 2^32 keys").to_le_bytes());
+
+        let mut entries: Vec<(Vec<u8>, &Hypeerlog<S>)> = self.sketches.iter()
+            .map(|(key, sketch)| {
+                let mut key_bytes = Vec::new();
+                key.write_key(&mut key_bytes);
+                (key_bytes, sketch)
+            })
+            .collect();
+        entries.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+        for (key, sketch) in entries {
+            put_field(&mut bytes, &key);
+            put_field(&mut bytes, &sketch.dump());
+        }
+        bytes
+    }
+
+    fn check_compatible(&self, precision: u8, hasher: &S) -> Result<(), HypeerlogError> {
+        if self.precision != precision {
+            return Err(HypeerlogError::PrecisionMismatch);
+        }
+        check_hashers(&self.hasher, hasher)
+    }
+}
+
+
+impl<K> HllMap<K, Murmur3BuildHasher>
+where
+    K: Hash + Eq,
+{
+    /// Creates an empty map whose sketches use the default `Murmur3BuildHasher` with a default precision of 14.
+    pub fn new() -> Self {
+        Self::with_precision(14)
+    }
+
+    /// Creates an empty map whose sketches have a specific precision.
+    ///
+    /// The precision value is silently clamped to the valid range of `4..=25`.
+    pub fn with_precision(precision: u8) -> Self {
+        Self::with_hasher_precision(precision, Murmur3BuildHasher::new(0))
+    }
+
+    /// Creates an empty map whose sketches have a specific precision and a custom seed for the default
+    /// `Murmur3BuildHasher`.
+    pub fn with_precision_seed(precision: u8, seed: u32) -> Self {
+        Self::with_hasher_precision(precision, Murmur3BuildHasher::new(seed))
+    }
+
+    /// Reads a map written by [`HllMap::dump`], using the default `Murmur3BuildHasher` restored from the seed
+    /// recorded in the dump.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`HllMap::from_bytes_with_hasher`].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, HypeerlogError>
+    where
+        K: HllMapKey,
+    {
+        let parsed = parse_map(bytes)?;
+        let (hasher, _) = parsed.stored.restore(Murmur3BuildHasher::default())?;
+        Self::from_parsed(parsed, hasher)
+    }
+}
+
+impl<K> Default for HllMap<K, Murmur3BuildHasher>
+where
+    K: Hash + Eq,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Two maps are equal when they have the same precision, hashers of the same seed and tag, and equal sketches for
+/// the same keys, see the equality of [`Hypeerlog`]. The hasher does not need to be `PartialEq`.
+impl<K, S> PartialEq for HllMap<K, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher + HasherId,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.precision == other.precision
+            && StoredHasher::of(&self.hasher) == StoredHasher::of(&other.hasher)
+            && self.sketches == other.sketches
+    }
+}
+
+impl<K, S> Eq for HllMap<K, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher + HasherId,
+{
+}
+
+// Summarized like the `Debug` of `Hypeerlog`, by the number of keys instead of their sketches
+impl<K, S> fmt::Debug for HllMap<K, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher + HasherId,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HllMap")
+            .field("precision", &self.precision)
+            .field("hasher_seed", &self.hasher.hasher_seed())
+            .field("hasher_tag", &self.hasher.hasher_tag())
+            .field("len", &self.sketches.len())
+            .finish()
+    }
+}
+
+
+// Sketch dumps are at most a few bytes more than 2^25 registers
+fn put_field(out: &mut Vec<u8>, field: &[u8]) {
+    let len = u32::try_from(field.len()).expect("keys and dumps are shorter than 4 GiB");
+    out.extend_from_slice(&len.to_le_bytes());
+    out.extend_from_slice(field);
+}
+
+// The configuration and the (key, dump) entries of a map dump
+struct ParsedMap<'a> {
+    precision: u8,
+    stored: StoredHasher,
+    entries: Vec<(&'a [u8], &'a [u8])>,
+}
+
+fn parse_map(bytes: &[u8]) -> Result<ParsedMap<'_>, HypeerlogError> {
+    if bytes.get(..4).is_none_or(|magic| magic != MAGIC) {
+        return Err(HypeerlogError::InvalidMagic);
+    }
+    let header = bytes.get(..HEADER_LEN).ok_or(HypeerlogError::InvalidLength)?;
+    if header[4] != VERSION {
+        return Err(HypeerlogError::UnsupportedVersion);
+    }
+    let precision = header[5];
+    if !(4..=25).contains(&precision) {
+        return Err(HypeerlogError::InvalidPrecision);
+    }
+    let stored = StoredHasher::read_header(header[6..19].try_into().expect("the hasher fields are 13 bytes"))?;
+    let count = u32::from_le_bytes(header[19..].try_into().expect("the count is 4 bytes")) as usize;
+
+    // Every entry takes at least its two lengths, which bounds the count before anything is allocated for it
+    let mut rest = &bytes[HEADER_LEN..];
+    if count > rest.len() / 8 {
+        return Err(HypeerlogError::InvalidLength);
+    }
+    let mut entries = Vec::with_capacity(count);
+    for _ in 0..count {
+        let key = take_field(&mut rest)?;
+        entries.push((key, take_field(&mut rest)?));
+    }
+    if !rest.is_empty() {
+        return Err(HypeerlogError::InvalidLength);
+    }
+    Ok(ParsedMap { precision, stored, entries })
+}
+
+fn take_field<'a>(bytes: &mut &'a [u8]) -> Result<&'a [u8], HypeerlogError> {
+    let len = bytes.get(..4).ok_or(HypeerlogError::InvalidLength)?;
+    let len = u32::from_le_bytes(len.try_into().expect("took 4 bytes")) as usize;
+    let field = bytes.get(4..4 + len).ok_or(HypeerlogError::InvalidLength)?;
+    *bytes = &bytes[4 + len..];
+    Ok(field)
+}
@@ -0,0 +1,155 @@
+use core::hash::BuildHasher;
+#[cfg(not(feature = "no_std"))]
+use core::hash::Hash;
+
+use crate::{Box, ConstHypeerlog, ConstPrecision, DecayingHypeerlog, HasherId, Hypeerlog, HypeerlogError, MultiHll, PackedHypeerlog, Precision};
+#[cfg(not(feature = "no_std"))]
+use crate::HllMap;
+
+
+
+/// A sketch with state-based CRDT union semantics.
+///
+/// Implementors guarantee that, whenever `merge_with` succeeds, merging behaves like a join on the
+/// register state:
+/// - **Commutative**: merging `b` into `a` yields the same registers as merging `a` into `b`.
+/// - **Associative**: `(a ∪ b) ∪ c` and `a ∪ (b ∪ c)` yield the same registers.
+/// - **Idempotent**: merging a sketch with itself (or re-applying the same merge) leaves the registers unchanged.
+///
+/// This lets replicas exchange and merge sketches in any order, any number of times, and still converge to the
+/// same registers, and so to the same estimates. The guarantee covers the registers only: merges add up the
+/// [`Hypeerlog::total_insertions`] of the sketches, so merging the same sketch again counts its insertions again.
+pub trait Mergeable {
+    /// Merges `other` into `self` in place.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`HypeerlogError`] if the two sketches are not compatible. `self` is left unmodified in that case.
+    fn merge_with(&mut self, other: &Self) -> Result<(), HypeerlogError>;
+}
+
+
+impl<S> Mergeable for Hypeerlog<S>
+where
+    S: BuildHasher + HasherId,
+{
+    fn merge_with(&mut self, other: &Self) -> Result<(), HypeerlogError> {
+        Hypeerlog::merge_with(self, other)
+    }
+}
+
+impl<const P: u8, S> Mergeable for ConstHypeerlog<P, S>
+where
+    Precision<P>: ConstPrecision,
+    S: BuildHasher + HasherId,
+{
+    fn merge_with(&mut self, other: &Self) -> Result<(), HypeerlogError> {
+        ConstHypeerlog::merge_with(self, other)
+    }
+}
+
+impl<S> Mergeable for PackedHypeerlog<S>
+where
+    S: BuildHasher + HasherId,
+{
+    fn merge_with(&mut self, other: &Self) -> Result<(), HypeerlogError> {
+        PackedHypeerlog::merge_with(self, other)
+    }
+}
+
+impl<S> Mergeable for MultiHll<S>
+where
+    S: BuildHasher + HasherId,
+{
+    fn merge_with(&mut self, other: &Self) -> Result<(), HypeerlogError> {
+        MultiHll::merge_with(self, other)
+    }
+}
+
+impl<S> Mergeable for DecayingHypeerlog<S>
+where
+    S: BuildHasher + HasherId + Clone,
+{
+    fn merge_with(&mut self, other: &Self) -> Result<(), HypeerlogError> {
+        DecayingHypeerlog::merge_with(self, other)
+    }
+}
+
+#[cfg(not(feature = "no_std"))]
+impl<K, S> Mergeable for HllMap<K, S>
+where
+    K: Hash + Eq + Clone,
+    S: BuildHasher + HasherId + Clone,
+{
+    fn merge_with(&mut self, other: &Self) -> Result<(), HypeerlogError> {
+        HllMap::merge_with(self, other)
+    }
+}
+
+
+/// The outcome of [`Hypeerlog::merge_with_report`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MergeReport {
+    /// Number of registers whose value increased during the merge.
+    ///
+    /// Zero means the other sketch held no information this one did not already have.
+    pub registers_updated: usize,
+    /// Cardinality estimate of the sketch before the merge.
+    pub estimate_before: f64,
+    /// Cardinality estimate of the sketch after the merge.
+    pub estimate_after: f64,
+}
+
+
+impl<S> Hypeerlog<S>
+where
+    S: BuildHasher + HasherId,
+{
+    /// Merges every borrowed sketch yielded by `others` into this one, in order.
+    ///
+    /// This is the non-consuming counterpart of folding with [`Hypeerlog::merge`], for sketches that
+    /// must stay available to the caller (e.g. living in a map).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`HypeerlogError::Incompatible`] with the position of the first incompatible sketch and the error
+    /// of merging it. Merging stops there: `self` keeps the registers of every sketch before that position,
+    /// and none of the ones after it.
+    pub fn merge_all<'a>(&mut self, others: impl IntoIterator<Item = &'a Self>) -> Result<(), HypeerlogError>
+    where
+        S: 'a,
+    {
+        for (index, other) in others.into_iter().enumerate() {
+            self.merge_with(other).map_err(|err| incompatible_at(index, err))?;
+        }
+        Ok(())
+    }
+
+    /// Merges a borrowed sketch into this one like [`Hypeerlog::merge_with`], and reports what changed.
+    ///
+    /// The before/after estimates come from the register aggregates that the merge keeps up to date, so this
+    /// costs a single pass over the registers, the same as a plain merge.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`HypeerlogError::PrecisionMismatch`] if the two sketches were initialized with
+    /// different precision thresholds. `self` is not modified in that case.
+    pub fn merge_with_report(&mut self, other: &Self) -> Result<MergeReport, HypeerlogError> {
+        self.check_compatible(other)?;
+
+        let estimate_before = self.cardinality();
+        let registers_updated = self.merge_registers(other);
+
+        Ok(MergeReport {
+            registers_updated,
+            estimate_before,
+            estimate_after: self.cardinality(),
+        })
+    }
+}
+
+
+// Attaches the position of the offending input of a sequence to the error of merging it
+pub(crate) fn incompatible_at(index: usize, err: HypeerlogError) -> HypeerlogError {
+    HypeerlogError::Incompatible { index, source: Box::new(err) }
+}
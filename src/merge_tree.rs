@@ -0,0 +1,270 @@
+use core::fmt;
+use core::hash::BuildHasher;
+use std::collections::BTreeMap;
+
+use crate::{HasherId, Hypeerlog, HypeerlogError, Murmur3BuildHasher, String};
+
+
+
+/// A tree of sketches at paths such as `["eu-west", "host-12", "partition-3"]`, whose inner nodes cache the union
+/// of the sketches below them.
+///
+/// [`MergeTree::update_leaf`] stores a sketch at a path and marks the nodes above it as stale, and a query such as
+/// [`MergeTree::cardinality_of`] recomputes only the stale nodes under the queried prefix, each from its cached
+/// children. Replacing one leaf among thousands and querying the root then recomputes one node per level, instead
+/// of merging every leaf again; [`MergeTree::recomputations`] counts the recomputed nodes.
+///
+/// All the sketches of the tree have the precision and hasher of the tree, which is checked when they are stored.
+/// A node may hold a sketch of its own as well as children, in which case its union covers both.
+///
+/// ```
+/// use hypeerlog::{Hypeerlog, MergeTree};
+///
+/// let mut tree = MergeTree::with_precision(12);
+/// tree.update_leaf(&["eu", "host-1"], Hypeerlog::from_iter_with_precision(12, 0..1000)).unwrap();
+/// tree.update_leaf(&["eu", "host-2"], Hypeerlog::from_iter_with_precision(12, 500..1500)).unwrap();
+/// tree.update_leaf(&["us", "host-1"], Hypeerlog::from_iter_with_precision(12, 0..10)).unwrap();
+///
+/// let eu = Hypeerlog::from_iter_with_precision(12, 0..1500).cardinality();
+/// assert_eq!(tree.cardinality_of(&["eu"]), Some(eu));
+/// assert_eq!(tree.cardinality_of(&[]), Some(eu));
+/// assert_eq!(tree.cardinality_of(&["asia"]), None);
+///
+/// // Only the nodes above the new leaf are recomputed by the next query
+/// let before = tree.recomputations();
+/// tree.update_leaf(&["us", "host-1"], Hypeerlog::from_iter_with_precision(12, 2000..2010)).unwrap();
+/// tree.cardinality_of(&[]);
+/// assert_eq!(tree.recomputations() - before, 2);
+/// ```
+#[derive(Clone)]
+pub struct MergeTree<S = Murmur3BuildHasher>
+where
+    S: BuildHasher + HasherId,
+{
+    // An empty sketch of the configuration of the tree
+    empty: Hypeerlog<S>,
+    root: Node<S>,
+    recomputations: u64,
+}
+
+#[derive(Clone)]
+struct Node<S>
+where
+    S: BuildHasher + HasherId,
+{
+    leaf: Option<Hypeerlog<S>>,
+    children: BTreeMap<String, Node<S>>,
+    // The union of the leaf and the children, `None` while stale; nodes without children use their leaf instead
+    union: Option<Hypeerlog<S>>,
+}
+
+
+impl<S> MergeTree<S>
+where
+    S: BuildHasher + HasherId + Clone,
+{
+    /// Creates an empty tree whose sketches use a custom hasher builder and a specific precision.
+    ///
+    /// The precision value is silently clamped to the valid range of `4..=25`.
+    pub fn with_hasher_precision(precision: u8, hasher_builder: S) -> Self {
+        MergeTree {
+            empty: Hypeerlog::with_hasher_precision(precision, hasher_builder),
+            root: Node::new(),
+            recomputations: 0,
+        }
+    }
+
+    /// Stores a sketch at `path`, replacing the sketch stored there before, and marks the nodes above it as stale.
+    ///
+    /// The nodes of the path are created as needed; the empty path stores the sketch at the root.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`HypeerlogError::PrecisionMismatch`] if the sketch has another precision than the tree, or
+    /// [`HypeerlogError::HasherMismatch`] or [`HypeerlogError::SeedMismatch`] if it uses another hasher. The tree is
+    /// not modified in these cases.
+    pub fn update_leaf(&mut self, path: &[&str], sketch: Hypeerlog<S>) -> Result<(), HypeerlogError> {
+        self.empty.check_compatible(&sketch)?;
+        let mut node = &mut self.root;
+        node.union = None;
+        for &segment in path {
+            node = node.children.entry(String::from(segment)).or_insert_with(Node::new);
+            node.union = None;
+        }
+        node.leaf = Some(sketch);
+        Ok(())
+    }
+
+    /// Returns the union of the sketches at and under `prefix`, or `None` if the tree has no node there.
+    ///
+    /// The stale nodes under `prefix` are recomputed first, see [`MergeTree`].
+    pub fn union_of(&mut self, prefix: &[&str]) -> Option<&Hypeerlog<S>> {
+        let mut node = &mut self.root;
+        for &segment in prefix {
+            node = node.children.get_mut(segment)?;
+        }
+        node.refresh(&self.empty, &mut self.recomputations);
+        Some(node.union(&self.empty))
+    }
+
+    /// Returns the estimated distinct element count of the sketches at and under `prefix`, or `None` if the tree
+    /// has no node there, see [`MergeTree::union_of`].
+    pub fn cardinality_of(&mut self, prefix: &[&str]) -> Option<f64> {
+        self.union_of(prefix).map(Hypeerlog::cardinality)
+    }
+}
+
+impl<S> MergeTree<S>
+where
+    S: BuildHasher + HasherId,
+{
+    /// Returns the precision of the sketches.
+    pub fn precision(&self) -> u8 {
+        self.empty.precision()
+    }
+
+    /// Returns the expected standard relative error of the precision.
+    pub fn relative_error(&self) -> f64 {
+        self.empty.relative_error()
+    }
+
+    /// Returns the sketch stored at `path` by [`MergeTree::update_leaf`], if any.
+    pub fn leaf(&self, path: &[&str]) -> Option<&Hypeerlog<S>> {
+        self.node(path)?.leaf.as_ref()
+    }
+
+    /// Returns `true` if the tree has a node at `prefix`, with a sketch of its own or below it.
+    pub fn contains(&self, prefix: &[&str]) -> bool {
+        self.node(prefix).is_some()
+    }
+
+    /// Returns the number of sketches stored in the tree.
+    pub fn leaf_count(&self) -> usize {
+        self.root.leaf_count()
+    }
+
+    /// Returns `true` if the tree holds no sketch.
+    pub fn is_empty(&self) -> bool {
+        self.root.leaf.is_none() && self.root.children.is_empty()
+    }
+
+    /// Removes the sketch stored at `path` and returns it, marking the nodes above it as stale.
+    ///
+    /// The nodes of the path that are left without a sketch or children are removed too.
+    pub fn remove_leaf(&mut self, path: &[&str]) -> Option<Hypeerlog<S>> {
+        self.root.remove(path)
+    }
+
+    /// Returns the number of union nodes recomputed by the queries since the tree was created.
+    pub fn recomputations(&self) -> u64 {
+        self.recomputations
+    }
+
+    fn node(&self, path: &[&str]) -> Option<&Node<S>> {
+        path.iter().try_fold(&self.root, |node, &segment| node.children.get(segment))
+    }
+}
+
+
+impl MergeTree<Murmur3BuildHasher> {
+    /// Creates an empty tree whose sketches use the default `Murmur3BuildHasher` with a default precision of 14.
+    pub fn new() -> Self {
+        Self::with_precision(14)
+    }
+
+    /// Creates an empty tree whose sketches have a specific precision.
+    ///
+    /// The precision value is silently clamped to the valid range of `4..=25`.
+    pub fn with_precision(precision: u8) -> Self {
+        Self::with_hasher_precision(precision, Murmur3BuildHasher::new(0))
+    }
+
+    /// Creates an empty tree whose sketches have a specific precision and a custom seed for the default
+    /// `Murmur3BuildHasher`.
+    pub fn with_precision_seed(precision: u8, seed: u32) -> Self {
+        Self::with_hasher_precision(precision, Murmur3BuildHasher::new(seed))
+    }
+}
+
+impl Default for MergeTree<Murmur3BuildHasher> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+
+// Summarized like the `Debug` of `Hypeerlog`, by the number of leaves instead of the nodes
+impl<S> fmt::Debug for MergeTree<S>
+where
+    S: BuildHasher + HasherId,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MergeTree")
+            .field("precision", &self.empty.precision)
+            .field("hasher_seed", &self.empty.hasher.hasher_seed())
+            .field("hasher_tag", &self.empty.hasher.hasher_tag())
+            .field("leaf_count", &self.leaf_count())
+            .field("recomputations", &self.recomputations)
+            .finish()
+    }
+}
+
+
+impl<S> Node<S>
+where
+    S: BuildHasher + HasherId,
+{
+    fn new() -> Self {
+        Node { leaf: None, children: BTreeMap::new(), union: None }
+    }
+
+    // The union of the node, which must have been refreshed
+    fn union<'a>(&'a self, empty: &'a Hypeerlog<S>) -> &'a Hypeerlog<S> {
+        if self.children.is_empty() {
+            return self.leaf.as_ref().unwrap_or(empty);
+        }
+        self.union.as_ref().expect("the node was refreshed")
+    }
+
+    fn leaf_count(&self) -> usize {
+        self.leaf.is_some() as usize + self.children.values().map(Node::leaf_count).sum::<usize>()
+    }
+
+    fn remove(&mut self, path: &[&str]) -> Option<Hypeerlog<S>> {
+        let removed = match path.split_first() {
+            None => self.leaf.take()?,
+            Some((&segment, rest)) => {
+                let child = self.children.get_mut(segment)?;
+                let removed = child.remove(rest)?;
+                if child.leaf.is_none() && child.children.is_empty() {
+                    self.children.remove(segment);
+                }
+                removed
+            }
+        };
+        self.union = None;
+        Some(removed)
+    }
+}
+
+impl<S> Node<S>
+where
+    S: BuildHasher + HasherId + Clone,
+{
+    // Recomputes the stale nodes of the subtree, each from its leaf and its children
+    fn refresh(&mut self, empty: &Hypeerlog<S>, recomputations: &mut u64) {
+        if self.children.is_empty() || self.union.is_some() {
+            return;
+        }
+        let mut union = empty.clone();
+        if let Some(leaf) = &self.leaf {
+            union.merge_registers(leaf);
+        }
+        for child in self.children.values_mut() {
+            child.refresh(empty, recomputations);
+            union.merge_registers(child.union(empty));
+        }
+        self.union = Some(union);
+        *recomputations += 1;
+    }
+}
@@ -0,0 +1,156 @@
+use core::fmt;
+use core::hash::BuildHasher;
+use core::ops::Range;
+use std::fs::File;
+use std::path::Path;
+
+use memmap2::Mmap;
+
+use crate::{murmur_from_stored, HasherId, HasherStatus, Hypeerlog, HypeerlogError, HypeerlogRef, Murmur3BuildHasher};
+use crate::dump::StoredHasher;
+use crate::view::parse_dense;
+
+
+
+/// A read-only sketch whose registers stay in a memory-mapped dump file, created by [`Hypeerlog::open_mmap`].
+///
+/// Only the pages of the file that are touched are loaded by the operating system, and the $2^p$ register bytes
+/// are never copied to the heap, so many historical sketches can be kept open at the cost of their mappings.
+/// Queries go through [`MappedHypeerlog::as_view`], which offers everything [`HypeerlogRef`] does.
+///
+/// # Safety assumptions
+///
+/// The header and checksum of the file are validated once, when it is opened. The mapping then reflects the
+/// file as it is on disk, so the file must not be modified or truncated while it is mapped: truncation makes
+/// accessing the missing pages fault (`SIGBUS` on Unix), and modification changes the sketch under the estimates.
+/// This is why [`Hypeerlog::open_mmap`] is `unsafe`; write new dumps to a new file and rename it over the old one
+/// instead of rewriting files that may be mapped.
+pub struct MappedHypeerlog<S = Murmur3BuildHasher>
+where
+    S: BuildHasher + HasherId,
+{
+    map: Mmap,
+    hasher: S,
+    precision: u8,
+    registers: Range<usize>,
+    insertions: u64,
+}
+
+
+impl<S> Hypeerlog<S>
+where
+    S: BuildHasher + HasherId,
+{
+    /// Memory-maps a dump file read-only, using a custom hasher.
+    ///
+    /// The dump is validated like [`Hypeerlog::borrow_dump_with_hasher`] does, which reads the registers once to
+    /// verify the checksum of dumps that have one.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified or truncated for as long as the returned sketch is alive, see
+    /// [`MappedHypeerlog`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`HypeerlogError::Io`] if the file cannot be opened or mapped, and any error of
+    /// [`Hypeerlog::borrow_dump_with_hasher`] if it does not hold a valid, uncompressed dump.
+    pub unsafe fn open_mmap_with_hasher(path: &Path, hasher_builder: S) -> Result<MappedHypeerlog<S>, HypeerlogError> {
+        // SAFETY: the caller guarantees that the file is not modified or truncated while mapped
+        unsafe {
+            Self::open_checked(path, |stored| stored.check(StoredHasher::of(&hasher_builder)).map(|status| (hasher_builder, status)))
+        }
+    }
+
+    unsafe fn open_checked(
+        path: &Path,
+        make_hasher: impl FnOnce(StoredHasher) -> Result<(S, HasherStatus), HypeerlogError>,
+    ) -> Result<MappedHypeerlog<S>, HypeerlogError> {
+        let file = File::open(path)?;
+        // SAFETY: the caller guarantees that the file is not modified or truncated while mapped
+        let map = unsafe { Mmap::map(&file)? };
+        let (hasher, precision, registers, insertions) = parse_dense(&map, make_hasher)?;
+        Ok(MappedHypeerlog { map, hasher, precision, registers, insertions })
+    }
+}
+
+impl Hypeerlog {
+    /// Memory-maps a dump file read-only, using the default `Murmur3BuildHasher` restored from its seed.
+    ///
+    /// ```no_run
+    /// use hypeerlog::Hypeerlog;
+    /// use std::path::Path;
+    ///
+    /// // SAFETY: dumps are written once and never modified in place
+    /// let mapped = unsafe { Hypeerlog::open_mmap(Path::new("sketches/2024-01-01.hll")) }.unwrap();
+    /// println!("{}", mapped.cardinality());
+    /// ```
+    ///
+    /// # Safety
+    ///
+    /// Same as [`Hypeerlog::open_mmap_with_hasher`].
+    ///
+    /// # Errors
+    ///
+    /// Same as [`Hypeerlog::open_mmap_with_hasher`], with the hasher checks of [`Hypeerlog::load`].
+    pub unsafe fn open_mmap(path: &Path) -> Result<MappedHypeerlog, HypeerlogError> {
+        // SAFETY: the caller guarantees that the file is not modified or truncated while mapped
+        unsafe { Self::open_checked(path, murmur_from_stored) }
+    }
+}
+
+
+impl<S> MappedHypeerlog<S>
+where
+    S: BuildHasher + HasherId + Clone,
+{
+    /// Returns a view of the mapped sketch, borrowing its registers from the mapping.
+    pub fn as_view(&self) -> HypeerlogRef<'_, S> {
+        HypeerlogRef {
+            hasher: self.hasher.clone(),
+            precision: self.precision,
+            registers: &self.map[self.registers.clone()],
+            insertions: self.insertions,
+        }
+    }
+
+    /// Returns the precision of the mapped sketch.
+    pub fn precision(&self) -> u8 {
+        self.precision
+    }
+
+    /// Estimates the number of unique elements, exactly like [`Hypeerlog::cardinality`] on the loaded sketch.
+    pub fn cardinality(&self) -> f64 {
+        self.as_view().cardinality()
+    }
+
+    /// Estimates the cardinality of the union of two mapped sketches, without materializing the merged registers.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`HypeerlogRef::union_cardinality`].
+    pub fn union_cardinality(&self, other: &MappedHypeerlog<S>) -> Result<f64, HypeerlogError> {
+        self.as_view().union_cardinality(&other.as_view())
+    }
+
+    /// Copies the mapped registers into an owned sketch, which no longer depends on the file.
+    pub fn to_hypeerlog(&self) -> Hypeerlog<S> {
+        self.as_view().to_hypeerlog()
+    }
+}
+
+
+// Summarized like the `Debug` of `Hypeerlog`, without the mapping
+impl<S> fmt::Debug for MappedHypeerlog<S>
+where
+    S: BuildHasher + HasherId,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MappedHypeerlog")
+            .field("precision", &self.precision)
+            .field("hasher_seed", &self.hasher.hasher_seed())
+            .field("hasher_tag", &self.hasher.hasher_tag())
+            .field("insertions", &self.insertions)
+            .finish()
+    }
+}
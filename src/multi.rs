@@ -0,0 +1,377 @@
+use core::fmt;
+use core::hash::{BuildHasher, Hash};
+use core::ops::{Bound, Range, RangeBounds};
+
+use crate::{check_hashers, murmur_from_stored, vec, HasherId, HypeerlogError, HypeerlogRef, Murmur3BuildHasher, Vec};
+use crate::dump::{check_ranks, StoredHasher};
+use crate::utils::{crc32, estimate_from_registers, get_bucket, longest_run, pow_two, rel_error_from_p};
+
+
+
+// Array dump layout, all integers little-endian:
+//
+// | Offset | Size        | Field                                                                |
+// |--------|-------------|----------------------------------------------------------------------|
+// | 0      | 4           | magic `HLLV`                                                         |
+// | 4      | 1           | version (1)                                                          |
+// | 5      | 1           | precision `p`                                                        |
+// | 6      | 1           | flags: bit 0 if the hasher has a seed, bit 1 if it has a tag         |
+// | 7      | 8           | seed of the hasher, 0 without one, see `HasherId::hasher_seed`       |
+// | 15     | 4           | tag of the hasher, 0 without one, see `HasherId::hasher_tag`         |
+// | 19     | 4           | number of sketches `n`                                               |
+// | 23     | 4           | CRC32 of the registers                                               |
+// | 27     | n * 2^p     | registers of the sketches, one after the other                       |
+const MAGIC: [u8; 4] = *b"HLLV";
+const VERSION: u8 = 1;
+const HEADER_LEN: usize = 27;
+
+
+/// A fixed-size array of sketches addressed by index, e.g. the distinct users of each hour of the day.
+///
+/// All the sketches share one precision and hasher, and their registers are stored one after the other in a single
+/// allocation of `len * 2^p` bytes, so that merging or dumping the whole array goes through one contiguous buffer.
+/// Inserts and estimates take the index of a sketch and fail with [`HypeerlogError::IndexOutOfBounds`] past the
+/// last one. [`MultiHll::union_range`] estimates the union of a range of sketches through a single scratch array of
+/// registers, without copying the sketches.
+///
+/// Unlike [`crate::Hypeerlog`], an estimate reads all the $2^p$ registers of its sketch.
+///
+/// ```
+/// use hypeerlog::MultiHll;
+///
+/// // Distinct users per hour of the day
+/// let mut hours = MultiHll::with_precision_seed_len(12, 0, 24);
+/// hours.insert(9, "ada").unwrap();
+/// hours.insert(10, "ada").unwrap();
+/// hours.insert(10, "grace").unwrap();
+/// hours.insert(22, "linus").unwrap();
+///
+/// assert_eq!(hours.cardinality(10).unwrap().round(), 2.0);
+/// assert_eq!(hours.union_range(9..18).unwrap().round(), 2.0);
+/// assert_eq!(hours.union_range(..).unwrap().round(), 3.0);
+/// assert!(hours.insert(24, "nobody").is_err());
+/// ```
+#[derive(Clone)]
+pub struct MultiHll<S = Murmur3BuildHasher>
+where
+    S: BuildHasher + HasherId,
+{
+    hasher: S,
+    precision: u8,
+    len: usize,
+    registers: Vec<u8>,
+}
+
+
+impl<S> MultiHll<S>
+where
+    S: BuildHasher + HasherId,
+{
+    /// Creates an array of `len` empty sketches with a custom hasher builder and a specific precision.
+    ///
+    /// The precision value is silently clamped to the valid range of `4..=25`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the registers of the sketches would not fit in memory.
+    pub fn with_hasher_precision_len(precision: u8, len: usize, hasher_builder: S) -> Self {
+        let p = precision.clamp(4, 25);
+        let registers = len.checked_mul(pow_two(p) as usize).expect("the registers of the sketches fit in memory");
+        MultiHll { hasher: hasher_builder, precision: p, len, registers: vec![0; registers] }
+    }
+
+    /// Returns the precision of the sketches.
+    pub fn precision(&self) -> u8 {
+        self.precision
+    }
+
+    /// Returns the number of sketches.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the array holds no sketch.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the expected standard relative error of the precision.
+    pub fn relative_error(&self) -> f64 {
+        rel_error_from_p(self.precision as u32)
+    }
+
+    /// Inserts a single hashable item into the sketch at `index`.
+    ///
+    /// Returns whether the item raised a register of the sketch, like [`crate::Hypeerlog::insert`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`HypeerlogError::IndexOutOfBounds`] if `index` is not below [`MultiHll::len`].
+    pub fn insert<H: Hash>(&mut self, index: usize, data: H) -> Result<bool, HypeerlogError> {
+        self.insert_hashed(index, self.hasher.hash_one(&data))
+    }
+
+    /// Inserts an item by a hash computed outside of the array into the sketch at `index`, see
+    /// [`crate::Hypeerlog::insert_hashed`].
+    ///
+    /// # Errors
+    ///
+    /// Same as [`MultiHll::insert`].
+    pub fn insert_hashed(&mut self, index: usize, hash: u64) -> Result<bool, HypeerlogError> {
+        let range = self.sketch_range(index)?;
+        Ok(raise(&mut self.registers[range], self.precision, hash))
+    }
+
+    /// Inserts a slice of items into the sketch at `index`, and returns the number of items that raised a register.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`MultiHll::insert`], in which case no item is inserted.
+    pub fn insert_many<H: Hash>(&mut self, index: usize, data: &[H]) -> Result<usize, HypeerlogError> {
+        let range = self.sketch_range(index)?;
+        let registers = &mut self.registers[range];
+        Ok(data.iter().map(|item| raise(registers, self.precision, self.hasher.hash_one(item)) as usize).sum())
+    }
+
+    /// Returns the estimated distinct element count of the sketch at `index`, see [`crate::Hypeerlog::cardinality`].
+    ///
+    /// # Errors
+    ///
+    /// Same as [`MultiHll::insert`].
+    pub fn cardinality(&self, index: usize) -> Result<f64, HypeerlogError> {
+        Ok(estimate_from_registers(self.precision, &self.registers[self.sketch_range(index)?]))
+    }
+
+    /// Returns the estimated distinct element count of the union of the sketches in `range`, e.g. `9..18` for the
+    /// working hours, 0 for an empty range.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`HypeerlogError::IndexOutOfBounds`] if the range ends past [`MultiHll::len`] or before it starts.
+    pub fn union_range(&self, range: impl RangeBounds<usize>) -> Result<f64, HypeerlogError> {
+        let Range { start, end } = self.resolve(range)?;
+        let m = pow_two(self.precision) as usize;
+        let mut union = vec![0; m];
+        for sketch in self.registers[start * m..end * m].chunks_exact(m) {
+            union.iter_mut().zip(sketch).for_each(|(register, &value)| *register = (*register).max(value));
+        }
+        Ok(estimate_from_registers(self.precision, &union))
+    }
+
+    /// Borrows the sketch at `index` as a read-only sketch, e.g. to merge it into a [`crate::Hypeerlog`].
+    ///
+    /// # Errors
+    ///
+    /// Same as [`MultiHll::insert`].
+    pub fn sketch(&self, index: usize) -> Result<HypeerlogRef<'_, S>, HypeerlogError>
+    where
+        S: Clone,
+    {
+        Ok(HypeerlogRef {
+            hasher: self.hasher.clone(),
+            precision: self.precision,
+            registers: &self.registers[self.sketch_range(index)?],
+            insertions: 0,
+        })
+    }
+
+    /// Merges another array into this one, sketch by sketch.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`HypeerlogError::LengthMismatch`] if the arrays have different lengths,
+    /// [`HypeerlogError::PrecisionMismatch`] if they have different precisions, or [`HypeerlogError::HasherMismatch`]
+    /// or [`HypeerlogError::SeedMismatch`] if they use different hashers. `self` is not modified in these cases.
+    pub fn merge_with(&mut self, other: &Self) -> Result<(), HypeerlogError> {
+        if self.len != other.len {
+            return Err(HypeerlogError::LengthMismatch);
+        }
+        if self.precision != other.precision {
+            return Err(HypeerlogError::PrecisionMismatch);
+        }
+        check_hashers(&self.hasher, &other.hasher)?;
+        self.registers.iter_mut().zip(&other.registers).for_each(|(register, &value)| *register = (*register).max(value));
+        Ok(())
+    }
+
+    /// Resets every sketch back to empty.
+    pub fn clear(&mut self) {
+        self.registers.fill(0);
+    }
+
+    /// Serializes the array: its configuration, then the registers of all its sketches.
+    pub fn dump(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_LEN + self.registers.len());
+        bytes.extend_from_slice(&MAGIC);
+        bytes.push(VERSION);
+        bytes.push(self.precision);
+        StoredHasher::of(&self.hasher).write_header(&mut bytes);
+        bytes.extend_from_slice(&u32::try_from(self.len).expect("an array holds fewer than 2^32 sketches").to_le_bytes());
+        bytes.extend_from_slice(&crc32(&self.registers).to_le_bytes());
+        bytes.extend_from_slice(&self.registers);
+        bytes
+    }
+
+    /// Reads an array written by [`MultiHll::dump`], with a custom hasher.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`HypeerlogError::InvalidMagic`] if the data is not an array dump,
+    /// [`HypeerlogError::UnsupportedVersion`] if it was written by a newer release, [`HypeerlogError::UnsupportedFlags`]
+    /// if its header sets unknown flags, [`HypeerlogError::InvalidPrecision`] if its precision is out of range,
+    /// [`HypeerlogError::InvalidLength`] if it does not hold the registers of exactly its number of sketches,
+    /// [`HypeerlogError::ChecksumMismatch`] if they were corrupted, [`HypeerlogError::InvalidRegisterValue`] if one of
+    /// them is out of range, or [`HypeerlogError::HasherMismatch`] or [`HypeerlogError::SeedMismatch`] if the array
+    /// was written with another hasher.
+    pub fn from_bytes_with_hasher(bytes: &[u8], hasher_builder: S) -> Result<Self, HypeerlogError> {
+        let parsed = parse_array(bytes)?;
+        parsed.stored.check(StoredHasher::of(&hasher_builder))?;
+        Ok(parsed.into_array(bytes, hasher_builder))
+    }
+
+    fn sketch_range(&self, index: usize) -> Result<Range<usize>, HypeerlogError> {
+        if index >= self.len {
+            return Err(HypeerlogError::IndexOutOfBounds { index, len: self.len });
+        }
+        let m = pow_two(self.precision) as usize;
+        Ok(index * m..(index + 1) * m)
+    }
+
+    fn resolve(&self, range: impl RangeBounds<usize>) -> Result<Range<usize>, HypeerlogError> {
+        let len = self.len;
+        let start = match range.start_bound() {
+            Bound::Included(&start) => start,
+            Bound::Excluded(&start) => start.checked_add(1).ok_or(HypeerlogError::IndexOutOfBounds { index: start, len })?,
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&end) => end.checked_add(1).ok_or(HypeerlogError::IndexOutOfBounds { index: end, len })?,
+            Bound::Excluded(&end) => end,
+            Bound::Unbounded => len,
+        };
+        if end > len {
+            return Err(HypeerlogError::IndexOutOfBounds { index: end, len });
+        }
+        if start > end {
+            return Err(HypeerlogError::IndexOutOfBounds { index: start, len });
+        }
+        Ok(start..end)
+    }
+}
+
+
+impl MultiHll<Murmur3BuildHasher> {
+    /// Creates an array of `len` empty sketches using the default `Murmur3BuildHasher` with a default precision
+    /// of 14.
+    pub fn with_len(len: usize) -> Self {
+        Self::with_precision_len(14, len)
+    }
+
+    /// Creates an array of `len` empty sketches with a specific precision.
+    ///
+    /// The precision value is silently clamped to the valid range of `4..=25`.
+    pub fn with_precision_len(precision: u8, len: usize) -> Self {
+        Self::with_hasher_precision_len(precision, len, Murmur3BuildHasher::new(0))
+    }
+
+    /// Creates an array of `len` empty sketches with a specific precision and a custom seed for the default
+    /// `Murmur3BuildHasher`.
+    pub fn with_precision_seed_len(precision: u8, seed: u32, len: usize) -> Self {
+        Self::with_hasher_precision_len(precision, len, Murmur3BuildHasher::new(seed))
+    }
+
+    /// Reads an array written by [`MultiHll::dump`], using the default `Murmur3BuildHasher` restored from the seed
+    /// recorded in the dump.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`MultiHll::from_bytes_with_hasher`].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, HypeerlogError> {
+        let parsed = parse_array(bytes)?;
+        let (hasher, _) = murmur_from_stored(parsed.stored)?;
+        Ok(parsed.into_array(bytes, hasher))
+    }
+}
+
+/// Two arrays are equal when they have the same precision, hashers of the same seed and tag, and the same registers
+/// in the same number of sketches. The hasher does not need to be `PartialEq`.
+impl<S: BuildHasher + HasherId> PartialEq for MultiHll<S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.precision == other.precision
+            && self.len == other.len
+            && StoredHasher::of(&self.hasher) == StoredHasher::of(&other.hasher)
+            && self.registers == other.registers
+    }
+}
+
+impl<S: BuildHasher + HasherId> Eq for MultiHll<S> {}
+
+// Summarized like the `Debug` of `Hypeerlog`, by the number of sketches instead of their registers
+impl<S> fmt::Debug for MultiHll<S>
+where
+    S: BuildHasher + HasherId,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MultiHll")
+            .field("precision", &self.precision)
+            .field("hasher_seed", &self.hasher.hasher_seed())
+            .field("hasher_tag", &self.hasher.hasher_tag())
+            .field("len", &self.len)
+            .finish()
+    }
+}
+
+
+// Raises the register of `hash` in the registers of one sketch, returning whether it was raised
+fn raise(registers: &mut [u8], precision: u8, hash: u64) -> bool {
+    let register = &mut registers[get_bucket(precision, hash)];
+    let rank = longest_run(precision, hash);
+    let raised = rank > *register;
+    *register = (*register).max(rank);
+    raised
+}
+
+// The configuration of a validated array dump, whose registers follow its header
+struct ParsedArray {
+    precision: u8,
+    stored: StoredHasher,
+    len: usize,
+}
+
+impl ParsedArray {
+    fn into_array<S: BuildHasher + HasherId>(self, bytes: &[u8], hasher: S) -> MultiHll<S> {
+        MultiHll { hasher, precision: self.precision, len: self.len, registers: bytes[HEADER_LEN..].to_vec() }
+    }
+}
+
+fn parse_array(bytes: &[u8]) -> Result<ParsedArray, HypeerlogError> {
+    if bytes.get(..4).is_none_or(|magic| magic != MAGIC) {
+        return Err(HypeerlogError::InvalidMagic);
+    }
+    let header = bytes.get(..HEADER_LEN).ok_or(HypeerlogError::InvalidLength)?;
+    if header[4] != VERSION {
+        return Err(HypeerlogError::UnsupportedVersion);
+    }
+    let precision = header[5];
+    if !(4..=25).contains(&precision) {
+        return Err(HypeerlogError::InvalidPrecision);
+    }
+    let stored = StoredHasher::read_header(header[6..19].try_into().expect("the hasher fields are 13 bytes"))?;
+    let len = u32::from_le_bytes(header[19..23].try_into().expect("the length is 4 bytes")) as usize;
+    let expected = u32::from_le_bytes(header[23..].try_into().expect("the checksum is 4 bytes"));
+
+    let registers = &bytes[HEADER_LEN..];
+    if len.checked_mul(pow_two(precision) as usize) != Some(registers.len()) {
+        return Err(HypeerlogError::InvalidLength);
+    }
+    let got = crc32(registers);
+    if got != expected {
+        return Err(HypeerlogError::ChecksumMismatch { expected, got });
+    }
+    check_ranks(precision, registers)?;
+    Ok(ParsedArray {
+        precision,
+        stored,
+        len,
+    })
+}
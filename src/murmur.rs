@@ -1,9 +1,56 @@
 use core::hash::{BuildHasher, Hasher};
 
+use crate::utils::write_integers_le;
+
 
 const C1: u32 = 0xcc9e2d51;
 const C2: u32 = 0x1b873593;
 
+/// The streaming MurmurHash3 (x86_32 variant) hasher built by [`Murmur3BuildHasher`], the default hasher of
+/// [`Hypeerlog`](crate::Hypeerlog).
+///
+/// Bytes written across several [`Hasher::write`] calls hash exactly like the same bytes written at once, so
+/// feeding a value in pieces does not change its hash. The hash of the written bytes is the canonical
+/// MurmurHash3_x86_32, e.g. `mmh3.hash(data, seed, signed=False)` in Python; to reproduce the hash of a
+/// value inserted with [`Hypeerlog::insert`](crate::Hypeerlog::insert), hash the bytes of its encoding below. Integers are written as their little-endian bytes, with
+/// `usize` and `isize` widened to 64 bits, so hashes are the same on every platform.
+///
+/// [`Hasher::finish`] is a pure snapshot that does not reset or consume the state: calling it again returns the
+/// same hash, and writing more bytes after it continues the stream as if `finish` had never been called, so the
+/// next `finish` returns the hash of everything written so far. The 32-bit hash is returned zero-extended in the
+/// low half of the `u64`.
+///
+/// The hasher is [`Clone`], and a clone continues from the same state: hash a common prefix once, then clone
+/// the hasher for each suffix.
+///
+/// The bytes fed to Murmur3 for a value are frozen, so that binaries built with different Rust toolchains
+/// keep producing mergeable sketches:
+/// - integers are their little-endian bytes, with `usize` and `isize` widened to 64 bits (`char` is a `u32`);
+/// - a `str` is its UTF-8 bytes followed by a `0xff` byte;
+/// - the length prefix of a slice or collection is its length as a little-endian `u64`.
+///
+/// The last two are written by `Hasher::write_str` and `Hasher::write_length_prefix`, which are still unstable.
+/// With the `nightly` feature this hasher implements them explicitly; on stable Rust their default
+/// implementations in `core` produce the same bytes, and the tests of this crate pin them.
+///
+/// ```
+/// use core::hash::{BuildHasher, Hasher};
+/// use hypeerlog::Murmur3BuildHasher;
+///
+/// let mut hasher = Murmur3BuildHasher::new(0).build_hasher();
+/// hasher.write(b"a");
+/// assert_eq!(hasher.finish(), 0x3c2569b2);
+/// hasher.write(b"bc");
+/// assert_eq!(hasher.finish(), 0xb3dd93fa); // the hash of "abc"
+///
+/// let mut branch = hasher.clone();
+/// branch.write(b"d");
+/// let mut full = Murmur3BuildHasher::new(0).build_hasher();
+/// full.write(b"abcd");
+/// assert_eq!(branch.finish(), full.finish());
+/// assert_eq!(hasher.finish(), 0xb3dd93fa); // the original is untouched
+/// ```
+#[derive(Clone)]
 pub struct Murmur3Hasher {
     h1: u32,
     tail: [u8; 4],   // Buffer for the last few bytes
@@ -55,6 +102,8 @@ impl Hasher for Murmur3Hasher {
         let ptr = bytes.as_ptr();
         while i + 4 <= bytes.len() {
             // Use read_unaligned for efficient unaligned reads
+            // SAFETY: the loop condition guarantees `i + 4 <= bytes.len()`, so the 4 bytes
+            // starting at `ptr.add(i)` are in bounds, and `read_unaligned` has no alignment requirement.
             let k1 = unsafe { (ptr.add(i) as *const u32).read_unaligned().to_le() };
 
             let mut k1 = k1.wrapping_mul(C1);
@@ -90,28 +139,101 @@ impl Hasher for Murmur3Hasher {
             final_h1 ^= k1;
         }
 
-        // Finalization mix (avalanche effect)
-        final_h1 ^= self.len as u32;
-        final_h1 ^= final_h1.wrapping_shr(16);
-        final_h1 = final_h1.wrapping_mul(0x85ebca6b);
-        final_h1 ^= final_h1.wrapping_shr(13);
-        final_h1 = final_h1.wrapping_mul(0xc2b2ae35);
-        final_h1 ^= final_h1.wrapping_shr(16);
+        fmix32(final_h1, self.len) as u64
+    }
+
+    write_integers_le!();
+
+    // The frozen encodings of strings and length prefixes, see the type documentation. On stable Rust these
+    // methods cannot be overridden, and the defaults of `Hasher` produce the same bytes.
+
+    #[cfg(feature = "nightly")]
+    fn write_str(&mut self, s: &str) {
+        self.write(s.as_bytes());
+        self.write_u8(0xff);
+    }
 
-        final_h1 as u64
+    #[cfg(feature = "nightly")]
+    fn write_length_prefix(&mut self, len: usize) {
+        self.write_usize(len);
     }
 }
 
-/// A `BuildHasher` for `Murmur3Hasher`
-#[derive(Default, Debug, Eq, PartialEq)]
+// Finalization mix (avalanche effect)
+#[inline]
+fn fmix32(mut h1: u32, len: usize) -> u32 {
+    h1 ^= len as u32;
+    h1 ^= h1.wrapping_shr(16);
+    h1 = h1.wrapping_mul(0x85ebca6b);
+    h1 ^= h1.wrapping_shr(13);
+    h1 = h1.wrapping_mul(0xc2b2ae35);
+    h1 ^= h1.wrapping_shr(16);
+    h1
+}
+
+#[inline]
+fn mix_k1(k1: u32) -> u32 {
+    k1.wrapping_mul(C1).rotate_left(15).wrapping_mul(C2)
+}
+
+// MurmurHash3_x86_32 of a whole slice at once, identical to writing it to a `Murmur3Hasher` but without
+// the bookkeeping of the incremental state
+pub(crate) fn murmur3_32(bytes: &[u8], seed: u32) -> u32 {
+    let mut h1 = seed;
+    let mut blocks = bytes.chunks_exact(4);
+    for block in &mut blocks {
+        let k1 = u32::from_le_bytes(block.try_into().expect("blocks are 4 bytes"));
+        h1 ^= mix_k1(k1);
+        h1 = h1.rotate_left(13).wrapping_mul(5).wrapping_add(0xe6546b64);
+    }
+
+    let tail = blocks.remainder();
+    if !tail.is_empty() {
+        let k1 = tail.iter().rev().fold(0u32, |acc, &b| acc << 8 | b as u32);
+        h1 ^= mix_k1(k1);
+    }
+    fmix32(h1, bytes.len())
+}
+
+// MurmurHash3_x86_32 of the little-endian bytes of each key, which is how `Murmur3Hasher` hashes a `u64`,
+// several keys at a time where the CPU allows it
+pub(crate) fn murmur3_u64s(keys: &[u64], seed: u32, hashes: &mut [u64]) {
+    debug_assert_eq!(keys.len(), hashes.len());
+    #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+    if crate::simd::avx2_available() {
+        // SAFETY: AVX2 was detected on the running CPU
+        unsafe { crate::simd::murmur3_u64s_avx2(keys, seed, hashes) };
+        return;
+    }
+    for (hash, &key) in hashes.iter_mut().zip(keys) {
+        *hash = murmur3_32(&key.to_le_bytes(), seed) as u64;
+    }
+}
+
+/// A `BuildHasher` for [`Murmur3Hasher`], seeded with a 32-bit seed.
+///
+/// This is the default hasher of [`Hypeerlog`](crate::Hypeerlog), so it can be used wherever other structures,
+/// such as a `HashMap`, must hash values exactly like a sketch with the same seed does.
+///
+/// `Murmur3BuildHasher::default()` has seed 0, the seed of [`Hypeerlog::new`](crate::Hypeerlog::new) and of every
+/// constructor that takes no seed, so `Hypeerlog::with_hasher(Default::default())` builds a sketch that merges
+/// with theirs.
+#[derive(Default, Debug, Clone, Copy, Eq, PartialEq)]
+#[cfg_attr(feature = "rkyv", derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize), rkyv(derive(Debug, PartialEq)))]
 pub struct Murmur3BuildHasher {
     seed: u32,
 }
 
 impl Murmur3BuildHasher {
-    pub fn new(seed: u32) -> Self {
+    /// Creates a builder of hashers with the given seed.
+    pub const fn new(seed: u32) -> Self {
         Murmur3BuildHasher { seed }
     }
+
+    /// Returns the seed of the built hashers.
+    pub const fn seed(&self) -> u32 {
+        self.seed
+    }
 }
 
 impl BuildHasher for Murmur3BuildHasher {
@@ -122,3 +244,81 @@ impl BuildHasher for Murmur3BuildHasher {
     }
 }
 
+
+// MurmurHash3_x64_128 needs the total length for its finalization, so the written bytes are buffered until
+// `finish`, which returns the first half of the hash
+#[cfg(any(feature = "datasketches-compat", feature = "pg-hll-compat"))]
+#[derive(Clone)]
+pub struct Murmur3x64Hasher {
+    seed: u64,
+    bytes: crate::Vec<u8>,
+}
+
+#[cfg(any(feature = "datasketches-compat", feature = "pg-hll-compat"))]
+impl Murmur3x64Hasher {
+    pub(crate) fn new(seed: u64) -> Self {
+        Murmur3x64Hasher { seed, bytes: crate::Vec::new() }
+    }
+}
+
+#[cfg(any(feature = "datasketches-compat", feature = "pg-hll-compat"))]
+impl Hasher for Murmur3x64Hasher {
+    fn write(&mut self, bytes: &[u8]) {
+        self.bytes.extend_from_slice(bytes);
+    }
+
+    fn finish(&self) -> u64 {
+        murmur3_x64_128(&self.bytes, self.seed).0
+    }
+}
+
+// MurmurHash3_x64_128 by Austin Appleby, returning the two 64-bit halves of the hash
+#[cfg(any(feature = "datasketches-compat", feature = "pg-hll-compat"))]
+pub(crate) fn murmur3_x64_128(key: &[u8], seed: u64) -> (u64, u64) {
+    const C1: u64 = 0x87c37b91114253d5;
+    const C2: u64 = 0x4cf5ad432745937f;
+
+    let mix_k1 = |k: u64| k.wrapping_mul(C1).rotate_left(31).wrapping_mul(C2);
+    let mix_k2 = |k: u64| k.wrapping_mul(C2).rotate_left(33).wrapping_mul(C1);
+
+    let (mut h1, mut h2) = (seed, seed);
+    let mut blocks = key.chunks_exact(16);
+    for block in &mut blocks {
+        let k1 = u64::from_le_bytes(block[..8].try_into().expect("blocks are 16 bytes"));
+        let k2 = u64::from_le_bytes(block[8..].try_into().expect("blocks are 16 bytes"));
+
+        h1 ^= mix_k1(k1);
+        h1 = h1.rotate_left(27).wrapping_add(h2).wrapping_mul(5).wrapping_add(0x52dce729);
+        h2 ^= mix_k2(k2);
+        h2 = h2.rotate_left(31).wrapping_add(h1).wrapping_mul(5).wrapping_add(0x38495ab5);
+    }
+
+    let tail = blocks.remainder();
+    let le_word = |bytes: &[u8]| bytes.iter().rev().fold(0u64, |acc, &b| acc << 8 | b as u64);
+    if tail.len() > 8 {
+        h2 ^= mix_k2(le_word(&tail[8..]));
+    }
+    if !tail.is_empty() {
+        h1 ^= mix_k1(le_word(&tail[..tail.len().min(8)]));
+    }
+
+    h1 ^= key.len() as u64;
+    h2 ^= key.len() as u64;
+    h1 = h1.wrapping_add(h2);
+    h2 = h2.wrapping_add(h1);
+    h1 = fmix64(h1);
+    h2 = fmix64(h2);
+    h1 = h1.wrapping_add(h2);
+    h2 = h2.wrapping_add(h1);
+    (h1, h2)
+}
+
+#[cfg(any(feature = "datasketches-compat", feature = "pg-hll-compat"))]
+fn fmix64(mut k: u64) -> u64 {
+    k ^= k >> 33;
+    k = k.wrapping_mul(0xff51afd7ed558ccd);
+    k ^= k >> 33;
+    k = k.wrapping_mul(0xc4ceb9fe1a85ec53);
+    k ^= k >> 33;
+    k
+}
@@ -0,0 +1,250 @@
+use core::hash::{BuildHasher, Hash};
+
+use rayon::prelude::*;
+
+use crate::{Box, HasherId, Hypeerlog, HypeerlogError, Murmur3BuildHasher, Vec};
+use crate::merge::incompatible_at;
+use crate::registers::RegisterArray;
+use crate::utils::get_bucket;
+
+
+// The items inserted by one rayon job; the jobs of a thread share its sketch, so this only bounds how finely
+// the work is split
+const INSERT_CHUNK: usize = 1 << 14;
+
+
+
+impl<S> Hypeerlog<S>
+where
+    S: BuildHasher + HasherId + Send + Sync,
+{
+    /// Merges a batch of sketches into one using a parallel tree reduction on the rayon thread pool.
+    ///
+    /// Union is associative and commutative, so the result is register-identical to folding the
+    /// sketches sequentially with [`Hypeerlog::merge`]. Returns `Ok(None)` for an empty batch.
+    ///
+    /// # Errors
+    ///
+    /// Every sketch is checked against the first one before any merging happens, so the error is
+    /// deterministic: [`HypeerlogError::Incompatible`] with the index of the first incompatible sketch.
+    pub fn par_merge_many(sketches: Vec<Self>) -> Result<Option<Self>, HypeerlogError> {
+        check_all_compatible(&sketches)?;
+
+        Ok(sketches.into_par_iter().reduce_with(|mut a, b| {
+            a.merge_registers(&b);
+            a
+        }))
+    }
+
+    /// Inserts a slice of items using the rayon thread pool, with the same registers as [`Hypeerlog::insert_many`].
+    ///
+    /// The slice is split across the pool, and each thread inserts its parts into a sketch of its own, with the
+    /// precision and a clone of the hasher of this one, so the threads share nothing until the end. The thread-local
+    /// sketches are then merged in a tree reduction, and the result into this sketch. Each of them takes $2^p$ bytes
+    /// while the call runs; slices too short to be split are inserted sequentially.
+    ///
+    /// Returns the number of registers of this sketch that the items raised, however the slice was split: unlike
+    /// the count of [`Hypeerlog::insert_many`], a register raised by several items counts once. It is 0 exactly
+    /// when the sketch did not change.
+    pub fn par_insert_many<H: Hash + Sync>(&mut self, data: &[H]) -> usize
+    where
+        S: Clone,
+    {
+        if data.len() <= INSERT_CHUNK || rayon::current_num_threads() == 1 {
+            // The buckets of the items that raised their register, each counted once
+            let mut raised = Vec::new();
+            for item in data {
+                let hash = self.hasher.hash_one(item);
+                if self.insert_hashed(hash) {
+                    raised.push(get_bucket(self.precision, hash));
+                }
+            }
+            raised.sort_unstable();
+            raised.dedup();
+            return raised.len();
+        }
+
+        self.par_fold(data.par_chunks(INSERT_CHUNK), |acc, chunk| {
+            acc.insert_many(chunk);
+        })
+    }
+
+    // Inserts the items of `iter` into per-thread sketches with `insert`, and merges those into this sketch,
+    // returning the number of its registers they raised
+    fn par_fold<I, F>(&mut self, iter: I, insert: F) -> usize
+    where
+        S: Clone,
+        I: ParallelIterator,
+        F: Fn(&mut Self, I::Item) + Send + Sync,
+    {
+        let empty = || Hypeerlog {
+            hasher: self.hasher.clone(),
+            precision: self.precision,
+            registers: RegisterArray::zeroed(self.len()),
+            insertions: 0,
+        };
+        // The accumulators are boxed, as rayon moves them through the fold once per item
+        let merged = iter
+            .fold(|| Box::new(empty()), |mut acc, item| {
+                insert(&mut acc, item);
+                acc
+            })
+            .reduce_with(|mut a, b| {
+                a.merge_registers(&b);
+                a
+            });
+        merged.map_or(0, |merged| self.merge_registers(&merged))
+    }
+
+    /// Merges a slice of borrowed sketches into a new one using a parallel tree reduction.
+    ///
+    /// Only one accumulator per rayon work split is cloned from the inputs (roughly one per thread),
+    /// never one per sketch. Returns `Ok(None)` for an empty slice.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`Hypeerlog::par_merge_many`].
+    pub fn par_merge_all(sketches: &[Self]) -> Result<Option<Self>, HypeerlogError>
+    where
+        S: Clone,
+    {
+        check_all_compatible(sketches)?;
+
+        Ok(sketches.par_iter()
+            .fold(|| None, |acc: Option<Self>, hll| match acc {
+                Some(mut acc) => {
+                    acc.merge_registers(hll);
+                    Some(acc)
+                }
+                None => Some(hll.clone()),
+            })
+            .flatten()
+            .reduce_with(|mut a, b| {
+                a.merge_registers(&b);
+                a
+            }))
+    }
+}
+
+
+impl Hypeerlog<Murmur3BuildHasher> {
+    /// Creates a sketch of the given precision, with the default `Murmur3BuildHasher`, from a parallel iterator.
+    ///
+    /// Each thread of the rayon pool inserts into a sketch of its own, and those are merged at the end, so the
+    /// registers are the same as inserting the items sequentially. `collect::<Hypeerlog>()` on a parallel iterator
+    /// does the same, always at precision 14.
+    ///
+    /// The precision is clamped to `4..=25` like [`Hypeerlog::with_precision`].
+    ///
+    /// ```
+    /// use hypeerlog::Hypeerlog;
+    /// use rayon::prelude::*;
+    ///
+    /// let ids: Vec<u64> = (0..100_000).map(|i| i % 1000).collect();
+    /// let hll = Hypeerlog::par_from_iter_with_precision(12, ids.par_iter());
+    /// assert_eq!(hll.precision(), 12);
+    /// assert!((hll.cardinality() - 1000.0).abs() < 50.0);
+    ///
+    /// let default: Hypeerlog = ids.par_iter().map(|id| id * 2).collect();
+    /// assert_eq!(default.precision(), 14);
+    /// ```
+    pub fn par_from_iter_with_precision<I>(precision: u8, iter: I) -> Self
+    where
+        I: IntoParallelIterator,
+        I::Item: Hash,
+    {
+        let mut hll = Self::with_precision(precision);
+        hll.par_extend(iter);
+        hll
+    }
+}
+
+
+/// Estimates the number of distinct items of a parallel iterator, with a sketch of the default precision of 14.
+///
+/// Each thread of the rayon pool inserts its items into a sketch of its own, and the result is the estimate of
+/// their union, like [`Hypeerlog::par_from_iter_with_precision`]. The registers are the same as inserting the
+/// items sequentially, so the estimate is bit-identical to [`count_distinct`](crate::count_distinct) on the same
+/// items, whatever the number of threads.
+///
+/// ```
+/// use rayon::prelude::*;
+///
+/// let ids: Vec<u64> = (0..100_000).map(|i| i % 1000).collect();
+/// let estimate = hypeerlog::count_distinct_par(ids.par_iter());
+/// assert_eq!(estimate, hypeerlog::count_distinct(&ids));
+/// ```
+pub fn count_distinct_par<I>(iter: I) -> f64
+where
+    I: IntoParallelIterator,
+    I::Item: Hash,
+{
+    Hypeerlog::par_from_iter_with_precision(14, iter).cardinality()
+}
+
+/// Estimates the number of distinct items of a rayon parallel iterator in a pipeline, with
+/// `iter.estimate_distinct(precision)`.
+///
+/// It is implemented for every parallel iterator of hashable items.
+///
+/// ```
+/// use hypeerlog::EstimateDistinct;
+/// use rayon::prelude::*;
+///
+/// let words = ["a", "b", "a", "c"];
+/// let estimate = words.par_iter().map(|word| word.to_uppercase()).estimate_distinct(12).unwrap();
+/// assert_eq!(estimate.round(), 3.0);
+/// ```
+pub trait EstimateDistinct: ParallelIterator {
+    /// Estimates the number of distinct items with a sketch of the given precision, like
+    /// [`count_distinct_par`] does at precision 14; the estimate is bit-identical to
+    /// [`count_distinct_with_precision`](crate::count_distinct_with_precision) on the same items.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`HypeerlogError::InvalidPrecision`] if `precision` is outside `4..=25`, before reading any item.
+    fn estimate_distinct(self, precision: u8) -> Result<f64, HypeerlogError>;
+}
+
+impl<I> EstimateDistinct for I
+where
+    I: ParallelIterator,
+    I::Item: Hash,
+{
+    fn estimate_distinct(self, precision: u8) -> Result<f64, HypeerlogError> {
+        if !(4..=25).contains(&precision) {
+            return Err(HypeerlogError::InvalidPrecision);
+        }
+        Ok(Hypeerlog::par_from_iter_with_precision(precision, self).cardinality())
+    }
+}
+
+
+// Each rayon job inserts into the sketch of its thread, and the sketches of the threads are merged at the end
+impl<H, S> ParallelExtend<H> for Hypeerlog<S>
+where
+    H: Hash + Send,
+    S: BuildHasher + HasherId + Clone + Send + Sync,
+{
+    fn par_extend<I: IntoParallelIterator<Item = H>>(&mut self, iter: I) {
+        self.par_fold(iter.into_par_iter(), |acc, item| {
+            acc.insert(item);
+        });
+    }
+}
+
+impl<H: Hash + Send> FromParallelIterator<H> for Hypeerlog<Murmur3BuildHasher> {
+    fn from_par_iter<I: IntoParallelIterator<Item = H>>(iter: I) -> Self {
+        Self::par_from_iter_with_precision(14, iter)
+    }
+}
+
+
+// Finds the first sketch that cannot be merged with the first one
+fn check_all_compatible<S: BuildHasher + HasherId + Sync>(sketches: &[Hypeerlog<S>]) -> Result<(), HypeerlogError> {
+    let Some(first) = sketches.first() else { return Ok(()) };
+    match sketches.par_iter().position_first(|hll| first.check_compatible(hll).is_err()) {
+        Some(index) => Err(incompatible_at(index, first.check_compatible(&sketches[index]).unwrap_err())),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,206 @@
+use core::hash::BuildHasher;
+
+use crate::{vec, HasherId, Hypeerlog, HypeerlogError, Vec};
+use crate::murmur::{murmur3_x64_128, Murmur3x64Hasher};
+use crate::registers::RegisterArray;
+
+
+
+// postgresql-hll storage format, schema version 1 (see STORAGE.markdown in citusdata/postgresql-hll):
+//
+// | byte | bits | field                                                              |
+// |------|------|--------------------------------------------------------------------|
+// | 0    | 7-4  | schema version, `1`                                                |
+// | 0    | 3-0  | type: `1` EMPTY, `2` EXPLICIT, `3` SPARSE, `4` FULL                |
+// | 1    | 7-5  | regwidth - 1                                                       |
+// | 1    | 4-0  | log2m                                                              |
+// | 2    | 6    | sparse representation enabled                                      |
+// | 2    | 5-0  | explicit threshold: `0` disabled, `63` automatic, else 2^(n - 1)   |
+// | 3    | ...  | data                                                               |
+//
+// EMPTY has no data. EXPLICIT is a sorted list of 8-byte big-endian hashed values. SPARSE is a list
+// of `(index, value)` entries of `log2m + regwidth` bits each, index first, and FULL holds the 2^log2m
+// registers of `regwidth` bits each. Both are bit-packed most significant bit first and zero-padded
+// to a whole byte.
+//
+// The bucket of a hashed value is its low log2m bits and its value one more than the trailing zeros
+// of the rest (or 0 if the rest is 0), capped at 2^regwidth - 1: the same convention as `get_bucket`
+// and `longest_run`, so registers carry over unchanged.
+
+const SCHEMA_VERSION: u8 = 1;
+const HEADER_LEN: usize = 3;
+
+const TYPE_EMPTY: u8 = 1;
+const TYPE_EXPLICIT: u8 = 2;
+const TYPE_SPARSE: u8 = 3;
+const TYPE_FULL: u8 = 4;
+
+// The default register width of the extension, and the only one supported here
+const REGWIDTH: u8 = 5;
+const MAX_VALUE: u8 = (1 << REGWIDTH) - 1;
+// The defaults of `hll_empty()`: sparse enabled and an automatic explicit threshold
+const DEFAULT_CUTOFF: u8 = 1 << 6 | 63;
+
+const MURMUR3_SEED: u64 = 0;
+
+
+/// The [`HasherId::hasher_tag`] of [`PgHllHasher`].
+pub const PG_HLL_TAG: u32 = u32::from_le_bytes(*b"PGHL");
+
+
+/// The hasher of sketches imported from the postgresql-hll extension with [`Hypeerlog::from_pg_hll`].
+///
+/// It hashes with MurmurHash3_x64_128 and seed 0, like the `hll_hash_*` functions of the extension, and carries
+/// its own [`HasherId::hasher_tag`], so a dump of an imported sketch refuses to load or merge into a sketch built
+/// with another hasher.
+///
+/// The `hll_hash_*` functions hash the binary representation of each SQL type, which [`Hypeerlog::insert`] does not
+/// reproduce. Use [`Hypeerlog::insert_pg_hll`] to add raw bytes exactly like `hll_add(h, hll_hash_bytea(b))`.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PgHllHasher;
+
+impl BuildHasher for PgHllHasher {
+    type Hasher = Murmur3x64Hasher;
+
+    fn build_hasher(&self) -> Self::Hasher {
+        Murmur3x64Hasher::new(MURMUR3_SEED)
+    }
+}
+
+impl HasherId for PgHllHasher {
+    fn hasher_tag(&self) -> Option<u32> {
+        Some(PG_HLL_TAG)
+    }
+}
+
+
+impl Hypeerlog<PgHllHasher> {
+    /// Imports a sketch stored by the postgresql-hll extension, as the raw bytes of an `hll` value (e.g. from
+    /// `hll::bytea` or a binary query result).
+    ///
+    /// All schema version 1 representations are accepted: EMPTY, EXPLICIT, SPARSE and FULL. The log2m of the sketch
+    /// becomes its precision. Only the default register width of 5 bits is supported. The extension hashes with a
+    /// different function than the default `Murmur3BuildHasher`, so the imported sketch can only be merged with other
+    /// imports or sketches filled with [`Hypeerlog::insert_pg_hll`]; its [`PgHllHasher`] makes dumps of it fail to
+    /// load or merge with [`HypeerlogError::HasherMismatch`] anywhere else.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The bytes are shorter than the header ([`HypeerlogError::InvalidMagic`]).
+    /// - The schema version is not 1 ([`HypeerlogError::UnsupportedVersion`]).
+    /// - The register width is not 5, or the type is UNDEFINED or unknown ([`HypeerlogError::UnsupportedEncoding`]).
+    /// - log2m is outside `4..=25` ([`HypeerlogError::InvalidPrecision`]).
+    /// - The data does not fit its representation, such as a FULL sketch with the wrong number of bytes or
+    ///   EXPLICIT data that is not a whole number of values ([`HypeerlogError::InvalidLength`]).
+    pub fn from_pg_hll(bytes: &[u8]) -> Result<Self, HypeerlogError> {
+        if bytes.len() < HEADER_LEN {
+            return Err(HypeerlogError::InvalidMagic);
+        }
+        if bytes[0] >> 4 != SCHEMA_VERSION {
+            return Err(HypeerlogError::UnsupportedVersion);
+        }
+        if (bytes[1] >> 5) + 1 != REGWIDTH {
+            return Err(HypeerlogError::UnsupportedEncoding);
+        }
+        let precision = bytes[1] & 0x1f;
+        if !(4..=25).contains(&precision) {
+            return Err(HypeerlogError::InvalidPrecision);
+        }
+
+        let len = 1 << precision;
+        let data = &bytes[HEADER_LEN..];
+        let mut registers = vec![0; len];
+        match bytes[0] & 0x0f {
+            TYPE_EMPTY if data.is_empty() => {}
+            TYPE_EXPLICIT if data.len().is_multiple_of(8) => {
+                for value in data.chunks_exact(8) {
+                    let hash = u64::from_be_bytes(value.try_into().expect("chunks are 8 bytes"));
+                    let (idx, val) = register_of(precision, hash);
+                    registers[idx] = registers[idx].max(val);
+                }
+            }
+            TYPE_SPARSE => {
+                let entry_bits = (precision + REGWIDTH) as usize;
+                for i in 0..data.len() * 8 / entry_bits {
+                    let entry = get_bits(data, i * entry_bits, entry_bits);
+                    let (idx, val) = ((entry >> REGWIDTH) as usize, (entry as u8) & MAX_VALUE);
+                    registers[idx] = registers[idx].max(val);
+                }
+            }
+            TYPE_FULL if data.len() == packed_len(len) => {
+                for (i, register) in registers.iter_mut().enumerate() {
+                    *register = get_bits(data, i * REGWIDTH as usize, REGWIDTH as usize) as u8;
+                }
+            }
+            TYPE_EMPTY | TYPE_EXPLICIT | TYPE_FULL => return Err(HypeerlogError::InvalidLength),
+            _ => return Err(HypeerlogError::UnsupportedEncoding),
+        }
+        // 5-bit registers never exceed the rank bound of a valid precision, so no rank check is needed
+        Ok(Hypeerlog {
+            hasher: PgHllHasher,
+            precision,
+            registers: RegisterArray::new(registers),
+            insertions: 0,
+        })
+    }
+
+    /// Exports the sketch in the FULL representation of postgresql-hll, with a register width of 5 bits and the
+    /// default sparse and explicit settings of `hll_empty()`, ready to be inserted as an `hll` value and combined
+    /// with `hll_union`.
+    ///
+    /// Registers above 31 do not fit in 5 bits and are capped, exactly like the extension caps them when adding values.
+    /// Every precision of this crate is a valid log2m, so unlike the other exports this cannot fail.
+    pub fn to_pg_hll(&self) -> Vec<u8> {
+        let mut bytes = vec![0; HEADER_LEN + packed_len(self.registers.len())];
+        bytes[0] = SCHEMA_VERSION << 4 | TYPE_FULL;
+        bytes[1] = (REGWIDTH - 1) << 5 | self.precision;
+        bytes[2] = DEFAULT_CUTOFF;
+        for (i, val) in self.registers.iter().enumerate() {
+            put_bits(&mut bytes[HEADER_LEN..], i * REGWIDTH as usize, REGWIDTH as usize, val.min(MAX_VALUE) as u64);
+        }
+        bytes
+    }
+
+    /// Adds raw bytes exactly like `hll_add(h, hll_hash_bytea(element))` does in the extension.
+    pub fn insert_pg_hll(&mut self, element: &[u8]) {
+        let (hash, _) = murmur3_x64_128(element, MURMUR3_SEED);
+        let (idx, val) = register_of(self.precision, hash);
+        self.insertions = self.insertions.saturating_add(1);
+        self.registers.raise(idx, val);
+    }
+}
+
+
+// The register index and value of a hashed value like the extension computes them, including its cap at the
+// largest 5-bit value
+fn register_of(precision: u8, hash: u64) -> (usize, u8) {
+    let idx = (hash & ((1 << precision) - 1)) as usize;
+    let rest = hash >> precision;
+    let val = if rest == 0 { 0 } else { (rest.trailing_zeros() as u8 + 1).min(MAX_VALUE) };
+    (idx, val)
+}
+
+fn packed_len(registers: usize) -> usize {
+    (registers * REGWIDTH as usize).div_ceil(8)
+}
+
+// Reads `width` (at most 57) bits starting `offset` bits into `bytes`, most significant bit first
+fn get_bits(bytes: &[u8], offset: usize, width: usize) -> u64 {
+    let (start, shift) = (offset / 8, offset % 8);
+    let mut window = 0u64;
+    for i in 0..8 {
+        window = window << 8 | *bytes.get(start + i).unwrap_or(&0) as u64;
+    }
+    (window << shift) >> (64 - width)
+}
+
+// Writes `width` bits of `value` starting `offset` bits into zeroed `bytes`, most significant bit first
+fn put_bits(bytes: &mut [u8], offset: usize, width: usize, value: u64) {
+    for bit in 0..width {
+        if value >> (width - 1 - bit) & 1 == 1 {
+            let pos = offset + bit;
+            bytes[pos / 8] |= 0x80 >> (pos % 8);
+        }
+    }
+}
@@ -0,0 +1,54 @@
+//! Strategies for the `proptest` crate generating valid sketches, enabled by the `proptest` feature.
+//!
+//! ```
+//! use hypeerlog::Hypeerlog;
+//! use hypeerlog::proptest::sketch;
+//! use proptest::prelude::*;
+//! use proptest::test_runner::TestRunner;
+//!
+//! TestRunner::default().run(&sketch(4..=10), |hll| {
+//!     prop_assert_eq!(Hypeerlog::load(hll.dump()).unwrap(), hll);
+//!     Ok(())
+//! }).unwrap();
+//! ```
+
+use core::ops::RangeInclusive;
+
+use ::proptest::collection::vec;
+use ::proptest::prelude::*;
+
+use crate::{Hypeerlog, Murmur3BuildHasher};
+use crate::registers::RegisterArray;
+use crate::utils::{max_rank, pow_two};
+
+
+
+/// Returns a strategy generating sketches whose precision is drawn from `precision`.
+///
+/// Registers are mostly empty or small, as in real sketches, with the occasional value up to the maximum
+/// rank of the precision, so generated sketches always pass the validation of [`Hypeerlog::load`]. They
+/// shrink towards empty registers. All of them use the default `Murmur3BuildHasher`, so any two with
+/// the same precision can be merged.
+///
+/// # Panics
+///
+/// Panics if `precision` is empty or reaches outside `4..=25`.
+pub fn sketch(precision: RangeInclusive<u8>) -> impl Strategy<Value = Hypeerlog> {
+    assert!(
+        precision.start() <= precision.end() && *precision.start() >= 4 && *precision.end() <= 25,
+        "sketch precisions {precision:?} are not within 4..=25",
+    );
+    precision.prop_flat_map(|p| {
+        let register = prop_oneof![
+            4 => Just(0),
+            5 => 1..=4u8,
+            1 => 0..=max_rank(p),
+        ];
+        vec(register, pow_two(p) as usize).prop_map(move |registers| Hypeerlog {
+            hasher: Murmur3BuildHasher::default(),
+            precision: p,
+            registers: RegisterArray::new(registers),
+            insertions: 0,
+        })
+    })
+}
@@ -0,0 +1,136 @@
+//! Protobuf types for exchanging sketches, enabled by the `prost` feature.
+//!
+//! [`Sketch`] mirrors the canonical `hypeerlog.v1.Sketch` message of `proto/hypeerlog.proto`, which ships with
+//! the crate so that services in other languages can generate matching types. Convert with
+//! [`Hypeerlog::to_proto`] and [`Hypeerlog::from_proto`], and encode with [`prost::Message`].
+//!
+//! ```
+//! use hypeerlog::Hypeerlog;
+//! use hypeerlog::proto::Sketch;
+//! use prost::Message;
+//!
+//! let mut hll = Hypeerlog::new();
+//! hll.insert(42);
+//!
+//! let bytes = hll.to_proto().encode_to_vec();
+//! let restored = Hypeerlog::from_proto(Sketch::decode(bytes.as_slice()).unwrap()).unwrap();
+//! assert_eq!(restored, hll);
+//! ```
+
+use core::hash::BuildHasher;
+
+use crate::{compact, HasherId, Hypeerlog, HypeerlogError, Vec};
+use crate::dump::{self, StoredHasher};
+use crate::registers::RegisterArray;
+use crate::utils::pow_two;
+
+
+
+/// The `format_version` written by [`Hypeerlog::to_proto`], and the newest one [`Hypeerlog::from_proto`] reads.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Bit of [`Sketch::flags`] marking registers in the compact run-length encoding of [`Hypeerlog::dump_compact`].
+pub const FLAG_COMPACT_REGISTERS: u32 = 0b1;
+
+
+/// The `hypeerlog.v1.Sketch` protobuf message.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Sketch {
+    /// Version of the message layout, currently [`FORMAT_VERSION`].
+    #[prost(uint32, tag = "1")]
+    pub format_version: u32,
+    /// Precision of the sketch, in `4..=25`.
+    #[prost(uint32, tag = "2")]
+    pub precision: u32,
+    /// Seed of the hasher, for hashers that have one.
+    #[prost(uint64, optional, tag = "3")]
+    pub seed: Option<u64>,
+    /// Stable identifier of the hash algorithm, see [`HasherId::hasher_tag`].
+    #[prost(fixed32, optional, tag = "4")]
+    pub hasher_tag: Option<u32>,
+    /// Encoding of the registers, see [`FLAG_COMPACT_REGISTERS`].
+    #[prost(uint32, tag = "5")]
+    pub flags: u32,
+    /// The registers, one byte each unless flagged otherwise.
+    #[prost(bytes = "vec", tag = "6")]
+    pub registers: Vec<u8>,
+}
+
+
+impl<S> Hypeerlog<S>
+where
+    S: BuildHasher + HasherId,
+{
+    /// Converts the sketch to its canonical protobuf message, with one byte per register.
+    pub fn to_proto(&self) -> Sketch {
+        let stored = StoredHasher::of(&self.hasher);
+        Sketch {
+            format_version: FORMAT_VERSION,
+            precision: self.precision as u32,
+            seed: stored.seed,
+            hasher_tag: stored.tag,
+            flags: 0,
+            registers: self.registers.to_vec(),
+        }
+    }
+
+    /// Converts a protobuf message back to a sketch, using a custom hasher.
+    ///
+    /// The message is validated like a dump is by [`Hypeerlog::load_with_hasher`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The message has a newer `format_version` ([`HypeerlogError::UnsupportedVersion`]) or sets unknown flags ([`HypeerlogError::UnsupportedFlags`]).
+    /// - The precision is outside `4..=25` ([`HypeerlogError::InvalidPrecision`]).
+    /// - The registers do not hold exactly $2^p$ values ([`HypeerlogError::InvalidLength`]), or one of them is larger
+    ///   than any rank possible at that precision ([`HypeerlogError::InvalidRegisterValue`]).
+    /// - The recorded seed or hasher tag differs from those of `hasher_builder` ([`HypeerlogError::SeedMismatch`],
+    ///   [`HypeerlogError::HasherMismatch`]).
+    pub fn from_proto_with_hasher(msg: Sketch, hasher_builder: S) -> Result<Self, HypeerlogError> {
+        Self::from_proto_checked(msg, |stored| stored.check(StoredHasher::of(&hasher_builder)).map(|_| hasher_builder))
+    }
+
+    fn from_proto_checked(msg: Sketch, make_hasher: impl FnOnce(StoredHasher) -> Result<S, HypeerlogError>) -> Result<Self, HypeerlogError> {
+        if msg.format_version > FORMAT_VERSION {
+            return Err(HypeerlogError::UnsupportedVersion);
+        }
+        if msg.flags & !FLAG_COMPACT_REGISTERS != 0 {
+            return Err(HypeerlogError::UnsupportedFlags);
+        }
+        let precision = u8::try_from(msg.precision).map_err(|_| HypeerlogError::InvalidPrecision)?;
+        if !(4..=25).contains(&precision) {
+            return Err(HypeerlogError::InvalidPrecision);
+        }
+
+        let len = pow_two(precision) as usize;
+        let registers = if msg.flags & FLAG_COMPACT_REGISTERS != 0 {
+            compact::decode(&msg.registers, len)?
+        } else {
+            msg.registers
+        };
+        if registers.len() != len {
+            return Err(HypeerlogError::InvalidLength);
+        }
+        dump::check_ranks(precision, &registers)?;
+
+        let hasher = make_hasher(StoredHasher { seed: msg.seed, tag: msg.hasher_tag })?;
+        Ok(Hypeerlog {
+            hasher,
+            precision,
+            registers: RegisterArray::new(registers),
+            insertions: 0,
+        })
+    }
+}
+
+impl Hypeerlog {
+    /// Converts a protobuf message back to a sketch, using the default `Murmur3BuildHasher` restored from its seed.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`Hypeerlog::from_proto_with_hasher`], with the hasher checks of [`Hypeerlog::load`].
+    pub fn from_proto(msg: Sketch) -> Result<Self, HypeerlogError> {
+        Self::from_proto_checked(msg, |stored| crate::murmur_from_stored(stored).map(|(hasher, _)| hasher))
+    }
+}
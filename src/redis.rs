@@ -0,0 +1,242 @@
+use core::hash::{BuildHasher, Hasher};
+
+use crate::{vec, HasherId, Hypeerlog, HypeerlogError, Vec};
+use crate::dump;
+use crate::registers::RegisterArray;
+use crate::utils::{get_bucket, longest_run};
+
+
+
+// Redis HyperLogLog string layout (see `hyperloglog.c` in the Redis sources):
+//
+// | offset | size  | field                                              |
+// |--------|-------|----------------------------------------------------|
+// | 0      | 4     | magic `b"HYLL"`                                    |
+// | 4      | 1     | encoding, `0` for dense and `1` for sparse         |
+// | 5      | 3     | unused                                             |
+// | 8      | 8     | cached cardinality, little-endian; the most        |
+// |        |       | significant bit set means the cache is stale       |
+// | 16     | ...   | registers                                          |
+//
+// Dense registers are 2^14 values of 6 bits, packed least significant bit first, for 12288 bytes.
+// Sparse registers are a stream of run-length opcodes covering all 2^14 registers in order:
+//
+// | opcode  | bits                  | registers                                    |
+// |---------|-----------------------|----------------------------------------------|
+// | `ZERO`  | `00xxxxxx`            | `xxxxxx + 1` (up to 64) zeros                |
+// | `XZERO` | `01xxxxxx yyyyyyyy`   | `xxxxxxyyyyyyyy + 1` (up to 16384) zeros     |
+// | `VAL`   | `1vvvvvxx`            | `xx + 1` (up to 4) registers of `vvvvv + 1`  |
+//
+// Redis hashes elements with MurmurHash64A, takes the bucket from the low 14 bits and the rank
+// from the trailing zeros of the remaining 50, which is exactly what `get_bucket` and `longest_run`
+// do at precision 14, so the registers carry over unchanged.
+
+const MAGIC: [u8; 4] = *b"HYLL";
+const HEADER_LEN: usize = 16;
+const ENCODING_DENSE: u8 = 0;
+const ENCODING_SPARSE: u8 = 1;
+const CARD_STALE: u8 = 0x80;
+
+const PRECISION: u8 = 14;
+const REGISTER_BITS: usize = 6;
+const REGISTER_MASK: u8 = (1 << REGISTER_BITS) - 1;
+const DENSE_LEN: usize = HEADER_LEN + (1 << PRECISION) * REGISTER_BITS / 8;
+
+const MURMUR64A_SEED: u64 = 0xadc83b19;
+
+
+/// The [`HasherId::hasher_tag`] of [`RedisHasher`].
+pub const REDIS_TAG: u32 = u32::from_le_bytes(*b"REDS");
+
+
+/// The hasher of sketches imported from Redis with [`Hypeerlog::from_redis`].
+///
+/// It hashes with MurmurHash64A and the seed Redis uses, and carries its own [`HasherId::hasher_tag`],
+/// so a dump of an imported sketch refuses to load or merge into a sketch built with another hasher.
+///
+/// Note that [`Hypeerlog::insert`] hashes an element through its [`Hash`](core::hash::Hash) implementation,
+/// which adds framing (such as length prefixes) that Redis does not, so it does not match `PFADD` of the
+/// same value. Use [`Hypeerlog::insert_redis`] to add raw elements exactly like `PFADD`.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RedisHasher;
+
+// MurmurHash64A needs the total length up front, so the written bytes are buffered until `finish`
+#[derive(Default)]
+pub struct RedisHasherState {
+    bytes: Vec<u8>,
+}
+
+impl Hasher for RedisHasherState {
+    fn write(&mut self, bytes: &[u8]) {
+        self.bytes.extend_from_slice(bytes);
+    }
+
+    fn finish(&self) -> u64 {
+        murmur64a(&self.bytes, MURMUR64A_SEED)
+    }
+}
+
+impl BuildHasher for RedisHasher {
+    type Hasher = RedisHasherState;
+
+    fn build_hasher(&self) -> Self::Hasher {
+        RedisHasherState::default()
+    }
+}
+
+impl HasherId for RedisHasher {
+    fn hasher_tag(&self) -> Option<u32> {
+        Some(REDIS_TAG)
+    }
+}
+
+
+impl Hypeerlog<RedisHasher> {
+    /// Imports a HyperLogLog created by Redis (`PFADD`, `PFMERGE`), as returned by `GET` on its key.
+    ///
+    /// Both the dense and the sparse encoding are accepted; Redis keeps small sketches sparse and converts them
+    /// to dense as they grow.
+    ///
+    /// Redis sketches always have a precision of 14. Redis hashes with a different function than the default
+    /// `Murmur3BuildHasher`, so the imported sketch can only be merged with other Redis-hashed data: other imports,
+    /// or sketches filled with [`Hypeerlog::insert_redis`]. Its [`RedisHasher`] makes dumps of it fail to load or
+    /// merge with [`HypeerlogError::HasherMismatch`] anywhere else.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The bytes do not start with the `HYLL` header ([`HypeerlogError::InvalidMagic`]).
+    /// - The sketch uses an encoding other than dense or sparse ([`HypeerlogError::UnsupportedEncoding`]).
+    /// - The bytes are not exactly as long as a dense sketch, or the opcodes of a sparse sketch are truncated
+    ///   or cover fewer than $2^{14}$ registers ([`HypeerlogError::InvalidLength`]).
+    /// - The runs of a sparse sketch reach past the last register ([`HypeerlogError::InvalidRegisterIndex`]).
+    /// - A register is larger than any rank possible at precision 14 ([`HypeerlogError::InvalidRegisterValue`]).
+    pub fn from_redis(bytes: &[u8]) -> Result<Self, HypeerlogError> {
+        if bytes.len() < HEADER_LEN || bytes[..4] != MAGIC {
+            return Err(HypeerlogError::InvalidMagic);
+        }
+        let registers = match bytes[4] {
+            ENCODING_DENSE if bytes.len() == DENSE_LEN => {
+                let packed = &bytes[HEADER_LEN..];
+                (0..1 << PRECISION).map(|i| get_packed(packed, i)).collect()
+            }
+            ENCODING_DENSE => return Err(HypeerlogError::InvalidLength),
+            ENCODING_SPARSE => decode_sparse(&bytes[HEADER_LEN..])?,
+            _ => return Err(HypeerlogError::UnsupportedEncoding),
+        };
+        dump::check_ranks(PRECISION, &registers)?;
+        Ok(Hypeerlog {
+            hasher: RedisHasher,
+            precision: PRECISION,
+            registers: RegisterArray::new(registers),
+            insertions: 0,
+        })
+    }
+
+    /// Exports the sketch in the dense Redis encoding, ready to be written to a key with `SET`.
+    ///
+    /// The sparse encoding is never produced, as Redis accepts dense sketches of any cardinality. The cached cardinality in the header is marked stale, so Redis recomputes it on the next `PFCOUNT`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`HypeerlogError::InvalidPrecision`] if the precision is not 14, the only one Redis supports.
+    pub fn to_redis(&self) -> Result<Vec<u8>, HypeerlogError> {
+        if self.precision != PRECISION {
+            return Err(HypeerlogError::InvalidPrecision);
+        }
+
+        let mut bytes = vec![0; DENSE_LEN];
+        bytes[..4].copy_from_slice(&MAGIC);
+        bytes[4] = ENCODING_DENSE;
+        bytes[HEADER_LEN - 1] = CARD_STALE;
+        let packed = &mut bytes[HEADER_LEN..];
+        for (i, val) in self.registers.iter().enumerate() {
+            set_packed(packed, i, val);
+        }
+        Ok(bytes)
+    }
+
+    /// Adds a raw element exactly like `PFADD` does in Redis, so the registers stay identical to a Redis sketch
+    /// fed the same elements.
+    pub fn insert_redis(&mut self, element: &[u8]) {
+        let hash = murmur64a(element, MURMUR64A_SEED);
+        let idx = get_bucket(self.precision, hash);
+        self.insertions = self.insertions.saturating_add(1);
+        self.registers.raise(idx, longest_run(self.precision, hash));
+    }
+}
+
+
+// Expands the opcodes of a sparse sketch, rejecting runs that would write past the last register
+fn decode_sparse(mut ops: &[u8]) -> Result<Vec<u8>, HypeerlogError> {
+    let len = 1 << PRECISION;
+    let mut registers = Vec::with_capacity(len);
+    while let Some((&op, rest)) = ops.split_first() {
+        ops = rest;
+        let (val, run) = match op >> 6 {
+            0b00 => (0, (op & 0x3f) as usize + 1),
+            0b01 => {
+                let (&low, rest) = ops.split_first().ok_or(HypeerlogError::InvalidLength)?;
+                ops = rest;
+                (0, (((op & 0x3f) as usize) << 8 | low as usize) + 1)
+            }
+            _ => (((op >> 2) & 0x1f) + 1, (op & 0x03) as usize + 1),
+        };
+        if registers.len() + run > len {
+            return Err(HypeerlogError::InvalidRegisterIndex);
+        }
+        registers.resize(registers.len() + run, val);
+    }
+    if registers.len() != len {
+        return Err(HypeerlogError::InvalidLength);
+    }
+    Ok(registers)
+}
+
+// Reads the 6-bit register `i`, which may straddle two bytes
+fn get_packed(packed: &[u8], i: usize) -> u8 {
+    let (byte, shift) = (i * REGISTER_BITS / 8, i * REGISTER_BITS % 8);
+    let lo = packed[byte] >> shift;
+    let hi = packed.get(byte + 1).map_or(0, |&b| b.checked_shl(8 - shift as u32).unwrap_or(0));
+    (lo | hi) & REGISTER_MASK
+}
+
+// Writes the 6-bit register `i` into zeroed packed storage
+fn set_packed(packed: &mut [u8], i: usize, val: u8) {
+    let (byte, shift) = (i * REGISTER_BITS / 8, i * REGISTER_BITS % 8);
+    packed[byte] |= val << shift;
+    if shift > 8 - REGISTER_BITS {
+        packed[byte + 1] |= val >> (8 - shift);
+    }
+}
+
+
+// MurmurHash64A by Austin Appleby, as used by Redis
+fn murmur64a(key: &[u8], seed: u64) -> u64 {
+    const M: u64 = 0xc6a4a7935bd1e995;
+    const R: u32 = 47;
+
+    let mut h = seed ^ (key.len() as u64).wrapping_mul(M);
+    let mut chunks = key.chunks_exact(8);
+    for chunk in &mut chunks {
+        let mut k = u64::from_le_bytes(chunk.try_into().expect("chunks are 8 bytes"));
+        k = k.wrapping_mul(M);
+        k ^= k >> R;
+        k = k.wrapping_mul(M);
+        h ^= k;
+        h = h.wrapping_mul(M);
+    }
+
+    let tail = chunks.remainder();
+    if !tail.is_empty() {
+        for (i, &b) in tail.iter().enumerate() {
+            h ^= (b as u64) << (8 * i);
+        }
+        h = h.wrapping_mul(M);
+    }
+
+    h ^= h >> R;
+    h = h.wrapping_mul(M);
+    h ^= h >> R;
+    h
+}
@@ -0,0 +1,358 @@
+use core::fmt::{self, Debug};
+use core::slice;
+
+#[cfg(feature = "allocator-api")]
+use crate::{Allocator, Global};
+use crate::{vec, Vec};
+use crate::utils::{crc32, crc32_zeros, fixed_to_f64, fixed_two_pow_neg, register_aggregates};
+
+
+
+// The registers of a sketch, together with the two aggregates the estimator needs: the number of zero
+// registers and the sum of 2^(-val) over all registers. Every write goes through `raise` or `clear`, which
+// adjust both for the registers that change, or through `raise_all`, which recomputes them once after a merge,
+// so that estimating the cardinality does not scan the registers.
+//
+// The sum is kept in fixed point, 2^(-val) scaled by 2^64 (at most 2^25 * 2^64 in total), so adding and
+// removing terms never accumulates rounding errors, and it is the same whatever order the registers changed in.
+//
+// The last estimate computed from them is cached until a register changes, and is not part of the state:
+// it is neither compared nor archived.
+//
+// An array created by `zeroed` is not allocated until a register is raised: `values` stays empty, and readers
+// see no slice of it (`values` is `None`), but zeros from `iter` and `chunks`. The aggregates of such an array
+// are those of its zero registers, so estimating its cardinality and checking whether it is empty do not read
+// the registers at all.
+//
+// With the `allocator-api` feature, the registers are allocated by `A`, which is the global allocator for all
+// the arrays built by `new` and `zeroed`.
+#[derive(Clone)]
+pub(crate) struct RegisterArray<#[cfg(feature = "allocator-api")] A: Allocator = Global> {
+    #[cfg(not(feature = "allocator-api"))]
+    values: Vec<u8>,
+    #[cfg(feature = "allocator-api")]
+    values: Vec<u8, A>,
+    len: usize,
+    zero_registers: usize,
+    harmonic_sum: u128,
+    cached_estimate: Option<f64>,
+}
+
+impl RegisterArray {
+    // Wraps the given register values, computing their aggregates from scratch
+    pub(crate) fn new(values: Vec<u8>) -> Self {
+        let (zero_registers, harmonic_sum) = register_aggregates(&values);
+        RegisterArray { len: values.len(), values, zero_registers, harmonic_sum, cached_estimate: None }
+    }
+
+    // An array of `len` zero registers (a power of two), allocated by the first `raise`
+    pub(crate) fn zeroed(len: usize) -> Self {
+        RegisterArray {
+            values: Vec::new(),
+            len,
+            zero_registers: len,
+            harmonic_sum: len as u128 * fixed_two_pow_neg(0),
+            cached_estimate: None,
+        }
+    }
+
+    pub(crate) fn into_vec(mut self) -> Vec<u8> {
+        self.allocate();
+        self.values
+    }
+}
+
+// Implements the operations of the arrays of any allocator with the `allocator-api` feature
+macro_rules! impl_registers {
+    ($(impl $($trait:ident for)? { $($body:tt)* })*) => {$(
+        #[cfg(not(feature = "allocator-api"))]
+        impl $($trait for)? RegisterArray { $($body)* }
+        #[cfg(feature = "allocator-api")]
+        impl<A: Allocator> $($trait for)? RegisterArray<A> { $($body)* }
+    )*};
+}
+
+#[cfg(feature = "allocator-api")]
+impl<A: Allocator> RegisterArray<A> {
+    // Like `zeroed`, allocating the registers from `alloc` when they are first raised
+    pub(crate) fn zeroed_in(len: usize, alloc: A) -> Self {
+        RegisterArray {
+            values: Vec::new_in(alloc),
+            len,
+            zero_registers: len,
+            harmonic_sum: len as u128 * fixed_two_pow_neg(0),
+            cached_estimate: None,
+        }
+    }
+
+    // Copies the given register values into `alloc`, computing their aggregates from scratch
+    pub(crate) fn from_slice_in(values: &[u8], alloc: A) -> Self {
+        let mut registers = Self::zeroed_in(values.len(), alloc);
+        (registers.zero_registers, registers.harmonic_sum) = register_aggregates(values);
+        registers.values.extend_from_slice(values);
+        registers
+    }
+
+    pub(crate) fn allocator(&self) -> &A {
+        self.values.allocator()
+    }
+
+    // Like `eq`, against the registers of another allocator
+    pub(crate) fn eq_in<B: Allocator>(&self, other: &RegisterArray<B>) -> bool {
+        match (self.values(), other.values()) {
+            (Some(values), Some(other)) => values == other,
+            _ => self.len == other.len && self.zero_registers == other.zero_registers && self.zero_registers == self.len,
+        }
+    }
+
+    // A copy of the registers and aggregates allocated from `alloc`, without the cached estimate
+    pub(crate) fn clone_in<B: Allocator>(&self, alloc: B) -> RegisterArray<B> {
+        let mut values = Vec::new_in(alloc);
+        values.extend_from_slice(&self.values);
+        RegisterArray {
+            values,
+            len: self.len,
+            zero_registers: self.zero_registers,
+            harmonic_sum: self.harmonic_sum,
+            cached_estimate: None,
+        }
+    }
+}
+
+impl_registers! {
+impl {
+    // Raises the register at `idx` to `val` if it is lower, returning whether it changed
+    #[inline]
+    pub(crate) fn raise(&mut self, idx: usize, val: u8) -> bool {
+        let Some(register) = self.values.get_mut(idx) else {
+            return self.raise_unallocated(idx, val);
+        };
+        let old = *register;
+        if val <= old {
+            return false;
+        }
+        *register = val;
+        self.zero_registers -= (old == 0) as usize;
+        self.harmonic_sum -= fixed_two_pow_neg(old) - fixed_two_pow_neg(val);
+        self.cached_estimate = None;
+        true
+    }
+
+    // Max-merges `other`, which must have the same length, returning the number of registers that changed.
+    //
+    // The registers are merged in a loop the compiler vectorizes, and the aggregates are recomputed once from
+    // the merged registers instead of being adjusted register by register
+    pub(crate) fn raise_all(&mut self, other: &[u8]) -> usize {
+        assert_eq!(other.len(), self.len, "register arrays of different lengths");
+        let changed = if self.is_allocated() {
+            // Counted in bytes per chunk of fewer than 256 registers, which keeps the loop in byte lanes
+            let mut changed = 0;
+            for (registers, others) in self.values.chunks_mut(128).zip(other.chunks(128)) {
+                let mut raised = 0u8;
+                for (register, &val) in registers.iter_mut().zip(others) {
+                    raised += (val > *register) as u8;
+                    *register = (*register).max(val);
+                }
+                changed += raised as usize;
+            }
+            changed
+        } else {
+            // Zero registers stay unallocated
+            let changed = other.iter().filter(|&&val| val != 0).count();
+            if changed > 0 {
+                self.values.extend_from_slice(other);
+            }
+            changed
+        };
+        if changed > 0 {
+            (self.zero_registers, self.harmonic_sum) = register_aggregates(&self.values);
+            self.cached_estimate = None;
+        }
+        changed
+    }
+
+    // Max-merges another array of the same length like `raise_all`, without reading or allocating either
+    // array while one of them is not allocated
+    pub(crate) fn union(&mut self, other: &Self) -> usize {
+        if !other.is_allocated() {
+            return 0;
+        }
+        if !self.is_allocated() {
+            // Copied into the allocation of `self`, which is where its registers come from with any allocator
+            self.values.extend_from_slice(&other.values);
+            self.zero_registers = other.zero_registers;
+            self.harmonic_sum = other.harmonic_sum;
+            self.cached_estimate = None;
+            return self.len - self.zero_registers;
+        }
+        self.raise_all(&other.values)
+    }
+
+    // Whether the registers have been allocated, i.e. raised or given by `new`, since `zeroed`
+    pub(crate) fn is_allocated(&self) -> bool {
+        !self.values.is_empty()
+    }
+
+    // The register values, or `None` while they are not allocated, in which case they are all zero
+    pub(crate) fn values(&self) -> Option<&[u8]> {
+        self.is_allocated().then_some(&*self.values)
+    }
+
+    // Iterates the register values, without reading any buffer while they are not allocated
+    pub(crate) fn iter(&self) -> Iter<'_> {
+        Iter { values: self.values.iter(), zeros: if self.is_allocated() { 0 } else { self.len } }
+    }
+
+    // The register values as consecutive slices: the registers themselves, or runs of zeros while they are
+    // not allocated, e.g. to write them out without copying
+    pub(crate) fn chunks(&self) -> impl Iterator<Item = &[u8]> {
+        let (values, zeros) = match self.values() {
+            Some(values) => (Some(values), 0),
+            None => (None, self.len),
+        };
+        let chunk = zeros.min(ZERO_CHUNK.len());
+        values.into_iter().chain((0..zeros.checked_div(chunk).unwrap_or(0)).map(move |_| &ZERO_CHUNK[..chunk]))
+    }
+
+    // The CRC32 of the register values, as recorded by dumps
+    pub(crate) fn checksum(&self) -> u32 {
+        self.values().map_or_else(|| crc32_zeros(self.len), crc32)
+    }
+
+    // A copy of the register values, zeros while they are not allocated
+    pub(crate) fn to_vec(&self) -> Vec<u8> {
+        if self.is_allocated() { self.values.to_vec() } else { vec![0; self.len] }
+    }
+
+    // Allocates the zero registers of an unallocated array, ahead of raising them
+    #[inline]
+    pub(crate) fn allocate(&mut self) {
+        if !self.is_allocated() {
+            self.allocate_zeros();
+        }
+    }
+
+    #[inline]
+    fn allocate_zeros(&mut self) {
+        // `vec!` gets memory the global allocator already zeroed, which is free for large arrays
+        #[cfg(not(feature = "allocator-api"))]
+        {
+            self.values = vec![0; self.len];
+        }
+        #[cfg(feature = "allocator-api")]
+        self.values.resize(self.len, 0);
+    }
+
+    // The first raise of an unallocated array, whose registers are all zero
+    #[inline]
+    fn raise_unallocated(&mut self, idx: usize, val: u8) -> bool {
+        assert!(idx < self.len, "register index out of bounds");
+        if val == 0 {
+            return false;
+        }
+        self.allocate_zeros();
+        self.values[idx] = val;
+        self.zero_registers -= 1;
+        self.harmonic_sum -= fixed_two_pow_neg(0) - fixed_two_pow_neg(val);
+        self.cached_estimate = None;
+        true
+    }
+
+    // The number of registers, allocated or not
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    // The bytes allocated for the register values, 0 until they are allocated
+    pub(crate) fn allocated_bytes(&self) -> usize {
+        self.values.capacity()
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.values.fill(0);
+        self.zero_registers = self.len;
+        self.harmonic_sum = self.len as u128 * fixed_two_pow_neg(0);
+        self.cached_estimate = None;
+    }
+
+    pub(crate) fn zero_registers(&self) -> usize {
+        self.zero_registers
+    }
+
+    // The sum of 2^(-val) over all registers, i.e. the denominator of the harmonic mean
+    pub(crate) fn harmonic_sum(&self) -> f64 {
+        fixed_to_f64(self.harmonic_sum)
+    }
+
+    // The same sum in its exact fixed-point form
+    pub(crate) fn fixed_harmonic_sum(&self) -> u128 {
+        self.harmonic_sum
+    }
+
+    // The estimate stored by `cache_estimate`, if no register changed since
+    pub(crate) fn cached_estimate(&self) -> Option<f64> {
+        self.cached_estimate
+    }
+
+    pub(crate) fn cache_estimate(&mut self, estimate: f64) {
+        self.cached_estimate = Some(estimate);
+    }
+
+    // Whether the maintained aggregates match the ones computed from scratch
+    pub(crate) fn aggregates_match(&self) -> bool {
+        if !self.is_allocated() {
+            return (self.zero_registers, self.harmonic_sum) == (self.len, self.len as u128 * fixed_two_pow_neg(0));
+        }
+        register_aggregates(&self.values) == (self.zero_registers, self.harmonic_sum)
+    }
+}
+
+// An unallocated array equals any array of zero registers of the same length
+impl PartialEq for {
+    fn eq(&self, other: &Self) -> bool {
+        match (self.is_allocated(), other.is_allocated()) {
+            (true, true) => self.values == other.values,
+            _ => self.len == other.len && self.zero_registers == other.zero_registers && self.zero_registers == self.len,
+        }
+    }
+}
+
+impl Eq for {}
+
+impl Debug for {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+}
+
+
+// Zeros standing in for the chunks of an unallocated array, which are never written
+static ZERO_CHUNK: [u8; 4096] = [0; 4096];
+
+// The register values of an array, zeros for an unallocated one
+#[derive(Clone)]
+pub(crate) struct Iter<'a> {
+    values: slice::Iter<'a, u8>,
+    zeros: usize,
+}
+
+impl Iterator for Iter<'_> {
+    type Item = u8;
+
+    #[inline]
+    fn next(&mut self) -> Option<u8> {
+        if let Some(&val) = self.values.next() {
+            return Some(val);
+        }
+        self.zeros = self.zeros.checked_sub(1)?;
+        Some(0)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.values.len() + self.zeros;
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for Iter<'_> {}
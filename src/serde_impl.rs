@@ -0,0 +1,273 @@
+use core::fmt;
+use core::hash::BuildHasher;
+use core::marker::PhantomData;
+
+use serde::de::{self, DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::ser::{SerializeStruct, Serializer};
+use serde::{Deserialize, Serialize};
+
+use crate::{base64, HasherId, Hypeerlog, HypeerlogError, SketchStats, Vec};
+use crate::dump::{self, StoredHasher};
+use crate::registers::RegisterArray;
+use crate::utils::pow_two;
+
+
+
+// Sketches are (de)serialized as a struct of their precision, the seed and the tag of their hasher
+// (when it has them), and their registers: a base64 string in human-readable formats such as JSON,
+// and a byte string in binary ones. The hasher itself is never serialized, nor is the insertion count,
+// so deserialized sketches start counting from 0. The tag is checked like the tag of a dump, and may
+// be missing from human-readable formats, which is read as an unknown hasher.
+const NAME: &str = "Hypeerlog";
+const FIELDS: &[&str] = &["precision", "seed", "tag", "registers"];
+
+
+impl<S> Serialize for Hypeerlog<S>
+where
+    S: BuildHasher + HasherId,
+{
+    fn serialize<Ser: Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+        let human_readable = serializer.is_human_readable();
+        let registers = self.register_values();
+        let mut state = serializer.serialize_struct(NAME, FIELDS.len())?;
+        state.serialize_field("precision", &self.precision)?;
+        state.serialize_field("seed", &self.hasher.hasher_seed())?;
+        state.serialize_field("tag", &self.hasher.hasher_tag())?;
+        if human_readable {
+            state.serialize_field("registers", &base64::encode(&registers))?;
+        } else {
+            state.serialize_field("registers", &Bytes(&registers))?;
+        }
+        state.end()
+    }
+}
+
+/// Deserializes sketches whose hasher can be created with [`Default`].
+///
+/// If the serialized sketch records a seed, the default hasher is reseeded with it through
+/// [`HasherId::reseeded`], so seeded Murmur3 sketches keep hashing the same way. A recorded hasher
+/// tag that differs from the tag of `S` fails with [`HypeerlogError::HasherMismatch`].
+impl<'de, S> Deserialize<'de> for Hypeerlog<S>
+where
+    S: BuildHasher + HasherId + Default,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let parts = deserializer.deserialize_struct(NAME, FIELDS, PartsVisitor)?;
+        parts.build(|stored| stored.restore(S::default()).map(|(hasher, _)| hasher))
+    }
+}
+
+
+/// A [`DeserializeSeed`] that deserializes a [`Hypeerlog`] with a given hasher, for hashers that
+/// cannot be created with [`Default`] (e.g. keyed ones).
+///
+/// As with [`Hypeerlog::load_with_hasher`], a recorded seed that differs from the seed of the
+/// hasher is rejected rather than applied, and so is a recorded tag that differs from its tag.
+///
+/// ```
+/// use serde::de::DeserializeSeed;
+/// use hypeerlog::{Hypeerlog, WithHasher};
+/// use std::hash::RandomState;
+///
+/// let hasher = RandomState::new();
+/// let mut hll = Hypeerlog::with_hasher(hasher.clone());
+/// hll.insert(42);
+///
+/// let json = serde_json::to_string(&hll).unwrap();
+/// let mut de = serde_json::Deserializer::from_str(&json);
+/// let restored = WithHasher(hasher).deserialize(&mut de).unwrap();
+/// assert_eq!(restored.cardinality(), hll.cardinality());
+/// ```
+#[derive(Debug, Clone)]
+pub struct WithHasher<S>(pub S);
+
+impl<'de, S> DeserializeSeed<'de> for WithHasher<S>
+where
+    S: BuildHasher + HasherId,
+{
+    type Value = Hypeerlog<S>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        let parts = deserializer.deserialize_struct(NAME, FIELDS, PartsVisitor)?;
+        parts.build(|stored| stored.check(StoredHasher::of(&self.0)).map(|_| self.0))
+    }
+}
+
+
+// The deserialized fields, not validated yet
+struct Parts {
+    precision: u8,
+    seed: Option<u64>,
+    tag: Option<u32>,
+    registers: Vec<u8>,
+}
+
+impl Parts {
+    // Runs the same validation as loading a dump
+    fn build<S, E>(self, make_hasher: impl FnOnce(StoredHasher) -> Result<S, HypeerlogError>) -> Result<Hypeerlog<S>, E>
+    where
+        S: BuildHasher + HasherId,
+        E: de::Error,
+    {
+        let validate = || {
+            if !(4..=25).contains(&self.precision) { return Err(HypeerlogError::InvalidPrecision); }
+            if self.registers.len() != pow_two(self.precision) as usize { return Err(HypeerlogError::InvalidLength); }
+            dump::check_ranks(self.precision, &self.registers)?;
+            make_hasher(StoredHasher { seed: self.seed, tag: self.tag })
+        };
+        let hasher = validate().map_err(E::custom)?;
+        Ok(Hypeerlog {
+            hasher,
+            precision: self.precision,
+            registers: RegisterArray::new(self.registers),
+            insertions: 0,
+        })
+    }
+}
+
+struct PartsVisitor;
+
+impl<'de> Visitor<'de> for PartsVisitor {
+    type Value = Parts;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a Hypeerlog sketch")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Parts, A::Error> {
+        let precision = seq.next_element()?.ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        let seed = seq.next_element()?.ok_or_else(|| de::Error::invalid_length(1, &self))?;
+        let tag = seq.next_element()?.ok_or_else(|| de::Error::invalid_length(2, &self))?;
+        let ByteBuf(registers) = seq.next_element()?.ok_or_else(|| de::Error::invalid_length(3, &self))?;
+        Ok(Parts { precision, seed, tag, registers })
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Parts, A::Error> {
+        let (mut precision, mut seed, mut tag, mut registers) = (None, None, None, None);
+        while let Some(key) = map.next_key::<Field>()? {
+            match key {
+                Field::Precision if precision.is_some() => return Err(de::Error::duplicate_field("precision")),
+                Field::Seed if seed.is_some() => return Err(de::Error::duplicate_field("seed")),
+                Field::Tag if tag.is_some() => return Err(de::Error::duplicate_field("tag")),
+                Field::Registers if registers.is_some() => return Err(de::Error::duplicate_field("registers")),
+                Field::Precision => precision = Some(map.next_value()?),
+                Field::Seed => seed = Some(map.next_value()?),
+                Field::Tag => tag = Some(map.next_value()?),
+                Field::Registers => registers = Some(map.next_value::<ByteBuf>()?.0),
+            }
+        }
+        Ok(Parts {
+            precision: precision.ok_or_else(|| de::Error::missing_field("precision"))?,
+            seed: seed.unwrap_or(None),
+            tag: tag.unwrap_or(None),
+            registers: registers.ok_or_else(|| de::Error::missing_field("registers"))?,
+        })
+    }
+}
+
+enum Field {
+    Precision,
+    Seed,
+    Tag,
+    Registers,
+}
+
+impl<'de> Deserialize<'de> for Field {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct FieldVisitor;
+
+        impl Visitor<'_> for FieldVisitor {
+            type Value = Field;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("`precision`, `seed`, `tag` or `registers`")
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<Field, E> {
+                match value {
+                    "precision" => Ok(Field::Precision),
+                    "seed" => Ok(Field::Seed),
+                    "tag" => Ok(Field::Tag),
+                    "registers" => Ok(Field::Registers),
+                    _ => Err(de::Error::unknown_field(value, FIELDS)),
+                }
+            }
+        }
+
+        deserializer.deserialize_identifier(FieldVisitor)
+    }
+}
+
+
+// Serializes a register slice as a byte string rather than a sequence of integers
+struct Bytes<'a>(&'a [u8]);
+
+impl Serialize for Bytes<'_> {
+    fn serialize<Ser: Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+        serializer.serialize_bytes(self.0)
+    }
+}
+
+// Deserializes the registers: a base64 string from human-readable formats (or the sequence of integers
+// they turn byte strings into), and a byte string from binary ones
+struct ByteBuf(Vec<u8>);
+
+impl<'de> Deserialize<'de> for ByteBuf {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ByteBufVisitor(PhantomData<ByteBuf>);
+
+        impl<'de> Visitor<'de> for ByteBufVisitor {
+            type Value = ByteBuf;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a byte string or a base64 string")
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<ByteBuf, E> {
+                base64::decode(v).map(ByteBuf).map_err(E::custom)
+            }
+
+            fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<ByteBuf, E> {
+                Ok(ByteBuf(v.to_vec()))
+            }
+
+            fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<ByteBuf, E> {
+                Ok(ByteBuf(v))
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<ByteBuf, A::Error> {
+                // Bounded so that a bogus size hint cannot reserve more than the largest sketch
+                let hint = seq.size_hint().unwrap_or(0).min(pow_two(25) as usize);
+                let mut bytes = Vec::with_capacity(hint);
+                while let Some(byte) = seq.next_element()? {
+                    bytes.push(byte);
+                }
+                Ok(ByteBuf(bytes))
+            }
+        }
+
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_any(ByteBufVisitor(PhantomData))
+        } else {
+            deserializer.deserialize_byte_buf(ByteBufVisitor(PhantomData))
+        }
+    }
+}
+
+
+/// Serializes the snapshot as a struct of its fields, e.g. to log it as JSON.
+impl Serialize for SketchStats {
+    fn serialize<Ser: Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+        let mut state = serializer.serialize_struct("SketchStats", 9)?;
+        state.serialize_field("precision", &self.precision)?;
+        state.serialize_field("registers", &self.registers)?;
+        state.serialize_field("nonzero_registers", &self.nonzero_registers)?;
+        state.serialize_field("zero_registers", &self.zero_registers)?;
+        state.serialize_field("max_register", &self.max_register)?;
+        state.serialize_field("estimate", &self.estimate)?;
+        state.serialize_field("relative_error", &self.relative_error)?;
+        state.serialize_field("memory_bytes", &self.memory_bytes)?;
+        state.serialize_field("total_insertions", &self.total_insertions)?;
+        state.end()
+    }
+}
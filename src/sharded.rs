@@ -0,0 +1,227 @@
+use core::fmt;
+use core::hash::{BuildHasher, Hash};
+use core::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
+
+use crate::{atomic, vec, HasherId, Hypeerlog, Murmur3BuildHasher, Vec};
+use crate::registers::RegisterArray;
+use crate::utils::{estimate_from_registers, get_bucket, longest_run, pow_two, rel_error_from_p};
+
+
+
+// Each thread is given the next slot the first time it inserts into any sharded sketch, and keeps it, so threads
+// that start inserting together spread evenly over the shards however their ids are allocated. Slots are shared
+// by every sketch of the process and never reused
+static NEXT_SLOT: AtomicUsize = AtomicUsize::new(0);
+
+std::thread_local! {
+    static SLOT: usize = NEXT_SLOT.fetch_add(1, Ordering::Relaxed);
+}
+
+
+/// A HyperLogLog sketch that many threads insert into through a shared reference, without locking.
+///
+/// The sketch holds one set of $2^p$ registers per shard, all with the same precision and hasher. Each
+/// [`ShardedHypeerlog::insert`] goes to the shard of the calling thread, or to the shard of a key with
+/// [`ShardedHypeerlog::insert_with_key`], and raises its register with an atomic maximum, which only writes
+/// when the register grows. Threads are given process-wide slots, in the order they first insert into any sharded
+/// sketch, and each inserts into the shard of its slot modulo the number of shards (the default is
+/// [`std::thread::available_parallelism`]). Threads that start inserting together thus mostly write to different
+/// shards, which reduces contention, but nothing makes a shard exclusive: slots are never reused, so a thread
+/// started later, e.g. by a pool that replaces its threads, may share a shard with a live one. Concurrent inserts
+/// into the same shard are still correct, only slower.
+///
+/// [`ShardedHypeerlog::to_merged`] unions the shards into a [`Hypeerlog`] on demand, and
+/// [`ShardedHypeerlog::cardinality`] estimates that union. The union of the same elements is the same whatever
+/// the shards they went to, so the registers are identical to inserting everything into one [`Hypeerlog`].
+/// Both can run while other threads insert, and then see any subset of the concurrent inserts. Each shard takes
+/// $2^p$ bytes, and each of these calls scans all of them.
+///
+/// ```
+/// use hypeerlog::{Hypeerlog, ShardedHypeerlog};
+///
+/// let sharded = ShardedHypeerlog::with_shards(12, 4);
+/// std::thread::scope(|s| {
+///     for t in 0..4u64 {
+///         let sharded = &sharded;
+///         s.spawn(move || (t * 1000..(t + 1) * 1000).for_each(|x| sharded.insert(x)));
+///     }
+/// });
+///
+/// let mut expected = Hypeerlog::with_precision(12);
+/// expected.insert_many(&(0..4000u64).collect::<Vec<_>>());
+/// assert_eq!(sharded.to_merged(), expected);
+/// assert_eq!(sharded.cardinality(), expected.cardinality());
+/// ```
+pub struct ShardedHypeerlog<S = Murmur3BuildHasher>
+where
+    S: BuildHasher + HasherId,
+{
+    hasher: S,
+    precision: u8,
+    shards: usize,
+    // The registers of shard `i` are `i << precision .. (i + 1) << precision`
+    registers: Vec<AtomicU8>,
+}
+
+
+impl<S> ShardedHypeerlog<S>
+where
+    S: BuildHasher + HasherId,
+{
+    /// Creates an empty sketch with a custom hasher builder and a specific precision, with one shard per
+    /// available thread.
+    ///
+    /// The precision value is silently clamped to the valid range of `4..=25`.
+    pub fn with_hasher_precision(precision: u8, hasher_builder: S) -> Self {
+        Self::with_hasher_precision_shards(precision, default_shards(), hasher_builder)
+    }
+
+    /// Creates an empty sketch with a custom hasher builder, a specific precision and number of shards.
+    ///
+    /// The precision value is silently clamped to the valid range of `4..=25`, and the number of shards is at
+    /// least 1.
+    pub fn with_hasher_precision_shards(precision: u8, shards: usize, hasher_builder: S) -> Self {
+        let p = precision.clamp(4, 25);
+        let shards = shards.max(1);
+        let mut registers = Vec::with_capacity(shards << p);
+        registers.resize_with(shards << p, || AtomicU8::new(0));
+        ShardedHypeerlog {
+            hasher: hasher_builder,
+            precision: p,
+            shards,
+            registers,
+        }
+    }
+
+    /// Returns the precision of the sketch.
+    pub fn precision(&self) -> u8 {
+        self.precision
+    }
+
+    /// Returns the number of shards of the sketch.
+    pub fn shards(&self) -> usize {
+        self.shards
+    }
+
+    /// Returns the expected standard relative error of the precision.
+    pub fn relative_error(&self) -> f64 {
+        rel_error_from_p(self.precision as u32)
+    }
+
+    /// Inserts a single hashable item into the shard of the calling thread.
+    pub fn insert<H: Hash>(&self, data: H) {
+        self.insert_hashed(self.hasher.hash_one(&data));
+    }
+
+    /// Inserts an item by a hash computed outside of the sketch into the shard of the calling thread, see
+    /// [`Hypeerlog::insert_hashed`].
+    pub fn insert_hashed(&self, hash: u64) {
+        self.insert_into(SLOT.with(|slot| *slot) % self.shards, hash);
+    }
+
+    /// Inserts a single hashable item into shard `key % shards`.
+    ///
+    /// This is for callers that already know which worker they run on, e.g. the index of a thread in a pool,
+    /// so that each worker keeps to its own shard.
+    pub fn insert_with_key<H: Hash>(&self, key: usize, data: H) {
+        self.insert_into(key % self.shards, self.hasher.hash_one(&data));
+    }
+
+    fn insert_into(&self, shard: usize, hash: u64) {
+        let rank = longest_run(self.precision, hash);
+        atomic::raise(&self.registers[(shard << self.precision) + get_bucket(self.precision, hash)], rank);
+    }
+
+    /// Returns `true` if no elements have been observed by any shard yet.
+    pub fn is_empty(&self) -> bool {
+        self.registers.iter().all(|reg| reg.load(Ordering::Relaxed) == 0)
+    }
+
+    /// Resets the registers of every shard back to zero.
+    pub fn clear(&mut self) {
+        self.registers.iter_mut().for_each(|reg| *reg.get_mut() = 0);
+    }
+
+    /// Returns the estimated distinct element count of the union of the shards.
+    pub fn cardinality(&self) -> f64 {
+        estimate_from_registers(self.precision, &self.merged_registers())
+    }
+
+    /// Unions the shards into a new [`Hypeerlog`] with the precision and hasher of this sketch.
+    pub fn to_merged(&self) -> Hypeerlog<S>
+    where
+        S: Clone,
+    {
+        Hypeerlog {
+            hasher: self.hasher.clone(),
+            precision: self.precision,
+            registers: RegisterArray::new(self.merged_registers()),
+            insertions: 0,
+        }
+    }
+
+    fn merged_registers(&self) -> Vec<u8> {
+        let len = pow_two(self.precision) as usize;
+        let mut merged = vec![0u8; len];
+        for shard in self.registers.chunks_exact(len) {
+            merged.iter_mut()
+                .zip(shard)
+                .for_each(|(a, b)| *a = (*a).max(b.load(Ordering::Relaxed)));
+        }
+        merged
+    }
+}
+
+
+// Summarized like the `Debug` of `Hypeerlog`, without reading the registers other threads may be writing
+impl<S> fmt::Debug for ShardedHypeerlog<S>
+where
+    S: BuildHasher + HasherId,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ShardedHypeerlog")
+            .field("precision", &self.precision)
+            .field("hasher_seed", &self.hasher.hasher_seed())
+            .field("hasher_tag", &self.hasher.hasher_tag())
+            .field("shards", &self.shards)
+            .finish()
+    }
+}
+
+
+impl ShardedHypeerlog<Murmur3BuildHasher> {
+    /// Creates an empty sketch using the default `Murmur3BuildHasher` with a default precision of 14, with one
+    /// shard per available thread.
+    pub fn new() -> Self {
+        Self::with_precision(14)
+    }
+
+    /// Creates an empty sketch with a specific precision, with one shard per available thread.
+    ///
+    /// The precision value is silently clamped to the valid range of `4..=25`.
+    pub fn with_precision(precision: u8) -> Self {
+        Self::with_hasher_precision(precision, Murmur3BuildHasher::new(0))
+    }
+
+    /// Creates an empty sketch with a specific precision and a custom seed for the default `Murmur3BuildHasher`,
+    /// with one shard per available thread.
+    pub fn with_precision_seed(precision: u8, seed: u32) -> Self {
+        Self::with_hasher_precision(precision, Murmur3BuildHasher::new(seed))
+    }
+
+    /// Creates an empty sketch with a specific precision and number of shards.
+    pub fn with_shards(precision: u8, shards: usize) -> Self {
+        Self::with_hasher_precision_shards(precision, shards, Murmur3BuildHasher::new(0))
+    }
+}
+
+impl Default for ShardedHypeerlog<Murmur3BuildHasher> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+
+fn default_shards() -> usize {
+    std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1)
+}
@@ -0,0 +1,181 @@
+use core::hash::{BuildHasher, Hash};
+use std::sync::{PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use crate::{HasherId, Hypeerlog, HypeerlogError, Murmur3BuildHasher, Vec};
+
+
+
+/// A [`Hypeerlog`] shared between threads, for many readers of its estimate and few writers.
+///
+/// The sketch is behind a [`RwLock`]: inserts, merges and [`SharedHypeerlog::clear`] take the write lock, while
+/// estimates, dumps and snapshots take the read lock, so readers only wait for a write in progress and never for
+/// each other. Every read sees exactly the writes that returned before it started, and none of a write still in
+/// progress: an estimate never lags the last completed insert, and a dump or [`SharedHypeerlog::snapshot`] is a
+/// consistent copy of the sketch at one point. [`Hypeerlog::cardinality`] is computed from aggregates maintained
+/// by the inserts, so the read lock is only held for a few operations, whatever the precision; dumps and snapshots
+/// hold it while they copy the registers.
+///
+/// An insert takes the write lock for each item, so writers that insert many items should batch them with
+/// [`SharedHypeerlog::insert_many`]. For many concurrent writers, [`crate::ShardedHypeerlog`] and
+/// [`crate::AtomicHypeerlog`] insert without locking. A panic while the lock is held cannot leave the registers
+/// half-updated, so a poisoned lock is used as it is.
+///
+/// ```
+/// use hypeerlog::{Hypeerlog, SharedHypeerlog};
+///
+/// let shared = SharedHypeerlog::with_precision(12);
+/// std::thread::scope(|s| {
+///     s.spawn(|| (0..5000u64).for_each(|x| { shared.insert(x); }));
+///     // Readers only wait for an insert in progress
+///     s.spawn(|| (0..100).for_each(|_| { shared.count(); }));
+/// });
+///
+/// let expected = Hypeerlog::from_iter_with_precision(12, 0..5000u64);
+/// assert_eq!(shared.count(), expected.count());
+/// assert_eq!(shared.snapshot(), expected);
+/// assert_eq!(shared.cardinality(), expected.cardinality());
+/// ```
+#[derive(Debug)]
+pub struct SharedHypeerlog<S = Murmur3BuildHasher>
+where
+    S: BuildHasher + HasherId,
+{
+    inner: RwLock<Hypeerlog<S>>,
+}
+
+
+impl<S> SharedHypeerlog<S>
+where
+    S: BuildHasher + HasherId,
+{
+    /// Creates an empty sketch with a custom hasher builder and a specific precision.
+    ///
+    /// The precision value is silently clamped to the valid range of `4..=25`.
+    pub fn with_hasher_precision(precision: u8, hasher_builder: S) -> Self {
+        Self::from(Hypeerlog::with_hasher_precision(precision, hasher_builder))
+    }
+
+    /// Returns the precision of the sketch.
+    pub fn precision(&self) -> u8 {
+        self.read().precision()
+    }
+
+    /// Returns the expected standard relative error of the precision.
+    pub fn relative_error(&self) -> f64 {
+        self.read().relative_error()
+    }
+
+    /// Inserts a single hashable item under the write lock, see [`Hypeerlog::insert`].
+    pub fn insert<H: Hash>(&self, data: H) -> bool {
+        self.write().insert(data)
+    }
+
+    /// Inserts an item by a hash computed outside of the sketch under the write lock, see
+    /// [`Hypeerlog::insert_hashed`].
+    pub fn insert_hashed(&self, hash: u64) -> bool {
+        self.write().insert_hashed(hash)
+    }
+
+    /// Inserts a slice of items under a single write lock, see [`Hypeerlog::insert_many`].
+    pub fn insert_many<H: Hash>(&self, data: &[H]) -> usize {
+        self.write().insert_many(data)
+    }
+
+    /// Merges another sketch into this one under the write lock, see [`Hypeerlog::merge_with`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of [`Hypeerlog::merge_with`], leaving the sketch unchanged.
+    pub fn merge_with(&self, other: &Hypeerlog<S>) -> Result<(), HypeerlogError> {
+        self.write().merge_with(other)
+    }
+
+    /// Resets the sketch to an empty one of the same precision and hasher, see [`Hypeerlog::clear`].
+    pub fn clear(&self) {
+        self.write().clear();
+    }
+
+    /// Returns `true` if no elements have been observed yet, or since the last [`SharedHypeerlog::clear`].
+    pub fn is_empty(&self) -> bool {
+        self.read().is_empty()
+    }
+
+    /// Returns the estimated distinct element count, see [`Hypeerlog::cardinality`].
+    pub fn cardinality(&self) -> f64 {
+        self.read().cardinality()
+    }
+
+    /// Returns the estimated distinct element count as an integer, see [`Hypeerlog::count`].
+    pub fn count(&self) -> u64 {
+        self.read().count()
+    }
+
+    /// Dumps the sketch under the read lock, see [`Hypeerlog::dump`].
+    pub fn dump(&self) -> Vec<u8> {
+        self.read().dump()
+    }
+
+    /// Returns a copy of the sketch, taken under the read lock.
+    pub fn snapshot(&self) -> Hypeerlog<S>
+    where
+        S: Clone,
+    {
+        self.read().clone()
+    }
+
+    /// Calls `f` with the sketch under the read lock, for the reads that have no method here.
+    ///
+    /// Writers wait until `f` returns, so it should not do more than read the sketch.
+    pub fn with<R>(&self, f: impl FnOnce(&Hypeerlog<S>) -> R) -> R {
+        f(&self.read())
+    }
+
+    /// Returns the sketch, which no other thread can access anymore.
+    pub fn into_inner(self) -> Hypeerlog<S> {
+        self.inner.into_inner().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn read(&self) -> RwLockReadGuard<'_, Hypeerlog<S>> {
+        self.inner.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, Hypeerlog<S>> {
+        self.inner.write().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+
+impl SharedHypeerlog<Murmur3BuildHasher> {
+    /// Creates an empty sketch using the default `Murmur3BuildHasher` with a default precision of 14.
+    pub fn new() -> Self {
+        Self::with_precision(14)
+    }
+
+    /// Creates an empty sketch with a specific precision.
+    ///
+    /// The precision value is silently clamped to the valid range of `4..=25`.
+    pub fn with_precision(precision: u8) -> Self {
+        Self::from(Hypeerlog::with_precision(precision))
+    }
+
+    /// Creates an empty sketch with a specific precision and a custom seed for the default `Murmur3BuildHasher`.
+    pub fn with_precision_seed(precision: u8, seed: u32) -> Self {
+        Self::from(Hypeerlog::with_precision_seed(precision, seed))
+    }
+}
+
+impl Default for SharedHypeerlog<Murmur3BuildHasher> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> From<Hypeerlog<S>> for SharedHypeerlog<S>
+where
+    S: BuildHasher + HasherId,
+{
+    /// Shares an existing sketch, keeping its registers and hasher.
+    fn from(hll: Hypeerlog<S>) -> Self {
+        SharedHypeerlog { inner: RwLock::new(hll) }
+    }
+}
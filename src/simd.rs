@@ -0,0 +1,162 @@
+use core::arch::x86_64::*;
+
+use crate::murmur::murmur3_32;
+
+
+
+// MurmurHash3_x86_32 of 8-byte keys on eight 32-bit lanes at once. Each key is two blocks, its low then its
+// high 32 bits, with no tail, so every lane runs exactly the scalar rounds of `murmur3_32` on its key.
+
+const C1: i32 = 0xcc9e2d51_u32 as i32;
+const C2: i32 = 0x1b873593_u32 as i32;
+const N: i32 = 0xe6546b64_u32 as i32;
+const F1: i32 = 0x85ebca6b_u32 as i32;
+const F2: i32 = 0xc2b2ae35_u32 as i32;
+
+
+pub(crate) fn avx2_available() -> bool {
+    #[cfg(not(feature = "no_std"))]
+    return std::is_x86_feature_detected!("avx2");
+    #[cfg(feature = "no_std")]
+    return cfg!(target_feature = "avx2");
+}
+
+// The scalar equivalent of each lane is `murmur3_32(&key.to_le_bytes(), seed)`, which the remainder uses
+#[target_feature(enable = "avx2")]
+pub(crate) fn murmur3_u64s_avx2(keys: &[u64], seed: u32, hashes: &mut [u64]) {
+    let mut key_chunks = keys.chunks_exact(8);
+    let mut hash_chunks = hashes.chunks_exact_mut(8);
+    for (keys, hashes) in (&mut key_chunks).zip(&mut hash_chunks) {
+        // SAFETY: both chunks hold 8 `u64`s, i.e. two unaligned 256-bit vectors each
+        unsafe {
+            let a = _mm256_loadu_si256(keys.as_ptr() as *const __m256i);
+            let b = _mm256_loadu_si256(keys.as_ptr().add(4) as *const __m256i);
+            let (lo, hi) = split_halves(a, b);
+            let h = hash_lanes(lo, hi, seed);
+            // Zero-extended to 64 bits, like `Murmur3Hasher::finish`
+            let out = hashes.as_mut_ptr() as *mut __m256i;
+            _mm256_storeu_si256(out, _mm256_cvtepu32_epi64(_mm256_castsi256_si128(h)));
+            _mm256_storeu_si256(out.add(1), _mm256_cvtepu32_epi64(_mm256_extracti128_si256::<1>(h)));
+        }
+    }
+    for (hash, &key) in hash_chunks.into_remainder().iter_mut().zip(key_chunks.remainder()) {
+        *hash = murmur3_32(&key.to_le_bytes(), seed) as u64;
+    }
+}
+
+// Splits eight `u64`s, four in `a` and four in `b`, into their low and high 32-bit halves, in key order
+#[target_feature(enable = "avx2")]
+fn split_halves(a: __m256i, b: __m256i) -> (__m256i, __m256i) {
+    // [lo0 hi0 lo1 hi1 | lo2 hi2 lo3 hi3] -> [lo0 lo1 lo2 lo3 | hi0 hi1 hi2 hi3]
+    let a = _mm256_permute4x64_epi64::<0b11_01_10_00>(_mm256_shuffle_epi32::<0b11_01_10_00>(a));
+    let b = _mm256_permute4x64_epi64::<0b11_01_10_00>(_mm256_shuffle_epi32::<0b11_01_10_00>(b));
+    (_mm256_permute2x128_si256::<0x20>(a, b), _mm256_permute2x128_si256::<0x31>(a, b))
+}
+
+#[target_feature(enable = "avx2")]
+fn hash_lanes(lo: __m256i, hi: __m256i, seed: u32) -> __m256i {
+    let mut h = _mm256_set1_epi32(seed as i32);
+    for block in [lo, hi] {
+        h = _mm256_xor_si256(h, mix_k1(block));
+        h = rotate_left::<13, 19>(h);
+        h = _mm256_add_epi32(_mm256_mullo_epi32(h, _mm256_set1_epi32(5)), _mm256_set1_epi32(N));
+    }
+
+    // `fmix32` with a length of 8
+    h = _mm256_xor_si256(h, _mm256_set1_epi32(8));
+    h = _mm256_xor_si256(h, _mm256_srli_epi32::<16>(h));
+    h = _mm256_mullo_epi32(h, _mm256_set1_epi32(F1));
+    h = _mm256_xor_si256(h, _mm256_srli_epi32::<13>(h));
+    h = _mm256_mullo_epi32(h, _mm256_set1_epi32(F2));
+    _mm256_xor_si256(h, _mm256_srli_epi32::<16>(h))
+}
+
+#[target_feature(enable = "avx2")]
+fn mix_k1(k1: __m256i) -> __m256i {
+    let k1 = _mm256_mullo_epi32(k1, _mm256_set1_epi32(C1));
+    _mm256_mullo_epi32(rotate_left::<15, 17>(k1), _mm256_set1_epi32(C2))
+}
+
+// `R` and `32 - R`, as the shifts take their counts as separate constants
+#[target_feature(enable = "avx2")]
+fn rotate_left<const R: i32, const L: i32>(x: __m256i) -> __m256i {
+    _mm256_or_si256(_mm256_slli_epi32::<R>(x), _mm256_srli_epi32::<L>(x))
+}
+
+
+// The number of zero registers and the fixed-point harmonic sum of `utils::register_aggregates`, 32 registers
+// at a time. The term 2^(64-val) of a register is split by the range of `val` into four parts of at most
+// 2^15, `part[k]` holding 2^(16(k+1)-val) of the values in 16k+1..=16(k+1) and scaled by 2^(48-16k) when the
+// parts are joined, so that each 32-bit lane can add 2^15 terms before it is flushed. The sum is an exact
+// integer, so it is identical to the scalar one.
+#[target_feature(enable = "avx2")]
+pub(crate) fn register_aggregates_avx2(registers: &[u8]) -> (usize, u128) {
+    aggregates_avx2::<false>(registers, registers)
+}
+
+// Same as `register_aggregates_avx2` on the max of the registers of `a` and `b`, which have the same length
+#[target_feature(enable = "avx2")]
+pub(crate) fn union_aggregates_avx2(a: &[u8], b: &[u8]) -> (usize, u128) {
+    aggregates_avx2::<true>(a, b)
+}
+
+#[target_feature(enable = "avx2")]
+fn aggregates_avx2<const UNION: bool>(a: &[u8], b: &[u8]) -> (usize, u128) {
+    // Each iteration adds at most 4 * 2^15 to a lane
+    const FLUSH_EVERY: usize = 1 << 14;
+
+    assert_eq!(a.len(), b.len());
+    let split = a.len() - a.len() % 32;
+    let mut zeros = 0;
+    let mut totals = [0u64; 4];
+    let mut parts = [_mm256_setzero_si256(); 4];
+    let offsets = [1, 17, 33, 49].map(|offset| _mm256_set1_epi32(offset));
+    let top = _mm256_set1_epi32(1 << 15);
+
+    for (iteration, start) in (0..split).step_by(32).enumerate() {
+        // SAFETY: `start + 32 <= split <= len`, and both slices have `len` bytes
+        let regs = unsafe {
+            let regs = _mm256_loadu_si256(a.as_ptr().add(start) as *const __m256i);
+            if UNION { _mm256_max_epu8(regs, _mm256_loadu_si256(b.as_ptr().add(start) as *const __m256i)) } else { regs }
+        };
+        zeros += (_mm256_movemask_epi8(_mm256_cmpeq_epi8(regs, _mm256_setzero_si256())) as u32).count_ones() as usize;
+
+        let (low, high) = (_mm256_castsi256_si128(regs), _mm256_extracti128_si256::<1>(regs));
+        for eight in [low, _mm_srli_si128::<8>(low), high, _mm_srli_si128::<8>(high)] {
+            let vals = _mm256_cvtepu8_epi32(eight);
+            for (part, offset) in parts.iter_mut().zip(offsets) {
+                // Shifts by more than 31, including the wrapped negative counts, give 0
+                *part = _mm256_add_epi32(*part, _mm256_srlv_epi32(top, _mm256_sub_epi32(vals, offset)));
+            }
+        }
+
+        if iteration % FLUSH_EVERY == FLUSH_EVERY - 1 {
+            flush(&mut parts, &mut totals);
+        }
+    }
+    flush(&mut parts, &mut totals);
+
+    let (tail_zeros, tail_sum) = if UNION {
+        crate::utils::scalar_aggregates(a[split..].iter().zip(&b[split..]).map(|(a, b)| *a.max(b)))
+    } else {
+        crate::utils::scalar_aggregates(a[split..].iter().copied())
+    };
+    let sum = ((zeros as u128) << 64)
+        + ((totals[0] as u128) << 48)
+        + ((totals[1] as u128) << 32)
+        + ((totals[2] as u128) << 16)
+        + totals[3] as u128;
+    (zeros + tail_zeros, sum + tail_sum)
+}
+
+// Adds the lanes of the 32-bit partial sums to their 64-bit totals and resets them
+#[target_feature(enable = "avx2")]
+fn flush(parts: &mut [__m256i; 4], totals: &mut [u64; 4]) {
+    for (part, total) in parts.iter_mut().zip(totals.iter_mut()) {
+        let mut lanes = [0u32; 8];
+        // SAFETY: `lanes` is 32 bytes long
+        unsafe { _mm256_storeu_si256(lanes.as_mut_ptr() as *mut __m256i, *part) };
+        *total += lanes.iter().map(|&lane| lane as u64).sum::<u64>();
+        *part = _mm256_setzero_si256();
+    }
+}
@@ -0,0 +1,137 @@
+use core::fmt;
+use core::hash::{BuildHasher, Hasher};
+
+use siphasher::sip::SipHasher13;
+
+use crate::HasherId;
+use crate::utils::write_integers_le;
+
+
+
+/// The [`HasherId::hasher_tag`] of [`SipBuildHasher`].
+pub const SIPHASH13_TAG: u32 = u32::from_le_bytes(*b"SI13");
+
+// Hashed under the key to identify it in dumps without revealing it
+const KEY_FINGERPRINT_MESSAGE: &[u8] = b"hypeerlog siphash key fingerprint";
+
+
+/// A `BuildHasher` for SipHash-1-3 with an explicit secret 128-bit key, for sketches fed by untrusted input.
+///
+/// The seeded non-cryptographic hashers (including the default Murmur3 with [`Hypeerlog::with_seed`]) do not
+/// stop an attacker who can observe estimates from crafting elements that all land in a few registers. SipHash
+/// is a keyed pseudo-random function, so without the key the attacker cannot predict the register of any element.
+/// Generate the key from a cryptographically secure source, or with [`SipBuildHasher::random`].
+///
+/// The key itself is never written to dumps or [`Debug`](core::fmt::Debug) output. Dumps record a 64-bit
+/// fingerprint of it as their [`HasherId::hasher_seed`] instead, so they have to be loaded with
+/// [`Hypeerlog::load_with_hasher`] and the same key, and sketches or dumps built with a different key refuse to load or
+/// merge with [`HypeerlogError::SeedMismatch`](crate::HypeerlogError::SeedMismatch).
+///
+/// ```
+/// use hypeerlog::{Hypeerlog, SipBuildHasher};
+///
+/// let key = [7u8; 16]; // in production, from a secret store or `SipBuildHasher::random()`
+/// let mut hll = Hypeerlog::with_hasher(SipBuildHasher::new_with_key(key));
+/// hll.insert_str("untrusted user input");
+///
+/// let restored = Hypeerlog::load_with_hasher(hll.dump(), SipBuildHasher::new_with_key(key)).unwrap();
+/// assert_eq!(restored, hll);
+/// ```
+///
+/// [`Hypeerlog::with_seed`]: crate::Hypeerlog::with_seed
+/// [`Hypeerlog::load_with_hasher`]: crate::Hypeerlog::load_with_hasher
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct SipBuildHasher {
+    k0: u64,
+    k1: u64,
+}
+
+impl SipBuildHasher {
+    /// Creates a builder of hashers keyed with the two 64-bit halves of a 128-bit key.
+    pub fn new_with_keys(k0: u64, k1: u64) -> Self {
+        SipBuildHasher { k0, k1 }
+    }
+
+    /// Creates a builder of hashers keyed with a 128-bit key, read as two little-endian 64-bit halves.
+    pub fn new_with_key(key: [u8; 16]) -> Self {
+        let (k0, k1) = key.split_at(8);
+        SipBuildHasher::new_with_keys(
+            u64::from_le_bytes(k0.try_into().expect("the halves are 8 bytes")),
+            u64::from_le_bytes(k1.try_into().expect("the halves are 8 bytes")),
+        )
+    }
+
+    /// Creates a builder of hashers with a fresh random key, drawn from the randomness `std` seeds its
+    /// `HashMap`s with.
+    ///
+    /// The key is not recoverable from dumps, so sketches built with it can only be loaded and merged by this
+    /// process. Use [`SipBuildHasher::key`] to store it if they need to outlive it.
+    #[cfg(not(feature = "no_std"))]
+    pub fn random() -> Self {
+        let state = std::collections::hash_map::RandomState::new();
+        SipBuildHasher::new_with_keys(state.hash_one(0u64), state.hash_one(1u64))
+    }
+
+    /// Returns the 128-bit key, in the layout accepted by [`SipBuildHasher::new_with_key`].
+    pub fn key(&self) -> [u8; 16] {
+        let mut key = [0; 16];
+        key[..8].copy_from_slice(&self.k0.to_le_bytes());
+        key[8..].copy_from_slice(&self.k1.to_le_bytes());
+        key
+    }
+
+    // A 64-bit identifier of the key, from which the key cannot be recovered
+    fn key_fingerprint(&self) -> u64 {
+        SipHasher13::new_with_keys(self.k0, self.k1).hash(KEY_FINGERPRINT_MESSAGE)
+    }
+}
+
+impl fmt::Debug for SipBuildHasher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SipBuildHasher")
+            .field("key_fingerprint", &format_args!("{:#018x}", self.key_fingerprint()))
+            .finish()
+    }
+}
+
+impl BuildHasher for SipBuildHasher {
+    type Hasher = Sip13Hasher;
+
+    fn build_hasher(&self) -> Self::Hasher {
+        Sip13Hasher(SipHasher13::new_with_keys(self.k0, self.k1))
+    }
+}
+
+impl HasherId for SipBuildHasher {
+    fn hasher_seed(&self) -> Option<u64> {
+        Some(self.key_fingerprint())
+    }
+
+    fn hasher_tag(&self) -> Option<u32> {
+        Some(SIPHASH13_TAG)
+    }
+
+    fn hash_bytes(&self, bytes: &[u8]) -> u64 {
+        SipHasher13::new_with_keys(self.k0, self.k1).hash(bytes)
+    }
+}
+
+
+/// The streaming SipHash-1-3 hasher built by [`SipBuildHasher`].
+///
+/// Bytes written in several pieces hash like the same bytes written at once, integers are written as their
+/// little-endian bytes (`usize` and `isize` widened to 64 bits), and [`Hasher::finish`] does not reset the state.
+#[derive(Clone)]
+pub struct Sip13Hasher(SipHasher13);
+
+impl Hasher for Sip13Hasher {
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.write(bytes);
+    }
+
+    fn finish(&self) -> u64 {
+        self.0.finish()
+    }
+
+    write_integers_le!();
+}
@@ -0,0 +1,300 @@
+use core::hash::BuildHasher;
+use std::collections::BTreeMap;
+use std::io::{Read, Seek, SeekFrom, Write};
+
+use crate::{HasherId, Hypeerlog, HypeerlogError, String, Vec};
+
+
+
+// Archive layout, all integers little-endian:
+//
+// | Offset           | Size | Field                                                      |
+// |------------------|------|------------------------------------------------------------|
+// | 0                | 4    | magic `HLLA`                                               |
+// | 4                | 1    | version (1)                                                |
+// | 5                | 4    | number of entries `n`                                      |
+// | 9                | ...  | `n` entries: name length (u32), UTF-8 name, dump length    |
+// |                  |      | (u32), dump                                                |
+// | index offset     | ...  | `n` index records: name length (u32), UTF-8 name, offset   |
+// |                  |      | of the dump (u64), dump length (u32)                       |
+// | end - 12         | 8    | index offset                                               |
+// | end - 4          | 4    | magic `HLLA`                                               |
+//
+// The index repeats the names so that one sketch can be found by reading only the header, the
+// footer, the index and its own dump.
+const MAGIC: [u8; 4] = *b"HLLA";
+const VERSION: u8 = 1;
+const HEADER_LEN: usize = 9;
+const FOOTER_LEN: usize = 12;
+
+
+/// A collection of named sketches stored together in one file.
+///
+/// Sketches are kept as dumps and only parsed when requested with [`SketchArchive::get`], so opening an
+/// archive of many sketches to query a few of them does not pay for the rest. Inserting a name that is
+/// already present replaces its sketch (last writer wins), and names are listed in sorted order.
+///
+/// ```
+/// use hypeerlog::{Hypeerlog, SketchArchive};
+///
+/// let mut de = Hypeerlog::new();
+/// de.insert("user-1");
+///
+/// let mut archive = SketchArchive::new();
+/// archive.insert("country=DE", &de);
+///
+/// let mut file = Vec::new();
+/// archive.write_to(&mut file).unwrap();
+///
+/// let archive = SketchArchive::read_from(&mut file.as_slice()).unwrap();
+/// assert_eq!(archive.get("country=DE").unwrap(), de);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SketchArchive {
+    entries: BTreeMap<String, Vec<u8>>,
+}
+
+impl SketchArchive {
+    /// Creates an empty archive.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of sketches in the archive.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if the archive holds no sketch.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the names of the sketches in the archive, in sorted order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.entries.keys().map(String::as_str)
+    }
+
+    /// Returns `true` if the archive holds a sketch called `name`.
+    pub fn contains(&self, name: &str) -> bool {
+        self.entries.contains_key(name)
+    }
+
+    /// Stores a sketch under `name`, replacing the sketch previously stored under it, if any.
+    pub fn insert<S>(&mut self, name: &str, hll: &Hypeerlog<S>)
+    where
+        S: BuildHasher + HasherId,
+    {
+        self.entries.insert(String::from(name), hll.dump());
+    }
+
+    /// Removes the sketch called `name` from the archive, returning whether it was present.
+    pub fn remove(&mut self, name: &str) -> bool {
+        self.entries.remove(name).is_some()
+    }
+
+    /// Parses the sketch called `name`, using the default `Murmur3BuildHasher` restored from the seed
+    /// recorded in its dump.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`HypeerlogError::UnknownName`] if the archive holds no sketch called `name`, or any error of
+    /// [`Hypeerlog::load`] if its dump is invalid.
+    pub fn get(&self, name: &str) -> Result<Hypeerlog, HypeerlogError> {
+        Hypeerlog::load(self.dump_of(name)?.to_vec())
+    }
+
+    /// Parses the sketch called `name` with a custom hasher.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`HypeerlogError::UnknownName`] if the archive holds no sketch called `name`, or any error of
+    /// [`Hypeerlog::load_with_hasher`] if its dump is invalid or was written with another hasher.
+    pub fn get_with_hasher<S>(&self, name: &str, hasher_builder: S) -> Result<Hypeerlog<S>, HypeerlogError>
+    where
+        S: BuildHasher + HasherId,
+    {
+        Hypeerlog::load_with_hasher(self.dump_of(name)?.to_vec(), hasher_builder)
+    }
+
+    fn dump_of(&self, name: &str) -> Result<&[u8], HypeerlogError> {
+        self.entries.get(name).map(Vec::as_slice).ok_or(HypeerlogError::UnknownName)
+    }
+
+    /// Writes the archive to a stream.
+    ///
+    /// # Errors
+    ///
+    /// Returns the [`std::io::Error`] of the underlying writer.
+    pub fn write_to<W: Write>(&self, w: &mut W) -> std::io::Result<()> {
+        let mut bytes = Vec::with_capacity(HEADER_LEN);
+        bytes.extend_from_slice(&MAGIC);
+        bytes.push(VERSION);
+        bytes.extend_from_slice(&entry_count(self.entries.len()).to_le_bytes());
+
+        let mut index = Vec::new();
+        for (name, dump) in &self.entries {
+            put_name(&mut bytes, name);
+            bytes.extend_from_slice(&dump_len(dump).to_le_bytes());
+            put_name(&mut index, name);
+            index.extend_from_slice(&(bytes.len() as u64).to_le_bytes());
+            index.extend_from_slice(&dump_len(dump).to_le_bytes());
+            bytes.extend_from_slice(dump);
+        }
+
+        let index_offset = bytes.len() as u64;
+        bytes.extend_from_slice(&index);
+        bytes.extend_from_slice(&index_offset.to_le_bytes());
+        bytes.extend_from_slice(&MAGIC);
+        w.write_all(&bytes)
+    }
+
+    /// Reads a whole archive written by [`SketchArchive::write_to`] from a stream.
+    ///
+    /// The structure of the archive is validated, but the sketches themselves are only parsed by
+    /// [`SketchArchive::get`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`HypeerlogError::Io`] if the reader fails, [`HypeerlogError::InvalidMagic`] if the data is not an
+    /// archive, [`HypeerlogError::UnsupportedVersion`] if it was written by a newer release, and
+    /// [`HypeerlogError::InvalidLength`] if it is truncated or its index is inconsistent.
+    pub fn read_from<R: Read>(r: &mut R) -> Result<Self, HypeerlogError> {
+        let mut bytes = Vec::new();
+        r.read_to_end(&mut bytes)?;
+
+        let header = bytes.get(..HEADER_LEN).ok_or(HypeerlogError::InvalidLength)?;
+        let count = parse_header(header.try_into().expect("the header has a fixed length"))?;
+        let footer_start = bytes.len().checked_sub(FOOTER_LEN).filter(|&start| start >= HEADER_LEN).ok_or(HypeerlogError::InvalidLength)?;
+        let index_offset = parse_footer(bytes[footer_start..].try_into().expect("the footer has a fixed length"))?;
+        let index_offset = usize::try_from(index_offset).ok().filter(|offset| (HEADER_LEN..=footer_start).contains(offset)).ok_or(HypeerlogError::InvalidLength)?;
+
+        let records = parse_index(&bytes[index_offset..footer_start], count)?;
+        let mut entries = BTreeMap::new();
+        for (name, offset, len) in records {
+            let dump = usize::try_from(offset)
+                .ok()
+                .and_then(|start| bytes[..index_offset].get(start..start.checked_add(len as usize)?))
+                .ok_or(HypeerlogError::InvalidLength)?;
+            entries.insert(name, dump.to_vec());
+        }
+        Ok(SketchArchive { entries })
+    }
+
+    /// Reads only the sketch called `name` from an archive, seeking to it through the index.
+    ///
+    /// Only the header, the footer, the index and the dump of that sketch are read, which is much cheaper than
+    /// [`SketchArchive::read_from`] when a few sketches of a large archive file are needed. The sketch is
+    /// loaded with the default `Murmur3BuildHasher`, as by [`SketchArchive::get`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of [`SketchArchive::read_from`] for an invalid archive, [`HypeerlogError::UnknownName`]
+    /// if it holds no sketch called `name`, or any error of [`Hypeerlog::load`] if its dump is invalid.
+    pub fn read_one<R: Read + Seek>(r: &mut R, name: &str) -> Result<Hypeerlog, HypeerlogError> {
+        let mut header = [0; HEADER_LEN];
+        r.seek(SeekFrom::Start(0))?;
+        read_exact_or_truncated(r, &mut header)?;
+        let count = parse_header(&header)?;
+
+        let end = r.seek(SeekFrom::End(0))?;
+        let footer_start = end.checked_sub(FOOTER_LEN as u64).filter(|&start| start >= HEADER_LEN as u64).ok_or(HypeerlogError::InvalidLength)?;
+        let mut footer = [0; FOOTER_LEN];
+        r.seek(SeekFrom::Start(footer_start))?;
+        read_exact_or_truncated(r, &mut footer)?;
+        let index_offset = parse_footer(&footer)?;
+        if !(HEADER_LEN as u64..=footer_start).contains(&index_offset) {
+            return Err(HypeerlogError::InvalidLength);
+        }
+
+        let mut index = vec![0; (footer_start - index_offset) as usize];
+        r.seek(SeekFrom::Start(index_offset))?;
+        read_exact_or_truncated(r, &mut index)?;
+        // Duplicate names cannot be written, but the last one wins as in `read_from` if they are present
+        let (_, offset, len) = parse_index(&index, count)?
+            .into_iter()
+            .rev()
+            .find(|(record, _, _)| record == name)
+            .ok_or(HypeerlogError::UnknownName)?;
+        if offset.checked_add(len as u64).is_none_or(|dump_end| offset < HEADER_LEN as u64 || dump_end > index_offset) {
+            return Err(HypeerlogError::InvalidLength);
+        }
+
+        let mut dump = vec![0; len as usize];
+        r.seek(SeekFrom::Start(offset))?;
+        read_exact_or_truncated(r, &mut dump)?;
+        Hypeerlog::load(dump)
+    }
+}
+
+
+fn entry_count(len: usize) -> u32 {
+    u32::try_from(len).expect("an archive holds fewer than 2^32 sketches")
+}
+
+// Dumps are at most a few bytes more than 2^25 registers
+fn dump_len(dump: &[u8]) -> u32 {
+    dump.len() as u32
+}
+
+fn put_name(out: &mut Vec<u8>, name: &str) {
+    let len = u32::try_from(name.len()).expect("sketch names are shorter than 4 GiB");
+    out.extend_from_slice(&len.to_le_bytes());
+    out.extend_from_slice(name.as_bytes());
+}
+
+fn parse_header(header: &[u8; HEADER_LEN]) -> Result<u32, HypeerlogError> {
+    if header[..4] != MAGIC {
+        return Err(HypeerlogError::InvalidMagic);
+    }
+    if header[4] != VERSION {
+        return Err(HypeerlogError::UnsupportedVersion);
+    }
+    Ok(u32::from_le_bytes(header[5..].try_into().expect("the count is 4 bytes")))
+}
+
+// A missing magic at the end means that the archive was cut short
+fn parse_footer(footer: &[u8; FOOTER_LEN]) -> Result<u64, HypeerlogError> {
+    if footer[8..] != MAGIC {
+        return Err(HypeerlogError::InvalidLength);
+    }
+    Ok(u64::from_le_bytes(footer[..8].try_into().expect("the offset is 8 bytes")))
+}
+
+// Parses exactly `count` index records spanning the whole of `index`
+fn parse_index(mut index: &[u8], count: u32) -> Result<Vec<(String, u64, u32)>, HypeerlogError> {
+    let mut records = Vec::new();
+    for _ in 0..count {
+        let name_len = take_u32(&mut index)? as usize;
+        let name = take(&mut index, name_len)?;
+        let name = String::from(core::str::from_utf8(name).map_err(|_| HypeerlogError::Encoding)?);
+        let offset = u64::from_le_bytes(take(&mut index, 8)?.try_into().expect("took 8 bytes"));
+        let len = take_u32(&mut index)?;
+        records.push((name, offset, len));
+    }
+    if !index.is_empty() {
+        return Err(HypeerlogError::InvalidLength);
+    }
+    Ok(records)
+}
+
+fn take<'a>(bytes: &mut &'a [u8], n: usize) -> Result<&'a [u8], HypeerlogError> {
+    if bytes.len() < n {
+        return Err(HypeerlogError::InvalidLength);
+    }
+    let (head, tail) = bytes.split_at(n);
+    *bytes = tail;
+    Ok(head)
+}
+
+fn take_u32(bytes: &mut &[u8]) -> Result<u32, HypeerlogError> {
+    Ok(u32::from_le_bytes(take(bytes, 4)?.try_into().expect("took 4 bytes")))
+}
+
+// The archive is truncated if a region its header or index points to is missing
+fn read_exact_or_truncated<R: Read>(r: &mut R, buf: &mut [u8]) -> Result<(), HypeerlogError> {
+    r.read_exact(buf).map_err(|err| match err.kind() {
+        std::io::ErrorKind::UnexpectedEof => HypeerlogError::InvalidLength,
+        _ => err.into(),
+    })
+}
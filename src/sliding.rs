@@ -0,0 +1,230 @@
+use core::fmt;
+use core::hash::{BuildHasher, Hash};
+
+use crate::{vec, HasherId, Hypeerlog, Murmur3BuildHasher, Vec};
+use crate::registers::RegisterArray;
+use crate::utils::{estimate_from_registers, get_bucket, longest_run, pow_two, rel_error_from_p};
+
+
+
+// Each register keeps the frontier of its (timestamp, rank) pairs: sorted by increasing timestamp and strictly
+// decreasing rank, so that no pair has both an earlier timestamp and a lower rank than another. A pair that
+// does is never the largest rank of a window, since every window that contains it contains the other one too.
+// The largest rank of the items since a cutoff is then the rank of the first pair at or after the cutoff.
+type Frontier = Vec<(u64, u8)>;
+
+
+/// A HyperLogLog sketch over a sliding time window, which estimates the distinct elements inserted since any
+/// cutoff within its window.
+///
+/// Every insert carries a timestamp, in any unit, e.g. seconds. Instead of the largest rank only, each register
+/// keeps the ranks that are still the largest of some window, each with the latest timestamp it was seen at, so
+/// that [`SlidingHypeerlog::cardinality_since`] gives the same estimate as a [`Hypeerlog`] of the items inserted
+/// from the cutoff on. A register holds one pair of a timestamp and a rank for each rank that no later item of
+/// the register matched, which is on the order of the logarithm of the items of the register, and 16 bytes per
+/// pair.
+///
+/// Timestamps need not be increasing: a late item counts in the windows that contain its own timestamp.
+/// [`SlidingHypeerlog::prune`] forgets the pairs older than the window before a given time, which bounds the
+/// memory of a long-running stream; inserts older than the last pruned window are then ignored, and cutoffs
+/// before it only see the items that were kept.
+///
+/// ```
+/// use hypeerlog::SlidingHypeerlog;
+///
+/// // Distinct users in the last 24 hours, with timestamps in seconds
+/// let mut users = SlidingHypeerlog::with_precision_window(12, 24 * 3600);
+/// users.insert("ada", 1_000);
+/// users.insert("grace", 5_000);
+/// users.insert("ada", 90_000);
+/// users.insert("linus", 95_000);
+///
+/// let now = 100_000;
+/// users.prune(now);
+/// assert_eq!(users.cardinality_since(now - 24 * 3600).round(), 2.0);
+/// assert_eq!(users.cardinality_since(now - 2 * 3600).round(), 1.0);
+/// ```
+#[derive(Clone)]
+pub struct SlidingHypeerlog<S = Murmur3BuildHasher>
+where
+    S: BuildHasher + HasherId,
+{
+    hasher: S,
+    precision: u8,
+    window: u64,
+    // Pairs older than this were pruned, and later inserts older than it are ignored
+    horizon: u64,
+    frontiers: Vec<Frontier>,
+}
+
+
+impl<S> SlidingHypeerlog<S>
+where
+    S: BuildHasher + HasherId,
+{
+    /// Creates an empty sketch with a custom hasher builder, a specific precision and the length of its window,
+    /// in the unit of the timestamps.
+    ///
+    /// The precision value is silently clamped to the valid range of `4..=25`.
+    pub fn with_hasher_precision_window(precision: u8, window: u64, hasher_builder: S) -> Self {
+        let p = precision.clamp(4, 25);
+        SlidingHypeerlog {
+            hasher: hasher_builder,
+            precision: p,
+            window,
+            horizon: 0,
+            frontiers: vec![Vec::new(); pow_two(p) as usize],
+        }
+    }
+
+    /// Returns the precision of the sketch.
+    pub fn precision(&self) -> u8 {
+        self.precision
+    }
+
+    /// Returns the length of the window that [`SlidingHypeerlog::prune`] keeps.
+    pub fn window(&self) -> u64 {
+        self.window
+    }
+
+    /// Returns the expected standard relative error of the precision.
+    pub fn relative_error(&self) -> f64 {
+        rel_error_from_p(self.precision as u32)
+    }
+
+    /// Inserts a single hashable item seen at `timestamp`.
+    ///
+    /// Returns `true` if the item is now the largest rank of some window of its register, and `false` if a later
+    /// or equally recent item of the register already had an equal or higher rank, or if `timestamp` is older
+    /// than the window of the last [`SlidingHypeerlog::prune`].
+    pub fn insert<H: Hash>(&mut self, data: H, timestamp: u64) -> bool {
+        self.insert_hashed(self.hasher.hash_one(&data), timestamp)
+    }
+
+    /// Inserts an item by a hash computed outside of the sketch, see [`Hypeerlog::insert_hashed`].
+    ///
+    /// Returns whether the item was kept, like [`SlidingHypeerlog::insert`].
+    pub fn insert_hashed(&mut self, hash: u64, timestamp: u64) -> bool {
+        if timestamp < self.horizon {
+            return false;
+        }
+        let rank = longest_run(self.precision, hash);
+        let frontier = &mut self.frontiers[get_bucket(self.precision, hash)];
+
+        // The pairs from `timestamp` on have decreasing ranks, so the first of them has the largest
+        let later = frontier.partition_point(|&(ts, _)| ts < timestamp);
+        if frontier.get(later).is_some_and(|&(_, r)| r >= rank) {
+            return false;
+        }
+        // The pairs up to `timestamp` with a rank of at most `rank` are now dominated, and they end that prefix
+        let end = frontier.partition_point(|&(ts, _)| ts <= timestamp);
+        let start = frontier[..end].partition_point(|&(_, r)| r > rank);
+        frontier.splice(start..end, [(timestamp, rank)]);
+        true
+    }
+
+    /// Returns the estimated distinct element count of the items inserted at or after `cutoff`.
+    ///
+    /// The estimate is the one of a [`Hypeerlog`] of these items, see [`Hypeerlog::cardinality`]. Items pruned
+    /// by [`SlidingHypeerlog::prune`] are not counted, so cutoffs before its window under-count.
+    pub fn cardinality_since(&self, cutoff: u64) -> f64 {
+        estimate_from_registers(self.precision, &self.registers_since(cutoff))
+    }
+
+    /// Returns the estimated distinct element count of every item that was kept, see
+    /// [`SlidingHypeerlog::cardinality_since`].
+    pub fn cardinality(&self) -> f64 {
+        self.cardinality_since(0)
+    }
+
+    /// Builds a [`Hypeerlog`] of the items inserted at or after `cutoff`, with the precision and hasher of this
+    /// sketch, e.g. to dump or merge the window.
+    ///
+    /// Its registers are the ones of a sketch of these items, and its insertion count is 0.
+    pub fn to_hypeerlog_since(&self, cutoff: u64) -> Hypeerlog<S>
+    where
+        S: Clone,
+    {
+        Hypeerlog {
+            hasher: self.hasher.clone(),
+            precision: self.precision,
+            registers: RegisterArray::new(self.registers_since(cutoff)),
+            insertions: 0,
+        }
+    }
+
+    /// Forgets the items older than the window before `now`, i.e. seen before `now - window`, and returns the
+    /// number of pairs removed.
+    ///
+    /// Later inserts older than that are ignored. Pruning with an earlier `now` than before removes nothing.
+    pub fn prune(&mut self, now: u64) -> usize {
+        self.horizon = self.horizon.max(now.saturating_sub(self.window));
+        let horizon = self.horizon;
+        self.frontiers.iter_mut()
+            .map(|frontier| {
+                let expired = frontier.partition_point(|&(ts, _)| ts < horizon);
+                frontier.drain(..expired);
+                expired
+            })
+            .sum()
+    }
+
+    /// Returns the number of (timestamp, rank) pairs held by the registers.
+    pub fn stored_pairs(&self) -> usize {
+        self.frontiers.iter().map(Vec::len).sum()
+    }
+
+    /// Returns `true` if no item is kept, because none was inserted or all of them were pruned.
+    pub fn is_empty(&self) -> bool {
+        self.frontiers.iter().all(Vec::is_empty)
+    }
+
+    /// Resets the sketch to an empty one, with the same precision, window and hasher, that accepts any timestamp.
+    pub fn clear(&mut self) {
+        self.horizon = 0;
+        self.frontiers.iter_mut().for_each(Vec::clear);
+    }
+
+    fn registers_since(&self, cutoff: u64) -> Vec<u8> {
+        self.frontiers.iter()
+            .map(|frontier| {
+                let since = frontier.partition_point(|&(ts, _)| ts < cutoff);
+                frontier.get(since).map_or(0, |&(_, rank)| rank)
+            })
+            .collect()
+    }
+}
+
+
+// Summarized like the `Debug` of `Hypeerlog`, by the number of stored pairs instead of the pairs
+impl<S> fmt::Debug for SlidingHypeerlog<S>
+where
+    S: BuildHasher + HasherId,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SlidingHypeerlog")
+            .field("precision", &self.precision)
+            .field("hasher_seed", &self.hasher.hasher_seed())
+            .field("hasher_tag", &self.hasher.hasher_tag())
+            .field("window", &self.window)
+            .field("horizon", &self.horizon)
+            .field("stored_pairs", &self.stored_pairs())
+            .finish()
+    }
+}
+
+
+impl SlidingHypeerlog<Murmur3BuildHasher> {
+    /// Creates an empty sketch using the default `Murmur3BuildHasher` with a default precision of 14, and the
+    /// length of its window.
+    pub fn with_window(window: u64) -> Self {
+        Self::with_precision_window(14, window)
+    }
+
+    /// Creates an empty sketch with a specific precision and the length of its window.
+    ///
+    /// The precision value is silently clamped to the valid range of `4..=25`.
+    pub fn with_precision_window(precision: u8, window: u64) -> Self {
+        Self::with_hasher_precision_window(precision, window, Murmur3BuildHasher::new(0))
+    }
+}
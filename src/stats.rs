@@ -0,0 +1,89 @@
+use core::fmt;
+use core::hash::BuildHasher;
+
+use crate::{HasherId, Hypeerlog};
+
+
+
+/// A diagnostic snapshot of a sketch, returned by [`Hypeerlog::stats`].
+///
+/// Its [`Display`](fmt::Display) form is one line of `key=value` pairs for logs, e.g.
+/// `p=14 registers=16384 nonzero=8703 zero=7681 max=12 estimate=12367.4 error=0.81% memory=16448B
+/// insertions=20000`. With the `serde` feature, it serializes as a struct of its fields.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SketchStats {
+    /// Precision of the sketch.
+    pub precision: u8,
+    /// Number of registers, $2^p$.
+    pub registers: usize,
+    /// Number of registers that saw at least one element.
+    pub nonzero_registers: usize,
+    /// Number of registers that saw no element, from which LinearCounting estimates small cardinalities.
+    pub zero_registers: usize,
+    /// Largest register value, 0 for an empty sketch.
+    pub max_register: u8,
+    /// Cardinality estimate, see [`Hypeerlog::cardinality`].
+    pub estimate: f64,
+    /// Expected standard relative error of the estimate, see [`Hypeerlog::relative_error`].
+    pub relative_error: f64,
+    /// Bytes held by the sketch: the struct itself and its registers, which an empty sketch has not allocated.
+    pub memory_bytes: usize,
+    /// Number of items inserted, see [`Hypeerlog::total_insertions`].
+    pub total_insertions: u64,
+}
+
+
+impl<S> Hypeerlog<S>
+where
+    S: BuildHasher + HasherId,
+{
+    /// Returns a diagnostic snapshot of the sketch, e.g. to log it.
+    ///
+    /// The register counts are computed in a single pass over the registers, like
+    /// [`Hypeerlog::register_histogram`], and none for an empty sketch.
+    ///
+    /// ```
+    /// use hypeerlog::Hypeerlog;
+    ///
+    /// let mut hll = Hypeerlog::with_precision(10);
+    /// hll.insert_many(&[1, 2, 3]);
+    /// let stats = hll.stats();
+    /// assert_eq!((stats.registers, stats.nonzero_registers, stats.zero_registers), (1024, 3, 1021));
+    /// assert_eq!(stats.estimate.round(), 3.0);
+    /// assert_eq!(stats.total_insertions, 3);
+    /// ```
+    pub fn stats(&self) -> SketchStats {
+        let histogram = self.register_histogram();
+        let zero_registers = histogram[0] as usize;
+        SketchStats {
+            precision: self.precision,
+            registers: self.registers.len(),
+            nonzero_registers: self.registers.len() - zero_registers,
+            zero_registers,
+            max_register: histogram.iter().rposition(|&count| count != 0).unwrap_or(0) as u8,
+            estimate: self.cardinality(),
+            relative_error: self.relative_error(),
+            memory_bytes: size_of::<Self>() + self.registers.allocated_bytes(),
+            total_insertions: self.insertions,
+        }
+    }
+}
+
+
+impl fmt::Display for SketchStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "p={} registers={} nonzero={} zero={} max={} estimate={:.1} error={:.2}% memory={}B insertions={}",
+            self.precision,
+            self.registers,
+            self.nonzero_registers,
+            self.zero_registers,
+            self.max_register,
+            self.estimate,
+            self.relative_error * 100.0,
+            self.memory_bytes,
+            self.total_insertions,
+        )
+    }
+}
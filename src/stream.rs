@@ -0,0 +1,93 @@
+use core::hash::BuildHasher;
+use std::io::{Read, Write};
+
+use crate::{HasherId, Hypeerlog, HypeerlogError, Vec};
+use crate::dump::{self, HEADER_LEN};
+
+
+
+impl<S> Hypeerlog<S>
+where
+    S: BuildHasher + HasherId,
+{
+    /// Writes the sketch to a stream in the versioned dump format, header first.
+    ///
+    /// The bytes are the same as those of [`Hypeerlog::dump`], so they can be read back with
+    /// [`Hypeerlog::read_from`] as well as [`Hypeerlog::load`], except for an empty sketch: its zero registers are
+    /// written out dense, since the empty compact payload of its dump would not tell a reader of the stream where
+    /// the dump ends.
+    ///
+    /// # Errors
+    ///
+    /// Returns the [`std::io::Error`] of the underlying writer, including when it stops accepting bytes partway.
+    pub fn write_to<W: Write>(&self, w: &mut W) -> std::io::Result<()> {
+        if !self.is_empty() {
+            return self.dump_to(w);
+        }
+        w.write_all(self.dense_dump_prefix().as_bytes())?;
+        self.registers.chunks().try_for_each(|chunk| w.write_all(chunk))
+    }
+
+    /// Reads a sketch written by [`Hypeerlog::write_to`] (or [`Hypeerlog::dump`]) from a stream, using a custom hasher.
+    ///
+    /// The header is read first, so the buffer for the registers is allocated once at its exact size, and
+    /// nothing past the end of the dump is consumed: several dumps can be read back to back from one stream.
+    /// Compact and compressed dumps do not record the length of their payload, so for those the rest of the
+    /// stream is read; packed dumps have a fixed length, like dense ones. Legacy dumps cannot be streamed this way;
+    /// use [`Hypeerlog::load_with_hasher`] for them.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`HypeerlogError::Io`] if the reader fails or ends early (with [`std::io::ErrorKind::UnexpectedEof`]),
+    /// [`HypeerlogError::TooLarge`] if a compact or compressed payload goes on past the size of any valid dump, or
+    /// any error of [`Hypeerlog::load_with_hasher`] if the dump itself is invalid.
+    pub fn read_from_with_hasher<R: Read>(r: &mut R, hasher_builder: S) -> Result<Self, HypeerlogError> {
+        Self::load_with_hasher(read_dump(r)?, hasher_builder)
+    }
+}
+
+
+impl Hypeerlog {
+    /// Reads a sketch written by [`Hypeerlog::write_to`] (or [`Hypeerlog::dump`]) from a stream, using the
+    /// default `Murmur3BuildHasher` restored from the seed recorded in the dump.
+    ///
+    /// See [`Hypeerlog::read_from_with_hasher`] for how the stream is consumed.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`HypeerlogError::Io`] if the reader fails or ends early, or any error of [`Hypeerlog::load`]
+    /// if the dump itself is invalid.
+    pub fn read_from<R: Read>(r: &mut R) -> Result<Self, HypeerlogError> {
+        Self::load(read_dump(r)?)
+    }
+}
+
+
+// Reads exactly one versioned dump from the stream, validating the header before allocating
+fn read_dump<R: Read>(r: &mut R) -> Result<Vec<u8>, HypeerlogError> {
+    let mut header = [0; HEADER_LEN];
+    r.read_exact(&mut header)?;
+    let (p, flags) = dump::parse_header(&header)?;
+
+    let prefix_len = HEADER_LEN + dump::fields_len(flags);
+    let registers_len = dump::registers_len(p, flags);
+    let mut bytes = Vec::with_capacity(prefix_len + registers_len.unwrap_or(0));
+    bytes.extend_from_slice(&header);
+
+    match registers_len {
+        Some(registers_len) => {
+            bytes.resize(prefix_len + registers_len, 0);
+            r.read_exact(&mut bytes[HEADER_LEN..])?;
+        }
+        None => {
+            // Bounded so that a stream that never ends cannot grow the buffer past any valid dump: after the header
+            // come the optional fields and the payload
+            let limit = dump::fields_len(flags) + dump::max_payload_len(p);
+            r.take(limit as u64 + 1).read_to_end(&mut bytes)?;
+            if bytes.len() > HEADER_LEN + limit {
+                return Err(HypeerlogError::TooLarge);
+            }
+        }
+    }
+    Ok(bytes)
+}
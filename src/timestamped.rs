@@ -0,0 +1,267 @@
+use core::fmt;
+use core::hash::{BuildHasher, Hash};
+
+use crate::{vec, HasherId, Hypeerlog, HypeerlogError, Murmur3BuildHasher, Vec};
+use crate::registers::RegisterArray;
+use crate::utils::{get_bucket, pow_two};
+
+
+
+// Timestamped dump layout, all integers little-endian:
+//
+// | Offset     | Size   | Field                                                            |
+// |------------|--------|------------------------------------------------------------------|
+// | 0          | 4      | magic `HLLT`                                                     |
+// | 4          | 1      | version (1)                                                      |
+// | 5          | 4      | length `n` of the dump of the registers                          |
+// | 9          | n      | dump of the registers, see `Hypeerlog::dump`                     |
+// | 9 + n      | 8 * 2^p | last update of each register                                    |
+const MAGIC: [u8; 4] = *b"HLLT";
+const VERSION: u8 = 1;
+const HEADER_LEN: usize = 9;
+
+
+/// A HyperLogLog sketch that records when each register was last updated, so that the contributions of old items
+/// can be expired with [`TimestampedHypeerlog::expire_before`].
+///
+/// Next to each register, the sketch stores the latest timestamp, in any unit, of the items that landed in it,
+/// whether they raised it or not. Expiring before a cutoff zeroes the registers that no item reached since then,
+/// which is exact for them, and keeps the others as they are. A kept register may still hold the rank of an older
+/// item, larger than the ranks of the items since the cutoff: the estimate after an expiry is biased high. The bias
+/// is negligible while the window holds fewer distinct items than the sketch has registers, since most registers
+/// then see no item of the window and are zeroed, and grows quickly past that, when almost every register is
+/// refreshed: at precision 12 (4096 registers), a window of 10,000 distinct items is overestimated by about 30%
+/// after 14 days of them, and by about 85% after 28, see the `timestamped` tests. [`crate::SlidingHypeerlog`]
+/// gives the unbiased estimate, at a higher memory cost, and [`crate::BucketedHypeerlog`] an unbiased one of whole
+/// buckets.
+///
+/// The timestamps take 8 bytes per register next to its byte, in this type only: [`Hypeerlog`] stays at one byte per
+/// register. Timestamps need not be increasing.
+///
+/// ```
+/// use hypeerlog::TimestampedHypeerlog;
+///
+/// let mut hll = TimestampedHypeerlog::with_precision(12);
+/// for day in 0..14u64 {
+///     hll.insert_many(&(day * 100..day * 100 + 100).collect::<Vec<_>>(), day);
+/// }
+///
+/// // Roughly this week's distinct items, which are far fewer than the registers
+/// hll.expire_before(7);
+/// assert!((hll.cardinality() - 700.0).abs() < 30.0);
+/// ```
+#[derive(Clone)]
+pub struct TimestampedHypeerlog<S = Murmur3BuildHasher>
+where
+    S: BuildHasher + HasherId,
+{
+    sketch: Hypeerlog<S>,
+    last_updated: Vec<u64>,
+}
+
+
+impl<S> TimestampedHypeerlog<S>
+where
+    S: BuildHasher + HasherId,
+{
+    /// Creates an empty sketch with a custom hasher builder and a specific precision.
+    ///
+    /// The precision value is silently clamped to the valid range of `4..=25`.
+    pub fn with_hasher_precision(precision: u8, hasher_builder: S) -> Self {
+        let sketch = Hypeerlog::with_hasher_precision(precision, hasher_builder);
+        let last_updated = vec![0; pow_two(sketch.precision) as usize];
+        TimestampedHypeerlog { sketch, last_updated }
+    }
+
+    /// Returns the precision of the sketch.
+    pub fn precision(&self) -> u8 {
+        self.sketch.precision()
+    }
+
+    /// Returns the expected standard relative error of the precision, before any expiry.
+    pub fn relative_error(&self) -> f64 {
+        self.sketch.relative_error()
+    }
+
+    /// Inserts a single hashable item seen at `timestamp`.
+    ///
+    /// Returns whether the register of the item was raised, like [`Hypeerlog::insert`]. Whether it was raised or
+    /// not, the last update of the register moves to `timestamp` if that is later.
+    pub fn insert<H: Hash>(&mut self, data: H, timestamp: u64) -> bool {
+        self.insert_hashed(self.sketch.hasher.hash_one(&data), timestamp)
+    }
+
+    /// Inserts an item by a hash computed outside of the sketch, see [`Hypeerlog::insert_hashed`].
+    ///
+    /// Returns whether the register was raised, like [`TimestampedHypeerlog::insert`].
+    pub fn insert_hashed(&mut self, hash: u64, timestamp: u64) -> bool {
+        let bucket = get_bucket(self.sketch.precision, hash);
+        self.last_updated[bucket] = self.last_updated[bucket].max(timestamp);
+        self.sketch.insert_hashed(hash)
+    }
+
+    /// Inserts a slice of items seen at the same `timestamp`, and returns the number of items that raised their
+    /// register, see [`TimestampedHypeerlog::insert`].
+    pub fn insert_many<H: Hash>(&mut self, data: &[H], timestamp: u64) -> usize {
+        data.iter().map(|item| self.insert(item, timestamp) as usize).sum()
+    }
+
+    /// Zeroes the registers last updated before `cutoff`, and returns how many it zeroed.
+    ///
+    /// The estimate then counts roughly the items seen since the cutoff, biased high, see [`TimestampedHypeerlog`].
+    /// The insertion count of the registers cannot be split by timestamp, so an expiry that zeroes any register
+    /// resets it to 0, see [`Hypeerlog::total_insertions`]; the count then only covers the inserts that follow.
+    pub fn expire_before(&mut self, cutoff: u64) -> usize {
+        let mut values = self.sketch.registers.to_vec();
+        let mut expired = 0;
+        for (value, last_updated) in values.iter_mut().zip(&mut self.last_updated) {
+            if *value != 0 && *last_updated < cutoff {
+                *value = 0;
+                *last_updated = 0;
+                expired += 1;
+            }
+        }
+        if expired > 0 {
+            self.sketch.registers = RegisterArray::new(values);
+            self.sketch.insertions = 0;
+        }
+        expired
+    }
+
+    /// Returns the timestamp of the last update of each register, 0 for the registers that saw no item or expired.
+    pub fn last_updated(&self) -> &[u64] {
+        &self.last_updated
+    }
+
+    /// Returns the estimated distinct element count of the registers that were not expired, see
+    /// [`Hypeerlog::cardinality`].
+    pub fn cardinality(&self) -> f64 {
+        self.sketch.cardinality()
+    }
+
+    /// Returns `true` if no register holds an item, because none was inserted or all of them expired.
+    pub fn is_empty(&self) -> bool {
+        self.sketch.is_empty()
+    }
+
+    /// Resets the registers and their timestamps back to zero.
+    pub fn clear(&mut self) {
+        self.sketch.clear();
+        self.last_updated.iter_mut().for_each(|ts| *ts = 0);
+    }
+
+    /// Returns the registers as a [`Hypeerlog`], without their timestamps, e.g. to merge them with other sketches.
+    pub fn to_hypeerlog(&self) -> Hypeerlog<S>
+    where
+        S: Clone,
+    {
+        self.sketch.clone()
+    }
+
+    /// Serializes the registers, see [`Hypeerlog::dump`], followed by their timestamps.
+    pub fn dump(&self) -> Vec<u8> {
+        let registers = self.sketch.dump();
+        let mut bytes = Vec::with_capacity(HEADER_LEN + registers.len() + 8 * self.last_updated.len());
+        bytes.extend_from_slice(&MAGIC);
+        bytes.push(VERSION);
+        bytes.extend_from_slice(&(registers.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&registers);
+        self.last_updated.iter().for_each(|ts| bytes.extend_from_slice(&ts.to_le_bytes()));
+        bytes
+    }
+
+    /// Reads a sketch written by [`TimestampedHypeerlog::dump`], with a custom hasher.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`HypeerlogError::InvalidMagic`] if the data is not a timestamped dump,
+    /// [`HypeerlogError::UnsupportedVersion`] if it was written by a newer release, [`HypeerlogError::InvalidLength`]
+    /// if it is truncated or has not one timestamp per register, or any error of
+    /// [`Hypeerlog::from_bytes_with_hasher`] for the dump of the registers.
+    pub fn from_bytes_with_hasher(bytes: &[u8], hasher_builder: S) -> Result<Self, HypeerlogError> {
+        let (registers, timestamps) = split_dump(bytes)?;
+        Self::from_parts(Hypeerlog::from_bytes_with_hasher(registers, hasher_builder)?, timestamps)
+    }
+
+    fn from_parts(sketch: Hypeerlog<S>, timestamps: &[u8]) -> Result<Self, HypeerlogError> {
+        if timestamps.len() != 8 * sketch.registers.len() {
+            return Err(HypeerlogError::InvalidLength);
+        }
+        let last_updated = timestamps.chunks_exact(8)
+            .map(|ts| u64::from_le_bytes(ts.try_into().expect("chunks of 8 bytes")))
+            .collect();
+        Ok(TimestampedHypeerlog { sketch, last_updated })
+    }
+}
+
+
+// Summarized like the `Debug` of `Hypeerlog`, by the latest timestamp instead of the timestamps of the registers
+impl<S> fmt::Debug for TimestampedHypeerlog<S>
+where
+    S: BuildHasher + HasherId,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TimestampedHypeerlog")
+            .field("precision", &self.sketch.precision)
+            .field("hasher_seed", &self.sketch.hasher.hasher_seed())
+            .field("hasher_tag", &self.sketch.hasher.hasher_tag())
+            .field("last_update", &self.last_updated.iter().max())
+            .field("estimate", &self.cardinality())
+            .finish()
+    }
+}
+
+
+impl TimestampedHypeerlog<Murmur3BuildHasher> {
+    /// Creates an empty sketch using the default `Murmur3BuildHasher` with a default precision of 14.
+    pub fn new() -> Self {
+        Self::with_precision(14)
+    }
+
+    /// Creates an empty sketch with a specific precision.
+    ///
+    /// The precision value is silently clamped to the valid range of `4..=25`.
+    pub fn with_precision(precision: u8) -> Self {
+        Self::with_hasher_precision(precision, Murmur3BuildHasher::new(0))
+    }
+
+    /// Creates an empty sketch with a specific precision and a custom seed for the default `Murmur3BuildHasher`.
+    pub fn with_precision_seed(precision: u8, seed: u32) -> Self {
+        Self::with_hasher_precision(precision, Murmur3BuildHasher::new(seed))
+    }
+
+    /// Reads a sketch written by [`TimestampedHypeerlog::dump`], using the default `Murmur3BuildHasher` restored
+    /// from the seed recorded in the dump of its registers.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`TimestampedHypeerlog::from_bytes_with_hasher`].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, HypeerlogError> {
+        let (registers, timestamps) = split_dump(bytes)?;
+        Self::from_parts(Hypeerlog::from_bytes(registers)?, timestamps)
+    }
+}
+
+impl Default for TimestampedHypeerlog<Murmur3BuildHasher> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+
+// The dump of the registers and the timestamps of a timestamped dump
+fn split_dump(bytes: &[u8]) -> Result<(&[u8], &[u8]), HypeerlogError> {
+    if bytes.get(..4).is_none_or(|magic| magic != MAGIC) {
+        return Err(HypeerlogError::InvalidMagic);
+    }
+    let header = bytes.get(..HEADER_LEN).ok_or(HypeerlogError::InvalidLength)?;
+    if header[4] != VERSION {
+        return Err(HypeerlogError::UnsupportedVersion);
+    }
+    let len = u32::from_le_bytes(header[5..].try_into().expect("the length is 4 bytes")) as usize;
+    let rest = &bytes[HEADER_LEN..];
+    if rest.len() < len {
+        return Err(HypeerlogError::InvalidLength);
+    }
+    Ok(rest.split_at(len))
+}
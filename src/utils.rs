@@ -18,9 +18,38 @@ pub fn get_bucket(precision: u8, hash: u64) -> usize {
     (mask & hash) as usize
 }
 
+// The rank is capped at the number of hash bits left after the bucket index, so an all-zero
+// remainder maps to `max_rank` instead of 65
 #[inline]
 pub fn longest_run(precision: u8, hash: u64) -> u8 {
-    (hash >> precision).trailing_zeros() as u8 + 1
+    (hash >> precision).trailing_zeros().min(64 - precision as u32) as u8 + 1
+}
+
+// Hints the CPU to start loading the cache line of `bytes[index]`; a no-op where no stable prefetch
+// instruction is available
+#[inline(always)]
+pub(crate) fn prefetch(bytes: &[u8], index: usize) {
+    #[cfg(target_arch = "x86_64")]
+    {
+        use core::arch::x86_64::{_mm_prefetch, _MM_HINT_T0};
+        let ptr = bytes.as_ptr().wrapping_add(index) as *const i8;
+        // SAFETY: SSE is part of the x86_64 baseline, and a prefetch is only a hint that never faults
+        // nor reads the memory architecturally, whatever the address
+        unsafe { _mm_prefetch::<_MM_HINT_T0>(ptr) };
+    }
+}
+
+// The largest register value a 64-bit hash can produce at the given precision
+#[inline]
+pub fn max_rank(precision: u8) -> u8 {
+    64 - precision + 1
+}
+
+// The estimate from which collisions of hashes of `hash_bits` bits bias the estimates, 2^bits / 30: the threshold
+// of the large-range correction of the original HyperLogLog paper, which has 32-bit hashes
+#[inline]
+pub fn large_range_threshold(hash_bits: u32) -> f64 {
+    (1u128 << hash_bits.clamp(1, 64)) as f64 / 30.0
 }
 
 
@@ -36,10 +65,86 @@ const TWO_POW_NEG: [f64; 65] = {
     table
 };
 
+// 2^(32-val) for the values up to 32 and 2^(64-val) for the others, the two halves of `fixed_two_pow_neg`
+const FIXED_TWO_POW_NEG: [(u64, u64); 65] = {
+    let mut table = [(0, 0); 65];
+    let mut i = 0;
+    while i < 65 {
+        table[i] = if i <= 32 { (1 << (32 - i), 0) } else { (0, 1 << (64 - i)) };
+        i += 1;
+    }
+    table
+};
+
+// The number of zero registers and the sum of `fixed_two_pow_neg` over all registers (the denominator of the
+// harmonic mean, summed exactly), in a single pass, 32 registers at a time with AVX2 (with the `simd` feature)
+#[inline]
+pub fn register_aggregates(registers: &[u8]) -> (usize, u128) {
+    #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+    if crate::simd::avx2_available() {
+        // SAFETY: AVX2 was detected on the running CPU
+        return unsafe { crate::simd::register_aggregates_avx2(registers) };
+    }
+    histogram_aggregates(registers)
+}
+
+// `register_aggregates` of the registers of the union of two sketches with the same precision
+#[inline]
+pub fn union_aggregates(a: &[u8], b: &[u8]) -> (usize, u128) {
+    #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+    if crate::simd::avx2_available() {
+        // SAFETY: AVX2 was detected on the running CPU
+        return unsafe { crate::simd::union_aggregates_avx2(a, b) };
+    }
+    scalar_aggregates(a.iter().zip(b).map(|(a, b)| *a.max(b)))
+}
+
+// The scalar loop of `register_aggregates`. Neither half of a term exceeds 2^32, so each half of the sum fits
+// a `u64` for up to 2^25 registers, and the loop needs no 128-bit additions
+#[inline]
+pub fn scalar_aggregates(registers: impl Iterator<Item = u8>) -> (usize, u128) {
+    let (zeros, high, low) = registers
+        .fold((0usize, 0u64, 0u64), |(zeros, high, low), val| {
+            let (h, l) = FIXED_TWO_POW_NEG[val as usize];
+            (zeros + (val == 0) as usize, high + h, low + l)
+        });
+    (zeros, ((high as u128) << 32) + low as u128)
+}
+
+// The scalar `register_aggregates` of a slice, from the number of registers of each value. The counts go to four
+// histograms in turn, so that consecutive registers of the same value do not wait on each other's increment
+fn histogram_aggregates(registers: &[u8]) -> (usize, u128) {
+    let mut counts = [[0u32; 256]; 4];
+    let mut chunks = registers.chunks_exact(4);
+    for chunk in &mut chunks {
+        for (histogram, &val) in counts.iter_mut().zip(chunk) {
+            histogram[val as usize] += 1;
+        }
+    }
+    for &val in chunks.remainder() {
+        counts[0][val as usize] += 1;
+    }
+    let count = |val: usize| counts.iter().map(|histogram| histogram[val]).sum::<u32>();
+    let sum = (0..FIXED_TWO_POW_NEG.len()).map(|val| count(val) as u128 * fixed_two_pow_neg(val as u8)).sum();
+    (count(0) as usize, sum)
+}
+
+// 2^(-val) scaled by 2^64, the fixed-point term of a register value in `register_aggregates`
+#[inline]
+pub fn fixed_two_pow_neg(val: u8) -> u128 {
+    1 << (64 - val as u32)
+}
+
+// Converts a fixed-point sum of `fixed_two_pow_neg` terms back to a float, rounding once
+#[inline]
+pub fn fixed_to_f64(sum: u128) -> f64 {
+    sum as f64 * TWO_POW_NEG[64]
+}
+
+// 2^(-val) for a single register value
 #[inline]
-pub fn harmonic_mean(registers: &[u8]) -> f64 {
-    let sum: f64 = registers.iter().map(|&val| TWO_POW_NEG[val as usize]).sum();
-    1.0 / sum
+pub fn two_pow_neg(val: u8) -> f64 {
+    TWO_POW_NEG[val as usize]
 }
 
 // Bias correction for the given number of registers
@@ -90,3 +195,182 @@ pub fn p_from_rel_error(target_rse: f64) -> u32 {
 
 
 
+
+// Estimates the cardinality of a register array
+#[inline]
+pub fn estimate_from_registers(precision: u8, registers: &[u8]) -> f64 {
+    let (num_zero_registers, sum) = register_aggregates(registers);
+    estimate_cardinality(precision, num_zero_registers, fixed_to_f64(sum))
+}
+
+// Turns the zero-register count and the register sum into the final estimate, applying the bias
+// correction and switching to LinearCounting for low-range estimates
+#[inline]
+pub fn estimate_cardinality(precision: u8, num_zero_registers: usize, register_sum: f64) -> f64 {
+    let m = pow_two(precision) as f64;
+    let alpha_m = get_alpha_m_bias(m);
+
+    if num_zero_registers == m as usize {
+        return 0.0;
+    }
+
+    let harmonic_mean = 1.0 / register_sum;
+    let mut estimate = alpha_m * m * m * harmonic_mean;
+
+    // Use LinearCounting if there are still empty buckets AND the raw HLL estimate is low
+    if num_zero_registers > 0 && estimate < (2.5 * m) { 
+        // Linear Counting formula: m * ln(m / V)
+        // V is the number of zero registers.
+        // estimate = m * (m / num_zero_registers as f64).ln();
+
+        let ratio = m / num_zero_registers as f64;
+        
+        #[cfg(not(feature = "no_std"))]
+        {
+            estimate = m * ratio.ln();
+        }
+        #[cfg(feature = "no_std")]
+        {
+            estimate = m * libm::log(ratio); // libm::log is the natural log (ln)
+        }
+    }
+    estimate
+}
+
+// Rounds an estimate half-up to an integer count, clamped to `0..=u64::MAX` (NaN gives 0). The float to
+// integer casts saturate, and comparing the fraction instead of adding 0.5 keeps 0.49999999999999994 at 0
+#[inline]
+pub fn round_count(estimate: f64) -> u64 {
+    let floor = estimate as u64;
+    if estimate - floor as f64 >= 0.5 { floor.saturating_add(1) } else { floor }
+}
+
+
+
+// Slicing-by-8 lookup tables for the reflected IEEE CRC32 polynomial: `CRC32_TABLES[0]` is the
+// classic byte-wise table, and `CRC32_TABLES[k]` advances a byte through `k` further zero bytes
+const CRC32_TABLES: [[u32; 256]; 8] = {
+    let mut tables = [[0u32; 256]; 8];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+            bit += 1;
+        }
+        tables[0][i] = crc;
+        i += 1;
+    }
+    let mut k = 1;
+    while k < 8 {
+        let mut i = 0;
+        while i < 256 {
+            let prev = tables[k - 1][i];
+            tables[k][i] = (prev >> 8) ^ tables[0][(prev & 0xFF) as usize];
+            i += 1;
+        }
+        k += 1;
+    }
+    tables
+};
+
+// The standard CRC32 (IEEE 802.3, as used by zlib and gzip) of the given bytes
+#[inline]
+pub fn crc32(bytes: &[u8]) -> u32 {
+    !crc32_update(!0, bytes)
+}
+
+// The CRC32 of `len` zero bytes, the checksum of the registers of a sketch that never saw an element
+pub fn crc32_zeros(len: usize) -> u32 {
+    const ZEROS: [u8; 256] = [0; 256];
+    let mut crc = !0u32;
+    let mut left = len;
+    while left > 0 {
+        let chunk = left.min(ZEROS.len());
+        crc = crc32_update(crc, &ZEROS[..chunk]);
+        left -= chunk;
+    }
+    !crc
+}
+
+// Feeds bytes to the (inverted) state of a CRC32 computation
+#[inline]
+fn crc32_update(mut crc: u32, bytes: &[u8]) -> u32 {
+    let t = &CRC32_TABLES;
+
+    let mut chunks = bytes.chunks_exact(8);
+    for chunk in &mut chunks {
+        let lo = crc ^ u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+        let hi = u32::from_le_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]);
+        crc = t[7][(lo & 0xFF) as usize]
+            ^ t[6][((lo >> 8) & 0xFF) as usize]
+            ^ t[5][((lo >> 16) & 0xFF) as usize]
+            ^ t[4][(lo >> 24) as usize]
+            ^ t[3][(hi & 0xFF) as usize]
+            ^ t[2][((hi >> 8) & 0xFF) as usize]
+            ^ t[1][((hi >> 16) & 0xFF) as usize]
+            ^ t[0][(hi >> 24) as usize];
+    }
+    for &b in chunks.remainder() {
+        crc = t[0][((crc ^ b as u32) & 0xFF) as usize] ^ (crc >> 8);
+    }
+    crc
+}
+
+
+// Integer writes of `Hasher` that feed the little-endian bytes of the value to `write`, instead of the
+// native-endian default forwarding, so that the same values land in the same registers on every platform
+// and with every release of std. `usize` and `isize` are widened to 64 bits for the same reason.
+macro_rules! write_integers_le {
+    () => {
+        fn write_u8(&mut self, i: u8) {
+            self.write(&i.to_le_bytes());
+        }
+
+        fn write_u16(&mut self, i: u16) {
+            self.write(&i.to_le_bytes());
+        }
+
+        fn write_u32(&mut self, i: u32) {
+            self.write(&i.to_le_bytes());
+        }
+
+        fn write_u64(&mut self, i: u64) {
+            self.write(&i.to_le_bytes());
+        }
+
+        fn write_u128(&mut self, i: u128) {
+            self.write(&i.to_le_bytes());
+        }
+
+        fn write_usize(&mut self, i: usize) {
+            self.write(&(i as u64).to_le_bytes());
+        }
+
+        fn write_i8(&mut self, i: i8) {
+            self.write(&i.to_le_bytes());
+        }
+
+        fn write_i16(&mut self, i: i16) {
+            self.write(&i.to_le_bytes());
+        }
+
+        fn write_i32(&mut self, i: i32) {
+            self.write(&i.to_le_bytes());
+        }
+
+        fn write_i64(&mut self, i: i64) {
+            self.write(&i.to_le_bytes());
+        }
+
+        fn write_i128(&mut self, i: i128) {
+            self.write(&i.to_le_bytes());
+        }
+
+        fn write_isize(&mut self, i: isize) {
+            self.write(&(i as i64).to_le_bytes());
+        }
+    };
+}
+pub(crate) use write_integers_le;
@@ -0,0 +1,184 @@
+use core::hash::BuildHasher;
+use core::ops::Range;
+
+use crate::{check_hashers, murmur_from_stored, HasherId, HasherStatus, Hypeerlog, HypeerlogError, Murmur3BuildHasher};
+use crate::dump::{self, Registers};
+use crate::registers::RegisterArray;
+use crate::utils::{estimate_cardinality, fixed_to_f64, fixed_two_pow_neg, pow_two, register_aggregates, union_aggregates};
+
+
+
+/// A read-only sketch whose registers are borrowed from a dump, created by [`Hypeerlog::borrow_dump`].
+///
+/// It supports estimating cardinalities and merging into an owned sketch without copying the $2^p$ register
+/// bytes, which suits dumps that sit in a memory-mapped file or a shared cache. It cannot be inserted into;
+/// use [`HypeerlogRef::to_hypeerlog`] to get an owned, mutable copy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HypeerlogRef<'a, S = Murmur3BuildHasher>
+where
+    S: BuildHasher + HasherId,
+{
+    pub(crate) hasher: S,
+    pub(crate) precision: u8,
+    // Empty for the compact dump of a sketch that never saw an element, whose registers are all zero
+    pub(crate) registers: &'a [u8],
+    pub(crate) insertions: u64,
+}
+
+
+impl<'a, S> Hypeerlog<S>
+where
+    S: BuildHasher + HasherId,
+{
+    /// Borrows the registers of a dump in place, using a custom hasher.
+    ///
+    /// The dump is validated exactly like [`Hypeerlog::from_bytes_with_hasher`] does, including its checksum. The
+    /// compact dump of a sketch that never saw an element, as written by [`Hypeerlog::dump`], has no register to
+    /// borrow and is read as all-zero registers.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`HypeerlogError::EncodedRegisters`] for dumps written by [`Hypeerlog::dump_compact`] with the compact
+    /// encoding, by [`PackedHypeerlog::dump`](crate::PackedHypeerlog::dump) or by one of the compressed variants,
+    /// and any error of [`Hypeerlog::from_bytes_with_hasher`] otherwise.
+    pub fn borrow_dump_with_hasher(bytes: &'a [u8], hasher_builder: S) -> Result<HypeerlogRef<'a, S>, HypeerlogError> {
+        Self::borrow_checked(bytes, |stored| {
+            stored.check(dump::StoredHasher::of(&hasher_builder)).map(|status| (hasher_builder, status))
+        })
+    }
+
+    fn borrow_checked(
+        bytes: &'a [u8],
+        make_hasher: impl FnOnce(dump::StoredHasher) -> Result<(S, HasherStatus), HypeerlogError>,
+    ) -> Result<HypeerlogRef<'a, S>, HypeerlogError> {
+        let (hasher, precision, range, insertions) = parse_dense(bytes, make_hasher)?;
+        Ok(HypeerlogRef {
+            hasher,
+            precision,
+            registers: &bytes[range],
+            insertions,
+        })
+    }
+}
+
+// Validates a dump whose registers can be borrowed in place, returning its hasher, precision, the range of its registers
+// and its insertion count
+pub(crate) fn parse_dense<S>(
+    bytes: &[u8],
+    make_hasher: impl FnOnce(dump::StoredHasher) -> Result<(S, HasherStatus), HypeerlogError>,
+) -> Result<(S, u8, Range<usize>, u64), HypeerlogError> {
+    let parsed = dump::parse_dump(bytes, dump::Checksum::Verify)?;
+    let range = match parsed.registers {
+        Registers::Dense(range) => range,
+        // The empty payload that ends the dump
+        Registers::Zeros => bytes.len()..bytes.len(),
+        Registers::Decoded(_) => return Err(HypeerlogError::EncodedRegisters),
+    };
+    let (hasher, _) = make_hasher(parsed.hasher)?;
+    Ok((hasher, parsed.precision, range, parsed.insertions))
+}
+
+impl<'a> Hypeerlog {
+    /// Borrows the registers of a dump in place, using the default `Murmur3BuildHasher` restored from its seed.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`Hypeerlog::borrow_dump_with_hasher`], with the hasher checks of [`Hypeerlog::load`].
+    pub fn borrow_dump(bytes: &'a [u8]) -> Result<HypeerlogRef<'a>, HypeerlogError> {
+        Self::borrow_checked(bytes, murmur_from_stored)
+    }
+}
+
+
+impl<'a, S> HypeerlogRef<'a, S>
+where
+    S: BuildHasher + HasherId,
+{
+    /// Returns the total number of register buckets of the borrowed sketch.
+    pub fn len(&self) -> usize {
+        pow_two(self.precision) as usize
+    }
+
+    /// Returns `true` if no element was ever inserted into the borrowed sketch.
+    pub fn is_empty(&self) -> bool {
+        self.registers.iter().all(|&val| val == 0)
+    }
+
+    /// Returns the precision of the borrowed sketch.
+    pub fn precision(&self) -> u8 {
+        self.precision
+    }
+
+    /// Estimates the number of unique elements, exactly like [`Hypeerlog::cardinality`] on the owned sketch.
+    pub fn cardinality(&self) -> f64 {
+        let (zeros, sum) = self.aggregates();
+        estimate_cardinality(self.precision, zeros, fixed_to_f64(sum))
+    }
+
+    /// Estimates the cardinality of the union of two borrowed sketches, without materializing the merged registers.
+    ///
+    /// The result is identical to merging the two sketches and calling [`Hypeerlog::cardinality`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`HypeerlogError::PrecisionMismatch`] if the precisions differ, or [`HypeerlogError::HasherMismatch`]
+    /// or [`HypeerlogError::SeedMismatch`] if the sketches were built with different hashers.
+    pub fn union_cardinality(&self, other: &HypeerlogRef<'_, S>) -> Result<f64, HypeerlogError> {
+        if self.precision != other.precision {
+            return Err(HypeerlogError::PrecisionMismatch);
+        }
+        check_hashers(&self.hasher, &other.hasher)?;
+
+        let (zeros, sum) = match (self.registers.is_empty(), other.registers.is_empty()) {
+            (false, false) => union_aggregates(self.registers, other.registers),
+            (_, true) => self.aggregates(),
+            (true, false) => other.aggregates(),
+        };
+        Ok(estimate_cardinality(self.precision, zeros, fixed_to_f64(sum)))
+    }
+
+    /// Max-merges the borrowed registers into an owned sketch, adding the insertion count of the dump to its
+    /// [`Hypeerlog::total_insertions`].
+    ///
+    /// # Errors
+    ///
+    /// Same as [`Hypeerlog::merge_with`]. `target` is not modified on error.
+    pub fn merge_into(&self, target: &mut Hypeerlog<S>) -> Result<(), HypeerlogError> {
+        if self.precision != target.precision {
+            return Err(HypeerlogError::PrecisionMismatch);
+        }
+        check_hashers(&self.hasher, &target.hasher)?;
+
+        if !self.registers.is_empty() {
+            target.registers.raise_all(self.registers);
+        }
+        target.insertions = target.insertions.saturating_add(self.insertions);
+        Ok(())
+    }
+
+    /// Copies the borrowed registers into an owned sketch.
+    pub fn to_hypeerlog(&self) -> Hypeerlog<S>
+    where
+        S: Clone,
+    {
+        Hypeerlog {
+            hasher: self.hasher.clone(),
+            precision: self.precision,
+            registers: if self.registers.is_empty() {
+                RegisterArray::zeroed(self.len())
+            } else {
+                RegisterArray::new(self.registers.to_vec())
+            },
+            insertions: self.insertions,
+        }
+    }
+
+    // The number of zero registers and the fixed-point harmonic sum of the registers, borrowed or all zero
+    fn aggregates(&self) -> (usize, u128) {
+        if self.registers.is_empty() {
+            (self.len(), self.len() as u128 * fixed_two_pow_neg(0))
+        } else {
+            register_aggregates(self.registers)
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use wasm_bindgen::prelude::*;
+
+use crate::Hypeerlog;
+
+
+
+/// A sketch with the default Murmur3 hasher for JavaScript, enabled by the `wasm` feature.
+///
+/// Strings and byte arrays are inserted through [`Hypeerlog::insert_str`] and [`Hypeerlog::insert_bytes`], the
+/// canonical byte-hashing path, and the `Uint8Array` of [`JsHll::dump`] is the dump of [`Hypeerlog::dump`]; a
+/// sketch built in a browser or an edge worker therefore has the registers of a native sketch of the same items,
+/// and its dumps load and merge on the server with [`Hypeerlog::load`].
+///
+/// Build the module with `cargo rustc --lib --release --target wasm32-unknown-unknown --features wasm
+/// --crate-type cdylib`, and generate its JavaScript glue with `wasm-bindgen --target web` (or `bundler` for
+/// workers).
+///
+/// ```js
+/// import { JsHll } from "hypeerlog";
+///
+/// const events = new JsHll(14, 0);
+/// events.insertStr("event-1");
+/// await fetch("/sketches", { method: "POST", body: events.dump() });
+/// ```
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct JsHll {
+    hll: Hypeerlog,
+}
+
+
+#[wasm_bindgen]
+impl JsHll {
+    /// Creates an empty sketch with a precision and a seed for the default Murmur3 hasher.
+    ///
+    /// The precision value is silently clamped to the valid range of `4..=25`.
+    #[wasm_bindgen(constructor)]
+    pub fn new(precision: u8, seed: u32) -> JsHll {
+        JsHll { hll: Hypeerlog::with_precision_seed(precision, seed) }
+    }
+
+    /// Reads a sketch from a dump, with the seed recorded in the dump, see [`Hypeerlog::from_bytes`].
+    ///
+    /// # Errors
+    ///
+    /// Throws the errors of [`Hypeerlog::from_bytes`] as JavaScript `Error`s.
+    pub fn load(bytes: &[u8]) -> Result<JsHll, JsError> {
+        Ok(JsHll { hll: Hypeerlog::from_bytes(bytes)? })
+    }
+
+    /// Inserts the UTF-8 bytes of a string, and returns whether its register was raised.
+    #[wasm_bindgen(js_name = insertStr)]
+    pub fn insert_str(&mut self, s: &str) -> bool {
+        self.hll.insert_str(s)
+    }
+
+    /// Inserts exactly the given bytes, and returns whether their register was raised.
+    #[wasm_bindgen(js_name = insertBytes)]
+    pub fn insert_bytes(&mut self, bytes: &[u8]) -> bool {
+        self.hll.insert_bytes(bytes)
+    }
+
+    /// Returns the estimated distinct element count.
+    pub fn cardinality(&self) -> f64 {
+        self.hll.cardinality()
+    }
+
+    /// Merges another sketch into this one, like [`Hypeerlog::merge_with`].
+    ///
+    /// # Errors
+    ///
+    /// Throws the errors of [`Hypeerlog::merge_with`] as JavaScript `Error`s, in which case `self` is not modified.
+    pub fn merge(&mut self, other: &JsHll) -> Result<(), JsError> {
+        Ok(self.hll.merge_with(&other.hll)?)
+    }
+
+    /// Serializes the sketch to a `Uint8Array`, see [`Hypeerlog::dump`].
+    pub fn dump(&self) -> Vec<u8> {
+        self.hll.dump()
+    }
+
+    /// Returns the precision of the sketch.
+    #[wasm_bindgen(getter)]
+    pub fn precision(&self) -> u8 {
+        self.hll.precision()
+    }
+}
+
+
+impl From<Hypeerlog> for JsHll {
+    fn from(hll: Hypeerlog) -> Self {
+        JsHll { hll }
+    }
+}
+
+impl From<JsHll> for Hypeerlog {
+    fn from(js: JsHll) -> Self {
+        js.hll
+    }
+}
@@ -0,0 +1,90 @@
+use core::hash::{BuildHasher, Hasher};
+
+use wyhash::{wyhash, WyHash};
+
+use crate::HasherId;
+use crate::utils::write_integers_le;
+
+
+
+/// The [`HasherId::hasher_tag`] of [`WyHashBuilder`].
+pub const WYHASH_TAG: u32 = u32::from_le_bytes(*b"WYHS");
+
+
+/// A `BuildHasher` for [`WyHasher`], seeded with a 64-bit seed.
+///
+/// wyhash only needs 64-bit multiplications, with no AES or SIMD instructions, which makes it the fastest of the
+/// optional hashers on small cores without those extensions (e.g. Cortex-A7 or other 32-bit ARM cores). It reads
+/// its input in a fixed byte order, so the same elements hash the same on every platform.
+///
+/// ```
+/// use hypeerlog::{Hypeerlog, WyHashBuilder};
+///
+/// let mut hll = Hypeerlog::with_hasher(WyHashBuilder::new(42));
+/// hll.insert_many(&[1u64, 2, 3, 2, 1]);
+/// assert_eq!(hll.cardinality().round(), 3.0);
+/// ```
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WyHashBuilder {
+    seed: u64,
+}
+
+impl WyHashBuilder {
+    /// Creates a builder of hashers with the given seed.
+    pub fn new(seed: u64) -> Self {
+        WyHashBuilder { seed }
+    }
+
+    /// Returns the seed of the built hashers.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+}
+
+impl BuildHasher for WyHashBuilder {
+    type Hasher = WyHasher;
+
+    fn build_hasher(&self) -> Self::Hasher {
+        WyHasher(WyHash::with_seed(self.seed))
+    }
+}
+
+impl HasherId for WyHashBuilder {
+    fn hasher_seed(&self) -> Option<u64> {
+        Some(self.seed)
+    }
+
+    fn reseeded(&self, seed: u64) -> Option<Self> {
+        Some(WyHashBuilder::new(seed))
+    }
+
+    fn hasher_tag(&self) -> Option<u32> {
+        Some(WYHASH_TAG)
+    }
+
+    fn hash_bytes(&self, bytes: &[u8]) -> u64 {
+        wyhash(bytes, self.seed)
+    }
+}
+
+
+/// The streaming wyhash hasher built by [`WyHashBuilder`].
+///
+/// Integers are written as their little-endian bytes (`usize` and `isize` widened to 64 bits) and
+/// [`Hasher::finish`] does not reset the state. Unlike the Murmur3 and XXH3 hashers, each [`Hasher::write`] is
+/// mixed in on its own, so the same bytes split over several writes hash differently than written at once;
+/// this is deterministic for [`Hash`](core::hash::Hash) implementations, which always write the same pieces.
+#[derive(Clone)]
+pub struct WyHasher(WyHash);
+
+impl Hasher for WyHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.write(bytes);
+    }
+
+    fn finish(&self) -> u64 {
+        self.0.finish()
+    }
+
+    write_integers_le!();
+}
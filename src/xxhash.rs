@@ -0,0 +1,93 @@
+use core::hash::{BuildHasher, Hasher};
+
+use xxhash_rust::xxh3::{xxh3_64_with_seed, Xxh3};
+
+use crate::HasherId;
+use crate::utils::write_integers_le;
+
+
+
+/// The [`HasherId::hasher_tag`] of [`Xxh3BuildHasher`].
+pub const XXH3_TAG: u32 = u32::from_le_bytes(*b"XXH3");
+
+
+/// A `BuildHasher` for [`Xxh3Hasher`], seeded with a 64-bit seed.
+///
+/// XXH3 (64-bit) is several times faster than the default `Murmur3BuildHasher` on long elements such as URLs
+/// or serialized documents, with an equally uniform distribution. Sketches built with it carry their own
+/// [`HasherId::hasher_tag`], so their dumps refuse to load or merge into Murmur3 sketches and vice versa.
+///
+/// The speedup is largest with [`Hypeerlog::insert_bytes`](crate::Hypeerlog::insert_bytes) and
+/// [`Hypeerlog::insert_str`](crate::Hypeerlog::insert_str), which hash the whole element at once; the streaming
+/// [`Xxh3Hasher`] behind [`Hypeerlog::insert`](crate::Hypeerlog::insert) has a larger state to set up per element.
+///
+/// ```
+/// use hypeerlog::{Hypeerlog, Xxh3BuildHasher};
+///
+/// let mut hll = Hypeerlog::with_hasher(Xxh3BuildHasher::new(42));
+/// hll.insert_str("https://example.com/a/long/path?with=query");
+/// assert!(hll.cardinality() > 0.0);
+/// ```
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Xxh3BuildHasher {
+    seed: u64,
+}
+
+impl Xxh3BuildHasher {
+    /// Creates a builder of hashers with the given seed.
+    pub fn new(seed: u64) -> Self {
+        Xxh3BuildHasher { seed }
+    }
+
+    /// Returns the seed of the built hashers.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+}
+
+impl BuildHasher for Xxh3BuildHasher {
+    type Hasher = Xxh3Hasher;
+
+    fn build_hasher(&self) -> Self::Hasher {
+        Xxh3Hasher(Xxh3::with_seed(self.seed))
+    }
+}
+
+impl HasherId for Xxh3BuildHasher {
+    fn hasher_seed(&self) -> Option<u64> {
+        Some(self.seed)
+    }
+
+    fn reseeded(&self, seed: u64) -> Option<Self> {
+        Some(Xxh3BuildHasher::new(seed))
+    }
+
+    fn hasher_tag(&self) -> Option<u32> {
+        Some(XXH3_TAG)
+    }
+
+    fn hash_bytes(&self, bytes: &[u8]) -> u64 {
+        xxh3_64_with_seed(bytes, self.seed)
+    }
+}
+
+
+/// The streaming XXH3 (64-bit) hasher built by [`Xxh3BuildHasher`].
+///
+/// Like [`Murmur3Hasher`](crate::Murmur3Hasher), bytes written in several pieces hash like the same bytes
+/// written at once, integers are written as their little-endian bytes (`usize` and `isize` widened to 64 bits),
+/// and [`Hasher::finish`] does not reset the state.
+#[derive(Clone)]
+pub struct Xxh3Hasher(Xxh3);
+
+impl Hasher for Xxh3Hasher {
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.update(bytes);
+    }
+
+    fn finish(&self) -> u64 {
+        self.0.digest()
+    }
+
+    write_integers_le!();
+}
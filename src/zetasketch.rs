@@ -0,0 +1,302 @@
+use core::hash::{BuildHasher, Hasher};
+
+use crate::{vec, HasherId, Hypeerlog, HypeerlogError, Vec};
+use crate::dump;
+use crate::registers::RegisterArray;
+use crate::utils::pow_two;
+
+
+
+// ZetaSketch `AggregatorStateProto` holding an HLL++ sketch, as produced by BigQuery's HLL_COUNT
+// functions. Only the fields used here are listed; all are protobuf varints unless noted:
+//
+// AggregatorStateProto
+// | field | name                              | value                                       |
+// |-------|-----------------------------------|---------------------------------------------|
+// | 1     | type                              | `112` (HYPERLOGLOG_PLUS_UNIQUE)             |
+// | 2     | num_values                        | number of values added, duplicates included |
+// | 3     | encoding_version                  | `2`                                         |
+// | 4     | value_type                        | `DefaultOpsType.Id` of the input values     |
+// | 112   | hyperloglogplus_unique_state      | HyperLogLogPlusUniqueStateProto (message)   |
+//
+// HyperLogLogPlusUniqueStateProto
+// | field | name                              | value                                       |
+// |-------|-----------------------------------|---------------------------------------------|
+// | 2     | sparse_size                       | number of sparse entries                    |
+// | 3     | precision_or_num_buckets          | normal precision                            |
+// | 4     | sparse_precision_or_num_buckets   | sparse precision                            |
+// | 5     | data                              | dense registers, one byte each (bytes)      |
+// | 6     | sparse_data                       | sparse entries (bytes)                      |
+//
+// Dense registers hold ranks like ours, so they carry over unchanged. ZetaSketch takes the bucket
+// from the top bits of its Fingerprint2011 hash rather than the low ones, which only matters for
+// inserting, not for estimating or merging.
+
+const TYPE_HLL_PLUS_UNIQUE: u64 = 112;
+const ENCODING_VERSION: u64 = 2;
+
+const FIELD_TYPE: u32 = 1;
+const FIELD_NUM_VALUES: u32 = 2;
+const FIELD_ENCODING_VERSION: u32 = 3;
+const FIELD_VALUE_TYPE: u32 = 4;
+const FIELD_HLL_STATE: u32 = 112;
+
+const FIELD_SPARSE_SIZE: u32 = 2;
+const FIELD_PRECISION: u32 = 3;
+const FIELD_SPARSE_PRECISION: u32 = 4;
+const FIELD_DATA: u32 = 5;
+const FIELD_SPARSE_DATA: u32 = 6;
+
+const MIN_PRECISION: u8 = 10;
+const MAX_PRECISION: u8 = 24;
+// ZetaSketch defaults the sparse precision to the normal one plus 5
+const SPARSE_PRECISION_DELTA: u8 = 5;
+const MAX_SPARSE_PRECISION: u8 = 25;
+
+const WIRE_VARINT: u8 = 0;
+const WIRE_FIXED64: u8 = 1;
+const WIRE_LEN: u8 = 2;
+const WIRE_FIXED32: u8 = 5;
+
+
+/// The [`HasherId::hasher_tag`] of [`ZetaSketchHasher`].
+pub const ZETASKETCH_TAG: u32 = u32::from_le_bytes(*b"ZETA");
+
+
+/// The hasher of sketches imported from ZetaSketch or BigQuery with [`Hypeerlog::from_zetasketch`].
+///
+/// It carries its own [`HasherId::hasher_tag`], and records the ZetaSketch value type of the sketch as its
+/// [`HasherId::hasher_seed`], so a dump of an imported sketch refuses to load or merge into a sketch built with
+/// another hasher, or imported from a sketch of another value type, which BigQuery would not merge either.
+///
+/// ZetaSketch hashes values with Fingerprint2011, which is not implemented here: the hash this hasher produces
+/// for [`Hypeerlog::insert`] is a placeholder that does not match BigQuery, so imported sketches should only be
+/// estimated, merged with each other and exported back.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ZetaSketchHasher {
+    value_type: u32,
+}
+
+impl ZetaSketchHasher {
+    /// Creates a hasher for sketches of the given ZetaSketch `DefaultOpsType.Id` value type.
+    pub fn new(value_type: u32) -> Self {
+        ZetaSketchHasher { value_type }
+    }
+
+    /// Returns the ZetaSketch `DefaultOpsType.Id` of the values of the sketch.
+    pub fn value_type(&self) -> u32 {
+        self.value_type
+    }
+}
+
+// FNV-1a, as a placeholder for the Fingerprint2011 hash that ZetaSketch uses
+pub struct ZetaSketchHasherState {
+    hash: u64,
+}
+
+impl Hasher for ZetaSketchHasherState {
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.hash = (self.hash ^ b as u64).wrapping_mul(0x100000001b3);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.hash
+    }
+}
+
+impl BuildHasher for ZetaSketchHasher {
+    type Hasher = ZetaSketchHasherState;
+
+    fn build_hasher(&self) -> Self::Hasher {
+        ZetaSketchHasherState { hash: 0xcbf29ce484222325 }
+    }
+}
+
+impl HasherId for ZetaSketchHasher {
+    fn hasher_seed(&self) -> Option<u64> {
+        Some(self.value_type as u64)
+    }
+
+    fn reseeded(&self, seed: u64) -> Option<Self> {
+        u32::try_from(seed).ok().map(ZetaSketchHasher::new)
+    }
+
+    fn hasher_tag(&self) -> Option<u32> {
+        Some(ZETASKETCH_TAG)
+    }
+}
+
+
+impl Hypeerlog<ZetaSketchHasher> {
+    /// Imports an HLL++ sketch serialized as a ZetaSketch `AggregatorStateProto`, such as the `BYTES` values
+    /// produced by BigQuery's `HLL_COUNT.INIT` and `HLL_COUNT.MERGE_PARTIAL`.
+    ///
+    /// Only the normal (dense) representation is supported, with precisions `10..=24`; the precision of the sketch
+    /// becomes its precision here, and its value type is kept in the [`ZetaSketchHasher`]. Unknown proto fields are
+    /// skipped. An empty sparse sketch, which is how BigQuery stores a sketch of no values, is imported as empty.
+    /// Since the Fingerprint2011 hash of ZetaSketch is not implemented, the imported sketch can only be merged with
+    /// other imports; dumps of it fail to load or merge with [`HypeerlogError::HasherMismatch`] anywhere else.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The bytes are not a well-formed proto ([`HypeerlogError::InvalidLength`]).
+    /// - The proto is not an HLL++ aggregator state, or lacks its HLL++ state ([`HypeerlogError::InvalidMagic`]).
+    /// - The state uses another encoding version, or holds sparse entries ([`HypeerlogError::UnsupportedEncoding`]).
+    /// - The precision is outside `10..=24` ([`HypeerlogError::InvalidPrecision`]).
+    /// - The dense data does not hold exactly $2^p$ registers ([`HypeerlogError::InvalidLength`]).
+    /// - A register is larger than any rank possible at that precision ([`HypeerlogError::InvalidRegisterValue`]).
+    pub fn from_zetasketch(bytes: &[u8]) -> Result<Self, HypeerlogError> {
+        let (mut aggregator_type, mut encoding_version, mut value_type, mut state) = (None, 1, 0, None);
+        let mut fields = Fields(bytes);
+        while let Some((field, value)) = fields.next_field()? {
+            match (field, value) {
+                (FIELD_TYPE, Value::Varint(v)) => aggregator_type = Some(v),
+                (FIELD_ENCODING_VERSION, Value::Varint(v)) => encoding_version = v,
+                (FIELD_VALUE_TYPE, Value::Varint(v)) => value_type = v as u32,
+                (FIELD_HLL_STATE, Value::Bytes(v)) => state = Some(v),
+                _ => {}
+            }
+        }
+        let state = match (aggregator_type, state) {
+            (Some(TYPE_HLL_PLUS_UNIQUE), Some(state)) => state,
+            _ => return Err(HypeerlogError::InvalidMagic),
+        };
+        if encoding_version != ENCODING_VERSION {
+            return Err(HypeerlogError::UnsupportedEncoding);
+        }
+
+        let (mut precision, mut sparse_size, mut data, mut sparse_data) = (0, 0, None, None);
+        let mut fields = Fields(state);
+        while let Some((field, value)) = fields.next_field()? {
+            match (field, value) {
+                (FIELD_SPARSE_SIZE, Value::Varint(v)) => sparse_size = v,
+                (FIELD_PRECISION, Value::Varint(v)) => precision = v,
+                (FIELD_DATA, Value::Bytes(v)) => data = Some(v),
+                (FIELD_SPARSE_DATA, Value::Bytes(v)) => sparse_data = Some(v),
+                _ => {}
+            }
+        }
+        if !(MIN_PRECISION as u64..=MAX_PRECISION as u64).contains(&precision) {
+            return Err(HypeerlogError::InvalidPrecision);
+        }
+        let precision = precision as u8;
+
+        let registers = match (data, sparse_data) {
+            (Some(data), None) if data.len() == pow_two(precision) as usize => data.to_vec(),
+            (Some(_), None) => return Err(HypeerlogError::InvalidLength),
+            (None, sparse) if sparse_size == 0 && sparse.is_none_or(|s| s.is_empty()) => vec![0; pow_two(precision) as usize],
+            _ => return Err(HypeerlogError::UnsupportedEncoding),
+        };
+        dump::check_ranks(precision, &registers)?;
+        Ok(Hypeerlog {
+            hasher: ZetaSketchHasher::new(value_type),
+            precision,
+            registers: RegisterArray::new(registers),
+            insertions: 0,
+        })
+    }
+
+    /// Exports the sketch as a ZetaSketch `AggregatorStateProto` in the normal (dense) representation, which BigQuery
+    /// accepts in `HLL_COUNT.MERGE`, `HLL_COUNT.MERGE_PARTIAL` and `HLL_COUNT.EXTRACT`.
+    ///
+    /// The sparse precision is set to the precision plus 5 (at most 25), the ZetaSketch default. The number of values
+    /// is not tracked here, so `num_values` is set to the rounded cardinality estimate, a lower bound of the real count.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`HypeerlogError::InvalidPrecision`] if the precision is outside `10..=24`, the range ZetaSketch supports.
+    pub fn to_zetasketch(&self) -> Result<Vec<u8>, HypeerlogError> {
+        if !(MIN_PRECISION..=MAX_PRECISION).contains(&self.precision) {
+            return Err(HypeerlogError::InvalidPrecision);
+        }
+
+        let mut state = Vec::with_capacity(self.registers.len() + 16);
+        put_varint_field(&mut state, FIELD_PRECISION, self.precision as u64);
+        put_varint_field(&mut state, FIELD_SPARSE_PRECISION, (self.precision + SPARSE_PRECISION_DELTA).min(MAX_SPARSE_PRECISION) as u64);
+        put_bytes_field(&mut state, FIELD_DATA, &self.register_values());
+
+        let mut bytes = Vec::with_capacity(state.len() + 32);
+        put_varint_field(&mut bytes, FIELD_TYPE, TYPE_HLL_PLUS_UNIQUE);
+        // Estimates are never negative, so adding 0.5 before truncating rounds to the nearest count
+        put_varint_field(&mut bytes, FIELD_NUM_VALUES, (self.cardinality() + 0.5) as u64);
+        put_varint_field(&mut bytes, FIELD_ENCODING_VERSION, ENCODING_VERSION);
+        put_varint_field(&mut bytes, FIELD_VALUE_TYPE, self.hasher.value_type as u64);
+        put_bytes_field(&mut bytes, FIELD_HLL_STATE, &state);
+        Ok(bytes)
+    }
+}
+
+
+// The protobuf fields of a message, in order
+struct Fields<'a>(&'a [u8]);
+
+enum Value<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+    Fixed,
+}
+
+impl<'a> Fields<'a> {
+    fn next_field(&mut self) -> Result<Option<(u32, Value<'a>)>, HypeerlogError> {
+        if self.0.is_empty() {
+            return Ok(None);
+        }
+        let key = self.varint()?;
+        let field = u32::try_from(key >> 3).map_err(|_| HypeerlogError::InvalidLength)?;
+        let value = match (key & 0b111) as u8 {
+            WIRE_VARINT => Value::Varint(self.varint()?),
+            WIRE_LEN => {
+                let len = usize::try_from(self.varint()?).map_err(|_| HypeerlogError::InvalidLength)?;
+                Value::Bytes(self.take(len)?)
+            }
+            WIRE_FIXED64 => { self.take(8)?; Value::Fixed }
+            WIRE_FIXED32 => { self.take(4)?; Value::Fixed }
+            _ => return Err(HypeerlogError::InvalidLength),
+        };
+        Ok(Some((field, value)))
+    }
+
+    fn varint(&mut self) -> Result<u64, HypeerlogError> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let (&b, rest) = self.0.split_first().ok_or(HypeerlogError::InvalidLength)?;
+            self.0 = rest;
+            value |= ((b & 0x7f) as u64) << shift;
+            if b & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(HypeerlogError::InvalidLength)
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], HypeerlogError> {
+        if len > self.0.len() {
+            return Err(HypeerlogError::InvalidLength);
+        }
+        let (taken, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(taken)
+    }
+}
+
+fn put_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn put_varint_field(out: &mut Vec<u8>, field: u32, value: u64) {
+    put_varint(out, (field as u64) << 3 | WIRE_VARINT as u64);
+    put_varint(out, value);
+}
+
+fn put_bytes_field(out: &mut Vec<u8>, field: u32, bytes: &[u8]) {
+    put_varint(out, (field as u64) << 3 | WIRE_LEN as u64);
+    put_varint(out, bytes.len() as u64);
+    out.extend_from_slice(bytes);
+}
@@ -1,12 +1,17 @@
-use hypeerlog::Hypeerlog;
+use hypeerlog::{assert_cardinality_within, HasherId, Hypeerlog};
 use rand::prelude::*;
 use std::collections::HashSet;
-use std::hash::Hash;
+use std::hash::{BuildHasher, Hash};
 
 
 
 
 fn generate_random_list_with_cardinality(length: usize, cardinality: usize) -> Result<Vec<u64>, String> {
+    generate_list_with_rng(&mut rand::rng(), length, cardinality)
+}
+
+// Like `generate_random_list_with_cardinality`, from a given generator, so that the accuracy tests are reproducible
+fn generate_list_with_rng(rng: &mut impl Rng, length: usize, cardinality: usize) -> Result<Vec<u64>, String> {
     if cardinality > length {
         return Err("Cardinality cannot be greater than length.".to_string());
     }
@@ -14,7 +19,6 @@ fn generate_random_list_with_cardinality(length: usize, cardinality: usize) -> R
         return Ok(vec![]);
     }
 
-    let mut rng = rand::rng();
     let mut unique_elems = HashSet::with_capacity(cardinality);
     let mut result_list = Vec::with_capacity(length);
 
@@ -33,11 +37,11 @@ fn generate_random_list_with_cardinality(length: usize, cardinality: usize) -> R
     let unique_elems_vec: Vec<u64> = unique_elems.into_iter().collect();
 
     for _ in 0..(length - cardinality) {
-        let random_index = rand::random_range(0..=unique_elems_vec.len() - 1);
+        let random_index = rng.random_range(0..=unique_elems_vec.len() - 1);
         result_list.push(unique_elems_vec[random_index]);
     }
 
-    result_list.shuffle(&mut rng);
+    result_list.shuffle(rng);
 
     Ok(result_list)
 }
@@ -45,8 +49,10 @@ fn generate_random_list_with_cardinality(length: usize, cardinality: usize) -> R
 
 
 
+// Each of the 46 estimates must be within 4 standard errors, which a correct estimator misses about once in 16,000
 #[test]
 fn test_accuracy() {
+    let mut rng = StdRng::seed_from_u64(390);
     let p_values = vec![6, 8, 10, 12];
     let cardinalities = vec![100, 200, 300, 400, 500, 600, 700, 800, 900, 1000];
 
@@ -55,10 +61,24 @@ fn test_accuracy() {
     for p in p_values {
         let m = 1_usize << p;
         for true_card in &cardinalities {
-            let list = generate_random_list_with_cardinality(3000, *true_card).unwrap();
-            let (estimate, relative_err) = run_trial(p, *true_card, &list);
+            let list = generate_list_with_rng(&mut rng, 3000, *true_card).unwrap();
+            let (hll, relative_err) = run_trial(p, *true_card, &list);
+
+            println!("{}, {}, {}, {}, {:.2}", p, m, hll.cardinality(), true_card, relative_err);
+            assert_cardinality_within!(hll, *true_card, 4);
+        }
+    }
+
+    // The cardinalities above are mostly estimated by LinearCounting; these reach the HyperLogLog estimator, at
+    // precisions whose bound catches a bias of a few percent
+    for p in [10, 12, 14] {
+        let m = 1_usize << p;
+        for true_card in [4 * m, 16 * m] {
+            let list = generate_list_with_rng(&mut rng, 2 * true_card, true_card).unwrap();
+            let (hll, relative_err) = run_trial(p, true_card, &list);
 
-            println!("{}, {}, {}, {}, {:.2}", p, m, estimate, true_card, relative_err);
+            println!("{}, {}, {}, {}, {:.2}", p, m, hll.cardinality(), true_card, relative_err);
+            assert_cardinality_within!(hll, true_card, 4);
         }
     }
 }
@@ -66,6 +86,33 @@ fn test_accuracy() {
 
 
 
+#[cfg(feature = "xxhash")]
+#[test]
+fn test_accuracy_xxh3() {
+    assert_accurate(|p| Hypeerlog::with_hasher_precision(p, hypeerlog::Xxh3BuildHasher::new(7)));
+}
+
+#[cfg(feature = "ahash")]
+#[test]
+fn test_accuracy_ahash() {
+    assert_accurate(|p| Hypeerlog::with_hasher_precision(p, hypeerlog::AHashBuilder::new(7)));
+}
+
+#[cfg(feature = "wyhash")]
+#[test]
+fn test_accuracy_wyhash() {
+    assert_accurate(|p| Hypeerlog::with_hasher_precision(p, hypeerlog::WyHashBuilder::new(7)));
+}
+
+#[cfg(feature = "siphash")]
+#[test]
+fn test_accuracy_siphash() {
+    assert_accurate(|p| Hypeerlog::with_hasher_precision(p, hypeerlog::SipBuildHasher::new_with_key(*b"accuracy harness")));
+}
+
+
+
+
 #[test]
 fn test_dump_reload() {
     let list = generate_random_list_with_cardinality(10_000, 1000);
@@ -103,16 +150,45 @@ fn test_merge() {
 
 
 
-// Takes the true cardinality and the elements, and returns the estimated cardinality and the relative error
-fn run_trial<H: Hash>(p: u8, card: usize, elems: &[H]) -> (f64, f64) {
-    let mut hll = Hypeerlog::with_precision(p);
+// Checks that the mean relative error of sketches built by `empty` stays within a few standard errors of
+// 1.04 / sqrt(m), which fails for hashers whose low bits (the bucket) or high bits (the rank) are poorly mixed
+#[cfg(any(feature = "xxhash", feature = "ahash", feature = "wyhash", feature = "siphash"))]
+fn assert_accurate<S: BuildHasher + HasherId>(empty: impl Fn(u8) -> Hypeerlog<S>) {
+    let mut rng = StdRng::seed_from_u64(390);
+    println!("p, m, estimate, true_cardinality, relative_Error");
+
+    for p in [10, 12, 14, 16] {
+        let m = 1_usize << p;
+        let mut total_err = 0.0;
+        let cardinalities = [1_000, 5_000, 20_000, 50_000];
+        for true_card in cardinalities {
+            let list = generate_list_with_rng(&mut rng, true_card * 2, true_card).unwrap();
+            let (hll, relative_err) = run_trial_with(empty(p), true_card, &list);
+            total_err += relative_err;
+
+            println!("{}, {}, {}, {}, {:.2}", p, m, hll.cardinality(), true_card, relative_err);
+            assert_cardinality_within!(hll, true_card, 4);
+        }
+        let mean_err = total_err / cardinalities.len() as f64;
+        assert!(mean_err < 3.0 * 1.04 / (m as f64).sqrt(), "p={}: mean relative error {}", p, mean_err);
+    }
+}
+
+// Takes the true cardinality and the elements, and returns the sketch of the elements and the relative error of
+// its estimate
+fn run_trial<H: Hash>(p: u8, card: usize, elems: &[H]) -> (Hypeerlog, f64) {
+    run_trial_with(Hypeerlog::with_precision(p), card, elems)
+}
 
+// Like `run_trial`, starting from an empty sketch with any hasher
+fn run_trial_with<H: Hash, S: BuildHasher + HasherId>(mut hll: Hypeerlog<S>, card: usize, elems: &[H]) -> (Hypeerlog<S>, f64) {
     hll.insert_many(elems);
 
     let estimated_cardinality = hll.cardinality();
+    #[allow(clippy::unnecessary_cast)]
     let relative_error = (estimated_cardinality as f64 - card as f64).abs() / card as f64;
 
-    (estimated_cardinality, relative_error)
+    (hll, relative_error)
 }
 
 
@@ -0,0 +1,53 @@
+#![cfg(feature = "ahash")]
+
+use std::hash::BuildHasher;
+
+use hypeerlog::{AHashBuilder, HasherId, Hypeerlog, HypeerlogError, AHASH_AES_TAG, AHASH_FALLBACK_TAG};
+
+
+
+
+fn ahash_sketch(seed: u64, elements: std::ops::Range<u64>) -> Hypeerlog<AHashBuilder> {
+    let mut hll = Hypeerlog::with_hasher(AHashBuilder::new(seed));
+    for i in elements {
+        hll.insert(i);
+    }
+    hll
+}
+
+
+
+
+#[test]
+fn test_ahash_is_reproducible() {
+    let (a, b) = (AHashBuilder::new(42), AHashBuilder::new(42));
+    assert_eq!(a.hash_one(123u64), b.hash_one(123u64));
+    assert_eq!(a.hash_one("hello"), b.hash_one("hello"));
+    assert_ne!(a.hash_one(123u64), AHashBuilder::new(43).hash_one(123u64));
+    assert_eq!(ahash_sketch(42, 0..5000), ahash_sketch(42, 0..5000));
+}
+
+#[test]
+fn test_ahash_sequential_integers() {
+    // Consecutive keys differ only in their low bits, which must still spread over all buckets
+    let hll = ahash_sketch(0, 0..100_000);
+    assert!((hll.cardinality() - 100_000.0).abs() < 100_000.0 * 0.03);
+}
+
+#[test]
+fn test_ahash_round_trip() {
+    let hll = ahash_sketch(9, 0..3000);
+    let restored = Hypeerlog::load_with_hasher(hll.dump(), AHashBuilder::default().reseeded(9).unwrap()).unwrap();
+    assert_eq!(restored, hll);
+    assert_eq!(Hypeerlog::load_with_hasher(hll.dump(), AHashBuilder::new(10)), Err(HypeerlogError::SeedMismatch));
+}
+
+#[test]
+fn test_ahash_sketches_do_not_mix_with_murmur() {
+    let dump = ahash_sketch(0, 0..3000).dump();
+    assert_eq!(Hypeerlog::load(dump.clone()), Err(HypeerlogError::HasherMismatch));
+    assert_eq!(Hypeerlog::new().merge_from_dump(&dump), Err(HypeerlogError::HasherMismatch));
+
+    let tag = AHashBuilder::new(0).hasher_tag().unwrap();
+    assert!(tag == AHASH_AES_TAG || tag == AHASH_FALLBACK_TAG);
+}
@@ -0,0 +1,138 @@
+#![cfg(feature = "allocator-api")]
+#![feature(allocator_api)]
+
+use std::alloc::{AllocError, Allocator, GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::ptr::NonNull;
+
+use hypeerlog::{Hypeerlog, HypeerlogError, Murmur3BuildHasher};
+
+mod common;
+use common::{allocated_by, CountingAlloc};
+
+
+
+
+// Counts the bytes the global allocator hands out, to check that the sketches of an arena do not use it
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+
+// An allocator that counts the bytes it allocated in total and those still live, taking them from `System`
+#[derive(Debug, Default)]
+struct Arena {
+    allocated: Cell<usize>,
+    live: Cell<usize>,
+}
+
+unsafe impl Allocator for Arena {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        // SAFETY: the slices and vectors of `u8` this allocator serves never ask for a zero-sized layout
+        let ptr = NonNull::new(unsafe { System.alloc(layout) }).ok_or(AllocError)?;
+        self.allocated.set(self.allocated.get() + layout.size());
+        self.live.set(self.live.get() + layout.size());
+        Ok(NonNull::slice_from_raw_parts(ptr, layout.size()))
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        self.live.set(self.live.get() - layout.size());
+        unsafe { System.dealloc(ptr.as_ptr(), layout) }
+    }
+}
+
+
+
+
+#[test]
+fn test_registers_are_allocated_by_the_given_allocator() {
+    let elems: Vec<u64> = (0..20_000).collect();
+    let arena = Arena::default();
+
+    let (mut hll, global) = allocated_by(|| {
+        let mut hll = Hypeerlog::with_precision_in(12, &arena);
+        assert_eq!(arena.allocated.get(), 0);
+        hll.insert_many(&elems);
+        hll.insert_str("a string");
+        hll.insert_hashes(&[1, 2, 3]);
+        hll
+    });
+    assert_eq!(global, 0);
+    assert_eq!((arena.allocated.get(), arena.live.get()), (1 << 12, 1 << 12));
+
+    let mut expected = Hypeerlog::with_precision(12);
+    expected.insert_many(&elems);
+    expected.insert_str("a string");
+    expected.insert_hashes(&[1, 2, 3]);
+    assert_eq!(hll, expected);
+    assert_eq!(hll.cardinality().to_bits(), expected.cardinality().to_bits());
+
+    hll.clear();
+    assert!(hll.is_empty());
+    assert_eq!(arena.allocated.get(), 1 << 12);
+    drop(hll);
+    assert_eq!(arena.live.get(), 0);
+}
+
+#[test]
+fn test_merging_allocates_from_the_allocator_of_the_target() {
+    let arena = Arena::default();
+    let other_arena = Arena::default();
+    let mut source = Hypeerlog::with_precision_seed_in(10, 5, &other_arena);
+    source.insert_many(&[1, 2, 3]);
+    let dump = source.dump();
+
+    let (mut merged, global) = allocated_by(|| {
+        let mut target = Hypeerlog::with_precision_seed_in(10, 5, &arena);
+        target.merge_with(&Hypeerlog::with_precision_seed_in(10, 5, &arena)).unwrap();
+        assert_eq!(arena.allocated.get(), 0);
+
+        let mut merged = Hypeerlog::with_precision_seed_in(10, 5, &arena);
+        merged.merge_from_dump(&dump).unwrap();
+        target.merge(merged).unwrap()
+    });
+    // The union copied the registers of the dropped sketch into the allocation of the target
+    assert_eq!(global, 0);
+    assert_eq!((arena.allocated.get(), arena.live.get()), (2 << 10, 1 << 10));
+    assert_eq!(merged, source);
+
+    let mut copy = source.clone_in(&arena);
+    copy.insert(4);
+    merged.merge_with(&copy).unwrap();
+    assert_eq!(merged.cardinality().round(), 4.0);
+    assert_eq!(arena.live.get(), 2 << 10);
+    assert_eq!(other_arena.live.get(), 1 << 10);
+
+    assert_eq!(merged.merge_with(&Hypeerlog::with_precision_in(10, &arena)), Err(HypeerlogError::SeedMismatch));
+    assert_eq!(merged.merge_with(&Hypeerlog::with_precision_seed_in(11, 5, &arena)), Err(HypeerlogError::PrecisionMismatch));
+}
+
+#[test]
+fn test_dumps_and_loads_through_the_allocator() {
+    let arena = Arena::default();
+    let mut hll = Hypeerlog::new_in(&arena);
+    hll.insert_many(&["a", "b", "c"]);
+
+    let (dump, global) = allocated_by(|| hll.dump_in(&arena));
+    assert_eq!(global, 0);
+    assert_eq!(dump[..], hll.dump()[..]);
+    assert_eq!(arena.allocated.get(), (1 << 14) + dump.len());
+
+    let (loaded, global) = allocated_by(|| Hypeerlog::from_bytes_in(&dump, &arena).unwrap());
+    assert_eq!(global, 0);
+    assert_eq!(loaded, hll);
+    assert_eq!(*loaded.allocator() as *const Arena, &arena as *const Arena);
+    assert_eq!(arena.live.get(), (2 << 14) + dump.len());
+
+    let hasher = Murmur3BuildHasher::new(0);
+    let loaded = Hypeerlog::from_bytes_with_hasher_in(&dump, hasher, &arena).unwrap();
+    assert_eq!(loaded, hll);
+    let seeded = Murmur3BuildHasher::new(1);
+    assert_eq!(Hypeerlog::from_bytes_with_hasher_in(&dump, seeded, &arena).unwrap_err(), HypeerlogError::SeedMismatch);
+
+    // Empty sketches stay unallocated through copies, and dump like any other empty sketch
+    let empty = Hypeerlog::with_precision_in(8, &arena);
+    let before = arena.allocated.get();
+    let copy = empty.clone_in(&arena);
+    assert_eq!(arena.allocated.get(), before);
+    assert_eq!(copy.dump_in(&arena)[..], Hypeerlog::with_precision(8).dump()[..]);
+}
@@ -0,0 +1,45 @@
+#![cfg(feature = "arbitrary")]
+
+use arbitrary::{Arbitrary, Unstructured};
+use hypeerlog::Hypeerlog;
+use rand::prelude::*;
+
+
+
+
+#[test]
+fn test_arbitrary_sketches_are_valid() {
+    let mut rng = rand::rng();
+    for _ in 0..500 {
+        let len = rng.random_range(0..70_000);
+        let data: Vec<u8> = (0..len).map(|_| rng.random()).collect();
+        let hll: Hypeerlog = Hypeerlog::arbitrary(&mut Unstructured::new(&data)).unwrap();
+
+        assert!((4..=16).contains(&hll.precision()));
+        assert_eq!(Hypeerlog::load(hll.dump()).unwrap(), hll);
+    }
+}
+
+#[test]
+fn test_arbitrary_sketches_merge() {
+    let mut rng = rand::rng();
+    let mut merged = 0;
+    for _ in 0..500 {
+        let data: Vec<u8> = (0..rng.random_range(0..2_000)).map(|_| rng.random()).collect();
+        let mut u = Unstructured::new(&data);
+        let (mut a, b): (Hypeerlog, Hypeerlog) = (Hypeerlog::arbitrary(&mut u).unwrap(), Hypeerlog::arbitrary(&mut u).unwrap());
+        if a.precision() == b.precision() {
+            a.merge_with(&b).unwrap();
+            assert_eq!(Hypeerlog::load(a.dump()).unwrap(), a);
+            merged += 1;
+        }
+    }
+    assert!(merged > 0);
+}
+
+#[test]
+fn test_arbitrary_empty_input() {
+    let hll: Hypeerlog = Hypeerlog::arbitrary(&mut Unstructured::new(&[])).unwrap();
+    assert_eq!(hll.precision(), 4);
+    assert!(hll.is_empty());
+}
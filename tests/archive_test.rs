@@ -0,0 +1,91 @@
+#![cfg(feature = "rkyv")]
+
+use hypeerlog::{ArchivedHypeerlog, Hypeerlog, HypeerlogError};
+use rkyv::rancor::Error;
+
+mod common;
+use common::sketch_of_range;
+
+
+
+
+// Same layout as `Hypeerlog`, so that archives breaking its invariants can be crafted
+#[derive(rkyv::Archive, rkyv::Serialize)]
+struct FakeHasher {
+    seed: u32,
+}
+
+#[derive(rkyv::Archive, rkyv::Serialize)]
+struct FakeHypeerlog {
+    hasher: FakeHasher,
+    precision: u8,
+    registers: Vec<u8>,
+}
+
+fn fake_archive(precision: u8, registers: Vec<u8>) -> rkyv::util::AlignedVec {
+    rkyv::to_bytes::<Error>(&FakeHypeerlog { hasher: FakeHasher { seed: 0 }, precision, registers }).unwrap()
+}
+
+
+
+
+#[test]
+fn test_archived_cardinality() {
+    for (p, n) in [(4, 10), (12, 1000), (14, 100_000)] {
+        let hll = sketch_of_range(p, 0, n);
+        let bytes = rkyv::to_bytes::<Error>(&hll).unwrap();
+        let archived = rkyv::access::<ArchivedHypeerlog, Error>(&bytes).unwrap();
+
+        assert_eq!(archived.cardinality(), hll.cardinality());
+        assert_eq!(archived.precision(), p);
+        assert_eq!(archived.len(), hll.len());
+        assert_eq!(archived.is_empty(), hll.is_empty());
+    }
+}
+
+#[test]
+fn test_archived_registers_are_not_copied() {
+    let hll = sketch_of_range(14, 0, 1000);
+    let bytes = rkyv::to_bytes::<Error>(&hll).unwrap();
+    let archived = rkyv::access::<ArchivedHypeerlog, Error>(&bytes).unwrap();
+
+    // The archive references the registers inside the buffer itself
+    assert!(bytes.as_ptr_range().contains(&(archived as *const ArchivedHypeerlog as *const u8)));
+    assert!(archived.len() <= bytes.len());
+}
+
+#[test]
+fn test_archived_union_cardinality() {
+    let a = sketch_of_range(12, 0, 10_000);
+    let b = sketch_of_range(12, 5_000, 30_000);
+    let (bytes_a, bytes_b) = (rkyv::to_bytes::<Error>(&a).unwrap(), rkyv::to_bytes::<Error>(&b).unwrap());
+    let archived_a = rkyv::access::<ArchivedHypeerlog, Error>(&bytes_a).unwrap();
+    let archived_b = rkyv::access::<ArchivedHypeerlog, Error>(&bytes_b).unwrap();
+
+    assert_eq!(archived_a.union_cardinality(archived_b).unwrap(), a.merge(b).unwrap().cardinality());
+
+    let seeded = rkyv::to_bytes::<Error>(&Hypeerlog::with_precision_seed(12, 3)).unwrap();
+    let small = rkyv::to_bytes::<Error>(&Hypeerlog::with_precision(10)).unwrap();
+    let archived_seeded = rkyv::access::<ArchivedHypeerlog, Error>(&seeded).unwrap();
+    let archived_small = rkyv::access::<ArchivedHypeerlog, Error>(&small).unwrap();
+    assert_eq!(archived_a.union_cardinality(archived_seeded), Err(HypeerlogError::SeedMismatch));
+    assert_eq!(archived_a.union_cardinality(archived_small), Err(HypeerlogError::PrecisionMismatch));
+}
+
+#[test]
+fn test_archived_round_trip() {
+    let hll = Hypeerlog::with_precision_seed(12, 7);
+    let bytes = rkyv::to_bytes::<Error>(&hll).unwrap();
+    let restored: Hypeerlog = rkyv::from_bytes::<Hypeerlog, Error>(&bytes).unwrap();
+    assert_eq!(restored, hll);
+}
+
+#[test]
+fn test_validation_enforces_invariants() {
+    assert!(rkyv::access::<ArchivedHypeerlog, Error>(&fake_archive(4, vec![1; 16])).is_ok());
+
+    for (precision, registers) in [(3, vec![0; 8]), (4, vec![0; 15]), (4, vec![0; 17]), (4, vec![62; 16])] {
+        let bytes = fake_archive(precision, registers);
+        assert!(rkyv::access::<ArchivedHypeerlog, Error>(&bytes).is_err());
+    }
+}
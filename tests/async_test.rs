@@ -0,0 +1,100 @@
+#![cfg(feature = "async")]
+
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::channel::mpsc;
+use futures::executor::block_on;
+use futures::stream::{self, Stream};
+use futures::SinkExt;
+use hypeerlog::Hypeerlog;
+
+
+
+
+// Counts the polls of the stream it wraps, to check that the insertion yields to the executor
+struct CountingPolls<St> {
+    inner: St,
+    polls: usize,
+}
+
+impl<St: Stream + Unpin> Stream for CountingPolls<St> {
+    type Item = St::Item;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<St::Item>> {
+        self.polls += 1;
+        Pin::new(&mut self.inner).poll_next(cx)
+    }
+}
+
+fn sequential(p: u8, elems: &[u64]) -> Hypeerlog {
+    let mut hll = Hypeerlog::with_precision(p);
+    hll.insert_many(elems);
+    hll
+}
+
+
+
+
+#[test]
+fn test_bounded_channel_matches_synchronous_insertion() {
+    let elems: Vec<u64> = (0..50_000).map(|x| x % 20_000).collect();
+    for p in [4, 12, 16] {
+        let (mut tx, rx) = mpsc::channel(16);
+        let producer = async {
+            for &x in &elems {
+                tx.send(x).await.unwrap();
+            }
+            tx.close_channel();
+        };
+        let (_, hll) = block_on(futures::future::join(producer, Hypeerlog::from_stream_with_precision(p, rx)));
+        let expected = sequential(p, &elems);
+        assert_eq!(hll, expected, "p={}", p);
+        assert_eq!(hll.cardinality().to_bits(), expected.cardinality().to_bits());
+    }
+}
+
+#[test]
+fn test_insert_stream_adds_to_the_sketch_and_yields() {
+    let elems: Vec<u64> = (0..10_000).collect();
+    let mut hll = sequential(14, &elems[..100]);
+
+    let mut counting = CountingPolls { inner: stream::iter(elems[100..].to_vec()), polls: 0 };
+    let mut future = Box::pin(hll.insert_stream(&mut counting));
+    let waker = futures::task::noop_waker();
+    let mut cx = Context::from_waker(&waker);
+
+    // An always-ready stream is consumed 1024 items per poll
+    let mut pending = 0;
+    while future.as_mut().poll(&mut cx).is_pending() {
+        pending += 1;
+    }
+    drop(future);
+    assert_eq!(pending, 9900 / 1024);
+    assert_eq!(counting.polls, 9901);
+    assert_eq!(hll, sequential(14, &elems));
+
+    block_on(hll.insert_stream(stream::empty::<u64>()));
+    assert_eq!(hll, sequential(14, &elems));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_insert_stream_on_tokio() {
+    let (tx, mut rx) = tokio::sync::mpsc::channel(8);
+    let producer = tokio::spawn(async move {
+        for x in 0..30_000u64 {
+            tx.send(x * 3).await.unwrap();
+        }
+    });
+    let records = stream::poll_fn(move |cx| rx.poll_recv(cx));
+    let consumer = tokio::spawn(async move {
+        let mut hll = Hypeerlog::new();
+        hll.insert_stream(records).await;
+        hll
+    });
+    producer.await.unwrap();
+    let hll = consumer.await.unwrap();
+
+    let expected: Hypeerlog = (0..30_000u64).map(|x| x * 3).collect();
+    assert_eq!(hll, expected);
+}
@@ -0,0 +1,98 @@
+#![cfg(not(feature = "no_std"))]
+
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use hypeerlog::{AtomicHypeerlog, Hypeerlog, HypeerlogError};
+use rand::prelude::*;
+
+
+
+
+#[test]
+fn test_no_updates_are_lost() {
+    let mut rng = StdRng::seed_from_u64(365);
+    // Few distinct values at a low precision, so that threads race on the same registers
+    let batches: Vec<Vec<u64>> = (0..16).map(|_| (0..20_000).map(|_| rng.random_range(0..5_000)).collect()).collect();
+
+    for p in [4, 8, 14] {
+        let atomic = Arc::new(AtomicHypeerlog::with_precision(p));
+        let locked = Arc::new(Mutex::new(Hypeerlog::with_precision(p)));
+        thread::scope(|s| {
+            for batch in &batches {
+                let (atomic, locked) = (Arc::clone(&atomic), Arc::clone(&locked));
+                s.spawn(move || {
+                    for x in batch {
+                        atomic.insert(x);
+                        locked.lock().unwrap().insert(x);
+                    }
+                });
+            }
+        });
+
+        let locked = locked.lock().unwrap();
+        assert_eq!(atomic.snapshot(), *locked, "p={}", p);
+        assert_eq!(atomic.cardinality().to_bits(), locked.cardinality().to_bits(), "p={}", p);
+        // Only the locked sketch counts its insertions, which its dump records
+        assert_eq!(locked.total_insertions(), 16 * 20_000, "p={}", p);
+        assert_eq!(Hypeerlog::load(atomic.dump()).unwrap(), *locked, "p={}", p);
+        assert_eq!(atomic.dump(), atomic.snapshot().dump(), "p={}", p);
+    }
+}
+
+#[test]
+fn test_concurrent_reads_never_go_back() {
+    let atomic = AtomicHypeerlog::with_precision(10);
+    let elems: Vec<u64> = (0..200_000).collect();
+
+    thread::scope(|s| {
+        for chunk in elems.chunks(50_000) {
+            let atomic = &atomic;
+            s.spawn(move || atomic.insert_many(chunk));
+        }
+        let atomic = &atomic;
+        s.spawn(move || {
+            let mut previous = Hypeerlog::with_precision(10);
+            for _ in 0..200 {
+                let snapshot = atomic.snapshot();
+                let mut merged = Hypeerlog::load(snapshot.dump()).unwrap();
+                merged.merge_with(&previous).unwrap();
+                assert_eq!(merged, snapshot, "a register was lowered");
+                Hypeerlog::load(atomic.dump()).unwrap();
+                previous = snapshot;
+            }
+        });
+    });
+
+    let mut expected = Hypeerlog::with_precision(10);
+    expected.insert_many(&elems);
+    assert_eq!(Hypeerlog::from(atomic), expected);
+}
+
+#[test]
+fn test_conversions_and_merges() {
+    let mut hll = Hypeerlog::with_precision_seed(12, 5);
+    hll.insert_many(&[1, 2, 3]);
+    let atomic = AtomicHypeerlog::from(Hypeerlog::load(hll.dump()).unwrap());
+    assert_eq!((atomic.precision(), atomic.len()), (12, 4096));
+    assert_eq!(atomic.snapshot(), hll);
+
+    let mut other = Hypeerlog::with_precision_seed(12, 5);
+    other.insert_many(&[3, 4, 5, 6]);
+    atomic.merge_from(&other).unwrap();
+    atomic.insert_str("seven");
+    hll.merge_with(&other).unwrap();
+    hll.insert_str("seven");
+    assert_eq!(Hypeerlog::from(atomic), hll);
+
+    let mut atomic = AtomicHypeerlog::with_precision(12);
+    assert_eq!(atomic.merge_from(&Hypeerlog::with_precision(13)), Err(HypeerlogError::PrecisionMismatch));
+    assert_eq!(atomic.merge_from(&Hypeerlog::with_precision_seed(12, 1)), Err(HypeerlogError::SeedMismatch));
+    assert!(atomic.is_empty());
+
+    atomic.insert(1u64);
+    assert!(!atomic.is_empty());
+    atomic.clear();
+    assert!(atomic.is_empty());
+    assert_eq!(AtomicHypeerlog::default().snapshot(), Hypeerlog::new());
+}
@@ -0,0 +1,72 @@
+use hypeerlog::{Hypeerlog, HypeerlogError};
+
+mod common;
+use common::sketch_of_range;
+
+
+
+
+#[test]
+fn test_base64_fixture() {
+    // Produced by this release and cross-checked against an independent base64 encoder
+    let hll = sketch_of_range(4, 0, 10);
+    let encoded = "SExMAgRHDr0hQwAAAAAAAAAATVVSMwoAAAAAAAAAAAEAAwMAAgEAAAAAAQAAAQ==";
+    assert_eq!(hll.dump_base64(), encoded);
+    assert_eq!(Hypeerlog::load_base64(encoded).unwrap(), hll);
+}
+
+#[test]
+fn test_base64_round_trip() {
+    for (p, n) in [(4, 0), (4, 10), (10, 1000), (14, 100_000)] {
+        let hll = sketch_of_range(p, 0, n);
+        let encoded = hll.dump_base64();
+        assert!(encoded.bytes().all(|c| c.is_ascii_alphanumeric() || b"+/=".contains(&c)));
+        assert_eq!(encoded.len() % 4, 0);
+        assert_eq!(Hypeerlog::load_base64(&encoded).unwrap(), hll);
+    }
+}
+
+#[test]
+fn test_base64_preserves_non_text_bytes() {
+    // Registers holding NUL, newline, carriage return, EOF and quote bytes survive the round-trip
+    let mut legacy: Vec<u8> = (0..1 << 10).map(|i| [0, b'\n', b'\r', 0x1a, b'"', b'\''][i % 6]).collect();
+    legacy.push(10);
+    let hll = Hypeerlog::load(legacy).unwrap();
+
+    let encoded = hll.dump_base64();
+    assert!(!encoded.contains(['\n', '\r', '\0']));
+    assert_eq!(Hypeerlog::load_base64(&encoded).unwrap(), hll);
+}
+
+#[test]
+fn test_base64_rejects_whitespace() {
+    let encoded = sketch_of_range(10, 0, 1000).dump_base64();
+
+    let wrapped: String = encoded.as_bytes()
+        .chunks(76)
+        .map(|line| std::str::from_utf8(line).unwrap())
+        .collect::<Vec<_>>()
+        .join("\n");
+    assert_eq!(Hypeerlog::load_base64(&wrapped), Err(HypeerlogError::Encoding));
+    assert_eq!(Hypeerlog::load_base64(&format!(" {}", encoded)), Err(HypeerlogError::Encoding));
+    assert_eq!(Hypeerlog::load_base64(&format!("{}\n", encoded)), Err(HypeerlogError::Encoding));
+}
+
+#[test]
+fn test_base64_rejects_malformed_input() {
+    let encoded = sketch_of_range(4, 0, 10).dump_base64();
+
+    for input in [
+        &encoded[..encoded.len() - 1],
+        "SExM=AgQ",
+        "SE===",
+        "SExMAg==AAAA",
+        "SExM*gQH",
+        // URL-safe alphabet
+        "SExM-gQ_",
+    ] {
+        assert_eq!(Hypeerlog::load_base64(input), Err(HypeerlogError::Encoding), "{input}");
+    }
+    // Well-formed base64 that does not hold a dump
+    assert_eq!(Hypeerlog::load_base64("aGVsbG8="), Err(HypeerlogError::InvalidMagic));
+}
@@ -0,0 +1,270 @@
+use std::cell::Cell;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::hash::{BuildHasher, BuildHasherDefault, Hash};
+use std::io::{BufRead, BufReader};
+use std::rc::Rc;
+
+use hypeerlog::{HasherId, Hypeerlog, Murmur3BuildHasher};
+use rand::prelude::*;
+
+
+
+
+// Counts the hashers it builds, one per hashed element
+#[derive(Debug, Default, Clone)]
+struct CountingHasher {
+    built: Rc<Cell<usize>>,
+}
+
+impl BuildHasher for CountingHasher {
+    type Hasher = DefaultHasher;
+
+    fn build_hasher(&self) -> DefaultHasher {
+        self.built.set(self.built.get() + 1);
+        DefaultHasher::new()
+    }
+}
+
+impl HasherId for CountingHasher {}
+
+fn insert_one_by_one<H: Hash, S: BuildHasher + HasherId + std::fmt::Debug>(hll: &mut Hypeerlog<S>, data: &[H]) {
+    for elem in data {
+        hll.insert(elem);
+    }
+}
+
+// Keys whose halves exercise the carries and sign bits of the lane arithmetic
+const EDGE_KEYS: [u64; 8] = [0, 1, u64::MAX, 1 << 32, (1 << 32) - 1, 0x8000_0000_8000_0000, 0x7fff_ffff_ffff_ffff, 0xdead_beef_cafe_f00d];
+
+
+
+
+#[test]
+fn test_hash_u64s_matches_hash_one() {
+    let mut rng = rand::rng();
+    for seed in [0, 42, rng.random()] {
+        let h = Murmur3BuildHasher::new(seed);
+        for len in 0..=40 {
+            let keys: Vec<u64> = EDGE_KEYS.iter().copied().chain((0..len).map(|_| rng.random())).take(len).collect();
+            let mut hashes = vec![0; len];
+            h.hash_u64s(&keys, &mut hashes);
+            let expected: Vec<u64> = keys.iter().map(|&key| h.hash_one(key)).collect();
+            assert_eq!(hashes, expected, "seed {} keys {:x?}", seed, keys);
+        }
+    }
+}
+
+#[test]
+fn test_default_hash_u64s_matches_hash_one() {
+    let h = BuildHasherDefault::<DefaultHasher>::default();
+    let mut hashes = [0; 8];
+    h.hash_u64s(&EDGE_KEYS, &mut hashes);
+    assert_eq!(hashes.to_vec(), EDGE_KEYS.iter().map(|&key| h.hash_one(key)).collect::<Vec<_>>());
+}
+
+#[test]
+fn test_insert_many_u64_matches_insert_many() {
+    let mut rng = rand::rng();
+    for p in [4, 10, 14, 18, 20] {
+        // Lengths around the batch size, and random ones
+        for len in [0, 1, 7, 8, 9, 255, 256, 257, 1000, rng.random_range(0..5000)] {
+            let seed = rng.random();
+            let keys: Vec<u64> = (0..len).map(|_| rng.random()).collect();
+            let (mut batched, mut scalar) = (Hypeerlog::with_precision_seed(p, seed), Hypeerlog::with_precision_seed(p, seed));
+            batched.insert_many_u64(&keys);
+            insert_one_by_one(&mut scalar, &keys);
+            assert_eq!(batched, scalar, "p={} len={} seed={}", p, len, seed);
+
+            let mut many = Hypeerlog::with_precision_seed(p, seed);
+            many.insert_many(&keys);
+            assert_eq!(many, scalar, "p={} len={} seed={}", p, len, seed);
+        }
+    }
+}
+
+#[test]
+fn test_insert_many_u64_with_other_hashers() {
+    let keys: Vec<u64> = (0..3000).collect();
+    let h = BuildHasherDefault::<DefaultHasher>::default();
+    let (mut batched, mut scalar) = (Hypeerlog::with_hasher(h.clone()), Hypeerlog::with_hasher(h));
+    batched.insert_many_u64(&keys);
+    insert_one_by_one(&mut scalar, &keys);
+    assert_eq!(batched, scalar);
+}
+
+#[test]
+fn test_insert_many_hashes_each_element_once() {
+    // Up to precision 20, `insert_many` inserts one element at a time, then in batches
+    for p in [12, 20, 22] {
+        for len in [0, 1, 255, 256, 257, 1000] {
+            let strings: Vec<String> = (0..len).map(|i| format!("element {}", i)).collect();
+            let hasher = CountingHasher::default();
+            let mut batched = Hypeerlog::with_hasher_precision(p, hasher.clone());
+            batched.insert_many(&strings);
+            assert_eq!(hasher.built.get(), len, "p={}", p);
+
+            let mut scalar = Hypeerlog::with_hasher_precision(p, CountingHasher::default());
+            insert_one_by_one(&mut scalar, &strings);
+            assert_eq!(batched.cardinality().to_bits(), scalar.cardinality().to_bits(), "p={} len={}", p, len);
+            assert_eq!(batched.dump(), scalar.dump(), "p={} len={}", p, len);
+        }
+    }
+}
+
+#[test]
+fn test_extend_hashes_each_element_once() {
+    for p in [12, 20, 22] {
+        for len in [0, 1, 255, 256, 257, 1000] {
+            let strings: Vec<String> = (0..len).map(|i| format!("element {}", i)).collect();
+            let hasher = CountingHasher::default();
+            let mut extended = Hypeerlog::with_hasher_precision(p, hasher.clone());
+            extended.extend(strings.iter().filter(|_| true));
+            assert_eq!(hasher.built.get(), len, "p={}", p);
+
+            let mut scalar = Hypeerlog::with_hasher_precision(p, CountingHasher::default());
+            insert_one_by_one(&mut scalar, &strings);
+            assert_eq!(extended.dump(), scalar.dump(), "p={} len={}", p, len);
+        }
+    }
+}
+
+// Fills any collection, the way generic code sees a sketch through `Extend`
+fn fill<C: Extend<u64>>(collection: &mut C, len: u64) {
+    collection.extend((0..len).map(|i| i * 7));
+}
+
+#[test]
+fn test_extend_from_collections() {
+    let ids: Vec<u64> = (0..10_000u64).map(|i| i * 7).collect();
+    let mut expected = Hypeerlog::new();
+    expected.insert_many(&ids);
+
+    let mut from_values = Hypeerlog::new();
+    from_values.extend(ids.clone());
+    let mut from_refs = Hypeerlog::new();
+    from_refs.extend(ids.iter());
+    let mut generic = Hypeerlog::new();
+    fill(&mut generic, 10_000);
+    assert_eq!(from_values, expected);
+    assert_eq!(from_refs, expected);
+    assert_eq!(generic, expected);
+
+    // Sets and maps insert each element once whatever their iteration order
+    let set: HashSet<u64> = ids.iter().copied().collect();
+    let mut from_set = Hypeerlog::new();
+    from_set.extend(&set);
+    assert_eq!(from_set, expected);
+
+    let map: BTreeMap<u64, &str> = ids.iter().map(|&id| (id, "value")).collect();
+    let mut from_keys = Hypeerlog::new();
+    from_keys.extend(map.keys());
+    assert_eq!(from_keys, expected);
+    assert!((from_keys.cardinality() - 10_000.0).abs() < 10_000.0 * 3.0 * from_keys.relative_error());
+
+    // Extending with nothing leaves the sketch empty
+    from_keys.clear();
+    from_keys.extend(Vec::<u64>::new());
+    assert_eq!(from_keys.cardinality(), 0.0);
+}
+
+#[test]
+fn test_insert_iter_matches_insert_many() {
+    let ids: Vec<u64> = (0..20_000u64).collect();
+    for p in [12, 20] {
+        // Lazily filtered and mapped items are inserted as they are produced
+        let mut lazy = Hypeerlog::with_precision(p);
+        lazy.insert_iter(ids.iter().filter(|&&id| id % 3 != 0).map(|id| id * 11));
+        let mut sliced = Hypeerlog::with_precision(p);
+        sliced.insert_many(&ids.iter().filter(|&&id| id % 3 != 0).map(|id| id * 11).collect::<Vec<_>>());
+        assert_eq!(lazy, sliced, "p={}", p);
+        assert_eq!(lazy.cardinality().to_bits(), sliced.cardinality().to_bits(), "p={}", p);
+
+        let map: BTreeMap<String, usize> = ids.iter().map(|id| (format!("key-{}", id), 0)).collect();
+        let mut keys = Hypeerlog::with_precision(p);
+        keys.insert_iter(map.keys());
+        let mut owned = Hypeerlog::with_precision(p);
+        owned.insert_many(&map.into_keys().collect::<Vec<_>>());
+        assert_eq!(keys, owned, "p={}", p);
+    }
+}
+
+#[test]
+fn test_insert_iter_from_lines() {
+    let text: String = (0..5000).map(|i| format!("line {}\n", i % 1200)).collect();
+    let mut from_lines = Hypeerlog::new();
+    from_lines.insert_iter(BufReader::new(text.as_bytes()).lines().map(Result::unwrap));
+
+    let lines: Vec<&str> = text.lines().collect();
+    let mut from_slice = Hypeerlog::new();
+    from_slice.insert_many(&lines);
+    assert_eq!(from_lines, from_slice);
+    assert_eq!(from_lines.cardinality().to_bits(), from_slice.cardinality().to_bits());
+    assert!((from_lines.cardinality() - 1200.0).abs() < 1200.0 * 3.0 * from_lines.relative_error());
+}
+
+// An unsized key, borrowed from its owned form like `str` from `String`
+#[derive(Hash)]
+#[repr(transparent)]
+struct Word(str);
+
+impl Word {
+    fn new(s: &str) -> &Word {
+        // SAFETY: `Word` is a transparent wrapper of `str`
+        unsafe { &*(s as *const str as *const Word) }
+    }
+}
+
+// Generic code that only holds references to its items
+fn insert_all<T: Hash + ?Sized>(hll: &mut Hypeerlog, items: &[&T]) {
+    for &item in items {
+        hll.insert_ref(item);
+    }
+}
+
+#[test]
+fn test_borrowed_items_insert_like_owned_ones() {
+    let owned: Vec<String> = (0..5000).map(|i| format!("user-{}", i)).collect();
+    let mut expected = Hypeerlog::new();
+    for name in owned.clone() {
+        expected.insert(name);
+    }
+
+    // `String`, `&str` and unsized `str` items land in the same registers, and `owned` is still usable
+    let strs: Vec<&str> = owned.iter().map(String::as_str).collect();
+    let mut from_strings = Hypeerlog::new();
+    from_strings.insert_many(&owned);
+    let mut from_strs = Hypeerlog::new();
+    from_strs.insert_many(&strs);
+    let mut from_refs = Hypeerlog::new();
+    insert_all::<str>(&mut from_refs, &strs);
+    let mut from_iter = Hypeerlog::new();
+    from_iter.insert_iter(&owned);
+    for hll in [&from_strings, &from_strs, &from_refs, &from_iter] {
+        assert_eq!(hll, &expected);
+        assert_eq!(hll.total_insertions(), 5000);
+    }
+    assert_eq!(owned.len(), 5000);
+
+    // Map keys are borrowed, not cloned
+    let map: HashMap<String, usize> = owned.iter().map(|name| (name.clone(), name.len())).collect();
+    let mut from_keys = Hypeerlog::new();
+    from_keys.insert_iter(map.keys());
+    assert_eq!(from_keys, expected);
+
+    // Byte slices hash like the vectors that own them
+    let bytes: Vec<Vec<u8>> = owned.iter().map(|name| name.clone().into_bytes()).collect();
+    let slices: Vec<&[u8]> = bytes.iter().map(Vec::as_slice).collect();
+    let mut from_vecs = Hypeerlog::new();
+    from_vecs.insert_many(&bytes);
+    let mut from_slices = Hypeerlog::new();
+    insert_all::<[u8]>(&mut from_slices, &slices);
+    assert_eq!(from_slices, from_vecs);
+
+    // A custom unsized type inserts through a reference, like the `str` it wraps
+    let words: Vec<&Word> = strs.iter().map(|s| Word::new(s)).collect();
+    let mut from_words = Hypeerlog::new();
+    insert_all::<Word>(&mut from_words, &words);
+    assert_eq!(from_words, expected);
+    assert!(!from_words.insert_ref(Word::new("user-0")));
+}
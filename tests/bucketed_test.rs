@@ -0,0 +1,155 @@
+use std::collections::HashSet;
+
+use hypeerlog::{assert_cardinality_within, BucketedHypeerlog, Hypeerlog, HypeerlogError};
+use rand::prelude::*;
+
+
+
+
+const HOUR: u64 = 60;
+
+// A fake clock in minutes: three days of events whose users drift every hour, some of them up to 20 minutes late
+fn events(rng: &mut StdRng) -> Vec<(u64, u64)> {
+    let mut events = Vec::new();
+    for minute in 0..72 * HOUR {
+        for _ in 0..10 {
+            let late = if rng.random_bool(0.1) { rng.random_range(0..20.min(minute + 1)) } else { 0 };
+            events.push((minute / HOUR * 300 + rng.random_range(0..3000), minute - late));
+        }
+    }
+    events
+}
+
+// The users of the events inserted so far, in the newest `n` hours up to `hour`
+fn users_in(events: &[(u64, u64)], hour: u64, n: u64) -> Vec<u64> {
+    let start = (hour + 1).saturating_sub(n) * HOUR;
+    events.iter().filter(|&&(_, ts)| ts >= start).map(|&(user, _)| user).collect()
+}
+
+
+
+
+#[test]
+fn test_rollups_match_exact_sets_per_window() {
+    let mut rng = StdRng::seed_from_u64(396);
+    let events = events(&mut rng);
+    let mut ring = BucketedHypeerlog::with_precision_buckets(12, HOUR, 24);
+
+    for (hour, chunk) in events.chunks(HOUR as usize * 10).enumerate() {
+        let hour = hour as u64;
+        chunk.iter().for_each(|&(user, ts)| { ring.insert(user, ts); });
+        assert_eq!(ring.newest_bucket_start(), Some(hour * HOUR));
+        if !hour.is_multiple_of(5) {
+            continue;
+        }
+
+        let inserted = &events[..(hour as usize + 1) * chunk.len()];
+        for n in [1, 6, 24] {
+            // Late events older than the ring were dropped, like the exact set forgets them
+            let users = users_in(inserted, hour, n);
+            let exact = Hypeerlog::from_slice_with_precision(12, &users);
+            let union = ring.union_last(n as usize);
+            assert_eq!(union, exact, "hour={} n={}", hour, n);
+            assert_eq!(ring.cardinality_last(n as usize).to_bits(), exact.cardinality().to_bits());
+            assert_cardinality_within!(union, users.iter().collect::<HashSet<_>>().len(), 4);
+        }
+        // Asking for more buckets than the ring holds gives the whole ring
+        assert_eq!(ring.union_last(100), ring.union_last(24));
+    }
+
+    // The buckets start an hour apart, from the oldest to the newest
+    let starts: Vec<u64> = ring.buckets().map(|(start, _)| start).collect();
+    assert_eq!(starts, (48..72).map(|hour| hour * HOUR).collect::<Vec<_>>());
+    assert!(ring.buckets().all(|(_, bucket)| !bucket.is_empty()));
+}
+
+#[test]
+fn test_advancing_the_clock_expires_buckets() {
+    let mut ring = BucketedHypeerlog::with_precision_buckets(10, HOUR, 4);
+    assert_eq!((ring.bucket_count(), ring.bucket_duration(), ring.newest_bucket_start()), (4, HOUR, None));
+    assert_eq!(ring.buckets().count(), 0);
+    assert_eq!(ring.cardinality_last(4), 0.0);
+
+    for hour in 0..4 {
+        ring.insert_hashed(hour, hour * HOUR);
+        ring.insert(format!("user-{}", hour), hour * HOUR + 30);
+    }
+    assert_eq!(ring.cardinality_last(4).round(), 8.0);
+    assert_eq!(ring.cardinality_last(0), 0.0);
+
+    // Advancing within the newest bucket or back in time changes nothing, and inserts older than the ring are
+    // ignored
+    assert_eq!(ring.advance(3 * HOUR + 59), 0);
+    assert_eq!(ring.advance(0), 0);
+    assert_eq!(ring.advance(5 * HOUR), 2);
+    assert_eq!(ring.cardinality_last(4).round(), 4.0);
+    assert_eq!(ring.cardinality_last(2), 0.0);
+    assert!(!ring.insert("late", HOUR));
+    assert!(ring.insert("late", 2 * HOUR));
+    assert_eq!(ring.cardinality_last(4).round(), 5.0);
+
+    // A jump past the whole ring clears every bucket once
+    assert_eq!(ring.advance(1000 * HOUR), 4);
+    assert!(ring.buckets().all(|(_, bucket)| bucket.is_empty()));
+    assert_eq!(ring.buckets().next().map(|(start, _)| start), Some(997 * HOUR));
+
+    ring.insert("again", 0);
+    ring.clear();
+    assert_eq!(ring.newest_bucket_start(), None);
+    assert!(ring.insert("again", 0));
+    assert_eq!(ring.cardinality_last(1).round(), 1.0);
+}
+
+#[test]
+fn test_dumps_keep_the_ring_and_its_boundaries() {
+    let mut ring = BucketedHypeerlog::with_precision_seed_buckets(11, 7, HOUR, 5);
+    assert_eq!(BucketedHypeerlog::from_bytes(&ring.dump()).unwrap().newest_bucket_start(), None);
+    for minute in 0..8 * HOUR {
+        ring.insert(minute % 700, minute);
+    }
+
+    let loaded = BucketedHypeerlog::from_bytes(&ring.dump()).unwrap();
+    assert_eq!(loaded.dump(), ring.dump());
+    assert_eq!((loaded.precision(), loaded.bucket_count(), loaded.newest_bucket_start()), (11, 5, Some(7 * HOUR)));
+    for n in 0..=5 {
+        assert_eq!(loaded.union_last(n), ring.union_last(n), "n={}", n);
+    }
+    assert!(loaded.buckets().map(|(start, bucket)| (start, bucket.dump())).eq(ring.buckets().map(|(start, bucket)| (start, bucket.dump()))));
+
+    // The loaded ring keeps rolling like the original
+    let (mut loaded, mut original) = (loaded, ring.clone());
+    for ring in [&mut loaded, &mut original] {
+        ring.insert("next", 9 * HOUR);
+        ring.insert("late", 6 * HOUR);
+    }
+    assert_eq!(loaded.dump(), original.dump());
+
+    // Invalid rings are rejected
+    let dump = ring.dump();
+    assert_eq!(BucketedHypeerlog::from_bytes(&dump[..dump.len() - 1]).unwrap_err(), HypeerlogError::InvalidLength);
+    assert_eq!(BucketedHypeerlog::from_bytes(&dump[..20]).unwrap_err(), HypeerlogError::InvalidLength);
+    assert_eq!(BucketedHypeerlog::from_bytes(b"HLL\x02").unwrap_err(), HypeerlogError::InvalidMagic);
+    let mut newer = dump.clone();
+    newer[4] = 2;
+    assert_eq!(BucketedHypeerlog::from_bytes(&newer).unwrap_err(), HypeerlogError::UnsupportedVersion);
+    let mut huge = dump.clone();
+    huge[13..17].copy_from_slice(&u32::MAX.to_le_bytes());
+    assert_eq!(BucketedHypeerlog::from_bytes(&huge).unwrap_err(), HypeerlogError::InvalidLength);
+    // The newest bucket must start at a timestamp that fits in a u64
+    let mut late = dump.clone();
+    late[18..26].copy_from_slice(&(u64::MAX / HOUR).to_le_bytes());
+    let latest = BucketedHypeerlog::from_bytes(&late).unwrap();
+    assert_eq!(latest.newest_bucket_start(), Some(u64::MAX / HOUR * HOUR));
+    assert_eq!(latest.buckets().count(), 5);
+    late[18..26].copy_from_slice(&(u64::MAX / HOUR + 1).to_le_bytes());
+    assert_eq!(BucketedHypeerlog::from_bytes(&late).unwrap_err(), HypeerlogError::InvalidLength);
+
+    // The buckets must share the seed of the ring
+    let other = BucketedHypeerlog::with_precision_seed_buckets(11, 8, HOUR, 5).dump();
+    let seeded = Hypeerlog::with_precision_seed(11, 7).dump();
+    let mut mixed = other[..26].to_vec();
+    mixed.extend_from_slice(&(seeded.len() as u32).to_le_bytes());
+    mixed.extend_from_slice(&seeded);
+    mixed.extend_from_slice(&other[26 + 4 + seeded.len()..]);
+    assert_eq!(BucketedHypeerlog::from_bytes(&mixed).unwrap_err(), HypeerlogError::SeedMismatch);
+}
@@ -0,0 +1,529 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::BuildHasherDefault;
+
+use hypeerlog::{cardinality_of_dump, count_distinct, count_distinct_with_precision, Hypeerlog, HypeerlogError};
+use rand::prelude::*;
+
+
+
+
+// `cardinality_of_dump` and views estimate from the register bytes, so they check the aggregates the sketch
+// maintains through each operation
+fn assert_consistent(hll: &Hypeerlog, step: &str) {
+    let dump = hll.dump();
+    let scanned = cardinality_of_dump(&dump).unwrap();
+    assert_eq!(hll.cardinality().to_bits(), scanned.to_bits(), "after {}: {} != {}", step, hll.cardinality(), scanned);
+    assert_eq!(Hypeerlog::borrow_dump(&dump).unwrap().cardinality().to_bits(), scanned.to_bits(), "after {}", step);
+    assert_eq!(hll.is_empty(), Hypeerlog::from_bytes(&dump).unwrap().iter().all(|val| val == 0), "after {}", step);
+}
+
+fn random_sketch(p: u8, n: usize, rng: &mut StdRng) -> Hypeerlog {
+    let mut hll = Hypeerlog::with_precision(p);
+    for _ in 0..n {
+        hll.insert(rng.random::<u64>());
+    }
+    hll
+}
+
+
+
+
+#[test]
+fn test_cardinality_is_maintained_by_every_operation() {
+    let mut rng = StdRng::seed_from_u64(354);
+    for p in [4, 8, 12, 16] {
+        let mut hll = Hypeerlog::with_precision(p);
+        assert_consistent(&hll, "creating");
+
+        hll.insert("one element");
+        assert_consistent(&hll, "insert");
+        hll.insert_hashed(u64::MAX << p);
+        assert_consistent(&hll, "insert_hashed of the largest rank");
+        let hashes: Vec<u64> = (0..5_000).map(|_| rng.random()).collect();
+        hll.insert_hashes(&hashes);
+        assert_consistent(&hll, "insert_hashes");
+        let keys: Vec<u64> = (0..5_000).map(|_| rng.random()).collect();
+        hll.insert_many_u64(&keys);
+        assert_consistent(&hll, "insert_many_u64");
+        hll.insert_bytes(b"bytes");
+        assert_consistent(&hll, "insert_bytes");
+
+        let snap = hll.snapshot();
+        hll.merge_with(&random_sketch(p, 20_000, &mut rng)).unwrap();
+        assert_consistent(&hll, "merge_with");
+        hll.merge_from_dump(&random_sketch(p, 50_000, &mut rng).dump()).unwrap();
+        assert_consistent(&hll, "merge_from_dump");
+        let report = hll.merge_with_report(&random_sketch(p, 1_000, &mut rng)).unwrap();
+        assert_consistent(&hll, "merge_with_report");
+        assert_eq!(report.estimate_after.to_bits(), hll.cardinality().to_bits());
+        let other = random_sketch(p, 3_000, &mut rng).dump();
+        Hypeerlog::borrow_dump(&other).unwrap().merge_into(&mut hll).unwrap();
+        assert_consistent(&hll, "merge_into");
+
+        let mut replica = Hypeerlog::with_precision(p);
+        replica.merge_with(&Hypeerlog::load(hll.dump()).unwrap()).unwrap();
+        let delta = hll.delta_since(&snap).unwrap();
+        hll.clear();
+        assert_consistent(&hll, "clear");
+        assert_eq!(hll.cardinality(), 0.0);
+        hll.apply_delta(&delta).unwrap();
+        assert_consistent(&hll, "apply_delta");
+
+        let loaded = Hypeerlog::load(replica.dump()).unwrap();
+        assert_consistent(&loaded, "load");
+        assert_consistent(&Hypeerlog::borrow_dump(&replica.dump()).unwrap().to_hypeerlog(), "to_hypeerlog");
+        assert_eq!(loaded.cardinality().to_bits(), replica.cardinality().to_bits());
+    }
+}
+
+#[test]
+fn test_cardinality_does_not_depend_on_insertion_order() {
+    let mut rng = StdRng::seed_from_u64(7);
+    let hashes: Vec<u64> = (0..50_000).map(|_| rng.random()).collect();
+    let mut shuffled = hashes.clone();
+    shuffled.shuffle(&mut rng);
+
+    let a = Hypeerlog::from_hashes(14, hashes);
+    let b = Hypeerlog::from_hashes(14, shuffled);
+    assert_eq!(a.cardinality().to_bits(), b.cardinality().to_bits());
+    assert_consistent(&a, "from_hashes");
+}
+
+#[test]
+fn test_merge_report_estimates_match_cardinality() {
+    let mut rng = StdRng::seed_from_u64(11);
+    let mut hll = random_sketch(12, 2_000, &mut rng);
+    let before = hll.cardinality();
+    let report = hll.merge_with_report(&random_sketch(12, 2_000, &mut rng)).unwrap();
+    assert_eq!(report.estimate_before.to_bits(), before.to_bits());
+    assert_eq!(report.estimate_after.to_bits(), hll.cardinality().to_bits());
+    assert!(report.registers_updated > 0);
+}
+
+// Every call of `cardinality_cached` is checked against `cardinality`, right before and after each operation,
+// so a cached estimate that survived a change of the registers would be caught
+#[test]
+fn test_cached_cardinality_is_invalidated_by_every_change() {
+    fn check(hll: &mut Hypeerlog, step: &str) {
+        let fresh = hll.cardinality();
+        assert_eq!(hll.cardinality_cached().to_bits(), fresh.to_bits(), "after {}", step);
+        assert_eq!(hll.cardinality_cached().to_bits(), fresh.to_bits(), "after {}, cached", step);
+    }
+
+    let mut rng = StdRng::seed_from_u64(355);
+    for p in [4, 10, 14] {
+        let mut hll = Hypeerlog::with_precision(p);
+        check(&mut hll, "creating");
+
+        for i in 0..600u64 {
+            hll.insert(i % 300);
+            check(&mut hll, "insert");
+        }
+        // Only duplicates, which move no register
+        for i in 0..300u64 {
+            hll.insert(i);
+            check(&mut hll, "duplicate insert");
+        }
+        hll.insert_hashes(&(0..1_000).map(|_| rng.random()).collect::<Vec<u64>>());
+        check(&mut hll, "insert_hashes");
+        hll.insert_many_u64(&(0..1_000).map(|_| rng.random()).collect::<Vec<u64>>());
+        check(&mut hll, "insert_many_u64");
+
+        let snap = hll.snapshot();
+        hll.merge_with(&random_sketch(p, 5_000, &mut rng)).unwrap();
+        check(&mut hll, "merge_with");
+        hll.merge_with(&Hypeerlog::with_precision(p)).unwrap();
+        check(&mut hll, "merge_with of an empty sketch");
+        hll.merge_from_dump(&random_sketch(p, 5_000, &mut rng).dump()).unwrap();
+        check(&mut hll, "merge_from_dump");
+        hll.merge_with_report(&random_sketch(p, 5_000, &mut rng)).unwrap();
+        check(&mut hll, "merge_with_report");
+        Hypeerlog::borrow_dump(&random_sketch(p, 5_000, &mut rng).dump()).unwrap().merge_into(&mut hll).unwrap();
+        check(&mut hll, "merge_into");
+
+        let delta = hll.delta_since(&snap).unwrap();
+        let dump = hll.dump();
+        hll.clear();
+        check(&mut hll, "clear");
+        hll.apply_delta(&delta).unwrap();
+        check(&mut hll, "apply_delta");
+
+        let mut loaded = Hypeerlog::load(dump).unwrap();
+        check(&mut loaded, "load");
+        loaded.insert("a new element");
+        check(&mut loaded, "insert after load");
+    }
+}
+
+#[test]
+fn test_cached_estimate_is_not_part_of_the_state() {
+    let mut cached = Hypeerlog::from_hashes(12, 0..10_000u64);
+    let uncached = Hypeerlog::from_hashes(12, 0..10_000u64);
+    cached.cardinality_cached();
+    assert_eq!(cached, uncached);
+    assert_eq!(cached.dump(), uncached.dump());
+}
+
+// With every register at rank `v` the harmonic sum is m * 2^-v, so the estimate pins down the entry of the
+// 2^-v table for `v`, for every rank a 64-bit hash can reach
+#[test]
+fn test_rank_weights_equal_powi() {
+    let p = 4;
+    let m = 16.0;
+    let alpha = 0.7213 / (1.0 + 1.079 / m);
+    for v in 1..=61 {
+        let hashes = (0..16u64).map(|idx| if v < 61 { idx | 1 << (p + v - 1) } else { idx });
+        let hll = Hypeerlog::from_hashes(p as u8, hashes);
+        let expected = alpha * m * m * (1.0 / (m * 2f64.powi(-v)));
+        assert_eq!(hll.cardinality().to_bits(), expected.to_bits(), "rank {}", v);
+        assert_consistent(&hll, "setting every register to one rank");
+
+        let union = Hypeerlog::borrow_dump(&hll.dump()).unwrap()
+            .union_cardinality(&Hypeerlog::borrow_dump(&Hypeerlog::with_precision(p as u8).dump()).unwrap())
+            .unwrap();
+        assert_eq!(union.to_bits(), expected.to_bits(), "union at rank {}", v);
+    }
+}
+
+// Sketches with each register at a random rank, including zero and the largest rank, exercise every part of
+// the vectorized scans (with the `simd` feature) as well as their scalar tails
+#[test]
+fn test_scans_match_maintained_aggregates_for_any_ranks() {
+    let mut rng = StdRng::seed_from_u64(358);
+    for p in (4..=18).chain([20]) {
+        let max_rank = 65 - p;
+        let random_ranks = |rng: &mut StdRng| {
+            let hashes: Vec<u64> = (0..1u64 << p)
+                .filter_map(|idx| match rng.random_range(0..=max_rank) {
+                    0 => None,
+                    rank if rank == max_rank => Some(idx),
+                    rank => Some(idx | 1 << (p + rank - 1)),
+                })
+                .collect();
+            Hypeerlog::from_hashes(p as u8, hashes)
+        };
+        let (a, b) = (random_ranks(&mut rng), random_ranks(&mut rng));
+        assert_consistent(&a, "inserting random ranks");
+
+        let (dump_a, dump_b) = (a.dump(), b.dump());
+        let union = Hypeerlog::borrow_dump(&dump_a).unwrap().union_cardinality(&Hypeerlog::borrow_dump(&dump_b).unwrap()).unwrap();
+        let merged = a.merge(b).unwrap();
+        assert_eq!(union.to_bits(), merged.cardinality().to_bits(), "p={}", p);
+        assert_consistent(&merged, "merging random ranks");
+    }
+}
+
+#[test]
+fn test_inserts_report_whether_they_raised_a_register() {
+    let mut hll = Hypeerlog::with_precision(12);
+    assert!(hll.insert("element"));
+    assert!(!hll.insert("element"));
+    assert!(hll.insert_str("element"));
+    assert!(!hll.insert_bytes(b"element"));
+
+    // A hash whose bits above the bucket are all zero has the largest rank, so its register cannot grow further
+    assert!(hll.insert_hashed(5));
+    assert!(!hll.insert_hashed(5));
+    assert!(!hll.insert_hashed(5 | 1 << 40));
+    let estimate = hll.cardinality_cached();
+    assert!(!hll.insert_hashed(5 | 1 << 12));
+    assert_eq!(hll.cardinality_cached().to_bits(), estimate.to_bits());
+
+    // The batched inserts count the items that raised their register
+    let mut rng = StdRng::seed_from_u64(372);
+    let elems: Vec<u64> = (0..50_000).map(|_| rng.random()).collect();
+    for p in [12, 21] {
+        let mut batched = Hypeerlog::with_precision(p);
+        let mut raised = 0;
+        let mut one_by_one = Hypeerlog::with_precision(p);
+        let changed = batched.insert_many(&elems);
+        elems.iter().for_each(|elem| raised += one_by_one.insert(elem) as usize);
+        assert_eq!(changed, raised, "p={}", p);
+        assert!(changed > 0 && changed < elems.len(), "p={}", p);
+        assert_eq!(batched.insert_many(&elems), 0, "p={}", p);
+        assert_eq!(batched.insert_iter(elems.iter()), 0, "p={}", p);
+        assert_eq!(batched.insert_many_u64(&elems), 0, "p={}", p);
+    }
+    let mut fresh = Hypeerlog::with_precision(12);
+    assert_eq!(fresh.insert_many_u64(&[7, 7, 7]), 1);
+    assert_eq!(fresh.insert_hashes(&[5, 5]), 1);
+}
+
+#[test]
+fn test_count_rounds_the_estimate() {
+    assert_eq!(Hypeerlog::new().count(), 0);
+    assert_eq!(Hypeerlog::with_precision(25).count(), 0);
+
+    // Linear counting gives estimates a little off the integers at small cardinalities
+    let mut hll = Hypeerlog::with_precision(10);
+    for n in 1..=200u64 {
+        hll.insert(n);
+        let estimate = hll.cardinality();
+        assert_ne!(estimate.fract(), 0.0, "n={}", n);
+        assert_eq!(hll.count(), estimate.round() as u64, "n={}", n);
+    }
+
+    // The counts of the parts add up to more than the count of their union when they overlap
+    let mut a = Hypeerlog::new();
+    let mut b = Hypeerlog::new();
+    a.insert_many(&(0..3000u64).collect::<Vec<_>>());
+    b.insert_many(&(2000..5000u64).collect::<Vec<_>>());
+    let (count_a, count_b) = (a.count(), b.count());
+    let merged = a.merge(b).unwrap();
+    assert!(merged.count() < count_a + count_b);
+    assert_eq!(merged.count(), merged.cardinality().round() as u64);
+
+    // Saturated registers at the lowest precision estimate more than `u64::MAX`
+    let mut saturated = vec![61u8; 16];
+    saturated.push(4);
+    let saturated = Hypeerlog::load(saturated).unwrap();
+    assert!(saturated.cardinality() > u64::MAX as f64);
+    assert_eq!(saturated.count(), u64::MAX);
+}
+
+#[test]
+fn test_clear_resets_to_a_new_sketch() {
+    let mut hll = Hypeerlog::with_precision_seed(12, 9);
+    assert!(hll.is_empty());
+    hll.insert("one");
+    assert!(!hll.is_empty());
+    hll.insert_many(&(0..10_000u64).collect::<Vec<_>>());
+    hll.cardinality_cached();
+
+    hll.clear();
+    let fresh = Hypeerlog::with_precision_seed(12, 9);
+    assert!(hll.is_empty());
+    assert_eq!(hll, fresh);
+    assert_eq!(hll.dump(), fresh.dump());
+    assert_eq!(hll.dump_compact(), fresh.dump_compact());
+    assert_eq!(hll.cardinality_cached(), 0.0);
+    assert_eq!(hll.count(), 0);
+    assert_consistent(&hll, "clear");
+
+    // A cleared sketch fills up again like a new one
+    let mut refilled = fresh;
+    assert!(hll.insert("one") && refilled.insert("one"));
+    assert_eq!(hll, refilled);
+    assert_eq!(hll.cardinality().to_bits(), refilled.cardinality().to_bits());
+}
+
+#[test]
+fn test_register_values_reflect_inserts() {
+    let mut hll = Hypeerlog::with_precision(10);
+    assert_eq!(hll.register_values(), &[0; 1 << 10][..]);
+
+    assert!(hll.insert("first"));
+    let raised: Vec<usize> = (0..hll.len()).filter(|&i| hll.register_values()[i] != 0).collect();
+    assert_eq!(raised.len(), 1);
+    let before = hll.register_values().to_vec();
+    assert!(!hll.insert("first"));
+    assert_eq!(hll.register_values(), &before[..]);
+
+    hll.insert_many(&(0..50_000u64).collect::<Vec<_>>());
+    let values = hll.register_values();
+    assert_eq!(values.len(), 1 << 10);
+    assert!(values.iter().all(|&rank| (1..=65 - 10).contains(&rank)));
+    assert!(values[raised[0]] >= before[raised[0]]);
+    assert_eq!(values, &hll.dump()[hll.dump_len() - hll.len()..]);
+    assert_eq!(Hypeerlog::load(hll.dump()).unwrap().register_values(), values);
+
+    let mut union = Hypeerlog::with_precision(10);
+    union.insert("other");
+    union.merge_with(&hll).unwrap();
+    assert!(union.register_values().iter().zip(values.iter()).all(|(&merged, &rank)| merged >= rank));
+    hll.clear();
+    assert!(hll.register_values().iter().all(|&rank| rank == 0));
+}
+
+#[test]
+fn test_iter_nonzero_yields_the_raised_registers() {
+    let mut hll = Hypeerlog::with_precision(8);
+    assert_eq!(hll.iter_nonzero().next(), None);
+    assert_eq!(hll.iter().len(), 256);
+    assert!(hll.iter().all(|rank| rank == 0));
+
+    // The low 8 bits pick the bucket, and the trailing zeros above them the rank minus one
+    for (bucket, zeros) in [(200u64, 0), (7, 3), (0, 55), (7, 1), (255, 10)] {
+        hll.insert_hashed(bucket | 1 << (8 + zeros));
+    }
+    hll.insert_hashed(42);
+    let expected = [(0, 56), (7, 4), (42, 57), (200, 1), (255, 11)];
+    assert_eq!(hll.iter_nonzero().collect::<Vec<_>>(), expected);
+
+    let ranks: Vec<u8> = hll.iter().collect();
+    assert_eq!(ranks, *hll.register_values());
+    let pairs: Vec<(usize, u8)> = ranks.iter().copied().enumerate().filter(|&(_, rank)| rank != 0).collect();
+    assert_eq!(pairs, expected);
+
+    hll.clear();
+    assert_eq!(hll.iter_nonzero().count(), 0);
+}
+
+#[test]
+fn test_register_histogram_counts_each_value() {
+    let empty = Hypeerlog::with_precision(12);
+    let mut expected = [0u32; 65];
+    expected[0] = 1 << 12;
+    assert_eq!(empty.register_histogram(), expected);
+
+    let mut rng = StdRng::seed_from_u64(383);
+    for p in [4, 9, 14] {
+        for n in [1, 100, 10_000, 200_000] {
+            let hll = random_sketch(p, n, &mut rng);
+            let histogram = hll.register_histogram();
+            assert_eq!(histogram.iter().map(|&count| count as usize).sum::<usize>(), 1 << p);
+
+            let mut brute = [0u32; 65];
+            for value in 0..65u8 {
+                brute[value as usize] = hll.register_values().iter().filter(|&&rank| rank == value).count() as u32;
+            }
+            assert_eq!(histogram, brute, "p={} n={}", p, n);
+            assert!(histogram[65 - p as usize + 1..].iter().all(|&count| count == 0));
+        }
+    }
+}
+
+#[test]
+fn test_stats_match_the_registers() {
+    let empty = Hypeerlog::with_precision(12).stats();
+    assert_eq!((empty.registers, empty.nonzero_registers, empty.zero_registers, empty.max_register), (4096, 0, 4096, 0));
+    assert_eq!((empty.estimate, empty.memory_bytes, empty.total_insertions), (0.0, size_of::<Hypeerlog>(), 0));
+
+    let mut hll = Hypeerlog::with_precision(12);
+    hll.insert_many(&(0..3000u64).collect::<Vec<_>>());
+    let stats = hll.stats();
+    let values = hll.register_values();
+    let zeros = values.iter().filter(|&&rank| rank == 0).count();
+    assert_eq!(stats.precision, 12);
+    assert_eq!((stats.registers, stats.zero_registers, stats.nonzero_registers), (4096, zeros, 4096 - zeros));
+    assert_eq!(stats.max_register, *values.iter().max().unwrap());
+    assert_eq!(stats.estimate.to_bits(), hll.cardinality().to_bits());
+    assert_eq!(stats.relative_error, hypeerlog::rel_error_from_p(12));
+    assert_eq!(stats.memory_bytes, size_of::<Hypeerlog>() + 4096);
+    assert_eq!(stats.total_insertions, 3000);
+
+    assert_eq!(
+        stats.to_string(),
+        format!(
+            "p=12 registers=4096 nonzero={} zero={} max={} estimate={:.1} error=1.62% memory={}B insertions=3000",
+            4096 - zeros, zeros, stats.max_register, hll.cardinality(), stats.memory_bytes,
+        ),
+    );
+
+    // Duplicates count as insertions without changing the registers
+    hll.insert_many(&[0u64, 1]);
+    let again = hll.stats();
+    assert_eq!((again.total_insertions, again.zero_registers), (3002, zeros));
+}
+
+#[test]
+fn test_saturation_grows_with_inserts() {
+    let mut hll = Hypeerlog::with_precision(10);
+    assert_eq!(hll.saturation(), 0.0);
+
+    let mut previous = 0.0;
+    for i in 0..20_000u64 {
+        hll.insert(i);
+        let saturation = hll.saturation();
+        assert!(saturation >= previous && saturation <= 1.0, "{} after {}", saturation, i);
+        previous = saturation;
+        if i == 0 {
+            assert_eq!(saturation, 1.0 / 1024.0);
+        }
+    }
+    assert_eq!(hll.saturation(), 1.0);
+    assert_eq!(hll.saturation(), hll.stats().nonzero_registers as f64 / 1024.0);
+
+    // After n distinct elements the saturation is about 1 - e^(-n/m), the relation LinearCounting inverts
+    let mut hll = Hypeerlog::with_precision(14);
+    hll.insert_many(&(0..20_000u64).collect::<Vec<_>>());
+    assert!((hll.saturation() - (1.0 - (-20_000.0f64 / 16384.0).exp())).abs() < 0.01, "{}", hll.saturation());
+    hll.clear();
+    assert_eq!(hll.saturation(), 0.0);
+}
+
+#[test]
+fn test_crafted_hashes_make_the_sketch_unreliable() {
+    let mut rng = StdRng::seed_from_u64(386);
+    let hll = random_sketch(10, 100_000, &mut rng);
+    assert!(hll.is_reliable());
+    assert_eq!(hll.max_rank(), *hll.register_values().iter().max().unwrap());
+    assert_eq!((Hypeerlog::new().max_rank(), Hypeerlog::new().is_reliable()), (0, true));
+
+    // With 64-bit hashes, ranks at p=10 reach 55, for hashes whose 54 bits above the bucket are zero
+    let mut hll = Hypeerlog::with_hasher_precision(10, BuildHasherDefault::<DefaultHasher>::default());
+    for _ in 0..100_000 {
+        hll.insert(rng.random::<u64>());
+    }
+    assert!(hll.is_reliable());
+    hll.insert_hashed(5 | 1 << (10 + 52));
+    assert_eq!((hll.max_rank(), hll.is_reliable()), (53, true));
+    hll.insert_hashed(6 | 1 << (10 + 53));
+    assert_eq!((hll.max_rank(), hll.is_reliable()), (54, false));
+    hll.insert_hashed(7);
+    assert_eq!((hll.max_rank(), hll.is_reliable()), (55, false));
+
+    // Every register at the given rank of p=4 estimates 0.673 * 16 * 2^rank, checked against 2^bits / 30
+    for (rank, bits_64, bits_32) in [(23u32, true, true), (24, true, false), (55, true, false), (59, false, false)] {
+        let mut wide = Hypeerlog::with_hasher_precision(4, BuildHasherDefault::<DefaultHasher>::default());
+        let mut murmur = Hypeerlog::with_precision(4);
+        for bucket in 0..16u64 {
+            wide.insert_hashed(bucket | 1 << (4 + rank - 1));
+            murmur.insert_hashed(bucket | 1 << (4 + rank - 1));
+        }
+        assert_eq!(wide.max_rank() as u32, rank);
+        assert_eq!(wide.is_reliable(), bits_64, "rank {}: {}", rank, wide.cardinality());
+        assert_eq!(murmur.is_reliable(), bits_32, "rank {}: {}", rank, murmur.cardinality());
+    }
+}
+
+#[test]
+fn test_repeated_inserts_count_towards_the_duplication_ratio() {
+    let mut hll = Hypeerlog::new();
+    assert_eq!((hll.total_insertions(), hll.duplication_ratio()), (0, 1.0));
+
+    for _ in 0..1000 {
+        hll.insert("same");
+    }
+    assert_eq!(hll.total_insertions(), 1000);
+    assert_eq!(hll.cardinality().round(), 1.0);
+    assert!((hll.duplication_ratio() - 1000.0).abs() < 1.0, "{}", hll.duplication_ratio());
+
+    // Every insert path counts each of its items, and clearing resets the count
+    hll.insert_many(&[1, 2, 3]);
+    hll.insert_iter(0..10u64);
+    hll.insert_many_u64(&[7; 5]);
+    hll.insert_hashes(&[1, 2]);
+    hll.insert_str("same");
+    assert_eq!(hll.total_insertions(), 1000 + 3 + 10 + 5 + 2 + 1);
+    hll.clear();
+    assert_eq!((hll.total_insertions(), hll.duplication_ratio()), (0, 1.0));
+
+    // From precision 20 the inserts are batched
+    let mut large = Hypeerlog::with_precision(20);
+    large.insert_iter((0..1000u64).map(|x| x % 10));
+    assert_eq!(large.total_insertions(), 1000);
+    assert_eq!(large.duplication_ratio().round(), 100.0);
+}
+
+#[test]
+fn test_count_distinct_estimates_within_the_default_error() {
+    // Three standard errors of p=14
+    let bound = 3.0 * Hypeerlog::new().relative_error();
+    let within = |estimate: f64, exact: f64| (estimate - exact).abs() <= bound * exact;
+
+    assert_eq!(count_distinct(std::iter::empty::<u64>()), 0.0);
+    assert!(within(count_distinct(0..100_000u64), 100_000.0), "{}", count_distinct(0..100_000u64));
+
+    let mut rng = StdRng::seed_from_u64(388);
+    let duplicated: Vec<u64> = (0..200_000).map(|_| rng.random_range(0..20_000)).collect();
+    let estimate = count_distinct(&duplicated);
+    assert!(within(estimate, 20_000.0), "{}", estimate);
+    assert_eq!(estimate.to_bits(), Hypeerlog::from_iter(&duplicated).cardinality().to_bits());
+
+    assert_eq!(count_distinct_with_precision(14, &duplicated), Ok(estimate));
+    assert_eq!(count_distinct_with_precision(20, 0..100_000u64), Ok(Hypeerlog::from_iter_with_precision(20, 0..100_000u64).cardinality()));
+    for precision in [0, 3, 26] {
+        assert_eq!(count_distinct_with_precision(precision, 0..10u64), Err(HypeerlogError::InvalidPrecision));
+    }
+}
@@ -0,0 +1,96 @@
+// Helpers shared by the integration tests, each of which only uses some of them
+#![allow(dead_code)]
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::BuildHasher;
+
+use hypeerlog::{HasherId, Hypeerlog, Murmur3BuildHasher, Murmur3Hasher};
+
+
+
+// A sketch of the integers `start..end`
+pub fn sketch_of_range(p: u8, start: u64, end: u64) -> Hypeerlog {
+    Hypeerlog::from_iter_with_precision(p, start..end)
+}
+
+// A hasher declaring an arbitrary tag, so that sketches of one type can still disagree
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tagged(pub u32);
+
+impl BuildHasher for Tagged {
+    type Hasher = DefaultHasher;
+
+    fn build_hasher(&self) -> DefaultHasher {
+        DefaultHasher::new()
+    }
+}
+
+impl HasherId for Tagged {
+    fn hasher_tag(&self) -> Option<u32> {
+        Some(self.0)
+    }
+}
+
+// A hasher that is not `PartialEq`
+#[derive(Debug, Clone, Default)]
+pub struct Opaque(Murmur3BuildHasher);
+
+impl BuildHasher for Opaque {
+    type Hasher = Murmur3Hasher;
+
+    fn build_hasher(&self) -> Murmur3Hasher {
+        self.0.build_hasher()
+    }
+}
+
+impl HasherId for Opaque {}
+
+// Counts the allocations and the bytes allocated by the current thread, so that the tests running in parallel do
+// not see each other's; a test binary installs it with `#[global_allocator]`
+pub struct CountingAlloc;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    static ALLOCATED: Cell<usize> = const { Cell::new(0) };
+}
+
+fn count(layout: Layout) {
+    let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+    let _ = ALLOCATED.try_with(|bytes| bytes.set(bytes.get() + layout.size()));
+}
+
+// SAFETY: every call is forwarded unchanged to the system allocator
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        count(layout);
+        // SAFETY: the caller upholds the `GlobalAlloc::alloc` contract
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        count(layout);
+        // SAFETY: the caller upholds the `GlobalAlloc::alloc_zeroed` contract
+        unsafe { System.alloc_zeroed(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        // SAFETY: `ptr` was returned by `alloc` or `alloc_zeroed` with the same layout
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+// The bytes allocated by the current thread while running `f`
+pub fn allocated_by<T>(f: impl FnOnce() -> T) -> (T, usize) {
+    let before = ALLOCATED.with(Cell::get);
+    let result = f();
+    (result, ALLOCATED.with(Cell::get) - before)
+}
+
+// The number of allocations of the current thread while running `f`
+pub fn allocations_by<T>(f: impl FnOnce() -> T) -> (T, usize) {
+    let before = ALLOCATIONS.with(Cell::get);
+    let result = f();
+    (result, ALLOCATIONS.with(Cell::get) - before)
+}
@@ -0,0 +1,96 @@
+use hypeerlog::{merge_dumps, Hypeerlog, HypeerlogError};
+
+
+
+
+// Builds a p=16 sketch with the given percentage of nonzero registers, spread evenly
+fn sketch_with_occupancy(percent: usize) -> Hypeerlog {
+    let len = 1 << 16;
+    let mut bytes: Vec<u8> = (0..len)
+        .map(|i| if i * percent % 100 < percent { (i % 20 + 1) as u8 } else { 0 })
+        .collect();
+    bytes.push(16);
+    Hypeerlog::load(bytes).unwrap()
+}
+
+fn occupied(hll: &Hypeerlog) -> usize {
+    hll.iter().filter(|&r| r != 0).count()
+}
+
+
+
+
+#[test]
+fn test_compact_round_trip() {
+    for percent in [0, 1, 50, 100] {
+        let hll = sketch_with_occupancy(percent);
+        assert_eq!(occupied(&hll) * 100 / hll.len(), percent);
+
+        let compact = hll.dump_compact();
+        assert_eq!(Hypeerlog::load(compact.clone()).unwrap(), hll);
+        assert_eq!(Hypeerlog::load_unchecked(compact).unwrap(), hll);
+    }
+}
+
+#[test]
+fn test_compact_is_smaller_for_sparse_sketches() {
+    for percent in [0, 1] {
+        let hll = sketch_with_occupancy(percent);
+        let compact = hll.dump_compact();
+        assert!(compact.len() < hll.len() / 10);
+        assert_ne!(compact[5] & 0b1000, 0);
+    }
+}
+
+#[test]
+fn test_compact_falls_back_to_dense() {
+    for percent in [50, 100] {
+        let hll = sketch_with_occupancy(percent);
+        assert_eq!(hll.dump_compact(), hll.dump());
+    }
+}
+
+#[test]
+fn test_compact_real_sketch() {
+    let mut hll = Hypeerlog::with_precision(16);
+    for i in 0..200u64 {
+        hll.insert(i);
+    }
+    let compact = hll.dump_compact();
+    assert!(compact.len() < 1000);
+    assert_eq!(Hypeerlog::load(compact).unwrap().cardinality(), hll.cardinality());
+}
+
+#[test]
+fn test_compact_merges() {
+    let a = sketch_with_occupancy(1);
+    let b = sketch_with_occupancy(50);
+    let merged = sketch_with_occupancy(1).merge(sketch_with_occupancy(50)).unwrap();
+
+    assert_eq!(Hypeerlog::load(merge_dumps([a.dump_compact(), b.dump()]).unwrap()).unwrap(), merged);
+
+    let mut target = a;
+    target.merge_from_dump(&b.dump_compact()).unwrap();
+    assert_eq!(target, merged);
+}
+
+#[test]
+fn test_compact_malformed() {
+    let hll = sketch_with_occupancy(1);
+    let compact = hll.dump_compact();
+
+    // A truncated pair
+    let truncated = compact[..compact.len() - 1].to_vec();
+    assert_eq!(Hypeerlog::load(truncated), Err(HypeerlogError::InvalidLength));
+
+    // A zero run pointing past the last register
+    let mut overflowing = compact.clone();
+    overflowing.extend_from_slice(&[0xff, 0xff, 0x7f, 1]);
+    assert_eq!(Hypeerlog::load_unchecked(overflowing), Err(HypeerlogError::InvalidRegisterIndex));
+
+    // A corrupted value is caught by the checksum over the decoded registers
+    let mut corrupted = compact;
+    let last = corrupted.len() - 1;
+    corrupted[last] ^= 0x01;
+    assert!(matches!(Hypeerlog::load(corrupted), Err(HypeerlogError::ChecksumMismatch { .. })));
+}
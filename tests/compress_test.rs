@@ -0,0 +1,128 @@
+use hypeerlog::{Hypeerlog, HypeerlogError};
+
+mod common;
+use common::sketch_of_range;
+
+
+
+
+// Header, checksum, seed, tag, codec and insertion count of a compressed dump from the default hasher
+const PREFIX_LEN: usize = 31;
+
+
+// Replaces the payload of a compressed dump, keeping its header
+fn with_payload(dump: &[u8], payload: &[u8]) -> Vec<u8> {
+    let mut bytes = dump[..PREFIX_LEN].to_vec();
+    bytes.extend_from_slice(payload);
+    bytes
+}
+
+
+
+
+#[test]
+fn test_unknown_codec() {
+    let dense = sketch_of_range(12, 0, 1000).dump();
+    // The codec goes between the tag and the insertion count
+    let mut header = dense[..22].to_vec();
+    header[5] |= 0b1_0000;
+    header.push(0xff);
+    header.extend_from_slice(&dense[22..30]);
+    let dump = with_payload(&header, &dense[30..]);
+    assert_eq!(Hypeerlog::load(dump), Err(HypeerlogError::UnsupportedCompression));
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn test_zstd_round_trip() {
+    let hll = sketch_of_range(14, 0, 100_000);
+    for level in [0, 1, 3, 9, 19] {
+        let dump = hll.dump_zstd(level);
+        assert!(dump.len() < hll.dump().len());
+        let loaded = Hypeerlog::load(dump).unwrap();
+        assert_eq!((&loaded, loaded.total_insertions()), (&hll, 100_000));
+    }
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn test_zstd_merges() {
+    let a = sketch_of_range(12, 0, 1000);
+    let b = sketch_of_range(12, 500, 5000);
+    let merged = sketch_of_range(12, 0, 5000);
+
+    let mut target = sketch_of_range(12, 0, 1000);
+    target.merge_from_dump(&b.dump_zstd(3)).unwrap();
+    assert_eq!(target, merged);
+    assert_eq!(Hypeerlog::load(hypeerlog::merge_dumps([a.dump_zstd(3), b.dump()]).unwrap()).unwrap(), merged);
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn test_zstd_decompression_bomb() {
+    let dump = sketch_of_range(12, 0, 1000).dump_zstd(3);
+
+    // Claims p=12 but expands to far more than 2^12 bytes
+    let bomb = zstd::bulk::compress(&vec![0u8; 1 << 24], 19).unwrap();
+    assert!(bomb.len() < 4096);
+    assert_eq!(Hypeerlog::load(with_payload(&dump, &bomb)), Err(HypeerlogError::InvalidLength));
+
+    // Only a single byte over the limit
+    let over = zstd::bulk::compress(&[0u8; (1 << 12) + 1], 3).unwrap();
+    assert_eq!(Hypeerlog::load(with_payload(&dump, &over)), Err(HypeerlogError::InvalidLength));
+
+    // And too short
+    let under = zstd::bulk::compress(&[0u8; (1 << 12) - 1], 3).unwrap();
+    assert_eq!(Hypeerlog::load(with_payload(&dump, &under)), Err(HypeerlogError::InvalidLength));
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn test_zstd_corrupted_payload() {
+    let dump = sketch_of_range(12, 0, 1000).dump_zstd(3);
+    assert_eq!(Hypeerlog::load(with_payload(&dump, b"not zstd at all")), Err(HypeerlogError::DecompressionFailed));
+}
+
+#[cfg(feature = "flate2")]
+#[test]
+fn test_gzip_round_trip() {
+    for hll in [sketch_of_range(4, 0, 10), sketch_of_range(12, 0, 1000), sketch_of_range(16, 0, 100_000)] {
+        let dump = hll.dump_gzip();
+        // The gzip framing outweighs the savings on tiny sketches
+        if hll.len() >= 1 << 12 {
+            assert!(dump.len() < hll.dump().len());
+        }
+        let loaded = Hypeerlog::load(dump).unwrap();
+        assert_eq!((&loaded, loaded.total_insertions()), (&hll, hll.total_insertions()));
+    }
+}
+
+#[cfg(feature = "flate2")]
+#[test]
+fn test_gzip_decompression_bomb() {
+    use std::io::Write;
+
+    let gzip = |bytes: &[u8]| {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::best());
+        encoder.write_all(bytes).unwrap();
+        encoder.finish().unwrap()
+    };
+    let dump = sketch_of_range(12, 0, 1000).dump_gzip();
+
+    let bomb = gzip(&vec![0u8; 1 << 24]);
+    assert!(bomb.len() < 1 << 16);
+    assert_eq!(Hypeerlog::load(with_payload(&dump, &bomb)), Err(HypeerlogError::InvalidLength));
+    assert_eq!(Hypeerlog::load(with_payload(&dump, &gzip(&[0u8; (1 << 12) + 1]))), Err(HypeerlogError::InvalidLength));
+    assert_eq!(Hypeerlog::load(with_payload(&dump, b"not gzip at all")), Err(HypeerlogError::DecompressionFailed));
+}
+
+#[cfg(all(feature = "zstd", feature = "flate2"))]
+#[test]
+fn test_both_codecs_enabled() {
+    let hll = sketch_of_range(14, 0, 50_000);
+    assert_eq!(Hypeerlog::load(hll.dump_gzip()).unwrap(), hll);
+    assert_eq!(Hypeerlog::load(hll.dump_zstd(3)).unwrap(), hll);
+
+    let merged = hypeerlog::merge_dumps([hll.dump_gzip(), hll.dump_zstd(3)]).unwrap();
+    assert_eq!(Hypeerlog::load(merged).unwrap(), hll);
+}
@@ -0,0 +1,234 @@
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{BuildHasher, Hasher};
+
+use hypeerlog::{AtomicHypeerlog, HasherId, Hypeerlog, HypeerlogError};
+
+mod common;
+use common::sketch_of_range;
+
+
+
+
+// A storage layer that only knows its values through conversion bounds
+#[derive(Default)]
+struct Store {
+    blobs: HashMap<String, Vec<u8>>,
+}
+
+impl Store {
+    fn put<'a, T>(&mut self, key: &str, value: &'a T)
+    where
+        Vec<u8>: From<&'a T>,
+    {
+        self.blobs.insert(key.to_string(), Vec::from(value));
+    }
+
+    fn get<T>(&self, key: &str) -> Result<T, HypeerlogError>
+    where
+        T: for<'b> TryFrom<&'b [u8], Error = HypeerlogError>,
+    {
+        T::try_from(self.blobs[key].as_slice())
+    }
+
+    fn take<T>(&mut self, key: &str) -> Result<T, T::Error>
+    where
+        T: TryFrom<Vec<u8>>,
+    {
+        T::try_from(self.blobs.remove(key).unwrap())
+    }
+}
+
+
+
+
+#[test]
+fn test_conversions_round_trip() {
+    let hll = sketch_of_range(12, 0, 1000);
+    let mut store = Store::default();
+    store.put("a", &hll);
+    store.put("b", &hll);
+
+    assert_eq!(store.blobs["a"], hll.dump());
+    assert_eq!(store.get::<Hypeerlog>("a").unwrap(), hll);
+    assert_eq!(store.take::<Hypeerlog>("b").unwrap(), hll);
+}
+
+#[test]
+fn test_conversions_validate() {
+    let mut dump = sketch_of_range(12, 0, 1000).dump();
+    dump.pop();
+    assert_eq!(Hypeerlog::try_from(dump.as_slice()), Err(HypeerlogError::InvalidLength));
+    assert_eq!(Hypeerlog::try_from(dump), Err(HypeerlogError::InvalidLength));
+
+    let mut store = Store::default();
+    store.blobs.insert("bad".to_string(), b"not a dump".to_vec());
+    assert_eq!(store.get::<Hypeerlog>("bad"), Err(HypeerlogError::InvalidMagic));
+}
+
+#[test]
+fn test_from_str() {
+    let hll = sketch_of_range(10, 0, 500);
+    let parsed: Hypeerlog = hll.dump_base64().parse().unwrap();
+    assert_eq!(parsed, hll);
+
+    assert_eq!("not base64!".parse::<Hypeerlog>(), Err(HypeerlogError::Encoding));
+    assert_eq!("".parse::<Hypeerlog>(), Err(HypeerlogError::InvalidMagic));
+}
+
+#[test]
+fn test_collect() {
+    let hll: Hypeerlog = (0..5000u64).collect();
+    assert_eq!(hll, sketch_of_range(14, 0, 5000));
+    assert_eq!(Hypeerlog::from_iter_with_precision(21, 0..5000u64), sketch_of_range(21, 0, 5000));
+    assert_eq!(Hypeerlog::from_iter_with_precision(30, 0..10u64).precision(), 25);
+
+    let names: Vec<String> = (0..1000).map(|i| format!("user-{}", i % 300)).collect();
+    let by_ref: Hypeerlog = names.iter().collect();
+    let by_value: Hypeerlog = names.clone().into_iter().collect();
+    let mut expected = Hypeerlog::new();
+    expected.insert_many(&names);
+    assert_eq!(by_ref, expected);
+    assert_eq!(by_value, expected);
+    assert!((by_ref.cardinality() - 300.0).abs() < 10.0);
+
+    let empty: Hypeerlog = std::iter::empty::<u64>().collect();
+    assert!(empty.is_empty());
+    assert_eq!(empty.cardinality(), 0.0);
+    assert_eq!(Hypeerlog::from_iter_with_precision(12, Vec::<&str>::new()).cardinality(), 0.0);
+}
+
+#[test]
+fn test_from_iter_and_slice_match_inserting() {
+    let names: Vec<String> = (0..20_000).map(|i| format!("user-{}", i % 7000)).collect();
+    for p in [4, 10, 14, 20] {
+        let mut expected = Hypeerlog::with_precision(p);
+        for name in &names {
+            expected.insert(name);
+        }
+        let from_iter = Hypeerlog::from_iter_with_precision(p, names.iter().map(String::as_str));
+        let from_slice = Hypeerlog::from_slice_with_precision(p, &names);
+        assert_eq!(from_iter, expected, "p={}", p);
+        assert_eq!(from_slice, expected, "p={}", p);
+        assert_eq!((from_iter.total_insertions(), from_slice.total_insertions()), (20_000, 20_000));
+
+        let hasher = Keyed(0x5eed);
+        let mut expected = Hypeerlog::with_hasher_precision(p, hasher.clone());
+        for name in &names {
+            expected.insert(name);
+        }
+        assert_eq!(Hypeerlog::from_iter_with_hasher_precision(p, hasher.clone(), &names), expected, "p={}", p);
+        assert_eq!(Hypeerlog::from_slice_with_hasher_precision(p, hasher.clone(), &names), expected, "p={}", p);
+    }
+
+    // Out of range precisions are clamped, like the other constructors
+    assert_eq!(Hypeerlog::from_slice_with_precision(30, &names).precision(), 25);
+    assert_eq!(Hypeerlog::from_slice_with_precision(0, &names), Hypeerlog::from_iter_with_precision(4, &names));
+    assert!(Hypeerlog::from_slice_with_precision(12, &Vec::<u64>::new()).is_empty());
+}
+
+#[test]
+fn test_display() {
+    let hll: Hypeerlog = (0..12_534u64).collect();
+    assert_eq!(hll.to_string(), "Hypeerlog(p=14, ~12367 distinct, ±0.8%)");
+    assert_eq!(format!("{:#}", hll), "Hypeerlog(p=14, ~12.4k distinct, ±0.8%)");
+
+    let hll = Hypeerlog::from_iter_with_precision(12, 0..1_500_000u64);
+    assert_eq!(hll.to_string(), format!("Hypeerlog(p=12, ~{} distinct, ±1.6%)", hll.count()));
+    assert_eq!(format!("{:#}", hll), "Hypeerlog(p=12, ~1.5M distinct, ±1.6%)");
+
+    assert_eq!(format!("{:#}", Hypeerlog::with_precision(4)), "Hypeerlog(p=4, ~0 distinct, ±26.0%)");
+    assert_eq!(Hypeerlog::from_iter_with_precision(9, 0..500u64).to_string(), "Hypeerlog(p=9, ~524 distinct, ±4.6%)");
+    assert_eq!(Hypeerlog::with_precision(21).to_string(), "Hypeerlog(p=21, ~0 distinct, ±0.07%)");
+
+    // Estimates above `u64::MAX` use the largest prefix
+    let mut saturated = vec![61u8; 16];
+    saturated.push(4);
+    let saturated = Hypeerlog::load(saturated).unwrap();
+    assert_eq!(format!("{:#}", saturated), "Hypeerlog(p=4, ~18.4E distinct, ±26.0%)");
+    assert_eq!(saturated.to_string(), format!("Hypeerlog(p=4, ~{} distinct, ±26.0%)", u64::MAX));
+}
+
+#[test]
+fn test_debug_summarizes_the_registers() {
+    let mut hll = Hypeerlog::with_precision(16);
+    let debug = format!("{:?}", hll);
+    assert!(debug.len() < 200, "{}", debug);
+    assert!(debug.contains("precision: 16") && debug.contains("len: 65536"), "{}", debug);
+    assert!(debug.contains("nonzero_registers: 0, max_register: 0, insertions: 0, estimate: 0.0"), "{}", debug);
+
+    hll.insert_many(&(0..100_000u64).collect::<Vec<_>>());
+    let debug = format!("{:?}", hll);
+    assert!(debug.len() < 200, "{}", debug);
+    let max = hll.dump()[hll.dump_len() - hll.len()..].iter().copied().max().unwrap();
+    assert!(debug.contains(&format!("max_register: {}, insertions: 100000, estimate: {:?}", max, hll.cardinality())), "{}", debug);
+
+    // The alternate form lists every register
+    let pretty = format!("{:#?}", Hypeerlog::with_precision(4));
+    let (_, registers) = pretty.split_once("registers: [").expect("the alternate form lists the registers");
+    assert_eq!(registers.matches("        0,").count(), 16, "{}", pretty);
+}
+
+// A keyed hasher that, like many, is deliberately not `Debug` so that its key cannot be printed
+#[derive(Clone)]
+struct Keyed(u64);
+
+impl BuildHasher for Keyed {
+    type Hasher = DefaultHasher;
+
+    fn build_hasher(&self) -> DefaultHasher {
+        let mut hasher = DefaultHasher::new();
+        hasher.write_u64(self.0);
+        hasher
+    }
+}
+
+impl HasherId for Keyed {}
+
+#[test]
+fn test_sketches_of_hashers_that_are_not_debug() {
+    const KEY: u64 = 0x5ec2_e7f0_0d12_3456;
+    let mut hll = Hypeerlog::with_hasher_precision(10, Keyed(KEY));
+    hll.insert_many(&(0..1000u64).collect::<Vec<_>>());
+    let mut other = hll.clone();
+    assert!(!other.insert(1000u64) || other != hll);
+    other.merge_with(&hll).unwrap();
+
+    let loaded = Hypeerlog::load_with_hasher(hll.dump(), Keyed(KEY)).unwrap();
+    assert_eq!(loaded, hll);
+    let atomic = AtomicHypeerlog::from(loaded);
+    assert_eq!(Hypeerlog::from(atomic), hll);
+
+    for debug in [format!("{:?}", hll), format!("{:#?}", hll), hll.to_string()] {
+        assert!(!debug.contains(&KEY.to_string()) && !debug.contains(&format!("{:x}", KEY)), "{}", debug);
+    }
+    assert!(format!("{:?}", hll).contains("hasher_seed: None, hasher_tag: None"));
+}
+
+// Some of the types are std-only
+#[cfg(not(feature = "no_std"))]
+#[test]
+fn test_every_sketch_type_summarizes_itself_with_any_hasher() {
+    use hypeerlog::{BucketedHypeerlog, DecayingHypeerlog, HllMap, MergeTree, MultiHll, PackedHypeerlog, ShardedHypeerlog, SlidingHypeerlog, TimestampedHypeerlog};
+
+    const KEY: u64 = 0x5ec2_e7f0_0d12_3456;
+    let keyed = Keyed(KEY);
+    let debugs = [
+        (format!("{:?}", AtomicHypeerlog::with_hasher_precision(16, keyed.clone())), "AtomicHypeerlog"),
+        (format!("{:?}", BucketedHypeerlog::with_hasher_precision_buckets(16, 60, 24, keyed.clone())), "BucketedHypeerlog"),
+        (format!("{:?}", DecayingHypeerlog::with_hasher_precision_half_life(16, 3600, keyed.clone())), "DecayingHypeerlog"),
+        (format!("{:?}", MultiHll::with_hasher_precision_len(16, 24, keyed.clone())), "MultiHll"),
+        (format!("{:?}", PackedHypeerlog::with_hasher_precision(16, keyed.clone())), "PackedHypeerlog"),
+        (format!("{:?}", SlidingHypeerlog::with_hasher_precision_window(16, 3600, keyed.clone())), "SlidingHypeerlog"),
+        (format!("{:?}", TimestampedHypeerlog::with_hasher_precision(16, keyed.clone())), "TimestampedHypeerlog"),
+        (format!("{:?}", HllMap::<u64, _>::with_hasher_precision(16, keyed.clone())), "HllMap"),
+        (format!("{:?}", MergeTree::with_hasher_precision(16, keyed.clone())), "MergeTree"),
+        (format!("{:?}", ShardedHypeerlog::with_hasher_precision_shards(16, 8, keyed.clone())), "ShardedHypeerlog"),
+    ];
+    for (debug, name) in debugs {
+        assert!(debug.starts_with(name) && debug.len() < 300, "{}", debug);
+        assert!(debug.contains("precision: 16") || debug.contains("ring: BucketedHypeerlog { precision: 16"), "{}", debug);
+        assert!(debug.contains("hasher_seed: None, hasher_tag: None"), "{}", debug);
+        assert!(!debug.contains(&KEY.to_string()) && !debug.contains(&format!("{:x}", KEY)), "{}", debug);
+    }
+}
@@ -0,0 +1,129 @@
+#![cfg(feature = "datasketches-compat")]
+
+use hypeerlog::{DataSketchesHasher, Hypeerlog, HypeerlogError, DATASKETCHES_TAG};
+use std::hash::{BuildHasher, Hasher};
+
+
+
+
+// An HLL_8 sketch image at lgK 12 holding `item-0` to `item-9999`. Generated with a port of the DataSketches
+// HLL update path and serialization (MurmurHash3_x64_128 with seed 9001, HLL mode preamble), not with the
+// Java or C++ library itself.
+const HLL_8_LGK_12: &[u8] = include_bytes!("fixtures/datasketches_hll8_lgk12.bin");
+
+fn items(n: usize) -> impl Iterator<Item = String> {
+    (0..n).map(|i| format!("item-{}", i))
+}
+
+fn datasketches_sketch(lg_k: u8, elements: impl Iterator<Item = String>) -> Hypeerlog<DataSketchesHasher> {
+    let mut hll = Hypeerlog::with_hasher_precision(lg_k, DataSketchesHasher);
+    for element in elements {
+        hll.insert_datasketches(element.as_bytes());
+    }
+    hll
+}
+
+// A preamble with the given mode byte and lgK, followed by `slots` zeroed slots
+fn image(mode: u8, lg_k: u8, slots: usize) -> Vec<u8> {
+    let mut bytes = vec![10, 1, 7, lg_k, 0, 0x10, 0, mode];
+    bytes.resize(40 + slots, 0);
+    bytes
+}
+
+
+
+
+#[test]
+fn test_from_datasketches_hll_8() {
+    let hll = Hypeerlog::from_datasketches(HLL_8_LGK_12).unwrap();
+    assert_eq!(hll.precision(), 12);
+    assert_eq!(hll.len(), 4096);
+
+    // lgK 12 has a standard error of ~1.6%
+    let estimate = hll.cardinality();
+    assert!((estimate - 10_000.0).abs() / 10_000.0 < 0.05, "estimate {}", estimate);
+}
+
+#[test]
+fn test_insert_datasketches_matches_update() {
+    assert_eq!(datasketches_sketch(12, items(10_000)), Hypeerlog::from_datasketches(HLL_8_LGK_12).unwrap());
+
+    // Empty elements are ignored, as in DataSketches
+    let mut hll = Hypeerlog::with_hasher_precision(12, DataSketchesHasher);
+    hll.insert_datasketches(b"");
+    assert!(hll.is_empty());
+}
+
+#[test]
+fn test_datasketches_round_trip() {
+    let hll = Hypeerlog::from_datasketches(HLL_8_LGK_12).unwrap();
+    let exported = hll.to_datasketches().unwrap();
+    assert_eq!(exported.len(), HLL_8_LGK_12.len());
+
+    // Everything but the HIP accumulator, which exports carry as the estimate of this crate
+    assert_eq!(exported[..8], HLL_8_LGK_12[..8]);
+    assert_eq!(f64::from_le_bytes(exported[8..16].try_into().unwrap()), hll.cardinality());
+    assert_eq!(exported[16..], HLL_8_LGK_12[16..]);
+    assert_eq!(Hypeerlog::from_datasketches(&exported).unwrap(), hll);
+
+    for lg_k in [4, 21] {
+        let hll = datasketches_sketch(lg_k, items(100));
+        assert_eq!(Hypeerlog::from_datasketches(&hll.to_datasketches().unwrap()).unwrap(), hll);
+    }
+}
+
+#[test]
+fn test_from_datasketches_rejects_invalid_input() {
+    assert_eq!(Hypeerlog::from_datasketches(&[10, 1, 7]), Err(HypeerlogError::InvalidMagic));
+    assert_eq!(Hypeerlog::from_datasketches(&Hypeerlog::new().dump()), Err(HypeerlogError::InvalidMagic));
+
+    let mut wrong_family = HLL_8_LGK_12.to_vec();
+    wrong_family[2] = 3;
+    assert_eq!(Hypeerlog::from_datasketches(&wrong_family), Err(HypeerlogError::InvalidMagic));
+
+    assert_eq!(Hypeerlog::from_datasketches(&HLL_8_LGK_12[..HLL_8_LGK_12.len() - 1]), Err(HypeerlogError::InvalidLength));
+    assert_eq!(Hypeerlog::from_datasketches(&image(2 | 2 << 2, 3, 8)), Err(HypeerlogError::InvalidPrecision));
+    assert_eq!(Hypeerlog::from_datasketches(&image(2 | 2 << 2, 22, 0)), Err(HypeerlogError::InvalidPrecision));
+
+    let mut too_large = HLL_8_LGK_12.to_vec();
+    too_large[40] = 54;
+    assert_eq!(Hypeerlog::from_datasketches(&too_large), Err(HypeerlogError::InvalidRegisterValue));
+}
+
+#[test]
+fn test_unsupported_modes() {
+    // List and set modes of HLL_8, then HLL mode of HLL_4 and HLL_6 (target type in bits 2-3, mode in bits 0-1)
+    for mode in [0b10_00, 0b10_01, 0b00_10, 0b01_10] {
+        assert_eq!(Hypeerlog::from_datasketches(&image(mode, 12, 4096)), Err(HypeerlogError::UnsupportedEncoding));
+    }
+    assert_eq!(HypeerlogError::UnsupportedEncoding.to_string(), "Unsupported encoding of a foreign sketch format");
+}
+
+#[test]
+fn test_to_datasketches_precision_limit() {
+    let hll = Hypeerlog::with_hasher_precision(22, DataSketchesHasher);
+    assert_eq!(hll.to_datasketches(), Err(HypeerlogError::InvalidPrecision));
+}
+
+#[test]
+fn test_datasketches_sketches_do_not_mix() {
+    let dump = Hypeerlog::from_datasketches(HLL_8_LGK_12).unwrap().dump();
+    assert_eq!(Hypeerlog::load(dump.clone()), Err(HypeerlogError::HasherMismatch));
+    assert_eq!(Hypeerlog::with_precision(12).merge_from_dump(&dump), Err(HypeerlogError::HasherMismatch));
+    assert!(Hypeerlog::load_with_hasher(dump, DataSketchesHasher).is_ok());
+    assert_eq!(hypeerlog::HasherId::hasher_tag(&DataSketchesHasher), Some(DATASKETCHES_TAG));
+}
+
+#[test]
+fn test_datasketches_hasher() {
+    // The first half of MurmurHash3_x64_128 with seed 9001, over the exact bytes written
+    let hash = |parts: &[&[u8]]| {
+        let mut hasher = DataSketchesHasher.build_hasher();
+        parts.iter().for_each(|part| hasher.write(part));
+        hasher.finish()
+    };
+    assert_eq!(hash(&[b""]), 0x1e70a32266491bb9);
+    assert_eq!(hash(&[b"hello"]), 0x21b77bd4a835c1aa);
+    assert_eq!(hash(&[b"0123456789abcdef"]), 0x257b60668d289420);
+    assert_eq!(hash(&[b"0123456789", b"abcdefX"]), 0x4f6d45067ea47d29);
+}
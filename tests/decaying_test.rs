@@ -0,0 +1,126 @@
+use hypeerlog::{BucketedHypeerlog, DecayingHypeerlog, HypeerlogError};
+
+
+
+
+const HALF_LIFE: u64 = 400;
+
+// One new user per time unit, from `start`
+fn trickle(hll: &mut DecayingHypeerlog, start: u64, end: u64) {
+    for ts in start..end {
+        hll.insert(ts, ts);
+    }
+}
+
+// The exact decayed weight of the trickle at `now`, with the ages taken from the middle of the buckets
+fn trickle_weight(start: u64, end: u64, now: u64) -> f64 {
+    let bucket = HALF_LIFE / 4;
+    let oldest = (end - 1) / bucket * bucket - (10 * HALF_LIFE - bucket);
+    (start.max(oldest)..end)
+        .map(|ts| {
+            let age = (now as f64 - (ts / bucket * bucket) as f64 - bucket as f64 / 2.0).max(0.0);
+            (-age / HALF_LIFE as f64).exp2()
+        })
+        .sum()
+}
+
+
+
+
+#[test]
+fn test_an_old_burst_fades_away() {
+    let mut quiet = DecayingHypeerlog::with_precision_half_life(12, HALF_LIFE);
+    let mut burst = quiet.clone();
+    for user in 0..20_000u64 {
+        burst.insert(u64::MAX - user, user % 100);
+    }
+    assert_eq!(burst.half_life(), HALF_LIFE);
+
+    // The burst sits in the middle of the first bucket, so its weight halves with each half-life
+    for half_lives in 0..12 {
+        let now = half_lives * HALF_LIFE + 50;
+        trickle(&mut quiet, now.saturating_sub(HALF_LIFE), now);
+        trickle(&mut burst, now.saturating_sub(HALF_LIFE), now);
+
+        let expected = 20_000.0 * 0.5f64.powi(half_lives as i32);
+        let influence = burst.decayed_cardinality(now) - quiet.decayed_cardinality(now);
+        if half_lives < 10 {
+            assert!((influence / expected - 1.0).abs() < 0.05, "{} half-lives: {} for {}", half_lives, influence, expected);
+        } else {
+            // Ten half-lives later, the ring dropped the burst
+            assert_eq!(influence, 0.0);
+        }
+        if half_lives >= 8 {
+            assert!(influence < 0.005 * 20_000.0);
+        }
+    }
+}
+
+#[test]
+fn test_a_steady_stream_settles_at_its_decayed_weight() {
+    let mut hll = DecayingHypeerlog::with_precision_half_life(12, HALF_LIFE);
+    trickle(&mut hll, 0, 20 * HALF_LIFE);
+    for now in [20 * HALF_LIFE - 1, 20 * HALF_LIFE + 150, 23 * HALF_LIFE] {
+        let estimate = hll.decayed_cardinality(now);
+        let truth = trickle_weight(0, 20 * HALF_LIFE, now);
+        assert!((estimate / truth - 1.0).abs() < 4.0 * hll.relative_error(), "now={}: {} for {}", now, estimate, truth);
+    }
+    // About the rate times the half-life over ln 2
+    let settled = hll.decayed_cardinality(20 * HALF_LIFE);
+    assert!((settled / (HALF_LIFE as f64 / 2f64.ln()) - 1.0).abs() < 0.1, "{}", settled);
+
+    // Seeing the old users again makes them recent
+    let mut again = hll.clone();
+    for user in 0..1000 {
+        again.insert(user, 20 * HALF_LIFE);
+    }
+    assert!(again.decayed_cardinality(20 * HALF_LIFE) > settled + 900.0);
+    again.clear();
+    assert_eq!(again.decayed_cardinality(20 * HALF_LIFE), 0.0);
+}
+
+#[test]
+fn test_merges_and_dumps_keep_the_decay() {
+    let (mut even, mut odd) = (DecayingHypeerlog::with_precision_seed_half_life(11, 3, HALF_LIFE), DecayingHypeerlog::with_precision_seed_half_life(11, 3, HALF_LIFE));
+    let mut all = even.clone();
+    for ts in 0..3 * HALF_LIFE {
+        let half = if ts % 2 == 0 { &mut even } else { &mut odd };
+        half.insert(ts % 700, ts);
+        all.insert(ts % 700, ts);
+    }
+    // A sketch that is behind is advanced to the newest bucket of the other one
+    let (early, mut late) = (odd, even);
+    late.merge_with(&early).unwrap();
+    assert_eq!(late.dump(), all.dump());
+    assert_eq!(late.decayed_cardinality(3 * HALF_LIFE), all.decayed_cardinality(3 * HALF_LIFE));
+
+    let before = late.dump();
+    let other_half_life = DecayingHypeerlog::with_precision_seed_half_life(11, 3, 2 * HALF_LIFE);
+    assert_eq!(late.merge_with(&other_half_life).unwrap_err(), HypeerlogError::HalfLifeMismatch);
+    let mut other_seed = DecayingHypeerlog::with_precision_seed_half_life(11, 4, HALF_LIFE);
+    other_seed.insert(1, 10 * HALF_LIFE);
+    assert_eq!(late.merge_with(&other_seed).unwrap_err(), HypeerlogError::SeedMismatch);
+    assert_eq!(late.dump(), before);
+
+    // Dumps keep the half-life and the position of the ring
+    let loaded = DecayingHypeerlog::from_bytes(&before).unwrap();
+    assert_eq!((loaded.half_life(), loaded.precision()), (HALF_LIFE, 11));
+    assert_eq!(loaded.dump(), before);
+    assert_eq!(loaded.decayed_cardinality(5 * HALF_LIFE), all.decayed_cardinality(5 * HALF_LIFE));
+
+    assert_eq!(DecayingHypeerlog::from_bytes(&before[..12]).unwrap_err(), HypeerlogError::InvalidLength);
+    assert_eq!(DecayingHypeerlog::from_bytes(&before[..before.len() - 1]).unwrap_err(), HypeerlogError::InvalidLength);
+    assert_eq!(DecayingHypeerlog::from_bytes(&before[13..]).unwrap_err(), HypeerlogError::InvalidMagic);
+    let mut newer = before.clone();
+    newer[4] = 2;
+    assert_eq!(DecayingHypeerlog::from_bytes(&newer).unwrap_err(), HypeerlogError::UnsupportedVersion);
+    // The ring must be the one of the half-life
+    let mut other = before.clone();
+    other[5..13].copy_from_slice(&(2 * HALF_LIFE).to_le_bytes());
+    assert_eq!(DecayingHypeerlog::from_bytes(&other).unwrap_err(), HypeerlogError::InvalidLength);
+    other[5..13].copy_from_slice(&0u64.to_le_bytes());
+    assert_eq!(DecayingHypeerlog::from_bytes(&other).unwrap_err(), HypeerlogError::InvalidLength);
+    let mut ring = before[..13].to_vec();
+    ring.extend_from_slice(&BucketedHypeerlog::with_precision_seed_buckets(11, 3, HALF_LIFE / 4, 10).dump());
+    assert_eq!(DecayingHypeerlog::from_bytes(&ring).unwrap_err(), HypeerlogError::InvalidLength);
+}
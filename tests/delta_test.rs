@@ -0,0 +1,178 @@
+use hypeerlog::{Hypeerlog, HypeerlogError};
+
+mod common;
+use common::sketch_of_range;
+
+
+
+
+const HEADER_LEN: usize = 5;
+const ENTRY_LEN: usize = 5;
+
+
+// The raw register values of a sketch, taken from its dump
+fn registers(hll: &Hypeerlog) -> Vec<u8> {
+    let dump = hll.dump();
+    dump[dump.len() - hll.len()..].to_vec()
+}
+
+// Decodes the (index, value) entries of a delta
+fn entries(delta: &[u8]) -> Vec<(u32, u8)> {
+    delta[HEADER_LEN..].chunks(ENTRY_LEN)
+        .map(|e| (u32::from_le_bytes([e[0], e[1], e[2], e[3]]), e[4]))
+        .collect()
+}
+
+
+
+
+#[test]
+fn test_delta_unchanged_sketch_is_header_only() {
+    let mut hll = sketch_of_range(12, 0, 1000);
+    let snap = hll.snapshot();
+
+    // Re-inserting already observed elements cannot move any register
+    for i in 0..1000u64 {
+        hll.insert(i);
+    }
+
+    let delta = hll.delta_since(&snap).unwrap();
+    assert_eq!(delta.len(), HEADER_LEN);
+    assert_eq!(delta[0], 12);
+}
+
+#[test]
+fn test_delta_single_change() {
+    let mut hll = Hypeerlog::with_precision(12);
+    let snap = hll.snapshot();
+    hll.insert("one element");
+
+    let delta = hll.delta_since(&snap).unwrap();
+    assert_eq!(delta.len(), HEADER_LEN + ENTRY_LEN);
+
+    let (idx, value) = entries(&delta)[0];
+    assert_eq!(registers(&hll)[idx as usize], value);
+    assert!(value > 0);
+}
+
+#[test]
+fn test_delta_many_changes() {
+    let mut hll = sketch_of_range(10, 0, 100);
+    let snap = hll.snapshot();
+    let before = registers(&hll);
+    for i in 100..5000u64 {
+        hll.insert(i);
+    }
+    let after = registers(&hll);
+
+    let delta = hll.delta_since(&snap).unwrap();
+    let changed: Vec<(u32, u8)> = (0..hll.len())
+        .filter(|&i| before[i] != after[i])
+        .map(|i| (i as u32, after[i]))
+        .collect();
+
+    assert!(!changed.is_empty());
+    assert_eq!(entries(&delta), changed);
+}
+
+#[test]
+fn test_delta_fingerprint_tracks_seed() {
+    let a = Hypeerlog::with_seed(1);
+    let b = Hypeerlog::with_seed(2);
+    let c = Hypeerlog::with_seed(1);
+
+    let fingerprint = |hll: &Hypeerlog| hll.delta_since(&hll.snapshot()).unwrap()[1..HEADER_LEN].to_vec();
+    assert_eq!(fingerprint(&a), fingerprint(&c));
+    assert_ne!(fingerprint(&a), fingerprint(&b));
+}
+
+#[test]
+fn test_delta_since_rejects_other_precision() {
+    let hll = Hypeerlog::with_precision(10);
+    let other = Hypeerlog::with_precision(12);
+    assert_eq!(hll.delta_since(&other.snapshot()), Err(HypeerlogError::PrecisionMismatch));
+}
+
+#[test]
+fn test_apply_delta_replicates_changes() {
+    let mut source = sketch_of_range(12, 0, 500);
+    let mut replica = Hypeerlog::load(source.dump()).unwrap();
+
+    let snap = source.snapshot();
+    for i in 500..3000u64 {
+        source.insert(i);
+    }
+
+    replica.apply_delta(&source.delta_since(&snap).unwrap()).unwrap();
+    assert_eq!(replica, source);
+}
+
+#[test]
+fn test_apply_delta_out_of_order_and_duplicated() {
+    let mut source = Hypeerlog::with_precision(10);
+    let mut deltas = Vec::new();
+    for batch in 0..5u64 {
+        let snap = source.snapshot();
+        for i in batch * 1000..(batch + 1) * 1000 {
+            source.insert(i);
+        }
+        deltas.push(source.delta_since(&snap).unwrap());
+    }
+
+    let mut in_order = Hypeerlog::with_precision(10);
+    for delta in &deltas {
+        in_order.apply_delta(delta).unwrap();
+    }
+
+    let mut shuffled = Hypeerlog::with_precision(10);
+    for &i in &[3, 0, 4, 4, 1, 2, 0, 3] {
+        shuffled.apply_delta(&deltas[i]).unwrap();
+    }
+
+    assert_eq!(in_order, source);
+    assert_eq!(shuffled, source);
+}
+
+#[test]
+fn test_apply_delta_precision_mismatch() {
+    let mut source = Hypeerlog::with_precision(10);
+    let snap = source.snapshot();
+    source.insert(1);
+
+    let mut target = Hypeerlog::with_precision(12);
+    assert_eq!(target.apply_delta(&source.delta_since(&snap).unwrap()), Err(HypeerlogError::PrecisionMismatch));
+}
+
+#[test]
+fn test_apply_delta_fingerprint_mismatch() {
+    let mut source = Hypeerlog::with_precision_seed(10, 1);
+    let snap = source.snapshot();
+    source.insert(1);
+
+    let mut target = Hypeerlog::with_precision_seed(10, 2);
+    assert_eq!(target.apply_delta(&source.delta_since(&snap).unwrap()), Err(HypeerlogError::FingerprintMismatch));
+}
+
+#[test]
+fn test_apply_delta_corrupted() {
+    let mut source = Hypeerlog::with_precision(10);
+    let snap = source.snapshot();
+    source.insert_many(&[1, 2, 3]);
+    let delta = source.delta_since(&snap).unwrap();
+
+    let mut target = Hypeerlog::with_precision(10);
+
+    let mut bad_index = delta.clone();
+    bad_index[HEADER_LEN..HEADER_LEN + 4].copy_from_slice(&1024u32.to_le_bytes());
+    assert_eq!(target.apply_delta(&bad_index), Err(HypeerlogError::InvalidRegisterIndex));
+
+    let mut bad_value = delta.clone();
+    bad_value[HEADER_LEN + 4] = 200;
+    assert_eq!(target.apply_delta(&bad_value), Err(HypeerlogError::InvalidRegisterValue));
+
+    assert_eq!(target.apply_delta(&delta[..delta.len() - 1]), Err(HypeerlogError::InvalidLength));
+    assert_eq!(target.apply_delta(&delta[..3]), Err(HypeerlogError::InvalidLength));
+
+    // None of the rejected deltas may have been partially applied
+    assert!(target.is_empty());
+}
@@ -0,0 +1,567 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{BuildHasher, BuildHasherDefault};
+
+use hypeerlog::{cardinality_of_dump, merge_dumps, HasherId, HasherStatus, Hypeerlog, HypeerlogError};
+
+mod common;
+use common::{sketch_of_range, Tagged};
+
+
+
+
+// Header, checksum, seed and tag of a dump from the default hasher
+const PREFIX_LEN: usize = 22;
+
+// The insertion count that follows them in dumps of sketches that saw an insert
+const INSERTIONS_LEN: usize = 8;
+
+
+// Rebuilds the legacy tail-byte encoding of a sketch from its registers
+fn legacy_dump(hll: &Hypeerlog) -> Vec<u8> {
+    let mut bytes = hll.register_values().to_vec();
+    bytes.push(hll.precision());
+    bytes
+}
+
+
+
+
+#[test]
+fn test_merge_dumps_matches_sketch_merge() {
+    let sketches: Vec<Hypeerlog> = (0..5).map(|i| sketch_of_range(12, i * 1000, i * 1000 + 1500)).collect();
+    let dumps: Vec<Vec<u8>> = sketches.iter().map(|hll| hll.dump()).collect();
+
+    let merged_dump = merge_dumps(&dumps).unwrap();
+
+    let merged_sketch = sketches.into_iter()
+        .reduce(|acc, hll| acc.merge(hll).unwrap())
+        .unwrap();
+
+    assert_eq!(Hypeerlog::load(merged_dump).unwrap(), merged_sketch);
+}
+
+#[test]
+fn test_merge_dumps_single_dump() {
+    let hll = sketch_of_range(10, 0, 100);
+    assert_eq!(merge_dumps([hll.dump()]).unwrap(), hll.dump());
+}
+
+#[test]
+fn test_merge_dumps_empty_input() {
+    let dumps: Vec<Vec<u8>> = Vec::new();
+    assert_eq!(merge_dumps(dumps), Err(HypeerlogError::EmptyInput));
+}
+
+#[test]
+fn test_merge_dumps_precision_mismatch() {
+    let dumps = vec![
+        sketch_of_range(10, 0, 100).dump(),
+        sketch_of_range(10, 100, 200).dump(),
+        sketch_of_range(12, 200, 300).dump(),
+    ];
+    assert_eq!(merge_dumps(&dumps), Err(HypeerlogError::Incompatible { index: 2, source: Box::new(HypeerlogError::PrecisionMismatch) }));
+}
+
+#[test]
+fn test_merge_dumps_invalid_dump() {
+    let mut truncated = sketch_of_range(10, 0, 100).dump();
+    truncated.pop();
+    let dumps = vec![sketch_of_range(10, 0, 100).dump(), truncated];
+    assert_eq!(merge_dumps(&dumps), Err(HypeerlogError::Incompatible { index: 1, source: Box::new(HypeerlogError::InvalidLength) }));
+    assert_eq!(merge_dumps(dumps.iter().rev()), Err(HypeerlogError::Incompatible { index: 0, source: Box::new(HypeerlogError::InvalidLength) }));
+}
+
+#[test]
+fn test_merge_dumps_mixed_formats() {
+    let a = sketch_of_range(10, 0, 100);
+    let b = sketch_of_range(10, 50, 300);
+    let merged = merge_dumps([legacy_dump(&a), b.dump()]).unwrap();
+    assert_eq!(Hypeerlog::load(merged).unwrap(), a.merge(b).unwrap());
+}
+
+
+
+
+#[test]
+fn test_versioned_dump_layout() {
+    let hll = sketch_of_range(12, 0, 1000);
+    let dump = hll.dump();
+    assert_eq!(&dump[..6], b"HLL\x02\x0c\x47");
+    assert_eq!(&dump[10..18], &0u64.to_le_bytes());
+    assert_eq!(&dump[18..22], b"MUR3");
+    assert_eq!(&dump[22..30], &1000u64.to_le_bytes());
+    assert_eq!(dump.len(), PREFIX_LEN + INSERTIONS_LEN + (1 << 12));
+
+    // Sketches that saw no insert do not record the count, and their zero registers take no byte
+    let empty = Hypeerlog::with_precision(12).dump();
+    assert_eq!(&empty[..6], b"HLL\x02\x0c\x0f");
+    assert_eq!(empty.len(), PREFIX_LEN);
+}
+
+#[test]
+fn test_dump_golden_bytes() {
+    // The byte format is a public contract: update the fixture (and the format version) only on purpose
+    let fixture = include_bytes!("fixtures/dump_p4_seed42.bin");
+    let mut hll = Hypeerlog::with_precision_seed(4, 42);
+    for i in 0..20u64 {
+        hll.insert(i);
+    }
+    assert_eq!(hll.dump(), fixture);
+    assert_eq!(Hypeerlog::load(fixture.to_vec()).unwrap(), hll);
+
+    // Header, CRC32 of the registers, seed, tag and insertion count, all little-endian
+    assert_eq!(&fixture[..6], b"HLL\x02\x04\x47");
+    assert_eq!(&fixture[6..10], &[0x5e, 0x54, 0x33, 0x79]);
+    assert_eq!(&fixture[10..18], &[42, 0, 0, 0, 0, 0, 0, 0]);
+    assert_eq!(&fixture[18..22], b"MUR3");
+    assert_eq!(&fixture[22..30], &[20, 0, 0, 0, 0, 0, 0, 0]);
+    assert_eq!(fixture.len(), PREFIX_LEN + INSERTIONS_LEN + 16);
+}
+
+#[test]
+fn test_dump_into_matches_dump() {
+    let mut out = vec![0xaa; 10];
+    for p in [4, 12, 16] {
+        let hll = sketch_of_range(p, 0, 1000);
+        hll.dump_into(&mut out);
+        assert_eq!(out, hll.dump());
+        assert_eq!(out.len(), hll.dump_len());
+    }
+
+    let tagged = tagged_sketch(1);
+    tagged.dump_into(&mut out);
+    assert_eq!(out, tagged.dump());
+    assert_eq!(out.len(), tagged.dump_len());
+}
+
+#[test]
+fn test_dump_into_reuses_buffer() {
+    let mut hll = sketch_of_range(14, 0, 1000);
+    let mut out = Vec::with_capacity(hll.dump_len());
+    let ptr = out.as_ptr();
+
+    for i in 0..10u64 {
+        hll.insert(i * 7919);
+        hll.dump_into(&mut out);
+        assert_eq!(out.as_ptr(), ptr);
+        assert_eq!(out.capacity(), hll.dump_len());
+    }
+}
+
+#[test]
+fn test_versioned_round_trip() {
+    let hll = sketch_of_range(12, 0, 1000);
+    assert_eq!(Hypeerlog::load(hll.dump()).unwrap(), hll);
+
+    let mut buf = vec![0; hll.dump().len()];
+    assert_eq!(hll.dump_to_slice(&mut buf), Ok(buf.len()));
+    assert_eq!(buf, hll.dump());
+}
+
+#[test]
+#[cfg(not(feature = "no_std"))]
+fn test_versioned_stream_round_trip() {
+    let hll = sketch_of_range(12, 0, 1000);
+    let mut written = Vec::new();
+    hll.dump_to(&mut written).unwrap();
+    assert_eq!(written, hll.dump());
+    assert_eq!(Hypeerlog::load_from(written.as_slice()).unwrap(), hll);
+}
+
+#[test]
+fn test_legacy_round_trip() {
+    let hll = sketch_of_range(10, 0, 1000);
+    assert_eq!(Hypeerlog::load(legacy_dump(&hll)).unwrap(), hll);
+}
+
+#[test]
+fn test_from_bytes_sub_slice() {
+    let hll = sketch_of_range(12, 0, 1000);
+    let tagged = tagged_sketch(3);
+
+    for dump in [hll.dump(), legacy_dump(&hll), hll.dump_compact()] {
+        let mut buffer = vec![0xee; 100];
+        buffer.extend_from_slice(&dump);
+        buffer.extend_from_slice(&[0xee; 100]);
+        let slice = &buffer[100..100 + dump.len()];
+        assert_eq!(Hypeerlog::from_bytes(slice).unwrap(), hll);
+    }
+
+    let mut buffer = tagged.dump();
+    buffer.extend_from_slice(&[0; 7]);
+    let slice = &buffer[..buffer.len() - 7];
+    assert_eq!(Hypeerlog::from_bytes_with_hasher(slice, Tagged(3)).unwrap(), tagged);
+    // The trailing bytes are not part of the dump
+    assert_eq!(Hypeerlog::from_bytes_with_hasher(&buffer, Tagged(3)), Err(HypeerlogError::InvalidLength));
+}
+
+#[test]
+fn test_from_bytes_validates_like_load() {
+    let hll = sketch_of_range(12, 0, 1000);
+    let dump = hll.dump();
+
+    let mut bad_version = dump.clone();
+    bad_version[3] = 3;
+    let mut bad_flags = dump.clone();
+    bad_flags[5] |= 0x80;
+    let inputs = [
+        Vec::new(),
+        b"HL".to_vec(),
+        dump[..dump.len() - 1].to_vec(),
+        bad_version,
+        bad_flags,
+        corrupted_dump(&hll),
+        tagged_sketch(1).dump(),
+        vec![0; 17],
+    ];
+    for input in inputs {
+        assert!(Hypeerlog::from_bytes(&input).is_err());
+        assert_eq!(Hypeerlog::from_bytes(&input), Hypeerlog::load(input.clone()));
+    }
+}
+
+#[test]
+fn test_cardinality_of_dump() {
+    for (p, n) in [(4, 10), (12, 0), (12, 1000), (14, 100_000), (16, 300)] {
+        let hll = sketch_of_range(p, 0, n);
+        let expected = Hypeerlog::load(hll.dump()).unwrap().cardinality();
+        for dump in [hll.dump(), legacy_dump(&hll), hll.dump_compact()] {
+            assert_eq!(cardinality_of_dump(&dump).unwrap().to_bits(), expected.to_bits());
+        }
+    }
+    assert_eq!(cardinality_of_dump(&tagged_sketch(1).dump()).unwrap(), tagged_sketch(1).cardinality());
+}
+
+#[test]
+fn test_cardinality_of_invalid_dump() {
+    let hll = sketch_of_range(12, 0, 1000);
+    let dump = hll.dump();
+    assert_eq!(cardinality_of_dump(&dump[..dump.len() - 1]), Err(HypeerlogError::InvalidLength));
+    assert_eq!(cardinality_of_dump(b"nope"), Err(HypeerlogError::InvalidMagic));
+    assert!(matches!(cardinality_of_dump(&corrupted_dump(&hll)), Err(HypeerlogError::ChecksumMismatch { .. })));
+}
+
+#[test]
+fn test_load_wrong_magic() {
+    let mut dump = sketch_of_range(10, 0, 100).dump();
+    dump[0] = b'X';
+    assert_eq!(Hypeerlog::load(dump), Err(HypeerlogError::InvalidMagic));
+    assert_eq!(Hypeerlog::load(b"not a sketch".to_vec()), Err(HypeerlogError::InvalidMagic));
+}
+
+#[test]
+fn test_load_unsupported_version() {
+    let mut dump = sketch_of_range(10, 0, 100).dump();
+    dump[3] = 3;
+    assert_eq!(Hypeerlog::load(dump), Err(HypeerlogError::UnsupportedVersion));
+}
+
+#[test]
+fn test_load_unsupported_flags() {
+    let mut dump = sketch_of_range(10, 0, 100).dump();
+    dump[5] = 0x80;
+    assert_eq!(Hypeerlog::load(dump), Err(HypeerlogError::UnsupportedFlags));
+}
+
+#[test]
+fn test_load_truncated_payload() {
+    let dump = sketch_of_range(10, 0, 100).dump();
+    assert_eq!(Hypeerlog::load(dump[..dump.len() - 1].to_vec()), Err(HypeerlogError::InvalidLength));
+    assert_eq!(Hypeerlog::load(dump[..4].to_vec()), Err(HypeerlogError::InvalidLength));
+
+    let mut oversized = dump.clone();
+    oversized.push(0);
+    assert_eq!(Hypeerlog::load(oversized), Err(HypeerlogError::InvalidLength));
+}
+
+#[test]
+fn test_load_invalid_precision() {
+    let mut dump = sketch_of_range(10, 0, 100).dump();
+    dump[4] = 30;
+    assert_eq!(Hypeerlog::load(dump), Err(HypeerlogError::InvalidPrecision));
+}
+
+
+#[test]
+fn test_load_precision_beyond_shift_range() {
+    let dump = sketch_of_range(10, 0, 100).dump();
+    for p in [0, 3, 26, 31, 32, 63, 64, 255] {
+        let mut bad = dump.clone();
+        bad[4] = p;
+        assert_eq!(Hypeerlog::load(bad), Err(HypeerlogError::InvalidPrecision), "p={}", p);
+
+        // Not a legacy dump either, whatever the length of the rest
+        let mut legacy = vec![0; 1 << 10];
+        legacy.push(p);
+        assert_eq!(Hypeerlog::load(legacy), Err(HypeerlogError::InvalidMagic), "p={}", p);
+    }
+}
+
+#[test]
+fn test_load_every_truncation() {
+    let hll = sketch_of_range(6, 0, 30);
+    for dump in [hll.dump(), hll.dump_compact(), legacy_dump(&hll)] {
+        for len in 0..dump.len() {
+            assert!(Hypeerlog::load(dump[..len].to_vec()).is_err(), "len {} of {}", len, dump.len());
+        }
+    }
+}
+
+#[test]
+fn test_load_bit_flips() {
+    // Every single-bit corruption is either rejected or yields a sketch whose registers are all legal
+    let hll = sketch_of_range(4, 0, 10);
+    for dump in [hll.dump(), hll.dump_compact(), legacy_dump(&hll)] {
+        for bit in 0..dump.len() * 8 {
+            let mut flipped = dump.clone();
+            flipped[bit / 8] ^= 1 << (bit % 8);
+            for loaded in [Hypeerlog::load(flipped.clone()), Hypeerlog::load_unchecked(flipped)].into_iter().flatten() {
+                assert!(loaded.cardinality().is_finite());
+                assert_eq!(Hypeerlog::load(loaded.dump()).unwrap(), loaded);
+            }
+        }
+    }
+}
+
+
+
+
+// Flips one bit in the middle of the register payload
+fn corrupted_dump(hll: &Hypeerlog) -> Vec<u8> {
+    let mut dump = hll.dump();
+    let middle = PREFIX_LEN + (dump.len() - PREFIX_LEN) / 2;
+    dump[middle] ^= 0x01;
+    dump
+}
+
+#[test]
+fn test_load_detects_corruption() {
+    let hll = sketch_of_range(12, 0, 1000);
+    let dump = hll.dump();
+    let expected = u32::from_le_bytes(dump[6..10].try_into().unwrap());
+
+    match Hypeerlog::load(corrupted_dump(&hll)) {
+        Err(HypeerlogError::ChecksumMismatch { expected: stored, got }) => {
+            assert_eq!(stored, expected);
+            assert_ne!(got, expected);
+        }
+        other => panic!("expected a checksum mismatch, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_load_rejects_impossible_ranks() {
+    let hll = sketch_of_range(12, 0, 1000);
+    let mut dump = hll.dump();
+    let last = dump.len() - 1;
+    // The largest rank possible at p=12
+    dump[last] = 53;
+    assert!(Hypeerlog::load_unchecked(dump.clone()).is_ok());
+    dump[last] = 54;
+    assert_eq!(Hypeerlog::load_unchecked(dump), Err(HypeerlogError::InvalidRegisterValue));
+
+    // A legacy dump keeps the error of its registers, as it has the shape of one
+    let mut legacy = legacy_dump(&hll);
+    legacy[0] = 54;
+    assert_eq!(Hypeerlog::load(legacy.clone()), Err(HypeerlogError::InvalidRegisterValue));
+    assert_eq!(cardinality_of_dump(&legacy), Err(HypeerlogError::InvalidRegisterValue));
+
+    // Without that shape, it is not a dump at all
+    legacy.pop();
+    assert_eq!(Hypeerlog::load(legacy), Err(HypeerlogError::InvalidMagic));
+}
+
+#[test]
+fn test_load_unchecked_skips_checksum() {
+    let hll = sketch_of_range(12, 0, 1000);
+    let loaded = Hypeerlog::load_unchecked(corrupted_dump(&hll)).unwrap();
+    assert_ne!(loaded, hll);
+    assert_eq!(Hypeerlog::load_unchecked(hll.dump()).unwrap(), hll);
+}
+
+#[test]
+fn test_merges_detect_corruption() {
+    let hll = sketch_of_range(12, 0, 1000);
+    let corrupted = corrupted_dump(&hll);
+
+    let mut target = Hypeerlog::with_precision(12);
+    assert!(matches!(target.merge_from_dump(&corrupted), Err(HypeerlogError::ChecksumMismatch { .. })));
+    assert!(target.is_empty());
+    let err = merge_dumps([hll.dump(), corrupted]).unwrap_err();
+    assert!(matches!(err, HypeerlogError::Incompatible { index: 1, source } if matches!(*source, HypeerlogError::ChecksumMismatch { .. })));
+}
+
+#[test]
+fn test_merge_from_dump() {
+    let mut a = sketch_of_range(12, 0, 1000);
+    let b = sketch_of_range(12, 500, 2000);
+    let dump = b.dump();
+    a.merge_from_dump(&dump).unwrap();
+    assert_eq!(a, sketch_of_range(12, 0, 1000).merge(b).unwrap());
+
+    assert_eq!(a.merge_from_dump(&sketch_of_range(10, 0, 10).dump()), Err(HypeerlogError::PrecisionMismatch));
+}
+
+
+
+
+#[test]
+fn test_seed_survives_round_trip() {
+    let mut hll = Hypeerlog::with_precision_seed(12, 7);
+    for i in 0..1000u64 {
+        hll.insert(i);
+    }
+
+    let mut reloaded = Hypeerlog::load(hll.dump()).unwrap();
+    for i in 1000..5000u64 {
+        reloaded.insert(i);
+    }
+
+    let mut reference = Hypeerlog::with_precision_seed(12, 7);
+    for i in 0..5000u64 {
+        reference.insert(i);
+    }
+
+    assert_eq!(reloaded, reference);
+    assert_eq!(reloaded.cardinality(), reference.cardinality());
+}
+
+#[test]
+fn test_seed_mismatch_on_merge() {
+    let live = Hypeerlog::with_precision_seed(10, 1);
+    let loaded = Hypeerlog::load(Hypeerlog::with_precision_seed(10, 2).dump()).unwrap();
+
+    let mut target = Hypeerlog::load(live.dump()).unwrap();
+    assert_eq!(target.merge_with(&loaded), Err(HypeerlogError::SeedMismatch));
+    assert_eq!(live.merge(loaded), Err(HypeerlogError::SeedMismatch));
+
+    let mut target = Hypeerlog::with_precision_seed(10, 1);
+    let other = Hypeerlog::with_precision_seed(10, 2).dump();
+    assert_eq!(target.merge_from_dump(&other), Err(HypeerlogError::SeedMismatch));
+    assert_eq!(merge_dumps([target.dump(), other]), Err(HypeerlogError::Incompatible { index: 1, source: Box::new(HypeerlogError::SeedMismatch) }));
+}
+
+#[test]
+fn test_legacy_dump_loads_with_seed_zero() {
+    let hll = sketch_of_range(10, 0, 100);
+    let loaded = Hypeerlog::load(legacy_dump(&hll)).unwrap();
+    assert!(loaded.merge(hll).is_ok());
+}
+
+fn tagged_sketch(tag: u32) -> Hypeerlog<Tagged> {
+    let mut hll = Hypeerlog::with_hasher_precision(10, Tagged(tag));
+    for i in 0..1000u64 {
+        hll.insert(i);
+    }
+    hll
+}
+
+#[test]
+fn test_hasher_tag_match() {
+    let hll = tagged_sketch(1);
+    let (loaded, status) = Hypeerlog::load_with_hasher_status(hll.dump(), Tagged(1)).unwrap();
+    assert_eq!(loaded, hll);
+    assert_eq!(status, HasherStatus::Verified);
+
+    let (_, status) = Hypeerlog::load_with_hasher_status(sketch_of_range(10, 0, 1000).dump(), std_hasher()).unwrap();
+    assert_eq!(status, HasherStatus::Unverified);
+}
+
+#[test]
+fn test_hasher_tag_mismatch() {
+    let murmur = sketch_of_range(10, 0, 1000).dump();
+    assert_eq!(Hypeerlog::load_with_hasher(murmur.clone(), Tagged(1)), Err(HypeerlogError::HasherMismatch));
+    assert_eq!(Hypeerlog::load(tagged_sketch(1).dump()), Err(HypeerlogError::HasherMismatch));
+    assert_eq!(merge_dumps([murmur, tagged_sketch(1).dump()]), Err(HypeerlogError::Incompatible { index: 1, source: Box::new(HypeerlogError::HasherMismatch) }));
+
+    let mut target = tagged_sketch(1);
+    assert_eq!(target.merge_from_dump(&tagged_sketch(2).dump()), Err(HypeerlogError::HasherMismatch));
+    assert_eq!(target.merge_with(&tagged_sketch(2)), Err(HypeerlogError::HasherMismatch));
+}
+
+#[test]
+fn test_hasher_tag_absent() {
+    let hll = sketch_of_range(10, 0, 1000);
+    let (loaded, status) = Hypeerlog::load_with_hasher_status(legacy_dump(&hll), Tagged(1)).unwrap();
+    assert_eq!(loaded.precision(), 10);
+    assert_eq!(status, HasherStatus::Unverified);
+
+    let untagged = Hypeerlog::with_hasher_precision(10, std_hasher()).dump();
+    assert_eq!(untagged[5] & 0b100, 0);
+    assert!(Hypeerlog::load_with_hasher(untagged, Tagged(1)).is_ok());
+}
+
+fn std_hasher() -> BuildHasherDefault<DefaultHasher> {
+    BuildHasherDefault::default()
+}
+
+
+
+
+// A seeded hasher that is not comparable and does not record its seed in dumps, like `RandomState`
+#[derive(Debug, Clone, Copy)]
+struct Salted(u64);
+
+impl BuildHasher for Salted {
+    type Hasher = DefaultHasher;
+
+    fn build_hasher(&self) -> DefaultHasher {
+        let mut hasher = DefaultHasher::new();
+        std::hash::Hasher::write_u64(&mut hasher, self.0);
+        hasher
+    }
+}
+
+impl HasherId for Salted {}
+
+#[test]
+fn test_equality_ignores_the_hasher_builder() {
+    let mut hll = Hypeerlog::with_hasher_precision(12, Salted(1));
+    hll.insert_many(&(0..1000u64).collect::<Vec<_>>());
+
+    // The dump does not record the salt, so a differently-salted builder loads the same state
+    let reloaded = Hypeerlog::load_with_hasher(hll.dump(), Salted(2)).unwrap();
+    assert!(reloaded == hll);
+    assert_eq!(reloaded.dump(), hll.dump());
+    assert_ne!(Hypeerlog::with_hasher_precision(13, Salted(1)), Hypeerlog::with_hasher_precision(12, Salted(1)));
+
+    // The default hasher records its seed, so only the builder it was dumped with loads it
+    let seeded = Hypeerlog::with_precision_seed(12, 7);
+    let dump = seeded.dump();
+    assert_eq!(Hypeerlog::load_with_hasher(dump.clone(), hypeerlog::Murmur3BuildHasher::new(7)).unwrap(), seeded);
+    assert_eq!(Hypeerlog::load_with_hasher(dump, hypeerlog::Murmur3BuildHasher::new(8)), Err(HypeerlogError::SeedMismatch));
+
+    // Equal sketches of hashers that disagree still refuse to merge
+    let (mut one, two) = (tagged_sketch(1), tagged_sketch(2));
+    assert_eq!(one, two);
+    assert_eq!(one.merge_with(&two), Err(HypeerlogError::HasherMismatch));
+    assert_eq!(Hypeerlog::with_precision_seed(10, 1), Hypeerlog::with_precision_seed(10, 2));
+}
+
+#[test]
+fn test_dumps_keep_the_insertion_count() {
+    let mut hll = sketch_of_range(12, 0, 1000);
+    hll.insert_many(&(0..500u64).collect::<Vec<_>>());
+    assert_eq!(hll.total_insertions(), 1500);
+
+    for dump in [hll.dump(), hll.dump_compact()] {
+        assert_eq!(Hypeerlog::from_bytes(&dump).unwrap().total_insertions(), 1500);
+    }
+    let dump = hll.dump();
+    assert_eq!(Hypeerlog::borrow_dump(&dump).unwrap().to_hypeerlog().total_insertions(), 1500);
+    assert_eq!(hll.dump_len(), hll.dump().len());
+
+    // Merging dumps adds their counts, and legacy dumps, which have none, count 0
+    let other = sketch_of_range(12, 1000, 1200);
+    let merged = merge_dumps([hll.dump(), other.dump(), legacy_dump(&other)]).unwrap();
+    assert_eq!(Hypeerlog::load(merged).unwrap().total_insertions(), 1700);
+    assert_eq!(Hypeerlog::load(legacy_dump(&hll)).unwrap().total_insertions(), 0);
+    let mut target = other.clone();
+    target.merge_from_dump(&hll.dump()).unwrap();
+    assert_eq!(target.total_insertions(), 1700);
+
+    // Setting the flag of the count without the field shortens the registers
+    let mut missing = Hypeerlog::with_precision(12).dump();
+    missing[5] |= 0x40;
+    assert_eq!(Hypeerlog::load(missing), Err(HypeerlogError::InvalidLength));
+}
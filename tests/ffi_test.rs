@@ -0,0 +1,113 @@
+#![cfg(feature = "ffi")]
+
+use std::ptr;
+
+use hypeerlog::ffi::*;
+use hypeerlog::Hypeerlog;
+
+
+
+
+// The dump of a sketch of this API, copied out of it
+fn dump(hll: *const Hll) -> Vec<u8> {
+    let mut len = 0;
+    // SAFETY: `hll` is a live sketch and `len` is writable
+    let bytes = unsafe { hll_dump(hll, &mut len) };
+    assert!(!bytes.is_null());
+    // SAFETY: `hll_dump` returned `len` bytes, freed right after the copy
+    unsafe {
+        let copy = std::slice::from_raw_parts(bytes, len).to_vec();
+        hll_dump_free(bytes, len);
+        copy
+    }
+}
+
+
+
+
+#[test]
+fn test_sketches_round_trip_between_c_and_rust() {
+    let c_side = hll_new(12, 7);
+    let mut rust_side = Hypeerlog::with_precision_seed(12, 7);
+    for user in 0..5000 {
+        let item = format!("user-{}", user);
+        // SAFETY: `c_side` is a live sketch and the item is `len` bytes long
+        let raised = unsafe { hll_insert_bytes(c_side, item.as_ptr(), item.len()) };
+        assert_eq!(raised, rust_side.insert_bytes(item.as_bytes()) as i32);
+    }
+    // The same bytes give the same registers on both sides
+    assert_eq!(dump(c_side), rust_side.dump());
+    // SAFETY: `c_side` is a live sketch
+    assert_eq!(unsafe { hll_cardinality(c_side) }, rust_side.cardinality());
+
+    // A dump of the aggregator merges into the C sketch, whose dump loads back in Rust
+    let mut later = Hypeerlog::with_precision_seed(12, 7);
+    for user in 4000..8000 {
+        later.insert_bytes(format!("user-{}", user).as_bytes());
+    }
+    let later_dump = later.dump();
+    // SAFETY: the dump is `len` bytes long, and the sketches are live until freed
+    unsafe {
+        let loaded = hll_load(later_dump.as_ptr(), later_dump.len());
+        assert!(!loaded.is_null());
+        assert_eq!(hll_merge(c_side, loaded), HLL_OK);
+        assert_eq!(hll_merge(c_side, c_side), HLL_OK);
+        hll_free(loaded);
+    }
+    rust_side.merge_with(&later).unwrap();
+    let merged = Hypeerlog::from_bytes(&dump(c_side)).unwrap();
+    assert_eq!(merged, rust_side);
+    // SAFETY: `c_side` is a live sketch, freed last
+    unsafe {
+        assert_eq!(hll_cardinality(c_side), rust_side.cardinality());
+        hll_free(c_side);
+    }
+}
+
+#[test]
+fn test_errors_are_returned_as_codes() {
+    let (p12, p13, seeded) = (hll_new(12, 0), hll_new(13, 0), hll_new(12, 1));
+    let sketches = [p12, p13, seeded];
+    // SAFETY: the sketches are live until freed at the end, and the data pointers are valid or null with `len` 0
+    unsafe {
+        assert_eq!(hll_merge(p12, p13), HLL_ERR_PRECISION_MISMATCH);
+        assert_eq!(hll_merge(p12, seeded), HLL_ERR_SEED_MISMATCH);
+        assert_eq!(hll_merge(p12, ptr::null()), HLL_ERR_NULL);
+        assert_eq!(hll_merge(ptr::null_mut(), p12), HLL_ERR_NULL);
+
+        assert_eq!(hll_insert_bytes(ptr::null_mut(), b"a".as_ptr(), 1), HLL_ERR_NULL);
+        assert_eq!(hll_insert_bytes(p12, ptr::null(), 1), HLL_ERR_NULL);
+        assert_eq!(hll_insert_bytes(p12, ptr::null(), 0), 1);
+        assert!(hll_cardinality(ptr::null()).is_nan());
+        assert_eq!(hll_cardinality(p12).round(), 1.0);
+
+        let mut len = 0;
+        assert!(hll_dump(ptr::null(), &mut len).is_null());
+        assert!(hll_dump(p12, ptr::null_mut()).is_null());
+        hll_dump_free(ptr::null_mut(), 0);
+
+        // Invalid dumps are not loaded
+        let valid = dump(p12);
+        assert!(hll_load(ptr::null(), 0).is_null());
+        assert!(hll_load(valid.as_ptr(), valid.len() - 1).is_null());
+        assert!(hll_load(b"not a dump".as_ptr(), 10).is_null());
+        hll_free(ptr::null_mut());
+
+        // Precisions are clamped like `Hypeerlog::with_precision` does
+        let clamped = hll_new(40, 0);
+        assert_eq!(Hypeerlog::from_bytes(&dump(clamped)).unwrap().precision(), 25);
+        hll_free(clamped);
+        for hll in sketches {
+            hll_free(hll);
+        }
+    }
+
+    // The header declares the whole API
+    let header = std::fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/include/hypeerlog.h")).unwrap();
+    for name in ["hll_new(", "hll_insert_bytes(", "hll_cardinality(", "hll_merge(", "hll_dump(", "hll_dump_free(", "hll_load(", "hll_free("] {
+        assert!(header.contains(name), "{} is not declared", name);
+    }
+    for (name, code) in [("HLL_OK", HLL_OK), ("HLL_ERR_NULL", HLL_ERR_NULL), ("HLL_ERR_PRECISION_MISMATCH", HLL_ERR_PRECISION_MISMATCH), ("HLL_ERR_SEED_MISMATCH", HLL_ERR_SEED_MISMATCH), ("HLL_ERR_HASHER_MISMATCH", HLL_ERR_HASHER_MISMATCH), ("HLL_ERR_PANIC", HLL_ERR_PANIC)] {
+        assert!(header.contains(&format!("#define {} {}\n", name, code)), "{} is not defined", name);
+    }
+}
@@ -0,0 +1,113 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::BuildHasherDefault;
+use std::mem::size_of;
+
+use hypeerlog::{ConstHypeerlog, ConstPrecision, Hypeerlog, HypeerlogError, Murmur3BuildHasher, Precision};
+use rand::prelude::*;
+
+mod common;
+use common::Opaque;
+
+
+
+static EMPTY: ConstHypeerlog<10> = ConstHypeerlog::new();
+
+
+// Inserts the same elements into a `ConstHypeerlog<P>` and a `Hypeerlog` of precision `P`, and checks that the
+// registers and estimates agree, and that the conversions round-trip
+fn assert_matches_dynamic<const P: u8>(rng: &mut StdRng)
+where
+    Precision<P>: ConstPrecision,
+{
+    let elems: Vec<u64> = (0..3usize << P).map(|_| rng.random()).collect();
+    let mut inline = ConstHypeerlog::<P>::new();
+    let mut dynamic = Hypeerlog::with_precision(P);
+    inline.insert_many(&elems);
+    dynamic.insert_many(&elems);
+    inline.insert_str("a string");
+    dynamic.insert_str("a string");
+    inline.insert_bytes(b"bytes");
+    dynamic.insert_bytes(b"bytes");
+
+    assert_eq!(inline.cardinality().to_bits(), dynamic.cardinality().to_bits(), "P={}", P);
+    assert_eq!(inline.len(), dynamic.len());
+    let converted = Hypeerlog::from(inline.clone());
+    assert_eq!(converted, dynamic, "P={}", P);
+    assert_eq!(ConstHypeerlog::<P>::try_from(converted).unwrap(), inline, "P={}", P);
+
+    let other: Vec<u64> = (0..1usize << P).map(|_| rng.random()).collect();
+    let mut inline_other = ConstHypeerlog::<P>::new();
+    inline_other.insert_many(&other);
+    let merged = inline.merge(inline_other).unwrap();
+    let mut dynamic_other = Hypeerlog::with_precision(P);
+    dynamic_other.insert_many(&other);
+    let expected = dynamic.merge(dynamic_other).unwrap();
+    assert_eq!(merged.cardinality().to_bits(), expected.cardinality().to_bits(), "P={}", P);
+    assert_eq!(Hypeerlog::from(merged), expected, "P={}", P);
+}
+
+
+#[test]
+fn test_size_is_the_registers_and_the_hasher() {
+    assert_eq!(size_of::<ConstHypeerlog<4>>(), size_of::<[u8; 16]>() + size_of::<Murmur3BuildHasher>());
+    assert_eq!(size_of::<ConstHypeerlog<12>>(), size_of::<[u8; 1 << 12]>() + size_of::<Murmur3BuildHasher>());
+    assert_eq!(size_of::<ConstHypeerlog<16>>(), size_of::<[u8; 1 << 16]>() + size_of::<Murmur3BuildHasher>());
+    assert_eq!(size_of::<ConstHypeerlog<14, BuildHasherDefault<DefaultHasher>>>(), 1 << 14);
+}
+
+#[test]
+fn test_estimates_match_the_dynamic_sketch() {
+    let mut rng = StdRng::seed_from_u64(360);
+    assert_matches_dynamic::<4>(&mut rng);
+    assert_matches_dynamic::<5>(&mut rng);
+    assert_matches_dynamic::<8>(&mut rng);
+    assert_matches_dynamic::<11>(&mut rng);
+    assert_matches_dynamic::<14>(&mut rng);
+    assert_matches_dynamic::<16>(&mut rng);
+}
+
+#[test]
+fn test_const_construction() {
+    assert!(EMPTY.is_empty());
+    assert_eq!(EMPTY.cardinality(), 0.0);
+    assert_eq!(EMPTY.precision(), 10);
+
+    let mut hll = EMPTY.clone();
+    hll.insert(1u64);
+    assert!(!hll.is_empty());
+    hll.clear();
+    assert_eq!(hll, EMPTY);
+    assert_eq!(ConstHypeerlog::<10>::default(), EMPTY);
+}
+
+#[test]
+fn test_conversion_checks_precision_and_merges_check_hashers() {
+    assert_eq!(ConstHypeerlog::<12>::try_from(Hypeerlog::with_precision(13)), Err(HypeerlogError::PrecisionMismatch));
+
+    let seeded = Hypeerlog::from(ConstHypeerlog::<12>::with_seed(7));
+    assert_eq!(Hypeerlog::with_precision_seed(12, 7).merge_with(&seeded), Ok(()));
+
+    let mut hll = ConstHypeerlog::<12>::new();
+    assert_eq!(hll.merge_with(&ConstHypeerlog::with_seed(7)), Err(HypeerlogError::SeedMismatch));
+}
+
+#[test]
+fn test_equality_and_debug_ignore_the_hasher_value() {
+    let mut hll = ConstHypeerlog::<14, Opaque>::with_hasher(Opaque::default());
+    hll.insert_many(&(0..1000u64).collect::<Vec<_>>());
+    let mut other = ConstHypeerlog::<14, Opaque>::with_hasher(Opaque::default());
+    assert_ne!(hll, other);
+    other.merge_with(&hll).unwrap();
+    assert_eq!(hll, other);
+
+    let debug = format!("{:?}", hll);
+    assert!(debug.len() < 200, "{}", debug);
+    assert!(debug.contains("precision: 14, hasher_seed: None, hasher_tag: None"), "{}", debug);
+    assert!(debug.contains(&format!("estimate: {:?}", hll.cardinality())), "{}", debug);
+    assert!(format!("{:?}", ConstHypeerlog::<14>::with_seed(7)).contains("hasher_seed: Some(7)"));
+
+    // The alternate form lists every register
+    let pretty = format!("{:#?}", ConstHypeerlog::<4>::new());
+    let (_, registers) = pretty.split_once("registers: [").expect("the alternate form lists the registers");
+    assert_eq!(registers.matches("        0,").count(), 16, "{}", pretty);
+}
@@ -0,0 +1,95 @@
+use hypeerlog::Hypeerlog;
+
+
+
+
+// The registers of a sketch, read from the end of its dump
+fn registers(hll: &Hypeerlog) -> Vec<u8> {
+    let dump = hll.dump();
+    dump[dump.len() - hll.len()..].to_vec()
+}
+
+// (seed, string, MurmurHash3_x86_32 of its UTF-8 bytes, register index at p=14, register value),
+// computed with a reference implementation, for other implementations to check against
+const VECTORS: [(u32, &str, u32, usize, u8); 12] = [
+    (0, "", 0x00000000, 0, 51),
+    (0, "a", 0x3c2569b2, 10674, 1),
+    (0, "hello", 0x248bfa47, 14919, 1),
+    (0, "hypeerlog", 0x42f67f8d, 16269, 1),
+    (0, "country=DE", 0x89d667c5, 10181, 1),
+    (0, "🦀 crab", 0x4b81dae8, 6888, 1),
+    (42, "", 0x087fcd5c, 3420, 1),
+    (42, "a", 0xb2e5a263, 8803, 2),
+    (42, "hello", 0xe2dbd2e1, 4833, 1),
+    (42, "hypeerlog", 0xc309ebd8, 11224, 1),
+    (42, "country=DE", 0x33e94bcd, 3021, 1),
+    (42, "🦀 crab", 0x1f108431, 1073, 2),
+];
+
+
+
+
+#[test]
+fn test_insert_str_vectors() {
+    for (seed, s, hash, index, value) in VECTORS {
+        assert_eq!(hash as usize & 0x3fff, index);
+
+        let mut hll = Hypeerlog::with_precision_seed(14, seed);
+        hll.insert_str(s);
+        let registers = registers(&hll);
+        assert_eq!(registers[index], value, "seed {} {:?}", seed, s);
+        assert_eq!(registers.iter().filter(|&&r| r != 0).count(), 1, "seed {} {:?}", seed, s);
+    }
+}
+
+#[test]
+fn test_insert_bytes_matches_insert_str() {
+    let (mut bytes, mut strs) = (Hypeerlog::new(), Hypeerlog::new());
+    for i in 0..5000 {
+        let s = format!("user-{}", i);
+        bytes.insert_bytes(s.as_bytes());
+        strs.insert_str(&s);
+    }
+    assert_eq!(bytes, strs);
+    assert!((bytes.cardinality() - 5000.0).abs() < 5000.0 * 0.05);
+}
+
+#[test]
+fn test_insert_str_differs_from_hash() {
+    // `Hash for str` appends a delimiter byte, so the two paths are not interchangeable
+    let (mut raw, mut hashed) = (Hypeerlog::new(), Hypeerlog::new());
+    raw.insert_str("hello");
+    hashed.insert("hello");
+    assert_ne!(raw, hashed);
+
+    let mut delimited = Hypeerlog::new();
+    delimited.insert_bytes(b"hello\xff");
+    assert_eq!(delimited, hashed);
+}
+
+#[test]
+fn test_python_fixture() {
+    // The Python bindings of `python/` rebuild this sketch from the same items and merge with it: update the
+    // fixture only on purpose, together with `python/tests/test_hypeerlog.py`
+    let fixture = include_bytes!("fixtures/python_items_p12_seed7.bin");
+    let mut hll = Hypeerlog::with_precision_seed(12, 7);
+    for i in 0..1000 {
+        hll.insert_bytes(format!("user-{}", i).as_bytes());
+    }
+    for i in 0..500u64 {
+        hll.insert_bytes(&i.to_le_bytes());
+    }
+    assert_eq!(hll.dump(), fixture);
+
+    // Python integers are inserted as the little-endian bytes of a `u64` or `i64`, which `Hypeerlog::insert` hashes
+    let (mut ints, mut bytes) = (Hypeerlog::new(), Hypeerlog::new());
+    for i in [0, 1, 499, u64::MAX] {
+        ints.insert(i);
+        bytes.insert_bytes(&i.to_le_bytes());
+    }
+    for i in [-1, i64::MIN] {
+        ints.insert(i);
+        bytes.insert_bytes(&i.to_le_bytes());
+    }
+    assert_eq!(ints, bytes);
+}
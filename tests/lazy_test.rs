@@ -0,0 +1,80 @@
+use hypeerlog::Hypeerlog;
+
+mod common;
+use common::{allocated_by, CountingAlloc};
+
+
+
+
+// Counts the bytes allocated by the tests
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+
+
+
+#[test]
+fn test_empty_sketches_do_not_allocate_registers() {
+    let mut sketches = Vec::with_capacity(1_000_000);
+    let ((), bytes) = allocated_by(|| sketches.extend((0..1_000_000).map(|_| Hypeerlog::with_precision(14))));
+    assert_eq!(bytes, 0);
+
+    let (estimates, bytes) = allocated_by(|| sketches.iter().map(|hll| (hll.cardinality(), hll.is_empty())).collect::<Vec<_>>());
+    assert_eq!(bytes, estimates.capacity() * size_of::<(f64, bool)>());
+    assert!(estimates.iter().all(|&est| est == (0.0, true)));
+
+    // Merging empty sketches, into empty and non-empty ones, leaves both sides unallocated
+    let mut full = Hypeerlog::with_precision(14);
+    full.insert(1u64);
+    let ((), bytes) = allocated_by(|| {
+        for pair in sketches.chunks_exact_mut(2).take(1000) {
+            let [a, b] = pair else { unreachable!() };
+            a.merge_with(b).unwrap();
+            full.merge_with(a).unwrap();
+        }
+    });
+    assert_eq!(bytes, 0);
+    assert!(sketches[0].is_empty());
+    assert_eq!(full.cardinality().round(), 1.0);
+
+    // The first insert allocates the registers of that sketch only
+    let (raised, bytes) = allocated_by(|| sketches[7].insert("first"));
+    assert!(raised);
+    assert_eq!(bytes, 1 << 14);
+    assert!(!sketches[7].is_empty() && sketches[8].is_empty());
+}
+
+#[test]
+fn test_empty_sketches_dump_and_compare_like_allocated_ones() {
+    let empty = Hypeerlog::with_precision_seed(12, 3);
+    let mut allocated = Hypeerlog::with_precision_seed(12, 3);
+    allocated.insert(1u64);
+    allocated.clear();
+
+    assert_eq!(empty, allocated);
+    assert_eq!(empty.dump(), allocated.dump());
+    assert_eq!(empty.dump_compact(), allocated.dump_compact());
+    assert_eq!(Hypeerlog::load(empty.dump()).unwrap(), empty);
+    assert_eq!(Hypeerlog::load(empty.dump_compact()).unwrap(), empty);
+    assert_ne!(empty, Hypeerlog::with_precision_seed(13, 3));
+
+    // Dumps of empty sketches are compact, and only allocate the dumps themselves
+    let dump = empty.dump();
+    assert_eq!(dump.len(), 22);
+    assert_ne!(dump[5] & 0b1000, 0);
+    assert_eq!(Hypeerlog::borrow_dump(&dump).unwrap().cardinality(), 0.0);
+    assert_eq!(Hypeerlog::borrow_dump(&dump).unwrap().to_hypeerlog(), empty);
+    let sketches: Vec<Hypeerlog> = (0..1000).map(|_| Hypeerlog::with_precision_seed(12, 3)).collect();
+    let (dumps, bytes) = allocated_by(|| sketches.iter().map(Hypeerlog::dump).collect::<Vec<_>>());
+    assert_eq!(bytes, dumps.capacity() * size_of::<Vec<u8>>() + dumps.iter().map(Vec::capacity).sum::<usize>());
+    assert!(dumps.iter().all(|dump| dump == &allocated.dump()));
+
+    // Merging an allocated sketch into an empty one gives the same sketch
+    let mut hll = Hypeerlog::with_precision(12);
+    hll.insert_many(&[1, 2, 3]);
+    let mut merged = Hypeerlog::with_precision(12);
+    let report = merged.merge_with_report(&hll).unwrap();
+    assert_eq!(merged, hll);
+    assert_eq!(report.registers_updated, 3);
+    assert_eq!(merged.cardinality().to_bits(), hll.cardinality().to_bits());
+}
@@ -0,0 +1,155 @@
+#![cfg(not(feature = "no_std"))]
+
+use std::collections::{HashMap, HashSet};
+
+use hypeerlog::{assert_cardinality_within, HllMap, Hypeerlog, HypeerlogError, Murmur3BuildHasher};
+use rand::prelude::*;
+
+mod common;
+use common::Opaque;
+
+
+
+
+// Visits of users per country, with a few large countries and many small ones
+fn visits(rng: &mut StdRng, n: usize) -> Vec<(u32, u64)> {
+    (0..n)
+        .map(|_| {
+            let country = if rng.random_bool(0.5) { rng.random_range(0..3) } else { rng.random_range(3..40) };
+            (country, rng.random_range(0..20_000))
+        })
+        .collect()
+}
+
+fn map_of(visits: &[(u32, u64)]) -> HllMap<u32> {
+    let mut map = HllMap::with_precision_seed(12, 3);
+    visits.iter().for_each(|&(country, user)| { map.insert(country, user); });
+    map
+}
+
+
+
+
+#[test]
+fn test_merging_maps_matches_a_map_of_all_visits() {
+    let mut rng = StdRng::seed_from_u64(398);
+    let (first, second) = (visits(&mut rng, 60_000), visits(&mut rng, 60_000));
+    // The second map misses some countries of the first and has some of its own
+    let second: Vec<_> = second.into_iter().map(|(country, user)| (country + 10, user)).collect();
+
+    let mut merged = map_of(&first);
+    merged.merge_with(&map_of(&second)).unwrap();
+    let all: Vec<_> = first.iter().chain(&second).copied().collect();
+    assert_eq!(merged, map_of(&all));
+    assert_eq!(merged.len(), 50);
+
+    let mut exact: HashMap<u32, HashSet<u64>> = HashMap::new();
+    all.iter().for_each(|&(country, user)| { exact.entry(country).or_default().insert(user); });
+    for (country, estimate) in merged.iter() {
+        let sketch = merged.get(country).unwrap();
+        assert_eq!(estimate, sketch.cardinality());
+        assert_eq!(merged.cardinality(country), Some(estimate));
+        assert_cardinality_within!(sketch, exact[country].len(), 4);
+    }
+    let every_user = Hypeerlog::from_iter_with_hasher_precision(12, Murmur3BuildHasher::new(3), all.iter().map(|&(_, user)| user));
+    assert_eq!(merged.union().register_values(), every_user.register_values());
+    assert_eq!(merged.cardinality(&50), None);
+
+    // Merging is the same in either order
+    let mut reversed = map_of(&second);
+    reversed.merge_with(&map_of(&first)).unwrap();
+    assert_eq!(reversed, merged);
+
+    // Maps and sketches of another configuration are rejected, leaving the map unchanged
+    let before = merged.clone();
+    let mut other_precision = HllMap::with_precision_seed(13, 3);
+    other_precision.insert(100, "x");
+    let mut other_seed = HllMap::with_precision_seed(12, 4);
+    other_seed.insert(100, "x");
+    assert_eq!(merged.merge_with(&other_precision).unwrap_err(), HypeerlogError::PrecisionMismatch);
+    assert_eq!(merged.merge_with(&other_seed).unwrap_err(), HypeerlogError::SeedMismatch);
+    assert_eq!(merged.merge_sketch(100, other_seed.get(&100).unwrap()).unwrap_err(), HypeerlogError::SeedMismatch);
+    assert_eq!(merged, before);
+
+    merged.merge_sketch(100, &Hypeerlog::from_iter_with_hasher_precision(12, Murmur3BuildHasher::new(3), ["x", "y"])).unwrap();
+    assert_eq!(merged.cardinality(&100).map(f64::round), Some(2.0));
+    assert_eq!(merged.remove(&100).map(|sketch| sketch.precision()), Some(12));
+    assert!(!merged.contains_key(&100));
+    merged.clear();
+    assert!(merged.is_empty());
+    assert_eq!((merged.precision(), merged.union().is_empty()), (12, true));
+}
+
+#[test]
+fn test_dumps_keep_the_keys_and_the_configuration() {
+    let mut map = HllMap::with_precision_seed(10, 9);
+    for user in 0..3000u64 {
+        map.insert(format!("country-{}", user % 7), user);
+    }
+    map.insert_many(String::from("two-users"), &["a", "b"]);
+
+    let dump = map.dump();
+    let loaded = HllMap::<String>::from_bytes(&dump).unwrap();
+    assert_eq!(loaded, map);
+    assert_eq!(loaded.cardinality("two-users"), map.cardinality("two-users"));
+
+    // An empty map keeps its configuration, so that it merges with the maps it was written with
+    let mut empty = HllMap::<String>::from_bytes(&HllMap::<String>::with_precision_seed(10, 9).dump()).unwrap();
+    assert!(empty.is_empty());
+    empty.merge_with(&map).unwrap();
+    assert_eq!(empty, map);
+    let bytes_map = HllMap::<Vec<u8>>::from_bytes(&HllMap::<String>::with_precision_seed(10, 9).dump()).unwrap();
+    assert_eq!(bytes_map.precision(), 10);
+
+    // Invalid dumps are rejected
+    assert_eq!(HllMap::<String>::from_bytes(&dump[..dump.len() - 1]).unwrap_err(), HypeerlogError::InvalidLength);
+    assert_eq!(HllMap::<String>::from_bytes(&dump[..20]).unwrap_err(), HypeerlogError::InvalidLength);
+    assert_eq!(HllMap::<String>::from_bytes(&Hypeerlog::new().dump()).unwrap_err(), HypeerlogError::InvalidMagic);
+    let mut newer = dump.clone();
+    newer[4] = 2;
+    assert_eq!(HllMap::<String>::from_bytes(&newer).unwrap_err(), HypeerlogError::UnsupportedVersion);
+    let mut precision = dump.clone();
+    precision[5] = 30;
+    assert_eq!(HllMap::<String>::from_bytes(&precision).unwrap_err(), HypeerlogError::InvalidPrecision);
+    precision[5] = 11;
+    assert_eq!(HllMap::<String>::from_bytes(&precision).unwrap_err(), HypeerlogError::PrecisionMismatch);
+    let mut seed = dump.clone();
+    seed[7] = 10;
+    assert_eq!(HllMap::<String>::from_bytes(&seed).unwrap_err(), HypeerlogError::SeedMismatch);
+    let mut flags = dump.clone();
+    flags[6] |= 4;
+    assert_eq!(HllMap::<String>::from_bytes(&flags).unwrap_err(), HypeerlogError::UnsupportedFlags);
+    let mut huge = dump.clone();
+    huge[19..23].copy_from_slice(&u32::MAX.to_le_bytes());
+    assert_eq!(HllMap::<String>::from_bytes(&huge).unwrap_err(), HypeerlogError::InvalidLength);
+
+    // Entries are written in the order of their keys, whatever order they were inserted in
+    let mut reversed = HllMap::with_precision_seed(10, 9);
+    for user in (0..3000u64).rev() {
+        reversed.insert(format!("country-{}", user % 7), user);
+    }
+    reversed.insert_many(String::from("two-users"), &["b", "a"]);
+    assert_eq!(reversed.dump(), dump);
+
+    // Keys must be readable as the key type of the map
+    assert_eq!(HllMap::<u64>::from_bytes(&dump).unwrap_err(), HypeerlogError::Encoding);
+    let numbers = map_of(&[(1, 1), (2, 2)]).dump();
+    assert_eq!(HllMap::<u32>::from_bytes(&numbers).unwrap(), map_of(&[(1, 1), (2, 2)]));
+    assert_eq!(HllMap::<u64>::from_bytes(&numbers).unwrap_err(), HypeerlogError::Encoding);
+}
+
+#[test]
+fn test_equality_compares_the_sketches_and_the_hasher_identity() {
+    let mut map = HllMap::with_hasher_precision(10, Opaque::default());
+    map.insert(1u32, "a");
+    let mut other = HllMap::with_hasher_precision(10, Opaque::default());
+    other.insert(1u32, "a");
+    assert_eq!(map, other);
+    other.insert(2, "b");
+    assert_ne!(map, other);
+
+    // Maps of hashers with another seed differ, even without any sketch
+    assert_ne!(HllMap::<u32>::with_precision_seed(10, 1), HllMap::with_precision_seed(10, 2));
+    assert_ne!(HllMap::<u32>::with_precision(10), HllMap::with_precision(11));
+    assert_eq!(HllMap::<u32>::with_precision_seed(10, 1), HllMap::with_precision_seed(10, 1));
+}
@@ -0,0 +1,336 @@
+use std::fmt::Debug;
+
+use hypeerlog::{ConstHypeerlog, DecayingHypeerlog, Hypeerlog, HypeerlogError, Mergeable, MultiHll, PackedHypeerlog};
+#[cfg(not(feature = "no_std"))]
+use hypeerlog::HllMap;
+use rand::prelude::*;
+
+mod common;
+use common::Tagged;
+
+
+
+
+// Builds a sketch from a random number of random elements
+fn random_sketch(rng: &mut ThreadRng, p: u8) -> Hypeerlog {
+    let mut hll = Hypeerlog::with_precision(p);
+    let n = rng.random_range(0..5_000);
+    for _ in 0..n {
+        hll.insert(rng.random::<u64>());
+    }
+    hll
+}
+
+// Merges `b` into a copy of `a` through the generic trait
+fn merged<M: Mergeable>(a: &M, b: &M, copy: impl Fn(&M) -> M) -> M {
+    let mut out = copy(a);
+    out.merge_with(b).unwrap();
+    out
+}
+
+fn copy(hll: &Hypeerlog) -> Hypeerlog {
+    Hypeerlog::load(hll.dump()).unwrap()
+}
+
+// Checks the join laws of `Mergeable` on three sketches of any type, comparing the merges by `key`
+fn assert_join_laws<M: Mergeable, K: PartialEq + Debug>([a, b, c]: [M; 3], copy: impl Fn(&M) -> M, key: impl Fn(&M) -> K) {
+    assert_eq!(key(&merged(&a, &b, &copy)), key(&merged(&b, &a, &copy)));
+    assert_eq!(key(&merged(&merged(&a, &b, &copy), &c, &copy)), key(&merged(&a, &merged(&b, &c, &copy), &copy)));
+    assert_eq!(key(&merged(&a, &a, &copy)), key(&a));
+    let ab = merged(&a, &b, &copy);
+    assert_eq!(key(&merged(&ab, &b, &copy)), key(&ab));
+}
+
+
+
+
+#[test]
+fn test_merge_is_commutative() {
+    let mut rng = rand::rng();
+    for _ in 0..50 {
+        let a = random_sketch(&mut rng, 8);
+        let b = random_sketch(&mut rng, 8);
+        assert_eq!(merged(&a, &b, copy), merged(&b, &a, copy));
+    }
+}
+
+#[test]
+fn test_merge_is_associative() {
+    let mut rng = rand::rng();
+    for _ in 0..50 {
+        let a = random_sketch(&mut rng, 8);
+        let b = random_sketch(&mut rng, 8);
+        let c = random_sketch(&mut rng, 8);
+
+        let left = merged(&merged(&a, &b, copy), &c, copy);
+        let right = merged(&a, &merged(&b, &c, copy), copy);
+        assert_eq!(left, right);
+    }
+}
+
+#[test]
+fn test_merge_is_idempotent() {
+    let mut rng = rand::rng();
+    for _ in 0..50 {
+        let a = random_sketch(&mut rng, 8);
+        let b = random_sketch(&mut rng, 8);
+
+        assert_eq!(merged(&a, &a, copy), a);
+
+        let ab = merged(&a, &b, copy);
+        assert_eq!(merged(&ab, &b, copy), ab);
+    }
+}
+
+#[test]
+fn test_every_sketch_type_merges_through_the_trait() {
+    let mut rng = rand::rng();
+    let items = |rng: &mut ThreadRng| (0..rng.random_range(0..3000)).map(|_| rng.random::<u64>()).collect::<Vec<_>>();
+
+    let consts = [(); 3].map(|()| {
+        let mut hll = ConstHypeerlog::<8>::new();
+        hll.insert_many(&items(&mut rng));
+        hll
+    });
+    assert_join_laws(consts, ConstHypeerlog::clone, |hll| Hypeerlog::from(hll.clone()));
+
+    let packed = [(); 3].map(|()| {
+        let mut hll = PackedHypeerlog::with_precision(8);
+        hll.insert_many(&items(&mut rng));
+        hll
+    });
+    assert_join_laws(packed, PackedHypeerlog::clone, PackedHypeerlog::clone);
+
+    let arrays = [(); 3].map(|()| {
+        let mut array = MultiHll::with_precision_len(8, 4);
+        for (i, item) in items(&mut rng).into_iter().enumerate() {
+            array.insert(i % 4, item).unwrap();
+        }
+        array
+    });
+    assert_join_laws(arrays, MultiHll::clone, MultiHll::dump);
+
+    let decaying = [(); 3].map(|()| {
+        let mut hll = DecayingHypeerlog::with_precision_half_life(8, 100);
+        for item in items(&mut rng) {
+            hll.insert(item, item % 2000);
+        }
+        hll
+    });
+    assert_join_laws(decaying, DecayingHypeerlog::clone, |hll| [1000, 2000, 4000].map(|now| hll.decayed_cardinality(now).to_bits()));
+
+    #[cfg(not(feature = "no_std"))]
+    {
+        let maps = [(); 3].map(|()| {
+            let mut map = HllMap::with_precision(8);
+            for item in items(&mut rng) {
+                map.insert(item % 5, item);
+            }
+            map
+        });
+        assert_join_laws(maps, HllMap::clone, HllMap::clone);
+    }
+
+    // Incompatible sketches are rejected through the trait too
+    let mut array = MultiHll::with_precision_len(8, 4);
+    assert_eq!(Mergeable::merge_with(&mut array, &MultiHll::with_precision_len(8, 5)), Err(HypeerlogError::LengthMismatch));
+    let mut decaying = DecayingHypeerlog::with_precision_half_life(8, 100);
+    assert_eq!(Mergeable::merge_with(&mut decaying, &DecayingHypeerlog::with_precision_half_life(8, 200)), Err(HypeerlogError::HalfLifeMismatch));
+}
+
+#[test]
+fn test_merge_with_precision_mismatch_leaves_self_untouched() {
+    let mut rng = rand::rng();
+    let mut a = random_sketch(&mut rng, 8);
+    let b = random_sketch(&mut rng, 10);
+    let before = copy(&a);
+
+    assert_eq!(Mergeable::merge_with(&mut a, &b), Err(HypeerlogError::PrecisionMismatch));
+    assert_eq!(a, before);
+}
+
+#[test]
+fn test_merge_all_matches_sequential_merge_with() {
+    let mut rng = rand::rng();
+    let sketches: Vec<Hypeerlog> = (0..20).map(|_| random_sketch(&mut rng, 10)).collect();
+
+    let mut folded = Hypeerlog::with_precision(10);
+    folded.merge_all(&sketches).unwrap();
+
+    let mut sequential = Hypeerlog::with_precision(10);
+    for hll in &sketches {
+        sequential.merge_with(hll).unwrap();
+    }
+
+    assert_eq!(folded, sequential);
+}
+
+#[test]
+fn test_merge_all_stops_at_first_mismatch() {
+    let mut rng = rand::rng();
+    let mut sketches: Vec<Hypeerlog> = (0..5).map(|_| random_sketch(&mut rng, 10)).collect();
+    sketches.insert(3, random_sketch(&mut rng, 12));
+
+    let mut folded = Hypeerlog::with_precision(10);
+    assert_eq!(folded.merge_all(&sketches), Err(HypeerlogError::Incompatible { index: 3, source: Box::new(HypeerlogError::PrecisionMismatch) }));
+
+    // Only the sketches before the mismatch were merged
+    let mut expected = Hypeerlog::with_precision(10);
+    expected.merge_all(&sketches[..3]).unwrap();
+    assert_eq!(folded, expected);
+}
+
+#[test]
+fn test_merge_all_reports_the_index_of_seed_and_hasher_mismatches() {
+    let seeds = [Hypeerlog::with_precision(10), Hypeerlog::with_precision(10), Hypeerlog::with_precision_seed(10, 7)];
+    let err = Hypeerlog::with_precision(10).merge_all(&seeds).unwrap_err();
+    assert_eq!(err, HypeerlogError::Incompatible { index: 2, source: Box::new(HypeerlogError::SeedMismatch) });
+    assert_eq!(std::error::Error::source(&err).unwrap().to_string(), HypeerlogError::SeedMismatch.to_string());
+
+    let tags = [1, 2, 1].map(|tag| Hypeerlog::with_hasher_precision(10, Tagged(tag)));
+    let err = Hypeerlog::with_hasher_precision(10, Tagged(1)).merge_all(&tags).unwrap_err();
+    assert_eq!(err, HypeerlogError::Incompatible { index: 1, source: Box::new(HypeerlogError::HasherMismatch) });
+}
+
+#[test]
+fn test_merge_with_report_identical_sketch() {
+    let mut rng = rand::rng();
+    let mut a = random_sketch(&mut rng, 10);
+    let b = copy(&a);
+
+    let report = a.merge_with_report(&b).unwrap();
+    assert_eq!(report.registers_updated, 0);
+    assert_eq!(report.estimate_before, report.estimate_after);
+    assert_eq!(report.estimate_after, a.cardinality());
+}
+
+#[test]
+fn test_merge_with_report_counts_differing_registers() {
+    let mut old = Hypeerlog::with_precision(10);
+    old.insert_many(&(0..500u64).collect::<Vec<_>>());
+
+    // A strictly newer sketch: it saw everything `old` saw, plus more
+    let mut newer = copy(&old);
+    newer.insert_many(&(500..3000u64).collect::<Vec<_>>());
+
+    // Skip the dump header, only the registers are compared
+    let (old_dump, new_dump) = (old.dump(), newer.dump());
+    let header_len = old_dump.len() - old.len();
+    let differing = old_dump[header_len..].iter().zip(new_dump[header_len..].iter()).filter(|(a, b)| a != b).count();
+    let estimate_before = old.cardinality();
+
+    let report = old.merge_with_report(&newer).unwrap();
+    assert!(differing > 0);
+    assert_eq!(report.registers_updated, differing);
+    assert_eq!(report.estimate_before, estimate_before);
+    assert_eq!(report.estimate_after, newer.cardinality());
+    assert_eq!(old, newer);
+}
+
+#[test]
+fn test_clones_are_independent_snapshots() {
+    let mut rng = rand::rng();
+    let mut original = random_sketch(&mut rng, 12);
+    let snapshot = original.clone();
+    assert_eq!(snapshot, original);
+    let frozen = snapshot.cardinality();
+
+    original.insert_many(&(0..10_000u64).map(|_| rng.random::<u64>()).collect::<Vec<_>>());
+    assert!(original.cardinality() > frozen + 5_000.0);
+    assert_eq!(snapshot.cardinality().to_bits(), frozen.to_bits());
+
+    // Merging into a clone keeps both inputs
+    let union = snapshot.clone().merge(original.clone()).unwrap();
+    assert_eq!(union, merged(&snapshot, &original, copy));
+    assert_eq!(snapshot.cardinality().to_bits(), frozen.to_bits());
+
+    // Clones of loaded and of empty sketches dump like their originals, and keep the hasher
+    let loaded = Hypeerlog::load(original.dump()).unwrap();
+    assert_eq!(loaded.clone().dump(), original.dump());
+    let seeded = Hypeerlog::with_precision_seed(10, 7);
+    assert_eq!(seeded.clone().dump(), seeded.dump());
+    assert_eq!(seeded.clone().merge_with(&Hypeerlog::with_precision(10)), Err(HypeerlogError::SeedMismatch));
+
+    // The cached estimate of a clone is invalidated by its own inserts
+    let mut cached = original.clone();
+    let before = cached.cardinality_cached();
+    let mut clone = cached.clone();
+    clone.insert_many(&(0..1000u64).map(|_| rng.random::<u64>()).collect::<Vec<_>>());
+    assert!(clone.cardinality_cached() > before);
+    assert_eq!(cached.cardinality_cached().to_bits(), before.to_bits());
+}
+
+// A container that builds its sketch through `Default`, like generic code does
+#[derive(Default)]
+struct Stats {
+    visitors: Hypeerlog,
+    pages: usize,
+}
+
+fn fresh<T: Default>() -> T {
+    T::default()
+}
+
+#[test]
+fn test_default_sketches_match_new_ones() {
+    let elems: Vec<u64> = (0..5000).collect();
+    let mut new = Hypeerlog::new();
+    let mut default: Hypeerlog = fresh();
+    let mut stats = Stats { pages: 3, ..Default::default() };
+    assert_eq!((stats.pages, stats.visitors.is_empty()), (3, true));
+    let mut with_default_hasher = Hypeerlog::with_hasher(Default::default());
+    for hll in [&mut new, &mut default, &mut stats.visitors, &mut with_default_hasher] {
+        assert_eq!((hll.precision(), hll.insert_many(&elems) > 0), (14, true));
+    }
+    assert_eq!(default, new);
+    assert_eq!(stats.visitors, new);
+    assert_eq!(with_default_hasher, new);
+    assert_eq!(default.dump(), new.dump());
+
+    default.merge_with(&new).unwrap();
+    stats.visitors.merge_with(&with_default_hasher).unwrap();
+    assert_eq!(default.merge(stats.visitors), Ok(new));
+}
+
+#[test]
+fn test_merges_add_the_insertion_counts() {
+    let mut a = Hypeerlog::with_precision(14);
+    a.insert_many(&(0..300u64).collect::<Vec<_>>());
+    let mut b = Hypeerlog::with_precision(14);
+    for _ in 0..2 {
+        b.insert_many(&(200..500u64).collect::<Vec<_>>());
+    }
+    assert_eq!((a.total_insertions(), b.total_insertions()), (300, 600));
+
+    let mut merged = a.clone();
+    merged.merge_with(&b).unwrap();
+    assert_eq!(merged.total_insertions(), 900);
+    assert!((merged.cardinality() - 500.0).abs() < 10.0, "{}", merged.cardinality());
+    assert!((merged.duplication_ratio() - 1.8).abs() < 0.05, "{}", merged.duplication_ratio());
+
+    // Merging `b` again leaves the registers as they are, but counts its insertions again
+    let mut again = merged.clone();
+    again.merge_with(&b).unwrap();
+    assert_eq!(again, merged);
+    assert_eq!(again.total_insertions(), 1500);
+    assert_ne!(again.dump(), merged.dump());
+    assert!(again.duplication_ratio() > merged.duplication_ratio());
+
+    // Every merge adds the count, and equality only compares the registers
+    assert_eq!(a.clone().merge(b.clone()).unwrap().total_insertions(), 900);
+    let mut reported = a.clone();
+    reported.merge_with_report(&b).unwrap();
+    assert_eq!(reported.total_insertions(), 900);
+    let mut folded = Hypeerlog::with_precision(14);
+    folded.merge_all([&a, &b, &a]).unwrap();
+    assert_eq!(folded.total_insertions(), 1200);
+    assert_eq!(folded, merged);
+
+    // Counts saturate instead of overflowing
+    let mut doubled = merged.clone();
+    for _ in 0..64 {
+        let copy = doubled.clone();
+        doubled.merge_with(&copy).unwrap();
+    }
+    assert_eq!(doubled.total_insertions(), u64::MAX);
+}
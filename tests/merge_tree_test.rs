@@ -0,0 +1,120 @@
+#![cfg(not(feature = "no_std"))]
+
+use hypeerlog::{Hypeerlog, HypeerlogError, MergeTree};
+use rand::prelude::*;
+
+
+
+
+// 2 regions of 32 hosts of 32 partitions
+fn paths() -> Vec<[String; 3]> {
+    let mut paths = Vec::new();
+    for region in ["eu", "us"] {
+        for host in 0..32 {
+            for partition in 0..32 {
+                paths.push([region.to_string(), format!("host-{}", host), format!("partition-{}", partition)]);
+            }
+        }
+    }
+    paths
+}
+
+fn leaf(rng: &mut StdRng) -> Hypeerlog {
+    let start = rng.random_range(0..100_000u64);
+    Hypeerlog::from_iter_with_precision(10, start..start + rng.random_range(0..200))
+}
+
+fn segments(path: &[String]) -> Vec<&str> {
+    path.iter().map(String::as_str).collect()
+}
+
+// The union of the leaves under `prefix`, merged from scratch
+fn union_under(leaves: &[([String; 3], Hypeerlog)], prefix: &[&str]) -> Hypeerlog {
+    let mut union = Hypeerlog::with_precision(10);
+    union.merge_all(leaves.iter().filter(|(path, _)| segments(path).starts_with(prefix)).map(|(_, sketch)| sketch)).unwrap();
+    union
+}
+
+
+
+
+#[test]
+fn test_updating_a_leaf_recomputes_only_its_ancestors() {
+    let mut rng = StdRng::seed_from_u64(401);
+    let mut leaves: Vec<_> = paths().into_iter().map(|path| (path, leaf(&mut rng))).collect();
+    let mut tree = MergeTree::with_precision(10);
+    for (path, sketch) in &leaves {
+        tree.update_leaf(&segments(path), sketch.clone()).unwrap();
+    }
+    assert_eq!(tree.leaf_count(), 2048);
+
+    // The first query computes every inner node once: the root, 2 regions and 64 hosts
+    assert_eq!(tree.union_of(&[]), Some(&union_under(&leaves, &[])));
+    assert_eq!(tree.recomputations(), 67);
+    assert_eq!(tree.union_of(&[]), Some(&union_under(&leaves, &[])));
+    assert_eq!(tree.recomputations(), 67);
+
+    for _ in 0..50 {
+        let updated = rng.random_range(0..leaves.len());
+        leaves[updated].1 = leaf(&mut rng);
+        tree.update_leaf(&segments(&leaves[updated].0), leaves[updated].1.clone()).unwrap();
+
+        // The queries recompute the host, the region and the root of the leaf once, and nothing else
+        let before = tree.recomputations();
+        let prefix = match rng.random_range(0..3) {
+            0 => Vec::new(),
+            1 => segments(&leaves[updated].0[..1]),
+            _ => segments(&leaves[rng.random_range(0..leaves.len())].0[..2]),
+        };
+        assert_eq!(tree.union_of(&prefix), Some(&union_under(&leaves, &prefix)), "prefix={:?}", prefix);
+        assert_eq!(tree.cardinality_of(&[]), Some(union_under(&leaves, &[]).cardinality()));
+        assert_eq!(tree.recomputations() - before, 3);
+    }
+
+    // A few updates recompute their ancestors once each
+    let before = tree.recomputations();
+    for updated in [0, 1, 40, 2047] {
+        leaves[updated].1 = leaf(&mut rng);
+        tree.update_leaf(&segments(&leaves[updated].0), leaves[updated].1.clone()).unwrap();
+    }
+    assert_eq!(tree.cardinality_of(&[]), Some(union_under(&leaves, &[]).cardinality()));
+    assert_eq!(tree.recomputations() - before, 1 + 2 + 3);
+    let leaf_path = segments(&leaves[7].0);
+    assert_eq!(tree.union_of(&leaf_path), Some(&leaves[7].1));
+    assert_eq!(tree.leaf(&leaf_path), Some(&leaves[7].1));
+}
+
+#[test]
+fn test_leaves_must_share_the_configuration_of_the_tree() {
+    let mut tree = MergeTree::with_precision_seed(10, 3);
+    assert!(tree.is_empty());
+    assert_eq!(tree.cardinality_of(&[]), Some(0.0));
+    assert_eq!(tree.cardinality_of(&["eu"]), None);
+
+    let sketch = |seed, precision, range: std::ops::Range<u64>| {
+        let mut hll = Hypeerlog::with_precision_seed(precision, seed);
+        hll.insert_many(&range.collect::<Vec<_>>());
+        hll
+    };
+    assert_eq!(tree.update_leaf(&["eu", "a"], sketch(3, 11, 0..10)).unwrap_err(), HypeerlogError::PrecisionMismatch);
+    assert_eq!(tree.update_leaf(&["eu", "a"], sketch(4, 10, 0..10)).unwrap_err(), HypeerlogError::SeedMismatch);
+    assert!(!tree.contains(&["eu"]));
+
+    // A node can hold a sketch of its own next to its children
+    tree.update_leaf(&["eu", "a"], sketch(3, 10, 0..100)).unwrap();
+    tree.update_leaf(&["eu"], sketch(3, 10, 50..150)).unwrap();
+    tree.update_leaf(&["eu", "b"], sketch(3, 10, 100..200)).unwrap();
+    assert_eq!((tree.leaf_count(), tree.precision()), (3, 10));
+    assert_eq!(tree.union_of(&["eu"]).unwrap().register_values(), sketch(3, 10, 0..200).register_values());
+
+    // Removing leaves prunes the nodes left empty, and the unions follow
+    assert_eq!(tree.remove_leaf(&["eu", "b"]).map(|hll| hll.register_values().to_vec()), Some(sketch(3, 10, 100..200).register_values().to_vec()));
+    assert_eq!(tree.remove_leaf(&["eu", "b"]), None);
+    assert!(!tree.contains(&["eu", "b"]));
+    assert_eq!(tree.union_of(&[]).unwrap().register_values(), sketch(3, 10, 0..150).register_values());
+    assert!(tree.remove_leaf(&["eu"]).is_some());
+    assert_eq!(tree.union_of(&["eu"]).unwrap().register_values(), sketch(3, 10, 0..100).register_values());
+    assert!(tree.remove_leaf(&["eu", "a"]).is_some());
+    assert!(tree.is_empty());
+    assert_eq!(tree.cardinality_of(&[]), Some(0.0));
+}
@@ -0,0 +1,105 @@
+#![cfg(feature = "mmap")]
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::BuildHasherDefault;
+use std::path::PathBuf;
+
+use hypeerlog::{Hypeerlog, HypeerlogError};
+
+mod common;
+use common::sketch_of_range;
+
+
+
+
+// A dump file in the temp directory, removed when dropped
+struct TempDump(PathBuf);
+
+impl TempDump {
+    fn new(name: &str, bytes: &[u8]) -> Self {
+        let path = std::env::temp_dir().join(format!("hypeerlog-mmap-{}-{}.hll", std::process::id(), name));
+        std::fs::write(&path, bytes).unwrap();
+        TempDump(path)
+    }
+}
+
+impl Drop for TempDump {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+
+
+
+#[test]
+fn test_mmap_matches_load() {
+    let hll = sketch_of_range(14, 0, 50_000);
+    let file = TempDump::new("matches", &hll.dump());
+
+    // SAFETY: the file is not modified while mapped
+    let mapped = unsafe { Hypeerlog::open_mmap(&file.0) }.unwrap();
+    let loaded = Hypeerlog::load(std::fs::read(&file.0).unwrap()).unwrap();
+    assert_eq!(mapped.precision(), 14);
+    assert_eq!(mapped.cardinality(), loaded.cardinality());
+    assert_eq!(mapped.to_hypeerlog(), loaded);
+}
+
+#[test]
+fn test_mmap_union() {
+    let (a, b) = (sketch_of_range(12, 0, 3000), sketch_of_range(12, 2000, 6000));
+    let (file_a, file_b) = (TempDump::new("union-a", &a.dump()), TempDump::new("union-b", &b.dump()));
+
+    // SAFETY: the files are not modified while mapped
+    let (mapped_a, mapped_b) = unsafe { (Hypeerlog::open_mmap(&file_a.0).unwrap(), Hypeerlog::open_mmap(&file_b.0).unwrap()) };
+    let merged = a.merge(b).unwrap();
+    assert_eq!(mapped_a.union_cardinality(&mapped_b).unwrap(), merged.cardinality());
+
+    let other = TempDump::new("union-p10", &sketch_of_range(10, 0, 10).dump());
+    // SAFETY: the file is not modified while mapped
+    let mapped_other = unsafe { Hypeerlog::open_mmap(&other.0) }.unwrap();
+    assert_eq!(mapped_a.union_cardinality(&mapped_other).unwrap_err(), HypeerlogError::PrecisionMismatch);
+}
+
+#[test]
+fn test_mmap_view_merges() {
+    let hll = sketch_of_range(12, 0, 1000);
+    let file = TempDump::new("view", &hll.dump());
+
+    // SAFETY: the file is not modified while mapped
+    let mapped = unsafe { Hypeerlog::open_mmap(&file.0) }.unwrap();
+    let mut target = sketch_of_range(12, 1000, 2000);
+    mapped.as_view().merge_into(&mut target).unwrap();
+    assert_eq!(target, sketch_of_range(12, 0, 2000));
+}
+
+#[test]
+fn test_mmap_custom_hasher() {
+    let hasher = BuildHasherDefault::<DefaultHasher>::default();
+    let mut hll = Hypeerlog::with_hasher(hasher.clone());
+    hll.insert(42);
+    let file = TempDump::new("custom", &hll.dump());
+
+    // SAFETY: the file is not modified while mapped
+    let mapped = unsafe { Hypeerlog::open_mmap_with_hasher(&file.0, hasher) }.unwrap();
+    assert_eq!(mapped.cardinality(), hll.cardinality());
+}
+
+#[test]
+fn test_mmap_rejects_invalid() {
+    let hll = sketch_of_range(12, 0, 1000);
+
+    let mut truncated = hll.dump();
+    truncated.pop();
+    let file = TempDump::new("truncated", &truncated);
+    // SAFETY: the file is not modified while mapped
+    assert_eq!(unsafe { Hypeerlog::open_mmap(&file.0) }.unwrap_err(), HypeerlogError::InvalidLength);
+
+    let file = TempDump::new("compact", &sketch_of_range(12, 0, 10).dump_compact());
+    // SAFETY: the file is not modified while mapped
+    assert_eq!(unsafe { Hypeerlog::open_mmap(&file.0) }.unwrap_err(), HypeerlogError::EncodedRegisters);
+
+    let missing = std::env::temp_dir().join("hypeerlog-mmap-missing.hll");
+    // SAFETY: the file does not exist
+    assert_eq!(unsafe { Hypeerlog::open_mmap(&missing) }.unwrap_err(), HypeerlogError::Io(std::io::ErrorKind::NotFound));
+}
@@ -0,0 +1,122 @@
+use std::collections::HashSet;
+
+use hypeerlog::{assert_cardinality_within, Hypeerlog, HypeerlogError, MultiHll, Murmur3BuildHasher};
+use rand::prelude::*;
+
+mod common;
+use common::Opaque;
+
+
+
+
+// A week of visits per hour, with users drifting from one day to the next
+fn visits(rng: &mut StdRng) -> Vec<(usize, u64)> {
+    (0..168)
+        .flat_map(|hour| (0..200).map(move |_| hour))
+        .map(|hour| (hour, (hour / 24 * 500) as u64 + rng.random_range(0..2000)))
+        .collect()
+}
+
+
+
+
+#[test]
+fn test_range_unions_match_sketches_of_the_ranges() {
+    let mut rng = StdRng::seed_from_u64(399);
+    let visits = visits(&mut rng);
+    let mut week = MultiHll::with_precision_seed_len(11, 5, 168);
+    for &(hour, user) in &visits {
+        week.insert(hour, user).unwrap();
+    }
+    assert_eq!((week.len(), week.precision()), (168, 11));
+
+    for range in [0..1, 9..17, 24..48, 0..168, 100..100] {
+        let users: Vec<u64> = visits.iter().filter(|(hour, _)| range.contains(hour)).map(|&(_, user)| user).collect();
+        let mut exact = Hypeerlog::with_precision_seed(11, 5);
+        exact.insert_many(&users);
+        assert_eq!(week.union_range(range.clone()).unwrap(), exact.cardinality(), "range={:?}", range);
+        if range.len() == 1 {
+            assert_eq!(week.cardinality(range.start).unwrap(), exact.cardinality());
+            assert_eq!(week.sketch(range.start).unwrap().to_hypeerlog().register_values(), exact.register_values());
+        }
+        assert_cardinality_within!(exact, users.iter().collect::<HashSet<_>>().len(), 4);
+    }
+    assert_eq!(week.union_range(..).unwrap(), week.union_range(0..168).unwrap());
+    assert_eq!(week.union_range(160..).unwrap(), week.union_range(160..=167).unwrap());
+    assert_eq!(week.relative_error(), Hypeerlog::with_precision(11).relative_error());
+
+    // Merging two halves of the visits gives the array of all of them, and dumps keep it
+    let mut first = MultiHll::with_precision_seed_len(11, 5, 168);
+    let mut second = first.clone();
+    for (i, &(hour, user)) in visits.iter().enumerate() {
+        let half = if i % 2 == 0 { &mut first } else { &mut second };
+        half.insert(hour, user).unwrap();
+    }
+    first.merge_with(&second).unwrap();
+    assert_eq!(first, week);
+    assert_eq!(MultiHll::from_bytes(&week.dump()).unwrap(), week);
+    week.clear();
+    assert_eq!(week.union_range(..).unwrap(), 0.0);
+}
+
+#[test]
+fn test_indices_out_of_bounds_are_rejected() {
+    let mut array = MultiHll::with_precision_len(10, 24);
+    let out_of_bounds = |index| HypeerlogError::IndexOutOfBounds { index, len: 24 };
+    assert!(array.insert(23, "last").unwrap());
+    assert_eq!(array.insert(24, "past").unwrap_err(), out_of_bounds(24));
+    assert_eq!(array.insert_hashed(usize::MAX, 1).unwrap_err(), out_of_bounds(usize::MAX));
+    assert_eq!(array.insert_many(30, &[1, 2, 3]).unwrap_err(), out_of_bounds(30));
+    assert_eq!(array.cardinality(24).unwrap_err(), out_of_bounds(24));
+    assert!(array.sketch(24).is_err());
+    assert_eq!(array.union_range(20..25).unwrap_err(), out_of_bounds(25));
+    assert_eq!(array.union_range(..=24).unwrap_err(), out_of_bounds(25));
+    #[allow(clippy::reversed_empty_ranges)]
+    let reversed = 10..5;
+    assert_eq!(array.union_range(reversed).unwrap_err(), out_of_bounds(10));
+    assert_eq!(array.union_range(24..).unwrap(), 0.0);
+    assert_eq!(array.insert_many(0, &[1, 2, 3]).unwrap(), 3);
+    assert_eq!(array.cardinality(0).unwrap().round(), 3.0);
+
+    // Arrays of another shape or hasher are not merged
+    let before = array.clone();
+    assert_eq!(array.merge_with(&MultiHll::with_precision_len(10, 23)).unwrap_err(), HypeerlogError::LengthMismatch);
+    assert_eq!(array.merge_with(&MultiHll::with_precision_len(11, 24)).unwrap_err(), HypeerlogError::PrecisionMismatch);
+    assert_eq!(array.merge_with(&MultiHll::with_precision_seed_len(10, 1, 24)).unwrap_err(), HypeerlogError::SeedMismatch);
+    assert_eq!(array, before);
+
+    // Dumps must hold the registers of exactly their sketches
+    let dump = array.dump();
+    assert_eq!(MultiHll::from_bytes(&dump[..dump.len() - 1]).unwrap_err(), HypeerlogError::InvalidLength);
+    assert_eq!(MultiHll::from_bytes(&dump[..20]).unwrap_err(), HypeerlogError::InvalidLength);
+    assert_eq!(MultiHll::from_bytes(&Hypeerlog::new().dump()).unwrap_err(), HypeerlogError::InvalidMagic);
+    let mut longer = dump.clone();
+    longer[19] = 25;
+    assert_eq!(MultiHll::from_bytes(&longer).unwrap_err(), HypeerlogError::InvalidLength);
+    let mut newer = dump.clone();
+    newer[4] = 2;
+    assert_eq!(MultiHll::from_bytes(&newer).unwrap_err(), HypeerlogError::UnsupportedVersion);
+    let mut flags = dump.clone();
+    flags[6] |= 4;
+    assert_eq!(MultiHll::from_bytes(&flags).unwrap_err(), HypeerlogError::UnsupportedFlags);
+    let mut corrupted = dump.clone();
+    *corrupted.last_mut().unwrap() ^= 1;
+    assert!(matches!(MultiHll::from_bytes(&corrupted), Err(HypeerlogError::ChecksumMismatch { .. })));
+    assert_eq!(MultiHll::from_bytes_with_hasher(&dump, Murmur3BuildHasher::new(1)).unwrap_err(), HypeerlogError::SeedMismatch);
+}
+
+#[test]
+fn test_equality_compares_the_registers_and_the_hasher_identity() {
+    let mut array = MultiHll::with_hasher_precision_len(10, 4, Opaque::default());
+    array.insert(1, "a").unwrap();
+    let mut other = MultiHll::with_hasher_precision_len(10, 4, Opaque::default());
+    other.insert(1, "a").unwrap();
+    assert_eq!(array, other);
+    other.insert(2, "b").unwrap();
+    assert_ne!(array, other);
+
+    // Arrays of hashers with another seed differ, even when all their sketches are empty
+    assert_ne!(MultiHll::with_precision_seed_len(10, 1, 4), MultiHll::with_precision_seed_len(10, 2, 4));
+    assert_ne!(MultiHll::with_precision_len(10, 4), MultiHll::with_precision_len(10, 5));
+    assert_eq!(MultiHll::from_bytes(&MultiHll::with_precision_seed_len(10, 1, 4).dump()).unwrap(), MultiHll::with_precision_seed_len(10, 1, 4));
+}
@@ -0,0 +1,356 @@
+use std::hash::{BuildHasher, Hash, Hasher};
+
+use hypeerlog::{HasherId, Hypeerlog, Murmur3BuildHasher};
+use rand::prelude::*;
+
+
+
+
+fn murmur(seed: u32) -> Murmur3BuildHasher {
+    Murmur3BuildHasher::new(seed)
+}
+
+// Hashes its bytes as written, with no length prefix
+struct Raw<'a>(&'a [u8]);
+
+impl Hash for Raw<'_> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write(self.0);
+    }
+}
+
+
+
+
+// The published MurmurHash3_x86_32 test vectors (SMHasher and the reference C++ implementation)
+#[test]
+fn test_reference_vectors() {
+    let vectors: [(&[u8], u32, u32); 11] = [
+        (b"", 0, 0x00000000),
+        (b"", 1, 0x514e28b7),
+        (b"", 0xffffffff, 0x81f16f39),
+        (b"\0\0\0\0", 0, 0x2362f9de),
+        (b"a", 0, 0x3c2569b2),
+        (b"abc", 0, 0xb3dd93fa),
+        (b"a", 0x9747b28c, 0x7fa09ea6),
+        (b"abc", 0x9747b28c, 0xc84a62dd),
+        (b"abcd", 0x9747b28c, 0xf0478627),
+        (b"Hello, world!", 0x9747b28c, 0x24884cba),
+        (b"The quick brown fox jumps over the lazy dog", 0x9747b28c, 0x2fa826cd),
+    ];
+    for (key, seed, expected) in vectors {
+        let h = murmur(seed);
+        assert_eq!(h.hash_one(Raw(key)), expected as u64, "{:?} seed {:#x}", key, seed);
+        assert_eq!(h.hash_bytes(key), expected as u64, "{:?} seed {:#x}", key, seed);
+    }
+}
+
+// SMHasher's verification test: the keys 0, 0 1, 0 1 2, ... up to 255 bytes are hashed with the seed
+// 256 - length, and the concatenated little-endian hashes are hashed again with the seed 0
+#[test]
+fn test_smhasher_verification_value() {
+    let key: Vec<u8> = (0..=255).collect();
+    let mut hashes = Vec::new();
+    for len in 0..256 {
+        let hash = murmur(256 - len as u32).hash_one(Raw(&key[..len])) as u32;
+        hashes.extend_from_slice(&hash.to_le_bytes());
+    }
+    assert_eq!(murmur(0).hash_one(Raw(&hashes)), 0xb0f57ee3);
+}
+
+// `write(a); write(b); ...` equals `write(a ++ b ++ ...)` however the bytes are chunked, including
+// chunks that leave, extend or complete a partial block
+#[test]
+fn test_streaming_matches_single_write_for_any_chunking() {
+    let mut rng = rand::rng();
+    let h = murmur(0x9747b28c);
+    for len in 0..=100 {
+        let bytes: Vec<u8> = (0..len).map(|_| rng.random()).collect();
+        let expected = h.hash_one(Raw(&bytes));
+
+        let mut one_at_a_time = h.build_hasher();
+        bytes.iter().for_each(|&b| one_at_a_time.write(&[b]));
+        assert_eq!(one_at_a_time.finish(), expected, "{:02x?}", bytes);
+
+        for _ in 0..20 {
+            let mut hasher = h.build_hasher();
+            let mut rest = &bytes[..];
+            while !rest.is_empty() {
+                let (chunk, tail) = rest.split_at(rng.random_range(0..=rest.len().min(9)));
+                hasher.write(chunk);
+                rest = tail;
+            }
+            assert_eq!(hasher.finish(), expected, "{:02x?}", bytes);
+        }
+    }
+}
+
+#[test]
+fn test_finish_does_not_reset_the_stream() {
+    let mut hasher = murmur(0).build_hasher();
+    hasher.write(b"a");
+    assert_eq!(hasher.finish(), 0x3c2569b2);
+    assert_eq!(hasher.finish(), 0x3c2569b2);
+    hasher.write(b"bc");
+    assert_eq!(hasher.finish(), 0xb3dd93fa);
+}
+
+#[test]
+fn test_write_finish_write_finish() {
+    let h = murmur(42);
+    let mut rng = rand::rng();
+    for len in 0..=40 {
+        let bytes: Vec<u8> = (0..len).map(|_| rng.random()).collect();
+        for split in 0..=len {
+            let mut hasher = h.build_hasher();
+            hasher.write(&bytes[..split]);
+            assert_eq!(hasher.finish(), h.hash_bytes(&bytes[..split]));
+            hasher.write(&bytes[split..]);
+            assert_eq!(hasher.finish(), h.hash_bytes(&bytes), "split {} of {:02x?}", split, bytes);
+        }
+    }
+}
+
+#[test]
+fn test_clone_then_diverge() {
+    let h = murmur(7);
+    for prefix_len in 0..=9 {
+        let prefix: Vec<u8> = (0..prefix_len).collect();
+        let mut shared = h.build_hasher();
+        shared.write(&prefix);
+
+        for suffix in [&b""[..], b"x", b"yz", b"suffix", b"a suffix longer than the prefix"] {
+            let mut branch = shared.clone();
+            branch.write(suffix);
+            assert_eq!(branch.finish(), h.hash_bytes(&[&prefix[..], suffix].concat()), "{:?} {:?}", prefix, suffix);
+        }
+        // The branches leave the shared prefix alone
+        assert_eq!(shared.finish(), h.hash_bytes(&prefix));
+    }
+}
+
+#[test]
+fn test_hash_map_agrees_with_sketch() {
+    let h = murmur(42);
+    let mut map = std::collections::HashMap::with_hasher(h);
+    let mut hll = Hypeerlog::with_seed(42);
+    for i in 0..100u64 {
+        map.insert(i, ());
+        hll.insert(i);
+    }
+    let mut from_hashes = Hypeerlog::with_seed(42);
+    for key in map.keys() {
+        from_hashes.insert_hashed(map.hasher().hash_one(key));
+    }
+    assert_eq!(from_hashes, hll);
+}
+
+
+
+
+// Fixtures computed with the reference MurmurHash3_x86_32 over the little-endian bytes of each value
+#[test]
+fn test_integer_hash_fixtures() {
+    let cases: [(u32, [u32; 12]); 2] = [
+        (0, [0x0865b3bf, 0x1b3dc56b, 0xc193d15c, 0xfa908422, 0xfbc7e3f6, 0xfd6cf10d, 0xbb3c5a28, 0xf4b67ea0, 0x516faf25, 0xe519346e, 0x6382f726, 0x9a7b1d75]),
+        (42, [0x097fadfd, 0x131ddab3, 0x086b46c3, 0x272bce7d, 0x2ff984a9, 0x10e99a12, 0x1fbb5ea8, 0x68d058a5, 0xcd1e64fb, 0xb7dbe945, 0x99baee6e, 0x0d3bf860]),
+    ];
+    for (seed, expected) in cases {
+        let h = murmur(seed);
+        let got = [
+            h.hash_one(0xabu8),
+            h.hash_one(0xbeefu16),
+            h.hash_one(0xdeadbeefu32),
+            h.hash_one(0x0123_4567_89ab_cdefu64),
+            h.hash_one(0x0123_4567_89ab_cdef_fedc_ba98_7654_3210u128),
+            h.hash_one(-1i8),
+            h.hash_one(-2i16),
+            h.hash_one(-42i32),
+            h.hash_one(i64::MIN),
+            h.hash_one(-1i128),
+            h.hash_one(1_000_000usize),
+            h.hash_one(-7isize),
+        ];
+        assert_eq!(got, expected.map(u64::from), "seed {}", seed);
+    }
+}
+
+#[test]
+fn test_integers_hash_as_little_endian_bytes() {
+    let h = murmur(7);
+    for i in [0u64, 1, 0xff, 0x1234_5678, u64::MAX] {
+        assert_eq!(h.hash_one(i), h.hash_one(Raw(&i.to_le_bytes())));
+        assert_eq!(h.hash_one(i as u32), h.hash_one(Raw(&(i as u32).to_le_bytes())));
+        assert_eq!(h.hash_one(i as usize), h.hash_one(Raw(&i.to_le_bytes())));
+    }
+}
+
+#[test]
+fn test_integer_sketches_match_byte_sketches() {
+    let mut ints = Hypeerlog::with_precision(12);
+    let mut bytes = Hypeerlog::with_precision(12);
+    for i in 0..10_000u64 {
+        ints.insert(i);
+        bytes.insert(Raw(&i.to_le_bytes()));
+    }
+    assert_eq!(ints, bytes);
+}
+
+#[test]
+fn test_usize_is_independent_of_pointer_width() {
+    // What a 32-bit target inserts for an identifier that fits in 32 bits, and what a 64-bit one does
+    let (mut narrow, mut wide, mut usizes) = (Hypeerlog::with_precision(12), Hypeerlog::with_precision(12), Hypeerlog::with_precision(12));
+    for id in 0..10_000u32 {
+        narrow.insert(Raw(&u64::from(id).to_le_bytes()));
+        wide.insert(u64::from(id));
+        usizes.insert(id as usize);
+    }
+    assert_eq!(narrow, wide);
+    assert_eq!(usizes, wide);
+
+    let h = murmur(0);
+    assert_eq!(h.hash_one(-7isize), h.hash_one(-7i64));
+    assert_eq!(h.hash_one(isize::MIN), h.hash_one(isize::MIN as i64));
+}
+
+#[test]
+fn test_slice_length_prefix_is_64_bits() {
+    let h = murmur(0);
+    let slice: &[u8] = &[1, 2, 3];
+    assert_eq!(h.hash_one(slice), h.hash_one(Raw(&[3, 0, 0, 0, 0, 0, 0, 0, 1, 2, 3])));
+}
+
+#[test]
+fn test_insert_hashed_matches_insert() {
+    let h = murmur(0);
+    let mut inserted = Hypeerlog::with_precision(14);
+    let mut hashed = Hypeerlog::with_precision(14);
+    for i in 0..20_000u64 {
+        inserted.insert(i);
+        hashed.insert_hashed(h.hash_one(i));
+    }
+    assert_eq!(hashed, inserted);
+    assert_eq!(Hypeerlog::from_hashes(14, (0..20_000u64).map(|i| h.hash_one(i))), inserted);
+}
+
+#[test]
+fn test_insert_hashed_uses_all_64_bits() {
+    // Bucket from the low bits, rank from the trailing zeros of the rest
+    let mut hll = Hypeerlog::with_precision(4);
+    hll.insert_hashed(0b1000_0011);
+    hll.insert_hashed(1 << 63 | 0b0101);
+    hll.insert_hashed(0b0111);
+    let dump = hll.dump();
+    let registers = &dump[dump.len() - 16..];
+    assert_eq!(registers[3], 4);
+    assert_eq!(registers[5], 60);
+    assert_eq!(registers[7], 61);
+
+    let from_hashes = Hypeerlog::from_hashes(40, [1, 2, 3]);
+    assert_eq!(from_hashes.precision(), 25);
+}
+
+#[test]
+fn test_insert_hashes_matches_insert_hashed() {
+    let h = murmur(3);
+    // Full 64-bit hashes, so that the ranks use the high bits too
+    let hashes: Vec<u64> = (0..50_000u64).map(|i| h.hash_one(i) << 32 | h.hash_one(!i)).collect();
+    for p in [4, 14, 18] {
+        let mut one_by_one = Hypeerlog::with_precision(p);
+        for &hash in &hashes {
+            one_by_one.insert_hashed(hash);
+        }
+
+        let mut batched = Hypeerlog::with_precision(p);
+        batched.insert_hashes(&hashes[..7]);
+        batched.insert_hashes(&hashes[7..]);
+        assert_eq!(batched, one_by_one, "p={}", p);
+    }
+}
+
+#[test]
+fn test_one_shot_matches_streaming() {
+    let mut rng = rand::rng();
+    for seed in [0, 42, rng.random()] {
+        let h = murmur(seed);
+        for len in 0..=64 {
+            for _ in 0..8 {
+                let bytes: Vec<u8> = (0..len).map(|_| rng.random()).collect();
+                let expected = h.hash_one(Raw(&bytes));
+                assert_eq!(h.hash_bytes(&bytes), expected, "seed {} {:02x?}", seed, bytes);
+
+                // The streaming hasher carries partial blocks across writes, wherever they are split
+                for split in 0..=len {
+                    let mut hasher = h.build_hasher();
+                    hasher.write(&bytes[..split]);
+                    hasher.write(&bytes[split..]);
+                    assert_eq!(hasher.finish(), expected, "seed {} split {} {:02x?}", seed, split, bytes);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_insert_bytes_matches_streaming_insert() {
+    for len in 0..=64 {
+        let bytes: Vec<u8> = (0..len as u8).map(|b| b.wrapping_mul(37)).collect();
+        let (mut one_shot, mut streaming) = (Hypeerlog::with_precision_seed(10, 7), Hypeerlog::with_precision_seed(10, 7));
+        one_shot.insert_bytes(&bytes);
+        streaming.insert(Raw(&bytes));
+        assert_eq!(one_shot, streaming, "length {}", len);
+    }
+}
+
+// The registers of a sketch, read from the end of its dump
+fn registers(hll: &Hypeerlog) -> Vec<u8> {
+    let dump = hll.dump();
+    dump[dump.len() - hll.len()..].to_vec()
+}
+
+// Checks the hash of `value` and the single register it sets at p=14
+fn assert_golden<T: Hash + std::fmt::Debug>(seed: u32, value: T, hash: u32, index: usize, rank: u8) {
+    assert_eq!(murmur(seed).hash_one(&value), hash as u64, "seed {} {:?}", seed, value);
+
+    let mut hll = Hypeerlog::with_precision_seed(14, seed);
+    hll.insert(&value);
+    let registers = registers(&hll);
+    assert_eq!(registers[index], rank, "seed {} {:?}", seed, value);
+    assert_eq!(registers.iter().filter(|&&r| r != 0).count(), 1, "seed {} {:?}", seed, value);
+}
+
+// (hash, register index at p=14, register value)
+type Golden = (u32, usize, u8);
+
+// Computed with the reference MurmurHash3_x86_32 over the frozen encodings: strings end with 0xff, and
+// length prefixes are little-endian u64s. A failure here means hashing of strings or collections changed,
+// e.g. in the defaults of `Hasher` in a new Rust release, which would break merges with stored sketches.
+#[test]
+fn test_string_hashing_golden_registers() {
+    let cases: [(u32, [Golden; 8]); 2] = [
+        (0, [(0xfd6cf10d, 12557, 1), (0xab1c3ded, 15853, 5), (0xb6b6fda6, 15782, 1), (0x60203eed, 16109, 8),
+             (0x9ee73a1f, 14879, 3), (0x01e34927, 2343, 1), (0x6c27c77c, 1916, 1), (0x81984149, 329, 1)]),
+        (42, [(0x10e99a12, 6674, 2), (0x41d53811, 14353, 3), (0xa5a71ca7, 7335, 3), (0xb847bf43, 16195, 2),
+              (0x55880621, 1569, 6), (0x1621eb0d, 11021, 1), (0x565cf13f, 12607, 1), (0xd5c39c5d, 7261, 2)]),
+    ];
+    for (seed, [empty, foo, hypeerlog, crab, bytes, strings, tuple, ch]) in cases {
+        assert_golden(seed, "", empty.0, empty.1, empty.2);
+        assert_golden(seed, "foo", foo.0, foo.1, foo.2);
+        assert_golden(seed, String::from("foo"), foo.0, foo.1, foo.2);
+        assert_golden(seed, "hypeerlog", hypeerlog.0, hypeerlog.1, hypeerlog.2);
+        assert_golden(seed, "🦀 crab", crab.0, crab.1, crab.2);
+        assert_golden(seed, &b"foo"[..], bytes.0, bytes.1, bytes.2);
+        assert_golden(seed, vec![0x66u8, 0x6f, 0x6f], bytes.0, bytes.1, bytes.2);
+        assert_golden(seed, vec!["a", "bc"], strings.0, strings.1, strings.2);
+        assert_golden(seed, ("user", 42u32), tuple.0, tuple.1, tuple.2);
+        assert_golden(seed, 'x', ch.0, ch.1, ch.2);
+    }
+}
+
+#[test]
+fn test_str_encoding_is_bytes_then_delimiter() {
+    let h = murmur(7);
+    for s in ["", "a", "abcd", "with\u{ff}inside", "🦀"] {
+        assert_eq!(h.hash_one(s), h.hash_bytes(&[s.as_bytes(), &[0xff]].concat()), "{:?}", s);
+    }
+}
@@ -0,0 +1,181 @@
+use hypeerlog::{cardinality_of_dump, merge_dumps, Hypeerlog, HypeerlogError, PackedHypeerlog};
+use rand::prelude::*;
+
+mod common;
+use common::Opaque;
+
+
+
+
+// The same random hashes inserted into a packed and an unpacked sketch
+fn sketch_pair(p: u8, n: usize, rng: &mut StdRng) -> (PackedHypeerlog, Hypeerlog) {
+    let hashes: Vec<u64> = (0..n).map(|_| rng.random()).collect();
+    let mut packed = PackedHypeerlog::with_precision(p);
+    packed.insert_hashes(&hashes);
+    (packed, Hypeerlog::from_hashes(p, hashes))
+}
+
+fn assert_same(packed: &PackedHypeerlog, unpacked: &Hypeerlog, step: &str) {
+    assert_eq!(packed.cardinality().to_bits(), unpacked.cardinality().to_bits(), "after {}", step);
+    assert_eq!(packed.is_empty(), unpacked.is_empty(), "after {}", step);
+    assert_eq!(&Hypeerlog::load(packed.dump()).unwrap(), unpacked, "after {}", step);
+}
+
+
+
+
+// Raising the registers one at a time, in an order that visits every position of the 3-byte groups in
+// turn, to every rank the precision allows, checks that writing a register that straddles two bytes
+// never disturbs its neighbours
+#[test]
+fn test_packed_registers_keep_their_neighbours() {
+    let p = 4;
+    let mut packed = PackedHypeerlog::with_precision(p);
+    let mut unpacked = Hypeerlog::with_precision(p);
+    for rank in 1..=61u64 {
+        for idx in [1, 2, 0, 3, 6, 5, 4, 7, 9, 10, 8, 11, 14, 13, 15, 12] {
+            let hash = if rank < 61 { idx | 1 << (p as u64 + rank - 1) } else { idx };
+            packed.insert_hashed(hash);
+            unpacked.insert_hashed(hash);
+            assert_eq!(Hypeerlog::from(PackedHypeerlog::load(packed.dump()).unwrap()), unpacked, "rank {}", rank);
+        }
+    }
+    assert_same(&packed, &unpacked, "raising every register to the largest rank");
+}
+
+#[test]
+fn test_packed_sketches_match_unpacked_ones() {
+    let mut rng = StdRng::seed_from_u64(359);
+    for p in 4..=18 {
+        let (mut packed, mut unpacked) = sketch_pair(p, 3 << p, &mut rng);
+        assert_eq!(packed.len(), unpacked.len());
+        assert_same(&packed, &unpacked, "insert_hashes");
+
+        packed.insert("element");
+        unpacked.insert("element");
+        packed.insert_str("a string");
+        unpacked.insert_str("a string");
+        assert_same(&packed, &unpacked, "insert");
+
+        let round_trip = PackedHypeerlog::from(Hypeerlog::from(packed));
+        assert_same(&round_trip, &unpacked, "converting");
+        assert_eq!(round_trip, PackedHypeerlog::from(unpacked));
+    }
+
+    let mut packed = PackedHypeerlog::new();
+    assert!(packed.is_empty());
+    packed.insert_many(&[1, 2, 3]);
+    assert_eq!(packed.cardinality().round(), 3.0);
+    packed.clear();
+    assert!(packed.is_empty());
+    assert_eq!(packed, PackedHypeerlog::new());
+}
+
+#[test]
+fn test_packed_accuracy() {
+    let mut rng = StdRng::seed_from_u64(1359);
+    for p in [10, 12, 14, 16] {
+        let cardinalities = [1_000, 5_000, 20_000, 50_000];
+        let mut total_err = 0.0;
+        for card in cardinalities {
+            let mut packed = PackedHypeerlog::with_precision(p);
+            for _ in 0..card {
+                packed.insert(rng.random::<u64>());
+            }
+            total_err += (packed.cardinality() - card as f64).abs() / card as f64;
+        }
+        let mean_err = total_err / cardinalities.len() as f64;
+        let m = (1u64 << p) as f64;
+        assert!(mean_err < 3.0 * 1.04 / m.sqrt(), "p={}: mean relative error {}", p, mean_err);
+    }
+}
+
+#[test]
+fn test_packed_dump_round_trip() {
+    let mut rng = StdRng::seed_from_u64(42);
+    for p in [4, 10, 14] {
+        let (packed, unpacked) = sketch_pair(p, 1 << p, &mut rng);
+        let dump = packed.dump();
+        // Packed sketches do not count their insertions, so their dumps have no insertion count either
+        assert_eq!(dump.len(), unpacked.dump().len() - 8 - unpacked.len() / 4);
+        assert_eq!(dump[..5], unpacked.dump()[..5]);
+        assert_eq!(dump[6..10], unpacked.dump()[6..10], "the checksum covers the unpacked registers");
+
+        assert_eq!(PackedHypeerlog::load(dump.clone()).unwrap(), packed);
+        assert_eq!(PackedHypeerlog::from_bytes(&unpacked.dump()).unwrap(), packed);
+        assert_eq!(Hypeerlog::from_bytes(&dump).unwrap(), unpacked);
+        assert_eq!(cardinality_of_dump(&dump).unwrap().to_bits(), packed.cardinality().to_bits());
+        assert_eq!(merge_dumps([dump.clone(), unpacked.dump()]).unwrap(), unpacked.dump());
+        assert_eq!(Hypeerlog::borrow_dump(&dump).unwrap_err(), HypeerlogError::EncodedRegisters);
+    }
+
+    let mut seeded = PackedHypeerlog::with_precision_seed(8, 7);
+    seeded.insert_many(&[1, 2, 3]);
+    let restored = PackedHypeerlog::load(seeded.dump()).unwrap();
+    assert_eq!(restored, seeded);
+
+    // Equality compares the registers, not the hasher builders, which do not need to be `PartialEq`
+    let mut opaque = PackedHypeerlog::with_hasher_precision(8, Opaque::default());
+    opaque.insert_many(&[1, 2, 3]);
+    let copy = opaque.clone();
+    assert_eq!(PackedHypeerlog::load_with_hasher(opaque.dump(), Opaque::default()).unwrap(), copy);
+    opaque.insert(4);
+    assert_ne!(opaque, copy);
+    assert_eq!(Hypeerlog::load(seeded.dump()).unwrap().merge_with(&Hypeerlog::with_precision(8)), Err(HypeerlogError::SeedMismatch));
+}
+
+#[test]
+fn test_corrupted_packed_dumps() {
+    let (packed, _) = sketch_pair(8, 1000, &mut StdRng::seed_from_u64(3));
+    let dump = packed.dump();
+
+    let mut flipped = dump.clone();
+    *flipped.last_mut().unwrap() ^= 0x01;
+    assert!(matches!(Hypeerlog::load(flipped), Err(HypeerlogError::ChecksumMismatch { .. })));
+
+    for len in [dump.len() - 1, dump.len() + 1] {
+        let mut resized = dump.clone();
+        resized.resize(len, 0);
+        assert_eq!(PackedHypeerlog::load(resized).unwrap_err(), HypeerlogError::InvalidLength);
+    }
+
+    // The compact and packed encodings exclude each other
+    let mut both = dump.clone();
+    both[5] |= 0b0000_1000;
+    assert_eq!(Hypeerlog::load(both).unwrap_err(), HypeerlogError::UnsupportedFlags);
+
+    // 6 bits hold ranks up to 63, two more than the largest rank at p=4
+    let mut out_of_range = b"HLL\x02\x04\x20".to_vec();
+    out_of_range.extend([0xff; 12]);
+    assert_eq!(Hypeerlog::load(out_of_range).unwrap_err(), HypeerlogError::InvalidRegisterValue);
+}
+
+#[test]
+fn test_packed_merges() {
+    let mut rng = StdRng::seed_from_u64(11);
+    for p in [4, 12, 16] {
+        let (packed_a, unpacked_a) = sketch_pair(p, 2 << p, &mut rng);
+        let (packed_b, unpacked_b) = sketch_pair(p, 1 << p, &mut rng);
+        let expected = unpacked_a.merge(unpacked_b).unwrap();
+
+        let mut merged = packed_a.clone();
+        merged.merge_with(&packed_b).unwrap();
+        assert_same(&merged, &expected, "merge_with");
+
+        let mut merged = packed_a.clone();
+        merged.merge_with_unpacked(&Hypeerlog::load(packed_b.dump()).unwrap()).unwrap();
+        assert_same(&merged, &expected, "merge_with_unpacked");
+
+        let mut merged = Hypeerlog::load(packed_a.dump()).unwrap();
+        merged.merge_with_packed(&packed_b).unwrap();
+        assert_eq!(merged, expected);
+    }
+
+    let mut packed = PackedHypeerlog::with_precision(12);
+    let mut unpacked = Hypeerlog::with_precision(12);
+    assert_eq!(packed.merge_with(&PackedHypeerlog::with_precision(13)), Err(HypeerlogError::PrecisionMismatch));
+    assert_eq!(packed.merge_with_unpacked(&Hypeerlog::with_precision(13)), Err(HypeerlogError::PrecisionMismatch));
+    assert_eq!(unpacked.merge_with_packed(&PackedHypeerlog::with_precision(13)), Err(HypeerlogError::PrecisionMismatch));
+    assert_eq!(packed.merge_with(&PackedHypeerlog::with_precision_seed(12, 1)), Err(HypeerlogError::SeedMismatch));
+    assert_eq!(unpacked.merge_with_packed(&PackedHypeerlog::with_precision_seed(12, 1)), Err(HypeerlogError::SeedMismatch));
+}
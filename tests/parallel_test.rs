@@ -0,0 +1,174 @@
+#![cfg(feature = "rayon")]
+
+use hypeerlog::{count_distinct, count_distinct_par, count_distinct_with_precision, EstimateDistinct, Hypeerlog, HypeerlogError};
+
+mod common;
+use common::Tagged;
+
+
+
+
+fn sketches(n: u64, p: u8) -> Vec<Hypeerlog> {
+    (0..n).map(|i| {
+        let mut hll = Hypeerlog::with_precision(p);
+        for x in i * 100..i * 100 + 250 {
+            hll.insert(x);
+        }
+        hll
+    }).collect()
+}
+
+fn sequential_fold(sketches: Vec<Hypeerlog>) -> Hypeerlog {
+    sketches.into_iter().reduce(|acc, hll| acc.merge(hll).unwrap()).unwrap()
+}
+
+
+
+
+#[test]
+fn test_par_merge_many_matches_sequential() {
+    let expected = sequential_fold(sketches(200, 12));
+    let merged = Hypeerlog::par_merge_many(sketches(200, 12)).unwrap().unwrap();
+    assert_eq!(merged, expected);
+}
+
+#[test]
+fn test_par_merge_all_matches_sequential() {
+    let inputs = sketches(200, 12);
+    let expected = sequential_fold(sketches(200, 12));
+    let merged = Hypeerlog::par_merge_all(&inputs).unwrap().unwrap();
+    assert_eq!(merged, expected);
+}
+
+#[test]
+fn test_par_merge_empty() {
+    let empty: Vec<Hypeerlog> = Vec::new();
+    assert!(Hypeerlog::par_merge_all(&empty).unwrap().is_none());
+    assert!(Hypeerlog::par_merge_many(empty).unwrap().is_none());
+}
+
+#[test]
+fn test_par_merge_mismatch_is_deterministic() {
+    for _ in 0..20 {
+        let mut inputs = sketches(100, 10);
+        inputs[40] = Hypeerlog::with_precision(12);
+        inputs[70] = Hypeerlog::with_precision(8);
+
+        let expected = HypeerlogError::Incompatible { index: 40, source: Box::new(HypeerlogError::PrecisionMismatch) };
+        assert_eq!(Hypeerlog::par_merge_all(&inputs).unwrap_err(), expected);
+        assert_eq!(Hypeerlog::par_merge_many(inputs).unwrap_err(), expected);
+    }
+}
+
+#[test]
+fn test_par_merge_reports_the_index_of_seed_and_hasher_mismatches() {
+    let mut inputs = sketches(100, 10);
+    inputs[60] = Hypeerlog::with_precision_seed(10, 7);
+    let expected = HypeerlogError::Incompatible { index: 60, source: Box::new(HypeerlogError::SeedMismatch) };
+    assert_eq!(Hypeerlog::par_merge_all(&inputs).unwrap_err(), expected);
+    assert_eq!(Hypeerlog::par_merge_many(inputs).unwrap_err(), expected);
+
+    let mut tagged: Vec<_> = (0..100).map(|_| Hypeerlog::with_hasher_precision(10, Tagged(1))).collect();
+    tagged[30] = Hypeerlog::with_hasher_precision(10, Tagged(2));
+    let expected = HypeerlogError::Incompatible { index: 30, source: Box::new(HypeerlogError::HasherMismatch) };
+    assert_eq!(Hypeerlog::par_merge_all(&tagged).unwrap_err(), expected);
+    assert_eq!(Hypeerlog::par_merge_many(tagged).unwrap_err(), expected);
+}
+
+#[test]
+fn test_par_insert_many_matches_insert_many() {
+    let elems: Vec<u64> = (0..200_000u64).map(|x| x.wrapping_mul(0x9e37_79b9_7f4a_7c15)).collect();
+    for threads in [1, 2, 3, 8] {
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+        for p in [4, 12, 18] {
+            // Around the length below which the slice is not split
+            for len in [0, 1, 16_384, 16_385, 100_000, elems.len()] {
+                let mut expected = Hypeerlog::with_precision_seed(p, 7);
+                expected.insert_many(&elems[..len]);
+
+                let mut hll = Hypeerlog::with_precision_seed(p, 7);
+                let raised = pool.install(|| hll.par_insert_many(&elems[..len]));
+                assert_eq!(hll, expected, "threads={} p={} len={}", threads, p, len);
+                // Every nonzero register of a new sketch was raised by the call
+                let nonzero = hll.iter().filter(|&rank| rank != 0).count();
+                assert_eq!(raised, nonzero, "threads={} p={} len={}", threads, p, len);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_par_insert_many_adds_to_the_sketch() {
+    let strings: Vec<String> = (0..50_000).map(|i| format!("user-{}", i)).collect();
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(4).build().unwrap();
+
+    let mut expected = Hypeerlog::new();
+    expected.insert("already there");
+    expected.insert_many(&strings);
+
+    let mut hll = Hypeerlog::new();
+    hll.insert("already there");
+    let before = hll.clone();
+    let raised = pool.install(|| hll.par_insert_many(&strings));
+    assert_eq!(hll, expected);
+    assert_eq!(hll.cardinality().to_bits(), expected.cardinality().to_bits());
+    assert_eq!(raised, hll.iter().zip(before.iter()).filter(|(new, old)| new != old).count());
+
+    // Inserting the same items again changes nothing, whether the slice is split or not
+    assert_eq!(pool.install(|| hll.par_insert_many(&strings)), 0);
+    assert_eq!(pool.install(|| hll.par_insert_many(&strings[..100])), 0);
+    assert_eq!(hll.register_values(), expected.register_values());
+}
+
+#[test]
+fn test_collect_matches_sequential_insertion() {
+    use rayon::prelude::*;
+
+    let elems: Vec<u64> = (0..100_000u64).map(|x| x % 30_000).collect();
+    for threads in [1, 2, 4, 8] {
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+
+        let collected: Hypeerlog = pool.install(|| elems.par_iter().map(|x| x + 1).collect());
+        let expected: Hypeerlog = elems.iter().map(|x| x + 1).collect();
+        assert_eq!(collected.precision(), 14);
+        assert_eq!(collected, expected, "threads={}", threads);
+
+        for p in [4, 10, 16] {
+            let collected = pool.install(|| Hypeerlog::par_from_iter_with_precision(p, &elems));
+            let mut expected = Hypeerlog::with_precision(p);
+            expected.insert_many(&elems);
+            assert_eq!(collected, expected, "threads={} p={}", threads, p);
+        }
+
+        let mut extended = Hypeerlog::with_precision_seed(12, 3);
+        extended.insert("already there");
+        pool.install(|| extended.par_extend((0..50_000u32).into_par_iter().map(|i| format!("user-{}", i))));
+        let mut expected = Hypeerlog::with_precision_seed(12, 3);
+        expected.insert("already there");
+        expected.extend((0..50_000u32).map(|i| format!("user-{}", i)));
+        assert_eq!(extended, expected, "threads={}", threads);
+    }
+
+    let empty: Hypeerlog = Vec::<u64>::new().into_par_iter().collect();
+    assert!(empty.is_empty());
+}
+
+#[test]
+fn test_count_distinct_par_matches_the_sequential_estimate() {
+    use rayon::prelude::*;
+
+    let elems: Vec<u64> = (0..300_000u64).map(|x| x.wrapping_mul(0x9e37_79b9_7f4a_7c15) % 50_000).collect();
+    let sequential = count_distinct(&elems);
+    assert_eq!(count_distinct_par(elems.par_iter()).to_bits(), sequential.to_bits());
+    assert_eq!(count_distinct_par(Vec::<u64>::new()), 0.0);
+
+    for threads in [1, 3, 8] {
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+        for p in [4, 12, 20] {
+            let estimate = pool.install(|| elems.par_iter().map(|x| x + 1).estimate_distinct(p)).unwrap();
+            let expected = count_distinct_with_precision(p, elems.iter().map(|x| x + 1)).unwrap();
+            assert_eq!(estimate.to_bits(), expected.to_bits(), "threads={} p={}", threads, p);
+        }
+    }
+    assert_eq!(elems.par_iter().estimate_distinct(26), Err(HypeerlogError::InvalidPrecision));
+}
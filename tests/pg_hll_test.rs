@@ -0,0 +1,115 @@
+#![cfg(feature = "pg-hll-compat")]
+
+use hypeerlog::{Hypeerlog, HypeerlogError, PgHllHasher, PG_HLL_TAG};
+use std::hash::{BuildHasher, Hasher};
+
+
+
+
+// `hll` values at log2m 11 and regwidth 5 holding `user-0` onwards, added as `hll_hash_bytea` hashes: 50 values
+// in the EXPLICIT representation, 300 in SPARSE and 20000 in FULL. Generated with a port of the storage format
+// and hashing of postgresql-hll, not read back from a database running the extension.
+const EXPLICIT_50: &[u8] = include_bytes!("fixtures/pg_hll_explicit.bin");
+const SPARSE_300: &[u8] = include_bytes!("fixtures/pg_hll_sparse.bin");
+const FULL_20000: &[u8] = include_bytes!("fixtures/pg_hll_full.bin");
+
+fn pg_sketch(n: usize) -> Hypeerlog<PgHllHasher> {
+    let mut hll = Hypeerlog::with_hasher_precision(11, PgHllHasher);
+    for i in 0..n {
+        hll.insert_pg_hll(format!("user-{}", i).as_bytes());
+    }
+    hll
+}
+
+
+
+
+#[test]
+fn test_from_pg_hll_representations() {
+    for (bytes, n) in [(EXPLICIT_50, 50), (SPARSE_300, 300), (FULL_20000, 20_000)] {
+        let hll = Hypeerlog::from_pg_hll(bytes).unwrap();
+        assert_eq!(hll.precision(), 11);
+        assert_eq!(hll, pg_sketch(n));
+
+        // log2m 11 has a standard error of ~2.3%
+        let estimate = hll.cardinality();
+        assert!((estimate - n as f64).abs() / (n as f64) < 0.08, "estimate {} for {}", estimate, n);
+    }
+}
+
+#[test]
+fn test_representations_agree() {
+    // The same set stored as EXPLICIT, SPARSE and FULL yields identical registers
+    let explicit = Hypeerlog::from_pg_hll(EXPLICIT_50).unwrap();
+    let full = Hypeerlog::from_pg_hll(&explicit.to_pg_hll()).unwrap();
+    assert_eq!(full, explicit);
+
+    let empty = Hypeerlog::from_pg_hll(&[0x11, 0x8b, 0x7f]).unwrap();
+    assert!(empty.is_empty());
+    assert_eq!(empty.precision(), 11);
+}
+
+#[test]
+fn test_to_pg_hll_full() {
+    let hll = Hypeerlog::from_pg_hll(FULL_20000).unwrap();
+    assert_eq!(hll.to_pg_hll(), FULL_20000);
+
+    for p in [4, 25] {
+        let hll = Hypeerlog::with_hasher_precision(p, PgHllHasher);
+        let bytes = hll.to_pg_hll();
+        assert_eq!(bytes[..3], [0x14, 0x80 | p, 0x7f]);
+        assert_eq!(bytes.len(), 3 + (5usize << p).div_ceil(8));
+        assert_eq!(Hypeerlog::from_pg_hll(&bytes).unwrap(), hll);
+    }
+}
+
+#[test]
+fn test_to_pg_hll_caps_registers() {
+    // 5-bit registers hold at most 31, so a larger rank is capped like the extension caps it when adding
+    let all_31 = [&[0x14, 0x84, 0x7f][..], &[0xff; 10]].concat();
+    let mut dump = Hypeerlog::from_pg_hll(&all_31).unwrap().dump();
+    let first_register = dump.len() - 16;
+    dump[first_register] = 40;
+    let hll = Hypeerlog::load_unchecked_with_hasher(dump, PgHllHasher).unwrap();
+    assert_eq!(hll.to_pg_hll(), all_31);
+}
+
+#[test]
+fn test_from_pg_hll_rejects_invalid_input() {
+    assert_eq!(Hypeerlog::from_pg_hll(&[0x14, 0x8b]), Err(HypeerlogError::InvalidMagic));
+    assert_eq!(Hypeerlog::from_pg_hll(&[0x24, 0x8b, 0x7f]), Err(HypeerlogError::UnsupportedVersion));
+
+    // Register widths other than 5, and the UNDEFINED and unknown types
+    for header in [[0x14, 0xab, 0x7f], [0x14, 0x6b, 0x7f], [0x10, 0x8b, 0x7f], [0x15, 0x8b, 0x7f]] {
+        assert_eq!(Hypeerlog::from_pg_hll(&header), Err(HypeerlogError::UnsupportedEncoding));
+    }
+
+    assert_eq!(Hypeerlog::from_pg_hll(&[0x14, 0x83, 0x7f]), Err(HypeerlogError::InvalidPrecision));
+    assert_eq!(Hypeerlog::from_pg_hll(&[0x14, 0x9a, 0x7f]), Err(HypeerlogError::InvalidPrecision));
+
+    // Data that does not fit its representation
+    assert_eq!(Hypeerlog::from_pg_hll(&FULL_20000[..FULL_20000.len() - 1]), Err(HypeerlogError::InvalidLength));
+    assert_eq!(Hypeerlog::from_pg_hll(&EXPLICIT_50[..EXPLICIT_50.len() - 1]), Err(HypeerlogError::InvalidLength));
+    assert_eq!(Hypeerlog::from_pg_hll(&[0x11, 0x8b, 0x7f, 0]), Err(HypeerlogError::InvalidLength));
+}
+
+#[test]
+fn test_pg_hll_sketches_do_not_mix() {
+    let dump = Hypeerlog::from_pg_hll(FULL_20000).unwrap().dump();
+    assert_eq!(Hypeerlog::load(dump.clone()), Err(HypeerlogError::HasherMismatch));
+    assert_eq!(Hypeerlog::with_precision(11).merge_from_dump(&dump), Err(HypeerlogError::HasherMismatch));
+    assert!(Hypeerlog::load_with_hasher(dump, PgHllHasher).is_ok());
+    assert_eq!(hypeerlog::HasherId::hasher_tag(&PgHllHasher), Some(PG_HLL_TAG));
+}
+
+#[test]
+fn test_pg_hll_hasher() {
+    // The first half of MurmurHash3_x64_128 with seed 0, like `hll_hash_bytea`
+    let hash = |bytes: &[u8]| {
+        let mut hasher = PgHllHasher.build_hasher();
+        hasher.write(bytes);
+        hasher.finish()
+    };
+    assert_eq!(hash(b""), 0);
+    assert_eq!(hash(b"hello"), 0xcbd8a7b341bd9b02);
+}
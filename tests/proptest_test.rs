@@ -0,0 +1,51 @@
+#![cfg(feature = "proptest")]
+
+use hypeerlog::Hypeerlog;
+use hypeerlog::proptest::sketch;
+use proptest::prelude::*;
+
+
+
+
+fn copy(hll: &Hypeerlog) -> Hypeerlog {
+    Hypeerlog::load(hll.dump()).unwrap()
+}
+
+// Three sketches sharing one precision, so that they can be merged together
+fn same_precision() -> impl Strategy<Value = (Hypeerlog, Hypeerlog, Hypeerlog)> {
+    (4u8..=10).prop_flat_map(|p| (sketch(p..=p), sketch(p..=p), sketch(p..=p)))
+}
+
+
+
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(300))]
+
+    #[test]
+    fn test_merge_is_associative((a, b, c) in same_precision()) {
+        let mut left = copy(&a);
+        left.merge_with(&b).unwrap();
+        left.merge_with(&c).unwrap();
+
+        let mut right = copy(&b);
+        right.merge_with(&c).unwrap();
+        let mut outer = copy(&a);
+        outer.merge_with(&right).unwrap();
+
+        prop_assert_eq!(left, outer);
+    }
+
+    #[test]
+    fn test_generated_sketches_are_valid(hll in sketch(4..=12)) {
+        prop_assert!((4..=12).contains(&hll.precision()));
+        prop_assert_eq!(Hypeerlog::from_bytes(&hll.dump()).unwrap(), copy(&hll));
+        prop_assert_eq!(Hypeerlog::load(hll.dump_compact()).unwrap(), hll);
+    }
+}
+
+#[test]
+#[should_panic(expected = "not within 4..=25")]
+fn test_invalid_precision_panics() {
+    let _ = sketch(3..=8);
+}
@@ -0,0 +1,129 @@
+#![cfg(feature = "prost")]
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::BuildHasherDefault;
+
+use hypeerlog::proto::{Sketch, FLAG_COMPACT_REGISTERS, FORMAT_VERSION};
+use hypeerlog::{Hypeerlog, HypeerlogError, MURMUR3_TAG};
+use prost::Message;
+
+mod common;
+use common::sketch_of_range;
+
+
+
+
+fn decode(bytes: &[u8]) -> Result<Hypeerlog, HypeerlogError> {
+    Hypeerlog::from_proto(Sketch::decode(bytes).unwrap())
+}
+
+
+
+
+#[test]
+fn test_proto_fixture() {
+    // The wire format is part of the public contract: update the fixture only on purpose
+    let fixture = include_bytes!("fixtures/sketch_p4.pb");
+    let hll = sketch_of_range(4, 0, 10);
+    assert_eq!(hll.to_proto().encode_to_vec(), fixture);
+    assert_eq!(decode(fixture).unwrap(), hll);
+}
+
+#[test]
+fn test_proto_fields() {
+    let hll = sketch_of_range(12, 0, 1000);
+    let msg = hll.to_proto();
+    assert_eq!(msg.format_version, FORMAT_VERSION);
+    assert_eq!(msg.precision, 12);
+    assert_eq!(msg.seed, Some(0));
+    assert_eq!(msg.hasher_tag, Some(MURMUR3_TAG));
+    assert_eq!(msg.flags, 0);
+    assert_eq!(msg.registers.len(), 1 << 12);
+}
+
+#[test]
+fn test_proto_round_trip() {
+    for p in [4, 10, 14, 18] {
+        let hll = sketch_of_range(p, 0, 5000);
+        let restored = decode(&hll.to_proto().encode_to_vec()).unwrap();
+        assert_eq!(restored, hll);
+        assert_eq!(restored.cardinality(), hll.cardinality());
+    }
+}
+
+#[test]
+fn test_proto_round_trip_seeded() {
+    let mut hll = Hypeerlog::with_precision_seed(12, 7);
+    for i in 0..1000 {
+        hll.insert(i);
+    }
+    let restored = decode(&hll.to_proto().encode_to_vec()).unwrap();
+    assert_eq!(restored, hll);
+    assert_eq!(restored.to_proto().seed, Some(7));
+
+    let mut msg = hll.to_proto();
+    msg.seed = Some(1 << 40);
+    assert_eq!(Hypeerlog::from_proto(msg).unwrap_err(), HypeerlogError::SeedMismatch);
+}
+
+#[test]
+fn test_proto_custom_hasher() {
+    let hasher = BuildHasherDefault::<DefaultHasher>::default();
+    let mut hll = Hypeerlog::with_hasher(hasher.clone());
+    hll.insert(42);
+
+    let msg = hll.to_proto();
+    assert_eq!((msg.seed, msg.hasher_tag), (None, None));
+    let restored = Hypeerlog::from_proto_with_hasher(msg, hasher).unwrap();
+    assert_eq!(restored.cardinality(), hll.cardinality());
+}
+
+#[test]
+fn test_proto_ignores_unknown_fields() {
+    let hll = sketch_of_range(10, 0, 500);
+    let mut bytes = hll.to_proto().encode_to_vec();
+    // Field 15 as a varint, and field 16 as a length-delimited string
+    bytes.extend_from_slice(&[15 << 3, 0x96, 0x01]);
+    bytes.extend_from_slice(&[0x82, 0x01, 3, b'n', b'e', b'w']);
+    assert_eq!(decode(&bytes).unwrap(), hll);
+}
+
+#[test]
+fn test_proto_compact_registers() {
+    let mut msg = Sketch::decode(include_bytes!("fixtures/sketch_p4.pb").as_slice()).unwrap();
+    let dense = msg.registers.clone();
+
+    // (zero run, value) pairs for every nonzero register
+    let mut compact = Vec::new();
+    let mut run = 0;
+    for &value in &dense {
+        if value == 0 {
+            run += 1;
+        } else {
+            compact.extend_from_slice(&[run, value]);
+            run = 0;
+        }
+    }
+    msg.flags = FLAG_COMPACT_REGISTERS;
+    msg.registers = compact;
+    assert_eq!(Hypeerlog::from_proto(msg).unwrap(), sketch_of_range(4, 0, 10));
+}
+
+#[test]
+fn test_proto_rejects_invalid() {
+    let valid = sketch_of_range(4, 0, 10).to_proto();
+    let rejected = |edit: fn(&mut Sketch)| {
+        let mut msg = valid.clone();
+        edit(&mut msg);
+        Hypeerlog::from_proto(msg).unwrap_err()
+    };
+
+    assert_eq!(rejected(|m| m.format_version = 2), HypeerlogError::UnsupportedVersion);
+    assert_eq!(rejected(|m| m.flags = 0b10), HypeerlogError::UnsupportedFlags);
+    assert_eq!(rejected(|m| m.precision = 3), HypeerlogError::InvalidPrecision);
+    assert_eq!(rejected(|m| m.precision = 26), HypeerlogError::InvalidPrecision);
+    assert_eq!(rejected(|m| m.precision = 5), HypeerlogError::InvalidLength);
+    assert_eq!(rejected(|m| m.registers.pop().map(drop).unwrap()), HypeerlogError::InvalidLength);
+    assert_eq!(rejected(|m| m.registers[0] = 62), HypeerlogError::InvalidRegisterValue);
+    assert_eq!(rejected(|m| m.hasher_tag = Some(u32::from_le_bytes(*b"XXXX"))), HypeerlogError::HasherMismatch);
+}
@@ -0,0 +1,167 @@
+#![cfg(feature = "redis-compat")]
+
+use hypeerlog::{Hypeerlog, HypeerlogError, RedisHasher, REDIS_TAG};
+use std::hash::{BuildHasher, Hasher};
+
+
+
+
+// The dense value of a Redis key holding `element:0` to `element:19999`, with the PFCOUNT result (19986)
+// cached in its header. Generated with a port of Redis's hyperloglog.c (MurmurHash64A, dense register
+// packing and the PFCOUNT estimator), not captured from a running server.
+const DENSE: &[u8] = include_bytes!("fixtures/redis_dense.bin");
+const DENSE_PFCOUNT: f64 = 19986.0;
+const DENSE_LEN: usize = 12304;
+
+// `element:0` to `element:999` (PFCOUNT 997) in the sparse encoding and in the dense one, from the same port
+const SPARSE_1000: &[u8] = include_bytes!("fixtures/redis_sparse.bin");
+const DENSE_1000: &[u8] = include_bytes!("fixtures/redis_dense_1000.bin");
+
+// A sparse sketch with the given opcodes and a stale cached cardinality
+fn sparse(ops: &[u8]) -> Vec<u8> {
+    [b"HYLL\x01\0\0\0\0\0\0\0\0\0\0\x80", ops].concat()
+}
+
+fn redis_sketch(elements: impl Iterator<Item = String>) -> Hypeerlog<RedisHasher> {
+    let mut hll = Hypeerlog::with_hasher_precision(14, RedisHasher);
+    for element in elements {
+        hll.insert_redis(element.as_bytes());
+    }
+    hll
+}
+
+fn fixture_elements() -> impl Iterator<Item = String> {
+    (0..20_000).map(|i| format!("element:{}", i))
+}
+
+
+
+
+#[test]
+fn test_from_redis_dense() {
+    let hll = Hypeerlog::from_redis(DENSE).unwrap();
+    assert_eq!(hll.precision(), 14);
+
+    // Within the ~0.8% standard error of both estimators
+    let estimate = hll.cardinality();
+    assert!((estimate - DENSE_PFCOUNT).abs() / DENSE_PFCOUNT < 0.02, "estimate {} vs PFCOUNT {}", estimate, DENSE_PFCOUNT);
+    assert!((estimate - 20_000.0).abs() / 20_000.0 < 0.02);
+}
+
+#[test]
+fn test_insert_redis_matches_pfadd() {
+    assert_eq!(redis_sketch(fixture_elements()), Hypeerlog::from_redis(DENSE).unwrap());
+}
+
+#[test]
+fn test_redis_round_trip() {
+    let hll = Hypeerlog::from_redis(DENSE).unwrap();
+    let exported = hll.to_redis().unwrap();
+    assert_eq!(exported.len(), DENSE_LEN);
+    assert_eq!(&exported[..8], b"HYLL\0\0\0\0");
+    // The cached cardinality is marked stale rather than carried over
+    assert_eq!(&exported[8..16], &[0, 0, 0, 0, 0, 0, 0, 0x80]);
+    assert_eq!(&exported[16..], &DENSE[16..]);
+    assert_eq!(Hypeerlog::from_redis(&exported).unwrap(), hll);
+}
+
+#[test]
+fn test_redis_max_registers_round_trip() {
+    // Every register holds 51 (0b110011), the largest rank, so all straddling 6-bit positions are exercised
+    let mut bytes = Hypeerlog::from_redis(DENSE).unwrap().to_redis().unwrap();
+    for (i, b) in bytes[16..].iter_mut().enumerate() {
+        *b = [0b1111_0011, 0b0011_1100, 0b1100_1111][i % 3];
+    }
+    let hll = Hypeerlog::from_redis(&bytes).unwrap();
+    assert_eq!(hll.to_redis().unwrap(), bytes);
+    assert!(hll.cardinality() > 1e12);
+}
+
+#[test]
+fn test_from_redis_rejects_invalid_input() {
+    assert_eq!(Hypeerlog::from_redis(b"HYL"), Err(HypeerlogError::InvalidMagic));
+    assert_eq!(Hypeerlog::from_redis(&Hypeerlog::new().dump()), Err(HypeerlogError::InvalidMagic));
+
+    let mut unknown = DENSE.to_vec();
+    unknown[4] = 2;
+    assert_eq!(Hypeerlog::from_redis(&unknown), Err(HypeerlogError::UnsupportedEncoding));
+
+    assert_eq!(Hypeerlog::from_redis(&DENSE[..DENSE_LEN - 1]), Err(HypeerlogError::InvalidLength));
+    assert_eq!(Hypeerlog::from_redis(&[DENSE, &[0]].concat()), Err(HypeerlogError::InvalidLength));
+
+    // 6-bit registers can hold 63, but no rank above 51 is possible at precision 14
+    let mut too_large = DENSE.to_vec();
+    too_large[16] |= 0x3f;
+    assert_eq!(Hypeerlog::from_redis(&too_large), Err(HypeerlogError::InvalidRegisterValue));
+}
+
+#[test]
+fn test_from_redis_sparse() {
+    let hll = Hypeerlog::from_redis(SPARSE_1000).unwrap();
+    assert_eq!(hll, redis_sketch((0..1000).map(|i| format!("element:{}", i))));
+    assert!((hll.cardinality() - 997.0).abs() / 997.0 < 0.02);
+
+    // The same set in both encodings yields identical registers
+    assert_eq!(hll, Hypeerlog::from_redis(DENSE_1000).unwrap());
+    assert_eq!(hll.to_redis().unwrap()[16..], DENSE_1000[16..]);
+}
+
+#[test]
+fn test_sparse_opcodes() {
+    // An empty key is a single XZERO covering all registers
+    let empty = Hypeerlog::from_redis(&sparse(&[0x7f, 0xff])).unwrap();
+    assert!(empty.is_empty());
+
+    // ZERO (3 zeros), VAL (2 registers of 5), XZERO for the rest
+    let rest = 16384 - 5 - 1;
+    let hll = Hypeerlog::from_redis(&sparse(&[0x02, 0x80 | 4 << 2 | 1, 0x40 | (rest >> 8) as u8, rest as u8])).unwrap();
+    let dump = hll.dump();
+    let registers = &dump[dump.len() - hll.len()..];
+    assert_eq!(&registers[..6], &[0, 0, 0, 5, 5, 0]);
+    assert!(registers[6..].iter().all(|&val| val == 0));
+}
+
+#[test]
+fn test_sparse_rejects_corrupted_runs() {
+    // Runs reaching past register 16383 must not over-index
+    assert_eq!(Hypeerlog::from_redis(&sparse(&[0x7f, 0xff, 0x00])), Err(HypeerlogError::InvalidRegisterIndex));
+    assert_eq!(Hypeerlog::from_redis(&sparse(&[0x7f, 0xfe, 0x83])), Err(HypeerlogError::InvalidRegisterIndex));
+    assert_eq!(Hypeerlog::from_redis(&sparse(&[0x7f, 0xff, 0x7f, 0xff])), Err(HypeerlogError::InvalidRegisterIndex));
+
+    // Opcodes that stop short of the last register, or a truncated XZERO
+    assert_eq!(Hypeerlog::from_redis(&sparse(&[])), Err(HypeerlogError::InvalidLength));
+    assert_eq!(Hypeerlog::from_redis(&sparse(&[0x7f, 0xfe])), Err(HypeerlogError::InvalidLength));
+    assert_eq!(Hypeerlog::from_redis(&sparse(&[0x7f])), Err(HypeerlogError::InvalidLength));
+}
+
+#[test]
+fn test_to_redis_requires_precision_14() {
+    let hll = Hypeerlog::with_hasher_precision(12, RedisHasher);
+    assert_eq!(hll.to_redis(), Err(HypeerlogError::InvalidPrecision));
+}
+
+#[test]
+fn test_redis_sketches_do_not_mix() {
+    let hll = Hypeerlog::from_redis(DENSE).unwrap();
+    let dump = hll.dump();
+
+    assert_eq!(Hypeerlog::load(dump.clone()), Err(HypeerlogError::HasherMismatch));
+    assert_eq!(Hypeerlog::new().merge_from_dump(&dump), Err(HypeerlogError::HasherMismatch));
+    assert_eq!(Hypeerlog::load_with_hasher(dump, RedisHasher).unwrap(), hll);
+    assert_eq!(hypeerlog::HasherId::hasher_tag(&RedisHasher), Some(REDIS_TAG));
+}
+
+#[test]
+fn test_redis_hasher() {
+    // MurmurHash64A with the Redis seed over the exact bytes written, however they are split
+    let hash = |parts: &[&[u8]]| {
+        let mut hasher = RedisHasher.build_hasher();
+        parts.iter().for_each(|part| hasher.write(part));
+        hasher.finish()
+    };
+    assert_eq!(hash(&[b""]), 0xd8dfea6585bc9732);
+    assert_eq!(hash(&[b"hello"]), 0x0f656f01eecfe400);
+    assert_eq!(hash(&[b"hel", b"lo"]), 0x0f656f01eecfe400);
+    assert_eq!(hash(&[b"abcdefgh"]), 0xf3a65df559914567);
+    assert_eq!(hash(&[b"abcdefghi"]), 0x834fba4d9152daf7);
+}
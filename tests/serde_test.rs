@@ -0,0 +1,205 @@
+#![cfg(feature = "serde")]
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::BuildHasherDefault;
+
+use hypeerlog::{Hypeerlog, HypeerlogError, MURMUR3_TAG};
+
+mod common;
+use common::sketch_of_range;
+
+
+
+
+fn json_with(precision: u8, seed: &str, registers: &[u8]) -> String {
+    format!(r#"{{"precision":{},"seed":{},"registers":{:?}}}"#, precision, seed, registers)
+}
+
+
+
+
+#[test]
+fn test_json_fixture() {
+    // The textual schema is part of the public contract: update the fixture only on purpose
+    let fixture = include_str!("fixtures/sketch_p4.json").trim_end();
+    let hll = sketch_of_range(4, 0, 10);
+    assert_eq!(serde_json::to_string(&hll).unwrap(), fixture);
+    assert_eq!(serde_json::from_str::<Hypeerlog>(fixture).unwrap(), hll);
+}
+
+#[test]
+fn test_json_uses_base64() {
+    let hll = sketch_of_range(14, 0, 1000);
+    let value: serde_json::Value = serde_json::to_value(&hll).unwrap();
+    assert_eq!(value["precision"], 14);
+    assert_eq!(value["seed"], 0);
+    assert_eq!(value["tag"], MURMUR3_TAG);
+    assert!(value["registers"].is_string());
+    assert!(serde_json::to_string(&hll).unwrap().len() < hll.len() * 2);
+
+    // Integer arrays, as written by Serialize impls that ignore `is_human_readable`, are still accepted
+    let registers: Vec<u8> = hll.register_values().to_vec();
+    let array = json_with(14, "0", &registers);
+    assert_eq!(serde_json::from_str::<Hypeerlog>(&array).unwrap(), hll);
+}
+
+#[test]
+fn test_bincode_uses_raw_bytes() {
+    let hll = sketch_of_range(12, 0, 1000);
+    let bytes = bincode::serialize(&hll).unwrap();
+    // precision, seed option tag and value, hasher tag option tag and value, length prefix, then the registers themselves
+    assert_eq!(bytes.len(), 1 + 1 + 8 + 1 + 4 + 8 + hll.len());
+    assert!(bytes.ends_with(&hll.register_values()));
+}
+
+#[test]
+fn test_json_round_trip() {
+    for (p, n) in [(4, 10), (12, 1000), (14, 100_000)] {
+        let hll = sketch_of_range(p, 0, n);
+        let json = serde_json::to_string(&hll).unwrap();
+        let restored: Hypeerlog = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored, hll);
+    }
+
+    // Written before the tag was serialized, which is read as an unknown hasher
+    let hll = sketch_of_range(4, 0, 10);
+    let untagged = json_with(4, "0", &hll.register_values());
+    assert_eq!(serde_json::from_str::<Hypeerlog>(&untagged).unwrap(), hll);
+}
+
+#[test]
+fn test_bincode_round_trip() {
+    for (p, n) in [(4, 10), (12, 1000), (16, 100_000)] {
+        let hll = sketch_of_range(p, 0, n);
+        let bytes = bincode::serialize(&hll).unwrap();
+        assert!(bytes.len() < hll.len() + 32);
+        let restored: Hypeerlog = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(restored, hll);
+    }
+}
+
+#[test]
+fn test_hasher_tag_is_checked() {
+    let hll = sketch_of_range(10, 0, 100);
+    let mut value = serde_json::to_value(&hll).unwrap();
+    value["tag"] = u32::from_le_bytes(*b"OTHR").into();
+    let err = serde_json::from_value::<Hypeerlog>(value).unwrap_err();
+    assert_eq!(err.to_string(), HypeerlogError::HasherMismatch.to_string());
+}
+
+#[cfg(feature = "xxhash")]
+#[test]
+fn test_tagged_hashers_round_trip() {
+    use serde::de::DeserializeSeed;
+    use hypeerlog::{WithHasher, Xxh3BuildHasher};
+
+    let mut hll = Hypeerlog::with_hasher_precision(12, Xxh3BuildHasher::new(7));
+    hll.insert_iter(0..1000u64);
+    let json = serde_json::to_string(&hll).unwrap();
+    let bytes = bincode::serialize(&hll).unwrap();
+    assert_eq!(serde_json::from_str::<Hypeerlog<Xxh3BuildHasher>>(&json).unwrap(), hll);
+    assert_eq!(bincode::deserialize::<Hypeerlog<Xxh3BuildHasher>>(&bytes).unwrap(), hll);
+
+    // An XXH3 sketch is not read as a Murmur3 one, whether through `Default` or a given hasher
+    let err = serde_json::from_str::<Hypeerlog>(&json).unwrap_err();
+    assert_eq!(err.to_string(), HypeerlogError::HasherMismatch.to_string());
+    assert!(bincode::deserialize::<Hypeerlog>(&bytes).is_err());
+    let mut de = serde_json::Deserializer::from_str(&json);
+    assert!(WithHasher(hypeerlog::Murmur3BuildHasher::new(7)).deserialize(&mut de).is_err());
+}
+
+#[test]
+fn test_seed_is_restored() {
+    let mut hll = Hypeerlog::with_precision_seed(10, 99);
+    hll.insert_many(&[1, 2, 3]);
+
+    let mut restored: Hypeerlog = serde_json::from_str(&serde_json::to_string(&hll).unwrap()).unwrap();
+    assert_eq!(restored, hll);
+    restored.insert(4);
+    hll.insert(4);
+    assert_eq!(restored, hll);
+}
+
+#[test]
+fn test_default_generic_hasher() {
+    let mut hll = Hypeerlog::with_hasher_precision(10, BuildHasherDefault::<DefaultHasher>::default());
+    hll.insert_many(&[1, 2, 3]);
+
+    let json = serde_json::to_string(&hll).unwrap();
+    assert!(json.contains(r#""seed":null"#));
+    let restored: Hypeerlog<BuildHasherDefault<DefaultHasher>> = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored, hll);
+}
+
+// `HasherId` is only implemented for `RandomState` with std
+#[cfg(not(feature = "no_std"))]
+#[test]
+fn test_with_hasher_keyed() {
+    use std::hash::RandomState;
+    use bincode::Options;
+    use serde::de::DeserializeSeed;
+    use hypeerlog::WithHasher;
+
+    let state = RandomState::new();
+    let mut hll = Hypeerlog::with_hasher_precision(10, state.clone());
+    hll.insert_many(&[1, 2, 3]);
+    let bytes = bincode::serialize(&hll).unwrap();
+
+    let options = bincode::DefaultOptions::new().with_fixint_encoding();
+    let mut restored = WithHasher(state)
+        .deserialize(&mut bincode::Deserializer::from_slice(&bytes, options))
+        .unwrap();
+    assert_eq!(restored.cardinality(), hll.cardinality());
+    restored.insert(3);
+    assert_eq!(restored.cardinality(), hll.cardinality());
+    // The insertion count is not serialized, so only the registers match
+    assert_eq!(restored.register_values(), hll.register_values());
+}
+
+#[test]
+fn test_rejects_corrupted_payloads() {
+    let registers = vec![0u8; 16];
+    assert!(serde_json::from_str::<Hypeerlog>(&json_with(4, "0", &registers)).is_ok());
+
+    let cases = [
+        // Precision out of range
+        json_with(3, "0", &[0; 8]),
+        json_with(26, "0", &registers),
+        // Wrong register count for the precision
+        json_with(4, "0", &[0; 15]),
+        json_with(4, "0", &[0; 17]),
+        // Rank larger than possible at p=4
+        json_with(4, "0", &[62; 16]),
+        // Seed outside of the 32-bit Murmur3 range
+        json_with(4, "4294967296", &registers),
+        // Invalid base64
+        r#"{"precision":4,"seed":0,"registers":"AAAA*AAAAAAAAAAAAAAAAA=="}"#.to_string(),
+        // Missing or unknown fields
+        r#"{"precision":4,"seed":0}"#.to_string(),
+        r#"{"precision":4,"seed":0,"registers":[],"extra":1}"#.to_string(),
+    ];
+    for json in cases {
+        assert!(serde_json::from_str::<Hypeerlog>(&json).is_err(), "{json}");
+    }
+
+    let mut bytes = bincode::serialize(&sketch_of_range(10, 0, 100)).unwrap();
+    bytes.truncate(bytes.len() - 1);
+    assert!(bincode::deserialize::<Hypeerlog>(&bytes).is_err());
+}
+
+#[test]
+fn test_stats_serialize_as_a_struct() {
+    let hll = sketch_of_range(10, 0, 500);
+    let stats = hll.stats();
+    let value: serde_json::Value = serde_json::to_value(stats).unwrap();
+    assert_eq!(value["precision"], 10);
+    assert_eq!(value["registers"], 1024);
+    assert_eq!(value["nonzero_registers"], stats.nonzero_registers);
+    assert_eq!(value["zero_registers"], stats.zero_registers);
+    assert_eq!(value["max_register"], stats.max_register);
+    assert_eq!(value["estimate"], stats.estimate);
+    assert_eq!(value["relative_error"], stats.relative_error);
+    assert_eq!(value["memory_bytes"], stats.memory_bytes);
+    assert_eq!(value["total_insertions"], 500);
+    assert_eq!(value.as_object().unwrap().len(), 9);
+}
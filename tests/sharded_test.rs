@@ -0,0 +1,83 @@
+#![cfg(not(feature = "no_std"))]
+
+use std::thread;
+
+use hypeerlog::{Hypeerlog, HypeerlogError, ShardedHypeerlog};
+
+
+
+
+// 16 threads each insert their own range and a range shared by all of them, so that the shards overlap
+fn hammer<F: Fn(usize, u64) + Sync>(insert: F) {
+    thread::scope(|s| {
+        for t in 0..16u64 {
+            let insert = &insert;
+            s.spawn(move || {
+                for x in t * 20_000..(t + 1) * 20_000 {
+                    insert(t as usize, x);
+                }
+                for x in 1_000_000..1_050_000 {
+                    insert(t as usize, x);
+                }
+            });
+        }
+    });
+}
+
+fn baseline(p: u8, seed: u32) -> Hypeerlog {
+    let mut hll = Hypeerlog::with_precision_seed(p, seed);
+    hll.insert_many(&(0..320_000u64).collect::<Vec<_>>());
+    hll.insert_many(&(1_000_000..1_050_000u64).collect::<Vec<_>>());
+    hll
+}
+
+
+
+
+#[test]
+fn test_concurrent_inserts_match_a_single_thread() {
+    for shards in [1, 3, 16, 64] {
+        let sharded = ShardedHypeerlog::with_shards(14, shards);
+        hammer(|_, x| sharded.insert(x));
+        let expected = baseline(14, 0);
+        assert_eq!(sharded.to_merged(), expected, "shards={}", shards);
+        assert_eq!(sharded.cardinality().to_bits(), expected.cardinality().to_bits(), "shards={}", shards);
+    }
+
+    let sharded = ShardedHypeerlog::with_precision_seed(10, 7);
+    hammer(|_, x| sharded.insert(x));
+    assert_eq!(sharded.to_merged(), baseline(10, 7));
+}
+
+#[test]
+fn test_inserts_with_a_shard_key() {
+    let sharded = ShardedHypeerlog::with_shards(12, 4);
+    hammer(|t, x| sharded.insert_with_key(t, x));
+    assert_eq!(sharded.to_merged(), baseline(12, 0));
+
+    // Everything in one shard gives the same sketch
+    let one_shard = ShardedHypeerlog::with_shards(12, 4);
+    hammer(|_, x| one_shard.insert_with_key(2, x));
+    assert_eq!(one_shard.to_merged(), sharded.to_merged());
+}
+
+#[test]
+fn test_sharded_construction() {
+    let default = ShardedHypeerlog::new();
+    assert_eq!(default.precision(), 14);
+    assert_eq!(default.shards(), thread::available_parallelism().unwrap().get());
+    assert!(default.is_empty());
+    assert_eq!(default.cardinality(), 0.0);
+    assert_eq!(default.to_merged(), Hypeerlog::new());
+
+    let mut sharded = ShardedHypeerlog::with_shards(30, 0);
+    assert_eq!((sharded.precision(), sharded.shards()), (25, 1));
+    sharded.insert("one");
+    assert!(!sharded.is_empty());
+    sharded.clear();
+    assert!(sharded.is_empty());
+
+    // The merged sketch keeps the seed of the shards
+    let mut seeded = ShardedHypeerlog::with_precision_seed(12, 9).to_merged();
+    assert_eq!(seeded.merge_with(&Hypeerlog::with_precision(12)), Err(HypeerlogError::SeedMismatch));
+}
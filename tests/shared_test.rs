@@ -0,0 +1,75 @@
+#![cfg(not(feature = "no_std"))]
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use hypeerlog::{Hypeerlog, HypeerlogError, SharedHypeerlog};
+
+
+
+
+#[test]
+fn test_reads_during_writes_see_consistent_sketches() {
+    let items: Vec<u64> = (0..200_000u64).map(|i| i.wrapping_mul(0x9e37_79b9_7f4a_7c15)).collect();
+    let baseline = Hypeerlog::from_slice_with_precision(12, &items);
+
+    let shared = SharedHypeerlog::with_precision(12);
+    let done = AtomicBool::new(false);
+    std::thread::scope(|s| {
+        s.spawn(|| {
+            // Single inserts and batches, each batch under one write lock
+            for chunk in items.chunks(1000) {
+                let (singles, batch) = chunk.split_at(100);
+                singles.iter().for_each(|&x| { shared.insert(x); });
+                shared.insert_many(batch);
+            }
+            done.store(true, Ordering::Release);
+        });
+        for _ in 0..4 {
+            s.spawn(|| {
+                let mut last = Hypeerlog::with_precision(12);
+                let mut reads = 0;
+                while !done.load(Ordering::Acquire) {
+                    // Every snapshot holds all the registers of the previous one, and dumps like itself
+                    let snapshot = shared.snapshot();
+                    assert!(snapshot.register_values().iter().zip(last.register_values().iter()).all(|(now, then)| now >= then));
+                    assert!(snapshot.total_insertions() >= last.total_insertions());
+                    assert_eq!(Hypeerlog::load(snapshot.dump()).unwrap(), snapshot);
+                    assert!(shared.cardinality() >= 0.0 && Hypeerlog::load(shared.dump()).is_ok());
+                    last = snapshot;
+                    reads += 1;
+                }
+                assert!(reads > 0 || last.is_empty());
+            });
+        }
+    });
+
+    assert_eq!(shared.snapshot(), baseline);
+    assert_eq!(shared.cardinality().to_bits(), baseline.cardinality().to_bits());
+    assert_eq!(shared.count(), baseline.count());
+    assert_eq!(shared.dump(), baseline.dump());
+    assert_eq!(shared.with(Hypeerlog::total_insertions), 200_000);
+    assert_eq!(shared.into_inner(), baseline);
+}
+
+#[test]
+fn test_shared_sketches_write_like_the_sketch_they_wrap() {
+    let shared = SharedHypeerlog::from(Hypeerlog::from_iter_with_precision(10, 0..500u64));
+    assert_eq!((shared.precision(), shared.relative_error()), (10, Hypeerlog::with_precision(10).relative_error()));
+    assert!(!shared.insert(1u64));
+    assert!(shared.insert_hashed(1 << 40));
+
+    let other = Hypeerlog::from_iter_with_precision(10, 500..1000u64);
+    shared.merge_with(&other).unwrap();
+    let mut expected = Hypeerlog::from_iter_with_precision(10, 0..1000u64);
+    expected.insert_hashed(1 << 40);
+    assert_eq!(shared.snapshot(), expected);
+
+    // A failed merge leaves the sketch unchanged
+    assert_eq!(shared.merge_with(&Hypeerlog::with_precision(12)), Err(HypeerlogError::PrecisionMismatch));
+    assert_eq!(shared.snapshot(), expected);
+
+    shared.clear();
+    assert!(shared.is_empty());
+    assert_eq!(shared.snapshot(), Hypeerlog::with_precision(10));
+    assert_eq!(SharedHypeerlog::default().precision(), 14);
+}
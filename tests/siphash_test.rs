@@ -0,0 +1,106 @@
+#![cfg(all(feature = "siphash", not(feature = "no_std")))]
+
+use std::collections::hash_map::{DefaultHasher, RandomState};
+use std::hash::{BuildHasher, Hasher};
+
+use hypeerlog::{HasherId, Hypeerlog, HypeerlogError, SipBuildHasher, SIPHASH13_TAG};
+
+
+
+
+const KEY: [u8; 16] = *b"0123456789abcdef";
+
+fn sip_sketch(key: [u8; 16], elements: std::ops::Range<u64>) -> Hypeerlog<SipBuildHasher> {
+    let mut hll = Hypeerlog::with_hasher(SipBuildHasher::new_with_key(key));
+    for i in elements {
+        hll.insert(i);
+    }
+    hll
+}
+
+
+
+
+#[test]
+fn test_siphash_matches_std_siphash13() {
+    // `DefaultHasher::new` is SipHash-1-3 with an all-zero key
+    let h = SipBuildHasher::new_with_keys(0, 0);
+    for len in 0..=64 {
+        let bytes: Vec<u8> = (0..len as u8).collect();
+        let mut std_hasher = DefaultHasher::new();
+        std_hasher.write(&bytes);
+        assert_eq!(h.hash_bytes(&bytes), std_hasher.finish(), "length {}", len);
+
+        let (head, tail) = bytes.split_at(len / 3);
+        let mut hasher = h.build_hasher();
+        hasher.write(head);
+        hasher.write(tail);
+        assert_eq!(hasher.finish(), std_hasher.finish(), "length {}", len);
+    }
+}
+
+#[test]
+fn test_siphash_keys() {
+    let h = SipBuildHasher::new_with_key(KEY);
+    assert_eq!(h.key(), KEY);
+    assert_eq!(SipBuildHasher::new_with_keys(0x3736353433323130, 0x6665646362613938), h);
+    assert_ne!(SipBuildHasher::random(), SipBuildHasher::random());
+    assert_eq!(h.hash_one(0xdeadbeefu32), h.hash_bytes(&0xdeadbeefu32.to_le_bytes()));
+}
+
+#[test]
+fn test_siphash_key_is_never_exposed() {
+    let hll = sip_sketch(KEY, 0..100);
+    let debug = format!("{:?}", hll);
+    let fingerprint = SipBuildHasher::new_with_key(KEY).hasher_seed().unwrap();
+    assert!(debug.contains(&format!("hasher_seed: Some({})", fingerprint)), "{}", debug);
+    let hasher_debug = format!("{:?}", SipBuildHasher::new_with_key(KEY));
+    assert!(hasher_debug.contains("key_fingerprint"));
+    for half in [0x3736353433323130u64, 0x6665646362613938] {
+        for debug in [&debug, &hasher_debug] {
+            assert!(!debug.contains(&format!("{:x}", half)) && !debug.contains(&half.to_string()));
+        }
+        assert!(!hll.dump().windows(8).any(|w| w == half.to_le_bytes()));
+    }
+}
+
+#[test]
+fn test_siphash_round_trip() {
+    let hll = sip_sketch(KEY, 0..3000);
+    assert!((hll.cardinality() - 3000.0).abs() < 3000.0 * 0.05);
+    assert_eq!(Hypeerlog::load_with_hasher(hll.dump(), SipBuildHasher::new_with_key(KEY)).unwrap(), hll);
+}
+
+#[test]
+fn test_siphash_sketches_with_different_keys_do_not_mix() {
+    let mut other_key = KEY;
+    other_key[15] ^= 1;
+    let (mut a, b) = (sip_sketch(KEY, 0..3000), sip_sketch(other_key, 0..3000));
+
+    assert_eq!(a.merge_with(&b), Err(HypeerlogError::SeedMismatch));
+    a.merge_with(&sip_sketch(KEY, 1000..4000)).unwrap();
+    assert!((a.cardinality() - 4000.0).abs() < 4000.0 * 0.05);
+
+    let dump = a.dump();
+    assert_eq!(Hypeerlog::load_with_hasher(dump.clone(), SipBuildHasher::new_with_key(other_key)), Err(HypeerlogError::SeedMismatch));
+    assert_eq!(sip_sketch(other_key, 0..10).merge_from_dump(&dump), Err(HypeerlogError::SeedMismatch));
+    assert_eq!(Hypeerlog::load(dump.clone()), Err(HypeerlogError::HasherMismatch));
+    assert_eq!(Hypeerlog::new().merge_from_dump(&dump), Err(HypeerlogError::HasherMismatch));
+    assert_eq!(SipBuildHasher::new_with_key(KEY).hasher_tag(), Some(SIPHASH13_TAG));
+}
+
+#[test]
+fn test_random_state_sketches() {
+    // `RandomState` keys itself randomly per instance; clones share the key and merge, other instances do not
+    let state = RandomState::new();
+    let (mut a, mut b) = (Hypeerlog::with_hasher(state.clone()), Hypeerlog::with_hasher(state));
+    let mut stranger = Hypeerlog::with_hasher(RandomState::new());
+    for i in 0..3000u64 {
+        a.insert(i);
+        b.insert(i + 1000);
+        stranger.insert(i);
+    }
+    a.merge_with(&b).unwrap();
+    assert!((a.cardinality() - 4000.0).abs() < 4000.0 * 0.05);
+    assert_eq!(a.merge_with(&stranger), Err(HypeerlogError::SeedMismatch));
+}
@@ -0,0 +1,162 @@
+#![cfg(not(feature = "no_std"))]
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::BuildHasherDefault;
+use std::io::{Cursor, Read, Seek, SeekFrom};
+
+use hypeerlog::{Hypeerlog, HypeerlogError, SketchArchive};
+
+mod common;
+use common::sketch_of_range;
+
+
+
+
+fn archive_of(n: u64) -> SketchArchive {
+    let mut archive = SketchArchive::new();
+    for i in 0..n {
+        archive.insert(&format!("dimension={}", i), &sketch_of_range(10, i * 10, i * 10 + 10 + i));
+    }
+    archive
+}
+
+fn bytes_of(archive: &SketchArchive) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    archive.write_to(&mut bytes).unwrap();
+    bytes
+}
+
+// Counts the bytes read through it, to check how much of an archive is touched
+struct CountingReader<R> {
+    inner: R,
+    read: usize,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.read += n;
+        Ok(n)
+    }
+}
+
+impl<R: Seek> Seek for CountingReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
+
+
+
+
+#[test]
+fn test_archive_round_trip() {
+    let archive = archive_of(500);
+    let restored = SketchArchive::read_from(&mut bytes_of(&archive).as_slice()).unwrap();
+    assert_eq!(restored, archive);
+    assert_eq!(restored.len(), 500);
+    for i in [0, 123, 499] {
+        assert_eq!(restored.get(&format!("dimension={}", i)).unwrap(), sketch_of_range(10, i * 10, i * 10 + 10 + i));
+    }
+}
+
+#[test]
+fn test_archive_random_access() {
+    let bytes = bytes_of(&archive_of(500));
+    let mut reader = CountingReader { inner: Cursor::new(&bytes), read: 0 };
+    for i in [7, 250, 498] {
+        let hll = SketchArchive::read_one(&mut reader, &format!("dimension={}", i)).unwrap();
+        assert_eq!(hll, sketch_of_range(10, i * 10, i * 10 + 10 + i));
+    }
+    // Three dumps and the index, far from the whole archive of 500 dumps
+    let dump_len = sketch_of_range(10, 0, 1).dump().len();
+    assert!(reader.read < bytes.len() / 10, "read {} of {} bytes", reader.read, bytes.len());
+    assert!(reader.read > 3 * dump_len);
+}
+
+#[test]
+fn test_archive_names() {
+    let mut archive = SketchArchive::new();
+    assert!(archive.is_empty());
+    archive.insert("platform=ios", &sketch_of_range(12, 0, 10));
+    archive.insert("country=DE", &sketch_of_range(12, 0, 20));
+    archive.insert("", &sketch_of_range(12, 0, 30));
+    assert_eq!(archive.names().collect::<Vec<_>>(), ["", "country=DE", "platform=ios"]);
+    assert!(archive.contains("country=DE"));
+
+    assert!(archive.remove("country=DE"));
+    assert!(!archive.remove("country=DE"));
+    assert_eq!(archive.get("country=DE").unwrap_err(), HypeerlogError::UnknownName);
+
+    let bytes = bytes_of(&archive);
+    let restored = SketchArchive::read_from(&mut bytes.as_slice()).unwrap();
+    assert_eq!(restored.names().collect::<Vec<_>>(), ["", "platform=ios"]);
+    assert_eq!(SketchArchive::read_one(&mut Cursor::new(&bytes), "country=DE").unwrap_err(), HypeerlogError::UnknownName);
+    assert_eq!(SketchArchive::read_one(&mut Cursor::new(&bytes), "").unwrap(), sketch_of_range(12, 0, 30));
+}
+
+#[test]
+fn test_archive_last_writer_wins() {
+    let mut archive = SketchArchive::new();
+    archive.insert("country=DE", &sketch_of_range(12, 0, 10));
+    archive.insert("country=DE", &sketch_of_range(12, 0, 1000));
+    assert_eq!(archive.len(), 1);
+    assert_eq!(archive.get("country=DE").unwrap(), sketch_of_range(12, 0, 1000));
+}
+
+#[test]
+fn test_archive_empty() {
+    let bytes = bytes_of(&SketchArchive::new());
+    assert_eq!(bytes.len(), 21);
+    assert!(SketchArchive::read_from(&mut bytes.as_slice()).unwrap().is_empty());
+}
+
+#[test]
+fn test_archive_custom_hasher() {
+    let hasher = BuildHasherDefault::<DefaultHasher>::default();
+    let mut hll = Hypeerlog::with_hasher(hasher.clone());
+    hll.insert(42);
+
+    let mut archive = SketchArchive::new();
+    archive.insert("custom", &hll);
+    let restored = SketchArchive::read_from(&mut bytes_of(&archive).as_slice()).unwrap();
+    assert_eq!(restored.get_with_hasher("custom", hasher).unwrap().cardinality(), hll.cardinality());
+}
+
+#[test]
+fn test_archive_truncated() {
+    let bytes = bytes_of(&archive_of(20));
+    for len in [0, 5, 9, 100, bytes.len() / 2, bytes.len() - 13, bytes.len() - 1] {
+        let truncated = &bytes[..len];
+        assert_eq!(SketchArchive::read_from(&mut &truncated[..]).unwrap_err(), HypeerlogError::InvalidLength, "len {}", len);
+        assert_eq!(SketchArchive::read_one(&mut Cursor::new(truncated), "dimension=3").unwrap_err(), HypeerlogError::InvalidLength, "len {}", len);
+    }
+}
+
+#[test]
+fn test_archive_rejects_invalid() {
+    let mut bytes = bytes_of(&archive_of(3));
+    assert_eq!(SketchArchive::read_from(&mut &b"not an archive at all"[..]).unwrap_err(), HypeerlogError::InvalidMagic);
+
+    bytes[4] = 2;
+    assert_eq!(SketchArchive::read_from(&mut bytes.as_slice()).unwrap_err(), HypeerlogError::UnsupportedVersion);
+    bytes[4] = 1;
+
+    // An index offset pointing past the index
+    let footer = bytes.len() - 12;
+    bytes[footer..footer + 8].copy_from_slice(&u64::MAX.to_le_bytes());
+    assert_eq!(SketchArchive::read_from(&mut bytes.as_slice()).unwrap_err(), HypeerlogError::InvalidLength);
+    assert_eq!(SketchArchive::read_one(&mut Cursor::new(&bytes), "dimension=1").unwrap_err(), HypeerlogError::InvalidLength);
+}
+
+#[test]
+fn test_archive_corrupted_dump() {
+    let mut archive = SketchArchive::new();
+    archive.insert("a", &sketch_of_range(4, 0, 10));
+    let mut bytes = bytes_of(&archive);
+    // The last register of the only dump, just before the index
+    let index_offset = u64::from_le_bytes(bytes[bytes.len() - 12..bytes.len() - 4].try_into().unwrap()) as usize;
+    bytes[index_offset - 1] = 200;
+    let restored = SketchArchive::read_from(&mut bytes.as_slice()).unwrap();
+    assert!(restored.get("a").is_err());
+}
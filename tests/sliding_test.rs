@@ -0,0 +1,99 @@
+use std::collections::HashSet;
+
+use hypeerlog::{assert_cardinality_within, Hypeerlog, SlidingHypeerlog};
+use rand::prelude::*;
+
+
+
+
+const MINUTES_PER_DAY: u64 = 24 * 60;
+
+// An item whose hash lands in register 0 of a p=8 sketch with the given rank
+fn ranked(rank: u32) -> u64 {
+    1 << (8 + rank - 1)
+}
+
+// Three days of events, one minute at a time: the active users drift every hour, and some events arrive up to
+// half an hour late
+fn events(rng: &mut StdRng) -> Vec<(u64, u64)> {
+    let mut events = Vec::new();
+    for minute in 0..3 * MINUTES_PER_DAY {
+        let active = minute / 60 * 500;
+        for _ in 0..20 {
+            let late = if rng.random_bool(0.1) { rng.random_range(0..30.min(minute + 1)) } else { 0 };
+            events.push((active + rng.random_range(0..5000), minute - late));
+        }
+    }
+    events
+}
+
+
+
+
+#[test]
+fn test_windowed_estimates_track_the_expiring_stream() {
+    let mut rng = StdRng::seed_from_u64(395);
+    let events = events(&mut rng);
+    let mut sliding = SlidingHypeerlog::with_precision_window(12, MINUTES_PER_DAY);
+    let m = 1 << 12;
+
+    for (i, chunk) in events.chunks(6 * 60 * 20).enumerate() {
+        chunk.iter().for_each(|&(user, ts)| { sliding.insert(user, ts); });
+        let now = (i as u64 + 1) * 6 * 60 - 1;
+        sliding.prune(now);
+
+        for cutoff in [now.saturating_sub(MINUTES_PER_DAY), now.saturating_sub(60)] {
+            let inserted = &events[..(i + 1) * chunk.len()];
+            let window: Vec<u64> = inserted.iter().filter(|&&(_, ts)| ts >= cutoff).map(|&(user, _)| user).collect();
+            let exact = Hypeerlog::from_slice_with_precision(12, &window);
+            let windowed = sliding.to_hypeerlog_since(cutoff);
+            assert_eq!(windowed, exact, "now={} cutoff={}", now, cutoff);
+            assert_eq!(sliding.cardinality_since(cutoff).to_bits(), exact.cardinality().to_bits());
+
+            let truth = window.iter().collect::<HashSet<_>>().len();
+            assert_cardinality_within!(windowed, truth, 4);
+        }
+        // Pruning keeps a few pairs per register, however long the stream ran
+        assert!(sliding.stored_pairs() < 8 * m, "{} pairs", sliding.stored_pairs());
+    }
+
+    // After a day without events, nothing is left in the window
+    let now = 4 * MINUTES_PER_DAY;
+    assert!(sliding.prune(now) > 0);
+    assert!(sliding.is_empty());
+    assert_eq!(sliding.cardinality_since(now - MINUTES_PER_DAY), 0.0);
+}
+
+#[test]
+fn test_registers_keep_the_ranks_that_are_the_largest_of_some_window() {
+    let mut sliding = SlidingHypeerlog::with_precision_window(8, 100);
+    let rank_since = |sliding: &SlidingHypeerlog, cutoff| sliding.to_hypeerlog_since(cutoff).register_values()[0];
+
+    assert!(sliding.insert_hashed(ranked(2), 10));
+    assert!(sliding.insert_hashed(ranked(1), 20));
+    // An older item of a lower rank never decides a window, nor does an equal rank seen at the same time
+    assert!(!sliding.insert_hashed(ranked(1), 5));
+    assert!(!sliding.insert_hashed(ranked(2), 10));
+    assert_eq!(sliding.stored_pairs(), 2);
+    assert_eq!([0, 10, 11, 20, 21].map(|cutoff| rank_since(&sliding, cutoff)), [2, 2, 1, 1, 0]);
+
+    // A late item counts in the windows that contain its timestamp, and supersedes the older ranks it matches
+    assert!(sliding.insert_hashed(ranked(3), 15));
+    assert_eq!(sliding.stored_pairs(), 2);
+    assert_eq!([0, 15, 16, 21].map(|cutoff| rank_since(&sliding, cutoff)), [3, 3, 1, 0]);
+    assert!(sliding.insert_hashed(ranked(1), 30));
+    assert_eq!(sliding.stored_pairs(), 2);
+    assert_eq!([16, 30, 31].map(|cutoff| rank_since(&sliding, cutoff)), [1, 1, 0]);
+
+    // Pruning drops the pairs before the window, and the inserts older than it
+    assert_eq!(sliding.prune(120), 1);
+    assert_eq!(rank_since(&sliding, 0), 1);
+    assert!(!sliding.insert_hashed(ranked(5), 19));
+    assert!(sliding.insert(7u64, 20));
+    assert_eq!(sliding.prune(110), 0);
+
+    sliding.clear();
+    assert!(sliding.is_empty() && sliding.insert_hashed(ranked(5), 0));
+    assert_eq!((sliding.precision(), sliding.window()), (8, 100));
+    assert_eq!(sliding.relative_error(), Hypeerlog::with_precision(8).relative_error());
+}
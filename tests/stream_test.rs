@@ -0,0 +1,205 @@
+#![cfg(not(feature = "no_std"))]
+
+use std::io::{Cursor, ErrorKind, Read, Write};
+
+use hypeerlog::{Hypeerlog, HypeerlogError, PackedHypeerlog};
+
+mod common;
+use common::sketch_of_range;
+
+
+
+
+// Hands out at most `chunk` bytes per read, and only the first `limit` bytes of `inner`
+struct ShortReader<'a> {
+    inner: &'a [u8],
+    chunk: usize,
+    limit: usize,
+}
+
+impl Read for ShortReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = buf.len().min(self.chunk).min(self.limit).min(self.inner.len());
+        buf[..n].copy_from_slice(&self.inner[..n]);
+        self.inner = &self.inner[n..];
+        self.limit -= n;
+        Ok(n)
+    }
+}
+
+// Accepts at most `chunk` bytes per write, then stops accepting anything after `limit` bytes
+struct ShortWriter {
+    written: Vec<u8>,
+    chunk: usize,
+    limit: usize,
+}
+
+impl Write for ShortWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let n = buf.len().min(self.chunk).min(self.limit - self.written.len());
+        self.written.extend_from_slice(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+
+
+
+#[test]
+fn test_file_round_trip() {
+    let hll = sketch_of_range(12, 0, 1000);
+    let path = std::env::temp_dir().join(format!("hypeerlog_stream_test_{}.hll", std::process::id()));
+
+    let mut file = std::fs::File::create(&path).unwrap();
+    hll.write_to(&mut file).unwrap();
+    drop(file);
+
+    let mut file = std::fs::File::open(&path).unwrap();
+    let loaded = Hypeerlog::read_from(&mut file).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(loaded, hll);
+}
+
+#[test]
+fn test_cursor_reads_back_to_back() {
+    let a = sketch_of_range(12, 0, 1000);
+    let b = sketch_of_range(14, 0, 5000);
+
+    let mut cursor = Cursor::new(Vec::new());
+    a.write_to(&mut cursor).unwrap();
+    b.write_to(&mut cursor).unwrap();
+    assert_eq!(cursor.get_ref(), &[a.dump(), b.dump()].concat());
+
+    cursor.set_position(0);
+    assert_eq!(Hypeerlog::read_from(&mut cursor).unwrap(), a);
+    assert_eq!(Hypeerlog::read_from(&mut cursor).unwrap(), b);
+    assert_eq!(Hypeerlog::read_from(&mut cursor), Err(HypeerlogError::Io(ErrorKind::UnexpectedEof)));
+}
+
+#[test]
+fn test_empty_sketches_are_written_dense() {
+    // The empty compact payload of their dumps would not delimit them in a stream
+    let empty = Hypeerlog::with_precision(12);
+    let b = sketch_of_range(12, 0, 1000);
+
+    let mut cursor = Cursor::new(Vec::new());
+    empty.write_to(&mut cursor).unwrap();
+    b.write_to(&mut cursor).unwrap();
+    empty.write_to(&mut cursor).unwrap();
+    assert_eq!(cursor.get_ref().len(), 2 * (22 + (1 << 12)) + b.dump().len());
+
+    cursor.set_position(0);
+    assert_eq!(Hypeerlog::read_from(&mut cursor).unwrap(), empty);
+    assert_eq!(Hypeerlog::read_from(&mut cursor).unwrap(), b);
+    assert_eq!(Hypeerlog::read_from(&mut cursor).unwrap(), empty);
+}
+
+#[test]
+fn test_short_reads() {
+    let hll = sketch_of_range(12, 0, 1000);
+    let dump = hll.dump();
+
+    let mut reader = ShortReader { inner: &dump, chunk: 3, limit: usize::MAX };
+    assert_eq!(Hypeerlog::read_from(&mut reader).unwrap(), hll);
+
+    // Early EOF in the header, the optional fields and the registers
+    for limit in [2, 8, dump.len() - 1] {
+        let mut reader = ShortReader { inner: &dump, chunk: 3, limit };
+        assert_eq!(Hypeerlog::read_from(&mut reader), Err(HypeerlogError::Io(ErrorKind::UnexpectedEof)));
+    }
+}
+
+#[test]
+fn test_short_writes() {
+    let hll = sketch_of_range(12, 0, 1000);
+
+    let mut writer = ShortWriter { written: Vec::new(), chunk: 7, limit: usize::MAX };
+    hll.write_to(&mut writer).unwrap();
+    assert_eq!(writer.written, hll.dump());
+
+    let mut writer = ShortWriter { written: Vec::new(), chunk: 7, limit: 100 };
+    assert_eq!(hll.write_to(&mut writer).unwrap_err().kind(), ErrorKind::WriteZero);
+}
+
+#[test]
+fn test_read_invalid_header() {
+    let dump = sketch_of_range(12, 0, 1000).dump();
+
+    let mut legacy = Cursor::new(&dump[22..]);
+    assert_eq!(Hypeerlog::read_from(&mut legacy), Err(HypeerlogError::InvalidMagic));
+
+    let mut bad_version = dump.clone();
+    bad_version[3] = 9;
+    assert_eq!(Hypeerlog::read_from(&mut bad_version.as_slice()), Err(HypeerlogError::UnsupportedVersion));
+
+    let mut corrupted = dump;
+    let last = corrupted.len() - 1;
+    corrupted[last] ^= 0xff;
+    assert!(matches!(Hypeerlog::read_from(&mut corrupted.as_slice()), Err(HypeerlogError::ChecksumMismatch { .. })));
+}
+
+#[test]
+fn test_read_compact() {
+    let hll = sketch_of_range(16, 0, 200);
+    let compact = hll.dump_compact();
+    assert_eq!(Hypeerlog::read_from(&mut compact.as_slice()).unwrap(), hll);
+}
+
+// Packed dumps have a fixed length, like dense ones, so they can be read back to back too
+#[test]
+fn test_read_packed_back_to_back() {
+    let a = sketch_of_range(12, 0, 1000);
+    let b = sketch_of_range(14, 0, 5000);
+    let stream = [PackedHypeerlog::from(a).dump(), PackedHypeerlog::from(b).dump()].concat();
+
+    let mut cursor = Cursor::new(stream);
+    assert_eq!(Hypeerlog::read_from(&mut cursor).unwrap(), sketch_of_range(12, 0, 1000));
+    assert_eq!(Hypeerlog::read_from(&mut cursor).unwrap(), sketch_of_range(14, 0, 5000));
+    assert_eq!(Hypeerlog::read_from(&mut cursor), Err(HypeerlogError::Io(ErrorKind::UnexpectedEof)));
+}
+
+#[test]
+fn test_read_bounds_input() {
+    // An endless stream is rejected after reading at most the size of the largest valid dump
+    assert_eq!(Hypeerlog::load_from(std::io::repeat(0)), Err(HypeerlogError::TooLarge));
+
+    let mut compact_header = sketch_of_range(10, 0, 10).dump_compact();
+    compact_header.truncate(22);
+    let mut endless = compact_header.as_slice().chain(std::io::repeat(0));
+    assert_eq!(Hypeerlog::read_from(&mut endless), Err(HypeerlogError::TooLarge));
+}
+
+#[test]
+fn test_read_accepts_the_largest_payload_after_every_optional_field() {
+    // A compact and compressed header with a checksum, a seed, a tag, a codec and an insertion count, 25 bytes of
+    // fields, followed by the largest payload of a valid dump at precision 4: its 16 registers and the margin of
+    // the codecs
+    let header = [b'H', b'L', b'L', 2, 4, 0b0101_1111];
+    let largest = [&header[..], &[0; 25], &[0; 16 + 256]].concat();
+
+    // The whole dump is read, and rejected for its content like a loaded one, not for its size
+    let read = Hypeerlog::read_from(&mut Cursor::new(&largest));
+    assert_ne!(read, Err(HypeerlogError::TooLarge));
+    assert_eq!(read, Hypeerlog::load(largest.clone()));
+
+    let mut longer = largest.as_slice().chain(std::io::repeat(0));
+    assert_eq!(Hypeerlog::read_from(&mut longer), Err(HypeerlogError::TooLarge));
+}
+
+#[test]
+fn test_load_from_reports_io_errors() {
+    let failing = Cursor::new(vec![1, 2, 3]).chain(FailingReader);
+    assert_eq!(Hypeerlog::load_from(failing), Err(HypeerlogError::Io(ErrorKind::BrokenPipe)));
+}
+
+struct FailingReader;
+
+impl Read for FailingReader {
+    fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+        Err(ErrorKind::BrokenPipe.into())
+    }
+}
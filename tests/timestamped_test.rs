@@ -0,0 +1,138 @@
+use std::collections::HashSet;
+
+use hypeerlog::{assert_cardinality_within, Hypeerlog, HypeerlogError, TimestampedHypeerlog};
+use rand::prelude::*;
+
+
+
+
+// A steady stream of users per day, of which 30% came back from the earlier days and the others are new
+fn days(rng: &mut StdRng, per_day: u64, days: u64) -> Vec<Vec<u64>> {
+    let mut next = 0;
+    (0..days)
+        .map(|_| {
+            (0..per_day)
+                .map(|_| {
+                    if next > 0 && rng.random_bool(0.3) {
+                        rng.random_range(0..next)
+                    } else {
+                        next += 1;
+                        next - 1
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+// The relative error of a sketch of all the days, after expiring the ones before `cutoff`
+fn expiry_error(rng: &mut StdRng, per_day: u64, total: u64, cutoff: u64) -> f64 {
+    let days = days(rng, per_day, total);
+    let mut hll = TimestampedHypeerlog::with_precision_seed(12, rng.random());
+    for (day, users) in days.iter().enumerate() {
+        hll.insert_many(users, day as u64);
+    }
+    hll.expire_before(cutoff);
+
+    let truth = days[cutoff as usize..].iter().flatten().collect::<HashSet<_>>().len() as f64;
+    (hll.cardinality() - truth) / truth
+}
+
+
+
+
+#[test]
+fn test_expiry_is_accurate_below_the_registers_and_biased_high_above() {
+    let mut rng = StdRng::seed_from_u64(397);
+
+    // A week of a few hundred users per day leaves most of the 4096 registers untouched, so expiring the week
+    // before is as accurate as a sketch of this week
+    for trial in 0..10 {
+        let days = days(&mut rng, 100, 14);
+        let mut hll = TimestampedHypeerlog::with_precision_seed(12, trial);
+        for (day, users) in days.iter().enumerate() {
+            hll.insert_many(users, day as u64);
+        }
+        hll.expire_before(7);
+        assert_cardinality_within!(hll, days[7..].iter().flatten().collect::<HashSet<_>>().len(), 4);
+    }
+
+    // A week of about 10,000 users refreshes almost every register, which keeps the ranks of the older weeks
+    let mean = |errors: Vec<f64>| errors.iter().sum::<f64>() / errors.len() as f64;
+    let two_weeks = mean((0..10).map(|_| expiry_error(&mut rng, 2000, 14, 7)).collect());
+    let four_weeks = mean((0..10).map(|_| expiry_error(&mut rng, 2000, 28, 21)).collect());
+    assert!(two_weeks > 0.15 && two_weeks < 0.5, "two weeks: {}", two_weeks);
+    assert!(four_weeks > two_weeks && four_weeks < 1.5, "four weeks: {}", four_weeks);
+}
+
+#[test]
+fn test_expiry_zeroes_the_registers_untouched_since_the_cutoff() {
+    let mut hll = TimestampedHypeerlog::with_precision(10);
+    assert!(hll.is_empty());
+    assert_eq!((hll.precision(), hll.last_updated().len()), (10, 1024));
+
+    // Hashes whose bucket is their low 10 bits, with a long run of zeros above them
+    let hash = |bucket: u64| bucket | (1 << 40);
+    for bucket in 0..8 {
+        assert!(hll.insert_hashed(hash(bucket), 10 + bucket));
+    }
+    // Touching a register without raising it still refreshes it, but never back in time
+    assert!(!hll.insert_hashed(1 << 20, 20));
+    assert!(!hll.insert_hashed(hash(1), 5));
+    assert_eq!(&hll.last_updated()[..9], &[20, 11, 12, 13, 14, 15, 16, 17, 0]);
+
+    let all = hll.to_hypeerlog();
+    assert_eq!(hll.expire_before(10), 0);
+    assert_eq!(hll.to_hypeerlog().total_insertions(), 10);
+    assert_eq!(hll.expire_before(14), 3);
+    // The insertions of the expired registers cannot be told apart from the others, so the count restarts
+    assert_eq!(hll.to_hypeerlog().total_insertions(), 0);
+    assert_eq!(&hll.last_updated()[..9], &[20, 0, 0, 0, 14, 15, 16, 17, 0]);
+    assert_eq!(hll.cardinality().round(), 5.0);
+    let (kept, all) = (hll.to_hypeerlog(), all.register_values().to_vec());
+    assert_eq!(&kept.register_values()[..9], &[all[0], 0, 0, 0, all[4], all[5], all[6], all[7], 0]);
+    assert_eq!(hll.expire_before(14), 0);
+    assert!(!hll.insert_hashed(hash(5), 30));
+    assert_eq!(hll.to_hypeerlog().total_insertions(), 1);
+
+    assert_eq!(hll.expire_before(u64::MAX), 5);
+    assert!(hll.is_empty());
+    hll.insert("again", 3);
+    hll.clear();
+    assert!(hll.is_empty());
+    assert!(hll.last_updated().iter().all(|&ts| ts == 0));
+}
+
+#[test]
+fn test_dumps_keep_the_timestamps() {
+    let mut hll = TimestampedHypeerlog::with_precision_seed(11, 7);
+    for item in 0..5000u64 {
+        hll.insert(item, item / 100);
+    }
+    let dump = hll.dump();
+    let mut loaded = TimestampedHypeerlog::from_bytes(&dump).unwrap();
+    assert_eq!(loaded.dump(), dump);
+    assert_eq!(loaded.last_updated(), hll.last_updated());
+    assert_eq!(loaded.to_hypeerlog(), hll.to_hypeerlog());
+
+    // The loaded sketch expires like the original
+    assert_eq!(loaded.expire_before(49), hll.expire_before(49));
+    assert_eq!(loaded.dump(), hll.dump());
+
+    // Invalid dumps are rejected
+    assert_eq!(TimestampedHypeerlog::from_bytes(&dump[..dump.len() - 1]).unwrap_err(), HypeerlogError::InvalidLength);
+    assert_eq!(TimestampedHypeerlog::from_bytes(&dump[..6]).unwrap_err(), HypeerlogError::InvalidLength);
+    assert_eq!(TimestampedHypeerlog::from_bytes(&dump[..100]).unwrap_err(), HypeerlogError::InvalidLength);
+    assert_eq!(TimestampedHypeerlog::from_bytes(&Hypeerlog::new().dump()).unwrap_err(), HypeerlogError::InvalidMagic);
+    let mut newer = dump.clone();
+    newer[4] = 2;
+    assert_eq!(TimestampedHypeerlog::from_bytes(&newer).unwrap_err(), HypeerlogError::UnsupportedVersion);
+
+    // The timestamps must match the precision of the registers
+    let registers = Hypeerlog::with_precision_seed(12, 7).dump();
+    let mut mismatched = dump[..5].to_vec();
+    mismatched.extend_from_slice(&(registers.len() as u32).to_le_bytes());
+    mismatched.extend_from_slice(&registers);
+    mismatched.extend_from_slice(&dump[dump.len() - 8 * 2048..]);
+    assert_eq!(TimestampedHypeerlog::from_bytes(&mismatched).unwrap_err(), HypeerlogError::InvalidLength);
+}
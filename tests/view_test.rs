@@ -0,0 +1,90 @@
+use hypeerlog::{Hypeerlog, HypeerlogError};
+
+mod common;
+use common::{allocations_by, sketch_of_range, CountingAlloc};
+
+
+
+
+// Counts the allocations of the tests
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+
+
+
+#[test]
+fn test_borrowed_cardinality_does_not_allocate() {
+    let hll = sketch_of_range(14, 0, 20_000);
+    let dump = hll.dump();
+
+    let ((view, estimate), allocations) = allocations_by(|| {
+        let view = Hypeerlog::borrow_dump(&dump).unwrap();
+        let estimate = view.cardinality();
+        (view, estimate)
+    });
+    assert_eq!(allocations, 0);
+
+    assert_eq!(estimate, hll.cardinality());
+    assert_eq!(view.len(), hll.len());
+    assert_eq!(view.precision(), 14);
+}
+
+#[test]
+fn test_borrowed_union_matches_owned() {
+    let a = sketch_of_range(12, 0, 10_000);
+    let b = sketch_of_range(12, 5_000, 30_000);
+    let (dump_a, dump_b) = (a.dump(), b.dump());
+
+    let (union, allocations) = allocations_by(|| {
+        let view_a = Hypeerlog::borrow_dump(&dump_a).unwrap();
+        let view_b = Hypeerlog::borrow_dump(&dump_b).unwrap();
+        view_a.union_cardinality(&view_b).unwrap()
+    });
+    assert_eq!(allocations, 0);
+
+    assert_eq!(union, a.merge(b).unwrap().cardinality());
+}
+
+#[test]
+fn test_borrowed_merge_into() {
+    let dump = sketch_of_range(12, 5_000, 30_000).dump();
+    let view = Hypeerlog::borrow_dump(&dump).unwrap();
+
+    let mut target = sketch_of_range(12, 0, 10_000);
+    view.merge_into(&mut target).unwrap();
+    assert_eq!(target, sketch_of_range(12, 0, 30_000));
+
+    let mut other = Hypeerlog::with_precision(10);
+    assert_eq!(view.merge_into(&mut other), Err(HypeerlogError::PrecisionMismatch));
+    let mut seeded = Hypeerlog::with_precision_seed(12, 9);
+    assert_eq!(view.merge_into(&mut seeded), Err(HypeerlogError::SeedMismatch));
+}
+
+#[test]
+fn test_borrowed_to_owned() {
+    let hll = sketch_of_range(12, 0, 1000);
+    let dump = hll.dump();
+    let view = Hypeerlog::borrow_dump(&dump).unwrap();
+
+    let (owned, allocations) = allocations_by(|| view.to_hypeerlog());
+    assert_eq!(allocations, 1);
+    assert_eq!(owned, hll);
+}
+
+#[test]
+fn test_borrow_validates() {
+    let hll = sketch_of_range(16, 0, 100);
+    let mut corrupted = hll.dump();
+    let last = corrupted.len() - 1;
+    corrupted[last] ^= 0x01;
+    assert!(matches!(Hypeerlog::borrow_dump(&corrupted), Err(HypeerlogError::ChecksumMismatch { .. })));
+
+    let compact = hll.dump_compact();
+    assert_eq!(Hypeerlog::borrow_dump(&compact), Err(HypeerlogError::EncodedRegisters));
+
+    let small = sketch_of_range(10, 0, 100).dump();
+    let dump = hll.dump();
+    let (a, b) = (Hypeerlog::borrow_dump(&dump).unwrap(), Hypeerlog::borrow_dump(&small).unwrap());
+    assert_eq!(a.union_cardinality(&b), Err(HypeerlogError::PrecisionMismatch));
+}
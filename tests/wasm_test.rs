@@ -0,0 +1,60 @@
+#![cfg(feature = "wasm")]
+
+// Runs natively with `cargo test --features wasm`, and in wasm with
+// `wasm-pack test --node -- --features wasm --test wasm_test`
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen_test::wasm_bindgen_test as test;
+
+use hypeerlog::{Hypeerlog, JsHll};
+
+
+
+// A native sketch of `events`: update the fixture only on purpose
+const EVENTS: &[u8] = include_bytes!("fixtures/wasm_events_p12.bin");
+
+fn events(hll: &mut JsHll, range: std::ops::Range<u32>) {
+    for i in range.clone() {
+        hll.insert_str(&format!("event-{}", i));
+    }
+    for i in range.start..range.end.min(500) {
+        hll.insert_bytes(&i.to_be_bytes());
+    }
+}
+
+
+
+
+#[test]
+fn test_dumps_round_trip_with_the_native_sketch() {
+    let mut js = JsHll::new(12, 0);
+    events(&mut js, 0..2000);
+    assert_eq!(js.dump(), EVENTS);
+    assert_eq!(js.precision(), 12);
+
+    let native = Hypeerlog::from_bytes(EVENTS).unwrap();
+    assert_eq!(js.cardinality(), native.cardinality());
+    let loaded = JsHll::load(EVENTS).unwrap();
+    assert_eq!(loaded.dump(), EVENTS);
+
+    // A sketch of newer events merges with the loaded one like the native sketches do
+    let mut newer = JsHll::new(12, 0);
+    events(&mut newer, 1500..4000);
+    let mut union = Hypeerlog::from_bytes(&newer.dump()).unwrap();
+    union.merge_with(&native).unwrap();
+    newer.merge(&loaded).unwrap();
+    assert_eq!(Hypeerlog::from(newer.clone()), union);
+    assert_eq!(newer.dump(), union.dump());
+    assert!((newer.cardinality() / 4500.0 - 1.0).abs() < 4.0 * union.relative_error());
+}
+
+// Errors become JavaScript exceptions, which only exist in wasm
+#[cfg(target_arch = "wasm32")]
+#[test]
+fn test_errors_are_thrown() {
+    let mut js = JsHll::new(12, 0);
+    assert!(js.merge(&JsHll::new(13, 0)).is_err());
+    assert!(js.merge(&JsHll::new(12, 1)).is_err());
+    assert!(JsHll::load(&EVENTS[..EVENTS.len() - 1]).is_err());
+    assert!(JsHll::load(b"not a dump").is_err());
+    assert_eq!(js.cardinality(), 0.0);
+}
@@ -0,0 +1,73 @@
+#![cfg(feature = "wyhash")]
+
+use std::hash::{BuildHasher, Hasher};
+
+use hypeerlog::{HasherId, Hypeerlog, HypeerlogError, WyHashBuilder, WYHASH_TAG};
+
+
+
+
+fn wyhash_sketch(seed: u64, precision: u8, elements: std::ops::Range<u64>) -> Hypeerlog<WyHashBuilder> {
+    let mut hll = Hypeerlog::with_hasher_precision(precision, WyHashBuilder::new(seed));
+    for i in elements {
+        hll.insert(i);
+    }
+    hll
+}
+
+// The registers of a sketch, read from the end of its dump
+fn registers(hll: &Hypeerlog<WyHashBuilder>) -> Vec<u8> {
+    let dump = hll.dump();
+    dump[dump.len() - hll.len()..].to_vec()
+}
+
+
+
+
+#[test]
+fn test_wyhash_hasher() {
+    let h = WyHashBuilder::new(3);
+    let hash = |bytes: &[u8]| {
+        let mut hasher = h.build_hasher();
+        hasher.write(bytes);
+        hasher.finish()
+    };
+    for len in 0..=64 {
+        let bytes: Vec<u8> = (0..len as u8).collect();
+        assert_eq!(h.hash_bytes(&bytes), hash(&bytes), "length {}", len);
+    }
+    assert_eq!(h.hash_one(0xdeadbeefu32), hash(&0xdeadbeefu32.to_le_bytes()));
+    assert_eq!(h.hash_one(7usize), hash(&7u64.to_le_bytes()));
+    assert_ne!(h.hash_one(7u64), WyHashBuilder::new(4).hash_one(7u64));
+}
+
+#[test]
+fn test_wyhash_bucket_distribution() {
+    // Sequential keys must fill the buckets evenly at every precision up to 16
+    for p in [4, 8, 12, 14, 16] {
+        let m = 1usize << p;
+        let hll = wyhash_sketch(0, p, 0..(m as u64 * 8));
+        let empty = registers(&hll).iter().filter(|&&r| r == 0).count();
+        // With 8 elements per bucket on average, about e^-8 of the buckets stay empty
+        assert!(empty as f64 <= (m as f64 * 0.002).max(1.0), "p={}: {} of {} buckets empty", p, empty, m);
+
+        let n = m as f64 * 8.0;
+        assert!((hll.cardinality() - n).abs() < n * 4.0 * 1.04 / (m as f64).sqrt(), "p={}", p);
+    }
+}
+
+#[test]
+fn test_wyhash_round_trip() {
+    let hll = wyhash_sketch(11, 14, 0..3000);
+    let restored = Hypeerlog::load_with_hasher(hll.dump(), WyHashBuilder::default().reseeded(11).unwrap()).unwrap();
+    assert_eq!(restored, hll);
+    assert_eq!(Hypeerlog::load_with_hasher(hll.dump(), WyHashBuilder::new(12)), Err(HypeerlogError::SeedMismatch));
+}
+
+#[test]
+fn test_wyhash_sketches_do_not_mix_with_murmur() {
+    let dump = wyhash_sketch(0, 14, 0..3000).dump();
+    assert_eq!(Hypeerlog::load(dump.clone()), Err(HypeerlogError::HasherMismatch));
+    assert_eq!(Hypeerlog::new().merge_from_dump(&dump), Err(HypeerlogError::HasherMismatch));
+    assert_eq!(WyHashBuilder::new(0).hasher_tag(), Some(WYHASH_TAG));
+}
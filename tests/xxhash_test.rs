@@ -0,0 +1,79 @@
+#![cfg(feature = "xxhash")]
+
+use std::hash::{BuildHasher, Hasher};
+
+use hypeerlog::{HasherId, Hypeerlog, HypeerlogError, Xxh3BuildHasher, XXH3_TAG};
+
+
+
+
+fn xxh3_sketch(seed: u64) -> Hypeerlog<Xxh3BuildHasher> {
+    let mut hll = Hypeerlog::with_hasher(Xxh3BuildHasher::new(seed));
+    for i in 0..2000 {
+        hll.insert_str(&format!("https://example.com/items/{}?ref=feed", i));
+    }
+    hll
+}
+
+
+
+
+#[test]
+fn test_xxh3_hasher() {
+    // XXH3_64bits of the exact bytes written, however they are split
+    let hash = |seed: u64, parts: &[&[u8]]| {
+        let mut hasher = Xxh3BuildHasher::new(seed).build_hasher();
+        parts.iter().for_each(|part| hasher.write(part));
+        hasher.finish()
+    };
+    assert_eq!(hash(0, &[b""]), 0x2d06800538d394c2);
+    assert_eq!(hash(0, &[b"hello"]), hash(0, &[b"he", b"", b"llo"]));
+    assert_ne!(hash(0, &[b"hello"]), hash(1, &[b"hello"]));
+
+    let long: Vec<u8> = (0..1000).map(|i| i as u8).collect();
+    let (head, tail) = long.split_at(333);
+    assert_eq!(hash(9, &[&long]), hash(9, &[head, tail]));
+    assert_eq!(Xxh3BuildHasher::new(9).hash_bytes(&long), hash(9, &[&long]));
+}
+
+#[test]
+fn test_xxh3_integers_hash_as_little_endian_bytes() {
+    let h = Xxh3BuildHasher::new(3);
+    let bytes = |b: &[u8]| h.hash_bytes(b);
+    assert_eq!(h.hash_one(0xdeadbeefu32), bytes(&0xdeadbeefu32.to_le_bytes()));
+    assert_eq!(h.hash_one(7usize), bytes(&7u64.to_le_bytes()));
+    assert_eq!(h.hash_one(-7isize), bytes(&(-7i64).to_le_bytes()));
+}
+
+#[test]
+fn test_xxh3_round_trip() {
+    let hll = xxh3_sketch(42);
+    assert!((hll.cardinality() - 2000.0).abs() < 2000.0 * 0.05);
+
+    let restored = Hypeerlog::load_with_hasher(hll.dump(), Xxh3BuildHasher::default().reseeded(42).unwrap()).unwrap();
+    assert_eq!(restored, hll);
+    assert_eq!(Hypeerlog::load_with_hasher(hll.dump(), Xxh3BuildHasher::new(1)), Err(HypeerlogError::SeedMismatch));
+}
+
+#[test]
+fn test_xxh3_sketches_do_not_mix_with_murmur() {
+    let hll = xxh3_sketch(0);
+    let dump = hll.dump();
+
+    assert_eq!(Hypeerlog::load(dump.clone()), Err(HypeerlogError::HasherMismatch));
+    assert_eq!(Hypeerlog::new().merge_from_dump(&dump), Err(HypeerlogError::HasherMismatch));
+    assert_eq!(
+        Hypeerlog::load_with_hasher(Hypeerlog::with_seed(0).dump(), Xxh3BuildHasher::new(0)),
+        Err(HypeerlogError::HasherMismatch)
+    );
+    assert_eq!(Xxh3BuildHasher::new(0).hasher_tag(), Some(XXH3_TAG));
+}
+
+#[test]
+fn test_xxh3_merge() {
+    let (mut a, mut b) = (Hypeerlog::with_hasher(Xxh3BuildHasher::new(5)), Hypeerlog::with_hasher(Xxh3BuildHasher::new(5)));
+    a.insert_many(&(0..3000u64).collect::<Vec<_>>());
+    b.insert_many(&(2000..5000u64).collect::<Vec<_>>());
+    let merged = a.merge(b).unwrap();
+    assert!((merged.cardinality() - 5000.0).abs() < 5000.0 * 0.05);
+}
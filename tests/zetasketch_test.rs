@@ -0,0 +1,128 @@
+#![cfg(feature = "zetasketch-compat")]
+
+use hypeerlog::{Hypeerlog, HypeerlogError, ZetaSketchHasher, ZETASKETCH_TAG};
+
+
+
+
+// An AggregatorStateProto of a dense HLL++ sketch at precision 15 (sparse precision 20, value type 4, 60000
+// values of which 50000 distinct). Generated by encoding BigQuery-style bucket/rank registers of random
+// hashes with the ZetaSketch proto schema, not exported from BigQuery itself.
+const DENSE_P15: &[u8] = include_bytes!("fixtures/zetasketch_p15.bin");
+
+fn varint(mut value: u64) -> Vec<u8> {
+    let mut out = Vec::new();
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+    out
+}
+
+fn varint_field(field: u64, value: u64) -> Vec<u8> {
+    [varint(field << 3), varint(value)].concat()
+}
+
+fn bytes_field(field: u64, bytes: &[u8]) -> Vec<u8> {
+    [varint(field << 3 | 2), varint(bytes.len() as u64), bytes.to_vec()].concat()
+}
+
+// An aggregator state of type 112 and encoding version 2 around the given HLL++ state fields
+fn aggregator(state: &[u8]) -> Vec<u8> {
+    [varint_field(1, 112), varint_field(2, 0), varint_field(3, 2), bytes_field(112, state)].concat()
+}
+
+
+
+
+#[test]
+fn test_from_zetasketch_dense() {
+    let hll = Hypeerlog::from_zetasketch(DENSE_P15).unwrap();
+    assert_eq!(hll.precision(), 15);
+
+    // Precision 15 has a standard error of ~0.6%
+    let estimate = hll.cardinality();
+    assert!((estimate - 50_000.0).abs() / 50_000.0 < 0.03, "estimate {}", estimate);
+}
+
+#[test]
+fn test_zetasketch_round_trip() {
+    let hll = Hypeerlog::from_zetasketch(DENSE_P15).unwrap();
+    let exported = hll.to_zetasketch().unwrap();
+    assert_eq!(Hypeerlog::from_zetasketch(&exported).unwrap(), hll);
+
+    // Only num_values differs: the HLL++ state, value type and header fields are written identically
+    let state_len = 32768 + 3 + 2 + 2 + 3;
+    assert_eq!(exported[exported.len() - state_len - 6..], DENSE_P15[DENSE_P15.len() - state_len - 6..]);
+    assert_eq!(exported[..2], [0x08, 112]);
+}
+
+#[test]
+fn test_zetasketch_skips_unknown_fields() {
+    let registers = vec![1; 1 << 10];
+    let state = [varint_field(3, 10), varint_field(9, 7), bytes_field(5, &registers), bytes_field(15, b"future")].concat();
+    let proto = [aggregator(&state), varint_field(20, 1), vec![0x2d, 1, 2, 3, 4], vec![0x31, 1, 2, 3, 4, 5, 6, 7, 8]].concat();
+    let hll = Hypeerlog::from_zetasketch(&proto).unwrap();
+    assert_eq!(hll.precision(), 10);
+    assert!(!hll.is_empty());
+}
+
+#[test]
+fn test_zetasketch_empty_sparse_sketch() {
+    let hll = Hypeerlog::from_zetasketch(&aggregator(&varint_field(3, 12))).unwrap();
+    assert_eq!(hll.precision(), 12);
+    assert!(hll.is_empty());
+}
+
+#[test]
+fn test_from_zetasketch_rejects_invalid_input() {
+    // Not an HLL++ aggregator, or missing its state
+    assert_eq!(Hypeerlog::from_zetasketch(&[]), Err(HypeerlogError::InvalidMagic));
+    let state = [varint_field(3, 10), bytes_field(5, &[0; 1 << 10])].concat();
+    let mut other_type = aggregator(&state);
+    other_type[1] = 100;
+    assert_eq!(Hypeerlog::from_zetasketch(&other_type), Err(HypeerlogError::InvalidMagic));
+    assert_eq!(Hypeerlog::from_zetasketch(&varint_field(1, 112)), Err(HypeerlogError::InvalidMagic));
+
+    // Malformed protos: truncated message, bad length and unterminated varint
+    assert_eq!(Hypeerlog::from_zetasketch(&DENSE_P15[..DENSE_P15.len() - 1]), Err(HypeerlogError::InvalidLength));
+    assert_eq!(Hypeerlog::from_zetasketch(&[0x0a, 0x05, 1]), Err(HypeerlogError::InvalidLength));
+    assert_eq!(Hypeerlog::from_zetasketch(&[0x08, 0xff]), Err(HypeerlogError::InvalidLength));
+
+    // Unsupported encodings: another version, or sparse entries
+    let mut version_1 = aggregator(&state);
+    version_1[5] = 1;
+    assert_eq!(Hypeerlog::from_zetasketch(&version_1), Err(HypeerlogError::UnsupportedEncoding));
+    let sparse = [varint_field(2, 3), varint_field(3, 10), bytes_field(6, &[1, 2, 3])].concat();
+    assert_eq!(Hypeerlog::from_zetasketch(&aggregator(&sparse)), Err(HypeerlogError::UnsupportedEncoding));
+
+    // Precisions outside 10..=24, a register count not matching the precision, and ranks out of bounds
+    assert_eq!(Hypeerlog::from_zetasketch(&aggregator(&varint_field(3, 9))), Err(HypeerlogError::InvalidPrecision));
+    assert_eq!(Hypeerlog::from_zetasketch(&aggregator(&varint_field(3, 25))), Err(HypeerlogError::InvalidPrecision));
+    let short = [varint_field(3, 10), bytes_field(5, &[0; 1000])].concat();
+    assert_eq!(Hypeerlog::from_zetasketch(&aggregator(&short)), Err(HypeerlogError::InvalidLength));
+    let too_large = [varint_field(3, 10), bytes_field(5, &[56; 1 << 10])].concat();
+    assert_eq!(Hypeerlog::from_zetasketch(&aggregator(&too_large)), Err(HypeerlogError::InvalidRegisterValue));
+}
+
+#[test]
+fn test_to_zetasketch_precision_range() {
+    for p in [9, 25] {
+        let hll = Hypeerlog::with_hasher_precision(p, ZetaSketchHasher::new(4));
+        assert_eq!(hll.to_zetasketch(), Err(HypeerlogError::InvalidPrecision));
+    }
+}
+
+#[test]
+fn test_zetasketch_sketches_do_not_mix() {
+    let hll = Hypeerlog::from_zetasketch(DENSE_P15).unwrap();
+    let dump = hll.dump();
+    assert_eq!(Hypeerlog::load(dump.clone()), Err(HypeerlogError::HasherMismatch));
+    assert_eq!(Hypeerlog::with_precision(15).merge_from_dump(&dump), Err(HypeerlogError::HasherMismatch));
+
+    // The value type travels with the hasher, and sketches of different value types do not merge
+    assert_eq!(Hypeerlog::load_with_hasher(dump.clone(), ZetaSketchHasher::new(4)).unwrap(), hll);
+    assert_eq!(Hypeerlog::load_with_hasher(dump, ZetaSketchHasher::new(11)), Err(HypeerlogError::SeedMismatch));
+    assert_eq!(hypeerlog::HasherId::hasher_tag(&ZetaSketchHasher::default()), Some(ZETASKETCH_TAG));
+}